@@ -1,13 +1,16 @@
-use crate::{ActorStat, PushedItemMessage, ReplayBufferProxy, ReplayBufferProxyConfig, SyncModel};
+use crate::{
+    ape_x::ApeXHooks, ActorStat, PushedItemMessage, ReplayBufferProxy, ReplayBufferProxyConfig,
+    SyncModel,
+};
 use border_core::{
     Agent, Configurable, Env, ExperienceBufferBase, ReplayBufferBase, Sampler, StepProcessor,
 };
-use crossbeam_channel::Sender;
+use crossbeam_channel::{Receiver, Sender};
 use log::{debug, info};
 use std::{
     marker::PhantomData,
     ops::DerefMut,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, PoisonError},
 };
 
 #[cfg_attr(doc, aquamarine::aquamarine)]
@@ -52,6 +55,16 @@ where
     replay_buffer_config: ReplayBufferProxyConfig,
     env_seed: i64,
     stats: Arc<Mutex<Option<ActorStat>>>,
+
+    /// Computes priorities of samples and sets the exploration rate as in Ape-X.
+    ape_x: Option<ApeXHooks<A, R::Item>>,
+
+    /// Exploration rate of the agent, set with [`Self::ape_x`].
+    epsilon: Option<f64>,
+
+    /// Receiver of the channel of samples, given to [`ReplayBufferProxy`].
+    receiver: Option<Receiver<PushedItemMessage<R::Item>>>,
+
     phantom: PhantomData<(A, E, P, R)>,
 }
 
@@ -82,12 +95,31 @@ where
             replay_buffer_config: replay_buffer_config.clone(),
             env_seed,
             stats,
+            ape_x: None,
+            epsilon: None,
+            receiver: None,
             phantom: PhantomData,
         }
     }
 
+    /// Sets the receiver of the channel of samples, with which [`ReplayBufferProxy`]
+    /// discards the oldest message under
+    /// [`BackpressurePolicy::DropOldest`](crate::BackpressurePolicy::DropOldest).
+    pub(crate) fn with_receiver(mut self, receiver: Receiver<PushedItemMessage<R::Item>>) -> Self {
+        self.receiver = Some(receiver);
+        self
+    }
+
+    /// Makes the actor compute the priorities of samples and explore with `epsilon`,
+    /// if given, as in Ape-X.
+    pub(crate) fn with_ape_x(mut self, ape_x: ApeXHooks<A, R::Item>, epsilon: Option<f64>) -> Self {
+        self.ape_x = Some(ape_x);
+        self.epsilon = epsilon;
+        self
+    }
+
     fn sync_model_first(agent: &mut A, model_info: &Arc<Mutex<(usize, A::ModelInfo)>>, id: usize) {
-        let model_info = model_info.lock().unwrap();
+        let model_info = model_info.lock().unwrap_or_else(PoisonError::into_inner);
         agent.sync_model(&model_info.1);
         info!("Received the initial model info in actor {}", id);
     }
@@ -98,7 +130,7 @@ where
         model_info: &Arc<Mutex<(usize, A::ModelInfo)>>,
         id: usize,
     ) {
-        let model_info = model_info.lock().unwrap();
+        let model_info = model_info.lock().unwrap_or_else(PoisonError::into_inner);
         if model_info.0 > *n_opt_steps {
             *n_opt_steps = model_info.0;
             agent.sync_model(&model_info.1);
@@ -127,8 +159,16 @@ where
         let mut agent: Box<dyn Agent<E, R>> = Box::new(A::build(self.agent_config.clone()));
         let mut buffer =
             ReplayBufferProxy::<R>::build_with_sender(self.id, &self.replay_buffer_config, sender);
+        if let Some(receiver) = &self.receiver {
+            buffer = buffer.with_receiver(receiver.clone());
+        }
+        if self.ape_x.is_some() {
+            buffer = buffer.with_priorities();
+        }
+        // Mutexes shared among actors are poisoned if a previous actor panicked while
+        // holding them, which are recovered so that restarted actors can run
         let mut sampler = {
-            let mut tmp = guard.lock().unwrap();
+            let mut tmp = guard.lock().unwrap_or_else(PoisonError::into_inner);
             let env = E::build(&self.env_config, self.env_seed).unwrap();
             let step_proc = P::build(&self.step_proc_config);
             *tmp = true;
@@ -142,11 +182,19 @@ where
 
         // Waits and syncs the initial model
         {
-            let mut guard_init_model = guard_init_model.lock().unwrap();
+            let mut guard_init_model = guard_init_model
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
             Self::sync_model_first(Self::downcast_mut(&mut agent), &model_info, self.id);
             *guard_init_model = true;
         }
 
+        // Set exploration rate of the actor
+        if let (Some(ape_x), Some(epsilon)) = (&self.ape_x, self.epsilon) {
+            (ape_x.set_epsilon)(Self::downcast_mut(&mut agent), epsilon);
+            info!("Set epsilon to {} in actor {}", epsilon, self.id);
+        }
+
         // Set agent training mode for exploration
         agent.train();
 
@@ -160,16 +208,26 @@ where
                 &model_info,
                 self.id,
             );
+            buffer.set_n_opt_steps(n_opt_steps);
 
             // TODO: error handling
             let _record = sampler.sample_and_push(&mut agent, &mut buffer).unwrap();
             env_steps += 1;
 
+            // Compute priorities of samples with the synced model
+            if let Some(ape_x) = &self.ape_x {
+                let agent = Self::downcast_mut(&mut agent);
+                buffer
+                    .send_with_priorities(|item| (ape_x.compute_priorities)(agent, item))
+                    .unwrap();
+            }
+
             // Stop sampling loop
-            if *self.stop.lock().unwrap() {
-                *self.stats.lock().unwrap() = Some(ActorStat {
+            if *self.stop.lock().unwrap_or_else(PoisonError::into_inner) {
+                *self.stats.lock().unwrap_or_else(PoisonError::into_inner) = Some(ActorStat {
                     env_steps,
                     duration: time.elapsed().unwrap(),
+                    n_restarts: 0,
                 });
                 break;
             }
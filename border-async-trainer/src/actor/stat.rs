@@ -1,23 +1,30 @@
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 /// Stats of sampling process in an [`Actor`](crate::Actor).
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ActorStat {
     /// The number of steps for interaction between agent and env.
     pub env_steps: usize,
 
     /// Duration of sampling loop in the [`Actor`](crate::Actor).
     pub duration: Duration,
+
+    /// The number of restarts of the [`Actor`](crate::Actor) after panics.
+    ///
+    /// `env_steps` and `duration` are of the sampling loop after the last restart.
+    #[serde(default)]
+    pub n_restarts: usize,
 }
 
 /// Returns a formatted string of the set of [`ActorStat`] for reporting.
 pub fn actor_stats_fmt(stats: &Vec<ActorStat>) -> String {
-    let mut s = "actor_id, samples, samples/sec, duration\n".to_string();
+    let mut s = "actor_id, samples, samples/sec, duration, restarts\n".to_string();
     for (i, stat) in stats.iter().enumerate() {
         let n = stat.env_steps;
         let d = stat.duration.as_secs_f32();
         let p = (n as f32) / d;
-        s += format!("{}, {}, {}, {}\n", i, n, p, d).as_str();
+        s += format!("{}, {}, {}, {}, {}\n", i, n, p, d, stat.n_restarts).as_str();
     }
     s
 }
@@ -1,14 +1,17 @@
 use crate::{
-    Actor, ActorManagerConfig, ActorStat, PushedItemMessage, ReplayBufferProxyConfig, SyncModel,
+    ape_x::ApeXHooks, messages::forward_pushed_items, Actor, ActorManagerConfig, ActorStat,
+    ApeXAgent, ApeXEpsilonConfig, BackpressurePolicy, PushedItemMessage, ReplayBufferProxyConfig,
+    SyncModel,
 };
 use border_core::{
     Agent, Configurable, Env, ExperienceBufferBase, ReplayBufferBase, StepProcessor,
 };
-use crossbeam_channel::{bounded, /*unbounded,*/ Receiver, Sender};
-use log::info;
+use crossbeam_channel::{bounded, Receiver, Sender};
+use log::{info, warn};
 use std::{
     marker::PhantomData,
-    sync::{Arc, Mutex},
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{Arc, Mutex, PoisonError},
     thread::JoinHandle,
 };
 
@@ -64,6 +67,21 @@ where
     /// Stats of [Actor]s, shared with actor threads.
     actor_stats: Vec<Arc<Mutex<Option<ActorStat>>>>,
 
+    /// Exploration rates of actors as in Ape-X.
+    ape_x_epsilon: Option<ApeXEpsilonConfig>,
+
+    /// If given, actors compute priorities of samples as in Ape-X.
+    ape_x: Option<ApeXHooks<A, R::Item>>,
+
+    /// Behavior of actors when the channel of samples is full.
+    backpressure: BackpressurePolicy,
+
+    /// The maximum number of restarts of each actor after panics.
+    max_restarts: usize,
+
+    /// Capacity of the channel of samples from actors.
+    channel_capacity: usize,
+
     phantom: PhantomData<R>,
 }
 
@@ -101,6 +119,11 @@ where
             model_info: None,
             model_info_receiver,
             actor_stats: vec![],
+            ape_x_epsilon: config.ape_x_epsilon.clone(),
+            ape_x: None,
+            backpressure: config.backpressure,
+            max_restarts: config.max_restarts,
+            channel_capacity: config.channel_capacity,
             phantom: PhantomData,
         }
     }
@@ -132,60 +155,111 @@ where
             info!("Starts thread for updating model info");
         }
 
+        if self.ape_x.is_none() && self.ape_x_epsilon.is_some() {
+            warn!("ape_x_epsilon is ignored as Ape-X is not enabled with ActorManager::ape_x()");
+        }
+
         // Create channel for [BatchMessage]
-        // let (s, r) = unbounded();
-        let (s, r) = bounded(1000);
+        let (s, r) = bounded(self.channel_capacity);
         self.batch_message_receiver = Some(r.clone());
 
         // Runs sampling processes
+        let n_actors = self.agent_configs.len();
         self.agent_configs
             .clone()
             .into_iter()
             .enumerate()
             .for_each(|(id, agent_config)| {
                 let sender = s.clone();
+                let receiver = r.clone();
                 let replay_buffer_proxy_config = ReplayBufferProxyConfig {
                     n_buffer: self.n_buffer,
+                    backpressure: self.backpressure,
                 };
                 let env_config = self.env_config.clone();
                 let step_proc_config = self.step_proc_config.clone();
                 let stop = self.stop.clone();
-                let seed = id;
                 let guard = guard_init_env.clone();
                 let guard_init_model = guard_init_model.clone();
                 let model_info = self.model_info.as_ref().unwrap().clone();
                 let stats = Arc::new(Mutex::new(None));
                 self.actor_stats.push(stats.clone());
+                let ape_x = self.ape_x;
+                let epsilon = self
+                    .ape_x_epsilon
+                    .as_ref()
+                    .map(|config| config.epsilon_of(id, n_actors));
+                let max_restarts = self.max_restarts;
 
-                // Spawn actor thread
+                // Spawn actor thread, which restarts the actor after panics
                 let handle = std::thread::spawn(move || {
-                    Actor::<A, E, P, R>::build(
-                        id,
-                        agent_config,
-                        env_config,
-                        step_proc_config,
-                        replay_buffer_proxy_config,
-                        stop,
-                        seed as i64,
-                        stats,
-                    )
-                    .run(sender, model_info, guard, guard_init_model);
+                    let mut n_restarts = 0;
+                    loop {
+                        // Seeds of environments do not overlap among actors
+                        let seed = id + n_restarts * n_actors;
+                        let mut actor = Actor::<A, E, P, R>::build(
+                            id,
+                            agent_config.clone(),
+                            env_config.clone(),
+                            step_proc_config.clone(),
+                            replay_buffer_proxy_config.clone(),
+                            stop.clone(),
+                            seed as i64,
+                            stats.clone(),
+                        )
+                        .with_receiver(receiver.clone());
+                        if let Some(ape_x) = ape_x {
+                            actor = actor.with_ape_x(ape_x, epsilon);
+                        }
+                        let result = catch_unwind(AssertUnwindSafe(|| {
+                            actor.run(
+                                sender.clone(),
+                                model_info.clone(),
+                                guard.clone(),
+                                guard_init_model.clone(),
+                            )
+                        }));
+
+                        match result {
+                            Ok(()) => {
+                                if let Some(stat) = stats
+                                    .lock()
+                                    .unwrap_or_else(PoisonError::into_inner)
+                                    .as_mut()
+                                {
+                                    stat.n_restarts = n_restarts;
+                                }
+                                break;
+                            }
+                            Err(_) if *stop.lock().unwrap_or_else(PoisonError::into_inner) => break,
+                            Err(_) if n_restarts < max_restarts => {
+                                n_restarts += 1;
+                                warn!("Restarts actor {} after panic ({} times)", id, n_restarts);
+                            }
+                            Err(_) => {
+                                warn!("Actor {} panicked after {} restarts", id, n_restarts);
+                                break;
+                            }
+                        }
+                    }
                 });
                 self.threads.push(handle);
             });
 
         // Thread for handling incoming samples
         {
-            let stop = self.stop.clone();
             let s = self.pushed_item_message_sender.clone();
+            let stop = self.stop.clone();
             let handle = std::thread::spawn(move || {
-                Self::handle_message(r, stop, s);
+                Self::handle_message(r, s, stop);
             });
             self.threads.push(handle);
         }
     }
 
     /// Waits until all actors finish.
+    ///
+    /// Stats of actors that stopped due to panics are omitted.
     pub fn join(self) -> Vec<ActorStat> {
         for h in self.threads {
             h.join().unwrap();
@@ -193,13 +267,20 @@ where
 
         self.actor_stats
             .iter()
-            .map(|e| e.lock().unwrap().clone().unwrap())
+            .enumerate()
+            .filter_map(|(id, e)| {
+                let stat = e.lock().unwrap_or_else(PoisonError::into_inner).clone();
+                if stat.is_none() {
+                    warn!("Stats of actor {} were not recorded", id);
+                }
+                stat
+            })
             .collect::<Vec<_>>()
     }
 
     /// Stops actor threads.
     pub fn stop(&self) {
-        let mut stop = self.stop.lock().unwrap();
+        let mut stop = self.stop.lock().unwrap_or_else(PoisonError::into_inner);
         *stop = true;
     }
 
@@ -210,29 +291,28 @@ where
     }
 
     /// Loop waiting [PushedItemMessage] from [Actor]s.
+    ///
+    /// Messages are forwarded to [`AsyncTrainer`](crate::AsyncTrainer), blocking while its
+    /// channel is full. The loop continues until all actors finish, so that actors blocked
+    /// on the full channel with [`BackpressurePolicy::Block`] can stop.
     fn handle_message(
         receiver: Receiver<PushedItemMessage<R::Item>>,
-        stop: Arc<Mutex<bool>>,
         sender: Sender<PushedItemMessage<R::Item>>,
+        stop: Arc<Mutex<bool>>,
     ) {
-        let mut _n_samples = 0;
-
-        loop {
-            // Handle incoming message
-            // TODO: error handling, timeout
-            // TODO: caching
-            // TODO: stats
-            let msg = receiver.recv();
-            if msg.is_ok() {
-                _n_samples += 1;
-                sender.try_send(msg.unwrap()).unwrap();
-            }
-
-            // Stop the loop
-            if *stop.lock().unwrap() {
-                break;
+        let mut n_discarded = 0;
+        for msg in receiver.iter() {
+            let n_items = msg.pushed_items.len();
+            if !forward_pushed_items(&sender, msg, &stop) {
+                n_discarded += n_items;
             }
         }
+        if n_discarded > 0 {
+            info!(
+                "Discarded {} samples after the trainer finished",
+                n_discarded
+            );
+        }
         info!("Stopped thread for message handling");
     }
 
@@ -243,25 +323,159 @@ where
         guard_init_model: Arc<Mutex<bool>>,
     ) {
         // Blocks threads sharing model_info until arriving the first message from AsyncTrainer.
+        // The first message has non-zero optimization steps when training is resumed.
+        // The mutexes are recovered if an actor panicked while holding them
         {
-            let mut guard_init_model = guard_init_model.lock().unwrap();
-            let mut model_info = model_info.lock().unwrap();
-            // TODO: error handling
-            let msg = model_info_receiver.recv().unwrap();
-            assert_eq!(msg.0, 0);
-            *model_info = msg;
+            let mut guard_init_model = guard_init_model
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let mut model_info = model_info.lock().unwrap_or_else(PoisonError::into_inner);
+            match model_info_receiver.recv() {
+                Ok(msg) => *model_info = msg,
+                Err(_) => {
+                    // The trainer finished, e.g., due to an error, before sending the model
+                    info!("Stopped model info thread before receiving the initial model");
+                    return;
+                }
+            }
             *guard_init_model = true;
         }
 
-        loop {
-            // TODO: error handling
-            let msg = model_info_receiver.recv().unwrap();
-            let mut model_info = model_info.lock().unwrap();
+        // The loop also stops when the trainer is dropped
+        while let Ok(msg) = model_info_receiver.recv() {
+            let mut model_info = model_info.lock().unwrap_or_else(PoisonError::into_inner);
             *model_info = msg;
-            if *stop.lock().unwrap() {
+            if *stop.lock().unwrap_or_else(PoisonError::into_inner) {
                 break;
             }
         }
         info!("Stopped model info thread");
     }
 }
+
+impl<A, E, R, P> ActorManager<A, E, R, P>
+where
+    A: Agent<E, R> + Configurable + SyncModel + ApeXAgent<R::Item>,
+    E: Env,
+    P: StepProcessor<E>,
+    R: ExperienceBufferBase<Item = P::Output> + ReplayBufferBase,
+{
+    /// Enables distributed prioritized experience replay as in Ape-X.
+    ///
+    /// Actors compute the priorities of samples with [`ApeXAgent::compute_priorities()`],
+    /// which are sent to the replay buffer with the samples. If
+    /// [`ActorManagerConfig::ape_x_epsilon`] is given, the exploration rates of actors
+    /// are set with [`ApeXAgent::set_epsilon()`].
+    pub fn ape_x(mut self) -> Self {
+        self.ape_x = Some(ApeXHooks::new());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{TestActBatch, TestAgent, TestAgentConfig, TestEnv, TestObsBatch};
+    use border_core::generic_replay_buffer::{
+        SimpleReplayBuffer, SimpleStepProcessor, SimpleStepProcessorConfig,
+    };
+    use crossbeam_channel::unbounded;
+    use std::time::Duration;
+
+    type ReplayBuffer = SimpleReplayBuffer<TestObsBatch, TestActBatch>;
+    type StepProcessor = SimpleStepProcessor<TestEnv, TestObsBatch, TestActBatch>;
+    type Item = <ReplayBuffer as ExperienceBufferBase>::Item;
+
+    /// Receives messages with a consumer slower than the actor, as a trainer would do.
+    fn run_slow_consumer(
+        backpressure: BackpressurePolicy,
+    ) -> anyhow::Result<(Vec<PushedItemMessage<Item>>, ActorStat)> {
+        let config = ActorManagerConfig {
+            n_buffer: 10,
+            ..ActorManagerConfig::default()
+        }
+        .backpressure(backpressure)
+        .channel_capacity(2);
+        let (item_s, item_r) = bounded(config.channel_capacity);
+        let (model_s, model_r) = unbounded();
+        let mut manager = ActorManager::<TestAgent, TestEnv, ReplayBuffer, StepProcessor>::build(
+            &config,
+            &vec![TestAgentConfig; 1],
+            &0,
+            &SimpleStepProcessorConfig::default(),
+            item_s,
+            model_r,
+            Arc::new(Mutex::new(false)),
+        );
+        manager.run(Arc::new(Mutex::new(true)));
+        model_s.send((0, 0))?;
+
+        let mut msgs = vec![];
+        for _ in 0..5 {
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(item_r.len() <= config.channel_capacity);
+            msgs.push(item_r.recv()?);
+        }
+
+        // The model info thread checks the stop flag after receiving model info
+        manager.stop();
+        model_s.send((0, 0))?;
+        let mut stats = manager.join();
+        Ok((msgs, stats.remove(0)))
+    }
+
+    #[test]
+    fn test_backpressure_block() -> anyhow::Result<()> {
+        let (msgs, stat) = run_slow_consumer(BackpressurePolicy::Block)?;
+        assert!(msgs.iter().all(|msg| msg.n_dropped == 0));
+
+        // The actor waits for the consumer, holding at most a few messages in channels
+        assert!(stat.env_steps <= 200, "env_steps = {}", stat.env_steps);
+        Ok(())
+    }
+
+    #[test]
+    fn test_backpressure_drop_newest() -> anyhow::Result<()> {
+        let (msgs, stat) = run_slow_consumer(BackpressurePolicy::DropNewest)?;
+        assert!(msgs.last().unwrap().n_dropped > 0);
+        assert!(stat.env_steps > 200, "env_steps = {}", stat.env_steps);
+        Ok(())
+    }
+
+    #[test]
+    fn test_restart_after_panic() -> anyhow::Result<()> {
+        let config = ActorManagerConfig {
+            n_buffer: 10,
+            ..ActorManagerConfig::default()
+        };
+        let (item_s, item_r) = bounded(config.channel_capacity);
+        let (model_s, model_r) = unbounded();
+        let guard_init_env = Arc::new(Mutex::new(true));
+
+        // The environment fails to be built with the seed of the first run, which makes the
+        // actor panic while holding the guard for the initialization of environments
+        let mut manager = ActorManager::<TestAgent, TestEnv, ReplayBuffer, StepProcessor>::build(
+            &config,
+            &vec![TestAgentConfig; 1],
+            &1,
+            &SimpleStepProcessorConfig::default(),
+            item_s,
+            model_r,
+            Arc::new(Mutex::new(false)),
+        );
+        manager.run(guard_init_env.clone());
+        model_s.send((0, 0))?;
+
+        // The restarted actor recovers the poisoned guard and sends samples
+        let msg = item_r.recv_timeout(Duration::from_secs(10))?;
+        assert_eq!(msg.pushed_items.len(), 10);
+        assert!(guard_init_env.is_poisoned());
+
+        manager.stop();
+        model_s.send((0, 0))?;
+        let stats = manager.join();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].n_restarts, 1);
+        Ok(())
+    }
+}
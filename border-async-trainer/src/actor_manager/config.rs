@@ -1,3 +1,4 @@
+use crate::{ApeXEpsilonConfig, BackpressurePolicy};
 use serde::{Deserialize, Serialize};
 
 /// Configuration of [`ActorManager`](super::ActorManager).
@@ -7,10 +8,84 @@ pub struct ActorManagerConfig {
     ///
     /// The default value is 100.
     pub n_buffer: usize,
+
+    /// Exploration rates of actors as in Ape-X.
+    ///
+    /// This is used when actors are enabled with [`ActorManager::ape_x()`].
+    /// If `None`, the exploration rates given by the configurations of agents are used.
+    /// The default value is `None`.
+    ///
+    /// [`ActorManager::ape_x()`]: super::ActorManager::ape_x
+    #[serde(default)]
+    pub ape_x_epsilon: Option<ApeXEpsilonConfig>,
+
+    /// Behavior of actors when the channel of samples is full.
+    ///
+    /// The default value is [`BackpressurePolicy::Block`].
+    #[serde(default)]
+    pub backpressure: BackpressurePolicy,
+
+    /// The maximum number of restarts of each actor after panics.
+    ///
+    /// A crashed actor is restarted with a fresh agent and environment, the latter of
+    /// which is built with a seed different from the previous one.
+    /// The default value is 3.
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: usize,
+
+    /// Capacity of the channels of samples.
+    ///
+    /// This is the capacity of the channel from actors to the manager. The channel from
+    /// the manager to [`AsyncTrainer`](crate::AsyncTrainer) should be bounded with the same
+    /// capacity, as in [`train_async()`](crate::util::train_async), so that
+    /// [`ActorManagerConfig::backpressure`] is applied when the trainer is slower than actors.
+    /// The default value is 1000.
+    #[serde(default = "default_channel_capacity")]
+    pub channel_capacity: usize,
+}
+
+fn default_max_restarts() -> usize {
+    3
+}
+
+fn default_channel_capacity() -> usize {
+    1000
 }
 
 impl Default for ActorManagerConfig {
     fn default() -> Self {
-        Self { n_buffer: 100 }
+        Self {
+            n_buffer: 100,
+            ape_x_epsilon: None,
+            backpressure: BackpressurePolicy::default(),
+            max_restarts: default_max_restarts(),
+            channel_capacity: default_channel_capacity(),
+        }
+    }
+}
+
+impl ActorManagerConfig {
+    /// Sets the exploration rates of actors as in Ape-X.
+    pub fn ape_x_epsilon(mut self, ape_x_epsilon: Option<ApeXEpsilonConfig>) -> Self {
+        self.ape_x_epsilon = ape_x_epsilon;
+        self
+    }
+
+    /// Sets the behavior of actors when the channel of samples is full.
+    pub fn backpressure(mut self, backpressure: BackpressurePolicy) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+
+    /// Sets the maximum number of restarts of each actor after panics.
+    pub fn max_restarts(mut self, max_restarts: usize) -> Self {
+        self.max_restarts = max_restarts;
+        self
+    }
+
+    /// Sets the capacity of the channels of samples.
+    pub fn channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.channel_capacity = channel_capacity;
+        self
     }
 }
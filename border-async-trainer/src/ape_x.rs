@@ -0,0 +1,154 @@
+//! Distributed prioritized experience replay as in Ape-X.
+//!
+//! In Ape-X ([Horgan et al., 2018](https://arxiv.org/abs/1803.00933)), actors compute the
+//! initial priorities of the transitions they sample, with the model synced from the
+//! learner, and each actor explores with a different epsilon. The priorities are sent
+//! with the transitions in [`PushedItemMessage`](crate::PushedItemMessage) and set in the
+//! replay buffer with [`ExperienceBufferBase::push_with_priority()`], instead of the
+//! maximum priority.
+//!
+//! [`ExperienceBufferBase::push_with_priority()`]: border_core::ExperienceBufferBase::push_with_priority
+use serde::{Deserialize, Serialize};
+
+/// An agent in actors of distributed prioritized experience replay.
+///
+/// `T` is the type of items pushed to the replay buffer, i.e., [`ExperienceBufferBase::Item`].
+///
+/// [`ExperienceBufferBase::Item`]: border_core::ExperienceBufferBase::Item
+pub trait ApeXAgent<T> {
+    /// Computes the priorities of the transitions in `item`, e.g., absolute TD errors.
+    fn compute_priorities(&mut self, item: &T) -> Vec<f32>;
+
+    /// Sets the exploration rate of the agent.
+    fn set_epsilon(&mut self, epsilon: f64);
+}
+
+/// Exploration rates of actors in Ape-X.
+///
+/// The `i`-th of `N` actors explores with `epsilon^(1 + i / (N - 1) * alpha)`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ApeXEpsilonConfig {
+    /// The base of exploration rates. The default value is 0.4.
+    pub epsilon: f64,
+
+    /// The exponent of exploration rates. The default value is 7.0.
+    pub alpha: f64,
+}
+
+impl Default for ApeXEpsilonConfig {
+    fn default() -> Self {
+        Self {
+            epsilon: 0.4,
+            alpha: 7.0,
+        }
+    }
+}
+
+impl ApeXEpsilonConfig {
+    /// Sets the base of exploration rates.
+    pub fn epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Sets the exponent of exploration rates.
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Returns the exploration rate of the `i`-th of `n` actors.
+    pub fn epsilon_of(&self, i: usize, n: usize) -> f64 {
+        if n <= 1 {
+            return self.epsilon;
+        }
+        let exponent = 1.0 + (i as f64) / ((n - 1) as f64) * self.alpha;
+        self.epsilon.powf(exponent)
+    }
+}
+
+/// Methods of [`ApeXAgent`] held by actors, which do not require `A: ApeXAgent<T>`.
+pub(crate) struct ApeXHooks<A, T> {
+    pub compute_priorities: fn(&mut A, &T) -> Vec<f32>,
+    pub set_epsilon: fn(&mut A, f64),
+}
+
+impl<A, T> ApeXHooks<A, T>
+where
+    A: ApeXAgent<T>,
+{
+    pub fn new() -> Self {
+        Self {
+            compute_priorities: A::compute_priorities,
+            set_epsilon: A::set_epsilon,
+        }
+    }
+}
+
+impl<A, T> Clone for ApeXHooks<A, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A, T> Copy for ApeXHooks<A, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test::{TestActBatch, TestAgent, TestAgentConfig, TestEnv, TestObsBatch},
+        ActorManager, ActorManagerConfig,
+    };
+    use border_core::generic_replay_buffer::{
+        SimpleReplayBuffer, SimpleStepProcessor, SimpleStepProcessorConfig,
+    };
+    use crossbeam_channel::{bounded, unbounded};
+    use std::sync::{Arc, Mutex};
+
+    type ReplayBuffer = SimpleReplayBuffer<TestObsBatch, TestActBatch>;
+    type StepProcessor = SimpleStepProcessor<TestEnv, TestObsBatch, TestActBatch>;
+
+    #[test]
+    fn test_epsilon_of() {
+        let config = ApeXEpsilonConfig::default();
+        assert_eq!(config.epsilon_of(0, 1), 0.4);
+        assert_eq!(config.epsilon_of(0, 8), 0.4);
+        assert!((config.epsilon_of(7, 8) - 0.4f64.powf(8.0)).abs() < 1e-12);
+        assert!(config.epsilon_of(3, 8) < config.epsilon_of(2, 8));
+    }
+
+    #[test]
+    fn test_actor_manager_ape_x() -> anyhow::Result<()> {
+        let config = ActorManagerConfig {
+            n_buffer: 10,
+            ape_x_epsilon: Some(ApeXEpsilonConfig::default()),
+            ..ActorManagerConfig::default()
+        };
+        let (item_s, item_r) = bounded(config.channel_capacity);
+        let (model_s, model_r) = unbounded();
+        let mut manager = ActorManager::<TestAgent, TestEnv, ReplayBuffer, StepProcessor>::build(
+            &config,
+            &vec![TestAgentConfig; 2],
+            &0,
+            &SimpleStepProcessorConfig::default(),
+            item_s,
+            model_r,
+            Arc::new(Mutex::new(false)),
+        )
+        .ape_x();
+        manager.run(Arc::new(Mutex::new(true)));
+
+        // Priorities are sent with samples
+        model_s.send((0, 0))?;
+        let msg = item_r.recv()?;
+        assert_eq!(msg.pushed_items.len(), 10);
+        assert_eq!(msg.priorities, Some(vec![vec![1.0]; 10]));
+
+        // The model info thread checks the stop flag after receiving model info
+        manager.stop();
+        model_s.send((0, 0))?;
+        assert_eq!(manager.join().len(), 2);
+        Ok(())
+    }
+}
@@ -3,4 +3,5 @@ mod config;
 mod stat;
 pub use base::AsyncTrainer;
 pub use config::AsyncTrainerConfig;
+use stat::ActorMetrics;
 pub use stat::AsyncTrainStat;
@@ -1,15 +1,19 @@
+use super::ActorMetrics;
 use crate::{AsyncTrainStat, AsyncTrainerConfig, PushedItemMessage, SyncModel};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use border_core::{
     record::{Record, RecordValue::Scalar, Recorder},
-    Agent, Configurable, Env, Evaluator, ExperienceBufferBase, ReplayBufferBase,
+    Agent, Configurable, Env, Evaluator, ExperienceBufferBase, ReplayBufferBase, StopReason,
+    TrainerCallback, TrainerCore,
 };
 use crossbeam_channel::{Receiver, Sender};
 use log::{debug, info};
 use std::{
+    collections::BTreeMap,
     marker::PhantomData,
-    ops::{Deref, DerefMut},
-    sync::{Arc, Mutex},
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
     time::{Duration, SystemTime},
 };
 
@@ -47,6 +51,28 @@ use std::{
 ///   [`ReplayBufferBase::Item`].
 /// * The proxy sends the transitions into the replay buffer in the [`AsyncTrainer`].
 ///
+/// # Shared Training Loop
+///
+/// Evaluation, model saving, training checkpoints, criteria for stopping training and
+/// callbacks are implemented in [`TrainerCore`], which is shared with
+/// [`Trainer`](border_core::Trainer). See the documentation of
+/// [`Trainer`](border_core::Trainer) for these features.
+///
+/// # Resuming Training
+///
+/// Training checkpoints are saved every `checkpoint_interval` optimization steps into
+/// `checkpoint_dir` of [`AsyncTrainerConfig`]. Training is resumed from a checkpoint by
+/// calling [`AsyncTrainer::resume_from`] before [`AsyncTrainer::train`]. The parameters of
+/// the agent, the replay buffer and the step counters are restored when the agent is built
+/// in [`AsyncTrainer::train`], then the restored model is sent to the actors.
+///
+/// # Offline Training
+///
+/// [`AsyncTrainer::train_offline`] trains the agent with samples in a given replay buffer,
+/// e.g., one filled with a dataset, without actors. It shares the training loop core and
+/// training checkpoints with [`AsyncTrainer::train`], so that an agent trained offline can be
+/// fine-tuned with actors by resuming from a checkpoint.
+///
 /// [`ActorManager`]: crate::ActorManager
 /// [`Actor`]: crate::Actor
 /// [`ReplayBufferBase::Item`]: border_core::ReplayBufferBase::PushedItem
@@ -69,24 +95,6 @@ where
     /// Configuration of the replay buffer.
     replay_buffer_config: R::Config,
 
-    /// Interval of recording computational cost in optimization steps.
-    record_compute_cost_interval: usize,
-
-    /// Interval of recording agent information in optimization steps.
-    record_agent_info_interval: usize,
-
-    /// Interval of flushing records in optimization steps.
-    flush_records_interval: usize,
-
-    /// Interval of evaluation in training steps.
-    eval_interval: usize,
-
-    /// Interval of saving the model in optimization steps.
-    save_interval: usize,
-
-    /// The maximal number of optimization steps.
-    max_opts: usize,
-
     /// Warmup period, for filling replay buffer, in environment steps
     warmup_period: usize,
 
@@ -105,23 +113,17 @@ where
     /// Sender of model info.
     model_info_sender: Sender<(usize, A::ModelInfo)>,
 
-    /// Counter for replay buffer samples.
-    samples_counter: usize,
-
-    /// Timer for replay buffer samples.
-    timer_for_samples: Duration,
-
-    /// Counter for optimization steps.
-    opt_steps_counter: usize,
+    /// Counters, evaluation, model saving and stopping criteria.
+    core: TrainerCore,
 
-    /// Timer for optimization steps.
-    timer_for_opt_steps: Duration,
+    /// Directory of the training checkpoint from which training is resumed.
+    resume_dir: Option<PathBuf>,
 
-    /// Max value of evaluation reward.
-    max_eval_reward: f32,
+    /// Metrics of actors, keyed by the IDs of actors.
+    actor_metrics: BTreeMap<usize, ActorMetrics>,
 
-    /// Optimization steps during training.
-    opt_steps: usize,
+    /// Time when the metrics of actors were recorded.
+    timer_for_actor_metrics: SystemTime,
 
     phantom: PhantomData<(A, E, R)>,
 }
@@ -145,79 +147,97 @@ where
         stop: Arc<Mutex<bool>>,
     ) -> Self {
         Self {
-            eval_interval: config.eval_interval,
-            max_opts: config.max_opts,
-            record_compute_cost_interval: config.record_compute_cost_interval,
-            record_agent_info_interval: config.record_agent_info_interval,
-            flush_records_interval: config.flush_record_interval,
-            save_interval: config.save_interval,
             sync_interval: config.sync_interval,
-            warmup_period: config.warmup_period,
+            warmup_period: config.trainer.warmup_period,
             agent_config: agent_config.clone(),
             env_config: env_config.clone(),
             replay_buffer_config: replay_buffer_config.clone(),
             r_bulk_pushed_item,
             model_info_sender,
             stop,
-            samples_counter: 0,
-            timer_for_samples: Duration::new(0, 0),
-            opt_steps_counter: 0,
-            timer_for_opt_steps: Duration::new(0, 0),
-            max_eval_reward: f32::MIN,
-            opt_steps: 0,
+            core: TrainerCore::build(&config.trainer_config()),
+            resume_dir: None,
+            actor_metrics: BTreeMap::new(),
+            timer_for_actor_metrics: SystemTime::now(),
             phantom: PhantomData,
         }
     }
 
-    /// Resets the counters.
-    fn reset_counters(&mut self) {
-        self.samples_counter = 0;
-        self.timer_for_samples = Duration::new(0, 0);
-        self.opt_steps_counter = 0;
-        self.timer_for_opt_steps = Duration::new(0, 0);
+    /// Sets callbacks invoked during training.
+    ///
+    /// As samples are taken in actors, [`TrainerCallback::on_env_step`] and
+    /// [`TrainerCallback::on_episode_end`] are not invoked.
+    ///
+    /// [`TrainerCallback::on_env_step`]: border_core::TrainerCallback::on_env_step
+    /// [`TrainerCallback::on_episode_end`]: border_core::TrainerCallback::on_episode_end
+    pub fn with_callbacks(mut self, callbacks: Vec<Box<dyn TrainerCallback>>) -> Self {
+        self.core = self.core.with_callbacks(callbacks);
+        self
     }
 
-    /// Calculates average time for optimization steps and samples in milliseconds.
-    fn average_time(&mut self) -> (f32, f32) {
-        let avr_opt_time = match self.opt_steps_counter {
-            0 => -1f32,
-            n => self.timer_for_opt_steps.as_millis() as f32 / n as f32,
-        };
-        let avr_sample_time = match self.samples_counter {
-            0 => -1f32,
-            n => self.timer_for_samples.as_millis() as f32 / n as f32,
-        };
-        (avr_opt_time, avr_sample_time)
+    /// Resumes training from the checkpoint saved in the directory `path`.
+    ///
+    /// The checkpoint is loaded in [`AsyncTrainer::train`], after the agent and the replay
+    /// buffer are built.
+    pub fn resume_from(&mut self, path: impl AsRef<Path>) {
+        self.resume_dir = Some(path.as_ref().to_path_buf());
     }
 
-    #[inline]
-    fn downcast_ref(agent: &Box<dyn Agent<E, R>>) -> &A {
-        agent.deref().as_any_ref().downcast_ref::<A>().unwrap()
+    /// Returns the number of samples pushed to the replay buffer.
+    pub fn env_steps(&self) -> usize {
+        self.core.env_steps()
+    }
+
+    /// Returns the number of optimization steps.
+    pub fn opt_steps(&self) -> usize {
+        self.core.opt_steps()
+    }
+
+    /// Records the metrics of actors since the last recording.
+    fn record_actor_metrics(&mut self, record: &mut Record) {
+        let secs = self
+            .timer_for_actor_metrics
+            .elapsed()
+            .unwrap()
+            .as_secs_f32();
+        let opt_steps = self.core.opt_steps();
+        for (id, metrics) in self.actor_metrics.iter_mut() {
+            let samples_per_sec = metrics.n_samples as f32 / secs;
+            let model_lag =
+                opt_steps.saturating_sub(metrics.n_opt_steps) as f32 / self.sync_interval as f32;
+            record.insert(
+                format!("actor_{}_samples_per_sec", id),
+                Scalar(samples_per_sec),
+            );
+            record.insert(
+                format!("actor_{}_queue_depth", id),
+                Scalar(metrics.queue_depth as f32),
+            );
+            record.insert(format!("actor_{}_model_lag", id), Scalar(model_lag));
+            record.insert(
+                format!("actor_{}_dropped_samples", id),
+                Scalar(metrics.n_dropped as f32),
+            );
+            metrics.n_samples = 0;
+        }
+        self.timer_for_actor_metrics = SystemTime::now();
     }
 
     #[inline]
-    fn downcast_mut(agent: &mut Box<dyn Agent<E, R>>) -> &mut A {
-        agent.deref_mut().as_any_mut().downcast_mut::<A>().unwrap()
+    fn downcast_ref(agent: &Box<dyn Agent<E, R>>) -> &A {
+        agent.deref().as_any_ref().downcast_ref::<A>().unwrap()
     }
 
     #[inline]
-    fn train_step(&mut self, agent: &mut Box<dyn Agent<E, R>>, buffer: &mut R) -> Record {
-        if buffer.len() < self.warmup_period {
-            return Record::empty();
-        } else if (self.opt_steps + 1) % self.record_agent_info_interval == 0 {
-            let timer = SystemTime::now();
-            let record = agent.opt_with_record(buffer);
-            self.opt_steps += 1;
-            self.opt_steps_counter += 1;
-            self.timer_for_opt_steps += timer.elapsed().unwrap();
-            return record;
+    fn train_step(
+        &mut self,
+        agent: &mut Box<dyn Agent<E, R>>,
+        buffer: &mut R,
+    ) -> Result<(Record, bool)> {
+        if buffer.len() < self.warmup_period || !buffer.is_ready() {
+            Ok((Record::empty(), false))
         } else {
-            let timer = SystemTime::now();
-            agent.opt(buffer);
-            self.opt_steps += 1;
-            self.opt_steps_counter += 1;
-            self.timer_for_opt_steps += timer.elapsed().unwrap();
-            return Record::empty();
+            Ok((self.core.opt_step(agent, buffer)?, true))
         }
     }
 
@@ -225,62 +245,69 @@ where
     fn post_process<D>(
         &mut self,
         agent: &mut Box<dyn Agent<E, R>>,
+        buffer: &R,
         evaluator: &mut D,
         recorder: &mut Box<dyn Recorder<E, R>>,
         record: &mut Record,
-    ) -> Result<()>
+    ) -> Result<Option<StopReason>>
     where
         E: Env,
         R: ReplayBufferBase,
         D: Evaluator<E>,
     {
-        // Evaluation
-        if self.opt_steps % self.eval_interval == 0 {
-            info!("Starts evaluation of the trained model");
-            agent.eval();
-            let (score, record_eval) = evaluator.evaluate(agent)?;
-            agent.train();
-            record.merge_inplace(record_eval);
-
-            // Save the best model up to the current iteration
-            if score > self.max_eval_reward {
-                self.max_eval_reward = score;
-                recorder.save_model("best".as_ref(), agent)?;
-            }
-        };
-
-        // Save the current model
-        if (self.save_interval > 0) && (self.opt_steps % self.save_interval == 0) {
-            recorder.save_model(format!("{}", self.opt_steps).as_ref(), agent)?;
-        }
+        let stop_reason = self
+            .core
+            .post_process(agent, buffer, evaluator, recorder, record)?;
 
         // Sync the current model
-        if self.opt_steps % self.sync_interval == 0 {
+        if self.core.opt_steps() % self.sync_interval == 0 {
             debug!("Sends the trained model info to ActorManager");
-            self.sync(Self::downcast_mut(agent));
+            self.sync(Self::downcast_ref(agent))?;
         }
 
-        Ok(())
+        Ok(stop_reason)
     }
 
     /// Synchronize model.
     #[inline]
-    fn sync(&mut self, agent: &A) {
+    fn sync(&mut self, agent: &A) -> Result<()> {
         let model_info = agent.model_info();
-        // TODO: error handling
-        self.model_info_sender.send(model_info).unwrap();
+        self.model_info_sender
+            .send(model_info)
+            .map_err(|_| anyhow!("Failed to send model info to actors"))?;
+        Ok(())
     }
 
+    /// Pushes samples received from actors into the replay buffer.
+    ///
+    /// Returns the number of the pushed samples.
     #[inline]
-    fn update_replay_buffer(&mut self, buffer: &mut R, samples_total: &mut usize) {
+    fn update_replay_buffer(&mut self, buffer: &mut R) -> Result<usize> {
         let msgs: Vec<_> = self.r_bulk_pushed_item.try_iter().collect();
-        msgs.into_iter().for_each(|msg| {
-            self.samples_counter += msg.pushed_items.len();
-            *samples_total += msg.pushed_items.len();
-            msg.pushed_items
-                .into_iter()
-                .for_each(|pushed_item| buffer.push(pushed_item).unwrap())
-        });
+        let mut n_samples = 0;
+        for msg in msgs.into_iter() {
+            n_samples += msg.pushed_items.len();
+
+            let metrics = self.actor_metrics.entry(msg.id).or_default();
+            metrics.n_samples += msg.pushed_items.len();
+            metrics.queue_depth = msg.queue_depth;
+            metrics.n_opt_steps = msg.n_opt_steps;
+            metrics.n_dropped = msg.n_dropped;
+            match msg.priorities {
+                // Priorities computed in actors (Ape-X)
+                Some(priorities) => {
+                    for (pushed_item, p) in msg.pushed_items.into_iter().zip(priorities) {
+                        buffer.push_with_priority(pushed_item, p)?;
+                    }
+                }
+                None => {
+                    for pushed_item in msg.pushed_items.into_iter() {
+                        buffer.push(pushed_item)?;
+                    }
+                }
+            }
+        }
+        Ok(n_samples)
     }
 
     /// Runs training loop.
@@ -292,41 +319,72 @@ where
     /// * `opt_steps_per_sec` - The number of optimization steps per second.
     /// * `samples_per_sec` - The number of samples per second.
     /// * `samples_per_opt_steps` - The number of samples per optimization step.
+    /// * `actor_{id}_samples_per_sec` - The number of samples per second from each actor.
+    /// * `actor_{id}_queue_depth` - The number of messages in the channel of samples
+    ///   when each actor sent the last samples.
+    /// * `actor_{id}_model_lag` - The difference of optimization steps between the trained
+    ///   model and the model of each actor, divided by the interval of synchronization.
+    /// * `actor_{id}_dropped_samples` - The total number of samples discarded by each actor
+    ///   (see [`BackpressurePolicy`](crate::BackpressurePolicy)).
+    /// * `stop_reason` - The reason why training stopped, recorded at the end of training.
     ///
     /// These values will typically be monitored with tensorboard.
     ///
+    /// Actors are asked to stop when this method returns, even if training failed.
+    ///
     /// [`ExperienceBufferBase::Item`]: border_core::ExperienceBufferBase::Item
     pub fn train<D>(
         &mut self,
         recorder: &mut Box<dyn Recorder<E, R>>,
         evaluator: &mut D,
         guard_init_env: Arc<Mutex<bool>>,
-    ) -> AsyncTrainStat
+    ) -> Result<AsyncTrainStat>
     where
         D: Evaluator<E>,
     {
-        // TODO: error handling
+        let result = self.train_loop(recorder, evaluator, guard_init_env);
+        *self.stop.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        result
+    }
+
+    fn train_loop<D>(
+        &mut self,
+        recorder: &mut Box<dyn Recorder<E, R>>,
+        evaluator: &mut D,
+        guard_init_env: Arc<Mutex<bool>>,
+    ) -> Result<AsyncTrainStat>
+    where
+        D: Evaluator<E>,
+    {
+        // The guard is recovered if an actor panicked while holding it
         let _env = {
-            let mut tmp = guard_init_env.lock().unwrap();
+            let mut tmp = guard_init_env
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
             *tmp = true;
-            E::build(&self.env_config, 0).unwrap()
+            E::build(&self.env_config, 0)?
         };
         let mut agent: Box<dyn Agent<E, R>> = Box::new(A::build(self.agent_config.clone()));
         let mut buffer = R::build(&self.replay_buffer_config);
+        if let Some(path) = self.resume_dir.clone() {
+            self.core.resume_from(path, &mut agent, &mut buffer)?;
+        }
         agent.train();
 
-        self.reset_counters();
-        self.opt_steps = 0;
-        self.max_eval_reward = f32::MIN;
+        let opt_steps_start = self.core.opt_steps();
         let time_total = SystemTime::now();
         let mut samples_total = 0;
+        self.actor_metrics.clear();
+        self.timer_for_actor_metrics = SystemTime::now();
 
         info!("Send model info first in AsyncTrainer");
-        self.sync(Self::downcast_ref(&agent));
+        self.sync(Self::downcast_ref(&agent))?;
 
         info!("Warmup period");
         loop {
-            self.update_replay_buffer(&mut buffer, &mut samples_total);
+            let n_samples = self.update_replay_buffer(&mut buffer)?;
+            self.core.add_env_steps(n_samples);
+            samples_total += n_samples;
             if buffer.len() >= self.warmup_period {
                 std::thread::sleep(Duration::from_millis(100));
                 break;
@@ -334,25 +392,35 @@ where
         }
 
         info!("Starts training loop");
-        loop {
+        let stop_reason = loop {
             // Update replay buffer
             let now = SystemTime::now();
-            self.update_replay_buffer(&mut buffer, &mut samples_total);
-            self.timer_for_samples += now.elapsed().unwrap();
+            let n_samples = self.update_replay_buffer(&mut buffer)?;
+            self.core.add_sample_time(n_samples, now.elapsed()?);
+            self.core.add_env_steps(n_samples);
+            samples_total += n_samples;
 
             // Performe optimization step(s)
-            let mut record = self.train_step(&mut agent, &mut buffer);
+            let (mut record, is_opt) = self.train_step(&mut agent, &mut buffer)?;
 
             // Postprocessing after each training step
-            self.post_process(&mut agent, evaluator, recorder, &mut record)
-                .unwrap(); // TODO: error handling
+            let mut stop_reason = None;
+            if is_opt {
+                stop_reason = self
+                    .core
+                    .invoke_callbacks(&mut agent, |cb, ctx| cb.on_opt_step(ctx, &record))?;
+                stop_reason = stop_reason.or(self.post_process(
+                    &mut agent,
+                    &buffer,
+                    evaluator,
+                    recorder,
+                    &mut record,
+                )?);
+            }
 
             // Record average time for optimization steps and sampling steps in milliseconds
-            if self.opt_steps % self.record_compute_cost_interval == 0 {
-                let (avr_opt_time, avr_sample_time) = self.average_time();
-                record.insert("average_opt_time", Scalar(avr_opt_time));
-                record.insert("average_sample_time", Scalar(avr_sample_time));
-                self.reset_counters();
+            if is_opt && self.core.record_compute_cost(&mut record, true) {
+                self.record_actor_metrics(&mut record);
             }
 
             // Store record to the recorder
@@ -361,29 +429,263 @@ where
             }
 
             // Flush records
-            if (self.opt_steps - 1) % self.flush_records_interval == 0 {
-                recorder.flush(self.opt_steps as _);
-            }
+            self.core.flush_records(recorder, is_opt);
 
             // Finish training
-            if self.opt_steps == self.max_opts {
+            if let Some(stop_reason) = stop_reason.or(self.core.stop_reason(time_total, true)?) {
                 // Flush channels
-                *self.stop.lock().unwrap() = true;
+                *self.stop.lock().unwrap_or_else(PoisonError::into_inner) = true;
                 let _: Vec<_> = self.r_bulk_pushed_item.try_iter().collect();
-                self.sync(Self::downcast_mut(&mut agent));
-                break;
+                self.sync(Self::downcast_ref(&agent))?;
+                self.core.finish(recorder, stop_reason);
+                break stop_reason;
             }
-        }
+        };
         info!("Stopped training loop");
 
-        let duration = time_total.elapsed().unwrap();
+        let duration = time_total.elapsed()?;
         let time_total = duration.as_secs_f32();
         let samples_per_sec = samples_total as f32 / time_total;
-        let opt_per_sec = self.max_opts as f32 / time_total;
-        AsyncTrainStat {
+        let opt_per_sec = (self.core.opt_steps() - opt_steps_start) as f32 / time_total;
+        Ok(AsyncTrainStat {
             samples_per_sec,
             duration,
             opt_per_sec,
+            stop_reason,
+        })
+    }
+
+    /// Trains the agent offline with samples in `buffer`, without actors.
+    ///
+    /// Samples sent from actors are not received and the model is not sent to actors.
+    /// `warmup_period` and `max_env_steps` of [`AsyncTrainerConfig`] are ignored.
+    /// If [`AsyncTrainer::resume_from`] was called, the agent, the replay buffer and
+    /// the step counters are restored from the checkpoint before training.
+    ///
+    /// In the training loop, `opt_steps_per_sec` and `stop_reason` are pushed into the given
+    /// recorder as in [`AsyncTrainer::train`].
+    pub fn train_offline<D>(
+        &mut self,
+        buffer: &mut R,
+        recorder: &mut Box<dyn Recorder<E, R>>,
+        evaluator: &mut D,
+    ) -> Result<AsyncTrainStat>
+    where
+        D: Evaluator<E>,
+    {
+        let mut agent: Box<dyn Agent<E, R>> = Box::new(A::build(self.agent_config.clone()));
+        if let Some(path) = self.resume_dir.clone() {
+            self.core.resume_from(path, &mut agent, buffer)?;
+        }
+        agent.train();
+
+        let opt_steps_start = self.core.opt_steps();
+        let time_total = SystemTime::now();
+
+        info!("Starts offline training loop");
+        let stop_reason = loop {
+            // Performe an optimization step
+            let mut record = self.core.opt_step(&mut agent, buffer)?;
+
+            // Postprocessing after each training step
+            let stop_reason = self
+                .core
+                .invoke_callbacks(&mut agent, |cb, ctx| cb.on_opt_step(ctx, &record))?;
+            let stop_reason = stop_reason.or(self.core.post_process(
+                &mut agent,
+                buffer,
+                evaluator,
+                recorder,
+                &mut record,
+            )?);
+
+            // Record average time for optimization steps in milliseconds
+            self.core.record_compute_cost(&mut record, false);
+
+            // Store record to the recorder
+            if !record.is_empty() {
+                recorder.store(record);
+            }
+
+            // Flush records
+            self.core.flush_records(recorder, true);
+
+            // Finish training
+            if let Some(stop_reason) = stop_reason.or(self.core.stop_reason(time_total, false)?) {
+                self.core.finish(recorder, stop_reason);
+                break stop_reason;
+            }
+        };
+        info!("Stopped offline training loop");
+
+        let duration = time_total.elapsed()?;
+        let opt_per_sec = (self.core.opt_steps() - opt_steps_start) as f32 / duration.as_secs_f32();
+        Ok(AsyncTrainStat {
+            samples_per_sec: 0.0,
+            duration,
+            opt_per_sec,
+            stop_reason,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test::{TestActBatch, TestAgent, TestAgentConfig, TestEnv, TestObsBatch},
+        ActorManager, ActorManagerConfig,
+    };
+    use border_core::{
+        generic_replay_buffer::{
+            SimpleReplayBuffer, SimpleReplayBufferConfig, SimpleStepProcessor,
+            SimpleStepProcessorConfig,
+        },
+        record::NullRecorder,
+        CallbackAction, CallbackContext, DefaultEvaluator,
+    };
+    use crossbeam_channel::{bounded, unbounded};
+
+    type ReplayBuffer = SimpleReplayBuffer<TestObsBatch, TestActBatch>;
+    type StepProcessor = SimpleStepProcessor<TestEnv, TestObsBatch, TestActBatch>;
+
+    /// Stores the numbers of optimization steps given to the callback.
+    struct OptStepsCallback(Arc<Mutex<Vec<usize>>>);
+
+    impl TrainerCallback for OptStepsCallback {
+        fn on_opt_step(&mut self, ctx: &CallbackContext, _: &Record) -> Result<CallbackAction> {
+            self.0.lock().unwrap().push(ctx.opt_steps);
+            Ok(CallbackAction::default())
         }
     }
+
+    fn train(
+        config: &AsyncTrainerConfig,
+        resume_dir: Option<&Path>,
+    ) -> Result<(AsyncTrainStat, Vec<usize>)> {
+        let stop = Arc::new(Mutex::new(false));
+        let actor_man_config = ActorManagerConfig::default();
+        let (item_s, item_r) = bounded(actor_man_config.channel_capacity);
+        let (model_s, model_r) = unbounded();
+        let guard_init_env = Arc::new(Mutex::new(true));
+        let mut actors = ActorManager::<TestAgent, TestEnv, ReplayBuffer, StepProcessor>::build(
+            &actor_man_config,
+            &vec![TestAgentConfig; 2],
+            &0,
+            &SimpleStepProcessorConfig::default(),
+            item_s,
+            model_r,
+            stop.clone(),
+        );
+        let opt_steps = Arc::new(Mutex::new(vec![]));
+        let mut trainer = AsyncTrainer::<TestAgent, TestEnv, ReplayBuffer>::build(
+            config,
+            &TestAgentConfig,
+            &0,
+            &SimpleReplayBufferConfig::default(),
+            item_r,
+            model_s,
+            stop,
+        )
+        .with_callbacks(vec![Box::new(OptStepsCallback(opt_steps.clone()))]);
+        if let Some(path) = resume_dir {
+            trainer.resume_from(path);
+        }
+        let mut recorder: Box<dyn Recorder<_, _>> = Box::new(NullRecorder::new());
+        let mut evaluator = DefaultEvaluator::<TestEnv>::new(&0, 0, 1)?;
+
+        actors.run(guard_init_env.clone());
+        let result = trainer.train(&mut recorder, &mut evaluator, guard_init_env);
+        drop(trainer);
+        actors.stop_and_join();
+        let opt_steps = opt_steps.lock().unwrap().clone();
+        Ok((result?, opt_steps))
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() -> Result<()> {
+        let checkpoint_dir =
+            std::env::temp_dir().join(format!("border_async_checkpoint_{}", std::process::id()));
+        let config = AsyncTrainerConfig::default()
+            .max_opts(10)
+            .eval_interval(usize::MAX)
+            .warmup_period(100)
+            .sync_interval(5)
+            .checkpoint_interval(10)
+            .checkpoint_dir(checkpoint_dir.to_str().unwrap());
+
+        let (stat, opt_steps) = train(&config, None)?;
+        assert_eq!(stat.stop_reason, StopReason::MaxOpts);
+        assert_eq!(opt_steps, (1..=10).collect::<Vec<_>>());
+        assert!(checkpoint_dir.join("trainer.yaml").exists());
+
+        // Optimization steps are counted from the checkpoint
+        let config = config.max_opts(15);
+        let (stat, opt_steps) = train(&config, Some(&checkpoint_dir))?;
+        assert_eq!(stat.stop_reason, StopReason::MaxOpts);
+        assert_eq!(opt_steps, (11..=15).collect::<Vec<_>>());
+
+        std::fs::remove_dir_all(&checkpoint_dir)?;
+        Ok(())
+    }
+
+    fn train_offline(
+        config: &AsyncTrainerConfig,
+        resume_dir: Option<&Path>,
+    ) -> Result<(AsyncTrainStat, Vec<usize>)> {
+        // No actors send samples or receive models
+        let (_, item_r) = bounded(1);
+        let (model_s, _) = unbounded();
+        let opt_steps = Arc::new(Mutex::new(vec![]));
+        let mut trainer = AsyncTrainer::<TestAgent, TestEnv, ReplayBuffer>::build(
+            config,
+            &TestAgentConfig,
+            &0,
+            &SimpleReplayBufferConfig::default(),
+            item_r,
+            model_s,
+            Arc::new(Mutex::new(false)),
+        )
+        .with_callbacks(vec![Box::new(OptStepsCallback(opt_steps.clone()))]);
+        if let Some(path) = resume_dir {
+            trainer.resume_from(path);
+        }
+        let mut buffer = ReplayBuffer::build(&SimpleReplayBufferConfig::default());
+        let mut recorder: Box<dyn Recorder<_, _>> = Box::new(NullRecorder::new());
+        let mut evaluator = DefaultEvaluator::<TestEnv>::new(&0, 0, 1)?;
+
+        let stat = trainer.train_offline(&mut buffer, &mut recorder, &mut evaluator)?;
+        let opt_steps = opt_steps.lock().unwrap().clone();
+        Ok((stat, opt_steps))
+    }
+
+    #[test]
+    fn test_train_offline() -> Result<()> {
+        let checkpoint_dir = std::env::temp_dir().join(format!(
+            "border_async_offline_checkpoint_{}",
+            std::process::id()
+        ));
+        // Warmup period and the maximum number of samples are ignored
+        let config = AsyncTrainerConfig::default()
+            .max_opts(10)
+            .eval_interval(usize::MAX)
+            .warmup_period(100)
+            .max_env_steps(1)
+            .sync_interval(5)
+            .checkpoint_interval(10)
+            .checkpoint_dir(checkpoint_dir.to_str().unwrap());
+
+        let (stat, opt_steps) = train_offline(&config, None)?;
+        assert_eq!(stat.stop_reason, StopReason::MaxOpts);
+        assert_eq!(opt_steps, (1..=10).collect::<Vec<_>>());
+        assert!(checkpoint_dir.join("trainer.yaml").exists());
+
+        let config = config.max_opts(15);
+        let (stat, opt_steps) = train_offline(&config, Some(&checkpoint_dir))?;
+        assert_eq!(stat.stop_reason, StopReason::MaxOpts);
+        assert_eq!(opt_steps, (11..=15).collect::<Vec<_>>());
+
+        std::fs::remove_dir_all(&checkpoint_dir)?;
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use border_core::{EvalMetric, TrainerConfig};
 use serde::{Deserialize, Serialize};
 use std::{
     fs::File,
@@ -7,77 +8,129 @@ use std::{
 };
 
 /// Configuration of [`AsyncTrainer`](crate::AsyncTrainer).
-#[derive(Clone, Debug, Deserialize, Serialize)]
+///
+/// The configuration of the training loop is shared with [`Trainer`](border_core::Trainer)
+/// and flattened in serialization, so fields of [`TrainerConfig`] appear at the top level
+/// of a YAML file.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct AsyncTrainerConfig {
-    /// The maximum number of optimization steps.
-    pub max_opts: usize,
-
-    /// Interval of evaluation in training steps.
-    pub eval_interval: usize,
-
-    /// Interval of flushing records in optimization steps.
-    pub flush_record_interval: usize,
-
-    /// Interval of recording agent information in optimization steps.
-    pub record_compute_cost_interval: usize,
-
-    /// Interval of recording agent information in optimization steps.
-    pub record_agent_info_interval: usize,
-
-    /// Interval of saving the model in optimization steps.
-    pub save_interval: usize,
-
-    /// Interval of synchronizing model parameters in training steps.
+    /// Configuration of the training loop.
+    ///
+    /// As samples are taken by actors, `opt_interval` is ignored and `warmup_period` is
+    /// the number of samples in the replay buffer before optimization begins.
+    #[serde(flatten)]
+    pub trainer: TrainerConfig,
+
+    /// Interval of synchronizing model parameters in optimization steps.
     pub sync_interval: usize,
-
-    /// Warmup period, for filling replay buffer, in environment steps
-    pub warmup_period: usize,
 }
 
 impl AsyncTrainerConfig {
     /// Sets the number of optimization steps.
-    pub fn max_opts(mut self, v: usize) -> Result<Self> {
-        self.max_opts = v;
-        Ok(self)
+    pub fn max_opts(mut self, v: usize) -> Self {
+        self.trainer = self.trainer.max_opts(v);
+        self
     }
 
     /// Sets the interval of evaluation in optimization steps.
-    pub fn eval_interval(mut self, v: usize) -> Result<Self> {
-        self.eval_interval = v;
-        Ok(self)
+    pub fn eval_interval(mut self, v: usize) -> Self {
+        self.trainer = self.trainer.eval_interval(v);
+        self
     }
 
     /// Sets the interval of computation cost in optimization steps.
-    pub fn record_compute_cost_interval(
-        mut self,
-        record_compute_cost_interval: usize,
-    ) -> Result<Self> {
-        self.record_compute_cost_interval = record_compute_cost_interval;
-        Ok(self)
+    pub fn record_compute_cost_interval(mut self, record_compute_cost_interval: usize) -> Self {
+        self.trainer = self
+            .trainer
+            .record_compute_cost_interval(record_compute_cost_interval);
+        self
+    }
+
+    /// Sets the interval of recording agent information in optimization steps.
+    pub fn record_agent_info_interval(mut self, record_agent_info_interval: usize) -> Self {
+        self.trainer = self
+            .trainer
+            .record_agent_info_interval(record_agent_info_interval);
+        self
     }
 
     /// Sets the interval of flushing recordd in optimization steps.
-    pub fn flush_record_interval(mut self, flush_record_interval: usize) -> Result<Self> {
-        self.flush_record_interval = flush_record_interval;
-        Ok(self)
+    pub fn flush_record_interval(mut self, flush_record_interval: usize) -> Self {
+        self.trainer = self.trainer.flush_record_interval(flush_record_interval);
+        self
     }
 
     /// Sets warmup period in environment steps.
-    pub fn warmup_period(mut self, warmup_period: usize) -> Result<Self> {
-        self.warmup_period = warmup_period;
-        Ok(self)
+    pub fn warmup_period(mut self, warmup_period: usize) -> Self {
+        self.trainer = self.trainer.warmup_period(warmup_period);
+        self
     }
 
     /// Sets the interval of saving in optimization steps.
-    pub fn save_interval(mut self, save_interval: usize) -> Result<Self> {
-        self.save_interval = save_interval;
-        Ok(self)
+    pub fn save_interval(mut self, save_interval: usize) -> Self {
+        self.trainer = self.trainer.save_interval(save_interval);
+        self
     }
 
-    /// Sets the interval of synchronizing model parameters in training steps.
-    pub fn sync_interval(mut self, sync_interval: usize) -> Result<Self> {
+    /// Sets the interval of synchronizing model parameters in optimization steps.
+    pub fn sync_interval(mut self, sync_interval: usize) -> Self {
         self.sync_interval = sync_interval;
-        Ok(self)
+        self
+    }
+
+    /// Sets the interval of saving training checkpoints in optimization steps.
+    pub fn checkpoint_interval(mut self, checkpoint_interval: usize) -> Self {
+        self.trainer = self.trainer.checkpoint_interval(checkpoint_interval);
+        self
+    }
+
+    /// Sets the directory where training checkpoints are saved.
+    pub fn checkpoint_dir(mut self, checkpoint_dir: impl Into<String>) -> Self {
+        self.trainer = self.trainer.checkpoint_dir(checkpoint_dir);
+        self
+    }
+
+    /// Sets the evaluation score at which training stops.
+    pub fn eval_threshold(mut self, v: f32) -> Self {
+        self.trainer = self.trainer.eval_threshold(v);
+        self
+    }
+
+    /// Sets the number of evaluations without improvement after which training stops.
+    pub fn early_stopping_patience(mut self, v: usize) -> Self {
+        self.trainer = self.trainer.early_stopping_patience(v);
+        self
+    }
+
+    /// Sets the wall-clock time in seconds after which training stops.
+    pub fn max_wall_time_secs(mut self, v: u64) -> Self {
+        self.trainer = self.trainer.max_wall_time_secs(v);
+        self
+    }
+
+    /// Sets the number of samples pushed to the replay buffer after which training stops.
+    pub fn max_env_steps(mut self, v: usize) -> Self {
+        self.trainer = self.trainer.max_env_steps(v);
+        self
+    }
+
+    /// Sets the metric of evaluation used for model selection.
+    pub fn eval_metric(mut self, v: EvalMetric) -> Self {
+        self.trainer = self.trainer.eval_metric(v);
+        self
+    }
+
+    /// Returns [`TrainerConfig`] of the training-loop core shared with
+    /// [`Trainer`](border_core::Trainer).
+    ///
+    /// As samples are taken by actors, `opt_interval` is 1 and `warmup_period`
+    /// is handled by [`AsyncTrainer`](crate::AsyncTrainer) itself.
+    pub fn trainer_config(&self) -> TrainerConfig {
+        TrainerConfig {
+            opt_interval: 1,
+            warmup_period: 0,
+            ..self.trainer.clone()
+        }
     }
 
     /// Constructs [AsyncTrainerConfig] from YAML file.
@@ -100,14 +153,49 @@ impl Default for AsyncTrainerConfig {
     /// There is no special intention behind these initial values.
     fn default() -> Self {
         Self {
-            max_opts: 10, //000,
-            eval_interval: 5000,
-            flush_record_interval: 5000,
-            record_compute_cost_interval: 5000,
-            record_agent_info_interval: 5000,
-            save_interval: 50000,
+            trainer: TrainerConfig::default()
+                .max_opts(10)
+                .eval_interval(5000)
+                .flush_record_interval(5000)
+                .record_compute_cost_interval(5000)
+                .record_agent_info_interval(5000)
+                .save_interval(50000)
+                .warmup_period(10000),
             sync_interval: 100,
-            warmup_period: 10000,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flattened_trainer_config() -> Result<()> {
+        let config = AsyncTrainerConfig::default()
+            .max_opts(15)
+            .sync_interval(5)
+            .eval_threshold(1.0);
+        let yaml = serde_yaml::to_string(&config)?;
+        assert!(yaml.lines().any(|l| l == "max_opts: 15"));
+        assert!(yaml.lines().any(|l| l == "sync_interval: 5"));
+        assert_eq!(serde_yaml::from_str::<AsyncTrainerConfig>(&yaml)?, config);
+
+        // Fields of the training loop with defaults can be omitted
+        let yaml = "
+max_opts: 15
+eval_interval: 10
+flush_record_interval: 10
+record_compute_cost_interval: 10
+record_agent_info_interval: 10
+warmup_period: 100
+save_interval: 10
+sync_interval: 5
+";
+        let config = serde_yaml::from_str::<AsyncTrainerConfig>(yaml)?;
+        assert_eq!(config.trainer.max_opts, 15);
+        assert_eq!(config.trainer.checkpoint_interval, usize::MAX);
+        assert_eq!(config.sync_interval, 5);
+        Ok(())
+    }
+}
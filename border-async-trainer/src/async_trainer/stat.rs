@@ -1,3 +1,4 @@
+use border_core::StopReason;
 use std::time::Duration;
 /// Stats of [`AsyncTrainer`](crate::AsyncTrainer)`::train()`.
 pub struct AsyncTrainStat {
@@ -9,6 +10,9 @@ pub struct AsyncTrainStat {
 
     /// The number of optimization steps per second.
     pub opt_per_sec: f32,
+
+    /// The reason why training stopped.
+    pub stop_reason: StopReason,
 }
 
 impl AsyncTrainStat {
@@ -25,3 +29,21 @@ impl AsyncTrainStat {
         s
     }
 }
+
+/// Metrics of an [`Actor`](crate::Actor), updated with [`PushedItemMessage`]s.
+///
+/// [`PushedItemMessage`]: crate::PushedItemMessage
+#[derive(Default)]
+pub(crate) struct ActorMetrics {
+    /// The number of samples received since the last recording.
+    pub n_samples: usize,
+
+    /// The number of messages in the channel when the last message was sent.
+    pub queue_depth: usize,
+
+    /// The number of optimization steps of the model with which the last samples were taken.
+    pub n_opt_steps: usize,
+
+    /// The total number of samples discarded by the actor.
+    pub n_dropped: usize,
+}
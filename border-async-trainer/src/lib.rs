@@ -133,7 +133,7 @@
 //!     &async_trainer_config,
 //!     &mut recorder,
 //!     &mut evaluator,
-//! );
+//! ).unwrap();
 //! ```
 //!
 //! Training process consists of the following two components:
@@ -154,25 +154,46 @@
 //! using CPU.
 //!
 //! Both [`AsyncTrainer`] and [`ActorManager`] are running in the same machine and
-//! communicate by channels.
+//! communicate by channels. When the channel of samples is full, actors behave as
+//! given by [`BackpressurePolicy`]. Actors that panic are restarted with fresh
+//! environments up to [`ActorManagerConfig::max_restarts`] times.
 //!
+//! [`ProcessActorManager`] can be used instead of [`ActorManager`] to run [`Actor`]s in
+//! separate processes, which exchange the same messages with [`AsyncTrainer`] over sockets.
+//! Environments of actors then do not contend with each other and the trainer, e.g.,
+//! on the GIL of Python. See [`util::train_async_with_processes()`].
+//!
+//! [`AsyncTrainer`] shares the core of the training loop, [`TrainerCore`], with
+//! [`Trainer`], so that evaluation, saving the best model, training checkpoints and
+//! criteria for stopping training behave identically in synchronous and asynchronous
+//! training. Training is resumed from a checkpoint with [`AsyncTrainer::resume_from`].
+//!
+//! [`TrainerCore`]: border_core::TrainerCore
+//! [`Trainer`]: border_core::Trainer
 //! [`Agent`]: border_core::Agent
 //! [`Env`]: border_core::Env
 mod actor;
 mod actor_manager;
+mod ape_x;
 mod async_trainer;
 mod error;
 mod messages;
+mod process_actor;
 mod replay_buffer_proxy;
 mod sync_model;
 pub mod util;
 
 pub use actor::{actor_stats_fmt, Actor, ActorStat};
 pub use actor_manager::{ActorManager, ActorManagerConfig};
+pub use ape_x::{ApeXAgent, ApeXEpsilonConfig};
 pub use async_trainer::{AsyncTrainStat, AsyncTrainer, AsyncTrainerConfig};
 pub use error::BorderAsyncTrainerError;
 pub use messages::PushedItemMessage;
-pub use replay_buffer_proxy::{ReplayBufferProxy, ReplayBufferProxyConfig};
+pub use process_actor::{
+    process_actor_env, run_process_actor, run_process_actor_ape_x, ActorAddress,
+    ProcessActorManager, ProcessActorManagerConfig, ACTOR_ADDRESS_ENV, ACTOR_ID_ENV,
+};
+pub use replay_buffer_proxy::{BackpressurePolicy, ReplayBufferProxy, ReplayBufferProxyConfig};
 pub use sync_model::SyncModel;
 
 /// Agent and Env for testing.
@@ -194,6 +215,7 @@ pub mod test {
     }
 
     /// Batch of obs for testing.
+    #[derive(Deserialize, Serialize)]
     pub struct TestObsBatch {
         obs: Vec<usize>,
     }
@@ -213,6 +235,23 @@ pub mod test {
             let obs = ixs.iter().map(|ix| self.obs[*ix]).collect();
             Self { obs }
         }
+
+        fn save(&self, path: &Path, len: usize) -> anyhow::Result<()> {
+            let data = self.obs[..len]
+                .iter()
+                .map(|&x| x as i64)
+                .collect::<Vec<_>>();
+            border_core::generic_replay_buffer::npy::write_npy(path.join("obs.npy"), &[len], &data)
+        }
+
+        fn load(&mut self, path: &Path) -> anyhow::Result<usize> {
+            let (data, _) =
+                border_core::generic_replay_buffer::npy::read_npy::<i64>(path.join("obs.npy"))?;
+            for (i, x) in data.iter().enumerate() {
+                self.obs[i] = *x as usize;
+            }
+            Ok(data.len())
+        }
     }
 
     impl From<TestObs> for TestObsBatch {
@@ -230,6 +269,7 @@ pub mod test {
     impl border_core::Act for TestAct {}
 
     /// Batch of act for testing.
+    #[derive(Deserialize, Serialize)]
     pub struct TestActBatch {
         act: Vec<usize>,
     }
@@ -255,6 +295,23 @@ pub mod test {
             let act = ixs.iter().map(|ix| self.act[*ix]).collect();
             Self { act }
         }
+
+        fn save(&self, path: &Path, len: usize) -> anyhow::Result<()> {
+            let data = self.act[..len]
+                .iter()
+                .map(|&x| x as i64)
+                .collect::<Vec<_>>();
+            border_core::generic_replay_buffer::npy::write_npy(path.join("act.npy"), &[len], &data)
+        }
+
+        fn load(&mut self, path: &Path) -> anyhow::Result<usize> {
+            let (data, _) =
+                border_core::generic_replay_buffer::npy::read_npy::<i64>(path.join("act.npy"))?;
+            for (i, x) in data.iter().enumerate() {
+                self.act[i] = *x as usize;
+            }
+            Ok(data.len())
+        }
     }
 
     /// Info for testing.
@@ -325,10 +382,14 @@ pub mod test {
             return (step, border_core::record::Record::empty());
         }
 
-        fn build(config: &Self::Config, _seed: i64) -> anyhow::Result<Self>
+        /// Fails if `seed` is less than `config`, which is used to test restarts of actors.
+        fn build(config: &Self::Config, seed: i64) -> anyhow::Result<Self>
         where
             Self: Sized,
         {
+            if seed < *config as i64 {
+                anyhow::bail!("Failed to build TestEnv with seed {}", seed);
+            }
             Ok(Self {
                 state_init: *config,
                 state: 0,
@@ -401,4 +462,23 @@ pub mod test {
             // nothing to do
         }
     }
+
+    impl
+        crate::ApeXAgent<
+            border_core::generic_replay_buffer::GenericTransitionBatch<TestObsBatch, TestActBatch>,
+        > for TestAgent
+    {
+        fn compute_priorities(
+            &mut self,
+            item: &border_core::generic_replay_buffer::GenericTransitionBatch<
+                TestObsBatch,
+                TestActBatch,
+            >,
+        ) -> Vec<f32> {
+            use border_core::TransitionBatch;
+            vec![1.0; item.len()]
+        }
+
+        fn set_epsilon(&mut self, _epsilon: f64) {}
+    }
 }
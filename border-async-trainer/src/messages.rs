@@ -1,10 +1,61 @@
+use crossbeam_channel::{SendTimeoutError, Sender};
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+
+/// Interval of checking the stop flag while the channel to the trainer is full.
+const FORWARD_TIMEOUT: Duration = Duration::from_millis(100);
+
 /// Message containing a [`ReplayBufferBase`](border_core::ReplayBufferBase)`::Item`.
 ///
 /// It will be sent from [`Actor`](crate::Actor) to [`ActorManager`](crate::ActorManager).
+/// It is serialized when sent from an actor process to
+/// [`ProcessActorManager`](crate::ProcessActorManager).
+#[derive(Deserialize, Serialize)]
 pub struct PushedItemMessage<T> {
     /// ID of [`Actor`](crate::Actor) which generates samples (`pushed_item`).
     pub id: usize,
 
     /// A batch.
     pub pushed_items: Vec<T>,
+
+    /// Priorities of the transitions in each item of `pushed_items`.
+    ///
+    /// These are computed in actors of distributed prioritized experience replay
+    /// (see [`ApeXAgent`](crate::ApeXAgent)). If `None`, the maximum priority is used.
+    pub priorities: Option<Vec<Vec<f32>>>,
+
+    /// The number of optimization steps of the model with which the samples were taken.
+    pub n_opt_steps: usize,
+
+    /// The number of messages in the channel when the actor sent this message.
+    pub queue_depth: usize,
+
+    /// The total number of samples discarded by the actor due to backpressure
+    /// (see [`BackpressurePolicy`](crate::BackpressurePolicy)).
+    pub n_dropped: usize,
+}
+
+/// Forwards a message from actors to [`AsyncTrainer`](crate::AsyncTrainer).
+///
+/// This blocks while the channel to the trainer is full, so that the channel of samples
+/// in actors becomes full and [`BackpressurePolicy`](crate::BackpressurePolicy) is applied.
+/// Returns `false` if the message is discarded because the trainer has finished.
+pub(crate) fn forward_pushed_items<T>(
+    sender: &Sender<PushedItemMessage<T>>,
+    mut msg: PushedItemMessage<T>,
+    stop: &Mutex<bool>,
+) -> bool {
+    loop {
+        if *stop.lock().unwrap_or_else(PoisonError::into_inner) {
+            return false;
+        }
+        msg = match sender.send_timeout(msg, FORWARD_TIMEOUT) {
+            Ok(()) => return true,
+            Err(SendTimeoutError::Timeout(msg)) => msg,
+            Err(SendTimeoutError::Disconnected(_)) => return false,
+        };
+    }
 }
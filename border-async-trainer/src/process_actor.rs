@@ -0,0 +1,179 @@
+//! Actors running in separate processes.
+//!
+//! [`ProcessActorManager`] is used in place of [`ActorManager`](crate::ActorManager) to run
+//! [`Actor`](crate::Actor)s in separate processes, which communicate with the trainer
+//! process over TCP or Unix domain sockets. By default, the manager spawns the executable
+//! of the trainer process for each actor, so the executable must start the actor when it
+//! is run as an actor process:
+//!
+//! ```ignore
+//! fn main() -> Result<()> {
+//!     if let Some((address, id)) = process_actor_env()? {
+//!         run_process_actor::<Agent, Env, StepProcessor, ReplayBuffer>(&address, id)?;
+//!         return Ok(());
+//!     }
+//!
+//!     // Training with `train_async_with_processes()`
+//!     // ...
+//! }
+//! ```
+//!
+//! With [`ProcessActorManagerConfig::spawn`] set to `false`, the manager does not spawn
+//! actors, but waits for actors launched by the user, e.g., on other hosts, each of which
+//! calls [`run_process_actor()`] with the address of the manager and its ID.
+//!
+//! For distributed prioritized experience replay as in Ape-X, actors are run with
+//! [`run_process_actor_ape_x()`] instead.
+mod actor;
+mod config;
+mod manager;
+mod messages;
+mod transport;
+pub use actor::{
+    process_actor_env, run_process_actor, run_process_actor_ape_x, ACTOR_ADDRESS_ENV, ACTOR_ID_ENV,
+};
+pub use config::ProcessActorManagerConfig;
+pub use manager::ProcessActorManager;
+pub use transport::ActorAddress;
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{
+        messages::ActorHello,
+        transport::{write_message, Stream},
+        *,
+    };
+    use crate::test::{TestActBatch, TestAgent, TestAgentConfig, TestEnv, TestObsBatch};
+    use anyhow::Result;
+    use border_core::generic_replay_buffer::{
+        SimpleReplayBuffer, SimpleStepProcessor, SimpleStepProcessorConfig,
+    };
+    use crossbeam_channel::{bounded, unbounded};
+    use std::{
+        io::{Read, Write},
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    type ReplayBuffer = SimpleReplayBuffer<TestObsBatch, TestActBatch>;
+    type StepProcessor = SimpleStepProcessor<TestEnv, TestObsBatch, TestActBatch>;
+    type Manager = ProcessActorManager<TestAgent, TestEnv, ReplayBuffer, StepProcessor>;
+
+    fn socket_address(name: &str) -> ActorAddress {
+        let file = format!("border_{}_{}.sock", name, std::process::id());
+        ActorAddress::Unix(std::env::temp_dir().join(file))
+    }
+
+    /// Connects to the manager and sends a message, expecting that the manager closes the
+    /// connection without replying.
+    fn connect_rejected(address: ActorAddress, msg: impl FnOnce(&mut Stream) -> Result<()>) {
+        let mut stream = Stream::connect_with_retry(&address, Duration::from_secs(10)).unwrap();
+        msg(&mut stream).unwrap();
+        assert_eq!(stream.read(&mut [0u8; 8]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_process_actor_manager() -> Result<()> {
+        let address = socket_address("actor");
+        let config = ProcessActorManagerConfig::default()
+            .address(address.clone())
+            .spawn(false)
+            .n_buffer(10);
+        let (item_s, item_r) = bounded(config.channel_capacity);
+        let (model_s, model_r) = unbounded();
+        let mut manager = Manager::build(
+            &config,
+            &vec![TestAgentConfig; 2],
+            &0,
+            &SimpleStepProcessorConfig::default(),
+            item_s,
+            model_r,
+            Arc::new(Mutex::new(false)),
+        );
+
+        // Connections with an unexpected ID or a broken hello are rejected
+        let rejected = vec![
+            std::thread::spawn({
+                let address = address.clone();
+                move || connect_rejected(address, |s| write_message(s, &ActorHello { id: 2 }))
+            }),
+            std::thread::spawn({
+                let address = address.clone();
+                move || connect_rejected(address, |s| Ok(s.write_all(&[0xff; 8])?))
+            }),
+        ];
+
+        // Actors run on threads instead of processes, connected over the socket after
+        // the rejected connections
+        let actors: Vec<_> = (0..2)
+            .map(|id| {
+                let address = address.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(Duration::from_millis(500));
+                    run_process_actor::<TestAgent, TestEnv, StepProcessor, ReplayBuffer>(
+                        &address, id,
+                    )
+                })
+            })
+            .collect();
+        manager.run()?;
+        for handle in rejected {
+            handle.join().unwrap();
+        }
+
+        // Actors start sampling after receiving the initial model info
+        model_s.send((0, 0))?;
+        let mut ids = vec![];
+        while ids.len() < 2 {
+            let msg = item_r.recv()?;
+            assert_eq!(msg.pushed_items.len(), 10);
+            if !ids.contains(&msg.id) {
+                ids.push(msg.id);
+            }
+        }
+
+        let stats = manager.stop_and_join();
+        assert_eq!(stats.len(), 2);
+        for actor in actors {
+            assert!(actor.join().unwrap()?.env_steps >= 10);
+        }
+
+        Ok(())
+    }
+    #[test]
+    fn test_kill_children_on_error() -> Result<()> {
+        // Actor 0 exits before connecting, while actor 1 keeps running
+        let pid_file = std::env::temp_dir().join(format!("border_pid_{}", std::process::id()));
+        let script = format!(
+            "if [ $BORDER_ACTOR_ID = 1 ]; then echo $$ > {}; exec sleep 30; fi; sleep 1",
+            pid_file.display()
+        );
+        let config = ProcessActorManagerConfig::default()
+            .address(socket_address("kill"))
+            .program("sh")
+            .args(vec!["-c".to_string(), script]);
+        let (item_s, _item_r) = unbounded();
+        let (_model_s, model_r) = unbounded();
+        let mut manager = Manager::build(
+            &config,
+            &vec![TestAgentConfig; 2],
+            &0,
+            &SimpleStepProcessorConfig::default(),
+            item_s,
+            model_r,
+            Arc::new(Mutex::new(false)),
+        );
+        assert!(manager.run().is_err());
+
+        // The process of actor 1 has been killed and reaped
+        let pid = std::fs::read_to_string(&pid_file)?;
+        std::fs::remove_file(&pid_file)?;
+        let status = std::process::Command::new("kill")
+            .args(["-0", pid.trim()])
+            .stderr(std::process::Stdio::null())
+            .status()?;
+        assert!(!status.success());
+
+        Ok(())
+    }
+}
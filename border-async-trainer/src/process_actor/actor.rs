@@ -0,0 +1,228 @@
+use super::{
+    messages::{ActorHello, ActorInit, ActorMessage, ManagerMessage},
+    transport::{read_message, write_message, Stream},
+    ActorAddress,
+};
+use crate::{
+    ape_x::ApeXHooks, Actor, ActorStat, ApeXAgent, PushedItemMessage, ReplayBufferProxyConfig,
+    SyncModel,
+};
+use anyhow::{anyhow, Context, Result};
+use border_core::{
+    Agent, Configurable, Env, ExperienceBufferBase, ReplayBufferBase, StepProcessor,
+};
+use crossbeam_channel::{bounded, Receiver};
+use log::{info, warn};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+/// Time for retrying to connect to the manager.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Environment variable holding the address of the manager, set for spawned actor processes.
+pub const ACTOR_ADDRESS_ENV: &str = "BORDER_ACTOR_ADDRESS";
+
+/// Environment variable holding the ID of the actor, set for spawned actor processes.
+pub const ACTOR_ID_ENV: &str = "BORDER_ACTOR_ID";
+
+/// Returns the address of the manager and the ID of the actor if the current process
+/// is an actor process.
+///
+/// These are given by the environment variables [`ACTOR_ADDRESS_ENV`] and [`ACTOR_ID_ENV`],
+/// which are set when [`ProcessActorManager`](super::ProcessActorManager) spawns the process.
+/// Returns `None` if the variables are not set.
+pub fn process_actor_env() -> Result<Option<(ActorAddress, usize)>> {
+    let address = match std::env::var(ACTOR_ADDRESS_ENV) {
+        Ok(address) => address.parse()?,
+        Err(_) => return Ok(None),
+    };
+    let id = std::env::var(ACTOR_ID_ENV)
+        .with_context(|| format!("{} is not set", ACTOR_ID_ENV))?
+        .parse()
+        .with_context(|| format!("Invalid {}", ACTOR_ID_ENV))?;
+    Ok(Some((address, id)))
+}
+
+/// Runs an [`Actor`] in the current process, connected to
+/// [`ProcessActorManager`](super::ProcessActorManager) at `address`.
+///
+/// The configurations of the agent, the environment and the step processor are received
+/// from the manager. Samples are pushed to the manager and the model is synchronized with
+/// [`SyncModel::ModelInfo`] from the manager, as [`Actor`]s in
+/// [`ActorManager`](crate::ActorManager) do with channels. If the manager is not listening
+/// yet, connection is retried for 30 seconds. This function returns when the manager stops
+/// the actor or the connection is lost.
+pub fn run_process_actor<A, E, P, R>(address: &ActorAddress, id: usize) -> Result<ActorStat>
+where
+    A: Agent<E, R> + Configurable + SyncModel + 'static,
+    E: Env,
+    P: StepProcessor<E>,
+    R: ExperienceBufferBase<Item = P::Output> + ReplayBufferBase,
+    E::Config: DeserializeOwned,
+    P::Config: DeserializeOwned,
+    R::Item: Serialize + Send + 'static,
+    A::ModelInfo: DeserializeOwned + Send + 'static,
+{
+    run::<A, E, P, R>(address, id, None)
+}
+
+/// Runs an [`Actor`] in the current process as [`run_process_actor()`], computing the
+/// priorities of samples as in Ape-X.
+///
+/// The priorities are computed with [`ApeXAgent::compute_priorities()`]. If
+/// [`ProcessActorManagerConfig::ape_x_epsilon`] is given, the exploration rate of the agent
+/// is set with [`ApeXAgent::set_epsilon()`].
+///
+/// [`ProcessActorManagerConfig::ape_x_epsilon`]: super::ProcessActorManagerConfig::ape_x_epsilon
+pub fn run_process_actor_ape_x<A, E, P, R>(address: &ActorAddress, id: usize) -> Result<ActorStat>
+where
+    A: Agent<E, R> + Configurable + SyncModel + ApeXAgent<R::Item> + 'static,
+    E: Env,
+    P: StepProcessor<E>,
+    R: ExperienceBufferBase<Item = P::Output> + ReplayBufferBase,
+    E::Config: DeserializeOwned,
+    P::Config: DeserializeOwned,
+    R::Item: Serialize + Send + 'static,
+    A::ModelInfo: DeserializeOwned + Send + 'static,
+{
+    run::<A, E, P, R>(address, id, Some(ApeXHooks::new()))
+}
+
+fn run<A, E, P, R>(
+    address: &ActorAddress,
+    id: usize,
+    ape_x: Option<ApeXHooks<A, R::Item>>,
+) -> Result<ActorStat>
+where
+    A: Agent<E, R> + Configurable + SyncModel + 'static,
+    E: Env,
+    P: StepProcessor<E>,
+    R: ExperienceBufferBase<Item = P::Output> + ReplayBufferBase,
+    E::Config: DeserializeOwned,
+    P::Config: DeserializeOwned,
+    R::Item: Serialize + Send + 'static,
+    A::ModelInfo: DeserializeOwned + Send + 'static,
+{
+    let mut stream = Stream::connect_with_retry(address, CONNECT_TIMEOUT)?;
+    write_message(&mut stream, &ActorHello { id })?;
+    let init: ActorInit<A::Config, E::Config, P::Config> = read_message(&mut stream)?;
+    let model_info = match read_message(&mut stream)? {
+        ManagerMessage::ModelInfo(n_opt_steps, model_info) => (n_opt_steps, model_info),
+        ManagerMessage::Stop => return Err(anyhow!("Actor {} was stopped before sampling", id)),
+    };
+    info!("Connected to {} in actor process {}", address, id);
+
+    let model_info = Arc::new(Mutex::new(model_info));
+    let stop = Arc::new(Mutex::new(false));
+
+    // Thread for receiving model info
+    let model_info_handle = {
+        let reader = stream.try_clone()?;
+        let model_info = model_info.clone();
+        let stop = stop.clone();
+        std::thread::spawn(move || run_model_info_loop(id, reader, model_info, stop))
+    };
+
+    // Thread for sending samples. When sending samples over the socket is slower than
+    // sampling, the channel becomes full and the backpressure policy is applied.
+    let (sender, receiver) = bounded(init.channel_capacity);
+    let push_handle = {
+        let writer = stream.try_clone()?;
+        let receiver = receiver.clone();
+        let stop = stop.clone();
+        std::thread::spawn(move || run_push_loop(id, writer, receiver, stop))
+    };
+
+    if ape_x.is_none() && init.epsilon.is_some() {
+        warn!(
+            "ape_x_epsilon is ignored as actor process {} does not run Ape-X",
+            id
+        );
+    }
+
+    // Runs sampling loop until stopped
+    let stats = Arc::new(Mutex::new(None));
+    let mut actor = Actor::<A, E, P, R>::build(
+        id,
+        init.agent_config,
+        init.env_config,
+        init.step_proc_config,
+        ReplayBufferProxyConfig {
+            n_buffer: init.n_buffer,
+            backpressure: init.backpressure,
+        },
+        stop,
+        init.env_seed,
+        stats.clone(),
+    )
+    .with_receiver(receiver);
+    if let Some(ape_x) = ape_x {
+        actor = actor.with_ape_x(ape_x, init.epsilon);
+    }
+    actor.run(
+        sender,
+        model_info,
+        Arc::new(Mutex::new(true)),
+        Arc::new(Mutex::new(true)),
+    );
+
+    // The sender has been dropped with the actor, so all samples have been sent
+    push_handle.join().unwrap();
+    let stat = stats
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .unwrap();
+    write_message(&mut stream, &ActorMessage::<R::Item>::Stat(stat.clone()))?;
+    model_info_handle.join().unwrap();
+    info!("Stopped actor process {}", id);
+
+    Ok(stat)
+}
+
+/// Updates the model info until the manager stops the actor.
+fn run_model_info_loop<M: DeserializeOwned>(
+    id: usize,
+    mut reader: Stream,
+    model_info: Arc<Mutex<(usize, M)>>,
+    stop: Arc<Mutex<bool>>,
+) {
+    loop {
+        match read_message(&mut reader) {
+            Ok(ManagerMessage::ModelInfo(n_opt_steps, msg)) => {
+                *model_info.lock().unwrap_or_else(PoisonError::into_inner) = (n_opt_steps, msg);
+            }
+            Ok(ManagerMessage::Stop) => break,
+            Err(e) => {
+                warn!(
+                    "Lost connection to the manager in actor process {}: {}",
+                    id, e
+                );
+                break;
+            }
+        }
+    }
+    *stop.lock().unwrap_or_else(PoisonError::into_inner) = true;
+}
+
+/// Sends samples to the manager until the actor drops the sender.
+fn run_push_loop<T: Serialize>(
+    id: usize,
+    mut writer: Stream,
+    receiver: Receiver<PushedItemMessage<T>>,
+    stop: Arc<Mutex<bool>>,
+) {
+    for msg in receiver.iter() {
+        if let Err(e) = write_message(&mut writer, &ActorMessage::PushedItems(msg)) {
+            warn!("Failed to push samples in actor process {}: {}", id, e);
+            *stop.lock().unwrap_or_else(PoisonError::into_inner) = true;
+
+            // Drains samples until the actor stops
+            receiver.iter().for_each(drop);
+            break;
+        }
+    }
+}
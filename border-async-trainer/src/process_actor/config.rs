@@ -0,0 +1,126 @@
+use super::ActorAddress;
+use crate::{ApeXEpsilonConfig, BackpressurePolicy};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Configuration of [`ProcessActorManager`](super::ProcessActorManager).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProcessActorManagerConfig {
+    /// Number of samples to be buffered in each actor until being pushed to the replay buffer.
+    ///
+    /// The default value is 100.
+    pub n_buffer: usize,
+
+    /// Address at which the manager waits for actor processes.
+    ///
+    /// The default value is `tcp://127.0.0.1:0`, i.e., a free port of the local host.
+    pub address: ActorAddress,
+
+    /// If `true`, the manager spawns actor processes on the local host.
+    ///
+    /// If `false`, the manager waits for actor processes launched by the user, which
+    /// connect to [`ProcessActorManagerConfig::address`], possibly from other hosts.
+    /// The default value is `true`.
+    pub spawn: bool,
+
+    /// Program run in the spawned actor processes.
+    ///
+    /// If `None`, the executable of the current process is run. The default value is `None`.
+    pub program: Option<PathBuf>,
+
+    /// Command line arguments of the spawned actor processes.
+    pub args: Vec<String>,
+
+    /// Exploration rates of actors as in Ape-X.
+    ///
+    /// This is used by actors run with [`run_process_actor_ape_x()`].
+    /// The default value is `None`.
+    ///
+    /// [`run_process_actor_ape_x()`]: super::run_process_actor_ape_x
+    #[serde(default)]
+    pub ape_x_epsilon: Option<ApeXEpsilonConfig>,
+
+    /// Behavior of actors when the channel of samples to be sent to the manager is full.
+    ///
+    /// The default value is [`BackpressurePolicy::Block`].
+    #[serde(default)]
+    pub backpressure: BackpressurePolicy,
+
+    /// Capacity of the channels of samples.
+    ///
+    /// This is the capacity of the channel of samples to be sent in each actor process.
+    /// [`train_async_with_processes()`](crate::util::train_async_with_processes) also bounds
+    /// the channel from the manager to the trainer with this capacity.
+    /// The default value is 1000.
+    #[serde(default = "default_channel_capacity")]
+    pub channel_capacity: usize,
+}
+
+fn default_channel_capacity() -> usize {
+    1000
+}
+
+impl Default for ProcessActorManagerConfig {
+    fn default() -> Self {
+        Self {
+            n_buffer: 100,
+            address: ActorAddress::default(),
+            spawn: true,
+            program: None,
+            args: vec![],
+            ape_x_epsilon: None,
+            backpressure: BackpressurePolicy::default(),
+            channel_capacity: default_channel_capacity(),
+        }
+    }
+}
+
+impl ProcessActorManagerConfig {
+    /// Sets the number of samples buffered in each actor.
+    pub fn n_buffer(mut self, n_buffer: usize) -> Self {
+        self.n_buffer = n_buffer;
+        self
+    }
+
+    /// Sets the address at which the manager waits for actor processes.
+    pub fn address(mut self, address: ActorAddress) -> Self {
+        self.address = address;
+        self
+    }
+
+    /// Sets if the manager spawns actor processes.
+    pub fn spawn(mut self, spawn: bool) -> Self {
+        self.spawn = spawn;
+        self
+    }
+
+    /// Sets the program run in the spawned actor processes.
+    pub fn program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = Some(program.into());
+        self
+    }
+
+    /// Sets the command line arguments of the spawned actor processes.
+    pub fn args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Sets the exploration rates of actors as in Ape-X.
+    pub fn ape_x_epsilon(mut self, ape_x_epsilon: Option<ApeXEpsilonConfig>) -> Self {
+        self.ape_x_epsilon = ape_x_epsilon;
+        self
+    }
+
+    /// Sets the behavior of actors when the channel of samples is full.
+    pub fn backpressure(mut self, backpressure: BackpressurePolicy) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+
+    /// Sets the capacity of the channels of samples.
+    pub fn channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.channel_capacity = channel_capacity;
+        self
+    }
+}
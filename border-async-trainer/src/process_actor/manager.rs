@@ -0,0 +1,393 @@
+use super::{
+    messages::{ActorHello, ActorInit, ActorMessage, ManagerMessage},
+    transport::{encode_message, read_message, write_encoded, write_message, Listener, Stream},
+    ProcessActorManagerConfig, ACTOR_ADDRESS_ENV, ACTOR_ID_ENV,
+};
+use crate::{messages::forward_pushed_items, ActorStat, PushedItemMessage, SyncModel};
+use anyhow::{anyhow, Result};
+use border_core::{
+    Agent, Configurable, Env, ExperienceBufferBase, ReplayBufferBase, StepProcessor,
+};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use log::{info, warn};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    marker::PhantomData,
+    process::{Child, Command},
+    sync::{Arc, Mutex, PoisonError},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// Time for waiting [`ActorHello`] from a connected actor.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Manages actors running in separate processes.
+///
+/// This struct plays the same role as [`ActorManager`](crate::ActorManager), except that
+/// each [`Actor`](crate::Actor) runs in its own process, which calls
+/// [`run_process_actor()`](super::run_process_actor). Environments of actors do not share
+/// resources of the trainer process, such as the GIL of Python, and actors can run on
+/// other hosts when connected with TCP.
+///
+/// The manager and actors communicate over a socket with messages serialized by bincode:
+///
+/// * An actor sends its ID and receives the configurations of the agent, the environment
+///   and the step processor.
+/// * [`SyncModel::ModelInfo`] from the [`AsyncTrainer`](crate::AsyncTrainer) is forwarded
+///   to all actors.
+/// * [`PushedItemMessage`]s from actors are forwarded to the
+///   [`AsyncTrainer`](crate::AsyncTrainer).
+/// * When stopped, the manager asks actors to stop and receives their [`ActorStat`]s.
+///
+/// Thus, `A::Config`, `E::Config`, `P::Config`, [`SyncModel::ModelInfo`] and
+/// [`ExperienceBufferBase::Item`] must be serializable.
+///
+/// Spawned actor processes are killed if [`ProcessActorManager::run()`] fails or the manager
+/// is dropped without [`ProcessActorManager::join()`].
+pub struct ProcessActorManager<A, E, R, P>
+where
+    A: Agent<E, R> + Configurable + SyncModel,
+    E: Env,
+    P: StepProcessor<E>,
+    R: ExperienceBufferBase<Item = P::Output> + ReplayBufferBase,
+{
+    config: ProcessActorManagerConfig,
+
+    /// Configurations of [`Agent`]s.
+    agent_configs: Vec<A::Config>,
+
+    /// Configuration of [`Env`].
+    env_config: E::Config,
+
+    /// Configuration of a `StepProcessor`.
+    step_proc_config: P::Config,
+
+    /// Thread handles.
+    threads: Vec<JoinHandle<()>>,
+
+    /// Actor processes spawned by the manager.
+    children: Vec<Child>,
+
+    /// Flag to stop training
+    stop: Arc<Mutex<bool>>,
+
+    /// Sender of [PushedItemMessage]s to [AsyncTrainer](crate::AsyncTrainer).
+    pushed_item_message_sender: Sender<PushedItemMessage<R::Item>>,
+
+    /// Receives incoming model info from [AsyncTrainer](crate::AsyncTrainer).
+    model_info_receiver: Receiver<(usize, A::ModelInfo)>,
+
+    /// Stats of actors, shared with the threads receiving messages from actors.
+    actor_stats: Vec<Arc<Mutex<Option<ActorStat>>>>,
+
+    phantom: PhantomData<R>,
+}
+
+impl<A, E, R, P> ProcessActorManager<A, E, R, P>
+where
+    A: Agent<E, R> + Configurable + SyncModel + 'static,
+    E: Env,
+    P: StepProcessor<E>,
+    R: ExperienceBufferBase<Item = P::Output> + ReplayBufferBase,
+    A::Config: Serialize,
+    E::Config: Serialize,
+    P::Config: Serialize,
+    R::Item: DeserializeOwned + Send + 'static,
+    A::ModelInfo: Serialize + Send + 'static,
+{
+    /// Builds a [`ProcessActorManager`].
+    ///
+    /// The number of actors is the length of `agent_configs`.
+    pub fn build(
+        config: &ProcessActorManagerConfig,
+        agent_configs: &Vec<A::Config>,
+        env_config: &E::Config,
+        step_proc_config: &P::Config,
+        pushed_item_message_sender: Sender<PushedItemMessage<R::Item>>,
+        model_info_receiver: Receiver<(usize, A::ModelInfo)>,
+        stop: Arc<Mutex<bool>>,
+    ) -> Self {
+        Self {
+            config: config.clone(),
+            agent_configs: agent_configs.clone(),
+            env_config: env_config.clone(),
+            step_proc_config: step_proc_config.clone(),
+            threads: vec![],
+            children: vec![],
+            stop,
+            pushed_item_message_sender,
+            model_info_receiver,
+            actor_stats: vec![],
+            phantom: PhantomData,
+        }
+    }
+
+    /// Spawns actor processes if configured, waits for all actors to connect and runs
+    /// threads for exchanging messages with them.
+    ///
+    /// Actors are blocked until receiving the initial [`SyncModel::ModelInfo`] from
+    /// [`AsyncTrainer`](crate::AsyncTrainer). Connections with an invalid or unexpected
+    /// [`ActorHello`] are rejected, and the manager keeps waiting for actors. If this method
+    /// fails, the spawned actor processes are killed.
+    pub fn run(&mut self) -> Result<()> {
+        let result = self.run_();
+        if result.is_err() {
+            kill_children(&mut self.children);
+        }
+        result
+    }
+
+    fn run_(&mut self) -> Result<()> {
+        let n_actors = self.agent_configs.len();
+        let listener = Listener::bind(&self.config.address)?;
+        let address = listener.local_address()?;
+        info!("Waits for {} actors at {}", n_actors, address);
+
+        if self.config.spawn {
+            let program = match &self.config.program {
+                Some(program) => program.clone(),
+                None => std::env::current_exe()?,
+            };
+            for id in 0..n_actors {
+                let child = Command::new(&program)
+                    .args(&self.config.args)
+                    .env(ACTOR_ADDRESS_ENV, address.to_string())
+                    .env(ACTOR_ID_ENV, id.to_string())
+                    .spawn()?;
+                info!("Spawned process {} for actor {}", child.id(), id);
+                self.children.push(child);
+            }
+        }
+
+        // Waits for actors, failing if a spawned process exits before connecting
+        let mut streams: Vec<Option<Stream>> = (0..n_actors).map(|_| None).collect();
+        listener.set_nonblocking(true)?;
+        while streams.iter().any(|s| s.is_none()) {
+            match listener.accept()? {
+                Some(mut stream) => {
+                    let id = match read_hello(&mut stream) {
+                        Ok(id) if id < n_actors && streams[id].is_none() => id,
+                        Ok(id) => {
+                            warn!("Rejected unexpected connection from actor {}", id);
+                            continue;
+                        }
+                        Err(e) => {
+                            warn!("Rejected connection with invalid hello: {}", e);
+                            continue;
+                        }
+                    };
+                    let init = ActorInit {
+                        agent_config: self.agent_configs[id].clone(),
+                        env_config: self.env_config.clone(),
+                        step_proc_config: self.step_proc_config.clone(),
+                        n_buffer: self.config.n_buffer,
+                        backpressure: self.config.backpressure,
+                        channel_capacity: self.config.channel_capacity,
+                        env_seed: id as i64,
+                        epsilon: self
+                            .config
+                            .ape_x_epsilon
+                            .as_ref()
+                            .map(|config| config.epsilon_of(id, n_actors)),
+                    };
+                    if let Err(e) = write_message(&mut stream, &init) {
+                        warn!("Failed to initialize actor {}: {}", id, e);
+                        continue;
+                    }
+                    info!("Connected to actor {}", id);
+                    streams[id] = Some(stream);
+                }
+                None => {
+                    for child in self.children.iter_mut() {
+                        if let Some(status) = child.try_wait()? {
+                            return Err(anyhow!(
+                                "Actor process {} exited with {} before connecting",
+                                child.id(),
+                                status
+                            ));
+                        }
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+            }
+        }
+        let streams: Vec<_> = streams.into_iter().map(Option::unwrap).collect();
+
+        // Threads for handling incoming samples
+        for (id, stream) in streams.iter().enumerate() {
+            let reader = stream.try_clone()?;
+            let sender = self.pushed_item_message_sender.clone();
+            let stats = Arc::new(Mutex::new(None));
+            self.actor_stats.push(stats.clone());
+            let stop = self.stop.clone();
+            let handle = std::thread::spawn(move || {
+                handle_message::<R::Item>(id, reader, sender, stats, stop);
+            });
+            self.threads.push(handle);
+        }
+
+        // Thread for sending [SyncModel::ModelInfo]
+        {
+            let model_info_receiver = self.model_info_receiver.clone();
+            let stop = self.stop.clone();
+            let handle = std::thread::spawn(move || {
+                run_model_info_loop::<A::ModelInfo>(model_info_receiver, streams, stop);
+            });
+            self.threads.push(handle);
+            info!("Starts thread for sending model info");
+        }
+
+        Ok(())
+    }
+
+    /// Waits until all actors finish.
+    ///
+    /// Stats of actors that have not been received, e.g., due to lost connections,
+    /// are omitted.
+    pub fn join(mut self) -> Vec<ActorStat> {
+        for h in self.threads.drain(..) {
+            h.join().unwrap();
+        }
+        for mut child in self.children.drain(..) {
+            if let Err(e) = child.wait() {
+                warn!("Failed to wait for actor process {}: {}", child.id(), e);
+            }
+        }
+
+        self.actor_stats
+            .iter()
+            .enumerate()
+            .filter_map(|(id, e)| {
+                let stat = e.lock().unwrap_or_else(PoisonError::into_inner).clone();
+                if stat.is_none() {
+                    warn!("Stats of actor {} were not received", id);
+                }
+                stat
+            })
+            .collect::<Vec<_>>()
+    }
+
+    /// Stops actors.
+    pub fn stop(&self) {
+        let mut stop = self.stop.lock().unwrap_or_else(PoisonError::into_inner);
+        *stop = true;
+    }
+
+    /// Stops and joins actors.
+    pub fn stop_and_join(self) -> Vec<ActorStat> {
+        self.stop();
+        self.join()
+    }
+}
+
+impl<A, E, R, P> Drop for ProcessActorManager<A, E, R, P>
+where
+    A: Agent<E, R> + Configurable + SyncModel,
+    E: Env,
+    P: StepProcessor<E>,
+    R: ExperienceBufferBase<Item = P::Output> + ReplayBufferBase,
+{
+    fn drop(&mut self) {
+        kill_children(&mut self.children);
+    }
+}
+
+/// Kills processes that are still running and waits for them.
+fn kill_children(children: &mut Vec<Child>) {
+    for mut child in children.drain(..) {
+        if let Ok(None) = child.try_wait() {
+            warn!("Kills actor process {}", child.id());
+            if let Err(e) = child.kill() {
+                warn!("Failed to kill actor process {}: {}", child.id(), e);
+            }
+        }
+        if let Err(e) = child.wait() {
+            warn!("Failed to wait for actor process {}: {}", child.id(), e);
+        }
+    }
+}
+
+/// Reads [`ActorHello`] from a connection, failing if it is not received in time.
+fn read_hello(stream: &mut Stream) -> Result<usize> {
+    stream.set_read_timeout(Some(HELLO_TIMEOUT))?;
+    let ActorHello { id } = read_message(stream)?;
+    stream.set_read_timeout(None)?;
+    Ok(id)
+}
+
+/// Loop waiting [PushedItemMessage]s from an actor until it sends [`ActorStat`].
+///
+/// Messages are forwarded to the trainer, blocking while its channel is full. Then the
+/// socket is not read and the backpressure policy is applied in the actor process.
+fn handle_message<T: DeserializeOwned>(
+    id: usize,
+    mut reader: Stream,
+    sender: Sender<PushedItemMessage<T>>,
+    stats: Arc<Mutex<Option<ActorStat>>>,
+    stop: Arc<Mutex<bool>>,
+) {
+    let mut n_discarded = 0;
+    loop {
+        match read_message(&mut reader) {
+            Ok(ActorMessage::PushedItems(msg)) => {
+                let n_items = msg.pushed_items.len();
+                if !forward_pushed_items(&sender, msg, &stop) {
+                    n_discarded += n_items;
+                }
+            }
+            Ok(ActorMessage::Stat(stat)) => {
+                *stats.lock().unwrap_or_else(PoisonError::into_inner) = Some(stat);
+                break;
+            }
+            Err(e) => {
+                warn!("Lost connection to actor {}: {}", id, e);
+                break;
+            }
+        }
+    }
+    if n_discarded > 0 {
+        info!(
+            "Discarded {} samples of actor {} after the trainer finished",
+            n_discarded, id
+        );
+    }
+    info!("Stopped thread for messages from actor {}", id);
+}
+
+/// Forwards model info to actors until stopped, then asks the actors to stop.
+fn run_model_info_loop<M: Serialize>(
+    model_info_receiver: Receiver<(usize, M)>,
+    mut writers: Vec<Stream>,
+    stop: Arc<Mutex<bool>>,
+) {
+    loop {
+        match model_info_receiver.recv_timeout(Duration::from_millis(100)) {
+            Ok((n_opt_steps, model_info)) => {
+                // Serializes the model info once for all actors
+                match encode_message(&ManagerMessage::ModelInfo(n_opt_steps, model_info)) {
+                    Ok(bytes) => writers.iter_mut().enumerate().for_each(|(id, w)| {
+                        if let Err(e) = write_encoded(w, &bytes) {
+                            warn!("Failed to send model info to actor {}: {}", id, e);
+                        }
+                    }),
+                    Err(e) => warn!("Failed to serialize model info: {}", e),
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if *stop.lock().unwrap_or_else(PoisonError::into_inner) {
+            break;
+        }
+    }
+
+    for (id, w) in writers.iter_mut().enumerate() {
+        if let Err(e) = write_message(w, &ManagerMessage::<M>::Stop) {
+            warn!("Failed to stop actor {}: {}", id, e);
+            w.shutdown();
+        }
+    }
+    info!("Stopped model info thread");
+}
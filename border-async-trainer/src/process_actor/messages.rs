@@ -0,0 +1,46 @@
+//! Messages exchanged between [`ProcessActorManager`](super::ProcessActorManager) and
+//! actor processes.
+use crate::{ActorStat, BackpressurePolicy, PushedItemMessage};
+use serde::{Deserialize, Serialize};
+
+/// Sent from an actor process right after connecting to the manager.
+#[derive(Debug, Deserialize, Serialize)]
+pub(super) struct ActorHello {
+    /// ID of the actor.
+    pub id: usize,
+}
+
+/// Sent from the manager in reply to [`ActorHello`], with which the actor is built.
+#[derive(Deserialize, Serialize)]
+pub(super) struct ActorInit<AC, EC, PC> {
+    pub agent_config: AC,
+    pub env_config: EC,
+    pub step_proc_config: PC,
+    pub n_buffer: usize,
+    pub backpressure: BackpressurePolicy,
+    pub channel_capacity: usize,
+    pub env_seed: i64,
+
+    /// Exploration rate of the actor as in Ape-X.
+    pub epsilon: Option<f64>,
+}
+
+/// Sent from the manager to actor processes after [`ActorInit`].
+#[derive(Deserialize, Serialize)]
+pub(super) enum ManagerMessage<M> {
+    /// The number of optimization steps and [`SyncModel::ModelInfo`](crate::SyncModel::ModelInfo).
+    ModelInfo(usize, M),
+
+    /// Stops the sampling loop.
+    Stop,
+}
+
+/// Sent from an actor process to the manager.
+#[derive(Deserialize, Serialize)]
+pub(super) enum ActorMessage<T> {
+    /// Samples to be pushed to the replay buffer.
+    PushedItems(PushedItemMessage<T>),
+
+    /// Stats of the actor, sent after the sampling loop finishes.
+    Stat(ActorStat),
+}
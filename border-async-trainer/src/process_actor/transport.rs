@@ -0,0 +1,279 @@
+//! Sockets and framing of messages between processes.
+use anyhow::{anyhow, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt,
+    io::{Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+/// Maximum size of a message in bytes (256 MiB), to detect broken streams.
+const MAX_MESSAGE_BYTES: u64 = 1 << 28;
+
+/// Address at which [`ProcessActorManager`](super::ProcessActorManager) waits for actors.
+///
+/// It is written as `tcp://<host>:<port>` or `unix://<path>`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum ActorAddress {
+    /// TCP socket address, e.g., `127.0.0.1:5000`.
+    ///
+    /// If the port is 0, a free port is assigned when the manager binds the address.
+    Tcp(String),
+
+    /// Path of a Unix domain socket.
+    Unix(PathBuf),
+}
+
+impl Default for ActorAddress {
+    fn default() -> Self {
+        Self::Tcp("127.0.0.1:0".to_string())
+    }
+}
+
+impl fmt::Display for ActorAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "tcp://{}", addr),
+            Self::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for ActorAddress {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(addr) = s.strip_prefix("tcp://") {
+            Ok(Self::Tcp(addr.to_string()))
+        } else if let Some(path) = s.strip_prefix("unix://") {
+            Ok(Self::Unix(PathBuf::from(path)))
+        } else {
+            Err(anyhow!("Invalid actor address: {}", s))
+        }
+    }
+}
+
+/// Listening socket of the manager.
+pub(super) enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    /// Binds the address.
+    pub fn bind(address: &ActorAddress) -> Result<Self> {
+        match address {
+            ActorAddress::Tcp(addr) => Ok(Self::Tcp(
+                TcpListener::bind(addr).with_context(|| format!("Failed to bind {}", address))?,
+            )),
+            #[cfg(unix)]
+            ActorAddress::Unix(path) => {
+                // Removes the socket file left by a previous run
+                if path.exists() {
+                    std::fs::remove_file(path)?;
+                }
+                let listener = UnixListener::bind(path)
+                    .with_context(|| format!("Failed to bind {}", address))?;
+                Ok(Self::Unix(listener, path.clone()))
+            }
+            #[cfg(not(unix))]
+            ActorAddress::Unix(_) => Err(anyhow!("Unix sockets are not supported")),
+        }
+    }
+
+    /// Returns the bound address, to which actors connect.
+    pub fn local_address(&self) -> Result<ActorAddress> {
+        match self {
+            Self::Tcp(listener) => Ok(ActorAddress::Tcp(listener.local_addr()?.to_string())),
+            #[cfg(unix)]
+            Self::Unix(_, path) => Ok(ActorAddress::Unix(path.clone())),
+        }
+    }
+
+    /// Makes [`Listener::accept()`] return `None` if no actor is waiting for a connection.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        match self {
+            Self::Tcp(listener) => listener.set_nonblocking(nonblocking)?,
+            #[cfg(unix)]
+            Self::Unix(listener, _) => listener.set_nonblocking(nonblocking)?,
+        }
+        Ok(())
+    }
+
+    /// Accepts a connection from an actor.
+    ///
+    /// The accepted connection is blocking, regardless of the listener.
+    pub fn accept(&self) -> Result<Option<Stream>> {
+        let stream = match self {
+            Self::Tcp(listener) => listener.accept().map(|(stream, _)| Stream::Tcp(stream)),
+            #[cfg(unix)]
+            Self::Unix(listener, _) => listener.accept().map(|(stream, _)| Stream::Unix(stream)),
+        };
+        match stream {
+            Ok(stream) => {
+                stream.set_blocking()?;
+                Ok(Some(stream))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Self::Unix(_, path) = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Connection between the manager and an actor.
+pub(super) enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    /// Connects to the manager, retrying until `timeout` as the manager may not be
+    /// listening yet.
+    pub fn connect_with_retry(address: &ActorAddress, timeout: Duration) -> Result<Self> {
+        let start = Instant::now();
+        loop {
+            match Self::connect(address) {
+                Ok(stream) => return Ok(stream),
+                Err(e) if start.elapsed() >= timeout => return Err(e),
+                Err(_) => std::thread::sleep(Duration::from_millis(100)),
+            }
+        }
+    }
+
+    /// Connects to the manager.
+    pub fn connect(address: &ActorAddress) -> Result<Self> {
+        match address {
+            ActorAddress::Tcp(addr) => {
+                let stream = TcpStream::connect(addr)
+                    .with_context(|| format!("Failed to connect {}", address))?;
+                stream.set_nodelay(true)?;
+                Ok(Self::Tcp(stream))
+            }
+            #[cfg(unix)]
+            ActorAddress::Unix(path) => Ok(Self::Unix(
+                UnixStream::connect(path)
+                    .with_context(|| format!("Failed to connect {}", address))?,
+            )),
+            #[cfg(not(unix))]
+            ActorAddress::Unix(_) => Err(anyhow!("Unix sockets are not supported")),
+        }
+    }
+
+    fn set_blocking(&self) -> Result<()> {
+        match self {
+            Self::Tcp(stream) => {
+                stream.set_nonblocking(false)?;
+                stream.set_nodelay(true)?;
+            }
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.set_nonblocking(false)?,
+        }
+        Ok(())
+    }
+
+    /// Sets the timeout of reading, where `None` means reading blocks indefinitely.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.set_read_timeout(timeout)?,
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.set_read_timeout(timeout)?,
+        }
+        Ok(())
+    }
+
+    /// Creates a handle of the same connection, used for reading and writing on different threads.
+    pub fn try_clone(&self) -> Result<Self> {
+        match self {
+            Self::Tcp(stream) => Ok(Self::Tcp(stream.try_clone()?)),
+            #[cfg(unix)]
+            Self::Unix(stream) => Ok(Self::Unix(stream.try_clone()?)),
+        }
+    }
+
+    /// Shuts down the connection, which unblocks threads reading it.
+    pub fn shutdown(&self) {
+        let _ = match self {
+            Self::Tcp(stream) => stream.shutdown(Shutdown::Both),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.shutdown(Shutdown::Both),
+        };
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Serializes a message with bincode.
+pub(super) fn encode_message<T: Serialize>(msg: &T) -> Result<Vec<u8>> {
+    Ok(bincode::serialize(msg)?)
+}
+
+/// Writes a message serialized with bincode, prefixed by its length in bytes.
+pub(super) fn write_message<T: Serialize>(writer: &mut impl Write, msg: &T) -> Result<()> {
+    write_encoded(writer, &encode_message(msg)?)
+}
+
+/// Writes a message encoded with [`encode_message`].
+///
+/// This is used to send the same message to multiple actors with a single serialization.
+pub(super) fn write_encoded(writer: &mut impl Write, bytes: &[u8]) -> Result<()> {
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(bytes)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads a message written with [`write_message`].
+pub(super) fn read_message<T: DeserializeOwned>(reader: &mut impl Read) -> Result<T> {
+    let mut len = [0u8; 8];
+    reader.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    if len > MAX_MESSAGE_BYTES {
+        return Err(anyhow!("Message of {} bytes is too large", len));
+    }
+    let mut bytes = vec![0u8; len as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(bincode::deserialize(&bytes)?)
+}
@@ -1,9 +1,24 @@
-use crate::PushedItemMessage;
+use crate::{BorderAsyncTrainerError, PushedItemMessage};
 use anyhow::Result;
 use border_core::{ExperienceBufferBase, ReplayBufferBase};
-use crossbeam_channel::Sender;
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 
+/// Behavior of [`ReplayBufferProxy`] when the channel to the trainer is full.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Blocks the actor until the channel has room.
+    #[default]
+    Block,
+
+    /// Discards the oldest message in the channel to make room for the new one.
+    DropOldest,
+
+    /// Discards the new message.
+    DropNewest,
+}
+
 /// Configuration of [`ReplayBufferProxy`].
 #[derive(Clone, Debug)]
 pub struct ReplayBufferProxyConfig {
@@ -11,6 +26,9 @@ pub struct ReplayBufferProxyConfig {
     ///
     /// A sample is a `R::Item` for [`ReplayBufferProxy`]`<R>`.
     pub n_buffer: usize,
+
+    /// Behavior when the channel to the trainer is full.
+    pub backpressure: BackpressurePolicy,
 }
 
 /// A wrapper of replay buffer for asynchronous trainer.
@@ -20,12 +38,29 @@ pub struct ReplayBufferProxy<R: ExperienceBufferBase> {
     /// Sender of [PushedItemMessage].
     sender: Sender<PushedItemMessage<R::Item>>,
 
+    /// Receiver of the channel, used to discard the oldest message with
+    /// [`BackpressurePolicy::DropOldest`].
+    receiver: Option<Receiver<PushedItemMessage<R::Item>>>,
+
+    /// Behavior when the channel is full.
+    backpressure: BackpressurePolicy,
+
     /// Number of samples buffered until sent to the trainer.
     n_buffer: usize,
 
     /// Buffer of `R::Item`s.
     buffer: Vec<R::Item>,
 
+    /// If `true`, buffered items are sent with their priorities by
+    /// [`ReplayBufferProxy::send_with_priorities()`], not when pushed.
+    with_priorities: bool,
+
+    /// The number of optimization steps of the model with which samples are taken.
+    n_opt_steps: usize,
+
+    /// The number of samples discarded due to backpressure.
+    n_dropped: usize,
+
     phantom: PhantomData<R>,
 }
 
@@ -39,34 +74,99 @@ impl<R: ExperienceBufferBase> ReplayBufferProxy<R> {
         Self {
             id,
             sender,
+            receiver: None,
+            backpressure: config.backpressure,
             n_buffer,
             buffer: Vec::with_capacity(n_buffer),
+            with_priorities: false,
+            n_opt_steps: 0,
+            n_dropped: 0,
             phantom: PhantomData,
         }
     }
-}
 
-impl<R: ExperienceBufferBase> ExperienceBufferBase for ReplayBufferProxy<R> {
-    type Item = R::Item;
+    /// Sets the receiver of the channel, with which the oldest message is discarded
+    /// under [`BackpressurePolicy::DropOldest`].
+    ///
+    /// Without the receiver, the new message is discarded instead.
+    pub(crate) fn with_receiver(mut self, receiver: Receiver<PushedItemMessage<R::Item>>) -> Self {
+        self.receiver = Some(receiver);
+        self
+    }
 
-    fn push(&mut self, tr: Self::Item) -> Result<()> {
-        self.buffer.push(tr);
-        if self.buffer.len() == self.n_buffer {
-            let mut buffer = Vec::with_capacity(self.n_buffer);
-            std::mem::swap(&mut self.buffer, &mut buffer);
+    /// Sets the number of optimization steps of the model with which samples are taken.
+    pub(crate) fn set_n_opt_steps(&mut self, n_opt_steps: usize) {
+        self.n_opt_steps = n_opt_steps;
+    }
+
+    /// Makes the proxy send items with their priorities.
+    pub(crate) fn with_priorities(mut self) -> Self {
+        self.with_priorities = true;
+        self
+    }
+
+    /// Sends the buffered items with their priorities computed by `f`, if the buffer is full.
+    pub(crate) fn send_with_priorities(
+        &mut self,
+        f: impl FnMut(&R::Item) -> Vec<f32>,
+    ) -> Result<()> {
+        if self.buffer.len() < self.n_buffer {
+            return Ok(());
+        }
+        let priorities = self.buffer.iter().map(f).collect();
+        self.send(Some(priorities))
+    }
+
+    fn send(&mut self, priorities: Option<Vec<Vec<f32>>>) -> Result<()> {
+        let mut buffer = Vec::with_capacity(self.n_buffer);
+        std::mem::swap(&mut self.buffer, &mut buffer);
 
-            let msg = PushedItemMessage {
-                id: self.id,
-                pushed_items: buffer,
+        let mut msg = PushedItemMessage {
+            id: self.id,
+            pushed_items: buffer,
+            priorities,
+            n_opt_steps: self.n_opt_steps,
+            queue_depth: self.sender.len(),
+            n_dropped: self.n_dropped,
+        };
+
+        if self.backpressure == BackpressurePolicy::Block {
+            return match self.sender.send(msg) {
+                Ok(()) => Ok(()),
+                Err(_e) => Err(BorderAsyncTrainerError::SendMsgForPush)?,
             };
+        }
 
-            match self.sender.try_send(msg) {
-                Ok(()) => {}
-                Err(_e) => {
-                    return Err(crate::BorderAsyncTrainerError::SendMsgForPush)?;
+        loop {
+            msg = match self.sender.try_send(msg) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Full(msg)) => msg,
+                Err(TrySendError::Disconnected(_)) => Err(BorderAsyncTrainerError::SendMsgForPush)?,
+            };
+            match (self.backpressure, &self.receiver) {
+                (BackpressurePolicy::DropOldest, Some(receiver)) => {
+                    // The channel might have been drained by the trainer in the meantime
+                    if let Ok(oldest) = receiver.try_recv() {
+                        self.n_dropped += oldest.pushed_items.len();
+                    }
+                }
+                _ => {
+                    self.n_dropped += msg.pushed_items.len();
+                    return Ok(());
                 }
             }
         }
+    }
+}
+
+impl<R: ExperienceBufferBase> ExperienceBufferBase for ReplayBufferProxy<R> {
+    type Item = R::Item;
+
+    fn push(&mut self, tr: Self::Item) -> Result<()> {
+        self.buffer.push(tr);
+        if self.buffer.len() == self.n_buffer && !self.with_priorities {
+            self.send(None)?;
+        }
 
         Ok(())
     }
@@ -92,3 +192,67 @@ impl<R: ExperienceBufferBase + ReplayBufferBase> ReplayBufferBase for ReplayBuff
         unimplemented!();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{TestActBatch, TestObsBatch};
+    use border_core::generic_replay_buffer::{GenericTransitionBatch, SimpleReplayBuffer};
+    use crossbeam_channel::bounded;
+
+    type ReplayBuffer = SimpleReplayBuffer<TestObsBatch, TestActBatch>;
+
+    fn proxy(
+        backpressure: BackpressurePolicy,
+    ) -> (
+        ReplayBufferProxy<ReplayBuffer>,
+        Receiver<PushedItemMessage<GenericTransitionBatch<TestObsBatch, TestActBatch>>>,
+    ) {
+        let config = ReplayBufferProxyConfig {
+            n_buffer: 1,
+            backpressure,
+        };
+        let (s, r) = bounded(1);
+        let proxy = ReplayBufferProxy::build_with_sender(0, &config, s).with_receiver(r.clone());
+        (proxy, r)
+    }
+
+    #[test]
+    fn test_backpressure() -> Result<()> {
+        // The new message is discarded
+        let (mut buffer, r) = proxy(BackpressurePolicy::DropNewest);
+        buffer.set_n_opt_steps(1);
+        buffer.push(GenericTransitionBatch::with_capacity(1))?;
+        buffer.set_n_opt_steps(2);
+        buffer.push(GenericTransitionBatch::with_capacity(1))?;
+        let msg = r.try_recv()?;
+        assert_eq!((msg.n_opt_steps, msg.n_dropped), (1, 0));
+        buffer.push(GenericTransitionBatch::with_capacity(1))?;
+        let msg = r.try_recv()?;
+        assert_eq!((msg.n_opt_steps, msg.n_dropped), (2, 1));
+
+        // The oldest message is discarded
+        let (mut buffer, r) = proxy(BackpressurePolicy::DropOldest);
+        buffer.set_n_opt_steps(1);
+        buffer.push(GenericTransitionBatch::with_capacity(1))?;
+        buffer.set_n_opt_steps(2);
+        buffer.push(GenericTransitionBatch::with_capacity(1))?;
+        let msg = r.try_recv()?;
+        assert_eq!((msg.n_opt_steps, msg.queue_depth), (2, 1));
+        assert!(r.is_empty());
+
+        // The actor waits until the message is received
+        let (mut buffer, r) = proxy(BackpressurePolicy::Block);
+        buffer.push(GenericTransitionBatch::with_capacity(1))?;
+        let handle = std::thread::spawn(move || {
+            buffer
+                .push(GenericTransitionBatch::with_capacity(1))
+                .unwrap();
+        });
+        assert_eq!(r.recv()?.queue_depth, 0);
+        handle.join().unwrap();
+        assert!(r.recv().is_ok());
+
+        Ok(())
+    }
+}
@@ -1,13 +1,16 @@
 //! Utility function.
 use crate::{
-    actor_stats_fmt, ActorManager, ActorManagerConfig, AsyncTrainer, AsyncTrainerConfig, SyncModel,
+    actor_stats_fmt, ActorManager, ActorManagerConfig, ApeXAgent, AsyncTrainer, AsyncTrainerConfig,
+    ProcessActorManager, ProcessActorManagerConfig, SyncModel,
 };
+use anyhow::Result;
 use border_core::{
     record::Recorder, Agent, Configurable, Env, Evaluator, ExperienceBufferBase, ReplayBufferBase,
     StepProcessor,
 };
-use crossbeam_channel::unbounded;
+use crossbeam_channel::{bounded, unbounded};
 use log::info;
+use serde::{de::DeserializeOwned, Serialize};
 use std::sync::{Arc, Mutex};
 
 /// Runs asynchronous training.
@@ -39,7 +42,91 @@ pub fn train_async<A, E, R, S>(
     async_trainer_config: &AsyncTrainerConfig,
     recorder: &mut Box<dyn Recorder<E, R>>,
     evaluator: &mut impl Evaluator<E>,
-) where
+) -> Result<()>
+where
+    A: Agent<E, R> + Configurable + SyncModel + 'static,
+    E: Env,
+    R: ExperienceBufferBase<Item = S::Output> + Send + 'static + ReplayBufferBase,
+    S: StepProcessor<E>,
+    A::Config: Send + 'static,
+    E::Config: Send + 'static,
+    S::Config: Send + 'static,
+    R::Item: Send + 'static,
+    A::ModelInfo: Send + 'static,
+{
+    run_train_async::<A, E, R, S>(
+        agent_config,
+        agent_configs,
+        env_config_train,
+        env_config_eval,
+        step_proc_config,
+        replay_buffer_config,
+        actor_man_config,
+        async_trainer_config,
+        recorder,
+        evaluator,
+        |actors| actors,
+    )
+}
+
+/// Runs asynchronous training with distributed prioritized experience replay as in Ape-X.
+///
+/// This function is the same as [`train_async()`], except that actors compute the
+/// priorities of samples with [`ApeXAgent::compute_priorities()`], enabled with
+/// [`ActorManager::ape_x()`]. The exploration rates of actors are given by
+/// [`ActorManagerConfig::ape_x_epsilon`].
+pub fn train_async_ape_x<A, E, R, S>(
+    agent_config: &A::Config,
+    agent_configs: &Vec<A::Config>,
+    env_config_train: &E::Config,
+    env_config_eval: &E::Config,
+    step_proc_config: &S::Config,
+    replay_buffer_config: &R::Config,
+    actor_man_config: &ActorManagerConfig,
+    async_trainer_config: &AsyncTrainerConfig,
+    recorder: &mut Box<dyn Recorder<E, R>>,
+    evaluator: &mut impl Evaluator<E>,
+) -> Result<()>
+where
+    A: Agent<E, R> + Configurable + SyncModel + ApeXAgent<R::Item> + 'static,
+    E: Env,
+    R: ExperienceBufferBase<Item = S::Output> + Send + 'static + ReplayBufferBase,
+    S: StepProcessor<E>,
+    A::Config: Send + 'static,
+    E::Config: Send + 'static,
+    S::Config: Send + 'static,
+    R::Item: Send + 'static,
+    A::ModelInfo: Send + 'static,
+{
+    run_train_async::<A, E, R, S>(
+        agent_config,
+        agent_configs,
+        env_config_train,
+        env_config_eval,
+        step_proc_config,
+        replay_buffer_config,
+        actor_man_config,
+        async_trainer_config,
+        recorder,
+        evaluator,
+        |actors| actors.ape_x(),
+    )
+}
+
+fn run_train_async<A, E, R, S>(
+    agent_config: &A::Config,
+    agent_configs: &Vec<A::Config>,
+    env_config_train: &E::Config,
+    env_config_eval: &E::Config,
+    step_proc_config: &S::Config,
+    replay_buffer_config: &R::Config,
+    actor_man_config: &ActorManagerConfig,
+    async_trainer_config: &AsyncTrainerConfig,
+    recorder: &mut Box<dyn Recorder<E, R>>,
+    evaluator: &mut impl Evaluator<E>,
+    setup_actors: impl FnOnce(ActorManager<A, E, R, S>) -> ActorManager<A, E, R, S>,
+) -> Result<()>
+where
     A: Agent<E, R> + Configurable + SyncModel + 'static,
     E: Env,
     R: ExperienceBufferBase<Item = S::Output> + Send + 'static + ReplayBufferBase,
@@ -53,15 +140,16 @@ pub fn train_async<A, E, R, S>(
     // Shared flag to stop actor threads
     let stop = Arc::new(Mutex::new(false));
 
-    // Creates channels
-    let (item_s, item_r) = unbounded(); // items pushed to replay buffer
+    // Creates channels. The channel of items pushed to the replay buffer is bounded, so that
+    // the backpressure policy of actors is applied when the trainer is slower than actors.
+    let (item_s, item_r) = bounded(actor_man_config.channel_capacity);
     let (model_s, model_r) = unbounded(); // model_info
 
     // guard for initialization of envs in multiple threads
     let guard_init_env = Arc::new(Mutex::new(true));
 
     // Actor manager and async trainer
-    let mut actors = ActorManager::<A, E, R, S>::build(
+    let mut actors = setup_actors(ActorManager::<A, E, R, S>::build(
         actor_man_config,
         agent_configs,
         env_config_train,
@@ -69,7 +157,7 @@ pub fn train_async<A, E, R, S>(
         item_s,
         model_r,
         stop.clone(),
-    );
+    ));
     let mut trainer = AsyncTrainer::<A, E, R>::build(
         async_trainer_config,
         agent_config,
@@ -82,11 +170,95 @@ pub fn train_async<A, E, R, S>(
 
     // Starts sampling and training
     actors.run(guard_init_env.clone());
-    let stats = trainer.train(recorder, evaluator, guard_init_env);
+    let result = trainer.train(recorder, evaluator, guard_init_env);
+
+    // Disconnects the channel of model info, so that actors stop even if training failed
+    drop(trainer);
+    let actor_stats = actors.stop_and_join();
+
+    let stats = result?;
     info!("Stats of async trainer");
     info!("{}", stats.fmt());
+    info!("Stats of generated samples in actors");
+    info!("{}", actor_stats_fmt(&actor_stats));
+
+    Ok(())
+}
+
+/// Runs asynchronous training with actors in separate processes.
+///
+/// This function is the same as [`train_async()`], except that [`ProcessActorManager`] runs
+/// actors in separate processes. When actor processes are spawned by the manager, the
+/// executable must call [`run_process_actor()`](crate::run_process_actor) if it is
+/// run as an actor process (see [`process_actor_env()`](crate::process_actor_env)).
+///
+/// * `actor_man_config` - Configuration of [`ProcessActorManager`].
+///
+/// See [`train_async()`] for the other arguments.
+pub fn train_async_with_processes<A, E, R, S>(
+    agent_config: &A::Config,
+    agent_configs: &Vec<A::Config>,
+    env_config_train: &E::Config,
+    env_config_eval: &E::Config,
+    step_proc_config: &S::Config,
+    replay_buffer_config: &R::Config,
+    actor_man_config: &ProcessActorManagerConfig,
+    async_trainer_config: &AsyncTrainerConfig,
+    recorder: &mut Box<dyn Recorder<E, R>>,
+    evaluator: &mut impl Evaluator<E>,
+) -> Result<()>
+where
+    A: Agent<E, R> + Configurable + SyncModel + 'static,
+    E: Env,
+    R: ExperienceBufferBase<Item = S::Output> + Send + 'static + ReplayBufferBase,
+    S: StepProcessor<E>,
+    A::Config: Serialize,
+    E::Config: Serialize,
+    S::Config: Serialize,
+    R::Item: DeserializeOwned + Send + 'static,
+    A::ModelInfo: Serialize + Send + 'static,
+{
+    // Shared flag to stop actors
+    let stop = Arc::new(Mutex::new(false));
 
-    let stats = actors.stop_and_join();
+    // Creates channels. The channel of items pushed to the replay buffer is bounded, so that
+    // the backpressure policy of actors is applied when the trainer is slower than actors.
+    let (item_s, item_r) = bounded(actor_man_config.channel_capacity);
+    let (model_s, model_r) = unbounded(); // model_info
+
+    // Environments of actors are initialized in their own processes
+    let guard_init_env = Arc::new(Mutex::new(true));
+
+    // Actor manager and async trainer
+    let mut actors = ProcessActorManager::<A, E, R, S>::build(
+        actor_man_config,
+        agent_configs,
+        env_config_train,
+        step_proc_config,
+        item_s,
+        model_r,
+        stop.clone(),
+    );
+    let mut trainer = AsyncTrainer::<A, E, R>::build(
+        async_trainer_config,
+        agent_config,
+        env_config_eval,
+        replay_buffer_config,
+        item_r,
+        model_s,
+        stop.clone(),
+    );
+
+    // Starts sampling and training
+    actors.run()?;
+    let result = trainer.train(recorder, evaluator, guard_init_env);
+    let actor_stats = actors.stop_and_join();
+
+    let stats = result?;
+    info!("Stats of async trainer");
+    info!("{}", stats.fmt());
     info!("Stats of generated samples in actors");
-    info!("{}", actor_stats_fmt(&stats));
+    info!("{}", actor_stats_fmt(&actor_stats));
+
+    Ok(())
 }
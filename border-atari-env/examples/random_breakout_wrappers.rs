@@ -0,0 +1,47 @@
+use anyhow::Result;
+use border_atari_env::{
+    BorderAtariAct, BorderAtariActRawFilter, BorderAtariEnv, BorderAtariEnvConfig, BorderAtariObs,
+    BorderAtariObsRawFilter,
+};
+use border_core::{
+    wrappers::{
+        EnvWrapper as _, EpisodicLife, EpisodicLifeConfig, FrameStack, FrameStackConfig,
+        LivesInfo as _, WrapperConfig,
+    },
+    Env as _,
+};
+
+type Obs = BorderAtariObs;
+type Act = BorderAtariAct;
+type ObsFilter = BorderAtariObsRawFilter<Obs>;
+type ActFilter = BorderAtariActRawFilter<Act>;
+type EnvConfig = BorderAtariEnvConfig<Obs, Act, ObsFilter, ActFilter>;
+type Env = EpisodicLife<FrameStack<BorderAtariEnv<Obs, Act, ObsFilter, ActFilter>>>;
+
+fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    fastrand::seed(42);
+
+    // The environment gives single frames, which are stacked with FrameStack
+    let env_config = EnvConfig::default().name("breakout").n_stack(1);
+    let env_config = WrapperConfig::new(env_config, FrameStackConfig::new(4));
+    let env_config = WrapperConfig::new(env_config, EpisodicLifeConfig::default());
+    let mut env = Env::build(&env_config, 42)?;
+    let n_acts = env.inner().inner().get_num_actions_atari() as u8;
+
+    let obs = env.reset(None)?;
+    assert_eq!(obs.n_frames, 4);
+
+    let mut n_episodes = 0;
+    while n_episodes < 5 {
+        let (step, _) = env.step_with_reset(&fastrand::u8(..n_acts).into());
+        assert_eq!(step.obs.n_frames, 4);
+        if step.is_done() {
+            // An episode ends when a life is lost
+            println!("lives: {}", step.info.lives());
+            n_episodes += 1;
+        }
+    }
+
+    Ok(())
+}
@@ -1,6 +1,6 @@
 //! Action for [BorderAtariEnv](crate::BorderAtariEnv)
 use anyhow::Result;
-use border_core::{record::Record, Act};
+use border_core::{record::Record, vec_env::VecEnvAct, Act};
 use serde::{Deserialize, Serialize};
 use std::{default::Default, marker::PhantomData};
 
@@ -8,25 +8,34 @@ use std::{default::Default, marker::PhantomData};
 /// Action for [`BorderAtariEnv`](crate::BorderAtariEnv).
 ///
 /// This action is a discrete action and denotes pushing a button.
+/// It holds one action for each sub-environment of a vectorized environment,
+/// and a single action otherwise.
 pub struct BorderAtariAct {
-    pub act: u8,
+    pub acts: Vec<u8>,
 }
 
 impl BorderAtariAct {
     pub fn new(act: u8) -> Self {
-        Self { act }
+        Self { acts: vec![act] }
     }
 }
 
 impl Act for BorderAtariAct {
     fn len(&self) -> usize {
-        1
+        self.acts.len()
+    }
+}
+
+impl VecEnvAct for BorderAtariAct {
+    fn split(self, n: usize) -> Vec<Self> {
+        assert_eq!(self.acts.len(), n);
+        self.acts.into_iter().map(Self::new).collect()
     }
 }
 
 impl From<u8> for BorderAtariAct {
     fn from(act: u8) -> Self {
-        Self { act }
+        Self::new(act)
     }
 }
 
@@ -38,7 +47,8 @@ pub mod candle {
 
     impl From<BorderAtariAct> for Tensor {
         fn from(act: BorderAtariAct) -> Tensor {
-            Tensor::from_vec(vec![act.act as u8], &[1, 1], &Cpu).unwrap()
+            let n = act.acts.len();
+            Tensor::from_vec(act.acts, &[n, 1], &Cpu).unwrap()
         }
     }
 
@@ -50,9 +60,12 @@ pub mod candle {
     }
 
     impl From<Tensor> for BorderAtariAct {
-        /// `t` must have single item.
+        /// `t` must have one item for each sub-environment.
         fn from(t: Tensor) -> Self {
-            (t.to_vec1::<i64>().unwrap()[0] as u8).into()
+            let acts = t.flatten_all().unwrap().to_vec1::<i64>().unwrap();
+            Self {
+                acts: acts.into_iter().map(|a| a as u8).collect(),
+            }
         }
     }
 }
@@ -61,12 +74,13 @@ pub mod candle {
 pub mod tch_ {
     use super::*;
     use border_tch_agent::TensorBatch;
-    use std::convert::TryInto;
+    use std::convert::TryFrom;
     use tch::Tensor;
 
     impl From<BorderAtariAct> for Tensor {
         fn from(act: BorderAtariAct) -> Tensor {
-            Tensor::from_slice(&[act.act as i64]).unsqueeze(-1)
+            let acts = act.acts.iter().map(|&a| a as i64).collect::<Vec<_>>();
+            Tensor::from_slice(&acts).unsqueeze(-1)
         }
     }
 
@@ -78,9 +92,12 @@ pub mod tch_ {
     }
 
     impl From<Tensor> for BorderAtariAct {
-        /// `t` must have single item.
+        /// `t` must have one item for each sub-environment.
         fn from(t: Tensor) -> Self {
-            (TryInto::<i64>::try_into(t).unwrap() as u8).into()
+            let acts = Vec::<i64>::try_from(&t.flatten(0, -1)).unwrap();
+            Self {
+                acts: acts.into_iter().map(|a| a as u8).collect(),
+            }
         }
     }
 }
@@ -4,7 +4,7 @@ use super::BorderAtariAct;
 use super::{BorderAtariActFilter, BorderAtariObsFilter};
 use crate::atari_env::{AtariAction, AtariEnv, EmulatorConfig};
 use anyhow::Result;
-use border_core::{record::Record, Act, Env, Info, Obs, Step};
+use border_core::{record::Record, wrappers::LivesInfo, Act, Env, Info, Obs, Step};
 pub use config::BorderAtariEnvConfig;
 use image::{
     imageops::{/*grayscale,*/ resize, FilterType::Triangle},
@@ -17,10 +17,19 @@ use window::AtariWindow;
 #[cfg(feature = "atari-env-sys")]
 use winit::{event_loop::ControlFlow, platform::run_return::EventLoopExtRunReturn};
 
-/// Empty struct.
-pub struct NullInfo;
+/// Information of [`BorderAtariEnv`].
+pub struct BorderAtariInfo {
+    /// The number of remaining lives.
+    pub lives: usize,
+}
 
-impl Info for NullInfo {}
+impl Info for BorderAtariInfo {}
+
+impl LivesInfo for BorderAtariInfo {
+    fn lives(&self) -> usize {
+        self.lives
+    }
+}
 
 fn env(rom_dir: &str, name: &str) -> AtariEnv {
     AtariEnv::new(
@@ -40,6 +49,12 @@ fn env(rom_dir: &str, name: &str) -> AtariEnv {
 ///
 /// Preprocessing is the same in the link:
 /// <https://stable-baselines3.readthedocs.io/en/master/common/atari_wrappers.html#stable_baselines3.common.atari_wrappers.AtariWrapper>.
+///
+/// Episodes are not terminated when a life is lost. Wrap the environment with
+/// [`EpisodicLife`](border_core::wrappers::EpisodicLife), which uses the number of lives
+/// in [`BorderAtariInfo`], for episodic life. Frames can also be stacked with
+/// [`FrameStack`](border_core::wrappers::FrameStack) by setting `n_stack` of the
+/// configuration to 1.
 pub struct BorderAtariEnv<O, A, OF, AF>
 where
     O: Obs,
@@ -68,6 +83,9 @@ where
     // Buffer for stacking frames
     frames: Vec<u8>,
 
+    // The number of stacked frames
+    n_stack: usize,
+
     // Filters
     obs_filter: OF,
     act_filter: AF,
@@ -101,7 +119,7 @@ where
 
     fn episodic_life_env_step(&mut self, a: &BorderAtariAct) -> (Vec<u8>, f32, i8) {
         let actions = self.env.minimal_actions();
-        let ix = a.act;
+        let ix = a.acts[0];
         let reward = self.env.step(actions[ix as usize]) as f32;
 
         let is_terminated = match self.env.is_game_over() {
@@ -187,15 +205,9 @@ where
     }
 
     fn stack_frame(&mut self, obs: Vec<u8>) {
-        unsafe {
-            let src: *const u8 = &self.frames[0];
-            let dst: *mut u8 = &mut self.frames[1 * 84 * 84];
-            copy(src, dst, 3 * 84 * 84);
-
-            let src: *const u8 = &obs[0];
-            let dst: *mut u8 = &mut self.frames[0];
-            copy(src, dst, 1 * 84 * 84);
-        }
+        let n = self.n_stack;
+        self.frames.copy_within(0..(n - 1) * 84 * 84, 84 * 84);
+        self.frames[..84 * 84].copy_from_slice(&obs);
     }
 }
 
@@ -216,7 +228,8 @@ where
             obs_buffer: [vec![], vec![]],
             lives: 0,
             was_real_done: true,
-            frames: vec![0; 4 * 84 * 84],
+            frames: vec![0; config.n_stack * 84 * 84],
+            n_stack: config.n_stack,
             obs_filter: OF::build(&config.obs_filter_config).unwrap(),
             act_filter: AF::build(&config.act_filter_config).unwrap(),
             phantom: PhantomData,
@@ -234,7 +247,7 @@ where
     type Config = BorderAtariEnvConfig<O, A, OF, AF>;
     type Obs = O;
     type Act = A;
-    type Info = NullInfo;
+    type Info = BorderAtariInfo;
 
     fn build(config: &Self::Config, _seed: i64) -> Result<Self>
     where
@@ -247,7 +260,8 @@ where
             obs_buffer: [vec![], vec![]],
             lives: 0,
             was_real_done: true,
-            frames: vec![0; 4 * 84 * 84],
+            frames: vec![0; config.n_stack * 84 * 84],
+            n_stack: config.n_stack,
             obs_filter: OF::build(&config.obs_filter_config)?,
             act_filter: AF::build(&config.act_filter_config)?,
             phantom: PhantomData,
@@ -289,7 +303,7 @@ where
 
         unsafe {
             let src: *const u8 = &obs[0];
-            for i in 0..4 {
+            for i in 0..self.n_stack {
                 let dst: *mut u8 = &mut self.frames[i * 84 * 84];
                 copy(src, dst, 84 * 84);
             }
@@ -324,7 +338,7 @@ where
                 reward,
                 is_terminated,
                 is_truncated,
-                NullInfo,
+                BorderAtariInfo { lives: self.lives },
                 None,
             );
             let record = Record::empty();
@@ -22,6 +22,17 @@ where
     pub act_filter_config: AF::Config,
     pub train: bool,
     pub render: bool,
+
+    /// The number of frames stacked in an observation.
+    ///
+    /// Set it to 1 to stack frames with
+    /// [`FrameStack`](border_core::wrappers::FrameStack) instead.
+    #[serde(default = "default_n_stack")]
+    pub n_stack: usize,
+}
+
+fn default_n_stack() -> usize {
+    4
 }
 
 impl<O, A, OF, AF> Clone for BorderAtariEnvConfig<O, A, OF, AF>
@@ -39,6 +50,7 @@ where
             act_filter_config: self.act_filter_config.clone(),
             train: self.train,
             render: self.render,
+            n_stack: self.n_stack,
         }
     }
 }
@@ -64,6 +76,7 @@ where
             act_filter_config: Default::default(),
             train: true,
             render: false,
+            n_stack: default_n_stack(),
         }
     }
 }
@@ -91,4 +104,11 @@ where
         self.render = render;
         self
     }
+
+    /// Sets the number of frames stacked in an observation.
+    pub fn n_stack(mut self, n_stack: usize) -> Self {
+        assert!(n_stack > 0, "n_stack must be positive");
+        self.n_stack = n_stack;
+        self
+    }
 }
@@ -90,5 +90,5 @@ mod env;
 mod obs;
 pub mod util;
 pub use act::{BorderAtariAct, BorderAtariActFilter, BorderAtariActRawFilter};
-pub use env::{BorderAtariEnv, BorderAtariEnvConfig};
+pub use env::{BorderAtariEnv, BorderAtariEnvConfig, BorderAtariInfo};
 pub use obs::{BorderAtariObs, BorderAtariObsFilter, BorderAtariObsRawFilter};
@@ -9,30 +9,69 @@
 //! * Resize to 84 x 84
 //! * Grayscale
 //! * Clip reward to {-1, 0, 1} in training
-//! * Stacking four frames
+//! * Stacking four frames, or the number of frames given by `n_stack` of
+//!   [`BorderAtariEnvConfig`](super::BorderAtariEnvConfig)
 //! It does not apply pixel scaling from 255 to 1.0 for saving memory of the replay buffer.
 //! Instead, the scaling is applied in CNN model.
 use anyhow::Result;
-use border_core::{record::Record, Obs};
+use border_core::{record::Record, vec_env::VecEnvObs, wrappers::FrameStackObs, Obs};
 use serde::{Deserialize, Serialize};
 use std::{default::Default, marker::PhantomData};
 
+/// The number of pixels in a frame.
+const FRAME_SIZE: usize = 84 * 84;
+
 /// Observation of [`BorderAtariEnv`](super::BorderAtariEnv).
 #[derive(Debug, Clone)]
 pub struct BorderAtariObs {
-    /// Four frames of 84 * 84 pixels.
+    /// Stacked frames of 84 * 84 pixels, the latest frame first.
+    ///
+    /// For a vectorized environment, the frames of the sub-environments are concatenated.
     pub frames: Vec<u8>,
+
+    /// The number of stacked frames in the observation of an environment.
+    pub n_frames: usize,
 }
 
 impl From<Vec<u8>> for BorderAtariObs {
+    /// Creates the observation of a (non-vectorized) environment from stacked frames.
     fn from(frames: Vec<u8>) -> Self {
-        Self { frames }
+        let n_frames = frames.len() / FRAME_SIZE;
+        Self { frames, n_frames }
     }
 }
 
 impl Obs for BorderAtariObs {
     fn len(&self) -> usize {
-        1
+        self.frames.len() / (self.n_frames * FRAME_SIZE)
+    }
+}
+
+impl VecEnvObs for BorderAtariObs {
+    fn concat(obs: Vec<Self>) -> Self {
+        let n_frames = obs[0].n_frames;
+        assert!(obs.iter().all(|o| o.n_frames == n_frames));
+        Self {
+            frames: obs.into_iter().flat_map(|o| o.frames).collect(),
+            n_frames,
+        }
+    }
+}
+
+impl FrameStackObs for BorderAtariObs {
+    /// Stacks observations of a (non-vectorized) environment.
+    ///
+    /// As in the observation given by the environment, the frames of the latest
+    /// observation come first.
+    fn stack(frames: &[Self]) -> Self {
+        Self {
+            frames: frames
+                .iter()
+                .rev()
+                .flat_map(|o| o.frames.iter().copied())
+                .collect(),
+            n_frames: frames.iter().map(|o| o.n_frames).sum(),
+        }
     }
 }
 
@@ -44,9 +83,8 @@ pub mod tch_ {
 
     impl From<BorderAtariObs> for Tensor {
         fn from(obs: BorderAtariObs) -> Tensor {
-            // Assumes the batch size is 1, implying non-vectorized environment
             Tensor::from_slice(&obs.frames)
-                .reshape(&[1, 4, 1, 84, 84])
+                .reshape(&[-1, obs.n_frames as i64, 1, 84, 84])
                 .to_kind(tch::Kind::Float)
         }
     }
@@ -67,11 +105,10 @@ pub mod canadle {
 
     impl From<BorderAtariObs> for Tensor {
         fn from(obs: BorderAtariObs) -> Tensor {
-            let tmp = obs.frames;
-            // Assumes the batch size is 1, implying non-vectorized environment
-            Tensor::from_vec(tmp, &[1 * 4 * 1 * 84 * 84], &Cpu)
+            let (n, n_frames) = (obs.len(), obs.n_frames);
+            Tensor::from_vec(obs.frames, &[n * n_frames * 1 * 84 * 84], &Cpu)
                 .unwrap()
-                .reshape(&[1, 4, 1, 84, 84])
+                .reshape(&[n, n_frames, 1, 84, 84])
                 .unwrap()
         }
     }
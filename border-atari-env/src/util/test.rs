@@ -7,7 +7,7 @@ use anyhow::Result;
 use border_core::{
     generic_replay_buffer::{BatchBase, SimpleReplayBuffer},
     record::Record,
-    Agent as Agent_, Configurable, Policy, ReplayBufferBase,
+    Agent as Agent_, Configurable, Obs as _, Policy, ReplayBufferBase,
 };
 use serde::Deserialize;
 use std::ptr::copy;
@@ -71,7 +71,7 @@ impl BatchBase for ObsBatch {
 impl From<Obs> for ObsBatch {
     fn from(obs: Obs) -> Self {
         Self {
-            n: 1,
+            n: obs.len(),
             m: 4 * FRAME_IN_BYTES,
             buf: obs.frames,
         }
@@ -126,9 +126,9 @@ impl BatchBase for ActBatch {
 impl From<Act> for ActBatch {
     fn from(act: Act) -> Self {
         Self {
-            n: 1,
+            n: act.acts.len(),
             m: 1,
-            buf: vec![act.act],
+            buf: act.acts,
         }
     }
 }
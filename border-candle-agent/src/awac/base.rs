@@ -2,8 +2,8 @@ use super::AwacConfig;
 use crate::{
     model::{SubModel1, SubModel2},
     util::{
-        actor::GaussianActor, critic::MultiCritic, gamma_not_done, smooth_l1_loss, CriticLoss,
-        OutDim,
+        actor::GaussianActor, critic::MultiCritic, gamma_not_done, insert_scheduled, load_yaml,
+        named_grad_stats, save_yaml, smooth_l1_loss, CriticLoss, OutDim,
     },
 };
 use anyhow::Result;
@@ -13,7 +13,7 @@ use border_core::{
 };
 use candle_core::{Device, Tensor, D};
 use candle_nn::{loss::mse, ops::softmax};
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     fs,
     marker::PhantomData,
@@ -41,6 +41,7 @@ where
     train: bool,
     // reward_scale: f32,
     n_opts: usize,
+    record_verbose_level: usize,
     exp_adv_max: f64,
     critic_loss: CriticLoss,
     phantom: PhantomData<(E, R)>,
@@ -48,6 +49,12 @@ where
     adv_softmax: bool,
 }
 
+/// Training state of [`Awac`] saved in checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct AwacState {
+    n_opts: usize,
+}
+
 impl<E, Q, P, R> Awac<E, Q, P, R>
 where
     E: Env,
@@ -167,6 +174,12 @@ where
         Ok((loss, adv_mean, adv_abs_mean, logp_mean))
     }
 
+    /// Returns the norms of gradients of the models in the last update, before clipping.
+    fn grad_stats(&self) -> Result<Record> {
+        let record = named_grad_stats(self.actor.grad_stats()?, "actor");
+        Ok(record.merge(named_grad_stats(self.critic.grad_stats()?, "critic")))
+    }
+
     fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
         let mut loss_critic = 0f32;
         let mut loss_actor = 0f32;
@@ -200,7 +213,7 @@ where
         adv_mean /= self.n_updates_per_opt as f32;
         adv_abs_mean /= self.n_updates_per_opt as f32;
 
-        let record = Record::from_slice(&[
+        let mut record = Record::from_slice(&[
             ("loss_critic", RecordValue::Scalar(loss_critic)),
             ("loss_actor", RecordValue::Scalar(loss_actor)),
             ("q_tgt_abs_mean", RecordValue::Scalar(q_tgt_abs_mean)),
@@ -211,6 +224,15 @@ where
             ("next_q_mean", RecordValue::Scalar(next_q_mean)),
         ]);
 
+        insert_scheduled(
+            &mut record,
+            &[
+                ("lr_actor", self.actor.scheduled_learning_rate()),
+                ("lr_critic", self.critic.scheduled_learning_rate()),
+                ("tau", self.critic.scheduled_tau()),
+            ],
+        );
+
         Ok(record)
     }
 }
@@ -269,6 +291,7 @@ where
             inv_lambda: config.inv_lambda,
             exp_adv_max: config.exp_adv_max,
             n_opts: 0,
+            record_verbose_level: config.record_verbose_level,
             train: false,
             device: device.into(),
             adv_softmax: config.adv_softmax,
@@ -305,7 +328,12 @@ where
     }
 
     fn opt_with_record(&mut self, buffer: &mut R) -> Record {
-        self.opt_(buffer).expect("Failed in Awac::opt_()")
+        let record = self.opt_(buffer).expect("Failed in Awac::opt_()");
+
+        match self.record_verbose_level >= 2 {
+            true => record.merge(self.grad_stats().expect("Failed in Awac::grad_stats()")),
+            false => record,
+        }
     }
 
     fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
@@ -325,6 +353,44 @@ where
         Ok(())
     }
 
+    /// Save the state of the agent for resuming training in the given directory.
+    ///
+    /// In addition to the files saved by [`Agent::save_params`], the states of the optimizers
+    /// and the number of optimization steps (`agent.yaml`) are saved.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+        paths.push(self.actor.save_opt(path.join("actor"))?);
+        paths.push(self.critic.save_opt(path.join("critic"))?);
+
+        let state_path = path.join("agent.yaml");
+        save_yaml(
+            &state_path,
+            &AwacState {
+                n_opts: self.n_opts,
+            },
+        )?;
+        paths.push(state_path);
+
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+        self.actor.load_opt(path.join("actor"))?;
+        self.critic.load_opt(path.join("critic"))?;
+
+        let state: AwacState = load_yaml(path.join("agent.yaml"))?;
+        self.n_opts = state.n_opts;
+
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.actor.set_learning_rate(lr);
+        self.critic.set_learning_rate(lr);
+        Ok(())
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
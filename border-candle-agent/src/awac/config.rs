@@ -77,6 +77,12 @@ where
     /// Device used for the actor and critic models (e.g., CPU or GPU).
     pub device: Option<Device>,
 
+    /// Verbose level of records.
+    ///
+    /// If it is 2 or more, the norms of gradients are recorded in optimization steps.
+    #[serde(default)]
+    pub record_verbose_level: usize,
+
     /// If true, advantage weights are calculated with softmax within each mini-batch.
     pub adv_softmax: bool,
 }
@@ -105,6 +111,7 @@ where
             exp_adv_max: self.exp_adv_max,
             seed: self.seed.clone(),
             device: self.device.clone(),
+            record_verbose_level: self.record_verbose_level,
             adv_softmax: self.adv_softmax,
         }
     }
@@ -134,6 +141,7 @@ where
             exp_adv_max: 100.0,
             seed: None,
             device: None,
+            record_verbose_level: 0,
             adv_softmax: false,
         }
     }
@@ -220,6 +228,12 @@ where
         self
     }
 
+    /// Sets verbose level.
+    pub fn record_verbose_level(mut self, v: usize) -> Self {
+        self.record_verbose_level = v;
+        self
+    }
+
     /// If true, advantage weights are calculated with softmax within each mini-batch.
     pub fn adv_softmax(mut self, b: bool) -> Self {
         self.adv_softmax = b;
@@ -122,7 +122,8 @@ where
             match self.record_verbose_level >= 2 {
                 true => {
                     let record_weights = self.policy_model.param_stats();
-                    let record = record.merge(record_weights);
+                    let record_grads = self.policy_model.grad_stats().unwrap();
+                    let record = record.merge(record_weights).merge(record_grads);
                     record
                 }
                 false => record,
@@ -151,6 +152,30 @@ where
             .load(&path.join("policy_model.pt").as_path())?;
         Ok(())
     }
+
+    /// Save the state of the agent for resuming training in the given directory.
+    ///
+    /// In addition to the parameters, the state of the optimizer is saved in
+    /// `policy_model.opt.pt`.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+        let opt_path = path.join("policy_model.opt.pt");
+        self.policy_model.save_opt(&opt_path)?;
+        paths.push(opt_path);
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+        self.policy_model
+            .load_opt(&path.join("policy_model.opt.pt"))?;
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.policy_model.set_learning_rate(lr);
+        Ok(())
+    }
 }
 
 impl<E, P, R> Bc<E, P, R>
@@ -205,9 +205,38 @@ where
         Ok(())
     }
 
+    /// Saves the state of the optimizer.
+    pub fn save_opt<T: AsRef<Path>>(&self, path: T) -> Result<()> {
+        self.opt.save(&path, &self.varmap)?;
+        info!("Save optimizer of bc model to {:?}", path.as_ref());
+        Ok(())
+    }
+
+    /// Loads the state of the optimizer.
+    pub fn load_opt<T: AsRef<Path>>(&mut self, path: T) -> Result<()> {
+        self.opt.load(&path, &self.varmap)?;
+        info!("Load optimizer of bc model from {:?}", path.as_ref());
+        Ok(())
+    }
+
+    /// Sets the learning rate of the optimizer.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.opt.set_learning_rate(lr);
+    }
+
+    /// Returns the learning rate used in the last update if it is scheduled.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        self.opt.scheduled_learning_rate()
+    }
+
     pub fn param_stats(&self) -> Record {
         crate::util::param_stats(&self.varmap)
     }
+
+    /// Returns the norms of gradients in the last update, before clipping.
+    pub fn grad_stats(&self) -> Result<Record> {
+        self.opt.grad_stats(&self.varmap)
+    }
 }
 
 impl<P> Clone for BcModel<P>
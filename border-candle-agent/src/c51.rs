@@ -0,0 +1,5 @@
+//! C51 agent.
+mod base;
+mod config;
+pub use base::C51;
+pub use config::C51Config;
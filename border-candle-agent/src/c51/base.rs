@@ -0,0 +1,512 @@
+//! C51 agent implemented with candle.
+use super::C51Config;
+use crate::{
+    dqn::{DqnExplorer, DqnModel},
+    model::SubModel1,
+    util::{gamma_n_not_done, insert_scheduled, load_yaml, save_yaml, track, OutDim},
+};
+use anyhow::Result;
+use border_core::{
+    record::{Record, RecordValue},
+    Agent, Configurable, Env, Policy, ReplayBufferBase, Schedule, TransitionBatch,
+};
+use candle_core::{shape::D, DType, Device, Tensor};
+use candle_nn::ops::{log_softmax, softmax};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+#[allow(clippy::upper_case_acronyms)]
+/// C51 agent implemented with candle.
+///
+/// The model `Q` outputs `n_actions * n_atoms` values, which are regarded as the logits
+/// of categorical distributions of the action values over `n_atoms` equally spaced atoms
+/// in `[v_min, v_max]`.
+pub struct C51<E, Q, R>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    soft_update_interval: usize,
+    soft_update_counter: usize,
+    n_updates_per_opt: usize,
+    batch_size: usize,
+    qnet: DqnModel<Q>,
+    qnet_tgt: DqnModel<Q>,
+    train: bool,
+    phantom: PhantomData<(E, R)>,
+    discount_factor: f64,
+    tau: f64,
+    tau_schedule: Option<Schedule>,
+    n_atoms: usize,
+    v_min: f64,
+    v_max: f64,
+
+    // Atoms of the support, [1, 1, n_atoms]
+    atoms: Tensor,
+    explorer: DqnExplorer,
+    device: Device,
+    n_opts: usize,
+    n_samples_act: usize,
+    n_samples_best_act: usize,
+    record_verbose_level: usize,
+    rng: ChaCha8Rng,
+}
+
+/// Returns the logits of the distributions of action values,
+/// whose shape is `[batch_size, n_actions, n_atoms]`.
+fn logits<Q>(qnet: &DqnModel<Q>, obs: &Q::Input, n_atoms: usize) -> Result<Tensor>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + Clone,
+{
+    let z = qnet.forward(obs);
+    let batch_size = z.dims()[0];
+    Ok(z.reshape((batch_size, (), n_atoms))?)
+}
+
+/// Returns the atoms of the support of the categorical distribution.
+fn support(n_atoms: usize, v_min: f64, v_max: f64) -> Vec<f32> {
+    let delta = (v_max - v_min) / (n_atoms - 1) as f64;
+    (0..n_atoms)
+        .map(|i| (v_min + delta * i as f64) as f32)
+        .collect()
+}
+
+/// Projects the distributions of `reward + gamma_not_done * z` onto the support.
+///
+/// `probs` is the probabilities of the next action values over the atoms for each sample.
+/// The returned vector is the flattened projected probabilities, `[batch_size * n_atoms]`.
+fn project(
+    probs: &[Vec<f32>],
+    reward: &[f32],
+    gamma_not_done: &[f32],
+    v_min: f64,
+    v_max: f64,
+) -> Vec<f32> {
+    let n_atoms = probs[0].len();
+    let atoms = support(n_atoms, v_min, v_max);
+    let delta = (v_max - v_min) as f32 / (n_atoms - 1) as f32;
+    let mut m = vec![0f32; probs.len() * n_atoms];
+
+    for (i, p) in probs.iter().enumerate() {
+        let m = &mut m[i * n_atoms..(i + 1) * n_atoms];
+        for (z, p) in atoms.iter().zip(p.iter()) {
+            let tz = (reward[i] + gamma_not_done[i] * z).clamp(v_min as f32, v_max as f32);
+            let b = (tz - v_min as f32) / delta;
+            let (l, u) = (b.floor() as usize, (b.ceil() as usize).min(n_atoms - 1));
+            if l == u {
+                m[l] += p;
+            } else {
+                m[l] += p * (u as f32 - b);
+                m[u] += p * (b - l as f32);
+            }
+        }
+    }
+
+    m
+}
+
+/// Training state of [`C51`] saved in checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct C51State {
+    n_opts: usize,
+    soft_update_counter: usize,
+    explorer: DqnExplorer,
+    rng_seed: [u8; 32],
+    rng_word_pos: u64,
+}
+
+impl<E, Q, R> C51<E, Q, R>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Returns the expected action values, whose shape is `[batch_size, n_actions]`.
+    fn action_values(&self, obs: &Q::Input) -> Result<Tensor> {
+        let p = softmax(&logits(&self.qnet, obs, self.n_atoms)?, D::Minus1)?;
+        Ok(p.broadcast_mul(&self.atoms)?.sum(D::Minus1)?)
+    }
+}
+
+impl<E, Q, R> C51<E, Q, R>
+where
+    E: Env,
+    Q: SubModel1<Output = Tensor>,
+    R: ReplayBufferBase,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input>,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
+{
+    fn update_critic(&mut self, buffer: &mut R) -> Result<Record> {
+        let mut record = Record::empty();
+        if matches!(self.explorer, DqnExplorer::NoisyNet(_)) {
+            self.qnet.reset_noise();
+            self.qnet_tgt.reset_noise();
+        }
+        let batch = buffer.batch(self.batch_size)?;
+        let n_step = batch.n_step().map(|n_step| n_step.to_vec());
+        let (obs, act, next_obs, reward, is_terminated, _is_truncated, ixs, weight) =
+            batch.unpack();
+        let obs = obs.into();
+        let act = act.into().to_device(&self.device)?;
+        let next_obs = next_obs.into();
+        let batch_size = reward.len();
+        let gamma_not_done = gamma_n_not_done(
+            self.discount_factor as f32,
+            is_terminated,
+            n_step.as_deref(),
+            &self.device,
+        )?
+        .to_vec1::<f32>()?;
+        let n_atoms = self.n_atoms;
+
+        // Log probabilities of z(s, a), where a is from minibatch, [batch_size, n_atoms]
+        let logp = {
+            let z = logits(&self.qnet, &obs, n_atoms)?;
+            let a = act
+                .to_dtype(DType::I64)?
+                .reshape((batch_size, 1, 1))?
+                .broadcast_as((batch_size, 1, n_atoms))?
+                .contiguous()?;
+            log_softmax(&z.gather(&a, 1)?.squeeze(1)?, D::Minus1)?
+        };
+
+        // Target distribution projected onto the support, [batch_size, n_atoms]
+        let tgt = {
+            let p = softmax(&logits(&self.qnet_tgt, &next_obs, n_atoms)?, D::Minus1)?;
+            let a = p
+                .broadcast_mul(&self.atoms)?
+                .sum(D::Minus1)?
+                .argmax(D::Minus1)?
+                .reshape((batch_size, 1, 1))?
+                .broadcast_as((batch_size, 1, n_atoms))?
+                .contiguous()?;
+            let p = p.gather(&a, 1)?.squeeze(1)?.to_vec2::<f32>()?;
+            let m = project(&p, &reward, &gamma_not_done, self.v_min, self.v_max);
+            Tensor::from_vec(m, (batch_size, n_atoms), &self.device)?
+        };
+
+        if self.record_verbose_level >= 2 {
+            let atoms = self.atoms.squeeze(0)?;
+            let pred_mean = logp.exp()?.broadcast_mul(&atoms)?.sum(D::Minus1)?;
+            let tgt_mean = tgt.broadcast_mul(&atoms)?.sum(D::Minus1)?;
+            record.insert(
+                "pred_mean",
+                RecordValue::Scalar(pred_mean.mean_all()?.to_vec0::<f32>()?),
+            );
+            record.insert(
+                "tgt_mean",
+                RecordValue::Scalar(tgt_mean.mean_all()?.to_vec0::<f32>()?),
+            );
+        }
+
+        // Cross entropy of each sample, [batch_size]
+        let losses = (tgt * logp)?.sum(D::Minus1)?.neg()?;
+
+        let loss = match weight {
+            Some(ws) => {
+                let ws = Tensor::from_vec(ws, (batch_size,), &self.device)?;
+                let td_errs = losses.detach().to_vec1::<f32>()?;
+                buffer.update_priority(&ixs, &Some(td_errs));
+                (losses * ws)?.mean_all()?
+            }
+            None => losses.mean_all()?,
+        };
+
+        // Backprop
+        self.qnet.backward_step(&loss)?;
+
+        record.insert("loss", RecordValue::Scalar(loss.to_scalar::<f32>()?));
+
+        Ok(record)
+    }
+
+    fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
+        let mut record_ = Record::empty();
+
+        for _ in 0..self.n_updates_per_opt {
+            let record = self.update_critic(buffer)?;
+            record_ = record_.merge(record);
+        }
+
+        if let Some(tau_schedule) = &self.tau_schedule {
+            self.tau = tau_schedule.value(self.n_opts);
+        }
+        insert_scheduled(
+            &mut record_,
+            &[
+                ("tau", self.tau_schedule.as_ref().map(|_| self.tau)),
+                ("lr", self.qnet.scheduled_learning_rate()),
+                ("eps", self.explorer.eps()),
+            ],
+        );
+
+        self.soft_update_counter += 1;
+        if self.soft_update_counter == self.soft_update_interval {
+            self.soft_update_counter = 0;
+            track(self.qnet_tgt.get_varmap(), self.qnet.get_varmap(), self.tau)?;
+        }
+
+        self.n_opts += 1;
+
+        Ok(record_)
+    }
+}
+
+impl<E, Q, R> Policy<E> for C51<E, Q, R>
+where
+    E: Env,
+    Q: SubModel1<Output = Tensor>,
+    E::Obs: Into<Q::Input>,
+    E::Act: From<Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// In evaluation mode, take a random action with probability 0.01.
+    fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        if self.train && matches!(self.explorer, DqnExplorer::NoisyNet(_)) {
+            self.qnet.reset_noise();
+        }
+        let a = self.action_values(&obs.clone().into()).unwrap().detach();
+        let a = if self.train {
+            self.n_samples_act += 1;
+            match &mut self.explorer {
+                DqnExplorer::Softmax(softmax) => softmax.action(&a, &mut self.rng),
+                DqnExplorer::EpsilonGreedy(egreedy) => {
+                    if self.record_verbose_level >= 2 {
+                        let (act, best) = egreedy.action_with_best(&a, &mut self.rng);
+                        if best {
+                            self.n_samples_best_act += 1;
+                        }
+                        act
+                    } else {
+                        egreedy.action(&a, &mut self.rng)
+                    }
+                }
+                DqnExplorer::NoisyNet(noisy_net) => noisy_net.action(&a),
+            }
+        } else if self.rng.gen::<f32>() < 0.01 {
+            let n_actions = a.dims()[1] as i64;
+            let a: i64 = self.rng.gen_range(0..n_actions);
+            Tensor::try_from(vec![a]).unwrap()
+        } else {
+            a.argmax(D::Minus1).unwrap().to_dtype(DType::I64).unwrap()
+        };
+        a.into()
+    }
+}
+
+impl<E, Q, R> Configurable for C51<E, Q, R>
+where
+    E: Env,
+    Q: SubModel1<Output = Tensor>,
+    E::Obs: Into<Q::Input>,
+    E::Act: From<Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    type Config = C51Config<Q>;
+
+    /// Constructs [`C51`] agent.
+    fn build(config: Self::Config) -> Self {
+        let device: Device = config
+            .device
+            .expect("No device is given for C51 agent")
+            .into();
+        let qnet = DqnModel::build(config.model_config.clone(), device.clone()).unwrap();
+        let qnet_tgt = DqnModel::build(config.model_config, device.clone()).unwrap();
+        assert!(
+            !matches!(config.explorer, DqnExplorer::NoisyNet(_)) || qnet.is_noisy(),
+            "DqnExplorer::NoisyNet requires a model with noisy layers"
+        );
+        track(qnet_tgt.get_varmap(), qnet.get_varmap(), 1.0).unwrap();
+        let atoms = Tensor::from_vec(
+            support(config.n_atoms, config.v_min, config.v_max),
+            (1, 1, config.n_atoms),
+            &device,
+        )
+        .unwrap();
+
+        C51 {
+            qnet,
+            qnet_tgt,
+            soft_update_interval: config.soft_update_interval,
+            soft_update_counter: 0,
+            n_updates_per_opt: config.n_updates_per_opt,
+            batch_size: config.batch_size,
+            discount_factor: config.discount_factor,
+            tau: config.tau,
+            tau_schedule: config.tau_schedule,
+            n_atoms: config.n_atoms,
+            v_min: config.v_min,
+            v_max: config.v_max,
+            atoms,
+            train: config.train,
+            explorer: config.explorer,
+            device,
+            n_opts: 0,
+            phantom: PhantomData,
+            n_samples_act: 0,
+            n_samples_best_act: 0,
+            record_verbose_level: config.record_verbose_level,
+            rng: ChaCha8Rng::seed_from_u64(42),
+        }
+    }
+}
+
+impl<E, Q, R> Agent<E, R> for C51<E, Q, R>
+where
+    E: Env + 'static,
+    Q: SubModel1<Output = Tensor> + 'static,
+    R: ReplayBufferBase + 'static,
+    E::Obs: Into<Q::Input>,
+    E::Act: From<Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input>,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
+{
+    fn train(&mut self) {
+        self.train = true;
+        if matches!(self.explorer, DqnExplorer::NoisyNet(_)) {
+            self.qnet.reset_noise();
+        }
+    }
+
+    /// In evaluation mode, noisy layers use their mean weights.
+    fn eval(&mut self) {
+        self.train = false;
+        if matches!(self.explorer, DqnExplorer::NoisyNet(_)) {
+            self.qnet.remove_noise();
+        }
+    }
+
+    fn is_train(&self) -> bool {
+        self.train
+    }
+
+    fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        let mut record = {
+            let record = self.opt_(buffer).expect("Failed in C51::opt_()");
+
+            match self.record_verbose_level >= 2 {
+                true => record
+                    .merge(self.qnet.param_stats())
+                    .merge(self.qnet.grad_stats().unwrap()),
+                false => record,
+            }
+        };
+
+        // Best action ratio for epsilon greedy
+        let ratio = match self.n_samples_act == 0 {
+            true => 0f32,
+            false => self.n_samples_best_act as f32 / self.n_samples_act as f32,
+        };
+        record.insert("ratio_best_act", RecordValue::Scalar(ratio));
+        self.n_samples_act = 0;
+        self.n_samples_best_act = 0;
+
+        record
+    }
+
+    /// Save model parameters in the given directory.
+    ///
+    /// The parameters of the model are saved as `qnet.pt`.
+    /// The parameters of the target model are saved as `qnet_tgt.pt`.
+    fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(path)?;
+        let path1 = path.join("qnet.pt");
+        let path2 = path.join("qnet_tgt.pt");
+        self.qnet.save(&path1)?;
+        self.qnet_tgt.save(&path2)?;
+        Ok(vec![path1, path2])
+    }
+
+    fn load_params(&mut self, path: &Path) -> Result<()> {
+        self.qnet.load(path.join("qnet.pt").as_path())?;
+        self.qnet_tgt.load(path.join("qnet_tgt.pt").as_path())?;
+        Ok(())
+    }
+
+    /// Save the state of the agent for resuming training in the given directory.
+    ///
+    /// In addition to the files saved by [`Agent::save_params`], the state of the optimizer
+    /// is saved as `qnet.opt.pt`, and the counters, the explorer and the random number
+    /// generator are saved in `agent.yaml`.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+
+        let opt_path = path.join("qnet.opt.pt");
+        self.qnet.save_opt(&opt_path)?;
+        paths.push(opt_path);
+
+        let state_path = path.join("agent.yaml");
+        let state = C51State {
+            n_opts: self.n_opts,
+            soft_update_counter: self.soft_update_counter,
+            explorer: self.explorer.clone(),
+            rng_seed: self.rng.get_seed(),
+            rng_word_pos: self.rng.get_word_pos() as u64,
+        };
+        save_yaml(&state_path, &state)?;
+        paths.push(state_path);
+
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+        self.qnet.load_opt(path.join("qnet.opt.pt"))?;
+
+        let state: C51State = load_yaml(path.join("agent.yaml"))?;
+        self.n_opts = state.n_opts;
+        self.soft_update_counter = state.soft_update_counter;
+        self.explorer = state.explorer;
+        self.rng = ChaCha8Rng::from_seed(state.rng_seed);
+        self.rng.set_word_pos(state.rng_word_pos as u128);
+
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.qnet.set_learning_rate(lr);
+        Ok(())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project() {
+        let probs = vec![vec![0.2f32, 0.3, 0.5]; 3];
+
+        // Terminal transition, the reward falls between the first and second atoms
+        let m = project(&probs, &[-0.5, 0.0, 0.0], &[0.0, 1.0, 0.5], -1.0, 1.0);
+        assert_eq!(&m[0..3], &[0.5, 0.5, 0.0]);
+
+        // Identity
+        assert_eq!(&m[3..6], &[0.2, 0.3, 0.5]);
+
+        // Shrinking the support to [-0.5, 0.5]
+        let expected = [0.1f32, 0.65, 0.25];
+        for (m, e) in m[6..9].iter().zip(expected.iter()) {
+            assert!((m - e).abs() < 1e-6);
+        }
+    }
+}
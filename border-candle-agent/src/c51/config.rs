@@ -0,0 +1,198 @@
+//! Configuration of C51 agent.
+use crate::{
+    dqn::{DqnExplorer, DqnModelConfig, Softmax},
+    model::SubModel1,
+    util::OutDim,
+    Device,
+};
+use anyhow::Result;
+use border_core::Schedule;
+use candle_core::Tensor;
+use log::info;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    default::Default,
+    fs::File,
+    io::{BufReader, Write},
+    marker::PhantomData,
+    path::Path,
+};
+
+/// Configuration of [`C51`](super::C51) agent.
+///
+/// The output dimension of the model given by
+/// [`DqnModelConfig`] must be `n_actions * n_atoms`.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct C51Config<Q>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    pub model_config: DqnModelConfig<Q::Config>,
+    pub soft_update_interval: usize,
+    pub n_updates_per_opt: usize,
+    pub batch_size: usize,
+    pub discount_factor: f64,
+    pub tau: f64,
+    #[serde(default)]
+    pub tau_schedule: Option<Schedule>,
+    pub train: bool,
+    pub explorer: DqnExplorer,
+    pub n_atoms: usize,
+    pub v_min: f64,
+    pub v_max: f64,
+    pub device: Option<Device>,
+    pub record_verbose_level: usize,
+    pub phantom: PhantomData<Q>,
+}
+
+impl<Q> Clone for C51Config<Q>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            model_config: self.model_config.clone(),
+            soft_update_interval: self.soft_update_interval,
+            n_updates_per_opt: self.n_updates_per_opt,
+            batch_size: self.batch_size,
+            discount_factor: self.discount_factor,
+            tau: self.tau,
+            tau_schedule: self.tau_schedule.clone(),
+            train: self.train,
+            explorer: self.explorer.clone(),
+            n_atoms: self.n_atoms,
+            v_min: self.v_min,
+            v_max: self.v_max,
+            device: self.device,
+            record_verbose_level: self.record_verbose_level,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<Q> Default for C51Config<Q>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Constructs C51 builder with default parameters.
+    fn default() -> Self {
+        Self {
+            model_config: Default::default(),
+            soft_update_interval: 1,
+            n_updates_per_opt: 1,
+            batch_size: 1,
+            discount_factor: 0.99,
+            tau: 0.005,
+            tau_schedule: None,
+            train: false,
+            explorer: DqnExplorer::Softmax(Softmax::new()),
+            n_atoms: 51,
+            v_min: -10.0,
+            v_max: 10.0,
+            device: None,
+            record_verbose_level: 0,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<Q> C51Config<Q>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Sets the configuration of the model.
+    pub fn model_config(mut self, model_config: DqnModelConfig<Q::Config>) -> Self {
+        self.model_config = model_config;
+        self
+    }
+
+    /// Sets soft update interval.
+    pub fn soft_update_interval(mut self, v: usize) -> Self {
+        self.soft_update_interval = v;
+        self
+    }
+
+    /// Sets the numper of parameter update steps per optimization step.
+    pub fn n_updates_per_opt(mut self, v: usize) -> Self {
+        self.n_updates_per_opt = v;
+        self
+    }
+
+    /// Batch size.
+    pub fn batch_size(mut self, v: usize) -> Self {
+        self.batch_size = v;
+        self
+    }
+
+    /// Discount factor.
+    pub fn discount_factor(mut self, v: f64) -> Self {
+        self.discount_factor = v;
+        self
+    }
+
+    /// Soft update coefficient.
+    pub fn tau(mut self, v: f64) -> Self {
+        self.tau = v;
+        self
+    }
+
+    /// Schedule of the soft update coefficient by optimization step, which overrides `tau`.
+    pub fn tau_schedule(mut self, v: Schedule) -> Self {
+        self.tau_schedule = Some(v);
+        self
+    }
+
+    /// Explorer.
+    pub fn explorer(mut self, v: DqnExplorer) -> Self {
+        self.explorer = v;
+        self
+    }
+
+    /// Sets the number of atoms of the categorical distribution.
+    pub fn n_atoms(mut self, v: usize) -> Self {
+        self.n_atoms = v;
+        self
+    }
+
+    /// Sets the range of the support of the categorical distribution.
+    pub fn support(mut self, v_min: f64, v_max: f64) -> Self {
+        self.v_min = v_min;
+        self.v_max = v_max;
+        self
+    }
+
+    /// Device.
+    pub fn device(mut self, device: candle_core::Device) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+
+    /// Sets verbose level.
+    pub fn record_verbose_level(mut self, v: usize) -> Self {
+        self.record_verbose_level = v;
+        self
+    }
+
+    /// Loads [`C51Config`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path_ = path.as_ref().to_owned();
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        info!("Load config of C51 agent from {}", path_.to_str().unwrap());
+        Ok(b)
+    }
+
+    /// Saves [`C51Config`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path_ = path.as_ref().to_owned();
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        info!("Save config of C51 agent into {}", path_.to_str().unwrap());
+        Ok(())
+    }
+}
@@ -0,0 +1,8 @@
+//! Conservative Q-learning (CQL) agent.
+//!
+//! [`Cql`] is for continuous actions. For discrete actions, CQL is implemented in
+//! [`Dqn`](crate::dqn::Dqn) with [`DqnConfig::cql_alpha()`](crate::dqn::DqnConfig::cql_alpha).
+mod base;
+mod config;
+pub use base::Cql;
+pub use config::CqlConfig;
@@ -0,0 +1,477 @@
+use super::CqlConfig;
+use crate::{
+    model::{SubModel1, SubModel2},
+    sac::{EntCoef, EntCoefMode},
+    util::{
+        actor::{ActionLimit, GaussianActor},
+        critic::MultiCritic,
+        gamma_n_not_done, insert_scheduled, load_yaml, named_grad_stats, save_yaml, smooth_l1_loss,
+        CriticLoss, OutDim,
+    },
+};
+use anyhow::Result;
+use border_core::{
+    record::{Record, RecordValue},
+    Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
+};
+use candle_core::{Device, Tensor};
+use candle_nn::loss::mse;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+type ActionValue = Tensor;
+type ActMean = Tensor;
+type ActStd = Tensor;
+
+/// Conservative Q-learning (CQL) agent for continuous actions.
+///
+/// The agent is built on SAC and adds the conservative penalty
+/// `logsumexp_a Q(s, a) - Q(s, a_data)` to the critic loss, where the logsumexp is estimated
+/// with actions sampled from the uniform distribution and the current policy
+/// ([Kumar et al., 2020](https://arxiv.org/abs/2006.04779)).
+/// The observations and actions are given as [`Tensor`]s.
+pub struct Cql<E, Q, P, R>
+where
+    Q: SubModel2<Input1 = Tensor, Input2 = Tensor, Output = ActionValue>,
+    P: SubModel1<Input = Tensor, Output = (ActMean, ActStd)>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    critic: MultiCritic<Q>,
+    actor: GaussianActor<P>,
+    gamma: f64,
+    ent_coef: EntCoef,
+    cql_alpha: f64,
+    // Lagrange multiplier of the conservative penalty, tuned like the entropy coefficient
+    cql_lagrange: Option<EntCoef>,
+    cql_temperature: f64,
+    n_action_samples: usize,
+    act_dim: usize,
+    action_range: (f64, f64),
+    n_updates_per_opt: usize,
+    batch_size: usize,
+    train: bool,
+    n_opts: usize,
+    record_verbose_level: usize,
+    critic_loss: CriticLoss,
+    phantom: PhantomData<(E, R)>,
+    device: Device,
+}
+
+/// Training state of [`Cql`] saved in checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct CqlState {
+    n_opts: usize,
+}
+
+impl<E, Q, P, R> Cql<E, Q, P, R>
+where
+    E: Env,
+    Q: SubModel2<Input1 = Tensor, Input2 = Tensor, Output = ActionValue>,
+    P: SubModel1<Input = Tensor, Output = (ActMean, ActStd)>,
+    R: ReplayBufferBase,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Tensor> + Clone,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
+{
+    /// Returns `logsumexp_a Q(s, a) - Q(s, a_data)` averaged over the critics.
+    ///
+    /// `qs` are the action values of the actions in the batch.
+    fn action_gap(&mut self, obs: &Tensor, next_obs: &Tensor, qs: &[Tensor]) -> Result<Tensor> {
+        let n = self.n_action_samples;
+        let batch_size = qs[0].dims()[0];
+        let obs_rep = Tensor::cat(&vec![obs.clone(); n], 0)?;
+        let next_obs_rep = Tensor::cat(&vec![next_obs.clone(); n], 0)?;
+
+        // Uniformly random actions and their log density
+        let (lo, up) = self.action_range;
+        let act_rand = Tensor::rand(
+            lo as f32,
+            up as f32,
+            (batch_size * n, self.act_dim),
+            &self.device,
+        )?;
+        let logp_rand = -(self.act_dim as f64) * (up - lo).ln();
+
+        // Actions of the current policy at the current and the next states
+        let act_pi = self.actor.sample(&obs_rep, true)?.detach();
+        let logp_pi = self.actor.logp(&obs_rep, &act_pi)?.detach();
+        let act_next = self.actor.sample(&next_obs_rep, true)?.detach();
+        let logp_next = self.actor.logp(&next_obs_rep, &act_next)?.detach();
+
+        let qs_rand = self.critic.qvals(&obs_rep, &act_rand);
+        let qs_pi = self.critic.qvals(&obs_rep, &act_pi);
+        let qs_next = self.critic.qvals(&obs_rep, &act_next);
+
+        let t = self.cql_temperature;
+        let gaps = qs
+            .iter()
+            .enumerate()
+            .map(|(i, q)| {
+                let q_ood = Tensor::cat(
+                    &[
+                        (&qs_rand[i] - logp_rand)?,
+                        (&qs_pi[i] - &logp_pi)?,
+                        (&qs_next[i] - &logp_next)?,
+                    ],
+                    0,
+                )?
+                .reshape((3 * n, batch_size))?;
+                let lse = ((q_ood / t)?.log_sum_exp(0)? * t)?;
+                lse - q
+            })
+            .collect::<Result<Vec<_>, candle_core::Error>>()?;
+
+        Ok(Tensor::stack(&gaps, 0)?.mean(0)?)
+    }
+
+    fn update_critic(&mut self, batch: R::Batch) -> Result<Record> {
+        // Extract items in the batch
+        let n_step = batch.n_step().map(|n_step| n_step.to_vec());
+        let (obs, act, next_obs, reward, is_terminated, _, _, _) = batch.unpack();
+        let batch_size = reward.len();
+        let reward = Tensor::from_slice(&reward[..], (batch_size,), &self.device)?;
+        let obs: Tensor = obs.into();
+        let next_obs: Tensor = next_obs.into();
+
+        // Prediction
+        let qs = self.critic.qvals(&obs, &act.into());
+
+        // Target
+        let tgt = {
+            let gamma_not_done = gamma_n_not_done(
+                self.gamma as f32,
+                is_terminated,
+                n_step.as_deref(),
+                &self.device,
+            )?;
+            let next_act = self.actor.sample(&next_obs, self.train)?;
+            let next_log_p = self.actor.logp(&next_obs, &next_act)?;
+            let next_q = self.critic.qvals_min_tgt(&next_obs, &next_act)?;
+            let next_q = (next_q - self.ent_coef.alpha()?.broadcast_mul(&next_log_p)?)?;
+            (&reward + (&gamma_not_done * next_q)?)?
+        }
+        .detach();
+        debug_assert_eq!(tgt.dims(), [self.batch_size]);
+
+        // TD loss
+        let loss_td = {
+            let losses: Vec<_> = match self.critic_loss {
+                CriticLoss::Mse => qs.iter().map(|pred| mse(pred, &tgt).unwrap()).collect(),
+                CriticLoss::SmoothL1 => qs
+                    .iter()
+                    .map(|pred| smooth_l1_loss(pred, &tgt).unwrap())
+                    .collect(),
+            };
+            Tensor::stack(&losses, 0)?.mean_all()?
+        };
+
+        // Conservative penalty
+        let penalty = (self.action_gap(&obs, &next_obs, &qs)? * self.cql_alpha)?;
+        let loss_penalty = match &mut self.cql_lagrange {
+            None => penalty.mean_all()?,
+            Some(lagrange) => {
+                let alpha_prime = lagrange.alpha()?.to_vec1::<f32>()?[0] as f64;
+                lagrange.update(&penalty.detach())?;
+                (penalty.mean_all()? * alpha_prime)?
+            }
+        };
+
+        let loss = (&loss_td + &loss_penalty)?;
+        self.critic.backward_step(&loss)?;
+
+        let mut record = Record::from_slice(&[
+            (
+                "loss_critic",
+                RecordValue::Scalar(loss_td.to_scalar::<f32>()?),
+            ),
+            (
+                "cql_penalty",
+                RecordValue::Scalar(penalty.mean_all()?.to_scalar::<f32>()?),
+            ),
+        ]);
+        if let Some(lagrange) = &self.cql_lagrange {
+            record.insert(
+                "cql_alpha_prime",
+                RecordValue::Scalar(lagrange.alpha()?.to_vec1::<f32>()?[0]),
+            );
+        }
+
+        Ok(record)
+    }
+
+    fn update_actor(&mut self, batch: &R::Batch) -> Result<f32> {
+        let loss = {
+            let obs: Tensor = batch.obs().clone().into();
+            let act = self.actor.sample(&obs, self.train)?;
+            let log_p = self.actor.logp(&obs, &act)?;
+
+            // Update the entropy coefficient
+            self.ent_coef.update(&log_p.detach())?;
+
+            // Loss
+            let q = self.critic.qvals_min(&obs, &act)?;
+            let alpha = self.ent_coef.alpha()?.detach();
+            (alpha.broadcast_mul(&log_p)? - &q)?.mean_all()?
+        };
+
+        self.actor.backward_step(&loss)?;
+
+        Ok(loss.to_scalar::<f32>()?)
+    }
+
+    /// Returns the norms of gradients of the models in the last update, before clipping.
+    fn grad_stats(&self) -> Result<Record> {
+        let record = named_grad_stats(self.actor.grad_stats()?, "actor");
+        Ok(record.merge(named_grad_stats(self.critic.grad_stats()?, "critic")))
+    }
+
+    fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
+        let mut record = Record::empty();
+        let mut loss_actor = 0f32;
+
+        for _ in 0..self.n_updates_per_opt {
+            let batch = buffer.batch(self.batch_size).unwrap();
+            loss_actor += self.update_actor(&batch)?;
+            record = record.merge(self.update_critic(batch)?);
+            self.critic.soft_update()?;
+            self.n_opts += 1;
+        }
+
+        loss_actor /= self.n_updates_per_opt as f32;
+        record.insert("loss_actor", RecordValue::Scalar(loss_actor));
+        record.insert(
+            "ent_coef",
+            RecordValue::Scalar(self.ent_coef.alpha()?.to_vec1::<f32>()?[0]),
+        );
+
+        insert_scheduled(
+            &mut record,
+            &[
+                ("lr_actor", self.actor.scheduled_learning_rate()),
+                ("lr_critic", self.critic.scheduled_learning_rate()),
+                ("tau", self.critic.scheduled_tau()),
+                ("target_entropy", self.ent_coef.scheduled_target_entropy()),
+            ],
+        );
+
+        Ok(record)
+    }
+}
+
+impl<E, Q, P, R> Policy<E> for Cql<E, Q, P, R>
+where
+    E: Env,
+    Q: SubModel2<Input1 = Tensor, Input2 = Tensor, Output = ActionValue>,
+    P: SubModel1<Input = Tensor, Output = (ActMean, ActStd)>,
+    E::Obs: Into<Tensor>,
+    E::Act: From<Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        self.actor
+            .sample(&obs.clone().into(), self.train)
+            .unwrap()
+            .into()
+    }
+}
+
+impl<E, Q, P, R> Configurable for Cql<E, Q, P, R>
+where
+    E: Env,
+    Q: SubModel2<Input1 = Tensor, Input2 = Tensor, Output = ActionValue>,
+    P: SubModel1<Input = Tensor, Output = (ActMean, ActStd)>,
+    E::Obs: Into<Tensor>,
+    E::Act: From<Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    type Config = CqlConfig<Q, P>;
+
+    /// Constructs [`Cql`] agent.
+    fn build(config: Self::Config) -> Self {
+        let device: Device = config
+            .device
+            .expect("No device is given for CQL agent")
+            .into();
+        let act_dim = config
+            .actor_config
+            .policy_config
+            .as_ref()
+            .expect("policy_config is not set")
+            .get_out_dim() as usize;
+        let action_range = match config.actor_config.action_limit {
+            ActionLimit::Tanh { action_scale } => (-action_scale as f64, action_scale as f64),
+            ActionLimit::Clamp {
+                action_min,
+                action_max,
+            } => (action_min as f64, action_max as f64),
+        };
+        let actor = GaussianActor::build(config.actor_config, device.clone()).unwrap();
+        let critic = MultiCritic::build(config.critic_config, device.clone()).unwrap();
+        let ent_coef = EntCoef::new(config.ent_coef_mode, device.clone()).unwrap();
+        // The multiplier is increased while the penalty exceeds the threshold
+        let cql_lagrange_lr = config.cql_lagrange_lr;
+        let cql_lagrange = config.cql_target_action_gap.map(|gap| {
+            let mode = EntCoefMode::Auto(-gap, cql_lagrange_lr);
+            EntCoef::new(mode, device.clone()).unwrap()
+        });
+
+        Cql {
+            actor,
+            critic,
+            gamma: config.gamma,
+            ent_coef,
+            cql_alpha: config.cql_alpha,
+            cql_lagrange,
+            cql_temperature: config.cql_temperature,
+            n_action_samples: config.n_action_samples,
+            act_dim,
+            action_range,
+            n_updates_per_opt: config.n_updates_per_opt,
+            batch_size: config.batch_size,
+            train: false,
+            critic_loss: config.critic_loss,
+            n_opts: 0,
+            record_verbose_level: config.record_verbose_level,
+            device,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, Q, P, R> Agent<E, R> for Cql<E, Q, P, R>
+where
+    E: Env + 'static,
+    Q: SubModel2<Input1 = Tensor, Input2 = Tensor, Output = ActionValue> + 'static,
+    P: SubModel1<Input = Tensor, Output = (ActMean, ActStd)> + 'static,
+    R: ReplayBufferBase + 'static,
+    E::Obs: Into<Tensor>,
+    E::Act: From<Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Tensor> + Clone,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
+{
+    fn train(&mut self) {
+        self.train = true;
+    }
+
+    fn eval(&mut self) {
+        self.train = false;
+    }
+
+    fn is_train(&self) -> bool {
+        self.train
+    }
+
+    fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        let record = self.opt_(buffer).expect("Failed in Cql::opt_()");
+
+        match self.record_verbose_level >= 2 {
+            true => record.merge(self.grad_stats().expect("Failed in Cql::grad_stats()")),
+            false => record,
+        }
+    }
+
+    /// Save model parameters in the given directory.
+    ///
+    /// The Lagrange multiplier of the conservative penalty is saved as `cql_alpha_prime.pt`
+    /// if it is used.
+    fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(path)?;
+
+        let actor_path = self.actor.save(path.join("actor"))?;
+        let (critic_path, critic_tgt_path) = self.critic.save(path.join("critic"))?;
+        let ent_coef_path = path.join("ent_coef.pt");
+        self.ent_coef.save(&ent_coef_path)?;
+        let mut paths = vec![actor_path, critic_path, critic_tgt_path, ent_coef_path];
+
+        if let Some(lagrange) = &self.cql_lagrange {
+            let lagrange_path = path.join("cql_alpha_prime.pt");
+            lagrange.save(&lagrange_path)?;
+            paths.push(lagrange_path);
+        }
+
+        Ok(paths)
+    }
+
+    fn load_params(&mut self, path: &Path) -> Result<()> {
+        self.actor.load(path.join("actor").as_path())?;
+        self.critic.load(path.join("critic").as_path())?;
+        self.ent_coef.load(path.join("ent_coef.pt").as_path())?;
+        if let Some(lagrange) = &mut self.cql_lagrange {
+            lagrange.load(path.join("cql_alpha_prime.pt").as_path())?;
+        }
+
+        Ok(())
+    }
+
+    /// Save the state of the agent for resuming training in the given directory.
+    ///
+    /// In addition to the files saved by [`Agent::save_params`], the states of the optimizers
+    /// and the number of optimization steps (`agent.yaml`) are saved.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+        paths.push(self.actor.save_opt(path.join("actor"))?);
+        paths.push(self.critic.save_opt(path.join("critic"))?);
+
+        let ent_coef_opt_path = path.join("ent_coef.opt.pt");
+        self.ent_coef.save_opt(&ent_coef_opt_path)?;
+        paths.push(ent_coef_opt_path);
+
+        if let Some(lagrange) = &self.cql_lagrange {
+            let lagrange_opt_path = path.join("cql_alpha_prime.opt.pt");
+            lagrange.save_opt(&lagrange_opt_path)?;
+            paths.push(lagrange_opt_path);
+        }
+
+        let state_path = path.join("agent.yaml");
+        save_yaml(
+            &state_path,
+            &CqlState {
+                n_opts: self.n_opts,
+            },
+        )?;
+        paths.push(state_path);
+
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+        self.actor.load_opt(path.join("actor"))?;
+        self.critic.load_opt(path.join("critic"))?;
+        self.ent_coef.load_opt(path.join("ent_coef.opt.pt"))?;
+        if let Some(lagrange) = &mut self.cql_lagrange {
+            lagrange.load_opt(path.join("cql_alpha_prime.opt.pt"))?;
+        }
+
+        let state: CqlState = load_yaml(path.join("agent.yaml"))?;
+        self.n_opts = state.n_opts;
+
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.actor.set_learning_rate(lr);
+        self.critic.set_learning_rate(lr);
+        Ok(())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
+}
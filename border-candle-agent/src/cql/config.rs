@@ -0,0 +1,241 @@
+//! Configuration of CQL agent.
+use crate::{
+    model::{SubModel1, SubModel2},
+    sac::EntCoefMode,
+    util::{actor::GaussianActorConfig, critic::MultiCriticConfig, CriticLoss, OutDim},
+    Device,
+};
+use anyhow::Result;
+use candle_core::Tensor;
+use log::info;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
+
+/// Configuration of [`Cql`](super::Cql).
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct CqlConfig<Q, P>
+where
+    Q: SubModel2<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+    P: SubModel1<Output = (Tensor, Tensor)>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+{
+    /// Configuration of the actor model.
+    pub actor_config: GaussianActorConfig<P::Config>,
+
+    /// Configuration of the critic model.
+    pub critic_config: MultiCriticConfig<Q::Config>,
+
+    /// Discont factor.
+    pub gamma: f64,
+
+    /// How to update entropy coefficient.
+    pub ent_coef_mode: EntCoefMode,
+
+    /// Weight of the conservative penalty.
+    pub cql_alpha: f64,
+
+    /// Threshold of the conservative penalty.
+    ///
+    /// If `Some`, the weight of the penalty is further multiplied by a Lagrange multiplier,
+    /// which is tuned such that the penalty is close to the threshold.
+    pub cql_target_action_gap: Option<f64>,
+
+    /// Learning rate of the Lagrange multiplier.
+    pub cql_lagrange_lr: f64,
+
+    /// Temperature of the logsumexp in the conservative penalty.
+    pub cql_temperature: f64,
+
+    /// Number of actions sampled per state for estimating the logsumexp.
+    pub n_action_samples: usize,
+
+    /// Number of parameter updates per optimization step.
+    pub n_updates_per_opt: usize,
+
+    /// Batch size for training.
+    pub batch_size: usize,
+
+    /// Type of critic loss function.
+    pub critic_loss: CriticLoss,
+
+    /// Device for actor/critic models.
+    pub device: Option<Device>,
+
+    /// Verbose level of records.
+    ///
+    /// If it is 2 or more, the norms of gradients are recorded in optimization steps.
+    #[serde(default)]
+    pub record_verbose_level: usize,
+}
+
+impl<Q, P> Clone for CqlConfig<Q, P>
+where
+    Q: SubModel2<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+    P: SubModel1<Output = (Tensor, Tensor)>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            actor_config: self.actor_config.clone(),
+            critic_config: self.critic_config.clone(),
+            gamma: self.gamma,
+            ent_coef_mode: self.ent_coef_mode.clone(),
+            cql_alpha: self.cql_alpha,
+            cql_target_action_gap: self.cql_target_action_gap,
+            cql_lagrange_lr: self.cql_lagrange_lr,
+            cql_temperature: self.cql_temperature,
+            n_action_samples: self.n_action_samples,
+            n_updates_per_opt: self.n_updates_per_opt,
+            batch_size: self.batch_size,
+            critic_loss: self.critic_loss.clone(),
+            device: self.device,
+            record_verbose_level: self.record_verbose_level,
+        }
+    }
+}
+
+impl<Q, P> Default for CqlConfig<Q, P>
+where
+    Q: SubModel2<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+    P: SubModel1<Output = (Tensor, Tensor)>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+{
+    fn default() -> Self {
+        Self {
+            actor_config: Default::default(),
+            critic_config: Default::default(),
+            gamma: 0.99,
+            ent_coef_mode: EntCoefMode::Fix(1.0),
+            cql_alpha: 5.0,
+            cql_target_action_gap: None,
+            cql_lagrange_lr: 0.0003,
+            cql_temperature: 1.0,
+            n_action_samples: 10,
+            n_updates_per_opt: 1,
+            batch_size: 1,
+            critic_loss: CriticLoss::Mse,
+            device: None,
+            record_verbose_level: 0,
+        }
+    }
+}
+
+impl<Q, P> CqlConfig<Q, P>
+where
+    Q: SubModel2<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+    P: SubModel1<Output = (Tensor, Tensor)>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+{
+    /// Sets the numper of parameter update steps per optimization step.
+    pub fn n_updates_per_opt(mut self, v: usize) -> Self {
+        self.n_updates_per_opt = v;
+        self
+    }
+
+    /// Batch size.
+    pub fn batch_size(mut self, v: usize) -> Self {
+        self.batch_size = v;
+        self
+    }
+
+    /// Discount factor.
+    pub fn discount_factor(mut self, v: f64) -> Self {
+        self.gamma = v;
+        self
+    }
+
+    /// SAC-alpha.
+    pub fn ent_coef_mode(mut self, v: EntCoefMode) -> Self {
+        self.ent_coef_mode = v;
+        self
+    }
+
+    /// Weight of the conservative penalty.
+    pub fn cql_alpha(mut self, v: f64) -> Self {
+        self.cql_alpha = v;
+        self
+    }
+
+    /// Threshold of the conservative penalty for the Lagrangian version of CQL.
+    pub fn cql_target_action_gap(mut self, v: Option<f64>) -> Self {
+        self.cql_target_action_gap = v;
+        self
+    }
+
+    /// Learning rate of the Lagrange multiplier.
+    pub fn cql_lagrange_lr(mut self, v: f64) -> Self {
+        self.cql_lagrange_lr = v;
+        self
+    }
+
+    /// Temperature of the logsumexp in the conservative penalty.
+    pub fn cql_temperature(mut self, v: f64) -> Self {
+        self.cql_temperature = v;
+        self
+    }
+
+    /// Number of actions sampled per state.
+    pub fn n_action_samples(mut self, v: usize) -> Self {
+        self.n_action_samples = v;
+        self
+    }
+
+    /// Critic loss.
+    pub fn critic_loss(mut self, v: CriticLoss) -> Self {
+        self.critic_loss = v;
+        self
+    }
+
+    /// Configuration of actor.
+    pub fn actor_config(mut self, actor_config: GaussianActorConfig<P::Config>) -> Self {
+        self.actor_config = actor_config;
+        self
+    }
+
+    /// Configuration of critic.
+    pub fn critic_config(mut self, critic_config: MultiCriticConfig<Q::Config>) -> Self {
+        self.critic_config = critic_config;
+        self
+    }
+
+    /// Device.
+    pub fn device(mut self, device: candle_core::Device) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+
+    /// Sets verbose level.
+    pub fn record_verbose_level(mut self, v: usize) -> Self {
+        self.record_verbose_level = v;
+        self
+    }
+
+    /// Constructs [`CqlConfig`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path_ = path.as_ref().to_owned();
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        info!("Load config of CQL agent from {}", path_.to_str().unwrap());
+        Ok(b)
+    }
+
+    /// Saves [`CqlConfig`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path_ = path.as_ref().to_owned();
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        info!("Save config of CQL agent into {}", path_.to_str().unwrap());
+        Ok(())
+    }
+}
@@ -0,0 +1,11 @@
+//! Deep deterministic policy gradient (DDPG) agent.
+//!
+//! DDPG is implemented as a special case of [`Td3`], having a single critic and updating
+//! the actor at every step without target policy smoothing. These are the default settings
+//! of [`DdpgConfig`], from which [`Ddpg`] is built.
+mod config;
+use crate::td3::Td3;
+pub use config::DdpgConfig;
+
+/// Deep deterministic policy gradient (DDPG) agent.
+pub type Ddpg<E, Q, P, R> = Td3<E, Q, P, R, DdpgConfig<Q, P>>;
@@ -5,5 +5,5 @@ mod explorer;
 mod model;
 pub use base::Dqn;
 pub use config::DqnConfig;
-pub use explorer::{DqnExplorer, EpsilonGreedy, Softmax};
+pub use explorer::{DqnExplorer, EpsilonGreedy, NoisyNet, Softmax};
 pub use model::{DqnModel, DqnModelConfig};
@@ -2,17 +2,21 @@
 use super::{config::DqnConfig, explorer::DqnExplorer, model::DqnModel};
 use crate::{
     model::SubModel1,
-    util::{smooth_l1_loss, track, CriticLoss, OutDim},
+    util::{
+        gamma_n_not_done, insert_scheduled, load_yaml, save_yaml, smooth_l1_loss, track,
+        CriticLoss, OutDim,
+    },
 };
 use anyhow::Result;
 use border_core::{
     record::{Record, RecordValue},
-    Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
+    Agent, Configurable, Env, Policy, ReplayBufferBase, Schedule, TransitionBatch,
 };
 use candle_core::{shape::D, DType, Device, Tensor};
 use candle_nn::loss::mse;
-use rand::{rngs::SmallRng, Rng, SeedableRng};
-use serde::{de::DeserializeOwned, Serialize};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{convert::TryFrom, path::PathBuf};
 use std::{fs, marker::PhantomData, path::Path};
 
@@ -33,17 +37,29 @@ where
     pub(in crate::dqn) phantom: PhantomData<(E, R)>,
     pub(in crate::dqn) discount_factor: f64,
     pub(in crate::dqn) tau: f64,
+    pub(in crate::dqn) tau_schedule: Option<Schedule>,
     pub(in crate::dqn) explorer: DqnExplorer,
     pub(in crate::dqn) device: Device,
     pub(in crate::dqn) n_opts: usize,
     pub(in crate::dqn) double_dqn: bool,
     pub(in crate::dqn) _clip_reward: Option<f64>,
     pub(in crate::dqn) clip_td_err: Option<(f64, f64)>,
+    pub(in crate::dqn) cql_alpha: Option<f64>,
     pub(in crate::dqn) critic_loss: CriticLoss,
     n_samples_act: usize,
     n_samples_best_act: usize,
     record_verbose_level: usize,
-    rng: SmallRng,
+    rng: ChaCha8Rng,
+}
+
+/// Training state of [`Dqn`] saved in checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct DqnState {
+    n_opts: usize,
+    soft_update_counter: usize,
+    explorer: DqnExplorer,
+    rng_seed: [u8; 32],
+    rng_word_pos: u64,
 }
 
 impl<E, Q, R> Dqn<E, Q, R>
@@ -58,32 +74,31 @@ where
 {
     fn update_critic(&mut self, buffer: &mut R) -> Record {
         let mut record = Record::empty();
+        if matches!(self.explorer, DqnExplorer::NoisyNet(_)) {
+            self.qnet.reset_noise();
+            self.qnet_tgt.reset_noise();
+        }
         let batch = buffer.batch(self.batch_size).unwrap();
-        let (obs, act, next_obs, reward, is_terminated, _is_truncated, _ixs, weight) =
+        let n_step = batch.n_step().map(|n_step| n_step.to_vec());
+        let (obs, act, next_obs, reward, is_terminated, _is_truncated, ixs, weight) =
             batch.unpack();
         let obs = obs.into();
         let act = act.into().to_device(&self.device).unwrap();
         let next_obs = next_obs.into();
         let reward = Tensor::from_slice(&reward[..], &[reward.len()], &self.device).unwrap();
-        let is_not_terminated = {
-            let is_not_terminated = is_terminated
-                .into_iter()
-                .map(|v| (1 - v) as f32)
-                .collect::<Vec<_>>();
-            Tensor::from_slice(
-                &is_not_terminated[..],
-                &[is_not_terminated.len()],
-                &self.device,
-            )
+        let gamma_not_done = gamma_n_not_done(
+            self.discount_factor as f32,
+            is_terminated,
+            n_step.as_deref(),
+            &self.device,
+        )
+        .unwrap();
+        let q_all = self.qnet.forward(&obs);
+        let pred = q_all
+            .gather(&act, D::Minus1)
             .unwrap()
-        };
-        let pred = {
-            let x = self.qnet.forward(&obs);
-            x.gather(&act, D::Minus1)
-                .unwrap()
-                .squeeze(D::Minus1)
-                .unwrap()
-        };
+            .squeeze(D::Minus1)
+            .unwrap();
 
         if self.record_verbose_level >= 2 {
             record.insert(
@@ -102,7 +117,8 @@ where
                 let x = self.qnet.forward(&next_obs);
                 let y = x.argmax(D::Minus1).unwrap();
                 let tgt = self.qnet_tgt.forward(&next_obs);
-                tgt.gather(&y, D::Minus1).unwrap()
+                tgt.gather(&y.unsqueeze(D::Minus1).unwrap(), D::Minus1)
+                    .unwrap()
             } else {
                 let x = self.qnet_tgt.forward(&next_obs);
                 let y = x.argmax(D::Minus1).unwrap();
@@ -110,7 +126,7 @@ where
                     .unwrap()
             };
 
-            reward + is_not_terminated * self.discount_factor * q.squeeze(D::Minus1).unwrap()
+            reward + gamma_not_done * q.squeeze(D::Minus1).unwrap()
         }
         .unwrap()
         .detach();
@@ -132,24 +148,27 @@ where
             );
         }
 
-        let loss = if let Some(_ws) = weight {
-            // Prioritized weighting loss, will be implemented later
-            panic!();
-            // let n = ws.len() as i64;
-            // let td_errs = match self.clip_td_err {
-            //     None => (&pred - &tgt).abs(),
-            //     Some((min, max)) => (&pred - &tgt).abs().clip(min, max),
-            // };
-            // let loss = Tensor::of_slice(&ws[..]).to(self.device) * &td_errs;
-            // let loss = loss.smooth_l1_loss(
-            //     &Tensor::zeros(&[n], tch::kind::FLOAT_CPU).to(self.device),
-            //     tch::Reduction::Mean,
-            //     1.0,
-            // );
-            // self.qnet.backward_step(&loss);
-            // let td_errs = Vec::<f32>::from(td_errs);
-            // buffer.update_priority(&ixs, &Some(td_errs));
-            // loss
+        let loss = if let Some(ws) = weight {
+            // Prioritized weighting loss
+            let ws = Tensor::from_vec(ws, &[pred.dims()[0]], &self.device).unwrap();
+            let td_errs = match self.clip_td_err {
+                None => (&pred - &tgt).unwrap().abs().unwrap(),
+                Some((min, max)) => (&pred - &tgt)
+                    .unwrap()
+                    .abs()
+                    .unwrap()
+                    .clamp(min, max)
+                    .unwrap(),
+            };
+            let loss = (ws * &td_errs).unwrap();
+            let zeros = loss.zeros_like().unwrap();
+            let loss = match self.critic_loss {
+                CriticLoss::Mse => mse(&loss, &zeros).unwrap(),
+                CriticLoss::SmoothL1 => smooth_l1_loss(&loss, &zeros).unwrap(),
+            };
+            let td_errs = td_errs.detach().to_vec1::<f32>().unwrap();
+            buffer.update_priority(&ixs, &Some(td_errs));
+            loss
         } else {
             match self.critic_loss {
                 CriticLoss::Mse => mse(&pred, &tgt).unwrap(),
@@ -157,6 +176,24 @@ where
             }
         };
 
+        // Conservative penalty of CQL
+        let loss = match self.cql_alpha {
+            None => loss,
+            Some(cql_alpha) => {
+                let penalty = (q_all.log_sum_exp(D::Minus1).unwrap() - &pred)
+                    .unwrap()
+                    .mean_all()
+                    .unwrap();
+                if self.record_verbose_level >= 2 {
+                    record.insert(
+                        "cql_penalty",
+                        RecordValue::Scalar(penalty.to_scalar::<f32>().unwrap()),
+                    );
+                }
+                (loss + (penalty * cql_alpha).unwrap()).unwrap()
+            }
+        };
+
         // Backprop
         self.qnet.backward_step(&loss).unwrap();
 
@@ -177,6 +214,18 @@ where
             record_ = record_.merge(record);
         }
 
+        if let Some(tau_schedule) = &self.tau_schedule {
+            self.tau = tau_schedule.value(self.n_opts);
+        }
+        insert_scheduled(
+            &mut record_,
+            &[
+                ("tau", self.tau_schedule.as_ref().map(|_| self.tau)),
+                ("lr", self.qnet.scheduled_learning_rate()),
+                ("eps", self.explorer.eps()),
+            ],
+        );
+
         self.soft_update_counter += 1;
         if self.soft_update_counter == self.soft_update_interval {
             self.soft_update_counter = 0;
@@ -200,6 +249,9 @@ where
 {
     /// In evaluation mode, take a random action with probability 0.01.
     fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        if self.train && matches!(self.explorer, DqnExplorer::NoisyNet(_)) {
+            self.qnet.reset_noise();
+        }
         let a = self.qnet.forward(&obs.clone().into()).detach();
         let a = if self.train {
             self.n_samples_act += 1;
@@ -216,6 +268,7 @@ where
                         egreedy.action(&a, &mut self.rng)
                     }
                 }
+                DqnExplorer::NoisyNet(noisy_net) => noisy_net.action(&a),
             }
         } else {
             if self.rng.gen::<f32>() < 0.01 {
@@ -248,6 +301,10 @@ where
             .into();
         let qnet = DqnModel::build(config.model_config.clone(), device.clone()).unwrap();
         let qnet_tgt = DqnModel::build(config.model_config.clone(), device.clone()).unwrap();
+        assert!(
+            !matches!(config.explorer, DqnExplorer::NoisyNet(_)) || qnet.is_noisy(),
+            "DqnExplorer::NoisyNet requires a model with noisy layers"
+        );
         let _ = track(qnet_tgt.get_varmap(), qnet.get_varmap(), 1.0);
 
         Dqn {
@@ -259,6 +316,7 @@ where
             batch_size: config.batch_size,
             discount_factor: config.discount_factor,
             tau: config.tau,
+            tau_schedule: config.tau_schedule,
             train: config.train,
             explorer: config.explorer,
             device,
@@ -266,12 +324,13 @@ where
             _clip_reward: config.clip_reward,
             double_dqn: config.double_dqn,
             clip_td_err: config.clip_td_err,
+            cql_alpha: config.cql_alpha,
             critic_loss: config.critic_loss,
             phantom: PhantomData,
             n_samples_act: 0,
             n_samples_best_act: 0,
             record_verbose_level: config.record_verbose_level,
-            rng: SmallRng::seed_from_u64(42),
+            rng: ChaCha8Rng::seed_from_u64(42),
         }
     }
 }
@@ -290,10 +349,17 @@ where
 {
     fn train(&mut self) {
         self.train = true;
+        if matches!(self.explorer, DqnExplorer::NoisyNet(_)) {
+            self.qnet.reset_noise();
+        }
     }
 
+    /// In evaluation mode, noisy layers use their mean weights.
     fn eval(&mut self) {
         self.train = false;
+        if matches!(self.explorer, DqnExplorer::NoisyNet(_)) {
+            self.qnet.remove_noise();
+        }
     }
 
     fn is_train(&self) -> bool {
@@ -311,7 +377,8 @@ where
             match self.record_verbose_level >= 2 {
                 true => {
                     let record_weights = self.qnet.param_stats();
-                    let record = record.merge(record_weights);
+                    let record_grads = self.qnet.grad_stats().unwrap();
+                    let record = record.merge(record_weights).merge(record_grads);
                     record
                 }
                 false => record,
@@ -350,6 +417,51 @@ where
         Ok(())
     }
 
+    /// Save the state of the agent for resuming training in the given directory.
+    ///
+    /// In addition to the files saved by [`Agent::save_params`], the state of the optimizer
+    /// is saved as `qnet.opt.pt`, and the counters, the explorer and the random number
+    /// generator are saved in `agent.yaml`.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+
+        let opt_path = path.join("qnet.opt.pt");
+        self.qnet.save_opt(&opt_path)?;
+        paths.push(opt_path);
+
+        let state_path = path.join("agent.yaml");
+        let state = DqnState {
+            n_opts: self.n_opts,
+            soft_update_counter: self.soft_update_counter,
+            explorer: self.explorer.clone(),
+            rng_seed: self.rng.get_seed(),
+            rng_word_pos: self.rng.get_word_pos() as u64,
+        };
+        save_yaml(&state_path, &state)?;
+        paths.push(state_path);
+
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+        self.qnet.load_opt(path.join("qnet.opt.pt"))?;
+
+        let state: DqnState = load_yaml(path.join("agent.yaml"))?;
+        self.n_opts = state.n_opts;
+        self.soft_update_counter = state.soft_update_counter;
+        self.explorer = state.explorer;
+        self.rng = ChaCha8Rng::from_seed(state.rng_seed);
+        self.rng.set_word_pos(state.rng_word_pos as u128);
+
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.qnet.set_learning_rate(lr);
+        Ok(())
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
@@ -378,16 +490,135 @@ where
     type ModelInfo = NamedTensors;
 
     fn model_info(&self) -> (usize, Self::ModelInfo) {
-        unimplemented!();
-        // (
-        //     self.n_opts,
-        //     NamedTensors::copy_from(self.qnet.get_var_store()),
-        // )
+        (
+            self.n_opts,
+            NamedTensors::copy_from(self.qnet.get_varmap()).unwrap(),
+        )
+    }
+
+    /// Synchronizes both the action-value network and its target with the given parameters.
+    fn sync_model(&mut self, model_info: &Self::ModelInfo) {
+        model_info.copy_to(self.qnet.get_varmap()).unwrap();
+        model_info.copy_to(self.qnet_tgt.get_varmap()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dqn::{DqnModelConfig, NoisyNet},
+        dueling::{Dueling, DuelingConfig},
+        mlp::{Mlp, MlpConfig},
+        opt::OptimizerConfig,
+        test::{max_abs_diff, transitions, Buffer, TestEnv, TestObs},
+        Activation,
+    };
+    use border_core::{generic_replay_buffer::SimpleReplayBufferConfig, ExperienceBufferBase};
+
+    fn mlp_config() -> MlpConfig {
+        MlpConfig::new(3, vec![8], 8, Activation::ReLU)
+    }
+
+    #[test]
+    fn test_noisy_net() -> Result<()> {
+        let q_config = DuelingConfig::new(mlp_config(), 8, 16, 8).noisy(true);
+        let config = DqnConfig::<Dueling<Mlp>>::default()
+            .model_config(DqnModelConfig::default().q_config(q_config))
+            .explorer(DqnExplorer::NoisyNet(NoisyNet::new()))
+            .device(Device::Cpu);
+        let mut agent = Dqn::<TestEnv, Dueling<Mlp>, Buffer>::build(config);
+        agent.train();
+
+        // The noise is resampled at every step, so greedy actions change
+        let obs = TestObs(Tensor::randn(0f32, 1f32, (32, 3), &Device::Cpu)?);
+        let act = |agent: &mut Dqn<TestEnv, Dueling<Mlp>, Buffer>| -> Result<Vec<i64>> {
+            Ok(agent.sample(&obs).0.to_vec1::<i64>()?)
+        };
+        let act1 = act(&mut agent)?;
+        assert!((0..10).any(|_| act(&mut agent).unwrap() != act1));
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "DqnExplorer::NoisyNet requires a model with noisy layers")]
+    fn test_noisy_net_without_noisy_layers() {
+        let config = DqnConfig::<Mlp>::default()
+            .model_config(DqnModelConfig::default().q_config(mlp_config()))
+            .explorer(DqnExplorer::NoisyNet(NoisyNet::new()))
+            .device(Device::Cpu);
+        let _ = Dqn::<TestEnv, Mlp, Buffer>::build(config);
     }
 
-    fn sync_model(&mut self, _model_info: &Self::ModelInfo) {
-        unimplemented!();
-        // let vs = self.qnet.get_var_store_mut();
-        // model_info.copy_to(vs);
+    #[test]
+    fn test_opt_cql() -> Result<()> {
+        let q_config = MlpConfig::new(3, vec![8], 8, Activation::None);
+        let model_config = DqnModelConfig::default()
+            .q_config(q_config)
+            .opt_config(OptimizerConfig::Adam { lr: 0.01 });
+        let config = DqnConfig::<Mlp>::default()
+            .model_config(model_config)
+            .cql_alpha(Some(10.0))
+            .batch_size(4)
+            .soft_update_interval(2)
+            .tau(1.0)
+            .record_verbose_level(2)
+            .device(Device::Cpu);
+        let mut agent = Dqn::<TestEnv, Mlp, Buffer>::build(config);
+
+        let obs = Tensor::randn(0f32, 1f32, (2, 3), &Device::Cpu)?;
+        let act = Tensor::from_vec(vec![0i64, 5], (2, 1), &Device::Cpu)?;
+        let next_obs = Tensor::randn(0f32, 1f32, (2, 3), &Device::Cpu)?;
+        let mut buffer = Buffer::build(&SimpleReplayBufferConfig::default().capacity(2));
+        buffer.push(transitions(obs.clone(), act, next_obs, vec![1.0, -1.0]))?;
+
+        let mut penalties = vec![];
+        for i in 1..=20 {
+            let record = agent.opt_(&mut buffer);
+            assert!(record.get_scalar("loss")?.is_finite());
+
+            // log-sum-exp of action values is not less than any of them
+            let penalty = record.get_scalar("cql_penalty")?;
+            assert!(penalty >= 0.0);
+            penalties.push(penalty);
+
+            // The target network is synchronized every `soft_update_interval` steps
+            let diff = max_abs_diff(&agent.qnet.forward(&obs), &agent.qnet_tgt.forward(&obs))?;
+            match i % 2 {
+                0 => assert!(diff < 1e-6),
+                _ => assert!(diff > 0.0),
+            }
+        }
+
+        // The penalty pushes down the action values out of the dataset
+        assert!(penalties[19] < penalties[0]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "border-async-trainer")]
+    #[test]
+    fn test_sync_model() -> Result<()> {
+        let config = || {
+            DqnConfig::<Mlp>::default()
+                .model_config(DqnModelConfig::default().q_config(mlp_config()))
+                .device(Device::Cpu)
+        };
+        let agent1 = Dqn::<TestEnv, Mlp, Buffer>::build(config());
+        let mut agent2 = Dqn::<TestEnv, Mlp, Buffer>::build(config());
+        let obs = Tensor::randn(0f32, 1f32, (32, 3), &Device::Cpu)?;
+        let q1 = agent1.qnet.forward(&obs);
+        assert!(max_abs_diff(&q1, &agent2.qnet.forward(&obs))? > 1e-3);
+
+        // Model parameters are sent to actors as serialized messages
+        let (_, model_info) = agent1.model_info();
+        let model_info: NamedTensors = bincode::deserialize(&bincode::serialize(&model_info)?)?;
+        agent2.sync_model(&model_info);
+
+        assert_eq!(max_abs_diff(&q1, &agent2.qnet.forward(&obs))?, 0.0);
+        assert_eq!(max_abs_diff(&q1, &agent2.qnet_tgt.forward(&obs))?, 0.0);
+
+        Ok(())
     }
 }
@@ -9,6 +9,7 @@ use crate::{
     Device,
 };
 use anyhow::Result;
+use border_core::Schedule;
 use candle_core::Tensor;
 use log::info;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
@@ -21,6 +22,11 @@ use std::{
 };
 
 /// Configuration of [`Dqn`](super::Dqn) agent.
+///
+/// Rainbow components other than the distributional loss can be combined here:
+/// `double_dqn`, [`Dueling`](crate::dueling::Dueling) with noisy layers as `Q` together with
+/// [`DqnExplorer::NoisyNet`], and a replay buffer with prioritized experience replay
+/// and multi-step returns.
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub struct DqnConfig<Q>
 where
@@ -33,6 +39,8 @@ where
     pub batch_size: usize,
     pub discount_factor: f64,
     pub tau: f64,
+    #[serde(default)]
+    pub tau_schedule: Option<Schedule>,
     pub train: bool,
     pub explorer: DqnExplorer,
     #[serde(default)]
@@ -40,6 +48,8 @@ where
     #[serde(default)]
     pub double_dqn: bool,
     pub clip_td_err: Option<(f64, f64)>,
+    #[serde(default)]
+    pub cql_alpha: Option<f64>,
     pub device: Option<Device>,
     pub critic_loss: CriticLoss,
     pub record_verbose_level: usize,
@@ -59,11 +69,13 @@ where
             batch_size: self.batch_size,
             discount_factor: self.discount_factor,
             tau: self.tau,
+            tau_schedule: self.tau_schedule.clone(),
             train: self.train,
             explorer: self.explorer.clone(),
             clip_reward: self.clip_reward,
             double_dqn: self.double_dqn,
             clip_td_err: self.clip_td_err,
+            cql_alpha: self.cql_alpha,
             device: self.device.clone(),
             critic_loss: self.critic_loss.clone(),
             record_verbose_level: self.record_verbose_level,
@@ -86,6 +98,7 @@ where
             batch_size: 1,
             discount_factor: 0.99,
             tau: 0.005,
+            tau_schedule: None,
             train: false,
             // replay_burffer_capacity: 100,
             explorer: DqnExplorer::Softmax(Softmax::new()),
@@ -93,6 +106,7 @@ where
             clip_reward: None,
             double_dqn: false,
             clip_td_err: None,
+            cql_alpha: None,
             device: None,
             critic_loss: CriticLoss::Mse,
             record_verbose_level: 0,
@@ -136,6 +150,12 @@ where
         self
     }
 
+    /// Schedule of the soft update coefficient by optimization step, which overrides `tau`.
+    pub fn tau_schedule(mut self, v: Schedule) -> Self {
+        self.tau_schedule = Some(v);
+        self
+    }
+
     /// Explorer.
     pub fn explorer(mut self, v: DqnExplorer) -> Self {
         self.explorer = v;
@@ -173,6 +193,15 @@ where
         self
     }
 
+    /// Weight of the conservative penalty of CQL for offline training.
+    ///
+    /// If `Some`, `logsumexp_a Q(s, a) - Q(s, a_data)` multiplied by the weight is added
+    /// to the loss.
+    pub fn cql_alpha(mut self, v: Option<f64>) -> Self {
+        self.cql_alpha = v;
+        self
+    }
+
     /// Device.
     pub fn device(mut self, device: candle_core::Device) -> Self {
         self.device = Some(device.into());
@@ -1,4 +1,5 @@
 //! Exploration strategies of DQN.
+use border_core::Schedule;
 use candle_core::{shape::D, DType, Tensor};
 use candle_nn::ops::softmax;
 use rand::{distributions::WeightedIndex, Rng};
@@ -12,6 +13,25 @@ pub enum DqnExplorer {
 
     /// Epsilon-greedy action selection.
     EpsilonGreedy(EpsilonGreedy),
+
+    /// Greedy action selection with noisy networks.
+    ///
+    /// The noise of the action-value function is resampled at every step in training mode.
+    /// The model must have noisy layers, e.g., [`Dueling`](crate::dueling::Dueling) with
+    /// `noisy = true`; otherwise, building an agent with this explorer panics, as
+    /// [`SubModel1::reset_noise`](crate::model::SubModel1::reset_noise) does nothing and
+    /// the agent would act greedily without exploration.
+    NoisyNet(NoisyNet),
+}
+
+impl DqnExplorer {
+    /// Returns the current epsilon if the explorer is [`EpsilonGreedy`].
+    pub fn eps(&self) -> Option<f64> {
+        match self {
+            Self::EpsilonGreedy(egreedy) => Some(egreedy.eps()),
+            _ => None,
+        }
+    }
 }
 
 /// Softmax explorer for DQN.
@@ -41,12 +61,18 @@ impl Softmax {
 }
 
 /// Epsilon-greedy explorer for DQN.
+///
+/// Epsilon decreases linearly from `eps_start` to `eps_final` in `final_step` steps,
+/// unless `eps_schedule` is given. Steps are counted by the number of calls of
+/// [`EpsilonGreedy::action()`] or [`EpsilonGreedy::action_with_best()`].
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct EpsilonGreedy {
     pub n_opts: usize,
     pub eps_start: f64,
     pub eps_final: f64,
     pub final_step: usize,
+    #[serde(default)]
+    pub eps_schedule: Option<Schedule>,
 }
 
 #[allow(clippy::new_without_default)]
@@ -58,6 +84,7 @@ impl EpsilonGreedy {
             eps_start: 1.0,
             eps_final: 0.02,
             final_step: 100_000,
+            eps_schedule: None,
         }
     }
 
@@ -70,15 +97,26 @@ impl EpsilonGreedy {
             eps_start: 1.0,
             eps_final: 0.02,
             final_step,
+            eps_schedule: None,
         })
     }
 
+    /// Returns the current epsilon.
+    pub fn eps(&self) -> f64 {
+        match &self.eps_schedule {
+            Some(eps_schedule) => eps_schedule.value(self.n_opts),
+            None => {
+                let d = (self.eps_start - self.eps_final) / (self.final_step as f64);
+                (self.eps_start - d * self.n_opts as f64).max(self.eps_final)
+            }
+        }
+    }
+
     /// Takes an action based on action values, returns i64 tensor.
     ///
     /// * `a` - action values.
     pub fn action(&mut self, a: &Tensor, rng: &mut impl Rng) -> Tensor {
-        let d = (self.eps_start - self.eps_final) / (self.final_step as f64);
-        let eps = (self.eps_start - d * self.n_opts as f64).max(self.eps_final);
+        let eps = self.eps();
         let r = rng.gen::<f32>();
         let is_random = r < eps as f32;
         self.n_opts += 1;
@@ -104,8 +142,7 @@ impl EpsilonGreedy {
     ///
     /// * `a` - action values.
     pub fn action_with_best(&mut self, a: &Tensor, rng: &mut impl Rng) -> (Tensor, bool) {
-        let d = (self.eps_start - self.eps_final) / (self.final_step as f64);
-        let eps = (self.eps_start - d * self.n_opts as f64).max(self.eps_final);
+        let eps = self.eps();
         let r = rng.gen::<f32>();
         let is_random = r < eps as f32;
         self.n_opts += 1;
@@ -145,4 +182,33 @@ impl EpsilonGreedy {
         s.eps_start = v;
         s
     }
+
+    /// Set the schedule of epsilon, which overrides the linear decay.
+    pub fn eps_schedule(self, v: Schedule) -> Self {
+        let mut s = self;
+        s.eps_schedule = Some(v);
+        s
+    }
+}
+
+/// Noisy-net explorer for DQN.
+///
+/// Exploration comes from the noise of the parameters of the action-value function,
+/// hence actions are taken greedily.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct NoisyNet {}
+
+#[allow(clippy::new_without_default)]
+impl NoisyNet {
+    /// Constructs noisy-net explorer.
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Takes the greedy action based on action values, returns i64 tensor.
+    ///
+    /// * `a` - action values.
+    pub fn action(&mut self, a: &Tensor) -> Tensor {
+        a.argmax(D::Minus1).unwrap().to_dtype(DType::I64).unwrap()
+    }
 }
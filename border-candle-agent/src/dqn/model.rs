@@ -165,16 +165,23 @@ where
         self.q.forward(obs)
     }
 
+    /// Resamples the noise of noisy layers in the action-value function.
+    pub fn reset_noise(&mut self) {
+        self.q.reset_noise();
+    }
+
+    /// Removes the noise of noisy layers in the action-value function.
+    pub fn remove_noise(&mut self) {
+        self.q.remove_noise();
+    }
+
+    /// Returns `true` if the action-value function has noisy layers.
+    pub fn is_noisy(&self) -> bool {
+        self.q.is_noisy()
+    }
+
+    /// Applies a backward step, where gradients are clipped if configured in the optimizer.
     pub fn backward_step(&mut self, loss: &Tensor) -> Result<()> {
-        // Consider to use gradient clipping, below code
-        // let mut grads = loss.backward()?;
-        // for (_, var) in self.varmap.data().lock().unwrap().iter() {
-        //     let g1 = grads.get(var).unwrap();
-        //     let g2 = g1.clamp(-1.0, 1.0)?;
-        //     let _ = grads.remove(&var).unwrap();
-        //     let _ = grads.insert(&var, g2);
-        // }
-        // self.opt.step(&grads)
         self.opt.backward_step(loss)
     }
 
@@ -194,9 +201,38 @@ where
         Ok(())
     }
 
+    /// Saves the state of the optimizer.
+    pub fn save_opt<T: AsRef<Path>>(&self, path: T) -> Result<()> {
+        self.opt.save(&path, &self.varmap)?;
+        info!("Save optimizer of dqnmodel to {:?}", path.as_ref());
+        Ok(())
+    }
+
+    /// Loads the state of the optimizer.
+    pub fn load_opt<T: AsRef<Path>>(&mut self, path: T) -> Result<()> {
+        self.opt.load(&path, &self.varmap)?;
+        info!("Load optimizer of dqnmodel from {:?}", path.as_ref());
+        Ok(())
+    }
+
+    /// Sets the learning rate of the optimizer.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.opt.set_learning_rate(lr);
+    }
+
+    /// Returns the learning rate used in the last update if it is scheduled.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        self.opt.scheduled_learning_rate()
+    }
+
     pub fn param_stats(&self) -> Record {
         crate::util::param_stats(&self.varmap)
     }
+
+    /// Returns the norms of gradients in the last update, before clipping.
+    pub fn grad_stats(&self) -> Result<Record> {
+        self.opt.grad_stats(&self.varmap)
+    }
 }
 
 impl<Q> Clone for DqnModel<Q>
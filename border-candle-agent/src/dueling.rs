@@ -0,0 +1,10 @@
+//! Dueling network architecture.
+//!
+//! [`Dueling`] puts value and advantage streams on top of a feature extractor like
+//! [`Mlp`](crate::mlp::Mlp) or [`AtariCnn`](crate::atari_cnn::AtariCnn) with `skip_linear`.
+//! The linear layers of the streams can be replaced with
+//! [`NoisyLinear`](crate::util::NoisyLinear) for noisy-net exploration.
+mod base;
+mod config;
+pub use base::Dueling;
+pub use config::DuelingConfig;
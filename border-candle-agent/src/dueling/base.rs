@@ -0,0 +1,153 @@
+use super::DuelingConfig;
+use crate::{model::SubModel1, util::NoisyLinear};
+use anyhow::Result;
+use candle_core::{Tensor, D};
+use candle_nn::{linear, Linear, Module, VarBuilder};
+
+enum Layer {
+    Linear(Linear),
+    Noisy(NoisyLinear),
+}
+
+impl Layer {
+    fn new(in_dim: i64, out_dim: i64, noisy: Option<f64>, vb: VarBuilder) -> Result<Self> {
+        Ok(match noisy {
+            None => Self::Linear(linear(in_dim as _, out_dim as _, vb)?),
+            Some(sigma_0) => Self::Noisy(NoisyLinear::new(in_dim as _, out_dim as _, sigma_0, vb)?),
+        })
+    }
+
+    fn forward(&self, xs: &Tensor) -> Tensor {
+        match self {
+            Self::Linear(l) => l.forward(xs),
+            Self::Noisy(l) => l.forward(xs),
+        }
+        .unwrap()
+    }
+
+    fn reset_noise(&mut self) {
+        if let Self::Noisy(l) = self {
+            l.reset_noise().unwrap();
+        }
+    }
+
+    fn remove_noise(&mut self) {
+        if let Self::Noisy(l) = self {
+            l.remove_noise().unwrap();
+        }
+    }
+}
+
+/// Dueling network, which outputs `Q(s, a) = V(s) + A(s, a) - mean_a' A(s, a')`.
+///
+/// `F` is the feature extractor, of which output is fed into the value and advantage
+/// streams. Each stream consists of two linear layers with ReLU activation in between.
+pub struct Dueling<F>
+where
+    F: SubModel1<Output = Tensor>,
+{
+    feature: F,
+    value: [Layer; 2],
+    advantage: [Layer; 2],
+}
+
+impl<F> SubModel1 for Dueling<F>
+where
+    F: SubModel1<Output = Tensor>,
+{
+    type Config = DuelingConfig<F::Config>;
+    type Input = F::Input;
+    type Output = Tensor;
+
+    fn forward(&self, x: &Self::Input) -> Tensor {
+        let xs = self.feature.forward(x);
+        let stream =
+            |layers: &[Layer; 2]| layers[1].forward(&layers[0].forward(&xs).relu().unwrap());
+        let v = stream(&self.value);
+        let a = stream(&self.advantage);
+        let a = a
+            .broadcast_sub(&a.mean_keepdim(D::Minus1).unwrap())
+            .unwrap();
+        a.broadcast_add(&v).unwrap()
+    }
+
+    fn build(vb: VarBuilder, config: Self::Config) -> Self {
+        let noisy = match config.noisy {
+            true => Some(config.sigma_0),
+            false => None,
+        };
+        let (f, h) = (config.feature_dim, config.hidden_dim);
+        let vb_v = vb.pp("value");
+        let vb_a = vb.pp("advantage");
+        let value = [
+            Layer::new(f, h, noisy, vb_v.pp("l0")).unwrap(),
+            Layer::new(h, 1, noisy, vb_v.pp("l1")).unwrap(),
+        ];
+        let advantage = [
+            Layer::new(f, h, noisy, vb_a.pp("l0")).unwrap(),
+            Layer::new(h, config.out_dim, noisy, vb_a.pp("l1")).unwrap(),
+        ];
+        let feature = F::build(vb.pp("feature"), config.feature_config);
+
+        Self {
+            feature,
+            value,
+            advantage,
+        }
+    }
+
+    fn reset_noise(&mut self) {
+        self.feature.reset_noise();
+        self.value.iter_mut().for_each(Layer::reset_noise);
+        self.advantage.iter_mut().for_each(Layer::reset_noise);
+    }
+
+    fn remove_noise(&mut self) {
+        self.feature.remove_noise();
+        self.value.iter_mut().for_each(Layer::remove_noise);
+        self.advantage.iter_mut().for_each(Layer::remove_noise);
+    }
+
+    fn is_noisy(&self) -> bool {
+        self.feature.is_noisy() || matches!(self.value[0], Layer::Noisy(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mlp::{Mlp, MlpConfig},
+        Activation,
+    };
+    use candle_core::{DType, Device};
+    use candle_nn::VarMap;
+
+    #[test]
+    fn test_dueling_mlp() -> Result<()> {
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        let config = DuelingConfig::new(MlpConfig::new(3, vec![8], 8, Activation::ReLU), 8, 16, 4)
+            .noisy(true);
+        let mut model = Dueling::<Mlp>::build(vb, config);
+        assert!(model.is_noisy());
+        let xs = Tensor::randn(0f32, 1f32, (5, 3), &Device::Cpu)?;
+
+        let q1 = model.forward(&xs);
+        assert_eq!(q1.dims(), [5, 4]);
+
+        model.reset_noise();
+        let q2 = model.forward(&xs);
+        assert!((&q1 - &q2)?.abs()?.max_all()?.to_scalar::<f32>()? > 1e-6);
+
+        // Without noise, the output is deterministic
+        model.remove_noise();
+        let q3 = model.forward(&xs);
+        model.reset_noise();
+        model.remove_noise();
+        let q4 = model.forward(&xs);
+        assert!((&q3 - &q4)?.abs()?.max_all()?.to_scalar::<f32>()? < 1e-6);
+
+        Ok(())
+    }
+}
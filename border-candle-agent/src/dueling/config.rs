@@ -0,0 +1,60 @@
+use crate::util::OutDim;
+use serde::{Deserialize, Serialize};
+
+fn default_sigma_0() -> f64 {
+    0.5
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+/// Configuration of [`Dueling`](super::Dueling).
+///
+/// `feature_dim` must be equal to the output dimension of the feature extractor,
+/// e.g., 3136 for [`AtariCnn`](crate::atari_cnn::AtariCnn) with `skip_linear`.
+/// `out_dim` is the number of actions.
+pub struct DuelingConfig<C> {
+    pub feature_config: C,
+    pub feature_dim: i64,
+    pub hidden_dim: i64,
+    pub out_dim: i64,
+    #[serde(default)]
+    pub noisy: bool,
+    #[serde(default = "default_sigma_0")]
+    pub sigma_0: f64,
+}
+
+impl<C> DuelingConfig<C> {
+    /// Constructs [`DuelingConfig`].
+    pub fn new(feature_config: C, feature_dim: i64, hidden_dim: i64, out_dim: i64) -> Self {
+        Self {
+            feature_config,
+            feature_dim,
+            hidden_dim,
+            out_dim,
+            noisy: false,
+            sigma_0: default_sigma_0(),
+        }
+    }
+
+    /// If `true`, [`NoisyLinear`](crate::util::NoisyLinear) is used in the value and
+    /// advantage streams.
+    pub fn noisy(mut self, v: bool) -> Self {
+        self.noisy = v;
+        self
+    }
+
+    /// Sets the initial scale of the noise of noisy layers.
+    pub fn sigma_0(mut self, v: f64) -> Self {
+        self.sigma_0 = v;
+        self
+    }
+}
+
+impl<C> OutDim for DuelingConfig<C> {
+    fn get_out_dim(&self) -> i64 {
+        self.out_dim
+    }
+
+    fn set_out_dim(&mut self, v: i64) {
+        self.out_dim = v;
+    }
+}
@@ -2,8 +2,9 @@ use super::{IqlConfig, Value};
 use crate::{
     model::{SubModel1, SubModel2},
     util::{
-        actor::GaussianActor, asymmetric_l2_loss, critic::MultiCritic, gamma_not_done, reward,
-        smooth_l1_loss, CriticLoss, OutDim,
+        actor::GaussianActor, asymmetric_l2_loss, critic::MultiCritic, gamma_not_done,
+        insert_scheduled, load_yaml, named_grad_stats, reward, save_yaml, smooth_l1_loss,
+        CriticLoss, OutDim,
     },
 };
 use anyhow::Result;
@@ -13,7 +14,7 @@ use border_core::{
 };
 use candle_core::{Device, Tensor, D};
 use candle_nn::{loss::mse, ops::softmax};
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     fs,
     marker::PhantomData,
@@ -50,9 +51,16 @@ where
     device: Device,
     adv_softmax: bool,
     n_opts: usize,
+    record_verbose_level: usize,
     phantom: PhantomData<(E, R, O, A)>,
 }
 
+/// Training state of [`Iql`] saved in checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct IqlState {
+    n_opts: usize,
+}
+
 impl<E, Q, P, V, R, O, A> Iql<E, Q, P, V, R, O, A>
 where
     E: Env,
@@ -154,6 +162,13 @@ where
         Ok(loss.to_scalar::<f32>()?)
     }
 
+    /// Returns the norms of gradients of the models in the last update, before clipping.
+    fn grad_stats(&self) -> Result<Record> {
+        let record = named_grad_stats(self.actor.grad_stats()?, "actor");
+        let record = record.merge(named_grad_stats(self.critic.grad_stats()?, "critic"));
+        Ok(record.merge(named_grad_stats(self.value.grad_stats()?, "value")))
+    }
+
     fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
         let mut loss_value = 0f32;
         let mut loss_critic = 0f32;
@@ -178,12 +193,22 @@ where
         loss_critic /= self.n_updates_per_opt as f32;
         loss_actor /= self.n_updates_per_opt as f32;
 
-        let record = Record::from_slice(&[
+        let mut record = Record::from_slice(&[
             ("loss_value", RecordValue::Scalar(loss_value)),
             ("loss_critic", RecordValue::Scalar(loss_critic)),
             ("loss_actor", RecordValue::Scalar(loss_actor)),
         ]);
 
+        insert_scheduled(
+            &mut record,
+            &[
+                ("lr_actor", self.actor.scheduled_learning_rate()),
+                ("lr_critic", self.critic.scheduled_learning_rate()),
+                ("lr_value", self.value.scheduled_learning_rate()),
+                ("tau", self.critic.scheduled_tau()),
+            ],
+        );
+
         Ok(record)
     }
 }
@@ -245,6 +270,7 @@ where
             critic_loss: config.critic_loss,
             exp_adv_max: config.exp_adv_max,
             n_opts: 0,
+            record_verbose_level: config.record_verbose_level,
             train: false,
             device: device.into(),
             adv_softmax: config.adv_softmax,
@@ -286,7 +312,12 @@ where
     }
 
     fn opt_with_record(&mut self, buffer: &mut R) -> Record {
-        self.opt_(buffer).expect("Failed in Iql::opt_()")
+        let record = self.opt_(buffer).expect("Failed in Iql::opt_()");
+
+        match self.record_verbose_level >= 2 {
+            true => record.merge(self.grad_stats().expect("Failed in Iql::grad_stats()")),
+            false => record,
+        }
     }
 
     fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
@@ -308,6 +339,47 @@ where
         Ok(())
     }
 
+    /// Save the state of the agent for resuming training in the given directory.
+    ///
+    /// In addition to the files saved by [`Agent::save_params`], the states of the optimizers
+    /// and the number of optimization steps (`agent.yaml`) are saved.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+        paths.push(self.actor.save_opt(path.join("actor"))?);
+        paths.push(self.critic.save_opt(path.join("critic"))?);
+        paths.push(self.value.save_opt(path.join("value"))?);
+
+        let state_path = path.join("agent.yaml");
+        save_yaml(
+            &state_path,
+            &IqlState {
+                n_opts: self.n_opts,
+            },
+        )?;
+        paths.push(state_path);
+
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+        self.actor.load_opt(path.join("actor"))?;
+        self.critic.load_opt(path.join("critic"))?;
+        self.value.load_opt(path.join("value"))?;
+
+        let state: IqlState = load_yaml(path.join("agent.yaml"))?;
+        self.n_opts = state.n_opts;
+
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.actor.set_learning_rate(lr);
+        self.critic.set_learning_rate(lr);
+        self.value.set_learning_rate(lr);
+        Ok(())
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
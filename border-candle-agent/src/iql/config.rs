@@ -65,6 +65,12 @@ where
     /// Device used for the actor and critic models (e.g., CPU or GPU).
     pub device: Option<Device>,
 
+    /// Verbose level of records.
+    ///
+    /// If it is 2 or more, the norms of gradients are recorded in optimization steps.
+    #[serde(default)]
+    pub record_verbose_level: usize,
+
     /// Maximum of exponent of advantage.
     pub exp_adv_max: f64,
 }
@@ -92,6 +98,7 @@ where
             adv_softmax: self.adv_softmax,
             critic_loss: self.critic_loss.clone(),
             device: self.device.clone(),
+            record_verbose_level: self.record_verbose_level,
             exp_adv_max: self.exp_adv_max,
         }
     }
@@ -120,6 +127,7 @@ where
             adv_softmax: false,
             critic_loss: CriticLoss::Mse,
             device: None,
+            record_verbose_level: 0,
             exp_adv_max: 100.0,
         }
     }
@@ -196,6 +204,12 @@ where
         self
     }
 
+    /// Sets verbose level.
+    pub fn record_verbose_level(mut self, v: usize) -> Self {
+        self.record_verbose_level = v;
+        self
+    }
+
     /// If true, advantage weights are calculated with softmax within each mini-batch.
     pub fn adv_softmax(mut self, b: bool) -> Self {
         self.adv_softmax = b;
@@ -0,0 +1,7 @@
+//! IQN agent.
+mod base;
+mod config;
+mod model;
+pub use base::Iqn;
+pub use config::IqnConfig;
+pub use model::{average, IqnModel, IqnModelConfig, IqnSample};
@@ -0,0 +1,443 @@
+//! IQN agent implemented with candle.
+use super::{average, IqnConfig, IqnModel, IqnSample};
+use crate::{
+    dqn::DqnExplorer,
+    model::SubModel1,
+    util::{
+        gamma_n_not_done, insert_scheduled, load_yaml, quantile_huber_loss, save_yaml, track,
+        OutDim,
+    },
+};
+use anyhow::Result;
+use border_core::{
+    record::{Record, RecordValue},
+    Agent, Configurable, Env, Policy, ReplayBufferBase, Schedule, TransitionBatch,
+};
+use candle_core::{shape::D, DType, Device, Tensor};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+#[allow(clippy::upper_case_acronyms)]
+/// IQN agent implemented with candle.
+///
+/// The type parameter `F` is a feature extractor, which takes `F::Input` and returns
+/// feature vectors. `M` maps the feature vectors merged with cosine-embedded percent
+/// points to the quantiles of action values.
+pub struct Iqn<E, F, M, R>
+where
+    F: SubModel1<Output = Tensor>,
+    M: SubModel1<Input = Tensor, Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+    M::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    soft_update_interval: usize,
+    soft_update_counter: usize,
+    n_updates_per_opt: usize,
+    batch_size: usize,
+    iqn: IqnModel<F, M>,
+    iqn_tgt: IqnModel<F, M>,
+    train: bool,
+    phantom: PhantomData<(E, R)>,
+    discount_factor: f64,
+    tau: f64,
+    tau_schedule: Option<Schedule>,
+    sample_percents_pred: IqnSample,
+    sample_percents_tgt: IqnSample,
+    sample_percents_act: IqnSample,
+    explorer: DqnExplorer,
+    device: Device,
+    n_opts: usize,
+    n_samples_act: usize,
+    n_samples_best_act: usize,
+    record_verbose_level: usize,
+    rng: ChaCha8Rng,
+}
+
+/// Training state of [`Iqn`] saved in checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct IqnState {
+    n_opts: usize,
+    soft_update_counter: usize,
+    explorer: DqnExplorer,
+    rng_seed: [u8; 32],
+    rng_word_pos: u64,
+}
+
+impl<E, F, M, R> Iqn<E, F, M, R>
+where
+    E: Env,
+    F: SubModel1<Output = Tensor>,
+    M: SubModel1<Input = Tensor, Output = Tensor>,
+    R: ReplayBufferBase,
+    F::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+    M::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<F::Input>,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
+{
+    fn update_critic(&mut self, buffer: &mut R) -> Result<Record> {
+        let mut record = Record::empty();
+        if matches!(self.explorer, DqnExplorer::NoisyNet(_)) {
+            self.iqn.reset_noise();
+            self.iqn_tgt.reset_noise();
+        }
+        let batch = buffer.batch(self.batch_size)?;
+        let n_step = batch.n_step().map(|n_step| n_step.to_vec());
+        let (obs, act, next_obs, reward, is_terminated, _is_truncated, ixs, weight) =
+            batch.unpack();
+        let obs = obs.into();
+        let act = act.into().to_device(&self.device)?;
+        let next_obs = next_obs.into();
+        let batch_size = reward.len();
+        let reward = Tensor::from_slice(&reward[..], (batch_size, 1), &self.device)?;
+        let gamma_not_done = gamma_n_not_done(
+            self.discount_factor as f32,
+            is_terminated,
+            n_step.as_deref(),
+            &self.device,
+        )?
+        .unsqueeze(1)?;
+        let n_percent_points_pred = self.sample_percents_pred.n_percent_points();
+        let n_percent_points_tgt = self.sample_percents_tgt.n_percent_points();
+
+        // Predictions of z(s, a), where a is from minibatch, [batch_size, n_percent_points]
+        let tau = self.sample_percents_pred.sample(batch_size, &self.device)?;
+        let pred = {
+            let z = self.iqn.forward(&obs, &tau)?;
+            let a = act
+                .to_dtype(DType::I64)?
+                .reshape((batch_size, 1, 1))?
+                .broadcast_as((batch_size, n_percent_points_pred, 1))?
+                .contiguous()?;
+            z.gather(&a, D::Minus1)?.squeeze(D::Minus1)?
+        };
+
+        // Target values with argmax_a q(s', a), [batch_size, n_percent_points]
+        let tgt = {
+            let tau = self.sample_percents_tgt.sample(batch_size, &self.device)?;
+            let z = self.iqn_tgt.forward(&next_obs, &tau)?;
+            let a = z
+                .mean(1)?
+                .argmax(D::Minus1)?
+                .reshape((batch_size, 1, 1))?
+                .broadcast_as((batch_size, n_percent_points_tgt, 1))?
+                .contiguous()?;
+            let z = z.gather(&a, D::Minus1)?.squeeze(D::Minus1)?;
+            reward.broadcast_add(&gamma_not_done.broadcast_mul(&z)?)?
+        }
+        .detach();
+
+        if self.record_verbose_level >= 2 {
+            record.insert(
+                "pred_mean",
+                RecordValue::Scalar(pred.mean_all()?.to_vec0::<f32>()?),
+            );
+            record.insert(
+                "tgt_mean",
+                RecordValue::Scalar(tgt.mean_all()?.to_vec0::<f32>()?),
+            );
+        }
+
+        // Quantile huber loss of each sample, [batch_size]
+        let losses = {
+            let diff = tgt.unsqueeze(1)?.broadcast_sub(&pred.unsqueeze(2)?)?;
+            debug_assert_eq!(
+                diff.dims(),
+                &[batch_size, n_percent_points_pred, n_percent_points_tgt]
+            );
+            quantile_huber_loss(&diff, &tau.unsqueeze(2)?)?
+                .mean(2)?
+                .sum(1)?
+        };
+
+        let loss = match weight {
+            Some(ws) => {
+                let ws = Tensor::from_vec(ws, (batch_size,), &self.device)?;
+                let td_errs = losses.detach().to_vec1::<f32>()?;
+                buffer.update_priority(&ixs, &Some(td_errs));
+                (losses * ws)?.mean_all()?
+            }
+            None => losses.mean_all()?,
+        };
+
+        // Backprop
+        self.iqn.backward_step(&loss)?;
+
+        record.insert("loss", RecordValue::Scalar(loss.to_scalar::<f32>()?));
+
+        Ok(record)
+    }
+
+    fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
+        let mut record_ = Record::empty();
+
+        for _ in 0..self.n_updates_per_opt {
+            let record = self.update_critic(buffer)?;
+            record_ = record_.merge(record);
+        }
+
+        if let Some(tau_schedule) = &self.tau_schedule {
+            self.tau = tau_schedule.value(self.n_opts);
+        }
+        insert_scheduled(
+            &mut record_,
+            &[
+                ("tau", self.tau_schedule.as_ref().map(|_| self.tau)),
+                ("lr", self.iqn.scheduled_learning_rate()),
+                ("eps", self.explorer.eps()),
+            ],
+        );
+
+        self.soft_update_counter += 1;
+        if self.soft_update_counter == self.soft_update_interval {
+            self.soft_update_counter = 0;
+            track(self.iqn_tgt.get_varmap(), self.iqn.get_varmap(), self.tau)?;
+        }
+
+        self.n_opts += 1;
+
+        Ok(record_)
+    }
+}
+
+impl<E, F, M, R> Policy<E> for Iqn<E, F, M, R>
+where
+    E: Env,
+    F: SubModel1<Output = Tensor>,
+    M: SubModel1<Input = Tensor, Output = Tensor>,
+    E::Obs: Into<F::Input>,
+    E::Act: From<Tensor>,
+    F::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+    M::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// In evaluation mode, take a random action with probability 0.01.
+    fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        if self.train && matches!(self.explorer, DqnExplorer::NoisyNet(_)) {
+            self.iqn.reset_noise();
+        }
+        // Do not support vectorized env
+        let a = average(
+            1,
+            &obs.clone().into(),
+            &self.iqn,
+            &self.sample_percents_act,
+            &self.device,
+        )
+        .unwrap()
+        .detach();
+        let a = if self.train {
+            self.n_samples_act += 1;
+            match &mut self.explorer {
+                DqnExplorer::Softmax(softmax) => softmax.action(&a, &mut self.rng),
+                DqnExplorer::EpsilonGreedy(egreedy) => {
+                    if self.record_verbose_level >= 2 {
+                        let (act, best) = egreedy.action_with_best(&a, &mut self.rng);
+                        if best {
+                            self.n_samples_best_act += 1;
+                        }
+                        act
+                    } else {
+                        egreedy.action(&a, &mut self.rng)
+                    }
+                }
+                DqnExplorer::NoisyNet(noisy_net) => noisy_net.action(&a),
+            }
+        } else if self.rng.gen::<f32>() < 0.01 {
+            let n_actions = a.dims()[1] as i64;
+            let a: i64 = self.rng.gen_range(0..n_actions);
+            Tensor::try_from(vec![a]).unwrap()
+        } else {
+            a.argmax(D::Minus1).unwrap().to_dtype(DType::I64).unwrap()
+        };
+        a.into()
+    }
+}
+
+impl<E, F, M, R> Configurable for Iqn<E, F, M, R>
+where
+    E: Env,
+    F: SubModel1<Output = Tensor>,
+    M: SubModel1<Input = Tensor, Output = Tensor>,
+    E::Obs: Into<F::Input>,
+    E::Act: From<Tensor>,
+    F::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+    M::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    type Config = IqnConfig<F, M>;
+
+    /// Constructs [`Iqn`] agent.
+    fn build(config: Self::Config) -> Self {
+        let device: Device = config
+            .device
+            .expect("No device is given for IQN agent")
+            .into();
+        let iqn = IqnModel::build(config.model_config.clone(), device.clone()).unwrap();
+        let iqn_tgt = IqnModel::build(config.model_config, device.clone()).unwrap();
+        assert!(
+            !matches!(config.explorer, DqnExplorer::NoisyNet(_)) || iqn.is_noisy(),
+            "DqnExplorer::NoisyNet requires a model with noisy layers"
+        );
+        track(iqn_tgt.get_varmap(), iqn.get_varmap(), 1.0).unwrap();
+
+        Iqn {
+            iqn,
+            iqn_tgt,
+            soft_update_interval: config.soft_update_interval,
+            soft_update_counter: 0,
+            n_updates_per_opt: config.n_updates_per_opt,
+            batch_size: config.batch_size,
+            discount_factor: config.discount_factor,
+            tau: config.tau,
+            tau_schedule: config.tau_schedule,
+            sample_percents_pred: config.sample_percents_pred,
+            sample_percents_tgt: config.sample_percents_tgt,
+            sample_percents_act: config.sample_percents_act,
+            train: config.train,
+            explorer: config.explorer,
+            device,
+            n_opts: 0,
+            phantom: PhantomData,
+            n_samples_act: 0,
+            n_samples_best_act: 0,
+            record_verbose_level: config.record_verbose_level,
+            rng: ChaCha8Rng::seed_from_u64(42),
+        }
+    }
+}
+
+impl<E, F, M, R> Agent<E, R> for Iqn<E, F, M, R>
+where
+    E: Env + 'static,
+    F: SubModel1<Output = Tensor> + 'static,
+    M: SubModel1<Input = Tensor, Output = Tensor> + 'static,
+    R: ReplayBufferBase + 'static,
+    E::Obs: Into<F::Input>,
+    E::Act: From<Tensor>,
+    F::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+    M::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<F::Input>,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
+{
+    fn train(&mut self) {
+        self.train = true;
+        if matches!(self.explorer, DqnExplorer::NoisyNet(_)) {
+            self.iqn.reset_noise();
+        }
+    }
+
+    /// In evaluation mode, noisy layers use their mean weights.
+    fn eval(&mut self) {
+        self.train = false;
+        if matches!(self.explorer, DqnExplorer::NoisyNet(_)) {
+            self.iqn.remove_noise();
+        }
+    }
+
+    fn is_train(&self) -> bool {
+        self.train
+    }
+
+    fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        let mut record = {
+            let record = self.opt_(buffer).expect("Failed in Iqn::opt_()");
+
+            match self.record_verbose_level >= 2 {
+                true => record
+                    .merge(self.iqn.param_stats())
+                    .merge(self.iqn.grad_stats().unwrap()),
+                false => record,
+            }
+        };
+
+        // Best action ratio for epsilon greedy
+        let ratio = match self.n_samples_act == 0 {
+            true => 0f32,
+            false => self.n_samples_best_act as f32 / self.n_samples_act as f32,
+        };
+        record.insert("ratio_best_act", RecordValue::Scalar(ratio));
+        self.n_samples_act = 0;
+        self.n_samples_best_act = 0;
+
+        record
+    }
+
+    /// Save model parameters in the given directory.
+    ///
+    /// The parameters of the model are saved as `iqn.pt`.
+    /// The parameters of the target model are saved as `iqn_tgt.pt`.
+    fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(path)?;
+        let path1 = path.join("iqn.pt");
+        let path2 = path.join("iqn_tgt.pt");
+        self.iqn.save(&path1)?;
+        self.iqn_tgt.save(&path2)?;
+        Ok(vec![path1, path2])
+    }
+
+    fn load_params(&mut self, path: &Path) -> Result<()> {
+        self.iqn.load(path.join("iqn.pt").as_path())?;
+        self.iqn_tgt.load(path.join("iqn_tgt.pt").as_path())?;
+        Ok(())
+    }
+
+    /// Save the state of the agent for resuming training in the given directory.
+    ///
+    /// In addition to the files saved by [`Agent::save_params`], the state of the optimizer
+    /// is saved as `iqn.opt.pt`, and the counters, the explorer and the random number
+    /// generator are saved in `agent.yaml`.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+
+        let opt_path = path.join("iqn.opt.pt");
+        self.iqn.save_opt(&opt_path)?;
+        paths.push(opt_path);
+
+        let state_path = path.join("agent.yaml");
+        let state = IqnState {
+            n_opts: self.n_opts,
+            soft_update_counter: self.soft_update_counter,
+            explorer: self.explorer.clone(),
+            rng_seed: self.rng.get_seed(),
+            rng_word_pos: self.rng.get_word_pos() as u64,
+        };
+        save_yaml(&state_path, &state)?;
+        paths.push(state_path);
+
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+        self.iqn.load_opt(path.join("iqn.opt.pt"))?;
+
+        let state: IqnState = load_yaml(path.join("agent.yaml"))?;
+        self.n_opts = state.n_opts;
+        self.soft_update_counter = state.soft_update_counter;
+        self.explorer = state.explorer;
+        self.rng = ChaCha8Rng::from_seed(state.rng_seed);
+        self.rng.set_word_pos(state.rng_word_pos as u128);
+
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.iqn.set_learning_rate(lr);
+        Ok(())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
+}
@@ -0,0 +1,432 @@
+//! IQN model.
+use crate::{
+    model::SubModel1,
+    opt::{Optimizer, OptimizerConfig},
+    util::OutDim,
+};
+use anyhow::{Context, Result};
+use border_core::record::Record;
+use candle_core::{DType, Device, Tensor, D};
+use candle_nn::{linear, Linear, Module, VarBuilder, VarMap};
+use log::info;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    f64::consts::PI,
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+/// Configuration of [`IqnModel`].
+///
+/// The type parameter `F` represents a configuration struct of a feature extractor.
+/// The type parameter `M` represents a configuration struct of a model for merging
+/// cosine-embedded percent points and feature vectors.
+pub struct IqnModelConfig<F, M>
+where
+    M: OutDim,
+{
+    /// Dimension of feature vector.
+    pub feature_dim: i64,
+
+    /// Embedding dimension.
+    pub embed_dim: i64,
+
+    /// Configuration of feature extractor.
+    pub f_config: Option<F>,
+
+    /// Configuration of a model for merging percentils and feature vectors.
+    pub m_config: Option<M>,
+
+    /// Configuration of optimizer.
+    #[serde(default)]
+    pub opt_config: OptimizerConfig,
+}
+
+impl<F, M> Default for IqnModelConfig<F, M>
+where
+    M: OutDim,
+{
+    fn default() -> Self {
+        Self {
+            feature_dim: 0,
+            embed_dim: 64,
+            f_config: None,
+            m_config: None,
+            opt_config: OptimizerConfig::default(),
+        }
+    }
+}
+
+impl<F, M> IqnModelConfig<F, M>
+where
+    F: DeserializeOwned + Serialize,
+    M: DeserializeOwned + Serialize + OutDim,
+{
+    /// Sets the dimension of cos-embedding of percent points.
+    pub fn embed_dim(mut self, v: i64) -> Self {
+        self.embed_dim = v;
+        self
+    }
+
+    /// Sets the dimension of feature vectors.
+    pub fn feature_dim(mut self, v: i64) -> Self {
+        self.feature_dim = v;
+        self
+    }
+
+    /// Sets configurations for feature extractor.
+    pub fn f_config(mut self, v: F) -> Self {
+        self.f_config = Some(v);
+        self
+    }
+
+    /// Sets configurations for merge model.
+    pub fn m_config(mut self, v: M) -> Self {
+        self.m_config = Some(v);
+        self
+    }
+
+    /// Sets output dimension of the model, i.e., the number of actions.
+    pub fn out_dim(mut self, v: i64) -> Self {
+        match &mut self.m_config {
+            None => {}
+            Some(m_config) => m_config.set_out_dim(v),
+        };
+        self
+    }
+
+    /// Sets optimizer configuration.
+    pub fn opt_config(mut self, v: OptimizerConfig) -> Self {
+        self.opt_config = v;
+        self
+    }
+
+    /// Constructs [`IqnModelConfig`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        Ok(b)
+    }
+
+    /// Saves [`IqnModelConfig`] as a YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// IQN model, which takes observations and percent points and returns action-value quantiles.
+///
+/// Observations are mapped to feature vectors by `F`. Percent points are embedded with
+/// cosine functions and a linear layer, then multiplied elementwise with the feature vectors.
+/// `M` maps the merged vectors to the quantiles of all actions.
+pub struct IqnModel<F, M>
+where
+    F: SubModel1<Output = Tensor>,
+    M: SubModel1<Input = Tensor, Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize,
+    M::Config: DeserializeOwned + Serialize + OutDim,
+{
+    device: Device,
+    varmap: VarMap,
+
+    // Dimension of the feature vector.
+    feature_dim: i64,
+
+    // Dimension of the cosine embedding vector.
+    embed_dim: i64,
+
+    // Dimension of the output vector (equal to the number of actions).
+    pub(super) out_dim: i64,
+
+    // Feature extractor
+    psi: F,
+
+    // Cosine embedding
+    phi: Linear,
+
+    // Merge network
+    f: M,
+
+    // Optimizer
+    opt: Optimizer,
+}
+
+impl<F, M> IqnModel<F, M>
+where
+    F: SubModel1<Output = Tensor>,
+    M: SubModel1<Input = Tensor, Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize,
+    M::Config: DeserializeOwned + Serialize + OutDim,
+{
+    /// Constructs [`IqnModel`].
+    pub fn build(config: IqnModelConfig<F::Config, M::Config>, device: Device) -> Result<Self> {
+        let f_config = config.f_config.context("f_config is not set.")?;
+        let m_config = config.m_config.context("m_config is not set.")?;
+        let feature_dim = config.feature_dim;
+        let embed_dim = config.embed_dim;
+        let out_dim = m_config.get_out_dim();
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &device);
+
+        // Feature extractor
+        let psi = F::build(vb.pp("psi"), f_config);
+
+        // Cosine embedding
+        let phi = linear(
+            embed_dim as _,
+            feature_dim as _,
+            vb.pp("iqn_cos_to_feature"),
+        )?;
+
+        // Merge
+        let f = M::build(vb.pp("f"), m_config);
+
+        // Optimizer
+        let opt = config.opt_config.build(varmap.all_vars())?;
+
+        Ok(Self {
+            device,
+            varmap,
+            feature_dim,
+            embed_dim,
+            out_dim,
+            psi,
+            phi,
+            f,
+            opt,
+        })
+    }
+
+    /// Returns the tensor of action-value quantiles.
+    ///
+    /// * The shape of `psi(x)` (feature vector) is `[batch_size, feature_dim]`.
+    /// * The shape of `tau` is `[batch_size, n_percent_points]`.
+    /// * The shape of the output is `[batch_size, n_percent_points, self.out_dim]`.
+    pub fn forward(&self, x: &F::Input, tau: &Tensor) -> Result<Tensor> {
+        let feature_dim = self.feature_dim as usize;
+        let embed_dim = self.embed_dim as usize;
+        let (batch_size, n_percent_points) = tau.dims2()?;
+
+        // Feature extraction
+        let psi = self.psi.forward(x);
+        debug_assert_eq!(psi.dims(), &[batch_size, feature_dim]);
+
+        // Cosine embedding of percent points, eq. (4) in the paper
+        let i = Tensor::arange(1u32, embed_dim as u32 + 1, &self.device)?
+            .to_dtype(DType::F32)?
+            .reshape((1, 1, embed_dim))?;
+        let cos = (tau.to_device(&self.device)?.unsqueeze(D::Minus1)? * PI)?
+            .broadcast_mul(&i)?
+            .cos()?
+            .reshape((batch_size * n_percent_points, embed_dim))?;
+        let phi =
+            self.phi
+                .forward(&cos)?
+                .relu()?
+                .reshape((batch_size, n_percent_points, feature_dim))?;
+
+        // Merge features and embedded quantiles by elem-wise multiplication
+        let m = psi
+            .unsqueeze(1)?
+            .broadcast_mul(&phi)?
+            .reshape((batch_size * n_percent_points, feature_dim))?;
+
+        // Action-value quantiles
+        let a =
+            self.f
+                .forward(&m)
+                .reshape((batch_size, n_percent_points, self.out_dim as usize))?;
+
+        Ok(a)
+    }
+
+    /// Resamples the noise of noisy layers in the feature extractor and the merge network.
+    pub fn reset_noise(&mut self) {
+        self.psi.reset_noise();
+        self.f.reset_noise();
+    }
+
+    /// Removes the noise of noisy layers in the feature extractor and the merge network.
+    pub fn remove_noise(&mut self) {
+        self.psi.remove_noise();
+        self.f.remove_noise();
+    }
+
+    /// Returns `true` if the feature extractor or the merge network has noisy layers.
+    pub fn is_noisy(&self) -> bool {
+        self.psi.is_noisy() || self.f.is_noisy()
+    }
+
+    pub fn backward_step(&mut self, loss: &Tensor) -> Result<()> {
+        self.opt.backward_step(loss)
+    }
+
+    pub fn get_varmap(&self) -> &VarMap {
+        &self.varmap
+    }
+
+    pub fn save<T: AsRef<Path>>(&self, path: T) -> Result<()> {
+        self.varmap.save(&path)?;
+        info!("Save IQN model to {:?}", path.as_ref());
+        Ok(())
+    }
+
+    pub fn load<T: AsRef<Path>>(&mut self, path: T) -> Result<()> {
+        self.varmap.load(&path)?;
+        info!("Load IQN model from {:?}", path.as_ref());
+        Ok(())
+    }
+
+    /// Saves the state of the optimizer.
+    pub fn save_opt<T: AsRef<Path>>(&self, path: T) -> Result<()> {
+        self.opt.save(&path, &self.varmap)?;
+        info!("Save optimizer of IQN model to {:?}", path.as_ref());
+        Ok(())
+    }
+
+    /// Loads the state of the optimizer.
+    pub fn load_opt<T: AsRef<Path>>(&mut self, path: T) -> Result<()> {
+        self.opt.load(&path, &self.varmap)?;
+        info!("Load optimizer of IQN model from {:?}", path.as_ref());
+        Ok(())
+    }
+
+    /// Sets the learning rate of the optimizer.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.opt.set_learning_rate(lr);
+    }
+
+    /// Returns the learning rate used in the last update if it is scheduled.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        self.opt.scheduled_learning_rate()
+    }
+
+    pub fn param_stats(&self) -> Record {
+        crate::util::param_stats(&self.varmap)
+    }
+
+    /// Returns the norms of gradients in the last update, before clipping.
+    pub fn grad_stats(&self) -> Result<Record> {
+        self.opt.grad_stats(&self.varmap)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+/// The way of taking percent points.
+pub enum IqnSample {
+    /// Samples over percent points `0.05:0.1:0.95`.
+    ///
+    /// The precent points are constants.
+    Const10,
+
+    /// The precent points are constants.
+    Const32,
+
+    /// 10 samples from uniform distribution.
+    Uniform10,
+
+    /// 8 samples from uniform distribution.
+    Uniform8,
+
+    /// 32 samples from uniform distribution.
+    Uniform32,
+
+    /// 64 samples from uniform distribution.
+    Uniform64,
+
+    /// Single sample, median.
+    Median,
+}
+
+impl IqnSample {
+    /// Returns samples of percent points, whose shape is `[batch_size, n_percent_points]`.
+    pub fn sample(&self, batch_size: usize, device: &Device) -> Result<Tensor> {
+        let n = self.n_percent_points();
+        let tau = match self {
+            Self::Const10 | Self::Const32 | Self::Median => {
+                let t = (0..n)
+                    .map(|i| (2 * i + 1) as f32 / (2 * n) as f32)
+                    .collect::<Vec<_>>();
+                Tensor::from_vec(t, (1, n), device)?.repeat((batch_size, 1))?
+            }
+            _ => Tensor::rand(0f32, 1f32, (batch_size, n), device)?,
+        };
+        Ok(tau)
+    }
+
+    /// Returns the number of percent points generated by this way.
+    pub fn n_percent_points(&self) -> usize {
+        match self {
+            Self::Const10 => 10,
+            Self::Const32 => 32,
+            Self::Uniform10 => 10,
+            Self::Uniform8 => 8,
+            Self::Uniform32 => 32,
+            Self::Uniform64 => 64,
+            Self::Median => 1,
+        }
+    }
+}
+
+/// Takes an average over percent points specified by `mode`.
+///
+/// The shape of the returned tensor is `[batch_size, n_actions]`.
+///
+/// * `obs` - Observations.
+/// * `iqn` - IQN model.
+/// * `mode` - The way of taking percent points.
+pub fn average<F, M>(
+    batch_size: usize,
+    obs: &F::Input,
+    iqn: &IqnModel<F, M>,
+    mode: &IqnSample,
+    device: &Device,
+) -> Result<Tensor>
+where
+    F: SubModel1<Output = Tensor>,
+    M: SubModel1<Input = Tensor, Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize,
+    M::Config: DeserializeOwned + Serialize + OutDim,
+{
+    let tau = mode.sample(batch_size, device)?;
+    let averaged_action_value = iqn.forward(obs, &tau)?.mean(1)?;
+    debug_assert_eq!(
+        averaged_action_value.dims(),
+        &[batch_size, iqn.out_dim as usize]
+    );
+    Ok(averaged_action_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mlp::{Mlp, MlpConfig},
+        Activation,
+    };
+
+    #[test]
+    fn test_forward() -> Result<()> {
+        let config = IqnModelConfig::default()
+            .feature_dim(16)
+            .embed_dim(8)
+            .f_config(MlpConfig::new(4, vec![16], 16, Activation::ReLU))
+            .m_config(MlpConfig::new(16, vec![16], 3, Activation::None));
+        let iqn = IqnModel::<Mlp, Mlp>::build(config, Device::Cpu)?;
+        let obs = Tensor::randn(0f32, 1f32, (5, 4), &Device::Cpu)?;
+        let tau = IqnSample::Uniform8.sample(5, &Device::Cpu)?;
+
+        assert_eq!(iqn.forward(&obs, &tau)?.dims(), [5, 8, 3]);
+        assert_eq!(
+            average(5, &obs, &iqn, &IqnSample::Const10, &Device::Cpu)?.dims(),
+            [5, 3]
+        );
+
+        Ok(())
+    }
+}
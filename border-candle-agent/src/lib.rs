@@ -1,15 +1,26 @@
 //! RL agents implemented with [candle](https://crates.io/crates/candle-core).
 pub mod atari_cnn;
-pub mod dqn;
-// pub mod iqn;
 pub mod awac;
 pub mod bc;
+pub mod c51;
+pub mod cql;
+pub mod ddpg;
+pub mod dqn;
+pub mod dueling;
 pub mod iql;
+pub mod iqn;
 pub mod mlp;
 pub mod model;
 pub mod opt;
+pub mod ppo;
+pub mod qrdqn;
 pub mod sac;
+pub mod sac_discrete;
+pub mod td3;
+pub mod td3_bc;
 mod tensor_batch;
+#[cfg(test)]
+mod test;
 pub mod util;
 use candle_core::{backend::BackendDevice, DeviceLocation, Module};
 use serde::{Deserialize, Serialize};
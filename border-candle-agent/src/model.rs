@@ -25,6 +25,26 @@ pub trait SubModel1 {
 
     /// A generalized forward function.
     fn forward(&self, input: &Self::Input) -> Self::Output;
+
+    /// Resamples the noise of noisy layers, e.g., [`NoisyLinear`].
+    ///
+    /// The default implementation does nothing, as most models have no noisy layers.
+    ///
+    /// [`NoisyLinear`]: crate::util::NoisyLinear
+    fn reset_noise(&mut self) {}
+
+    /// Removes the noise of noisy layers, so that their mean weights are used until
+    /// [`SubModel1::reset_noise`] is called.
+    ///
+    /// The default implementation does nothing, as most models have no noisy layers.
+    fn remove_noise(&mut self) {}
+
+    /// Returns `true` if the model has noisy layers.
+    ///
+    /// The default implementation returns `false`, as most models have no noisy layers.
+    fn is_noisy(&self) -> bool {
+        false
+    }
 }
 
 /// Neural network model not owing its [`VarMap`] internally.
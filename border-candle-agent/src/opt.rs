@@ -1,9 +1,20 @@
 //! Optimizers.
-use anyhow::Result;
-use candle_core::{Tensor, Var};
-use candle_nn::{AdamW, Optimizer as _, ParamsAdamW};
-use candle_optimisers::adam::{Adam, ParamsAdam};
+//!
+//! The optimizers in this module are implemented in this crate, instead of using those
+//! in [`candle_nn`], so that their internal states, e.g., moments of Adam, can be saved
+//! and loaded for resuming training.
+//!
+//! Gradients can be clipped by value and by global norm before updates, see
+//! [`OptimizerConfig::clip_grad_value()`] and [`OptimizerConfig::max_grad_norm()`].
+use anyhow::{anyhow, Result};
+use border_core::{
+    record::{Record, RecordValue},
+    Schedule,
+};
+use candle_core::{backprop::GradStore, DType, Device, Tensor, TensorId, Var};
+use candle_nn::{ParamsAdamW, VarMap};
 use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
 
 /// Configuration of optimizer for training neural networks in an RL agent.
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -26,6 +37,37 @@ pub enum OptimizerConfig {
         /// Learning rate.
         lr: f64,
     },
+
+    /// Optimizer with a learning rate schedule.
+    ///
+    /// The learning rate of each update is given by `lr_schedule` with the number of updates
+    /// done by the optimizer, instead of the learning rate in `config`.
+    /// See [`OptimizerConfig::lr_schedule()`].
+    Scheduled {
+        /// Configuration of the optimizer.
+        config: Box<OptimizerConfig>,
+
+        /// Schedule of the learning rate.
+        lr_schedule: Schedule,
+    },
+
+    /// Optimizer with gradient clipping.
+    ///
+    /// Gradients are clipped by value, then by global norm, i.e., the norm of all gradients
+    /// concatenated. See [`OptimizerConfig::clip_grad_value()`] and
+    /// [`OptimizerConfig::max_grad_norm()`].
+    Clipped {
+        /// Configuration of the optimizer.
+        config: Box<OptimizerConfig>,
+
+        /// Maximum of the global norm of gradients.
+        #[serde(default)]
+        max_grad_norm: Option<f64>,
+
+        /// Maximum of the absolute values of gradients.
+        #[serde(default)]
+        clip_grad_value: Option<f64>,
+    },
 }
 
 fn default_beta1() -> f64 {
@@ -45,7 +87,7 @@ fn default_weight_decay() -> f64 {
 }
 
 impl OptimizerConfig {
-    /// Constructs [`AdamW`] optimizer.
+    /// Constructs an optimizer.
     pub fn build(&self, vars: Vec<Var>) -> Result<Optimizer> {
         match &self {
             OptimizerConfig::AdamW {
@@ -66,13 +108,33 @@ impl OptimizerConfig {
                 Ok(Optimizer::AdamW(opt))
             }
             OptimizerConfig::Adam { lr } => {
-                let params = ParamsAdam {
+                let params = ParamsAdamW {
                     lr: *lr,
-                    ..ParamsAdam::default()
+                    weight_decay: 0.0,
+                    ..ParamsAdamW::default()
                 };
-                let opt = Adam::new(vars, params)?;
+                let opt = AdamW::new(vars, params)?;
                 Ok(Optimizer::Adam(opt))
             }
+            OptimizerConfig::Scheduled {
+                config,
+                lr_schedule,
+            } => {
+                let mut opt = config.build(vars)?;
+                opt.set_learning_rate(lr_schedule.value(0));
+                opt.inner_mut().lr_schedule = Some(lr_schedule.clone());
+                Ok(opt)
+            }
+            OptimizerConfig::Clipped {
+                config,
+                max_grad_norm,
+                clip_grad_value,
+            } => {
+                let mut opt = config.build(vars)?;
+                opt.inner_mut().max_grad_norm = *max_grad_norm;
+                opt.inner_mut().clip_grad_value = *clip_grad_value;
+                Ok(opt)
+            }
         }
     }
 
@@ -93,6 +155,91 @@ impl OptimizerConfig {
                 weight_decay,
             },
             Self::Adam { lr: _ } => Self::Adam { lr },
+            Self::Scheduled {
+                config,
+                lr_schedule,
+            } => Self::Scheduled {
+                config: Box::new(config.learning_rate(lr)),
+                lr_schedule,
+            },
+            Self::Clipped {
+                config,
+                max_grad_norm,
+                clip_grad_value,
+            } => Self::Clipped {
+                config: Box::new(config.learning_rate(lr)),
+                max_grad_norm,
+                clip_grad_value,
+            },
+        }
+    }
+
+    /// Sets the schedule of the learning rate by the number of updates of the optimizer.
+    ///
+    /// ```
+    /// use border_candle_agent::opt::OptimizerConfig;
+    /// use border_core::Schedule;
+    ///
+    /// let config = OptimizerConfig::Adam { lr: 3e-4 }.lr_schedule(Schedule::Linear {
+    ///     start: 3e-4,
+    ///     end: 0.0,
+    ///     n_steps: 100_000,
+    /// });
+    /// ```
+    pub fn lr_schedule(self, lr_schedule: Schedule) -> Self {
+        let config = match self {
+            Self::Scheduled { config, .. } => config,
+            config => Box::new(config),
+        };
+        Self::Scheduled {
+            config,
+            lr_schedule,
+        }
+    }
+
+    /// Clips gradients so that their global norm is at most `v`.
+    ///
+    /// ```
+    /// use border_candle_agent::opt::OptimizerConfig;
+    ///
+    /// let config = OptimizerConfig::Adam { lr: 3e-4 }.max_grad_norm(10.0);
+    /// ```
+    pub fn max_grad_norm(self, v: f64) -> Self {
+        match self {
+            Self::Clipped {
+                config,
+                clip_grad_value,
+                ..
+            } => Self::Clipped {
+                config,
+                max_grad_norm: Some(v),
+                clip_grad_value,
+            },
+            config => Self::Clipped {
+                config: Box::new(config),
+                max_grad_norm: Some(v),
+                clip_grad_value: None,
+            },
+        }
+    }
+
+    /// Clips each element of gradients to `[-v, v]`.
+    pub fn clip_grad_value(self, v: f64) -> Self {
+        match self {
+            Self::Clipped {
+                config,
+                max_grad_norm,
+                ..
+            } => Self::Clipped {
+                config,
+                max_grad_norm,
+                clip_grad_value: Some(v),
+            },
+            config => Self::Clipped {
+                config: Box::new(config),
+                max_grad_norm: None,
+                clip_grad_value: Some(v),
+            },
         }
     }
 }
@@ -110,31 +257,407 @@ impl Default for OptimizerConfig {
     }
 }
 
-/// Optimizers.
+/// A variable and its moments in [`AdamW`].
+struct VarAdamW {
+    var: Var,
+    first_moment: Var,
+    second_moment: Var,
+}
+
+/// Adam optimizer with decoupled weight decay.
 ///
-/// This is a thin wrapper of [`candle_nn::optim::Optimizer`].
+/// The update rule is the same as [`candle_nn::AdamW`]. Adam without weight decay
+/// is given by setting `weight_decay` to zero.
+pub struct AdamW {
+    vars: Vec<VarAdamW>,
+    step_t: usize,
+    params: ParamsAdamW,
+    lr_schedule: Option<Schedule>,
+    max_grad_norm: Option<f64>,
+    clip_grad_value: Option<f64>,
+
+    // Squared norms of the gradients of variables in the last update, before clipping
+    grad_sq_norms: Vec<(TensorId, Tensor)>,
+}
+
+impl AdamW {
+    /// Constructs an optimizer for the given variables.
+    ///
+    /// Variables of non-float types are ignored.
+    pub fn new(vars: Vec<Var>, params: ParamsAdamW) -> Result<Self> {
+        let vars = vars
+            .into_iter()
+            .filter(|var| var.dtype().is_float())
+            .map(|var| {
+                let first_moment = Var::zeros(var.shape(), var.dtype(), var.device())?;
+                let second_moment = Var::zeros(var.shape(), var.dtype(), var.device())?;
+                Ok(VarAdamW {
+                    var,
+                    first_moment,
+                    second_moment,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            vars,
+            step_t: 0,
+            params,
+            lr_schedule: None,
+            max_grad_norm: None,
+            clip_grad_value: None,
+            grad_sq_norms: vec![],
+        })
+    }
+
+    /// Updates the variables with the given gradients.
+    ///
+    /// If the learning rate is scheduled, it is set to the value at the current number of updates.
+    /// Gradients are clipped if configured.
+    pub fn step(&mut self, grads: &GradStore) -> Result<()> {
+        if let Some(lr_schedule) = &self.lr_schedule {
+            self.params.lr = lr_schedule.value(self.step_t);
+        }
+        let grads = self.clip_grads(grads)?;
+        self.step_t += 1;
+        let lr = self.params.lr;
+        let lr_lambda = lr * self.params.weight_decay;
+        let beta1 = self.params.beta1;
+        let beta2 = self.params.beta2;
+        let scale_m = 1f64 / (1f64 - beta1.powi(self.step_t as i32));
+        let scale_v = 1f64 / (1f64 - beta2.powi(self.step_t as i32));
+        for (var, g) in self.vars.iter().zip(grads.iter()) {
+            let theta = &var.var;
+            let m = &var.first_moment;
+            let v = &var.second_moment;
+            if let Some(g) = g {
+                let next_m = ((m.as_tensor() * beta1)? + (g * (1.0 - beta1))?)?;
+                let next_v = ((v.as_tensor() * beta2)? + (g.sqr()? * (1.0 - beta2))?)?;
+                let m_hat = (&next_m * scale_m)?;
+                let v_hat = (&next_v * scale_v)?;
+                let next_theta = (theta.as_tensor() * (1f64 - lr_lambda))?;
+                let adjusted_grad = (m_hat / (v_hat.sqrt()? + self.params.eps)?)?;
+                let next_theta = (next_theta - (adjusted_grad * lr)?)?;
+                m.set(&next_m)?;
+                v.set(&next_v)?;
+                theta.set(&next_theta)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the gradients of the variables, clipped by value and then by global norm.
+    ///
+    /// The squared norms of the gradients before clipping are kept for [`AdamW::grad_stats()`].
+    /// No values are copied to the host here, so that clipping does not block GPU computation.
+    fn clip_grads(&mut self, grads: &GradStore) -> Result<Vec<Option<Tensor>>> {
+        let mut grads = self
+            .vars
+            .iter()
+            .map(|var| grads.get(&var.var).cloned())
+            .collect::<Vec<_>>();
+
+        self.grad_sq_norms = self
+            .vars
+            .iter()
+            .zip(grads.iter())
+            .filter_map(|(var, g)| g.as_ref().map(|g| (var.var.as_tensor().id(), g)))
+            .map(|(id, g)| Ok((id, g.to_dtype(DType::F32)?.sqr()?.sum_all()?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Some(v) = self.clip_grad_value {
+            for g in grads.iter_mut().flatten() {
+                *g = g.clamp(-v, v)?;
+            }
+        }
+
+        if let (Some(max_norm), false) = (self.max_grad_norm, self.grad_sq_norms.is_empty()) {
+            // scale = min(1, max_norm / (norm + 1e-6)), computed on the device
+            let sq_norms = self
+                .grad_sq_norms
+                .iter()
+                .map(|(_, t)| t.clone())
+                .collect::<Vec<_>>();
+            let norm = Tensor::stack(&sq_norms, 0)?.sum_all()?.sqrt()?;
+            let scale = ((norm + 1e-6)?.recip()? * max_norm)?.clamp(0f64, 1f64)?;
+            for g in grads.iter_mut().flatten() {
+                *g = g.broadcast_mul(&scale.to_dtype(g.dtype())?)?;
+            }
+        }
+
+        Ok(grads)
+    }
+
+    /// Returns the norms of gradients in the last update, before clipping.
+    ///
+    /// The record has the global norm of all gradients with key `grad_norm` and the norms
+    /// of gradients of each module, i.e., variables sharing the name except for the last
+    /// component, e.g., `fc1` of `fc1.weight` and `fc1.bias`, with keys `{module}_grad_norm`.
+    pub fn grad_stats(&self, varmap: &VarMap) -> Result<Record> {
+        let names = var_names(varmap);
+        let mut total = 0f32;
+        let mut modules = HashMap::<String, f32>::new();
+        for (id, sq_norm) in self.grad_sq_norms.iter() {
+            let sq_norm = sq_norm.to_scalar::<f32>()?;
+            total += sq_norm;
+            if let Some(name) = names.get(id) {
+                let module = match name.rfind('.') {
+                    Some(ix) => &name[..ix],
+                    None => name.as_str(),
+                };
+                *modules.entry(module.to_string()).or_insert(0f32) += sq_norm;
+            }
+        }
+
+        let mut record = Record::from_slice(&[("grad_norm", RecordValue::Scalar(total.sqrt()))]);
+        for (module, sq_norm) in modules.into_iter() {
+            record.insert(
+                format!("{}_grad_norm", module),
+                RecordValue::Scalar(sq_norm.sqrt()),
+            );
+        }
+        Ok(record)
+    }
+
+    /// Returns the learning rate.
+    pub fn learning_rate(&self) -> f64 {
+        self.params.lr
+    }
+
+    /// Sets the learning rate.
+    ///
+    /// If the learning rate is scheduled, the given value is overwritten at the next update.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.params.lr = lr;
+    }
+
+    /// Returns the number of updates.
+    pub fn n_steps(&self) -> usize {
+        self.step_t
+    }
+
+    /// Returns the learning rate used in the last update if it is scheduled.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        self.lr_schedule.as_ref().map(|_| self.params.lr)
+    }
+
+    /// Returns the state of the optimizer as named tensors.
+    ///
+    /// The moments of a variable are named after the variable in `varmap`,
+    /// with suffixes `.m` and `.v`.
+    fn state(&self, varmap: &VarMap) -> Result<HashMap<String, Tensor>> {
+        let names = var_names(varmap);
+        let mut state = HashMap::new();
+        for var in self.vars.iter() {
+            let name = names
+                .get(&var.var.as_tensor().id())
+                .ok_or_else(|| anyhow!("A variable of the optimizer is not in the varmap"))?;
+            state.insert(format!("{}.m", name), var.first_moment.as_tensor().clone());
+            state.insert(format!("{}.v", name), var.second_moment.as_tensor().clone());
+        }
+        state.insert(
+            "step_t".to_string(),
+            Tensor::new(&[self.step_t as i64], &Device::Cpu)?,
+        );
+        Ok(state)
+    }
+
+    /// Restores the state of the optimizer from named tensors.
+    fn set_state(&mut self, state: &HashMap<String, Tensor>, varmap: &VarMap) -> Result<()> {
+        let names = var_names(varmap);
+        for var in self.vars.iter() {
+            let name = names
+                .get(&var.var.as_tensor().id())
+                .ok_or_else(|| anyhow!("A variable of the optimizer is not in the varmap"))?;
+            for (suffix, moment) in [("m", &var.first_moment), ("v", &var.second_moment)].iter() {
+                let key = format!("{}.{}", name, suffix);
+                let t = state
+                    .get(&key)
+                    .ok_or_else(|| anyhow!("{} is not found in the optimizer state", key))?;
+                moment.set(&t.to_device(moment.device())?.to_dtype(moment.dtype())?)?;
+            }
+        }
+        let step_t = state
+            .get("step_t")
+            .ok_or_else(|| anyhow!("step_t is not found in the optimizer state"))?;
+        self.step_t = step_t.to_dtype(DType::I64)?.to_vec1::<i64>()?[0] as usize;
+        Ok(())
+    }
+}
+
+/// Returns the names of variables in a varmap, keyed by the IDs of the tensors.
+fn var_names(varmap: &VarMap) -> HashMap<TensorId, String> {
+    varmap
+        .data()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, var)| (var.as_tensor().id(), name.clone()))
+        .collect()
+}
+
+/// Optimizers.
 ///
-/// [`candle_nn::optim::Optimizer`]: https://docs.rs/candle-nn/0.4.1/candle_nn/optim/trait.Optimizer.html
+/// Both variants are [`AdamW`]; `Adam` has no weight decay.
 pub enum Optimizer {
-    /// Adam optimizer.
+    /// Adam optimizer with decoupled weight decay.
     AdamW(AdamW),
 
-    Adam(Adam),
+    /// Adam optimizer.
+    Adam(AdamW),
 }
 
 impl Optimizer {
     /// Applies a backward step pass.
     pub fn backward_step(&mut self, loss: &Tensor) -> Result<()> {
+        let grads = loss.backward()?;
+        self.step(&grads)
+    }
+
+    /// Updates the variables with the given gradients.
+    pub fn step(&mut self, grads: &GradStore) -> Result<()> {
+        match self {
+            Self::AdamW(opt) => opt.step(grads),
+            Self::Adam(opt) => opt.step(grads),
+        }
+    }
+
+    /// Returns the learning rate.
+    pub fn learning_rate(&self) -> f64 {
+        self.inner().learning_rate()
+    }
+
+    /// Sets the learning rate.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.inner_mut().set_learning_rate(lr);
+    }
+
+    /// Returns the number of updates, which is also saved with the state of the optimizer.
+    pub fn n_steps(&self) -> usize {
+        self.inner().n_steps()
+    }
+
+    /// Returns the learning rate used in the last update if it is scheduled.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        self.inner().scheduled_learning_rate()
+    }
+
+    /// Returns the norms of gradients in the last update, before clipping.
+    ///
+    /// See [`AdamW::grad_stats()`].
+    pub fn grad_stats(&self, varmap: &VarMap) -> Result<Record> {
+        self.inner().grad_stats(varmap)
+    }
+
+    fn inner(&self) -> &AdamW {
         match self {
-            Self::AdamW(opt) => Ok(opt.backward_step(loss)?),
-            Self::Adam(opt) => Ok(opt.backward_step(loss)?),
+            Self::AdamW(opt) => opt,
+            Self::Adam(opt) => opt,
         }
     }
 
-    pub fn step(&mut self, grads: &candle_core::backprop::GradStore) -> Result<()> {
+    fn inner_mut(&mut self) -> &mut AdamW {
         match self {
-            Self::AdamW(opt) => Ok(opt.step(grads)?),
-            Self::Adam(opt) => Ok(opt.step(grads)?),
+            Self::AdamW(opt) => opt,
+            Self::Adam(opt) => opt,
+        }
+    }
+
+    /// Saves the state of the optimizer into a safetensors file.
+    ///
+    /// `varmap` is used to name the state of each variable, so it must contain
+    /// the variables given when the optimizer was built.
+    pub fn save(&self, path: impl AsRef<Path>, varmap: &VarMap) -> Result<()> {
+        let state = self.inner().state(varmap)?;
+        candle_core::safetensors::save(&state, path)?;
+        Ok(())
+    }
+
+    /// Loads the state of the optimizer from a file saved by [`Optimizer::save`].
+    pub fn load(&mut self, path: impl AsRef<Path>, varmap: &VarMap) -> Result<()> {
+        let state = candle_core::safetensors::load(path, &Device::Cpu)?;
+        self.inner_mut().set_state(&state, varmap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lr_schedule() -> Result<()> {
+        let var = Var::new(&[1f32, 2f32], &Device::Cpu)?;
+        let mut opt = OptimizerConfig::default()
+            .lr_schedule(Schedule::Linear {
+                start: 1e-3,
+                end: 0.0,
+                n_steps: 4,
+            })
+            .build(vec![var.clone()])?;
+        assert_eq!(opt.scheduled_learning_rate(), Some(1e-3));
+
+        for _ in 0..3 {
+            let loss = var.as_tensor().sqr()?.sum_all()?;
+            opt.backward_step(&loss)?;
         }
+        assert_eq!(opt.n_steps(), 3);
+        assert!((opt.scheduled_learning_rate().unwrap() - 5e-4).abs() < 1e-12);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_adamw_same_as_candle_nn() -> Result<()> {
+        use candle_nn::Optimizer as _;
+
+        // Adam (without weight decay) and AdamW
+        for weight_decay in [0.0, 0.1].iter() {
+            let params = ParamsAdamW {
+                lr: 1e-2,
+                weight_decay: *weight_decay,
+                ..ParamsAdamW::default()
+            };
+            let var1 = Var::new(&[1f32, -2f32, 3f32], &Device::Cpu)?;
+            let var2 = Var::new(&[1f32, -2f32, 3f32], &Device::Cpu)?;
+            let mut opt1 = AdamW::new(vec![var1.clone()], params.clone())?;
+            let mut opt2 = candle_nn::AdamW::new(vec![var2.clone()], params)?;
+
+            for _ in 0..5 {
+                let loss1 = (var1.as_tensor().sqr()? * 0.5)?.sum_all()?;
+                let loss2 = (var2.as_tensor().sqr()? * 0.5)?.sum_all()?;
+                opt1.step(&loss1.backward()?)?;
+                opt2.backward_step(&loss2)?;
+            }
+            let diff = (var1.as_tensor() - var2.as_tensor())?
+                .abs()?
+                .max(0)?
+                .to_scalar::<f32>()?;
+            assert!(diff < 1e-6);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clip_grads() -> Result<()> {
+        let varmap = VarMap::new();
+        let vb = candle_nn::VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        let w = vb.get_with_hints(2, "fc.weight", candle_nn::init::ZERO)?;
+        let mut opt = OptimizerConfig::Adam { lr: 1e-3 }
+            .max_grad_norm(1.0)
+            .build(varmap.all_vars())?;
+
+        // The gradient is [3, 4], of which the norm is 5
+        let loss = (w * &Tensor::new(&[3f32, 4f32], &Device::Cpu)?)?.sum_all()?;
+        let grads = loss.backward()?;
+        let clipped = opt.inner_mut().clip_grads(&grads)?;
+        let g = clipped[0].as_ref().unwrap().to_vec1::<f32>()?;
+        assert!((g[0] - 0.6).abs() < 1e-5 && (g[1] - 0.8).abs() < 1e-5);
+
+        let record = opt.grad_stats(&varmap)?;
+        assert_eq!(record.get_scalar("grad_norm")?, 5.0);
+        assert_eq!(record.get_scalar("fc_grad_norm")?, 5.0);
+
+        Ok(())
     }
 }
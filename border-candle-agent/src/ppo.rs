@@ -0,0 +1,7 @@
+//! Proximal policy optimization (PPO) agent.
+mod actor;
+mod base;
+mod config;
+pub use actor::{PpoActionType, PpoActor, PpoActorConfig};
+pub use base::Ppo;
+pub use config::PpoConfig;
@@ -0,0 +1,372 @@
+//! Stochastic policy of PPO agent.
+use crate::{
+    model::SubModel1,
+    opt::{Optimizer, OptimizerConfig},
+    util::OutDim,
+};
+use anyhow::{Context, Result};
+use candle_core::{DType, Device, Tensor, D};
+use candle_nn::{
+    ops::{log_softmax, softmax},
+    Init, VarBuilder, VarMap,
+};
+use log::info;
+use rand::{distributions::WeightedIndex, Rng};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    f64::consts::PI,
+    fs::File,
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+/// Action type of [`PpoActor`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum PpoActionType {
+    /// Discrete action.
+    ///
+    /// The output of the policy model is regarded as the logits of a categorical
+    /// distribution. Actions are `i64` tensors of the indices.
+    Discrete,
+
+    /// Continuous action.
+    ///
+    /// The output of the policy model is regarded as the mean of a diagonal Gaussian
+    /// distribution, whose log standard deviation is a learnable parameter independent
+    /// of the observation.
+    Continuous {
+        /// Initial value of the log standard deviation.
+        log_std_init: f64,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+/// Configuration of [`PpoActor`].
+pub struct PpoActorConfig<P: OutDim> {
+    pub policy_config: Option<P>,
+    pub opt_config: OptimizerConfig,
+    pub action_type: PpoActionType,
+}
+
+impl<P: OutDim> Default for PpoActorConfig<P> {
+    fn default() -> Self {
+        Self {
+            policy_config: None,
+            opt_config: OptimizerConfig::Adam { lr: 0.0003 },
+            action_type: PpoActionType::Discrete,
+        }
+    }
+}
+
+impl<P> PpoActorConfig<P>
+where
+    P: DeserializeOwned + Serialize + OutDim,
+{
+    /// Sets configurations for policy function.
+    pub fn policy_config(mut self, v: P) -> Self {
+        self.policy_config = Some(v);
+        self
+    }
+
+    /// Sets output dimension of the model.
+    pub fn out_dim(mut self, v: i64) -> Self {
+        match &mut self.policy_config {
+            None => {}
+            Some(pi_config) => pi_config.set_out_dim(v),
+        };
+        self
+    }
+
+    /// Sets optimizer configuration.
+    pub fn opt_config(mut self, v: OptimizerConfig) -> Self {
+        self.opt_config = v;
+        self
+    }
+
+    /// Sets action type.
+    pub fn action_type(mut self, v: PpoActionType) -> Self {
+        self.action_type = v;
+        self
+    }
+
+    /// Loads [`PpoActorConfig`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        Ok(b)
+    }
+
+    /// Saves [`PpoActorConfig`] as YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Stochastic policy with a categorical or a diagonal Gaussian distribution.
+pub struct PpoActor<P>
+where
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Clone,
+{
+    device: Device,
+    varmap: VarMap,
+
+    // Policy model
+    policy: P,
+
+    // Log standard deviation, only for continuous actions
+    log_std: Option<Tensor>,
+
+    // Optimizer
+    opt: Optimizer,
+}
+
+impl<P> PpoActor<P>
+where
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Clone,
+{
+    /// Constructs [`PpoActor`].
+    pub fn build(config: PpoActorConfig<P::Config>, device: Device) -> Result<PpoActor<P>> {
+        let policy_config = config.policy_config.context("policy_config is not set.")?;
+        let out_dim = policy_config.get_out_dim() as usize;
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &device);
+        let policy = P::build(vb.pp("actor"), policy_config);
+        let log_std = match config.action_type {
+            PpoActionType::Discrete => None,
+            PpoActionType::Continuous { log_std_init } => {
+                Some(vb.get_with_hints(out_dim, "log_std", Init::Const(log_std_init))?)
+            }
+        };
+        let opt = config.opt_config.build(varmap.all_vars())?;
+
+        Ok(Self {
+            device,
+            varmap,
+            policy,
+            log_std,
+            opt,
+        })
+    }
+
+    /// Returns the output of the policy model, i.e., the logits or the mean of actions.
+    pub fn forward(&self, x: &P::Input) -> Tensor {
+        self.policy.forward(x)
+    }
+
+    /// Returns the log probabilities (densities) of the given actions and the entropies
+    /// of the action distributions.
+    ///
+    /// The shape of the both tensors is `(batch_size,)`.
+    pub fn logp_entropy(&self, obs: &P::Input, act: &Tensor) -> Result<(Tensor, Tensor)> {
+        let out = self.forward(obs);
+        let act = act.to_device(&self.device)?;
+
+        match &self.log_std {
+            None => {
+                let batch_size = out.dims()[0];
+                let act = act.to_dtype(DType::I64)?.reshape((batch_size, 1))?;
+                let logps = log_softmax(&out, D::Minus1)?;
+                let logp = logps.gather(&act, D::Minus1)?.squeeze(D::Minus1)?;
+                let entropy = (softmax(&out, D::Minus1)? * &logps)?
+                    .sum(D::Minus1)?
+                    .neg()?;
+                Ok((logp, entropy))
+            }
+            Some(log_std) => {
+                let act = act.reshape(out.shape())?;
+                let z = act.sub(&out)?.broadcast_div(&log_std.exp()?)?.sqr()?;
+                let logp = ((-0.5 * z)?.broadcast_sub(log_std)? - 0.5 * (2.0 * PI).ln())?
+                    .sum(D::Minus1)?;
+                let entropy = (log_std + 0.5 * (1.0 + (2.0 * PI).ln()))?
+                    .sum_all()?
+                    .broadcast_as(logp.shape())?;
+                Ok((logp, entropy))
+            }
+        }
+    }
+
+    /// Samples actions.
+    ///
+    /// If `train` is `true`, actions are sampled from the distribution with `rng`. Otherwise,
+    /// the mode of the distribution, i.e., the action with the maximum probability or the mean,
+    /// is returned.
+    pub fn sample(&self, obs: &P::Input, train: bool, rng: &mut impl Rng) -> Result<Tensor> {
+        let out = self.forward(obs).detach();
+
+        match (&self.log_std, train) {
+            (None, true) => {
+                let probs = softmax(&out, D::Minus1)?.to_vec2::<f32>()?;
+                let n_samples = probs.len();
+                let data = probs
+                    .iter()
+                    .map(|p| Ok(rng.sample(WeightedIndex::new(p)?) as i64))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Tensor::from_vec(data, &[n_samples], out.device())?)
+            }
+            (None, false) => Ok(out.argmax(D::Minus1)?.to_dtype(DType::I64)?),
+            (Some(log_std), true) => {
+                // Standard normal noise with the Box-Muller transform
+                let noise = (0..out.elem_count())
+                    .map(|_| {
+                        let (u1, u2) = (1.0 - rng.gen::<f64>(), rng.gen::<f64>());
+                        ((-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()) as f32
+                    })
+                    .collect::<Vec<_>>();
+                let noise = Tensor::from_vec(noise, out.shape(), out.device())?
+                    .to_dtype(out.dtype())?
+                    .broadcast_mul(&log_std.exp()?)?;
+                Ok((out + noise)?)
+            }
+            (Some(_), false) => Ok(out),
+        }
+    }
+
+    /// Backward step for all variables of the actor, including the log standard deviation.
+    pub fn backward_step(&mut self, loss: &Tensor) -> Result<()> {
+        self.opt.backward_step(loss)
+    }
+
+    /// Save variables to prefix + ".pt".
+    pub fn save(&self, prefix: impl AsRef<Path>) -> Result<PathBuf> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("pt");
+        self.varmap.save(path.as_path())?;
+        info!("Save actor parameters to {:?}", path);
+
+        Ok(path)
+    }
+
+    /// Load variables from prefix + ".pt".
+    pub fn load(&mut self, prefix: impl AsRef<Path>) -> Result<()> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("pt");
+        self.varmap.load(path.as_path())?;
+        info!("Load actor parameters from {:?}", path);
+
+        Ok(())
+    }
+
+    /// Save the state of the optimizer to prefix + ".opt.pt".
+    pub fn save_opt(&self, prefix: impl AsRef<Path>) -> Result<PathBuf> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("opt.pt");
+        self.opt.save(&path, &self.varmap)?;
+        info!("Save actor optimizer to {:?}", path);
+
+        Ok(path)
+    }
+
+    /// Load the state of the optimizer from prefix + ".opt.pt".
+    pub fn load_opt(&mut self, prefix: impl AsRef<Path>) -> Result<()> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("opt.pt");
+        self.opt.load(&path, &self.varmap)?;
+        info!("Load actor optimizer from {:?}", path);
+
+        Ok(())
+    }
+
+    /// Sets the learning rate of the optimizer.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.opt.set_learning_rate(lr);
+    }
+
+    /// Returns the learning rate used in the last update if it is scheduled.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        self.opt.scheduled_learning_rate()
+    }
+
+    /// Returns the norms of gradients in the last update, before clipping.
+    pub fn grad_stats(&self) -> Result<border_core::record::Record> {
+        self.opt.grad_stats(&self.varmap)
+    }
+
+    /// Returns statistics of the parameters.
+    pub fn param_stats(&self) -> border_core::record::Record {
+        crate::util::param_stats(&self.varmap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mlp::{Mlp, MlpConfig},
+        Activation,
+    };
+
+    fn actor(action_type: PpoActionType) -> Result<PpoActor<Mlp>> {
+        let config = PpoActorConfig::default()
+            .policy_config(MlpConfig::new(3, vec![8], 2, Activation::None))
+            .action_type(action_type);
+        PpoActor::build(config, Device::Cpu)
+    }
+
+    #[test]
+    fn test_logp_entropy() -> Result<()> {
+        let obs = Tensor::randn(0f32, 1f32, (4, 3), &Device::Cpu)?;
+        let mut rng = rand::thread_rng();
+
+        // Probabilities of the two actions sum up to one
+        let actor_d = actor(PpoActionType::Discrete)?;
+        let (logp0, entropy) =
+            actor_d.logp_entropy(&obs, &Tensor::zeros(4, DType::I64, &Device::Cpu)?)?;
+        let (logp1, _) = actor_d.logp_entropy(&obs, &Tensor::ones(4, DType::I64, &Device::Cpu)?)?;
+        let p = (logp0.exp()? + logp1.exp()?)?.to_vec1::<f32>()?;
+        assert!(p.iter().all(|p| (p - 1.0).abs() < 1e-5));
+        assert!(entropy
+            .to_vec1::<f32>()?
+            .iter()
+            .all(|&e| e <= 2f32.ln() + 1e-5));
+        assert_eq!(actor_d.sample(&obs, true, &mut rng)?.dims(), [4]);
+
+        // Density at the mean of the standard normal distribution
+        let actor_c = actor(PpoActionType::Continuous { log_std_init: 0.0 })?;
+        let mean = actor_c.forward(&obs);
+        let (logp, entropy) = actor_c.logp_entropy(&obs, &mean)?;
+        let ln_2pi = (2.0 * PI as f32).ln();
+        assert!(logp
+            .to_vec1::<f32>()?
+            .iter()
+            .all(|l| (l + ln_2pi).abs() < 1e-5));
+        assert!(entropy
+            .to_vec1::<f32>()?
+            .iter()
+            .all(|e| (e - 1.0 - ln_2pi).abs() < 1e-5));
+        assert_eq!(actor_c.sample(&obs, false, &mut rng)?.dims(), [4, 2]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_with_rng() -> Result<()> {
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha8Rng;
+
+        let obs = Tensor::randn(0f32, 1f32, (1000, 3), &Device::Cpu)?;
+        let actor = actor(PpoActionType::Continuous { log_std_init: 0.0 })?;
+        let mean = actor.forward(&obs);
+        let sample = |seed| -> Result<Tensor> {
+            actor.sample(&obs, true, &mut ChaCha8Rng::seed_from_u64(seed))
+        };
+
+        // Noise is reproducible with the seed
+        let (a1, a2, a3) = (sample(0)?, sample(0)?, sample(1)?);
+        assert_eq!(a1.to_vec2::<f32>()?, a2.to_vec2::<f32>()?);
+        assert_ne!(a1.to_vec2::<f32>()?, a3.to_vec2::<f32>()?);
+
+        // Noise follows the standard normal distribution
+        let noise = (a1 - mean)?.flatten_all()?;
+        let m = noise.mean_all()?.to_scalar::<f32>()?;
+        let v = noise.sqr()?.mean_all()?.to_scalar::<f32>()?;
+        assert!(m.abs() < 0.1);
+        assert!((v - 1.0).abs() < 0.1);
+
+        Ok(())
+    }
+}
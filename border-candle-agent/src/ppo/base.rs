@@ -0,0 +1,483 @@
+//! Proximal policy optimization (PPO) agent implemented with candle.
+use super::{PpoActor, PpoConfig};
+use crate::{
+    iql::Value,
+    model::SubModel1,
+    util::{load_yaml, named_grad_stats, save_yaml, OutDim},
+};
+use anyhow::Result;
+use border_core::{
+    generic_replay_buffer::{BatchBase, RolloutBuffer},
+    record::{Record, RecordValue},
+    Agent, Configurable, Env, ExperienceBufferBase, Policy, ReplayBufferBase, TransitionBatch,
+};
+use candle_core::{DType, Device, Tensor, D};
+use candle_nn::loss::mse;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+/// Proximal policy optimization (PPO) agent.
+///
+/// The agent is trained with [`RolloutBuffer`]. In an optimization step, the agent
+/// computes the log probabilities of the actions and the values of the observations
+/// in the rollout, gives them to the buffer to estimate advantages with GAE, updates
+/// the actor and the value function over `n_epochs` epochs of minibatches, then clears
+/// the buffer. The actor is updated with the clipped surrogate objective and the entropy
+/// bonus, while the value function, which has its own optimizer, is updated with the
+/// optionally clipped squared error.
+///
+/// As the buffer becomes ready when a rollout has been collected, [`Trainer`] should be
+/// configured with `opt_interval = 1` and `warmup_period = 0`.
+///
+/// [`Trainer`]: border_core::Trainer
+pub struct Ppo<E, P, V, R>
+where
+    P: SubModel1<Output = Tensor>,
+    V: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+{
+    actor: PpoActor<P>,
+    value: Value<V>,
+    n_epochs: usize,
+    batch_size: usize,
+    clip_range: f64,
+    clip_range_vf: Option<f64>,
+    ent_coef: f64,
+    normalize_advantage: bool,
+    train: bool,
+    n_opts: usize,
+    record_verbose_level: usize,
+    rng: ChaCha8Rng,
+    device: Device,
+    phantom: PhantomData<(E, R)>,
+}
+
+/// Training state of [`Ppo`] saved in checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct PpoState {
+    n_opts: usize,
+    rng_seed: [u8; 32],
+    rng_word_pos: u64,
+}
+
+/// Losses and statistics of a minibatch update.
+#[derive(Default)]
+struct PpoStats {
+    loss_actor: f32,
+    loss_value: f32,
+    entropy: f32,
+    approx_kl: f32,
+    clip_fraction: f32,
+}
+
+impl<E, P, V, O, A> Ppo<E, P, V, RolloutBuffer<O, A>>
+where
+    E: Env,
+    P: SubModel1<Output = Tensor>,
+    V: SubModel1<Input = P::Input, Output = Tensor>,
+    O: BatchBase + Into<P::Input>,
+    A: BatchBase + Into<Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+{
+    /// Returns a tensor on the device of the agent.
+    fn tensor(&self, v: &[f32]) -> Result<Tensor> {
+        Ok(Tensor::from_slice(v, (v.len(),), &self.device)?)
+    }
+
+    /// Gives the log probabilities and the values of the transitions in the buffer to it.
+    fn set_values(&mut self, buffer: &mut RolloutBuffer<O, A>) -> Result<()> {
+        let (obs, act, next_obs, _, _, _, _, _) = buffer.transitions().unpack();
+        let obs = obs.into();
+        let (logp, _) = self.actor.logp_entropy(&obs, &act.into())?;
+        let value = self.value.forward(&obs).squeeze(D::Minus1)?;
+        let next_value = self.value.forward(&next_obs.into()).squeeze(D::Minus1)?;
+
+        buffer.set_values(
+            logp.detach().to_vec1()?,
+            value.detach().to_vec1()?,
+            next_value.detach().to_vec1()?,
+        )
+    }
+
+    fn update(&mut self, buffer: &mut RolloutBuffer<O, A>) -> Result<PpoStats> {
+        let batch = buffer.batch(self.batch_size.min(buffer.len()))?;
+        let obs = batch.obs.into();
+        let act = batch.act.into();
+        let logp_old = self.tensor(&batch.logp)?;
+        let value_old = self.tensor(&batch.value)?;
+        let ret = self.tensor(&batch.ret)?;
+        let adv = {
+            let adv = self.tensor(&batch.advantage)?;
+            match self.normalize_advantage && batch.advantage.len() > 1 {
+                true => {
+                    let centered = adv.broadcast_sub(&adv.mean_all()?)?;
+                    let std = centered.sqr()?.mean_all()?.sqrt()?;
+                    centered.broadcast_div(&(std + 1e-8)?)?
+                }
+                false => adv,
+            }
+        };
+
+        // Actor
+        let (logp, entropy) = self.actor.logp_entropy(&obs, &act)?;
+        let ratio = (&logp - &logp_old)?.exp()?;
+        let (loss_actor, entropy) = {
+            let surr1 = (&ratio * &adv)?;
+            let surr2 = (ratio.clamp(1.0 - self.clip_range, 1.0 + self.clip_range)? * &adv)?;
+            let loss_pi = surr1.minimum(&surr2)?.mean_all()?.neg()?;
+            let entropy = entropy.mean_all()?;
+            ((loss_pi - (self.ent_coef * &entropy)?)?, entropy)
+        };
+        self.actor.backward_step(&loss_actor)?;
+
+        // Value function
+        let value = self.value.forward(&obs).squeeze(D::Minus1)?;
+        let loss_value = match self.clip_range_vf {
+            None => mse(&value, &ret)?,
+            Some(c) => {
+                let value_clipped = (&value_old + (&value - &value_old)?.clamp(-c, c)?)?;
+                let loss1 = (&value - &ret)?.sqr()?;
+                let loss2 = (&value_clipped - &ret)?.sqr()?;
+                loss1.maximum(&loss2)?.mean_all()?
+            }
+        };
+        self.value.backward_step(&loss_value)?;
+
+        // Statistics
+        let approx_kl = (&logp_old - &logp)?.mean_all()?.to_scalar::<f32>()?;
+        let clip_fraction = (ratio - 1.0)?
+            .abs()?
+            .gt(self.clip_range)?
+            .to_dtype(DType::F32)?
+            .mean_all()?
+            .to_scalar::<f32>()?;
+
+        Ok(PpoStats {
+            loss_actor: loss_actor.to_scalar::<f32>()?,
+            loss_value: loss_value.to_scalar::<f32>()?,
+            entropy: entropy.to_scalar::<f32>()?,
+            approx_kl,
+            clip_fraction,
+        })
+    }
+
+    /// Returns the norms of gradients of the models in the last update, before clipping.
+    fn grad_stats(&self) -> Result<Record> {
+        let record = named_grad_stats(self.actor.grad_stats()?, "actor");
+        Ok(record.merge(named_grad_stats(self.value.grad_stats()?, "value")))
+    }
+
+    fn opt_(&mut self, buffer: &mut RolloutBuffer<O, A>) -> Result<Record> {
+        self.set_values(buffer)?;
+
+        let n_updates = self.n_epochs * (buffer.len() / self.batch_size).max(1);
+        let mut stats = PpoStats::default();
+        for _ in 0..n_updates {
+            let s = self.update(buffer)?;
+            stats.loss_actor += s.loss_actor;
+            stats.loss_value += s.loss_value;
+            stats.entropy += s.entropy;
+            stats.approx_kl += s.approx_kl;
+            stats.clip_fraction += s.clip_fraction;
+        }
+        buffer.clear();
+        self.n_opts += 1;
+
+        let n = n_updates as f32;
+        let record = Record::from_slice(&[
+            ("loss_actor", RecordValue::Scalar(stats.loss_actor / n)),
+            ("loss_value", RecordValue::Scalar(stats.loss_value / n)),
+            ("entropy", RecordValue::Scalar(stats.entropy / n)),
+            ("approx_kl", RecordValue::Scalar(stats.approx_kl / n)),
+            (
+                "clip_fraction",
+                RecordValue::Scalar(stats.clip_fraction / n),
+            ),
+        ]);
+
+        Ok(record)
+    }
+}
+
+impl<E, P, V, R> Policy<E> for Ppo<E, P, V, R>
+where
+    E: Env,
+    P: SubModel1<Output = Tensor>,
+    V: SubModel1<Output = Tensor>,
+    E::Obs: Into<P::Input>,
+    E::Act: From<Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+{
+    fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        self.actor
+            .sample(&obs.clone().into(), self.train, &mut self.rng)
+            .unwrap()
+            .into()
+    }
+}
+
+impl<E, P, V, R> Configurable for Ppo<E, P, V, R>
+where
+    E: Env,
+    P: SubModel1<Output = Tensor>,
+    V: SubModel1<Output = Tensor>,
+    E::Obs: Into<P::Input>,
+    E::Act: From<Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+{
+    type Config = PpoConfig<P, V>;
+
+    /// Constructs [`Ppo`] agent.
+    fn build(config: Self::Config) -> Self {
+        let device: Device = config
+            .device
+            .expect("No device is given for PPO agent")
+            .into();
+        let actor = PpoActor::build(config.actor_config, device.clone()).unwrap();
+        let value = Value::build(config.value_config, device.clone()).unwrap();
+
+        Ppo {
+            actor,
+            value,
+            n_epochs: config.n_epochs,
+            batch_size: config.batch_size,
+            clip_range: config.clip_range,
+            clip_range_vf: config.clip_range_vf,
+            ent_coef: config.ent_coef,
+            normalize_advantage: config.normalize_advantage,
+            train: false,
+            n_opts: 0,
+            record_verbose_level: config.record_verbose_level,
+            rng: ChaCha8Rng::seed_from_u64(config.seed),
+            device,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, P, V, O, A> Agent<E, RolloutBuffer<O, A>> for Ppo<E, P, V, RolloutBuffer<O, A>>
+where
+    E: Env + 'static,
+    P: SubModel1<Output = Tensor> + 'static,
+    V: SubModel1<Input = P::Input, Output = Tensor> + 'static,
+    O: BatchBase + Into<P::Input> + 'static,
+    A: BatchBase + Into<Tensor> + 'static,
+    E::Obs: Into<P::Input>,
+    E::Act: From<Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+{
+    fn train(&mut self) {
+        self.train = true;
+    }
+
+    fn eval(&mut self) {
+        self.train = false;
+    }
+
+    fn is_train(&self) -> bool {
+        self.train
+    }
+
+    fn opt_with_record(&mut self, buffer: &mut RolloutBuffer<O, A>) -> Record {
+        let record = self.opt_(buffer).expect("Failed in Ppo::opt_()");
+
+        match self.record_verbose_level >= 2 {
+            true => record.merge(self.grad_stats().expect("Failed in Ppo::grad_stats()")),
+            false => record,
+        }
+    }
+
+    fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        // TODO: consider to rename the path if it already exists
+        fs::create_dir_all(path)?;
+
+        let actor_path = self.actor.save(path.join("actor"))?;
+        let value_path = self.value.save(path.join("value"))?;
+
+        Ok(vec![actor_path, value_path])
+    }
+
+    fn load_params(&mut self, path: &Path) -> Result<()> {
+        self.actor.load(path.join("actor").as_path())?;
+        self.value.load(path.join("value").as_path())?;
+
+        Ok(())
+    }
+
+    /// Save the state of the agent for resuming training in the given directory.
+    ///
+    /// In addition to the files saved by [`Agent::save_params`], the states of the optimizers
+    /// are saved, and the number of optimization steps and the random number generator are
+    /// saved in `agent.yaml`.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+        paths.push(self.actor.save_opt(path.join("actor"))?);
+        paths.push(self.value.save_opt(path.join("value"))?);
+
+        let state_path = path.join("agent.yaml");
+        let state = PpoState {
+            n_opts: self.n_opts,
+            rng_seed: self.rng.get_seed(),
+            rng_word_pos: self.rng.get_word_pos() as u64,
+        };
+        save_yaml(&state_path, &state)?;
+        paths.push(state_path);
+
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+        self.actor.load_opt(path.join("actor"))?;
+        self.value.load_opt(path.join("value"))?;
+
+        let state: PpoState = load_yaml(path.join("agent.yaml"))?;
+        self.n_opts = state.n_opts;
+        self.rng = ChaCha8Rng::from_seed(state.rng_seed);
+        self.rng.set_word_pos(state.rng_word_pos as u128);
+
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.actor.set_learning_rate(lr);
+        self.value.set_learning_rate(lr);
+        Ok(())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        iql::ValueConfig,
+        mlp::{Mlp, MlpConfig},
+        ppo::{PpoActionType, PpoActorConfig},
+        Activation, TensorBatch,
+    };
+    use border_core::{
+        dummy::DummyEnv,
+        generic_replay_buffer::{GenericTransitionBatch, RolloutBufferConfig},
+    };
+
+    type Buffer = RolloutBuffer<TensorBatch, TensorBatch>;
+
+    fn ppo() -> Result<Ppo<DummyEnv, Mlp, Mlp, Buffer>> {
+        let actor_config = PpoActorConfig::default()
+            .policy_config(MlpConfig::new(3, vec![8], 2, Activation::None))
+            .action_type(PpoActionType::Continuous { log_std_init: 0.0 });
+        let value_config =
+            ValueConfig::default().value_config(MlpConfig::new(3, vec![8], 1, Activation::None));
+
+        Ok(Ppo {
+            actor: PpoActor::build(actor_config, Device::Cpu)?,
+            value: Value::build(value_config, Device::Cpu)?,
+            n_epochs: 4,
+            batch_size: 4,
+            clip_range: 0.2,
+            clip_range_vf: None,
+            ent_coef: 0.0,
+            normalize_advantage: true,
+            train: true,
+            n_opts: 0,
+            record_verbose_level: 0,
+            rng: ChaCha8Rng::seed_from_u64(42),
+            device: Device::Cpu,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Pushes a rollout of 16 single-step episodes with reward 1.
+    fn push_rollout(buffer: &mut Buffer) -> Result<()> {
+        let randn = |dim| -> Result<TensorBatch> {
+            Ok(TensorBatch::from_tensor(Tensor::randn(
+                0f32,
+                1f32,
+                (1, dim),
+                &Device::Cpu,
+            )?))
+        };
+        for _ in 0..16 {
+            buffer.push(GenericTransitionBatch {
+                obs: randn(3)?,
+                act: randn(2)?,
+                next_obs: randn(3)?,
+                reward: vec![1.0],
+                is_terminated: vec![1],
+                is_truncated: vec![0],
+                n_step: None,
+                ix_sample: None,
+                weight: None,
+            })?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_opt() -> Result<()> {
+        let mut agent = ppo()?;
+        let mut buffer = Buffer::build(&RolloutBufferConfig::default().capacity(16));
+
+        let mut loss_value = vec![];
+        for _ in 0..100 {
+            push_rollout(&mut buffer)?;
+            assert!(buffer.is_ready());
+
+            let record = agent.opt_(&mut buffer)?;
+            for key in [
+                "loss_actor",
+                "loss_value",
+                "entropy",
+                "approx_kl",
+                "clip_fraction",
+            ] {
+                assert!(record.get_scalar(key)?.is_finite());
+            }
+            loss_value.push(record.get_scalar("loss_value")?);
+
+            // The rollout is consumed in an optimization step
+            assert_eq!(buffer.len(), 0);
+        }
+        assert_eq!(agent.n_opts, 100);
+
+        // The value function learns the return of the single-step episodes
+        assert!(loss_value[99] < 0.5 * loss_value[0]);
+
+        Ok(())
+    }
+    #[test]
+    fn test_grad_stats() -> Result<()> {
+        let mut agent = ppo()?;
+        let mut buffer = Buffer::build(&RolloutBufferConfig::default().capacity(16));
+        push_rollout(&mut buffer)?;
+        agent.opt_(&mut buffer)?;
+
+        let record = agent.grad_stats()?;
+        for key in ["actor_grad_norm", "value_grad_norm"] {
+            let grad_norm = record.get_scalar(key)?;
+            assert!(grad_norm.is_finite() && grad_norm > 0.0);
+        }
+
+        Ok(())
+    }
+}
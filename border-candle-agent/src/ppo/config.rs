@@ -0,0 +1,211 @@
+//! Configuration of PPO agent.
+use super::{PpoActionType, PpoActorConfig};
+use crate::{iql::ValueConfig, model::SubModel1, util::OutDim, Device};
+use anyhow::Result;
+use candle_core::Tensor;
+use log::info;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
+
+/// Configuration of [`Ppo`](super::Ppo).
+///
+/// The discount factor and the parameter of GAE are given to
+/// [`RolloutBufferConfig`](border_core::generic_replay_buffer::RolloutBufferConfig), as
+/// advantages are estimated in the rollout buffer.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct PpoConfig<P, V>
+where
+    P: SubModel1<Output = Tensor>,
+    V: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+{
+    /// Configuration of the actor model.
+    pub actor_config: PpoActorConfig<P::Config>,
+
+    /// Configuration of the value model.
+    pub value_config: ValueConfig<V::Config>,
+
+    /// Number of epochs over the rollout in an optimization step.
+    pub n_epochs: usize,
+
+    /// Minibatch size.
+    pub batch_size: usize,
+
+    /// Clipping parameter of the probability ratio in the surrogate objective.
+    pub clip_range: f64,
+
+    /// Clipping parameter of the difference between the new and old values.
+    /// If `None`, the value function is not clipped.
+    pub clip_range_vf: Option<f64>,
+
+    /// Coefficient of the entropy bonus.
+    pub ent_coef: f64,
+
+    /// If `true`, advantages are normalized within each minibatch.
+    pub normalize_advantage: bool,
+
+    /// Random seed for sampling discrete actions.
+    pub seed: u64,
+
+    /// Device used for the actor and value models (e.g., CPU or GPU).
+    pub device: Option<Device>,
+
+    /// Verbose level of records.
+    ///
+    /// If it is 2 or more, the norms of gradients are recorded in optimization steps.
+    #[serde(default)]
+    pub record_verbose_level: usize,
+}
+
+impl<P, V> Clone for PpoConfig<P, V>
+where
+    P: SubModel1<Output = Tensor>,
+    V: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            actor_config: self.actor_config.clone(),
+            value_config: self.value_config.clone(),
+            n_epochs: self.n_epochs,
+            batch_size: self.batch_size,
+            clip_range: self.clip_range,
+            clip_range_vf: self.clip_range_vf,
+            ent_coef: self.ent_coef,
+            normalize_advantage: self.normalize_advantage,
+            seed: self.seed,
+            device: self.device,
+            record_verbose_level: self.record_verbose_level,
+        }
+    }
+}
+
+impl<P, V> Default for PpoConfig<P, V>
+where
+    P: SubModel1<Output = Tensor>,
+    V: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+{
+    fn default() -> Self {
+        Self {
+            actor_config: Default::default(),
+            value_config: Default::default(),
+            n_epochs: 10,
+            batch_size: 64,
+            clip_range: 0.2,
+            clip_range_vf: None,
+            ent_coef: 0.0,
+            normalize_advantage: true,
+            seed: 42,
+            device: None,
+            record_verbose_level: 0,
+        }
+    }
+}
+
+impl<P, V> PpoConfig<P, V>
+where
+    P: SubModel1<Output = Tensor>,
+    V: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+{
+    /// Configuration of actor.
+    pub fn actor_config(mut self, actor_config: PpoActorConfig<P::Config>) -> Self {
+        self.actor_config = actor_config;
+        self
+    }
+
+    /// Configuration of value function.
+    pub fn value_config(mut self, value_config: ValueConfig<V::Config>) -> Self {
+        self.value_config = value_config;
+        self
+    }
+
+    /// Sets action type.
+    pub fn action_type(mut self, v: PpoActionType) -> Self {
+        self.actor_config.action_type = v;
+        self
+    }
+
+    /// Number of epochs in an optimization step.
+    pub fn n_epochs(mut self, v: usize) -> Self {
+        self.n_epochs = v;
+        self
+    }
+
+    /// Minibatch size.
+    pub fn batch_size(mut self, v: usize) -> Self {
+        self.batch_size = v;
+        self
+    }
+
+    /// Clipping parameter of the probability ratio.
+    pub fn clip_range(mut self, v: f64) -> Self {
+        self.clip_range = v;
+        self
+    }
+
+    /// Clipping parameter of the value function.
+    pub fn clip_range_vf(mut self, v: f64) -> Self {
+        self.clip_range_vf = Some(v);
+        self
+    }
+
+    /// Coefficient of the entropy bonus.
+    pub fn ent_coef(mut self, v: f64) -> Self {
+        self.ent_coef = v;
+        self
+    }
+
+    /// If true, advantages are normalized within each minibatch.
+    pub fn normalize_advantage(mut self, b: bool) -> Self {
+        self.normalize_advantage = b;
+        self
+    }
+
+    /// Random seed.
+    pub fn seed(mut self, v: u64) -> Self {
+        self.seed = v;
+        self
+    }
+
+    /// Device.
+    pub fn device(mut self, device: candle_core::Device) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+
+    /// Sets verbose level.
+    pub fn record_verbose_level(mut self, v: usize) -> Self {
+        self.record_verbose_level = v;
+        self
+    }
+
+    /// Saves [`PpoConfig`] to YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path_ = path.as_ref().to_owned();
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        info!("Save config of PPO agent into {}", path_.to_str().unwrap());
+        Ok(())
+    }
+
+    /// Constructs [`PpoConfig`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path_ = path.as_ref().to_owned();
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        info!("Load config of PPO agent from {}", path_.to_str().unwrap());
+        Ok(b)
+    }
+}
@@ -2,8 +2,8 @@ use super::{EntCoef, SacConfig};
 use crate::{
     model::{SubModel1, SubModel2},
     util::{
-        actor::GaussianActor, critic::MultiCritic, gamma_not_done, smooth_l1_loss, CriticLoss,
-        OutDim,
+        actor::GaussianActor, critic::MultiCritic, gamma_n_not_done, insert_scheduled, load_yaml,
+        named_grad_stats, save_yaml, smooth_l1_loss, CriticLoss, OutDim,
     },
 };
 use anyhow::Result;
@@ -13,7 +13,7 @@ use border_core::{
 };
 use candle_core::{Device, Tensor, D};
 use candle_nn::loss::mse;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     fs,
     marker::PhantomData,
@@ -40,11 +40,18 @@ where
     batch_size: usize,
     train: bool,
     n_opts: usize,
+    record_verbose_level: usize,
     critic_loss: CriticLoss,
     phantom: PhantomData<(E, R)>,
     device: Device,
 }
 
+/// Training state of [`Sac`] saved in checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct SacState {
+    n_opts: usize,
+}
+
 impl<E, Q, P, R> Sac<E, Q, P, R>
 where
     E: Env,
@@ -63,6 +70,7 @@ where
     fn update_critic(&mut self, batch: R::Batch) -> Result<f32> {
         let loss = {
             // Extract items in the batch
+            let n_step = batch.n_step().map(|n_step| n_step.to_vec());
             let (obs, act, next_obs, reward, is_terminated, _, _, _) = batch.unpack();
             let batch_size = reward.len();
             let reward = Tensor::from_slice(&reward[..], (batch_size,), &self.device)?;
@@ -72,8 +80,12 @@ where
 
             // Target
             let tgt = {
-                let gamma_not_done =
-                    gamma_not_done(self.gamma as f32, is_terminated, None, &self.device)?;
+                let gamma_not_done = gamma_n_not_done(
+                    self.gamma as f32,
+                    is_terminated,
+                    n_step.as_deref(),
+                    &self.device,
+                )?;
                 let next_act = self.actor.sample(&next_obs.clone().into(), self.train)?;
                 let next_log_p = self.actor.logp(&next_obs.clone().into(), &next_act)?;
                 let next_q = self
@@ -121,6 +133,12 @@ where
         Ok(loss.to_scalar::<f32>()?)
     }
 
+    /// Returns the norms of gradients of the models in the last update, before clipping.
+    fn grad_stats(&self) -> Result<Record> {
+        let record = named_grad_stats(self.actor.grad_stats()?, "actor");
+        Ok(record.merge(named_grad_stats(self.critic.grad_stats()?, "critic")))
+    }
+
     fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
         let mut loss_critic = 0f32;
         let mut loss_actor = 0f32;
@@ -136,7 +154,7 @@ where
         loss_critic /= self.n_updates_per_opt as f32;
         loss_actor /= self.n_updates_per_opt as f32;
 
-        let record = Record::from_slice(&[
+        let mut record = Record::from_slice(&[
             ("loss_critic", RecordValue::Scalar(loss_critic)),
             ("loss_actor", RecordValue::Scalar(loss_actor)),
             (
@@ -145,6 +163,16 @@ where
             ),
         ]);
 
+        insert_scheduled(
+            &mut record,
+            &[
+                ("lr_actor", self.actor.scheduled_learning_rate()),
+                ("lr_critic", self.critic.scheduled_learning_rate()),
+                ("tau", self.critic.scheduled_tau()),
+                ("target_entropy", self.ent_coef.scheduled_target_entropy()),
+            ],
+        );
+
         Ok(record)
     }
 }
@@ -204,6 +232,7 @@ where
             train: false,
             critic_loss: config.critic_loss,
             n_opts: 0,
+            record_verbose_level: config.record_verbose_level,
             device: device.into(),
             phantom: PhantomData,
         }
@@ -238,7 +267,12 @@ where
     }
 
     fn opt_with_record(&mut self, buffer: &mut R) -> Record {
-        self.opt_(buffer).expect("Failed in Sac::opt_()")
+        let record = self.opt_(buffer).expect("Failed in Sac::opt_()");
+
+        match self.record_verbose_level >= 2 {
+            true => record.merge(self.grad_stats().expect("Failed in Sac::grad_stats()")),
+            false => record,
+        }
     }
 
     fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
@@ -269,6 +303,49 @@ where
         Ok(())
     }
 
+    /// Save the state of the agent for resuming training in the given directory.
+    ///
+    /// In addition to the files saved by [`Agent::save_params`], the states of the optimizers
+    /// and the number of optimization steps (`agent.yaml`) are saved.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+        paths.push(self.actor.save_opt(path.join("actor"))?);
+        paths.push(self.critic.save_opt(path.join("critic"))?);
+
+        let ent_coef_opt_path = path.join("ent_coef.opt.pt");
+        self.ent_coef.save_opt(&ent_coef_opt_path)?;
+        paths.push(ent_coef_opt_path);
+
+        let state_path = path.join("agent.yaml");
+        save_yaml(
+            &state_path,
+            &SacState {
+                n_opts: self.n_opts,
+            },
+        )?;
+        paths.push(state_path);
+
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+        self.actor.load_opt(path.join("actor"))?;
+        self.critic.load_opt(path.join("critic"))?;
+        self.ent_coef.load_opt(path.join("ent_coef.opt.pt"))?;
+
+        let state: SacState = load_yaml(path.join("agent.yaml"))?;
+        self.n_opts = state.n_opts;
+
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.actor.set_learning_rate(lr);
+        self.critic.set_learning_rate(lr);
+        Ok(())
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
@@ -49,6 +49,12 @@ where
 
     /// Device for actor/critic models.
     pub device: Option<Device>,
+
+    /// Verbose level of records.
+    ///
+    /// If it is 2 or more, the norms of gradients are recorded in optimization steps.
+    #[serde(default)]
+    pub record_verbose_level: usize,
 }
 
 impl<Q, P> Clone for SacConfig<Q, P>
@@ -68,6 +74,7 @@ where
             batch_size: self.batch_size.clone(),
             critic_loss: self.critic_loss.clone(),
             device: self.device.clone(),
+            record_verbose_level: self.record_verbose_level,
         }
     }
 }
@@ -89,6 +96,7 @@ where
             batch_size: 1,
             critic_loss: CriticLoss::Mse,
             device: None,
+            record_verbose_level: 0,
         }
     }
 }
@@ -148,6 +156,12 @@ where
         self
     }
 
+    /// Sets verbose level.
+    pub fn record_verbose_level(mut self, v: usize) -> Self {
+        self.record_verbose_level = v;
+        self
+    }
+
     /// Constructs [`SacConfig`] from YAML file.
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         let path_ = path.as_ref().to_owned();
@@ -3,6 +3,7 @@ use std::convert::TryFrom;
 
 use crate::opt::{Optimizer, OptimizerConfig};
 use anyhow::Result;
+use border_core::Schedule;
 use candle_core::{DType, Device, Tensor};
 use candle_nn::{init::Init, VarBuilder, VarMap};
 use log::info;
@@ -16,6 +17,21 @@ pub enum EntCoefMode {
     Fix(f64),
     /// Automatic tuning given `(target_entropy, learning_rate)`.
     Auto(f64, f64),
+    /// Automatic tuning given `(target_entropy_schedule, learning_rate)`.
+    ///
+    /// The target entropy is scheduled by the number of updates of the coefficient.
+    AutoScheduled(Schedule, f64),
+}
+
+impl EntCoefMode {
+    /// Automatic tuning for discrete actions.
+    ///
+    /// The target entropy is `ratio * ln(n_actions)`, i.e., `ratio` times the maximum entropy
+    /// of the categorical distribution over `n_actions` actions. The SAC-Discrete paper uses
+    /// `ratio = 0.98`.
+    pub fn auto_discrete(n_actions: usize, ratio: f64, learning_rate: f64) -> Self {
+        Self::Auto(ratio * (n_actions as f64).ln(), learning_rate)
+    }
 }
 
 /// The entropy coefficient of SAC.
@@ -23,6 +39,7 @@ pub struct EntCoef {
     varmap: VarMap,
     log_alpha: Tensor,
     target_entropy: Option<f64>,
+    target_entropy_schedule: Option<Schedule>,
     opt: Option<Optimizer>,
 }
 
@@ -31,19 +48,26 @@ impl EntCoef {
     pub fn new(mode: EntCoefMode, device: Device) -> Result<Self> {
         let varmap = VarMap::new();
         let vb = VarBuilder::from_varmap(&varmap, DType::F32, &device);
-        let (log_alpha, target_entropy, opt) = match mode {
+        let (target_entropy, target_entropy_schedule) = match &mode {
+            EntCoefMode::Fix(_) => (None, None),
+            EntCoefMode::Auto(target_entropy, _) => (Some(*target_entropy), None),
+            EntCoefMode::AutoScheduled(schedule, _) => {
+                (Some(schedule.value(0)), Some(schedule.clone()))
+            }
+        };
+        let (log_alpha, opt) = match mode {
             EntCoefMode::Fix(alpha) => {
                 let init = Init::Const(alpha.ln());
                 let log_alpha = vb.get_with_hints(1, "log_alpha", init)?;
-                (log_alpha, None, None)
+                (log_alpha, None)
             }
-            EntCoefMode::Auto(target_entropy, learning_rate) => {
+            EntCoefMode::Auto(_, learning_rate) | EntCoefMode::AutoScheduled(_, learning_rate) => {
                 let init = Init::Const(0.0);
                 let log_alpha = vb.get_with_hints(1, "log_alpha", init)?;
                 let opt = OptimizerConfig::default()
                     .learning_rate(learning_rate)
                     .build(varmap.all_vars())?;
-                (log_alpha, Some(target_entropy), Some(opt))
+                (log_alpha, Some(opt))
             }
         };
 
@@ -52,6 +76,7 @@ impl EntCoef {
             log_alpha,
             opt,
             target_entropy,
+            target_entropy_schedule,
         })
     }
 
@@ -67,8 +92,18 @@ impl EntCoef {
         }
     }
 
+    /// Returns the target entropy if it is scheduled.
+    pub fn scheduled_target_entropy(&self) -> Option<f64> {
+        self.target_entropy_schedule
+            .as_ref()
+            .and(self.target_entropy)
+    }
+
     /// Update the parameter given an action probability vector.
     pub fn update(&mut self, logp: &Tensor) -> Result<()> {
+        if let (Some(schedule), Some(opt)) = (&self.target_entropy_schedule, &self.opt) {
+            self.target_entropy = Some(schedule.value(opt.n_steps()));
+        }
         if let Some(target_entropy) = &self.target_entropy {
             let target_entropy =
                 Tensor::try_from(*target_entropy as f32)?.to_device(logp.device())?;
@@ -96,4 +131,28 @@ impl EntCoef {
         info!("Load entropy coefficient from {:?}", path.as_ref());
         Ok(())
     }
+
+    /// Save the state of the optimizer into a file, if the coefficient is tuned automatically.
+    pub fn save_opt<T: AsRef<Path>>(&self, path: T) -> Result<()> {
+        if let Some(opt) = &self.opt {
+            opt.save(&path, &self.varmap)?;
+            info!(
+                "Save optimizer of entropy coefficient to {:?}",
+                path.as_ref()
+            );
+        }
+        Ok(())
+    }
+
+    /// Load the state of the optimizer from a file, if the coefficient is tuned automatically.
+    pub fn load_opt<T: AsRef<Path>>(&mut self, path: T) -> Result<()> {
+        if let Some(opt) = &mut self.opt {
+            opt.load(&path, &self.varmap)?;
+            info!(
+                "Load optimizer of entropy coefficient from {:?}",
+                path.as_ref()
+            );
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,17 @@
+//! Soft actor-critic agent for discrete actions (SAC-Discrete).
+//!
+//! The actor outputs a categorical distribution and the critics output action values of
+//! all actions. The expectations over actions in the actor and critic losses are computed
+//! exactly, instead of sampling actions. See [Christodoulou (2019)](https://arxiv.org/abs/1910.07207).
+//!
+//! Actions are `i64` tensors of the action indices, which is the same layout as [`Dqn`](crate::dqn::Dqn).
+//! For the automatic tuning of the entropy coefficient, [`EntCoefMode::auto_discrete()`](crate::sac::EntCoefMode::auto_discrete)
+//! gives the target entropy relative to the maximum entropy of the categorical distribution.
+mod actor;
+mod base;
+mod config;
+mod critic;
+pub use actor::{CategoricalActor, CategoricalActorConfig};
+pub use base::SacDiscrete;
+pub use config::SacDiscreteConfig;
+pub use critic::DiscreteCritic;
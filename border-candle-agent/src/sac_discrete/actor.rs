@@ -0,0 +1,252 @@
+//! Categorical policy of SAC-Discrete agent.
+use crate::{
+    model::SubModel1,
+    opt::{Optimizer, OptimizerConfig},
+    util::OutDim,
+};
+use anyhow::{Context, Result};
+use candle_core::{DType, Device, Tensor, D};
+use candle_nn::{
+    ops::{log_softmax, softmax},
+    VarBuilder, VarMap,
+};
+use log::info;
+use rand::{distributions::WeightedIndex, Rng};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+/// Configuration of [`CategoricalActor`].
+pub struct CategoricalActorConfig<P: OutDim> {
+    pub policy_config: Option<P>,
+    pub opt_config: OptimizerConfig,
+}
+
+impl<P: OutDim> Default for CategoricalActorConfig<P> {
+    fn default() -> Self {
+        Self {
+            policy_config: None,
+            opt_config: OptimizerConfig::Adam { lr: 0.0003 },
+        }
+    }
+}
+
+impl<P> CategoricalActorConfig<P>
+where
+    P: DeserializeOwned + Serialize + OutDim,
+{
+    /// Sets configurations for policy function.
+    pub fn policy_config(mut self, v: P) -> Self {
+        self.policy_config = Some(v);
+        self
+    }
+
+    /// Sets output dimension of the model, i.e., the number of actions.
+    pub fn out_dim(mut self, v: i64) -> Self {
+        match &mut self.policy_config {
+            None => {}
+            Some(pi_config) => pi_config.set_out_dim(v),
+        };
+        self
+    }
+
+    /// Sets optimizer configuration.
+    pub fn opt_config(mut self, v: OptimizerConfig) -> Self {
+        self.opt_config = v;
+        self
+    }
+
+    /// Loads [`CategoricalActorConfig`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        Ok(b)
+    }
+
+    /// Saves [`CategoricalActorConfig`] as YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Stochastic policy with a categorical distribution.
+///
+/// The output of the policy model is regarded as the logits of the distribution.
+pub struct CategoricalActor<P>
+where
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Clone,
+{
+    varmap: VarMap,
+
+    // Policy model
+    policy: P,
+
+    // Optimizer
+    opt: Optimizer,
+}
+
+impl<P> CategoricalActor<P>
+where
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Clone,
+{
+    /// Constructs [`CategoricalActor`].
+    pub fn build(
+        config: CategoricalActorConfig<P::Config>,
+        device: Device,
+    ) -> Result<CategoricalActor<P>> {
+        let policy_config = config.policy_config.context("policy_config is not set.")?;
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &device);
+        let policy = P::build(vb.pp("actor"), policy_config);
+        let opt = config.opt_config.build(varmap.all_vars())?;
+
+        Ok(Self {
+            varmap,
+            policy,
+            opt,
+        })
+    }
+
+    /// Returns the logits of the action distribution.
+    pub fn forward(&self, obs: &P::Input) -> Tensor {
+        self.policy.forward(obs)
+    }
+
+    /// Returns the probabilities and the log probabilities of all actions.
+    ///
+    /// The shape of the both tensors is `(batch_size, n_actions)`.
+    pub fn probs_logps(&self, obs: &P::Input) -> Result<(Tensor, Tensor)> {
+        let logits = self.forward(obs);
+        let probs = softmax(&logits, D::Minus1)?;
+        let logps = log_softmax(&logits, D::Minus1)?;
+        Ok((probs, logps))
+    }
+
+    /// Samples actions.
+    ///
+    /// If `train` is `true`, actions are sampled from the distribution. Otherwise, the action
+    /// with the maximum probability is returned. The shape of the output is `(batch_size,)`.
+    pub fn sample(&self, obs: &P::Input, train: bool, rng: &mut impl Rng) -> Result<Tensor> {
+        let logits = self.forward(obs).detach();
+
+        match train {
+            true => {
+                let probs = softmax(&logits, D::Minus1)?.to_vec2::<f32>()?;
+                let n_samples = probs.len();
+                let data = probs
+                    .iter()
+                    .map(|p| Ok(rng.sample(WeightedIndex::new(p)?) as i64))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Tensor::from_vec(data, &[n_samples], logits.device())?)
+            }
+            false => Ok(logits.argmax(D::Minus1)?.to_dtype(DType::I64)?),
+        }
+    }
+
+    /// Backward step for all variables of the actor.
+    pub fn backward_step(&mut self, loss: &Tensor) -> Result<()> {
+        self.opt.backward_step(loss)
+    }
+
+    /// Save variables to prefix + ".pt".
+    pub fn save(&self, prefix: impl AsRef<Path>) -> Result<PathBuf> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("pt");
+        self.varmap.save(path.as_path())?;
+        info!("Save actor parameters to {:?}", path);
+
+        Ok(path)
+    }
+
+    /// Load variables from prefix + ".pt".
+    pub fn load(&mut self, prefix: impl AsRef<Path>) -> Result<()> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("pt");
+        self.varmap.load(path.as_path())?;
+        info!("Load actor parameters from {:?}", path);
+
+        Ok(())
+    }
+
+    /// Save the state of the optimizer to prefix + ".opt.pt".
+    pub fn save_opt(&self, prefix: impl AsRef<Path>) -> Result<PathBuf> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("opt.pt");
+        self.opt.save(&path, &self.varmap)?;
+        info!("Save actor optimizer to {:?}", path);
+
+        Ok(path)
+    }
+
+    /// Load the state of the optimizer from prefix + ".opt.pt".
+    pub fn load_opt(&mut self, prefix: impl AsRef<Path>) -> Result<()> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("opt.pt");
+        self.opt.load(&path, &self.varmap)?;
+        info!("Load actor optimizer from {:?}", path);
+
+        Ok(())
+    }
+
+    /// Sets the learning rate of the optimizer.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.opt.set_learning_rate(lr);
+    }
+
+    /// Returns the learning rate used in the last update if it is scheduled.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        self.opt.scheduled_learning_rate()
+    }
+
+    /// Returns the norms of gradients in the last update, before clipping.
+    pub fn grad_stats(&self) -> Result<border_core::record::Record> {
+        self.opt.grad_stats(&self.varmap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mlp::{Mlp, MlpConfig},
+        Activation,
+    };
+
+    #[test]
+    fn test_probs_logps() -> Result<()> {
+        let config = CategoricalActorConfig::default().policy_config(MlpConfig::new(
+            3,
+            vec![8],
+            4,
+            Activation::None,
+        ));
+        let actor = CategoricalActor::<Mlp>::build(config, Device::Cpu)?;
+        let obs = Tensor::randn(0f32, 1f32, (5, 3), &Device::Cpu)?;
+        let mut rng = rand::thread_rng();
+
+        let (probs, logps) = actor.probs_logps(&obs)?;
+        assert_eq!(probs.dims(), [5, 4]);
+        let sum = probs.sum(D::Minus1)?.to_vec1::<f32>()?;
+        assert!(sum.iter().all(|s| (s - 1.0).abs() < 1e-5));
+        let diff = (probs.log()? - logps)?
+            .abs()?
+            .max_all()?
+            .to_scalar::<f32>()?;
+        assert!(diff < 1e-5);
+
+        let act = actor.sample(&obs, true, &mut rng)?;
+        assert_eq!(act.dims(), [5]);
+        assert_eq!(act.dtype(), DType::I64);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,446 @@
+use super::{CategoricalActor, DiscreteCritic, SacDiscreteConfig};
+use crate::{
+    model::SubModel1,
+    sac::EntCoef,
+    util::{
+        gamma_n_not_done, insert_scheduled, load_yaml, named_grad_stats, save_yaml, smooth_l1_loss,
+        CriticLoss, OutDim,
+    },
+};
+use anyhow::Result;
+use border_core::{
+    record::{Record, RecordValue},
+    Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
+};
+use candle_core::{DType, Device, Tensor, D};
+use candle_nn::loss::mse;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+/// Soft actor critic agent for discrete actions (SAC-Discrete).
+///
+/// The critic loss uses the soft state value of the next observations,
+/// `V(s') = sum_a pi(a|s') (min_i Q_tgt_i(s', a) - alpha * log pi(a|s'))`, and the actor loss is
+/// `sum_a pi(a|s) (alpha * log pi(a|s) - min_i Q_i(s, a))`, both computed exactly over actions.
+pub struct SacDiscrete<E, Q, P, R>
+where
+    Q: SubModel1<Output = Tensor>,
+    P: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    critic: DiscreteCritic<Q>,
+    actor: CategoricalActor<P>,
+    gamma: f64,
+    ent_coef: EntCoef,
+    n_updates_per_opt: usize,
+    batch_size: usize,
+    train: bool,
+    n_opts: usize,
+    record_verbose_level: usize,
+    critic_loss: CriticLoss,
+    rng: ChaCha8Rng,
+    phantom: PhantomData<(E, R)>,
+    device: Device,
+}
+
+/// Training state of [`SacDiscrete`] saved in checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct SacDiscreteState {
+    n_opts: usize,
+    rng_seed: [u8; 32],
+    rng_word_pos: u64,
+}
+
+impl<E, Q, P, R> SacDiscrete<E, Q, P, R>
+where
+    E: Env,
+    Q: SubModel1<Output = Tensor>,
+    P: SubModel1<Output = Tensor>,
+    R: ReplayBufferBase,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input> + Into<P::Input> + Clone,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
+{
+    fn update_critic(&mut self, batch: R::Batch) -> Result<f32> {
+        let loss = {
+            // Extract items in the batch
+            let n_step = batch.n_step().map(|n_step| n_step.to_vec());
+            let (obs, act, next_obs, reward, is_terminated, _, _, _) = batch.unpack();
+            let batch_size = reward.len();
+            let reward = Tensor::from_slice(&reward[..], (batch_size,), &self.device)?;
+            let act = act
+                .into()
+                .to_device(&self.device)?
+                .to_dtype(DType::I64)?
+                .reshape((batch_size, 1))?;
+
+            // Prediction
+            let qs: Vec<_> = self
+                .critic
+                .qvals(&obs.into())
+                .iter()
+                .map(|q| q.gather(&act, D::Minus1)?.squeeze(D::Minus1))
+                .collect::<Result<_, _>>()?;
+
+            // Target
+            let tgt = {
+                let gamma_not_done = gamma_n_not_done(
+                    self.gamma as f32,
+                    is_terminated,
+                    n_step.as_deref(),
+                    &self.device,
+                )?;
+                let (next_probs, next_logps) = self.actor.probs_logps(&next_obs.clone().into())?;
+                let next_q = self.critic.qvals_min_tgt(&next_obs.into())?;
+                let next_v = (next_q - self.ent_coef.alpha()?.broadcast_mul(&next_logps)?)?;
+                let next_v = (next_probs * next_v)?.sum(D::Minus1)?;
+                (&reward + (&gamma_not_done * next_v)?)?
+            }
+            .detach();
+            debug_assert_eq!(tgt.dims(), [self.batch_size]);
+
+            // Loss
+            let losses: Vec<_> = match self.critic_loss {
+                CriticLoss::Mse => qs.iter().map(|pred| mse(pred, &tgt).unwrap()).collect(),
+                CriticLoss::SmoothL1 => qs
+                    .iter()
+                    .map(|pred| smooth_l1_loss(pred, &tgt).unwrap())
+                    .collect(),
+            };
+            Tensor::stack(&losses, 0)?.mean_all()?
+        };
+
+        self.critic.backward_step(&loss)?;
+
+        Ok(loss.to_scalar::<f32>()?)
+    }
+
+    fn update_actor(&mut self, batch: &R::Batch) -> Result<(f32, f32)> {
+        let (loss, entropy) = {
+            let obs = batch.obs().clone();
+            let (probs, logps) = self.actor.probs_logps(&obs.clone().into())?;
+
+            // Update the entropy coefficient with the expected log probabilities
+            let logp = (&probs * &logps)?.sum(D::Minus1)?.detach();
+            self.ent_coef.update(&logp)?;
+
+            // Loss
+            let q = self.critic.qvals_min(&obs.into())?.detach();
+            let alpha = self.ent_coef.alpha()?.detach();
+            let loss = (probs * (alpha.broadcast_mul(&logps)? - q)?)?
+                .sum(D::Minus1)?
+                .mean_all()?;
+            (loss, logp.mean_all()?.neg()?)
+        };
+
+        self.actor.backward_step(&loss)?;
+
+        Ok((loss.to_scalar::<f32>()?, entropy.to_scalar::<f32>()?))
+    }
+
+    /// Returns the norms of gradients of the models in the last update, before clipping.
+    fn grad_stats(&self) -> Result<Record> {
+        let record = named_grad_stats(self.actor.grad_stats()?, "actor");
+        Ok(record.merge(named_grad_stats(self.critic.grad_stats()?, "critic")))
+    }
+
+    fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
+        let mut loss_critic = 0f32;
+        let mut loss_actor = 0f32;
+        let mut entropy = 0f32;
+
+        for _ in 0..self.n_updates_per_opt {
+            let batch = buffer.batch(self.batch_size).unwrap();
+            let (loss, ent) = self.update_actor(&batch)?;
+            loss_actor += loss;
+            entropy += ent;
+            loss_critic += self.update_critic(batch)?;
+            self.critic.soft_update()?;
+            self.n_opts += 1;
+        }
+
+        loss_critic /= self.n_updates_per_opt as f32;
+        loss_actor /= self.n_updates_per_opt as f32;
+        entropy /= self.n_updates_per_opt as f32;
+
+        let mut record = Record::from_slice(&[
+            ("loss_critic", RecordValue::Scalar(loss_critic)),
+            ("loss_actor", RecordValue::Scalar(loss_actor)),
+            ("entropy", RecordValue::Scalar(entropy)),
+            (
+                "ent_coef",
+                RecordValue::Scalar(self.ent_coef.alpha()?.to_vec1::<f32>()?[0]),
+            ),
+        ]);
+
+        insert_scheduled(
+            &mut record,
+            &[
+                ("lr_actor", self.actor.scheduled_learning_rate()),
+                ("lr_critic", self.critic.scheduled_learning_rate()),
+                ("tau", self.critic.scheduled_tau()),
+                ("target_entropy", self.ent_coef.scheduled_target_entropy()),
+            ],
+        );
+
+        Ok(record)
+    }
+}
+
+impl<E, Q, P, R> Policy<E> for SacDiscrete<E, Q, P, R>
+where
+    E: Env,
+    Q: SubModel1<Output = Tensor>,
+    P: SubModel1<Output = Tensor>,
+    E::Obs: Into<Q::Input> + Into<P::Input>,
+    E::Act: From<Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Samples actions from the policy in training mode, or takes the most probable
+    /// actions in evaluation mode.
+    fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        self.actor
+            .sample(&obs.clone().into(), self.train, &mut self.rng)
+            .unwrap()
+            .into()
+    }
+}
+
+impl<E, Q, P, R> Configurable for SacDiscrete<E, Q, P, R>
+where
+    E: Env,
+    Q: SubModel1<Output = Tensor>,
+    P: SubModel1<Output = Tensor>,
+    E::Obs: Into<Q::Input> + Into<P::Input>,
+    E::Act: From<Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    type Config = SacDiscreteConfig<Q, P>;
+
+    /// Constructs [`SacDiscrete`] agent.
+    fn build(config: Self::Config) -> Self {
+        let device: Device = config
+            .device
+            .expect("No device is given for SAC-Discrete agent")
+            .into();
+        let actor = CategoricalActor::build(config.actor_config, device.clone()).unwrap();
+        let critic = DiscreteCritic::build(config.critic_config, device.clone()).unwrap();
+        let ent_coef = EntCoef::new(config.ent_coef_mode, device.clone()).unwrap();
+
+        SacDiscrete {
+            actor,
+            critic,
+            gamma: config.gamma,
+            ent_coef,
+            n_updates_per_opt: config.n_updates_per_opt,
+            batch_size: config.batch_size,
+            train: false,
+            critic_loss: config.critic_loss,
+            n_opts: 0,
+            record_verbose_level: config.record_verbose_level,
+            rng: ChaCha8Rng::seed_from_u64(config.seed),
+            device,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, Q, P, R> Agent<E, R> for SacDiscrete<E, Q, P, R>
+where
+    E: Env + 'static,
+    Q: SubModel1<Output = Tensor> + 'static,
+    P: SubModel1<Output = Tensor> + 'static,
+    R: ReplayBufferBase + 'static,
+    E::Obs: Into<Q::Input> + Into<P::Input>,
+    E::Act: From<Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input> + Into<P::Input> + Clone,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
+{
+    fn train(&mut self) {
+        self.train = true;
+    }
+
+    fn eval(&mut self) {
+        self.train = false;
+    }
+
+    fn is_train(&self) -> bool {
+        self.train
+    }
+
+    fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        let record = self.opt_(buffer).expect("Failed in SacDiscrete::opt_()");
+
+        match self.record_verbose_level >= 2 {
+            true => record.merge(
+                self.grad_stats()
+                    .expect("Failed in SacDiscrete::grad_stats()"),
+            ),
+            false => record,
+        }
+    }
+
+    fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        // TODO: consider to rename the path if it already exists
+        fs::create_dir_all(path)?;
+
+        let actor_path = self.actor.save(path.join("actor"))?;
+        let (critic_path, critic_tgt_path) = self.critic.save(path.join("critic"))?;
+        let ent_coef_path = {
+            let ent_coef_path = path.join("ent_coef.pt").to_path_buf();
+            self.ent_coef.save(&ent_coef_path)?;
+            ent_coef_path
+        };
+
+        Ok(vec![
+            actor_path,
+            critic_path,
+            critic_tgt_path,
+            ent_coef_path,
+        ])
+    }
+
+    fn load_params(&mut self, path: &Path) -> Result<()> {
+        self.actor.load(path.join("actor").as_path())?;
+        self.critic.load(path.join("critic").as_path())?;
+        self.ent_coef.load(path.join("ent_coef.pt").as_path())?;
+
+        Ok(())
+    }
+
+    /// Save the state of the agent for resuming training in the given directory.
+    ///
+    /// In addition to the files saved by [`Agent::save_params`], the states of the optimizers
+    /// are saved, and the number of optimization steps and the random number generator are
+    /// saved in `agent.yaml`.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+        paths.push(self.actor.save_opt(path.join("actor"))?);
+        paths.push(self.critic.save_opt(path.join("critic"))?);
+
+        let ent_coef_opt_path = path.join("ent_coef.opt.pt");
+        self.ent_coef.save_opt(&ent_coef_opt_path)?;
+        paths.push(ent_coef_opt_path);
+
+        let state_path = path.join("agent.yaml");
+        let state = SacDiscreteState {
+            n_opts: self.n_opts,
+            rng_seed: self.rng.get_seed(),
+            rng_word_pos: self.rng.get_word_pos() as u64,
+        };
+        save_yaml(&state_path, &state)?;
+        paths.push(state_path);
+
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+        self.actor.load_opt(path.join("actor"))?;
+        self.critic.load_opt(path.join("critic"))?;
+        self.ent_coef.load_opt(path.join("ent_coef.opt.pt"))?;
+
+        let state: SacDiscreteState = load_yaml(path.join("agent.yaml"))?;
+        self.n_opts = state.n_opts;
+        self.rng = ChaCha8Rng::from_seed(state.rng_seed);
+        self.rng.set_word_pos(state.rng_word_pos as u128);
+
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.actor.set_learning_rate(lr);
+        self.critic.set_learning_rate(lr);
+        Ok(())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mlp::{Mlp, MlpConfig},
+        sac::EntCoefMode,
+        sac_discrete::CategoricalActorConfig,
+        test::{max_abs_diff, transitions, Buffer, TestEnv},
+        util::critic::MultiCriticConfig,
+        Activation,
+    };
+    use border_core::{generic_replay_buffer::SimpleReplayBufferConfig, ExperienceBufferBase};
+
+    const N_ACTIONS: usize = 4;
+
+    #[test]
+    fn test_opt() -> Result<()> {
+        for ratio in [0.5, 1.5] {
+            let target_entropy = ratio * (N_ACTIONS as f64).ln();
+            let actor_config = CategoricalActorConfig::default().policy_config(MlpConfig::new(
+                3,
+                vec![8],
+                N_ACTIONS as _,
+                Activation::None,
+            ));
+            let critic_config = MultiCriticConfig::default()
+                .q_config(MlpConfig::new(3, vec![8], N_ACTIONS as _, Activation::None))
+                .tau(1.0);
+            let config = SacDiscreteConfig::<Mlp, Mlp>::default()
+                .actor_config(actor_config)
+                .critic_config(critic_config)
+                .ent_coef_mode(EntCoefMode::auto_discrete(N_ACTIONS, ratio, 0.01))
+                .batch_size(4)
+                .device(Device::Cpu);
+            let mut agent = SacDiscrete::<TestEnv, Mlp, Mlp, Buffer>::build(config);
+
+            let obs = Tensor::randn(0f32, 1f32, (2, 3), &Device::Cpu)?;
+            let act = Tensor::from_vec(vec![0i64, 3], (2, 1), &Device::Cpu)?;
+            let next_obs = Tensor::randn(0f32, 1f32, (2, 3), &Device::Cpu)?;
+            let mut buffer = Buffer::build(&SimpleReplayBufferConfig::default().capacity(2));
+            buffer.push(transitions(obs.clone(), act, next_obs, vec![1.0, -1.0]))?;
+
+            for i in 0..5 {
+                let ent_coef = agent.ent_coef.alpha()?.to_vec1::<f32>()?[0];
+                let record = agent.opt_(&mut buffer)?;
+                assert!(record.get_scalar("loss_critic")?.is_finite());
+                assert!(record.get_scalar("loss_actor")?.is_finite());
+
+                // The entropy coefficient decreases if the entropy of the policy is higher
+                // than the target, and increases otherwise. This is checked in the first
+                // step, as the momentum of the optimizer affects the following steps.
+                if i == 0 {
+                    let entropy = record.get_scalar("entropy")? as f64;
+                    let diff = (record.get_scalar("ent_coef")? - ent_coef) as f64;
+                    assert!(diff * (entropy - target_entropy) < 0.0);
+                }
+
+                // The target critics are synchronized with `tau` of 1
+                let q = agent.critic.qvals_min(&obs)?;
+                let q_tgt = agent.critic.qvals_min_tgt(&obs)?;
+                assert!(max_abs_diff(&q, &q_tgt)? < 1e-6);
+            }
+        }
+
+        Ok(())
+    }
+}
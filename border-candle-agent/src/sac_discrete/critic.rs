@@ -0,0 +1,206 @@
+//! Critic of SAC-Discrete agent.
+use crate::{
+    model::SubModel1,
+    opt::{Optimizer, OptimizerConfig},
+    util::{critic::MultiCriticConfig, track_with_replace_substring},
+};
+use anyhow::{Context, Result};
+use border_core::Schedule;
+use candle_core::{DType::F32, Device, Tensor};
+use candle_nn::{VarBuilder, VarMap};
+use log::info;
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Critic for agents with discrete action.
+///
+/// It takes observations as inputs and outputs action values of all actions,
+/// i.e., a tensor of shape `(batch_size, n_actions)`.
+///
+/// This struct has multiple q functions and corresponding target networks.
+/// It is configured with [`MultiCriticConfig`].
+pub struct DiscreteCritic<Q>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize,
+{
+    tau: f64,
+    tau_schedule: Option<Schedule>,
+    varmap: VarMap,
+    varmap_tgt: VarMap, // for target network
+
+    /// Action-value function
+    qs: Vec<Q>,
+    qs_tgt: Vec<Q>, // for target network
+
+    opt: Optimizer, // no optimizer required for tatget networks
+}
+
+impl<Q> DiscreteCritic<Q>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + Clone,
+{
+    /// Constructs [`DiscreteCritic`].
+    pub fn build(config: MultiCriticConfig<Q::Config>, device: Device) -> Result<Self> {
+        let tau = config.tau;
+        let tau_schedule = config.tau_schedule;
+        let n_nets = config.n_nets;
+        let q_config = config.q_config.context("q_config is not set.")?;
+        let opt_config: OptimizerConfig = config.opt_config;
+
+        // Critic networks
+        let (varmap, qs) = Self::build_critic_networks(&q_config, &device, n_nets, "critic");
+
+        // Target networks
+        let (varmap_tgt, qs_tgt) =
+            Self::build_critic_networks(&q_config, &device, n_nets, "critic_tgt");
+
+        // Optimizer, shared with critic networks
+        let opt = opt_config.build(varmap.all_vars())?;
+
+        // Copy parameters
+        track_with_replace_substring(&varmap_tgt, &varmap, 1.0, ("critic", "critic_tgt"))?;
+
+        Ok(Self {
+            tau,
+            tau_schedule,
+            varmap,
+            varmap_tgt,
+            qs,
+            qs_tgt,
+            opt,
+        })
+    }
+
+    fn build_critic_networks(
+        q_config: &Q::Config,
+        device: &Device,
+        n_nets: usize,
+        prefix: &str,
+    ) -> (VarMap, Vec<Q>) {
+        let varmap = VarMap::new();
+        let qs = (0..n_nets)
+            .map(|ix| {
+                let vb = VarBuilder::from_varmap(&varmap, F32, device)
+                    .set_prefix(format!("{}{}", prefix, ix));
+                Q::build(vb, q_config.clone())
+            })
+            .collect();
+
+        (varmap, qs)
+    }
+
+    /// Updates the target networks with the soft update coefficient.
+    pub fn soft_update(&mut self) -> Result<()> {
+        track_with_replace_substring(
+            &self.varmap_tgt,
+            &self.varmap,
+            self.tau(),
+            ("critic", "critic_tgt"),
+        )
+    }
+
+    /// Returns the soft update coefficient for the current number of updates.
+    pub fn tau(&self) -> f64 {
+        match &self.tau_schedule {
+            Some(tau_schedule) => tau_schedule.value(self.opt.n_steps()),
+            None => self.tau,
+        }
+    }
+
+    /// Returns the soft update coefficient if it is scheduled.
+    pub fn scheduled_tau(&self) -> Option<f64> {
+        self.tau_schedule.as_ref().map(|_| self.tau())
+    }
+
+    /// Returns action values of all critics.
+    pub fn qvals(&self, obs: &Q::Input) -> Vec<Tensor> {
+        self.qs.iter().map(|critic| critic.forward(obs)).collect()
+    }
+
+    /// Returns minimum action values of all critics.
+    pub fn qvals_min(&self, obs: &Q::Input) -> Result<Tensor> {
+        Ok(Tensor::stack(&self.qvals(obs), 0)?.min(0)?)
+    }
+
+    /// Returns minimum action values of all target critics.
+    pub fn qvals_min_tgt(&self, obs: &Q::Input) -> Result<Tensor> {
+        let qvals: Vec<Tensor> = self
+            .qs_tgt
+            .iter()
+            .map(|critic| critic.forward(obs))
+            .collect();
+        Ok(Tensor::stack(&qvals, 0)?.min(0)?)
+    }
+
+    /// Backward step for all variables in critic networks.
+    pub fn backward_step(&mut self, loss: &Tensor) -> Result<()> {
+        self.opt.backward_step(loss)
+    }
+
+    /// Save variables to prefix + ".pt" and + ".tgt.pt".
+    pub fn save<T: AsRef<Path>>(&self, prefix: T) -> Result<(PathBuf, PathBuf)> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("pt");
+        self.varmap.save(path.as_path())?;
+        info!("Save critics to {:?}", path);
+
+        let mut path_tgt = PathBuf::from(prefix.as_ref());
+        path_tgt.set_extension("tgt.pt");
+        self.varmap_tgt.save(path_tgt.as_path())?;
+        info!("Save target critics to {:?}", path_tgt);
+
+        Ok((path, path_tgt))
+    }
+
+    /// Load variables from prefix + ".pt" and + ".tgt.pt".
+    pub fn load<T: AsRef<Path>>(&mut self, prefix: T) -> Result<()> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("pt");
+        self.varmap.load(path.as_path())?;
+        info!("Load critics from {:?}", path);
+
+        let mut path_tgt = PathBuf::from(prefix.as_ref());
+        path_tgt.set_extension("tgt.pt");
+        self.varmap_tgt.load(path_tgt.as_path())?;
+        info!("Load target critics from {:?}", path_tgt);
+
+        Ok(())
+    }
+
+    /// Save the state of the optimizer to prefix + ".opt.pt".
+    pub fn save_opt<T: AsRef<Path>>(&self, prefix: T) -> Result<PathBuf> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("opt.pt");
+        self.opt.save(&path, &self.varmap)?;
+        info!("Save critic optimizer to {:?}", path);
+
+        Ok(path)
+    }
+
+    /// Load the state of the optimizer from prefix + ".opt.pt".
+    pub fn load_opt<T: AsRef<Path>>(&mut self, prefix: T) -> Result<()> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("opt.pt");
+        self.opt.load(&path, &self.varmap)?;
+        info!("Load critic optimizer from {:?}", path);
+
+        Ok(())
+    }
+
+    /// Sets the learning rate of the optimizer.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.opt.set_learning_rate(lr);
+    }
+
+    /// Returns the learning rate used in the last update if it is scheduled.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        self.opt.scheduled_learning_rate()
+    }
+
+    /// Returns the norms of gradients in the last update, before clipping.
+    pub fn grad_stats(&self) -> Result<border_core::record::Record> {
+        self.opt.grad_stats(&self.varmap)
+    }
+}
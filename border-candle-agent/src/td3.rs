@@ -0,0 +1,8 @@
+//! Twin delayed deep deterministic policy gradient (TD3) agent.
+//!
+//! Deep deterministic policy gradient (DDPG) and TD3+BC for offline training are implemented
+//! with this agent; see [`crate::ddpg`] and [`crate::td3_bc`].
+mod base;
+mod config;
+pub use base::Td3;
+pub use config::Td3Config;
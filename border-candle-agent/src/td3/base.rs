@@ -0,0 +1,546 @@
+use super::Td3Config;
+use crate::{
+    model::{SubModel1, SubModel2},
+    util::{
+        critic::MultiCritic, deterministic_actor::DeterministicActor, gamma_n_not_done,
+        insert_scheduled, load_yaml, named_grad_stats, save_yaml, smooth_l1_loss, CriticLoss,
+        OutDim,
+    },
+};
+use anyhow::Result;
+use border_core::{
+    record::{Record, RecordValue},
+    Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
+};
+use candle_core::{Device, Tensor, D};
+use candle_nn::loss::mse;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+type ActionValue = Tensor;
+type Action = Tensor;
+
+/// Twin delayed deep deterministic policy gradient (TD3) agent.
+///
+/// `C` is the type of the configuration, from which the agent is built with
+/// [`Configurable::build()`]. It is [`DdpgConfig`](crate::ddpg::DdpgConfig) for
+/// [`Ddpg`](crate::ddpg::Ddpg) and [`Td3BcConfig`](crate::td3_bc::Td3BcConfig) for
+/// [`Td3Bc`](crate::td3_bc::Td3Bc).
+pub struct Td3<E, Q, P, R, C = Td3Config<Q, P>>
+where
+    Q: SubModel2<Output = ActionValue>,
+    P: SubModel1<Output = Action>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    critic: MultiCritic<Q>,
+    actor: DeterministicActor<P>,
+    gamma: f64,
+    target_noise: f64,
+    target_noise_clip: f64,
+    policy_delay: usize,
+    bc_alpha: Option<f64>,
+    n_updates_per_opt: usize,
+    batch_size: usize,
+    train: bool,
+    n_opts: usize,
+    record_verbose_level: usize,
+    critic_loss: CriticLoss,
+    phantom: PhantomData<(E, R, C)>,
+    device: Device,
+}
+
+/// Training state of [`Td3`] saved in checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct Td3State {
+    n_opts: usize,
+}
+
+impl<E, Q, P, R, C> Td3<E, Q, P, R, C>
+where
+    E: Env,
+    Q: SubModel2<Output = ActionValue>,
+    P: SubModel1<Output = Action>,
+    R: ReplayBufferBase,
+    E::Obs: Into<Q::Input1> + Into<P::Input>,
+    E::Act: Into<Q::Input2>,
+    Q::Input2: From<Action>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input1> + Into<P::Input> + Clone,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Q::Input2> + Into<Tensor> + Clone,
+{
+    fn update_critic(&mut self, batch: R::Batch) -> Result<f32> {
+        let loss = {
+            // Extract items in the batch
+            let n_step = batch.n_step().map(|n_step| n_step.to_vec());
+            let (obs, act, next_obs, reward, is_terminated, _, _, _) = batch.unpack();
+            let batch_size = reward.len();
+            let reward = Tensor::from_slice(&reward[..], (batch_size,), &self.device)?;
+
+            // Prediction
+            let qs = self.critic.qvals(&obs.into(), &act.into());
+
+            // Target
+            let tgt = {
+                let gamma_not_done = gamma_n_not_done(
+                    self.gamma as f32,
+                    is_terminated,
+                    n_step.as_deref(),
+                    &self.device,
+                )?;
+
+                // Target policy smoothing
+                let next_act = self.actor.forward_tgt(&next_obs.clone().into())?;
+                let next_act = match self.target_noise > 0.0 {
+                    true => {
+                        let noise = next_act
+                            .randn_like(0., self.target_noise)?
+                            .clamp(-self.target_noise_clip, self.target_noise_clip)?;
+                        self.actor.clip(&(next_act + noise)?)?
+                    }
+                    false => next_act,
+                };
+                let next_q = self
+                    .critic
+                    .qvals_min_tgt(&next_obs.into(), &next_act.into())?;
+                (&reward + (&gamma_not_done * next_q)?)?.squeeze(D::Minus1)?
+            }
+            .detach();
+            debug_assert_eq!(tgt.dims(), [self.batch_size]);
+
+            // Loss
+            let losses: Vec<_> = match self.critic_loss {
+                CriticLoss::Mse => qs.iter().map(|pred| mse(pred, &tgt).unwrap()).collect(),
+                CriticLoss::SmoothL1 => qs
+                    .iter()
+                    .map(|pred| smooth_l1_loss(pred, &tgt).unwrap())
+                    .collect(),
+            };
+            Tensor::stack(&losses, 0)?.mean_all()?
+        };
+
+        self.critic.backward_step(&loss)?;
+
+        Ok(loss.to_scalar::<f32>()?)
+    }
+
+    fn update_actor(
+        &mut self,
+        obs: <R::Batch as TransitionBatch>::ObsBatch,
+        act_data: <R::Batch as TransitionBatch>::ActBatch,
+    ) -> Result<(f32, Option<f32>)> {
+        let (loss, loss_bc) = {
+            let act = self.actor.forward(&obs.clone().into())?;
+
+            // Only the first critic is used for the policy gradient
+            let q = self
+                .critic
+                .qvals(&obs.into(), &act.clone().into())
+                .swap_remove(0);
+            match self.bc_alpha {
+                None => (q.mean_all()?.neg()?, None),
+                Some(alpha) => {
+                    // Behavior cloning term of TD3+BC, Q is normalized by its mean magnitude
+                    let lambda = alpha / q.abs()?.mean_all()?.to_scalar::<f32>()? as f64;
+                    let act_data: Tensor = act_data.into();
+                    let bc = mse(&act, &act_data.to_device(act.device())?)?;
+                    let loss_bc = bc.to_scalar::<f32>()?;
+                    (((q.mean_all()? * -lambda)? + bc)?, Some(loss_bc))
+                }
+            }
+        };
+
+        self.actor.backward_step(&loss)?;
+
+        Ok((loss.to_scalar::<f32>()?, loss_bc))
+    }
+
+    /// Returns the norms of gradients of the models in the last update, before clipping.
+    fn grad_stats(&self) -> Result<Record> {
+        let record = named_grad_stats(self.actor.grad_stats()?, "actor");
+        Ok(record.merge(named_grad_stats(self.critic.grad_stats()?, "critic")))
+    }
+
+    fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
+        let mut loss_critic = 0f32;
+        let mut loss_actor = 0f32;
+        let mut loss_bc = 0f32;
+        let mut n_actor_updates = 0;
+
+        for _ in 0..self.n_updates_per_opt {
+            let batch = buffer.batch(self.batch_size).unwrap();
+
+            // The critic is updated every step, then the actor and the target networks are
+            // updated with the updated critic every `policy_delay` steps
+            let actor_batch = match (self.n_opts + 1).checked_rem(self.policy_delay) == Some(0) {
+                true => Some((batch.obs().clone(), batch.act().clone())),
+                false => None,
+            };
+            loss_critic += self.update_critic(batch)?;
+            if let Some((obs, act)) = actor_batch {
+                let (loss, bc) = self.update_actor(obs, act)?;
+                loss_actor += loss;
+                loss_bc += bc.unwrap_or(0.0);
+                n_actor_updates += 1;
+                self.actor.soft_update()?;
+                self.critic.soft_update()?;
+            }
+            self.n_opts += 1;
+        }
+
+        loss_critic /= self.n_updates_per_opt as f32;
+
+        let mut record = Record::from_slice(&[("loss_critic", RecordValue::Scalar(loss_critic))]);
+        if n_actor_updates > 0 {
+            record.insert(
+                "loss_actor",
+                RecordValue::Scalar(loss_actor / n_actor_updates as f32),
+            );
+            if self.bc_alpha.is_some() {
+                record.insert(
+                    "loss_bc",
+                    RecordValue::Scalar(loss_bc / n_actor_updates as f32),
+                );
+            }
+        }
+
+        insert_scheduled(
+            &mut record,
+            &[
+                ("lr_actor", self.actor.scheduled_learning_rate()),
+                ("lr_critic", self.critic.scheduled_learning_rate()),
+                ("tau_actor", self.actor.scheduled_tau()),
+                ("tau_critic", self.critic.scheduled_tau()),
+            ],
+        );
+
+        Ok(record)
+    }
+}
+
+impl<E, Q, P, R, C> Policy<E> for Td3<E, Q, P, R, C>
+where
+    E: Env,
+    Q: SubModel2<Output = ActionValue>,
+    P: SubModel1<Output = Action>,
+    E::Obs: Into<Q::Input1> + Into<P::Input>,
+    E::Act: Into<Q::Input2> + From<Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        self.actor
+            .sample(&obs.clone().into(), self.train)
+            .unwrap()
+            .into()
+    }
+}
+
+impl<E, Q, P, R, C> Configurable for Td3<E, Q, P, R, C>
+where
+    C: Into<Td3Config<Q, P>> + Clone + DeserializeOwned,
+    E: Env,
+    Q: SubModel2<Output = ActionValue>,
+    P: SubModel1<Output = Action>,
+    E::Obs: Into<Q::Input1> + Into<P::Input>,
+    E::Act: Into<Q::Input2> + From<Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    type Config = C;
+
+    /// Constructs [`Td3`] agent.
+    fn build(config: Self::Config) -> Self {
+        let config: Td3Config<Q, P> = config.into();
+        let device: Device = config
+            .device
+            .expect("No device is given for TD3 agent")
+            .into();
+        let actor = DeterministicActor::build(config.actor_config, device.clone()).unwrap();
+        let critic = MultiCritic::build(config.critic_config, device.clone()).unwrap();
+
+        Td3 {
+            actor,
+            critic,
+            gamma: config.gamma,
+            target_noise: config.target_noise,
+            target_noise_clip: config.target_noise_clip,
+            policy_delay: config.policy_delay.max(1),
+            bc_alpha: config.bc_alpha,
+            n_updates_per_opt: config.n_updates_per_opt,
+            batch_size: config.batch_size,
+            train: false,
+            critic_loss: config.critic_loss,
+            n_opts: 0,
+            record_verbose_level: config.record_verbose_level,
+            device,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, Q, P, R, C> Agent<E, R> for Td3<E, Q, P, R, C>
+where
+    E: Env + 'static,
+    Q: SubModel2<Output = ActionValue> + 'static,
+    P: SubModel1<Output = Action> + 'static,
+    R: ReplayBufferBase + 'static,
+    C: 'static,
+    E::Obs: Into<Q::Input1> + Into<P::Input>,
+    E::Act: Into<Q::Input2> + From<Tensor>,
+    Q::Input2: From<Action>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input1> + Into<P::Input> + Clone,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Q::Input2> + Into<Tensor> + Clone,
+{
+    fn train(&mut self) {
+        self.train = true;
+    }
+
+    fn eval(&mut self) {
+        self.train = false;
+        self.actor.reset_noise();
+    }
+
+    fn is_train(&self) -> bool {
+        self.train
+    }
+
+    fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        let record = self.opt_(buffer).expect("Failed in Td3::opt_()");
+
+        match self.record_verbose_level >= 2 {
+            true => record.merge(self.grad_stats().expect("Failed in Td3::grad_stats()")),
+            false => record,
+        }
+    }
+
+    fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(path)?;
+
+        let (actor_path, actor_tgt_path) = self.actor.save(path.join("actor"))?;
+        let (critic_path, critic_tgt_path) = self.critic.save(path.join("critic"))?;
+
+        Ok(vec![
+            actor_path,
+            actor_tgt_path,
+            critic_path,
+            critic_tgt_path,
+        ])
+    }
+
+    fn load_params(&mut self, path: &Path) -> Result<()> {
+        self.actor.load(path.join("actor").as_path())?;
+        self.critic.load(path.join("critic").as_path())?;
+
+        Ok(())
+    }
+
+    /// Save the state of the agent for resuming training in the given directory.
+    ///
+    /// In addition to the files saved by [`Agent::save_params`], the states of the optimizers
+    /// and the number of optimization steps (`agent.yaml`) are saved.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+        paths.push(self.actor.save_opt(path.join("actor"))?);
+        paths.push(self.critic.save_opt(path.join("critic"))?);
+
+        let state_path = path.join("agent.yaml");
+        save_yaml(
+            &state_path,
+            &Td3State {
+                n_opts: self.n_opts,
+            },
+        )?;
+        paths.push(state_path);
+
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+        self.actor.load_opt(path.join("actor"))?;
+        self.critic.load_opt(path.join("critic"))?;
+
+        let state: Td3State = load_yaml(path.join("agent.yaml"))?;
+        self.n_opts = state.n_opts;
+
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.actor.set_learning_rate(lr);
+        self.critic.set_learning_rate(lr);
+        Ok(())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ddpg::{Ddpg, DdpgConfig},
+        mlp::{Mlp, MlpConfig},
+        td3_bc::{Td3Bc, Td3BcConfig},
+        test::{transitions, Buffer, TestEnv},
+        util::{critic::MultiCriticConfig, deterministic_actor::DeterministicActorConfig},
+        Activation,
+    };
+    use border_core::{generic_replay_buffer::SimpleReplayBufferConfig, ExperienceBufferBase};
+
+    fn td3(policy_delay: usize) -> Result<Td3<TestEnv, Mlp, Mlp, Buffer>> {
+        let actor_config = DeterministicActorConfig::default().policy_config(MlpConfig::new(
+            3,
+            vec![8],
+            2,
+            Activation::None,
+        ));
+        let critic_config =
+            MultiCriticConfig::default().q_config(MlpConfig::new(5, vec![8], 1, Activation::None));
+
+        Ok(Td3 {
+            critic: MultiCritic::build(critic_config, Device::Cpu)?,
+            actor: DeterministicActor::build(actor_config, Device::Cpu)?,
+            gamma: 0.99,
+            target_noise: 0.0,
+            target_noise_clip: 0.5,
+            policy_delay,
+            bc_alpha: None,
+            n_updates_per_opt: 1,
+            batch_size: 4,
+            train: true,
+            n_opts: 0,
+            record_verbose_level: 0,
+            critic_loss: CriticLoss::Mse,
+            phantom: PhantomData,
+            device: Device::Cpu,
+        })
+    }
+
+    #[test]
+    fn test_opt() -> Result<()> {
+        let mut agent = td3(2)?;
+
+        // A single transition, which is sampled in every batch
+        let obs = Tensor::randn(0f32, 1f32, (1, 3), &Device::Cpu)?;
+        let mut buffer = Buffer::build(&SimpleReplayBufferConfig::default().capacity(1));
+        buffer.push(transitions(
+            obs.clone(),
+            Tensor::randn(0f32, 1f32, (1, 2), &Device::Cpu)?,
+            Tensor::randn(0f32, 1f32, (1, 3), &Device::Cpu)?,
+            vec![1.0],
+        ))?;
+
+        for i in 1..=6 {
+            let act = agent.actor.forward(&obs)?;
+            let record = agent.opt_(&mut buffer)?;
+            assert!(record.get_scalar("loss_critic")?.is_finite());
+
+            // The actor is updated every `policy_delay` steps
+            if i % 2 != 0 {
+                assert!(record.get_scalar("loss_actor").is_err());
+                continue;
+            }
+
+            // The actor loss is computed with the critic updated in the same step, which is
+            // not changed by the update of the actor
+            let q = agent.critic.qvals(&obs, &act).swap_remove(0);
+            let expected = q.mean_all()?.neg()?.to_scalar::<f32>()?;
+            assert!((record.get_scalar("loss_actor")? - expected).abs() < 1e-5);
+        }
+        assert_eq!(agent.n_opts, 6);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ddpg_config() -> Result<()> {
+        let actor_config = DeterministicActorConfig::default().policy_config(MlpConfig::new(
+            3,
+            vec![8],
+            2,
+            Activation::None,
+        ));
+        let mut config = DdpgConfig::<Mlp, Mlp>::default()
+            .actor_config(actor_config)
+            .batch_size(4)
+            .device(Device::Cpu);
+
+        // The number of critics is kept
+        let q_config = MlpConfig::new(5, vec![8], 1, Activation::None);
+        config.critic_config = config.critic_config.q_config(q_config);
+        let mut agent = Ddpg::<TestEnv, Mlp, Mlp, Buffer>::build(config);
+
+        // A single critic without target policy smoothing
+        assert_eq!(agent.target_noise, 0.0);
+        assert_eq!(agent.policy_delay, 1);
+        assert_eq!(agent.bc_alpha, None);
+        let obs = Tensor::randn(0f32, 1f32, (1, 3), &Device::Cpu)?;
+        let act = Tensor::randn(0f32, 1f32, (1, 2), &Device::Cpu)?;
+        assert_eq!(agent.critic.qvals(&obs, &act).len(), 1);
+
+        // The actor is updated at every step
+        let mut buffer = Buffer::build(&SimpleReplayBufferConfig::default().capacity(1));
+        buffer.push(transitions(obs.clone(), act, obs, vec![1.0]))?;
+        for _ in 0..2 {
+            let record = agent.opt_(&mut buffer)?;
+            assert!(record.get_scalar("loss_actor")?.is_finite());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_td3_bc() -> Result<()> {
+        let actor_config = DeterministicActorConfig::default().policy_config(MlpConfig::new(
+            3,
+            vec![8],
+            2,
+            Activation::None,
+        ));
+        let critic_config =
+            MultiCriticConfig::default().q_config(MlpConfig::new(5, vec![8], 1, Activation::None));
+        let config = Td3BcConfig::<Mlp, Mlp>::default()
+            .actor_config(actor_config)
+            .critic_config(critic_config)
+            .batch_size(4)
+            .device(Device::Cpu);
+        let mut agent = Td3Bc::<TestEnv, Mlp, Mlp, Buffer>::build(config);
+        assert_eq!(agent.bc_alpha, Some(2.5));
+
+        let obs = Tensor::randn(0f32, 1f32, (1, 3), &Device::Cpu)?;
+        let act = Tensor::randn(0f32, 1f32, (1, 2), &Device::Cpu)?;
+        let mut buffer = Buffer::build(&SimpleReplayBufferConfig::default().capacity(1));
+        buffer.push(transitions(
+            obs.clone(),
+            act.clone(),
+            obs.clone(),
+            vec![1.0],
+        ))?;
+
+        // The behavior cloning term is recorded when the actor is updated
+        let record = agent.opt_(&mut buffer)?;
+        assert!(record.get_scalar("loss_bc").is_err());
+        let expected = mse(&agent.actor.forward(&obs)?, &act)?.to_scalar::<f32>()?;
+        let record = agent.opt_(&mut buffer)?;
+        assert!((record.get_scalar("loss_bc")? - expected).abs() < 1e-5);
+
+        Ok(())
+    }
+}
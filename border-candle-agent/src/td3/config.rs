@@ -0,0 +1,228 @@
+//! Configuration of TD3 agent.
+use crate::{
+    model::{SubModel1, SubModel2},
+    util::{
+        critic::MultiCriticConfig,
+        deterministic_actor::{DeterministicActorConfig, ExplorationNoise},
+        CriticLoss, OutDim,
+    },
+    Device,
+};
+use anyhow::Result;
+use candle_core::Tensor;
+use log::info;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
+
+/// Configuration of [`Td3`](super::Td3).
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct Td3Config<Q, P>
+where
+    Q: SubModel2<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+{
+    /// Configuration of the actor model.
+    pub actor_config: DeterministicActorConfig<P::Config>,
+
+    /// Configuration of the critic model.
+    pub critic_config: MultiCriticConfig<Q::Config>,
+
+    /// Discont factor.
+    pub gamma: f64,
+
+    /// Standard deviation of the noise added to target actions (target policy smoothing).
+    pub target_noise: f64,
+
+    /// Bound of the absolute value of the noise added to target actions.
+    pub target_noise_clip: f64,
+
+    /// The actor and the target networks are updated once every `policy_delay` critic updates.
+    pub policy_delay: usize,
+
+    /// Weight of the Q-value term relative to the behavior cloning term in the actor loss.
+    ///
+    /// If `Some`, the agent works as TD3+BC for offline training;
+    /// see also [`Td3BcConfig`](crate::td3_bc::Td3BcConfig).
+    #[serde(default)]
+    pub bc_alpha: Option<f64>,
+
+    /// Number of parameter updates per optimization step.
+    pub n_updates_per_opt: usize,
+
+    /// Batch size for training.
+    pub batch_size: usize,
+
+    /// Type of critic loss function.
+    pub critic_loss: CriticLoss,
+
+    /// Device for actor/critic models.
+    pub device: Option<Device>,
+
+    /// Verbose level of records.
+    ///
+    /// If it is 2 or more, the norms of gradients are recorded in optimization steps.
+    #[serde(default)]
+    pub record_verbose_level: usize,
+}
+
+impl<Q, P> Clone for Td3Config<Q, P>
+where
+    Q: SubModel2<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            actor_config: self.actor_config.clone(),
+            critic_config: self.critic_config.clone(),
+            gamma: self.gamma,
+            target_noise: self.target_noise,
+            target_noise_clip: self.target_noise_clip,
+            policy_delay: self.policy_delay,
+            bc_alpha: self.bc_alpha,
+            n_updates_per_opt: self.n_updates_per_opt,
+            batch_size: self.batch_size,
+            critic_loss: self.critic_loss.clone(),
+            device: self.device,
+            record_verbose_level: self.record_verbose_level,
+        }
+    }
+}
+
+impl<Q, P> Default for Td3Config<Q, P>
+where
+    Q: SubModel2<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+{
+    fn default() -> Self {
+        Self {
+            actor_config: Default::default(),
+            critic_config: Default::default(),
+            gamma: 0.99,
+            target_noise: 0.2,
+            target_noise_clip: 0.5,
+            policy_delay: 2,
+            bc_alpha: None,
+            n_updates_per_opt: 1,
+            batch_size: 1,
+            critic_loss: CriticLoss::Mse,
+            device: None,
+            record_verbose_level: 0,
+        }
+    }
+}
+
+impl<Q, P> Td3Config<Q, P>
+where
+    Q: SubModel2<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+{
+    /// Sets the numper of parameter update steps per optimization step.
+    pub fn n_updates_per_opt(mut self, v: usize) -> Self {
+        self.n_updates_per_opt = v;
+        self
+    }
+
+    /// Batch size.
+    pub fn batch_size(mut self, v: usize) -> Self {
+        self.batch_size = v;
+        self
+    }
+
+    /// Discount factor.
+    pub fn discount_factor(mut self, v: f64) -> Self {
+        self.gamma = v;
+        self
+    }
+
+    /// Standard deviation of the noise for target policy smoothing.
+    pub fn target_noise(mut self, v: f64) -> Self {
+        self.target_noise = v;
+        self
+    }
+
+    /// Clip range of the noise for target policy smoothing.
+    pub fn target_noise_clip(mut self, v: f64) -> Self {
+        self.target_noise_clip = v;
+        self
+    }
+
+    /// Delay of actor updates.
+    pub fn policy_delay(mut self, v: usize) -> Self {
+        self.policy_delay = v;
+        self
+    }
+
+    /// Weight of the Q-value term for TD3+BC.
+    pub fn bc_alpha(mut self, v: Option<f64>) -> Self {
+        self.bc_alpha = v;
+        self
+    }
+
+    /// Exploration noise.
+    pub fn exploration_noise(mut self, v: ExplorationNoise) -> Self {
+        self.actor_config.exploration_noise = v;
+        self
+    }
+
+    /// Critic loss.
+    pub fn critic_loss(mut self, v: CriticLoss) -> Self {
+        self.critic_loss = v;
+        self
+    }
+
+    /// Configuration of actor.
+    pub fn actor_config(mut self, actor_config: DeterministicActorConfig<P::Config>) -> Self {
+        self.actor_config = actor_config;
+        self
+    }
+
+    /// Configuration of critic.
+    pub fn critic_config(mut self, critic_config: MultiCriticConfig<Q::Config>) -> Self {
+        self.critic_config = critic_config;
+        self
+    }
+
+    /// Device.
+    pub fn device(mut self, device: candle_core::Device) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+
+    /// Sets verbose level.
+    pub fn record_verbose_level(mut self, v: usize) -> Self {
+        self.record_verbose_level = v;
+        self
+    }
+
+    /// Constructs [`Td3Config`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path_ = path.as_ref().to_owned();
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        info!("Load config of TD3 agent from {}", path_.to_str().unwrap());
+        Ok(b)
+    }
+
+    /// Saves [`Td3Config`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path_ = path.as_ref().to_owned();
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        info!("Save config of TD3 agent into {}", path_.to_str().unwrap());
+        Ok(())
+    }
+}
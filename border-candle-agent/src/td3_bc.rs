@@ -0,0 +1,12 @@
+//! TD3+BC agent for offline training.
+//!
+//! TD3+BC is implemented as [`Td3`] with a behavior cloning term in the actor loss
+//! ([Fujimoto and Gu, 2021](https://arxiv.org/abs/2106.06860)). [`Td3Bc`] is built from
+//! [`Td3BcConfig`], which enables the term by default, and records the behavior cloning term
+//! with key `loss_bc`.
+mod config;
+use crate::td3::Td3;
+pub use config::Td3BcConfig;
+
+/// TD3+BC agent.
+pub type Td3Bc<E, Q, P, R> = Td3<E, Q, P, R, Td3BcConfig<Q, P>>;
@@ -1,5 +1,8 @@
-use border_core::generic_replay_buffer::BatchBase;
+use crate::util::TensorData;
+use border_core::generic_replay_buffer::{BatchBase, FrameStackBatch};
 use candle_core::{error::Result, DType, Device, IndexOp, Tensor};
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use std::path::Path;
 
 /// Adds capability of constructing [`Tensor`] with a static method.
 ///
@@ -29,7 +32,8 @@ impl ZeroTensor for i64 {
 
 /// A buffer consisting of a [`Tensor`].
 ///
-/// The internal buffer is `Vec<Tensor>`.
+/// The internal buffer is `Vec<Tensor>`. It is serialized with the shape and elements
+/// of the tensor, and deserialized on the CPU.
 ///
 /// [`Tensor`]: https://docs.rs/candle-core/0.4.1/candle_core/struct.Tensor.html
 #[derive(Clone, Debug)]
@@ -55,6 +59,41 @@ impl TensorBatch {
     }
 }
 
+/// Serialized form of [`TensorBatch`].
+#[derive(Deserialize, Serialize)]
+struct TensorBatchData {
+    buf: Option<TensorData>,
+    capacity: usize,
+}
+
+impl Serialize for TensorBatch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let buf = match &self.buf {
+            Some(buf) => Some(TensorData::from_tensor(buf).map_err(ser::Error::custom)?),
+            None => None,
+        };
+        TensorBatchData {
+            buf,
+            capacity: self.capacity,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TensorBatch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let data = TensorBatchData::deserialize(deserializer)?;
+        let buf = match data.buf {
+            Some(buf) => Some(buf.to_tensor(&Device::Cpu).map_err(de::Error::custom)?),
+            None => None,
+        };
+        Ok(Self {
+            buf,
+            capacity: data.capacity,
+        })
+    }
+}
+
 impl BatchBase for TensorBatch {
     fn new(capacity: usize) -> Self {
         Self {
@@ -115,6 +154,55 @@ impl BatchBase for TensorBatch {
         let buf = Some(self.buf.as_ref().unwrap().index_select(&ixs, 0).unwrap());
         Self { buf, capacity }
     }
+
+    /// Saves the first `len` elements as `data.npy` in the directory `path`.
+    ///
+    /// Nothing is saved if the internal buffer has not been initialized.
+    fn save(&self, path: &Path, len: usize) -> anyhow::Result<()> {
+        if let Some(buf) = &self.buf {
+            buf.narrow(0, 0, len)?.write_npy(path.join("data.npy"))?;
+        }
+        Ok(())
+    }
+
+    /// Loads the elements in `data.npy` in the directory `path`.
+    ///
+    /// If the file does not exist, no elements are loaded.
+    fn load(&mut self, path: &Path) -> anyhow::Result<usize> {
+        let path = path.join("data.npy");
+        if !path.exists() {
+            return Ok(0);
+        }
+
+        let data = Tensor::read_npy(&path)?;
+        let len = data.dims()[0];
+        if len > self.capacity {
+            return Err(anyhow::anyhow!(
+                "{:?} has {} elements, more than the capacity {}",
+                path,
+                len,
+                self.capacity
+            ));
+        }
+        if len > 0 {
+            self.push(0, Self::from_tensor(data));
+        }
+        Ok(len)
+    }
+}
+
+impl FrameStackBatch for TensorBatch {
+    /// Returns the elements of the internal `u8` tensor.
+    fn to_frames(&self) -> (Vec<u8>, Vec<usize>) {
+        let buf = self.buf.as_ref().unwrap();
+        let data = buf.flatten_all().unwrap().to_vec1::<u8>().unwrap();
+        (data, buf.dims().to_vec())
+    }
+
+    /// Creates a batch with a `u8` tensor on the CPU.
+    fn from_frames(data: Vec<u8>, shape: &[usize]) -> Self {
+        Self::from_tensor(Tensor::from_vec(data, shape, &Device::Cpu).unwrap())
+    }
 }
 
 impl From<TensorBatch> for Tensor {
@@ -122,3 +210,48 @@ impl From<TensorBatch> for Tensor {
         b.buf.unwrap()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_save_load() -> anyhow::Result<()> {
+        let dir = TempDir::new("tensor_batch")?;
+        let data = Tensor::arange(0f32, 12f32, &Device::Cpu)?.reshape((4, 3))?;
+        let mut batch = TensorBatch::new(5);
+        batch.push(0, TensorBatch::from_tensor(data.clone()));
+        batch.save(dir.path(), 3)?;
+
+        let mut batch2 = TensorBatch::new(5);
+        assert_eq!(batch2.load(dir.path())?, 3);
+        let loaded: Tensor = batch2.sample(&vec![0, 1, 2]).into();
+        let expected = data.narrow(0, 0, 3)?;
+        assert_eq!(loaded.to_vec2::<f32>()?, expected.to_vec2::<f32>()?);
+
+        // An empty batch is not saved
+        let dir = TempDir::new("tensor_batch")?;
+        TensorBatch::new(5).save(dir.path(), 0)?;
+        assert_eq!(TensorBatch::new(5).load(dir.path())?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serde() -> anyhow::Result<()> {
+        let data = Tensor::arange(0i64, 6i64, &Device::Cpu)?.reshape((3, 2))?;
+        let batch = TensorBatch::from_tensor(data.clone());
+        let batch: TensorBatch = bincode::deserialize(&bincode::serialize(&batch)?)?;
+        assert_eq!(batch.capacity, 3);
+        let t: Tensor = batch.into();
+        assert_eq!(t.to_vec2::<i64>()?, data.to_vec2::<i64>()?);
+
+        // An empty batch
+        let batch: TensorBatch = bincode::deserialize(&bincode::serialize(&TensorBatch::new(5))?)?;
+        assert!(batch.buf.is_none());
+        assert_eq!(batch.capacity, 5);
+
+        Ok(())
+    }
+}
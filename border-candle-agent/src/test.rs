@@ -0,0 +1,103 @@
+//! Types for testing agents.
+use anyhow::Result;
+use border_core::{
+    generic_replay_buffer::{GenericTransitionBatch, SimpleReplayBuffer},
+    record::Record,
+    Env, Step,
+};
+use candle_core::Tensor;
+
+use crate::TensorBatch;
+
+/// Observation for testing.
+#[derive(Clone, Debug)]
+pub struct TestObs(pub Tensor);
+
+impl border_core::Obs for TestObs {
+    fn len(&self) -> usize {
+        self.0.dims()[0]
+    }
+}
+
+impl From<TestObs> for Tensor {
+    fn from(obs: TestObs) -> Self {
+        obs.0
+    }
+}
+
+/// Action for testing.
+#[derive(Clone, Debug)]
+pub struct TestAct(pub Tensor);
+
+impl border_core::Act for TestAct {}
+
+impl From<TestAct> for Tensor {
+    fn from(act: TestAct) -> Self {
+        act.0
+    }
+}
+
+impl From<Tensor> for TestAct {
+    fn from(t: Tensor) -> Self {
+        Self(t)
+    }
+}
+
+/// Environment only used for type parameters.
+pub struct TestEnv;
+
+impl Env for TestEnv {
+    type Config = ();
+    type Obs = TestObs;
+    type Act = TestAct;
+    type Info = ();
+
+    fn build(_config: &Self::Config, _seed: i64) -> Result<Self> {
+        unimplemented!();
+    }
+
+    fn step(&mut self, _a: &Self::Act) -> (Step<Self>, Record) {
+        unimplemented!();
+    }
+
+    fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        unimplemented!();
+    }
+
+    fn step_with_reset(&mut self, _a: &Self::Act) -> (Step<Self>, Record) {
+        unimplemented!();
+    }
+
+    fn reset_with_index(&mut self, _ix: usize) -> Result<Self::Obs> {
+        unimplemented!();
+    }
+}
+
+/// Replay buffer for testing.
+pub type Buffer = SimpleReplayBuffer<TensorBatch, TensorBatch>;
+
+/// Returns a batch of transitions with the given observations, actions and next observations.
+pub fn transitions(
+    obs: Tensor,
+    act: Tensor,
+    next_obs: Tensor,
+    reward: Vec<f32>,
+) -> GenericTransitionBatch<TensorBatch, TensorBatch> {
+    let n = reward.len();
+    GenericTransitionBatch {
+        obs: TensorBatch::from_tensor(obs),
+        act: TensorBatch::from_tensor(act),
+        next_obs: TensorBatch::from_tensor(next_obs),
+        reward,
+        is_terminated: vec![0; n],
+        is_truncated: vec![0; n],
+        n_step: None,
+        ix_sample: None,
+        weight: None,
+    }
+}
+
+/// Returns the maximum of the absolute differences of two tensors.
+pub fn max_abs_diff(a: &Tensor, b: &Tensor) -> Result<f32> {
+    Ok((a - b)?.abs()?.max_all()?.to_scalar::<f32>()?)
+}
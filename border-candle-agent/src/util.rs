@@ -3,17 +3,27 @@ use anyhow::Result;
 use candle_core::{DType, Device, Tensor, WithDType, D};
 use candle_nn::VarMap;
 use log::trace;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 mod named_tensors;
+mod noisy_linear;
 mod quantile_loss;
+mod tensor_data;
 use border_core::record::{Record, RecordValue};
 pub use named_tensors::NamedTensors;
 use ndarray::ArrayD;
+pub use noisy_linear::NoisyLinear;
 use num_traits::AsPrimitive;
 pub use quantile_loss::quantile_huber_loss;
-use std::convert::TryFrom;
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
+pub(crate) use tensor_data::TensorData;
 pub mod actor;
 pub mod critic;
+pub mod deterministic_actor;
 
 /// Critic loss type.
 #[allow(clippy::upper_case_acronyms)]
@@ -182,6 +192,46 @@ pub fn param_stats(varmap: &VarMap) -> Record {
     record
 }
 
+/// Renames the global gradient norm in a record of `grad_stats()` to `{name}_grad_norm`.
+///
+/// This is used to merge gradient statistics of models updated by different optimizers,
+/// e.g., an actor and a critic, into a single record.
+pub fn named_grad_stats(record: Record, name: &str) -> Record {
+    let mut renamed = Record::empty();
+    for (k, v) in record.into_iter_in_record() {
+        match k.as_str() {
+            "grad_norm" => renamed.insert(format!("{}_grad_norm", name), v),
+            _ => renamed.insert(k, v),
+        }
+    }
+    renamed
+}
+
+/// Inserts the current values of scheduled hyperparameters into a record.
+///
+/// Hyperparameters given as `None`, i.e., not scheduled, are skipped.
+pub fn insert_scheduled(record: &mut Record, values: &[(&str, Option<f64>)]) {
+    for (name, v) in values.iter() {
+        if let Some(v) = v {
+            record.insert(*name, RecordValue::Scalar(*v as _));
+        }
+    }
+}
+
+/// Saves a value as a YAML file, e.g., the training state of an agent in a checkpoint.
+pub fn save_yaml<T: Serialize>(path: impl AsRef<Path>, v: &T) -> Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(serde_yaml::to_string(v)?.as_bytes())?;
+    Ok(())
+}
+
+/// Loads a value from a YAML file.
+pub fn load_yaml<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T> {
+    let file = File::open(path)?;
+    let rdr = BufReader::new(file);
+    Ok(serde_yaml::from_reader(rdr)?)
+}
+
 pub fn vec_to_tensor<T1, T2>(v: Vec<T1>, add_batch_dim: bool) -> Result<Tensor>
 where
     T1: AsPrimitive<T2>,
@@ -254,6 +304,33 @@ pub fn gamma_not_done(
     Ok(Tensor::from_slice(&not_done[..], (batch_size,), device)?)
 }
 
+/// Returns discount factors of n-step transitions multiplied by not-terminated flags.
+///
+/// The discount factor of a transition is `gamma^n`, where n is given by
+/// [`TransitionBatch::n_step`]. If `n_step` is `None`, n is 1 for all transitions.
+///
+/// [`TransitionBatch::n_step`]: border_core::TransitionBatch::n_step
+pub fn gamma_n_not_done(
+    gamma: f32,
+    is_terminated: Vec<i8>,
+    n_step: Option<&[usize]>,
+    device: &Device,
+) -> Result<Tensor> {
+    let batch_size = is_terminated.len();
+    let not_done = match n_step {
+        Some(n_step) => is_terminated
+            .iter()
+            .zip(n_step.iter())
+            .map(|(e, n)| (1f32 - *e as f32) * gamma.powi(*n as i32))
+            .collect::<Vec<_>>(),
+        None => is_terminated
+            .iter()
+            .map(|e| (1f32 - *e as f32) * gamma)
+            .collect::<Vec<_>>(),
+    };
+    Ok(Tensor::from_slice(&not_done[..], (batch_size,), device)?)
+}
+
 pub fn reward(reward: Vec<f32>, device: &Device) -> Result<Tensor> {
     let batch_size = reward.len();
     Ok(Tensor::from_slice(&reward[..], (batch_size,), device)?)
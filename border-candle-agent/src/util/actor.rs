@@ -264,6 +264,41 @@ where
 
         Ok(())
     }
+
+    /// Save the state of the optimizer to prefix + ".opt.pt".
+    pub fn save_opt(&self, prefix: impl AsRef<Path>) -> Result<PathBuf> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("opt.pt");
+        self.opt.save(&path, &self.varmap)?;
+        info!("Save actor optimizer to {:?}", path);
+
+        Ok(path)
+    }
+
+    /// Load the state of the optimizer from prefix + ".opt.pt".
+    pub fn load_opt(&mut self, prefix: impl AsRef<Path>) -> Result<()> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("opt.pt");
+        self.opt.load(&path, &self.varmap)?;
+        info!("Load actor optimizer from {:?}", path);
+
+        Ok(())
+    }
+
+    /// Sets the learning rate of the optimizer.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.opt.set_learning_rate(lr);
+    }
+
+    /// Returns the learning rate used in the last update if it is scheduled.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        self.opt.scheduled_learning_rate()
+    }
+
+    /// Returns the norms of gradients in the last update, before clipping.
+    pub fn grad_stats(&self) -> Result<border_core::record::Record> {
+        self.opt.grad_stats(&self.varmap)
+    }
 }
 
 impl<P> Clone for GaussianActor<P>
@@ -5,9 +5,10 @@ use crate::{
     util::track_with_replace_substring,
 };
 use anyhow::{Context, Result};
+use border_core::Schedule;
 use candle_core::{DType::F32, Device, Tensor, D};
 use candle_nn::{VarBuilder, VarMap};
-use log::info;
+use log::{info, warn};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     fs::File,
@@ -29,6 +30,12 @@ pub struct MultiCriticConfig<Q> {
 
     /// Soft update coefficient.
     pub tau: f64,
+
+    /// Schedule of the soft update coefficient, which overrides `tau`.
+    ///
+    /// The schedule is indexed by the number of updates of the critic networks.
+    #[serde(default)]
+    pub tau_schedule: Option<Schedule>,
 }
 
 impl<Q> Default for MultiCriticConfig<Q> {
@@ -38,6 +45,7 @@ impl<Q> Default for MultiCriticConfig<Q> {
             q_config: None,
             opt_config: OptimizerConfig::Adam { lr: 0.0003 },
             tau: 0.005,
+            tau_schedule: None,
         }
     }
 }
@@ -70,6 +78,12 @@ where
         self
     }
 
+    /// Sets the schedule of soft update parameter tau.
+    pub fn tau_schedule(mut self, v: Schedule) -> Self {
+        self.tau_schedule = Some(v);
+        self
+    }
+
     /// Constructs [`MultiCriticConfig`] from YAML file.
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         let file = File::open(path)?;
@@ -98,6 +112,7 @@ where
 {
     n_nets: usize,
     tau: f64,
+    tau_schedule: Option<Schedule>,
     device: Device,
     varmap: VarMap,
     varmap_tgt: VarMap, // for target network
@@ -119,6 +134,7 @@ where
     /// Constructs [`MultiCritic`].
     pub fn build(config: MultiCriticConfig<Q::Config>, device: Device) -> Result<MultiCritic<Q>> {
         let tau = config.tau;
+        let tau_schedule = config.tau_schedule;
         let n_nets = config.n_nets;
         let q_config = config.q_config.context("q_config is not set.")?;
         let opt_config = config.opt_config;
@@ -138,6 +154,7 @@ where
 
         Ok(Self {
             tau,
+            tau_schedule,
             n_nets,
             device,
             varmap,
@@ -171,16 +188,30 @@ where
         (varmap, qs)
     }
 
+    /// Updates the target networks with the soft update coefficient.
     pub fn soft_update(&mut self) -> Result<()> {
         track_with_replace_substring(
             &self.varmap_tgt,
             &self.varmap,
-            self.tau,
+            self.tau(),
             ("critic", "critic_tgt"),
         )?;
         Ok(())
     }
 
+    /// Returns the soft update coefficient for the current number of updates.
+    pub fn tau(&self) -> f64 {
+        match &self.tau_schedule {
+            Some(tau_schedule) => tau_schedule.value(self.opt.n_steps()),
+            None => self.tau,
+        }
+    }
+
+    /// Returns the soft update coefficient if it is scheduled.
+    pub fn scheduled_tau(&self) -> Option<f64> {
+        self.tau_schedule.as_ref().map(|_| self.tau())
+    }
+
     /// Returns action values of all critics.
     pub fn qvals(&self, obs: &Q::Input1, act: &Q::Input2) -> Vec<Tensor> {
         self.qs
@@ -225,6 +256,7 @@ where
 {
     fn clone(&self) -> Self {
         let tau = self.tau;
+        let tau_schedule = self.tau_schedule.clone();
         let n_nets = self.n_nets;
         let device = self.device.clone();
         let q_config = self.q_config.clone();
@@ -246,6 +278,7 @@ where
 
         Self {
             tau,
+            tau_schedule,
             n_nets,
             device,
             varmap,
@@ -278,13 +311,16 @@ where
 
         let mut path_tgt = PathBuf::from(prefix.as_ref());
         path_tgt.set_extension("tgt.pt");
-        self.varmap.save(&path_tgt.as_path())?;
+        self.varmap_tgt.save(&path_tgt.as_path())?;
         info!("Save target critics to {:?}", path_tgt);
 
         Ok((path, path_tgt))
     }
 
-    /// Load variables from prefix + ".pt" and + "_tgt.pt".
+    /// Load variables from prefix + ".pt" and + ".tgt.pt".
+    ///
+    /// Files saved by older versions have the parameters of the critics in ".tgt.pt".
+    /// In this case, the target networks are synchronized with the critics.
     pub fn load<T: AsRef<Path>>(&mut self, prefix: T) -> Result<()> {
         let mut path = PathBuf::from(prefix.as_ref());
         path.set_extension("pt");
@@ -293,11 +329,57 @@ where
 
         let mut path = PathBuf::from(prefix.as_ref());
         path.set_extension("tgt.pt");
-        self.varmap.load(&path.as_path())?;
-        info!("Load target critics from {:?}", path);
+        match self.varmap_tgt.load(&path.as_path()) {
+            Ok(()) => info!("Load target critics from {:?}", path),
+            Err(e) => {
+                warn!("Failed to load target critics from {:?}: {}", path, e);
+                warn!("Target critics are synchronized with critics");
+                track_with_replace_substring(
+                    &self.varmap_tgt,
+                    &self.varmap,
+                    1.0,
+                    ("critic", "critic_tgt"),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Save the state of the optimizer to prefix + ".opt.pt".
+    pub fn save_opt<T: AsRef<Path>>(&self, prefix: T) -> Result<PathBuf> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("opt.pt");
+        self.opt.save(&path, &self.varmap)?;
+        info!("Save critic optimizer to {:?}", path);
+
+        Ok(path)
+    }
+
+    /// Load the state of the optimizer from prefix + ".opt.pt".
+    pub fn load_opt<T: AsRef<Path>>(&mut self, prefix: T) -> Result<()> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("opt.pt");
+        self.opt.load(&path, &self.varmap)?;
+        info!("Load critic optimizer from {:?}", path);
 
         Ok(())
     }
+
+    /// Sets the learning rate of the optimizer.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.opt.set_learning_rate(lr);
+    }
+
+    /// Returns the learning rate used in the last update if it is scheduled.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        self.opt.scheduled_learning_rate()
+    }
+
+    /// Returns the norms of gradients in the last update, before clipping.
+    pub fn grad_stats(&self) -> Result<border_core::record::Record> {
+        self.opt.grad_stats(&self.varmap)
+    }
 }
 
 mod test {
@@ -0,0 +1,402 @@
+//! Actor with deterministic policy.
+use super::actor::ActionLimit;
+use crate::{
+    model::SubModel1,
+    opt::{Optimizer, OptimizerConfig},
+    util::{track_with_replace_substring, OutDim},
+};
+use anyhow::{Context, Result};
+use border_core::Schedule;
+use candle_core::{DType, Device, Tensor};
+use candle_nn::{VarBuilder, VarMap};
+use log::{info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+/// Exploration noise added to the actions of [`DeterministicActor`] in training mode.
+pub enum ExplorationNoise {
+    /// Independent Gaussian noise with the given standard deviation.
+    Gaussian { std: f64 },
+
+    /// Ornstein-Uhlenbeck process, which gives temporally correlated noise.
+    ///
+    /// `x <- x + theta * (-x) * dt + sigma * sqrt(dt) * N(0, 1)`
+    OrnsteinUhlenbeck { theta: f64, sigma: f64, dt: f64 },
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+/// Configuration of [`DeterministicActor`].
+pub struct DeterministicActorConfig<P: OutDim> {
+    pub policy_config: Option<P>,
+    pub opt_config: OptimizerConfig,
+    pub tau: f64,
+    #[serde(default)]
+    pub tau_schedule: Option<Schedule>,
+    pub action_limit: ActionLimit,
+    pub exploration_noise: ExplorationNoise,
+}
+
+impl<P: OutDim> Default for DeterministicActorConfig<P> {
+    fn default() -> Self {
+        Self {
+            policy_config: None,
+            opt_config: OptimizerConfig::Adam { lr: 0.001 },
+            tau: 0.005,
+            tau_schedule: None,
+            action_limit: ActionLimit::Tanh { action_scale: 1.0 },
+            exploration_noise: ExplorationNoise::Gaussian { std: 0.1 },
+        }
+    }
+}
+
+impl<P> DeterministicActorConfig<P>
+where
+    P: DeserializeOwned + Serialize + OutDim,
+{
+    /// Sets configurations for policy function.
+    pub fn policy_config(mut self, v: P) -> Self {
+        self.policy_config = Some(v);
+        self
+    }
+
+    /// Sets output dimension of the model.
+    pub fn out_dim(mut self, v: i64) -> Self {
+        match &mut self.policy_config {
+            None => {}
+            Some(pi_config) => pi_config.set_out_dim(v),
+        };
+        self
+    }
+
+    /// Sets optimizer configuration.
+    pub fn opt_config(mut self, v: OptimizerConfig) -> Self {
+        self.opt_config = v;
+        self
+    }
+
+    /// Sets the parameter of soft update of the target network.
+    pub fn tau(mut self, v: f64) -> Self {
+        self.tau = v;
+        self
+    }
+
+    /// Sets the schedule of soft update parameter tau.
+    ///
+    /// The schedule is indexed by the number of updates of the actor.
+    pub fn tau_schedule(mut self, v: Schedule) -> Self {
+        self.tau_schedule = Some(v);
+        self
+    }
+
+    /// Sets action limit.
+    pub fn action_limit(mut self, action_limit: ActionLimit) -> Self {
+        self.action_limit = action_limit;
+        self
+    }
+
+    /// Sets exploration noise.
+    pub fn exploration_noise(mut self, v: ExplorationNoise) -> Self {
+        self.exploration_noise = v;
+        self
+    }
+
+    /// Loads [`DeterministicActorConfig`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        Ok(b)
+    }
+
+    /// Saves [`DeterministicActorConfig`] as YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Actor with deterministic policy and its target network, used in TD3 and DDPG.
+pub struct DeterministicActor<P>
+where
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Clone,
+{
+    tau: f64,
+    tau_schedule: Option<Schedule>,
+    varmap: VarMap,
+    varmap_tgt: VarMap,
+
+    // Policy model and its target
+    policy: P,
+    policy_tgt: P,
+
+    // Optimizer
+    opt: Optimizer,
+
+    action_limit: ActionLimit,
+    exploration_noise: ExplorationNoise,
+
+    // State of the Ornstein-Uhlenbeck process
+    noise_state: Option<Tensor>,
+}
+
+impl<P> DeterministicActor<P>
+where
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Clone,
+{
+    /// Constructs [`DeterministicActor`].
+    pub fn build(
+        config: DeterministicActorConfig<P::Config>,
+        device: Device,
+    ) -> Result<DeterministicActor<P>> {
+        let policy_config = config.policy_config.context("policy_config is not set.")?;
+        let varmap = VarMap::new();
+        let policy = {
+            let vb = VarBuilder::from_varmap(&varmap, DType::F32, &device).set_prefix("actor");
+            P::build(vb, policy_config.clone())
+        };
+        let varmap_tgt = VarMap::new();
+        let policy_tgt = {
+            let vb =
+                VarBuilder::from_varmap(&varmap_tgt, DType::F32, &device).set_prefix("actor_tgt");
+            P::build(vb, policy_config)
+        };
+        let opt = config.opt_config.build(varmap.all_vars())?;
+
+        // Copy parameters
+        track_with_replace_substring(&varmap_tgt, &varmap, 1.0, ("actor", "actor_tgt"))?;
+
+        Ok(Self {
+            tau: config.tau,
+            tau_schedule: config.tau_schedule,
+            varmap,
+            varmap_tgt,
+            policy,
+            policy_tgt,
+            opt,
+            action_limit: config.action_limit,
+            exploration_noise: config.exploration_noise,
+            noise_state: None,
+        })
+    }
+
+    fn squash(&self, x: Tensor) -> Result<Tensor> {
+        match self.action_limit {
+            ActionLimit::Clamp {
+                action_min,
+                action_max,
+            } => Ok(x.clamp(action_min, action_max)?),
+            ActionLimit::Tanh { action_scale } => Ok((action_scale as f64 * x.tanh()?)?),
+        }
+    }
+
+    /// Clips actions into the range given by the action limit.
+    pub fn clip(&self, act: &Tensor) -> Result<Tensor> {
+        match self.action_limit {
+            ActionLimit::Clamp {
+                action_min,
+                action_max,
+            } => Ok(act.clamp(action_min, action_max)?),
+            ActionLimit::Tanh { action_scale } => Ok(act.clamp(-action_scale, action_scale)?),
+        }
+    }
+
+    /// Returns actions given an observation.
+    ///
+    /// The shape of the tensor is `(batch_size, action_dimension)`.
+    pub fn forward(&self, obs: &P::Input) -> Result<Tensor> {
+        self.squash(self.policy.forward(obs))
+    }
+
+    /// Returns actions of the target network given an observation.
+    pub fn forward_tgt(&self, obs: &P::Input) -> Result<Tensor> {
+        self.squash(self.policy_tgt.forward(obs))
+    }
+
+    /// Samples actions.
+    ///
+    /// If `train` is `true`, exploration noise is added to the actions, which are then
+    /// clipped into the action range. Otherwise, the output of the policy is returned.
+    pub fn sample(&mut self, obs: &P::Input, train: bool) -> Result<Tensor> {
+        let act = self.forward(obs)?.detach();
+        if !train {
+            return Ok(act);
+        }
+
+        let noise = match self.exploration_noise {
+            ExplorationNoise::Gaussian { std } => act.randn_like(0., std)?,
+            ExplorationNoise::OrnsteinUhlenbeck { theta, sigma, dt } => {
+                let x = match self.noise_state.take() {
+                    Some(x) if x.dims() == act.dims() => x,
+                    _ => act.zeros_like()?,
+                };
+                let dx = ((-theta * dt) * &x)?;
+                let dw = act.randn_like(0., sigma * dt.sqrt())?;
+                let x = ((x + dx)? + dw)?;
+                self.noise_state = Some(x.clone());
+                x
+            }
+        };
+        self.clip(&(act + noise)?)
+    }
+
+    /// Resets the state of the Ornstein-Uhlenbeck process.
+    pub fn reset_noise(&mut self) {
+        self.noise_state = None;
+    }
+
+    /// Applies soft update to the target network.
+    pub fn soft_update(&mut self) -> Result<()> {
+        track_with_replace_substring(
+            &self.varmap_tgt,
+            &self.varmap,
+            self.tau(),
+            ("actor", "actor_tgt"),
+        )
+    }
+
+    /// Returns the soft update coefficient for the current number of updates.
+    pub fn tau(&self) -> f64 {
+        match &self.tau_schedule {
+            Some(tau_schedule) => tau_schedule.value(self.opt.n_steps()),
+            None => self.tau,
+        }
+    }
+
+    /// Returns the soft update coefficient if it is scheduled.
+    pub fn scheduled_tau(&self) -> Option<f64> {
+        self.tau_schedule.as_ref().map(|_| self.tau())
+    }
+
+    pub fn backward_step(&mut self, loss: &Tensor) -> Result<()> {
+        self.opt.backward_step(loss)
+    }
+
+    /// Save variables to prefix + ".pt" and + ".tgt.pt".
+    pub fn save(&self, prefix: impl AsRef<Path>) -> Result<(PathBuf, PathBuf)> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("pt");
+        self.varmap.save(path.as_path())?;
+        info!("Save actor parameters to {:?}", path);
+
+        let mut path_tgt = PathBuf::from(prefix.as_ref());
+        path_tgt.set_extension("tgt.pt");
+        self.varmap_tgt.save(path_tgt.as_path())?;
+        info!("Save target actor parameters to {:?}", path_tgt);
+
+        Ok((path, path_tgt))
+    }
+
+    /// Load variables from prefix + ".pt" and + ".tgt.pt".
+    ///
+    /// If ".tgt.pt" is missing, the target network is synchronized with the actor.
+    pub fn load(&mut self, prefix: impl AsRef<Path>) -> Result<()> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("pt");
+        self.varmap.load(path.as_path())?;
+        info!("Load actor parameters from {:?}", path);
+
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("tgt.pt");
+        match self.varmap_tgt.load(path.as_path()) {
+            Ok(()) => info!("Load target actor parameters from {:?}", path),
+            Err(e) => {
+                warn!("Failed to load target actor from {:?}: {}", path, e);
+                warn!("Target actor is synchronized with actor");
+                track_with_replace_substring(
+                    &self.varmap_tgt,
+                    &self.varmap,
+                    1.0,
+                    ("actor", "actor_tgt"),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Save the state of the optimizer to prefix + ".opt.pt".
+    pub fn save_opt(&self, prefix: impl AsRef<Path>) -> Result<PathBuf> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("opt.pt");
+        self.opt.save(&path, &self.varmap)?;
+        info!("Save actor optimizer to {:?}", path);
+
+        Ok(path)
+    }
+
+    /// Load the state of the optimizer from prefix + ".opt.pt".
+    pub fn load_opt(&mut self, prefix: impl AsRef<Path>) -> Result<()> {
+        let mut path = PathBuf::from(prefix.as_ref());
+        path.set_extension("opt.pt");
+        self.opt.load(&path, &self.varmap)?;
+        info!("Load actor optimizer from {:?}", path);
+
+        Ok(())
+    }
+
+    /// Sets the learning rate of the optimizer.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.opt.set_learning_rate(lr);
+    }
+
+    /// Returns the learning rate used in the last update if it is scheduled.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        self.opt.scheduled_learning_rate()
+    }
+
+    /// Returns the norms of gradients in the last update, before clipping.
+    pub fn grad_stats(&self) -> Result<border_core::record::Record> {
+        self.opt.grad_stats(&self.varmap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mlp::{Mlp, MlpConfig},
+        Activation,
+    };
+
+    #[test]
+    fn test_sample() -> Result<()> {
+        let config = DeterministicActorConfig::default()
+            .policy_config(MlpConfig::new(3, vec![8], 2, Activation::None))
+            .action_limit(ActionLimit::Tanh { action_scale: 2.0 })
+            .exploration_noise(ExplorationNoise::OrnsteinUhlenbeck {
+                theta: 0.15,
+                sigma: 10.0,
+                dt: 0.01,
+            });
+        let mut actor = DeterministicActor::<Mlp>::build(config, Device::Cpu)?;
+        let obs = Tensor::randn(0f32, 1f32, (4, 3), &Device::Cpu)?;
+
+        // The target network is a copy of the actor
+        let act = actor.forward(&obs)?;
+        let diff = (&act - actor.forward_tgt(&obs)?)?.abs()?.max_all()?;
+        assert!(diff.to_scalar::<f32>()? < 1e-6);
+        assert!(
+            act.eq(&actor.sample(&obs, false)?)?
+                .min_all()?
+                .to_scalar::<u8>()?
+                == 1
+        );
+
+        // Noisy actions are clipped into the action range
+        for _ in 0..10 {
+            let act = actor.sample(&obs, true)?;
+            assert_eq!(act.dims(), [4, 2]);
+            assert!(act.abs()?.max_all()?.to_scalar::<f32>()? <= 2.0);
+        }
+
+        Ok(())
+    }
+}
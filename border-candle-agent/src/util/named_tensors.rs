@@ -1,104 +1,109 @@
-use candle_core::Tensor;
+use super::tensor_data::TensorData;
+use anyhow::{anyhow, Result};
+use candle_core::{Device::Cpu, Tensor};
 use candle_nn::VarMap;
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
-// use tch::{nn::VarStore, Device::Cpu, Tensor};
 
 /// Named tensors to send model parameters using a channel.
+///
+/// The tensors are serialized with their shapes and elements, so that model parameters
+/// can also be sent to actors in other processes.
+#[derive(Clone)]
 pub struct NamedTensors {
     pub named_tensors: HashMap<String, Tensor>,
 }
 
 impl NamedTensors {
     /// Copy data of [`VarMap`] to CPU.
-    pub fn copy_from(_vs: &VarMap) -> Self {
-        unimplemented!();
-
-        // let src = vs.variables();
-
-        // tch::no_grad(|| NamedTensors {
-        //     named_tensors: HashMap::from_iter(src.iter().map(|(k, v)| {
-        //         let v = v.detach().to(Cpu).data();
-        //         (k.clone(), v)
-        //     })),
-        // })
+    pub fn copy_from(vs: &VarMap) -> Result<Self> {
+        let src = vs.data().lock().unwrap();
+        let named_tensors = src
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), v.as_tensor().to_device(&Cpu)?.copy()?)))
+            .collect::<Result<_>>()?;
+
+        Ok(Self { named_tensors })
     }
 
     /// Copy named tensors to [`VarMap`].
-    pub fn copy_to(&self, _vs: &mut VarMap) {
-        unimplemented!();
-
-        // let src = &self.named_tensors;
-        // let dest = &mut vs.variables();
-        // // let device = vs.device();
-        // debug_assert_eq!(src.len(), dest.len());
-
-        // tch::no_grad(|| {
-        //     for (name, src) in src.iter() {
-        //         let dest = dest.get_mut(name).unwrap();
-        //         dest.copy_(src);
-        //     }
-        // });
+    pub fn copy_to(&self, vs: &VarMap) -> Result<()> {
+        let src = &self.named_tensors;
+        let dest = vs.data().lock().unwrap();
+        debug_assert_eq!(src.len(), dest.len());
+
+        for (name, src) in src.iter() {
+            let dest = dest
+                .get(name)
+                .ok_or_else(|| anyhow!("Variable {} is not found", name))?;
+            dest.set(&src.to_device(dest.device())?)?;
+        }
+
+        Ok(())
     }
 }
 
-impl Clone for NamedTensors {
-    fn clone(&self) -> Self {
-        unimplemented!();
-
-        // let src = &self.named_tensors;
+impl Serialize for NamedTensors {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let named_tensors = self
+            .named_tensors
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), TensorData::from_tensor(v)?)))
+            .collect::<Result<HashMap<_, _>>>()
+            .map_err(ser::Error::custom)?;
+        named_tensors.serialize(serializer)
+    }
+}
 
-        // tch::no_grad(|| NamedTensors {
-        //     named_tensors: HashMap::from_iter(src.iter().map(|(k, v)| {
-        //         let v = v.detach().to(Cpu).data();
-        //         (k.clone(), v)
-        //     })),
-        // })
+impl<'de> Deserialize<'de> for NamedTensors {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let named_tensors = HashMap::<String, TensorData>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(k, v)| Ok((k, v.to_tensor(&Cpu)?)))
+            .collect::<Result<_>>()
+            .map_err(de::Error::custom)?;
+        Ok(Self { named_tensors })
     }
 }
 
 #[cfg(test)]
 mod test {
-    // use super::NamedTensors;
-    // use std::convert::{TryFrom, TryInto};
-    // use tch::{
-    //     nn::{self, Module},
-    //     Device::Cpu,
-    //     Tensor,
-    // };
+    use super::NamedTensors;
+    use anyhow::Result;
+    use candle_core::{DType, Device::Cpu, Module, Tensor};
+    use candle_nn::{linear, seq, Sequential, VarBuilder, VarMap};
+
+    fn model(varmap: &VarMap) -> Result<Sequential> {
+        let vb = VarBuilder::from_varmap(varmap, DType::F32, &Cpu);
+        Ok(seq()
+            .add(linear(3, 8, vb.pp("layer1"))?)
+            .add(linear(8, 2, vb.pp("layer2"))?))
+    }
 
     #[test]
-    fn test_named_tensors() {
-        // tch::manual_seed(42);
-
-        // let tensor1 = Tensor::try_from(vec![1., 2., 3.])
-        //     .unwrap()
-        //     .internal_cast_float(false);
-
-        // let vs1 = nn::VarStore::new(Cpu);
-        // let model1 = nn::seq()
-        //     .add(nn::linear(&vs1.root() / "layer1", 3, 8, Default::default()))
-        //     .add(nn::linear(&vs1.root() / "layer2", 8, 2, Default::default()));
-
-        // let mut vs2 = nn::VarStore::new(tch::Device::cuda_if_available());
-        // let model2 = nn::seq()
-        //     .add(nn::linear(&vs2.root() / "layer1", 3, 8, Default::default()))
-        //     .add(nn::linear(&vs2.root() / "layer2", 8, 2, Default::default()));
-        // let device = vs2.device();
-
-        // let t1: Vec<f64> = model1.forward(&tensor1).try_into().unwrap();
-        // let t2: Vec<f64> = model2.forward(&tensor1.to(device)).try_into().unwrap();
-
-        // let nt = NamedTensors::copy_from(&vs1);
-        // nt.copy_to(&mut vs2);
-
-        // let t3: Vec<f64> = model2.forward(&tensor1.to(device)).try_into().unwrap();
-
-        // for i in 0..2 {
-        //     assert!((t1[i] - t2[i]).abs() >= t1[i].abs() * 0.001);
-        //     assert!((t1[i] - t3[i]).abs() < t1[i].abs() * 0.001);
-        // }
-        // // println!("{:?}", t1);
-        // // println!("{:?}", t2);
-        // // println!("{:?}", t3);
+    fn test_named_tensors() -> Result<()> {
+        let x = Tensor::new(&[[1f32, 2., 3.]], &Cpu)?;
+
+        let vs1 = VarMap::new();
+        let model1 = model(&vs1)?;
+        let vs2 = VarMap::new();
+        let model2 = model(&vs2)?;
+
+        let t1 = model1.forward(&x)?.flatten_all()?.to_vec1::<f32>()?;
+        let t2 = model2.forward(&x)?.flatten_all()?.to_vec1::<f32>()?;
+
+        // Send the parameters of model1 through a serialized message
+        let nt = NamedTensors::copy_from(&vs1)?;
+        let nt: NamedTensors = bincode::deserialize(&bincode::serialize(&nt)?)?;
+        nt.copy_to(&vs2)?;
+
+        let t3 = model2.forward(&x)?.flatten_all()?.to_vec1::<f32>()?;
+
+        for i in 0..2 {
+            assert!((t1[i] - t2[i]).abs() >= t1[i].abs() * 0.001);
+            assert_eq!(t1[i], t3[i]);
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,125 @@
+//! Linear layer with factorized Gaussian noise.
+use anyhow::Result;
+use candle_core::{Device, Tensor};
+use candle_nn::{init::Init, Module, VarBuilder};
+
+/// Linear layer with learnable factorized Gaussian noise on its weights and biases.
+///
+/// The layer computes `y = (w_mu + w_sigma * eps_w) x + b_mu + b_sigma * eps_b`, where
+/// `eps_w = f(eps_out) f(eps_in)^T`, `eps_b = f(eps_out)` and `f(x) = sign(x) sqrt(|x|)`
+/// ([Fortunato et al., 2018](https://arxiv.org/abs/1706.10295)).
+/// The noise is kept fixed until [`NoisyLinear::reset_noise`] is called, and
+/// [`NoisyLinear::remove_noise`] sets it to zero, e.g., for evaluation.
+pub struct NoisyLinear {
+    in_dim: usize,
+    out_dim: usize,
+    device: Device,
+    w_mu: Tensor,
+    w_sigma: Tensor,
+    b_mu: Tensor,
+    b_sigma: Tensor,
+    eps_in: Tensor,
+    eps_out: Tensor,
+}
+
+impl NoisyLinear {
+    /// Constructs [`NoisyLinear`].
+    ///
+    /// `sigma_0` is the initial scale of the noise, 0.5 in the paper.
+    pub fn new(in_dim: usize, out_dim: usize, sigma_0: f64, vb: VarBuilder) -> Result<Self> {
+        let bound = 1.0 / (in_dim as f64).sqrt();
+        let init_mu = Init::Uniform {
+            lo: -bound,
+            up: bound,
+        };
+        let init_sigma = Init::Const(sigma_0 * bound);
+        let w_mu = vb.get_with_hints((out_dim, in_dim), "weight_mu", init_mu)?;
+        let w_sigma = vb.get_with_hints((out_dim, in_dim), "weight_sigma", init_sigma)?;
+        let b_mu = vb.get_with_hints(out_dim, "bias_mu", init_mu)?;
+        let b_sigma = vb.get_with_hints(out_dim, "bias_sigma", init_sigma)?;
+        let device = vb.device().clone();
+
+        let mut layer = Self {
+            in_dim,
+            out_dim,
+            eps_in: Tensor::zeros(in_dim, w_mu.dtype(), &device)?,
+            eps_out: Tensor::zeros(out_dim, w_mu.dtype(), &device)?,
+            device,
+            w_mu,
+            w_sigma,
+            b_mu,
+            b_sigma,
+        };
+        layer.reset_noise()?;
+
+        Ok(layer)
+    }
+
+    fn scaled_noise(&self, n: usize) -> Result<Tensor> {
+        let x = Tensor::randn(0f32, 1f32, n, &self.device)?;
+        Ok((x.sign()? * x.abs()?.sqrt()?)?)
+    }
+
+    /// Resamples the noise.
+    pub fn reset_noise(&mut self) -> Result<()> {
+        self.eps_in = self.scaled_noise(self.in_dim)?;
+        self.eps_out = self.scaled_noise(self.out_dim)?;
+        Ok(())
+    }
+
+    /// Sets the noise to zero, so that the mean weights and biases are used until
+    /// [`NoisyLinear::reset_noise`] is called.
+    pub fn remove_noise(&mut self) -> Result<()> {
+        self.eps_in = self.eps_in.zeros_like()?;
+        self.eps_out = self.eps_out.zeros_like()?;
+        Ok(())
+    }
+}
+
+impl Module for NoisyLinear {
+    fn forward(&self, xs: &Tensor) -> Result<Tensor, candle_core::Error> {
+        let eps_w = self
+            .eps_out
+            .unsqueeze(1)?
+            .broadcast_mul(&self.eps_in.unsqueeze(0)?)?;
+        let w = (&self.w_mu + (&self.w_sigma * eps_w)?)?;
+        let b = (&self.b_mu + (&self.b_sigma * &self.eps_out)?)?;
+        xs.broadcast_matmul(&w.t()?)?.broadcast_add(&b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_core::DType;
+    use candle_nn::VarMap;
+
+    #[test]
+    fn test_reset_noise() -> Result<()> {
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        let mut layer = NoisyLinear::new(3, 2, 0.5, vb)?;
+        let xs = Tensor::randn(0f32, 1f32, (4, 3), &Device::Cpu)?;
+
+        // Output is fixed until the noise is resampled
+        let y1 = layer.forward(&xs)?;
+        let y2 = layer.forward(&xs)?;
+        assert_eq!(y1.dims(), [4, 2]);
+        assert!((&y1 - &y2)?.abs()?.max_all()?.to_scalar::<f32>()? < 1e-6);
+
+        layer.reset_noise()?;
+        let y3 = layer.forward(&xs)?;
+        assert!((&y1 - &y3)?.abs()?.max_all()?.to_scalar::<f32>()? > 1e-6);
+
+        // Output with the mean weights and biases
+        layer.remove_noise()?;
+        let y4 = layer.forward(&xs)?;
+        let y5 = xs.matmul(&layer.w_mu.t()?)?.broadcast_add(&layer.b_mu)?;
+        assert!((&y4 - &y5)?.abs()?.max_all()?.to_scalar::<f32>()? < 1e-6);
+
+        // Noise parameters are registered as trainable variables
+        assert_eq!(varmap.all_vars().len(), 4);
+
+        Ok(())
+    }
+}
@@ -1,15 +1,33 @@
 //! Quantile loss.
-use candle_core::Tensor;
+use candle_core::{DType, Tensor};
 
 /// Returns the quantile huber loss.
 ///
-/// `x` and `tau` has the same shape.
-pub fn quantile_huber_loss(_x: &Tensor, _tau: &Tensor) -> Tensor {
-    // TODO: implements this
-    panic!();
-    // debug_assert_eq!(x.size().as_slice(), tau.size().as_slice());
+/// `x` is the difference between target and predicted quantiles and `tau` is the
+/// percent points of the predicted quantiles. `tau` must be broadcastable to the shape
+/// of `x`. The loss is computed elementwise, i.e., no reduction is applied.
+pub fn quantile_huber_loss(x: &Tensor, tau: &Tensor) -> Result<Tensor, candle_core::Error> {
+    let d = x.abs()?;
+    let m = d.lt(1.0)?.to_dtype(DType::F32)?;
+    let huber = (((0.5 * &m)? * d.sqr()?)? + ((1.0 - &m)? * (d - 0.5)?)?)?;
+    let lt_0 = x.lt(0.0)?.to_dtype(DType::F32)?;
+    tau.broadcast_sub(&lt_0)?.abs()? * huber
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_core::Device;
 
-    // let lt_0 = &x.lt(0.0).detach();
-    // let loss = x.smooth_l1_loss(&Tensor::zeros_like(x), tch::Reduction::None, 1.0);
-    // (tau - Tensor::where_scalar(lt_0, 1., 0.)).abs() * loss
+    #[test]
+    fn test_quantile_huber_loss() -> Result<(), candle_core::Error> {
+        let x = Tensor::new(&[-2f32, -0.5, 0.5, 2.0], &Device::Cpu)?;
+        let tau = Tensor::new(&[0.25f32], &Device::Cpu)?;
+        let loss = quantile_huber_loss(&x, &tau)?.to_vec1::<f32>()?;
+        let expected = [0.75 * 1.5, 0.75 * 0.125, 0.25 * 0.125, 0.25 * 1.5];
+        for (l, e) in loss.iter().zip(expected.iter()) {
+            assert!((l - e).abs() < 1e-6);
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,53 @@
+//! Serializable data of tensors.
+use anyhow::{anyhow, Result};
+use candle_core::{DType, Device, Tensor};
+use serde::{Deserialize, Serialize};
+
+/// Elements of a tensor.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+enum Elements {
+    U8(Vec<u8>),
+    U32(Vec<u32>),
+    I64(Vec<i64>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+/// Shape and elements of a tensor.
+///
+/// This is used to serialize tensors, e.g., to send them to actors in other processes.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub(crate) struct TensorData {
+    shape: Vec<usize>,
+    elements: Elements,
+}
+
+impl TensorData {
+    /// Copies the shape and elements of a tensor.
+    pub fn from_tensor(t: &Tensor) -> Result<Self> {
+        let shape = t.dims().to_vec();
+        let t = t.flatten_all()?;
+        let elements = match t.dtype() {
+            DType::U8 => Elements::U8(t.to_vec1()?),
+            DType::U32 => Elements::U32(t.to_vec1()?),
+            DType::I64 => Elements::I64(t.to_vec1()?),
+            DType::F32 => Elements::F32(t.to_vec1()?),
+            DType::F64 => Elements::F64(t.to_vec1()?),
+            dtype => return Err(anyhow!("Unsupported dtype: {:?}", dtype)),
+        };
+        Ok(Self { shape, elements })
+    }
+
+    /// Creates a tensor on `device`.
+    pub fn to_tensor(&self, device: &Device) -> Result<Tensor> {
+        let shape = self.shape.as_slice();
+        let t = match &self.elements {
+            Elements::U8(v) => Tensor::from_slice(v, shape, device)?,
+            Elements::U32(v) => Tensor::from_slice(v, shape, device)?,
+            Elements::I64(v) => Tensor::from_slice(v, shape, device)?,
+            Elements::F32(v) => Tensor::from_slice(v, shape, device)?,
+            Elements::F64(v) => Tensor::from_slice(v, shape, device)?,
+        };
+        Ok(t)
+    }
+}
@@ -33,9 +33,9 @@ pub use step::{Info, Step, StepProcessor};
 ///
 /// # Note
 ///
-/// While the interface supports vectorized environments through the `len` method,
-/// the current implementation only supports single environments. Therefore,
-/// `len()` is expected to return 1 in all cases.
+/// For vectorized environments, an observation holds the observations of all
+/// sub-environments and `len()` returns the number of sub-environments.
+/// Otherwise, `len()` is expected to return 1.
 ///
 /// # Examples
 ///
@@ -57,8 +57,8 @@ pub trait Obs: Clone + Debug {
     ///
     /// # Returns
     ///
-    /// The number of observations. Currently, this should always return 1
-    /// as vectorized environments are not supported.
+    /// The number of observations. This should return 1 except for
+    /// observations of vectorized environments.
     fn len(&self) -> usize;
 }
 
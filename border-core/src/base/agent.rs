@@ -5,7 +5,7 @@
 //! parameter optimization, and model persistence.
 use super::{Env, Policy, ReplayBufferBase};
 use crate::record::Record;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::path::{Path, PathBuf};
 
 /// A trainable policy that can learn from environment interactions.
@@ -116,6 +116,63 @@ pub trait Agent<E: Env, R: ReplayBufferBase>: Policy<E> {
         unimplemented!();
     }
 
+    /// Saves the whole state of the agent required to resume training.
+    ///
+    /// In addition to the parameters saved by [`save_params`], the state includes
+    /// everything that affects subsequent optimization steps, such as target networks,
+    /// moments of optimizers and internal counters. This method is used by
+    /// [`Trainer::save_checkpoint`].
+    ///
+    /// The default implementation calls [`save_params`], which is sufficient for agents
+    /// without such additional state.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The directory where the state will be saved
+    ///
+    /// # Returns
+    ///
+    /// A vector of paths to the saved files
+    ///
+    /// [`save_params`]: Agent::save_params
+    /// [`Trainer::save_checkpoint`]: crate::Trainer::save_checkpoint
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        self.save_params(path)
+    }
+
+    /// Loads the state of the agent saved by [`save_checkpoint`].
+    ///
+    /// The default implementation calls [`load_params`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The directory containing the saved state
+    ///
+    /// [`save_checkpoint`]: Agent::save_checkpoint
+    /// [`load_params`]: Agent::load_params
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)
+    }
+
+    /// Sets the learning rate of the optimizers of the agent.
+    ///
+    /// This method is called by [`Trainer`] when a [`TrainerCallback`] requests
+    /// a change of the learning rate. Auxiliary optimizers, e.g., the one for the
+    /// entropy coefficient in SAC, are not affected.
+    ///
+    /// The default implementation returns an error, as the agent does not support
+    /// changing the learning rate.
+    ///
+    /// [`Trainer`]: crate::Trainer
+    /// [`TrainerCallback`]: crate::TrainerCallback
+    #[allow(unused_variables)]
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        Err(anyhow!(
+            "{} does not support setting the learning rate",
+            std::any::type_name::<Self>()
+        ))
+    }
+
     /// Returns a reference to the agent as a type-erased `Any` value.
     ///
     /// This method is required for asynchronous training, allowing the agent to be
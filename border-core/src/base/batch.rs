@@ -93,4 +93,13 @@ pub trait TransitionBatch {
     /// This provides efficient access to the actions without unpacking the
     /// entire batch.
     fn act(&self) -> &Self::ActBatch;
+
+    /// Returns the number of steps n between `o_t` and `o_t+n` of each transition.
+    ///
+    /// Agents should use `gamma^n` as the discount factor of the bootstrapped value
+    /// of `o_t+n`, where the reward is the discounted sum of n rewards. `None` means
+    /// that all of the transitions are one-step transitions.
+    fn n_step(&self) -> Option<&[usize]> {
+        None
+    }
 }
@@ -112,9 +112,12 @@ pub trait Env {
     ///
     /// # Note
     ///
-    /// While the interface supports vectorized environments through `is_done`,
-    /// the current implementation only supports single environments.
-    /// Therefore, `is_done.len()` is expected to be 1.
+    /// For vectorized environments (see [`VecEnv`]), `is_done` has one flag per
+    /// sub-environment. Only the sub-environments with `is_done[i] == 1` are reset,
+    /// and the returned observation contains the current observations of the others.
+    /// `None` resets all sub-environments.
+    ///
+    /// [`VecEnv`]: crate::vec_env::VecEnv
     fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs>;
 
     /// Performs a step and automatically resets the environment if the episode ends.
@@ -123,6 +126,9 @@ pub trait Env {
     /// If the step results in episode termination, the environment is automatically
     /// reset and the initial observation is included in the returned step.
     ///
+    /// For vectorized environments, only the finished sub-environments are reset
+    /// by giving [`Step::dones`] to [`reset`].
+    ///
     /// # Arguments
     ///
     /// * `a` - The action to apply to the environment
@@ -140,9 +146,11 @@ pub trait Env {
         Self: Sized,
     {
         let (step, record) = self.step(a);
-        assert_eq!(step.is_terminated.len(), 1);
-        let step = if step.is_done() {
-            let init_obs = self.reset(None).unwrap();
+        let step = if step.is_any_done() {
+            let init_obs = match step.n_envs() {
+                1 => self.reset(None).unwrap(),
+                _ => self.reset(Some(&step.dones())).unwrap(),
+            };
             Step {
                 act: step.act,
                 obs: step.obs,
@@ -4,7 +4,8 @@
 //! Replay buffers are essential components that store and sample experiences (transitions)
 //! for training agents, enabling more efficient learning through experience replay.
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use std::path::Path;
 
 /// Interface for buffers that store experiences from environments.
 ///
@@ -53,6 +54,22 @@ pub trait ExperienceBufferBase {
     /// `Ok(())` if the push was successful, or an error if it failed
     fn push(&mut self, tr: Self::Item) -> Result<()>;
 
+    /// Pushes a new experience with the priorities of its transitions.
+    ///
+    /// This is used in distributed prioritized experience replay, where actors compute
+    /// the initial priorities of transitions, e.g., absolute TD errors. Buffers with
+    /// prioritized experience replay use them instead of the maximum priority.
+    /// The default implementation ignores the priorities.
+    ///
+    /// # Arguments
+    ///
+    /// * `tr` - The experience to store
+    /// * `priorities` - Priorities of the transitions in the experience
+    fn push_with_priority(&mut self, tr: Self::Item, priorities: Vec<f32>) -> Result<()> {
+        let _ = priorities;
+        self.push(tr)
+    }
+
     /// Returns the current number of experiences in the buffer.
     ///
     /// # Returns
@@ -124,6 +141,55 @@ pub trait ReplayBufferBase {
     /// This method is optional and may be moved to a separate trait
     /// in future versions to better support non-prioritized replay buffers.
     fn update_priority(&mut self, ixs: &Option<Vec<usize>>, td_err: &Option<Vec<f32>>);
+
+    /// Returns `true` if the buffer can provide batches for an optimization step.
+    ///
+    /// [`Trainer`](crate::Trainer) skips optimization steps while this method returns
+    /// `false`. Replay buffers of off-policy algorithms are always ready by default, while
+    /// buffers of on-policy algorithms, e.g.,
+    /// [`RolloutBuffer`](crate::generic_replay_buffer::RolloutBuffer), become ready when
+    /// a rollout has been collected.
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    /// Saves the state of the replay buffer into the directory `path`.
+    ///
+    /// The state includes the stored experiences and any additional information
+    /// required to continue sampling in the same way, e.g., priorities and the state
+    /// of the random number generator. This method is used for training checkpoints.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The directory where the state will be saved
+    ///
+    /// The default implementation returns an error, as the replay buffer does not
+    /// support training checkpoints.
+    #[allow(unused_variables)]
+    fn save(&self, path: &Path) -> Result<()> {
+        Err(anyhow!(
+            "{} does not support checkpointing",
+            std::any::type_name::<Self>()
+        ))
+    }
+
+    /// Loads the state of the replay buffer saved by [`ReplayBufferBase::save`].
+    ///
+    /// The replay buffer should be built with the same configuration as the saved one.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The directory containing the saved state
+    ///
+    /// The default implementation returns an error, as the replay buffer does not
+    /// support training checkpoints.
+    #[allow(unused_variables)]
+    fn load(&mut self, path: &Path) -> Result<()> {
+        Err(anyhow!(
+            "{} does not support checkpointing",
+            std::any::type_name::<Self>()
+        ))
+    }
 }
 
 /// A dummy replay buffer that does nothing.
@@ -149,4 +215,14 @@ impl ReplayBufferBase for NullReplayBuffer {
     fn update_priority(&mut self, ixs: &Option<Vec<usize>>, td_err: &Option<Vec<f32>>) {
         unimplemented!();
     }
+
+    #[allow(unused_variables)]
+    fn save(&self, path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    fn load(&mut self, path: &Path) -> Result<()> {
+        Ok(())
+    }
 }
@@ -27,6 +27,9 @@ pub trait Info {}
 
 impl Info for () {}
 
+/// Information of the sub-environments in a vectorized environment.
+impl<I: Info> Info for Vec<I> {}
+
 /// Represents a single step in the environment, containing the action taken,
 /// the resulting observation, reward, and episode status.
 ///
@@ -88,6 +91,10 @@ pub struct Step<E: Env> {
 
     /// The initial observation of the next episode, if applicable.
     /// This is used when an episode ends and a new one begins.
+    ///
+    /// For vectorized environments, this holds the observations from which all
+    /// sub-environments continue: the initial observation of the new episode for
+    /// finished sub-environments and `obs` for the others.
     pub init_obs: Option<E::Obs>,
 }
 
@@ -136,6 +143,40 @@ impl<E: Env> Step<E> {
     pub fn is_done(&self) -> bool {
         self.is_terminated[0] == 1 || self.is_truncated[0] == 1
     }
+
+    /// Returns the number of (sub-)environments this step was produced by.
+    ///
+    /// This is 1 for non-vectorized environments.
+    #[inline]
+    pub fn n_envs(&self) -> usize {
+        self.reward.len()
+    }
+
+    /// Checks if the episode of the `ix`-th sub-environment has ended.
+    #[inline]
+    pub fn is_done_at(&self, ix: usize) -> bool {
+        self.is_terminated[ix] == 1 || self.is_truncated[ix] == 1
+    }
+
+    /// Checks if the episode of any sub-environment has ended.
+    ///
+    /// For non-vectorized environments, this is equivalent to [`Step::is_done`].
+    #[inline]
+    pub fn is_any_done(&self) -> bool {
+        (0..self.n_envs()).any(|ix| self.is_done_at(ix))
+    }
+
+    /// Returns flags indicating which sub-environments have ended their episodes.
+    ///
+    /// The returned vector can be given to [`Env::reset`] in order to reset
+    /// only the finished sub-environments.
+    ///
+    /// [`Env::reset`]: crate::Env::reset
+    pub fn dones(&self) -> Vec<i8> {
+        (0..self.n_envs())
+            .map(|ix| self.is_done_at(ix) as i8)
+            .collect()
+    }
 }
 
 /// Processes environment steps and produces items for a replay buffer.
@@ -10,8 +10,72 @@
 
 use crate::{record::Record, Agent, Env, ReplayBufferBase};
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 mod default_evaluator;
+mod parallel_evaluator;
+mod stats_evaluator;
 pub use default_evaluator::DefaultEvaluator;
+pub use parallel_evaluator::ParallelEvaluator;
+pub use stats_evaluator::StatsEvaluator;
+
+/// Metric of evaluation used for model selection in [`Trainer`].
+///
+/// Except for [`EvalMetric::Score`], the metric is taken from the [`Record`] returned by
+/// [`Evaluator::evaluate`] with the key given by [`EvalMetric::key`]. The keys of the
+/// statistics are those recorded by [`StatsEvaluator`].
+///
+/// [`Trainer`]: crate::Trainer
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub enum EvalMetric {
+    /// The performance metric returned by [`Evaluator::evaluate`].
+    #[default]
+    Score,
+
+    /// Mean of episode returns.
+    Mean,
+
+    /// Median of episode returns.
+    Median,
+
+    /// Interquartile mean of episode returns.
+    Iqm,
+
+    /// Minimum of episode returns.
+    Min,
+
+    /// Maximum of episode returns.
+    Max,
+
+    /// Lower bound of the confidence interval of the mean return.
+    CiLower,
+
+    /// A scalar value in the record with the given key.
+    Key(String),
+}
+
+impl EvalMetric {
+    /// Returns the key of the metric in the record, or `None` for [`EvalMetric::Score`].
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            Self::Score => None,
+            Self::Mean => Some("Episode return"),
+            Self::Median => Some("Episode return median"),
+            Self::Iqm => Some("Episode return IQM"),
+            Self::Min => Some("Episode return min"),
+            Self::Max => Some("Episode return max"),
+            Self::CiLower => Some("Episode return CI lower"),
+            Self::Key(key) => Some(key),
+        }
+    }
+
+    /// Returns the value of the metric given the result of [`Evaluator::evaluate`].
+    pub fn value(&self, score: f32, record: &Record) -> Result<f32> {
+        match self.key() {
+            None => Ok(score),
+            Some(key) => Ok(record.get_scalar(key)?),
+        }
+    }
+}
 
 /// Interface for evaluating reinforcement learning agents.
 ///
@@ -0,0 +1,441 @@
+//! Evaluator running episodes concurrently in multiple environment instances.
+use super::{stats_evaluator::episode_record, Evaluator};
+use crate::{record::Record, Agent, Configurable, Env, ReplayBufferBase};
+use anyhow::{anyhow, Result};
+use std::{
+    fs::{create_dir_all, remove_dir_all},
+    marker::PhantomData,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, Receiver, Sender},
+    },
+    thread::JoinHandle,
+};
+
+/// Counter for making directories of parameters unique in the process.
+static N_EVALUATORS: AtomicUsize = AtomicUsize::new(0);
+
+/// A request to a worker thread to evaluate the policy.
+struct Request {
+    /// Directory of the parameters of the policy.
+    params_dir: PathBuf,
+
+    /// If `true`, the policy is evaluated in training mode.
+    is_train: bool,
+
+    /// Indices of episodes given to [`Env::reset_with_index`].
+    ixs: Vec<usize>,
+}
+
+/// Results of episodes, i.e., indices, returns and lengths.
+type Episodes = Vec<(usize, f32, f32)>;
+
+/// A thread owning an environment and a policy.
+struct Worker {
+    tx: Option<Sender<Request>>,
+    rx: Receiver<Result<Episodes>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    fn send(&self, request: Request) -> Result<()> {
+        self.tx
+            .as_ref()
+            .unwrap()
+            .send(request)
+            .map_err(|_| anyhow!("The worker thread of ParallelEvaluator has terminated"))
+    }
+
+    fn recv(&self) -> Result<Episodes> {
+        self.rx
+            .recv()
+            .map_err(|_| anyhow!("The worker thread of ParallelEvaluator has terminated"))?
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // Closing the channel terminates the loop in the worker thread
+        self.tx = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Loop of a worker thread.
+fn run_worker<E, A, R>(
+    env_config: E::Config,
+    agent_config: A::Config,
+    seed: i64,
+    rx: Receiver<Request>,
+    tx: Sender<Result<Episodes>>,
+) where
+    E: Env,
+    A: Agent<E, R> + Configurable,
+    R: ReplayBufferBase,
+{
+    let mut agent = A::build(agent_config);
+    let mut env = match E::build(&env_config, seed) {
+        Ok(env) => {
+            let _ = tx.send(Ok(vec![]));
+            env
+        }
+        Err(e) => {
+            let _ = tx.send(Err(e));
+            return;
+        }
+    };
+
+    let mut run = |request: Request| -> Result<Episodes> {
+        agent.load_params(&request.params_dir)?;
+        match request.is_train {
+            true => agent.train(),
+            false => agent.eval(),
+        }
+
+        let mut episodes = Vec::with_capacity(request.ixs.len());
+        for ix in request.ixs {
+            let mut prev_obs = env.reset_with_index(ix)?;
+            let (mut ret, mut len) = (0f32, 0usize);
+
+            loop {
+                let act = agent.sample(&prev_obs);
+                let (step, _) = env.step(&act);
+                ret += step.reward[0];
+                len += 1;
+                if step.is_done() {
+                    break;
+                }
+                prev_obs = step.obs;
+            }
+
+            episodes.push((ix, ret, len as f32));
+        }
+
+        Ok(episodes)
+    };
+
+    while let Ok(request) = rx.recv() {
+        if tx.send(run(request)).is_err() {
+            break;
+        }
+    }
+}
+
+/// An evaluator running episodes concurrently on worker threads.
+///
+/// Each worker thread builds an environment from `E::Config` and an agent of type `A`
+/// from `A::Config`. The `i`-th environment is given seed `seed + i`, and the `j`-th
+/// episode, reset with [`Env::reset_with_index`]`(j)`, is run by the `(j % n_workers)`-th
+/// worker. Since results are merged in the order of the indices, they do not depend on
+/// scheduling of the threads.
+///
+/// At each evaluation, the parameters of the evaluated agent are saved with
+/// [`Agent::save_params`] into a temporary directory, then loaded into the agents of
+/// the workers with [`Agent::load_params`]. The agents of the workers are switched to
+/// the same mode, i.e., training or evaluation mode, as the evaluated agent.
+///
+/// The performance metric is the average return, and the returned [`Record`] contains
+/// the same values as [`StatsEvaluator`](super::StatsEvaluator) except for the
+/// confidence interval.
+///
+/// The worker threads are terminated when this struct is dropped.
+///
+/// # Type Parameters
+///
+/// * `E` - The environment type
+/// * `A` - The agent type used in the worker threads
+/// * `R` - The replay buffer type of `A`, which is not used in evaluation
+///
+/// # Examples
+///
+/// ```ignore
+/// let mut evaluator =
+///     ParallelEvaluator::<Env, Dqn<Env, Model, ReplayBuffer>, ReplayBuffer>::new(
+///         &env_config, &agent_config, 42, 32, 8,
+///     )?;
+/// let (score, record) = evaluator.evaluate(&mut agent)?;
+/// ```
+pub struct ParallelEvaluator<E, A, R>
+where
+    E: Env,
+    A: Agent<E, R> + Configurable,
+    R: ReplayBufferBase,
+{
+    /// The number of episodes to run during evaluation.
+    n_episodes: usize,
+
+    /// Worker threads, each of which owns an environment and an agent.
+    workers: Vec<Worker>,
+
+    /// Temporary directory of the parameters of the evaluated agent.
+    params_dir: PathBuf,
+
+    phantom: PhantomData<(E, A, R)>,
+}
+
+impl<E, A, R> ParallelEvaluator<E, A, R>
+where
+    E: Env + 'static,
+    E::Config: Send + 'static,
+    A: Agent<E, R> + Configurable + 'static,
+    A::Config: Send + 'static,
+    R: ReplayBufferBase + 'static,
+{
+    /// Constructs a new [`ParallelEvaluator`] and spawns worker threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `env_config` - Configuration for the environments
+    /// * `agent_config` - Configuration for the agents of the workers
+    /// * `seed` - Random seed for environment initialization
+    /// * `n_episodes` - Number of episodes to run during evaluation, which should be positive
+    /// * `n_workers` - Number of worker threads
+    pub fn new(
+        env_config: &E::Config,
+        agent_config: &A::Config,
+        seed: i64,
+        n_episodes: usize,
+        n_workers: usize,
+    ) -> Result<Self> {
+        if n_episodes == 0 {
+            return Err(anyhow!(
+                "n_episodes of ParallelEvaluator should be positive"
+            ));
+        }
+        if n_workers == 0 {
+            return Err(anyhow!("n_workers of ParallelEvaluator should be positive"));
+        }
+
+        let mut workers = Vec::with_capacity(n_workers);
+        for i in 0..n_workers {
+            let (tx_request, rx_request) = channel();
+            let (tx_response, rx_response) = channel();
+            let env_config = env_config.clone();
+            let agent_config = agent_config.clone();
+            let seed = seed + i as i64;
+            let handle = std::thread::Builder::new()
+                .name(format!("parallel_evaluator_{}", i))
+                .spawn(move || {
+                    run_worker::<E, A, R>(env_config, agent_config, seed, rx_request, tx_response)
+                })?;
+            workers.push(Worker {
+                tx: Some(tx_request),
+                rx: rx_response,
+                handle: Some(handle),
+            });
+        }
+
+        // Wait for the environments to be built
+        for worker in workers.iter() {
+            worker.recv()?;
+        }
+
+        let params_dir = std::env::temp_dir().join(format!(
+            "border_parallel_evaluator_{}_{}",
+            std::process::id(),
+            N_EVALUATORS.fetch_add(1, Ordering::SeqCst)
+        ));
+
+        Ok(Self {
+            n_episodes,
+            workers,
+            params_dir,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<E, A, R> Drop for ParallelEvaluator<E, A, R>
+where
+    E: Env,
+    A: Agent<E, R> + Configurable,
+    R: ReplayBufferBase,
+{
+    fn drop(&mut self) {
+        if self.params_dir.exists() {
+            let _ = remove_dir_all(&self.params_dir);
+        }
+    }
+}
+
+impl<E, A, R> Evaluator<E> for ParallelEvaluator<E, A, R>
+where
+    E: Env,
+    A: Agent<E, R> + Configurable,
+    R: ReplayBufferBase,
+{
+    /// Evaluates the agent by running episodes on the worker threads.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Saving or loading the parameters fails
+    /// - An environment fails to reset
+    /// - A worker thread has terminated unexpectedly
+    fn evaluate<R_>(&mut self, agent: &mut Box<dyn Agent<E, R_>>) -> Result<(f32, Record)>
+    where
+        R_: ReplayBufferBase,
+    {
+        create_dir_all(&self.params_dir)?;
+        agent.save_params(&self.params_dir)?;
+
+        let (n_workers, is_train) = (self.workers.len(), agent.is_train());
+        let sent = self
+            .workers
+            .iter()
+            .enumerate()
+            .map(|(i, worker)| {
+                worker.send(Request {
+                    params_dir: self.params_dir.clone(),
+                    is_train,
+                    ixs: (i..self.n_episodes).step_by(n_workers).collect(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Receive all results before handling errors, so that no result is left in channels
+        let results = self
+            .workers
+            .iter()
+            .zip(sent)
+            .map(|(worker, sent)| sent.and_then(|_| worker.recv()))
+            .collect::<Vec<_>>();
+
+        // Merge results in the order of the indices of episodes
+        let mut episodes = vec![];
+        for result in results {
+            episodes.extend(result?);
+        }
+        episodes.sort_by_key(|&(ix, _, _)| ix);
+        let (returns, lengths): (Vec<_>, Vec<_>) =
+            episodes.into_iter().map(|(_, ret, len)| (ret, len)).unzip();
+
+        let performance = returns.iter().sum::<f32>() / returns.len() as f32;
+        Ok((performance, episode_record(returns, lengths, "")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generic_replay_buffer::SimpleReplayBuffer,
+        test::{TestAct, TestActBatch, TestInfo, TestObs, TestObsBatch},
+        Policy, Step,
+    };
+    use std::path::Path;
+
+    type ReplayBuffer = SimpleReplayBuffer<TestObsBatch, TestActBatch>;
+
+    /// Environment whose `ix`-th episode lasts `ix + 1` steps, where the reward is the action.
+    struct EpisodeEnv {
+        t: usize,
+        t_max: usize,
+    }
+
+    impl Env for EpisodeEnv {
+        type Config = ();
+        type Obs = TestObs;
+        type Act = TestAct;
+        type Info = TestInfo;
+
+        fn build(_config: &Self::Config, _seed: i64) -> Result<Self> {
+            Ok(Self { t: 0, t_max: 0 })
+        }
+
+        fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+            unimplemented!();
+        }
+
+        fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+            self.t = 0;
+            self.t_max = ix + 1;
+            Ok(TestObs { obs: 0 })
+        }
+
+        fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+            self.t += 1;
+            let step = Step {
+                obs: TestObs { obs: self.t },
+                act: a.clone(),
+                reward: vec![a.act as f32],
+                is_terminated: vec![(self.t == self.t_max) as i8],
+                is_truncated: vec![0],
+                info: TestInfo {},
+                init_obs: None,
+            };
+            (step, Record::empty())
+        }
+
+        fn step_with_reset(&mut self, _a: &Self::Act) -> (Step<Self>, Record) {
+            unimplemented!();
+        }
+    }
+
+    /// Agent taking the action loaded from a file.
+    struct ConstAgent {
+        act: usize,
+    }
+
+    impl Policy<EpisodeEnv> for ConstAgent {
+        fn sample(&mut self, _obs: &TestObs) -> TestAct {
+            TestAct { act: self.act }
+        }
+    }
+
+    impl Configurable for ConstAgent {
+        type Config = usize;
+
+        fn build(config: Self::Config) -> Self {
+            Self { act: config }
+        }
+    }
+
+    impl Agent<EpisodeEnv, ReplayBuffer> for ConstAgent {
+        fn train(&mut self) {}
+
+        fn eval(&mut self) {}
+
+        fn is_train(&self) -> bool {
+            false
+        }
+
+        fn opt_with_record(&mut self, _buffer: &mut ReplayBuffer) -> Record {
+            Record::empty()
+        }
+
+        fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+            let path = path.join("act.txt");
+            std::fs::write(&path, self.act.to_string())?;
+            Ok(vec![path])
+        }
+
+        fn load_params(&mut self, path: &Path) -> Result<()> {
+            self.act = std::fs::read_to_string(path.join("act.txt"))?.parse()?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parallel_evaluator() -> Result<()> {
+        let mut evaluator =
+            ParallelEvaluator::<EpisodeEnv, ConstAgent, ReplayBuffer>::new(&(), &0, 0, 5, 2)?;
+        let mut agent: Box<dyn Agent<EpisodeEnv, ReplayBuffer>> = Box::new(ConstAgent { act: 2 });
+
+        let (score, record) = evaluator.evaluate(&mut agent)?;
+        assert_eq!(score, 6.0);
+        assert_eq!(
+            record.get_array1("Episode returns")?,
+            vec![2.0, 4.0, 6.0, 8.0, 10.0]
+        );
+        assert_eq!(
+            record.get_array1("Episode lengths")?,
+            vec![1.0, 2.0, 3.0, 4.0, 5.0]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+//! Evaluator reporting statistics of episode returns.
+//!
+//! This module provides an evaluator that records per-episode returns and lengths
+//! together with summary statistics, optionally with confidence intervals and results
+//! of a stochastic policy.
+
+use super::Evaluator;
+use crate::{
+    record::{Record, RecordValue},
+    Agent, Env, ReplayBufferBase,
+};
+use anyhow::{anyhow, Result};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+/// Number of resamples for bootstrap confidence intervals.
+const N_BOOTSTRAP: usize = 1000;
+
+/// Summary statistics of values over episodes.
+#[derive(Clone, Debug, PartialEq)]
+struct Stats {
+    mean: f32,
+    std: f32,
+    min: f32,
+    max: f32,
+    median: f32,
+    iqm: f32,
+}
+
+impl Stats {
+    /// Computes statistics of non-empty values.
+    fn new(values: &[f32]) -> Self {
+        let n = values.len();
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let mean = sorted.iter().sum::<f32>() / n as f32;
+        let var = sorted.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n as f32;
+
+        // Interquartile mean, the mean of the values with the lowest and highest 25% trimmed
+        let n_trim = n / 4;
+        let middle = &sorted[n_trim..n - n_trim];
+        let iqm = middle.iter().sum::<f32>() / middle.len() as f32;
+
+        Self {
+            mean,
+            std: var.sqrt(),
+            min: sorted[0],
+            max: sorted[n - 1],
+            median: quantile(&sorted, 0.5),
+            iqm,
+        }
+    }
+}
+
+/// Returns the `q`-quantile of sorted values with linear interpolation.
+fn quantile(sorted: &[f32], q: f32) -> f32 {
+    let pos = q * (sorted.len() - 1) as f32;
+    let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f32)
+}
+
+/// Creates a record of the returns and the lengths of episodes with their statistics.
+///
+/// The keys are those described in [`StatsEvaluator`] followed by `suffix`, except for
+/// the confidence interval.
+pub(super) fn episode_record(returns: Vec<f32>, lengths: Vec<f32>, suffix: &str) -> Record {
+    let key = |name: &str| format!("{}{}", name, suffix);
+    let stats = Stats::new(&returns);
+    let mut record = Record::from_slice(&[
+        (key("Episode return"), RecordValue::Scalar(stats.mean)),
+        (key("Episode return std"), RecordValue::Scalar(stats.std)),
+        (key("Episode return min"), RecordValue::Scalar(stats.min)),
+        (key("Episode return max"), RecordValue::Scalar(stats.max)),
+        (
+            key("Episode return median"),
+            RecordValue::Scalar(stats.median),
+        ),
+        (key("Episode return IQM"), RecordValue::Scalar(stats.iqm)),
+        (
+            key("Episode length"),
+            RecordValue::Scalar(Stats::new(&lengths).mean),
+        ),
+    ]);
+    record.insert(key("Episode returns"), RecordValue::Array1(returns));
+    record.insert(key("Episode lengths"), RecordValue::Array1(lengths));
+    record
+}
+
+/// An evaluator reporting statistics of episode returns.
+///
+/// Like [`DefaultEvaluator`](super::DefaultEvaluator), this evaluator runs a specified
+/// number of episodes and returns the average return as the performance metric. In
+/// addition, the returned [`Record`] contains the following values:
+///
+/// * `Episode return` - Mean of the returns
+/// * `Episode return std`, `Episode return min`, `Episode return max` - Standard deviation,
+///   minimum and maximum of the returns
+/// * `Episode return median`, `Episode return IQM` - Median and interquartile mean of
+///   the returns
+/// * `Episode return CI lower`, `Episode return CI upper` - Bounds of the bootstrap
+///   confidence interval of the mean return, if [`StatsEvaluator::confidence_level`] is set
+/// * `Episode length` - Mean of the episode lengths
+/// * `Episode returns`, `Episode lengths` - Returns and lengths of all episodes, as
+///   [`RecordValue::Array1`]
+///
+/// If [`StatsEvaluator::stochastic`] is set, the agent is also evaluated in training mode,
+/// where actions are sampled from the stochastic policy, on the same episodes. The results
+/// are recorded with the keys above followed by ` (stochastic)`.
+///
+/// Any of the scalar values can be used for model selection with
+/// [`TrainerConfig::eval_metric`](crate::TrainerConfig::eval_metric).
+///
+/// # Examples
+///
+/// ```ignore
+/// let evaluator = StatsEvaluator::new(&config, 42, 10)?
+///     .confidence_level(0.95)
+///     .stochastic(true);
+/// ```
+pub struct StatsEvaluator<E: Env> {
+    /// The number of episodes to run during evaluation.
+    n_episodes: usize,
+
+    /// The environment instance used for evaluation.
+    env: E,
+
+    /// Confidence level of the confidence interval of the mean return.
+    confidence_level: Option<f32>,
+
+    /// If `true`, the stochastic policy is also evaluated.
+    stochastic: bool,
+
+    /// Random number generator for bootstrapping.
+    rng: ChaCha12Rng,
+}
+
+impl<E: Env> StatsEvaluator<E> {
+    /// Constructs a new [`StatsEvaluator`].
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration for the environment
+    /// * `seed` - Random seed for environment initialization and bootstrapping
+    /// * `n_episodes` - Number of episodes to run during evaluation, which should be positive
+    pub fn new(config: &E::Config, seed: i64, n_episodes: usize) -> Result<Self> {
+        if n_episodes == 0 {
+            return Err(anyhow!("n_episodes of StatsEvaluator should be positive"));
+        }
+
+        Ok(Self {
+            n_episodes,
+            env: E::build(config, seed)?,
+            confidence_level: None,
+            stochastic: false,
+            rng: ChaCha12Rng::seed_from_u64(seed as u64),
+        })
+    }
+
+    /// Sets the confidence level, e.g., 0.95, of the confidence interval of the mean return.
+    pub fn confidence_level(mut self, v: f32) -> Self {
+        self.confidence_level = Some(v);
+        self
+    }
+
+    /// If `true`, the stochastic policy is evaluated side by side with the deterministic one.
+    pub fn stochastic(mut self, v: bool) -> Self {
+        self.stochastic = v;
+        self
+    }
+
+    /// Runs episodes and returns the returns and the lengths of them.
+    fn run_episodes<R>(&mut self, agent: &mut Box<dyn Agent<E, R>>) -> Result<(Vec<f32>, Vec<f32>)>
+    where
+        R: ReplayBufferBase,
+    {
+        let mut returns = Vec::with_capacity(self.n_episodes);
+        let mut lengths = Vec::with_capacity(self.n_episodes);
+
+        for ix in 0..self.n_episodes {
+            let mut prev_obs = self.env.reset_with_index(ix)?;
+            let (mut ret, mut len) = (0f32, 0usize);
+
+            loop {
+                let act = agent.sample(&prev_obs);
+                let (step, _) = self.env.step(&act);
+                ret += step.reward[0];
+                len += 1;
+                if step.is_done() {
+                    break;
+                }
+                prev_obs = step.obs;
+            }
+
+            returns.push(ret);
+            lengths.push(len as f32);
+        }
+
+        Ok((returns, lengths))
+    }
+
+    /// Returns the bootstrap confidence interval of the mean of values.
+    fn confidence_interval(&mut self, values: &[f32], level: f32) -> (f32, f32) {
+        let n = values.len();
+        let rng = &mut self.rng;
+        let mut means = (0..N_BOOTSTRAP)
+            .map(|_| {
+                let sum = (0..n)
+                    .map(|_| values[(rng.next_u64() as usize) % n])
+                    .sum::<f32>();
+                sum / n as f32
+            })
+            .collect::<Vec<_>>();
+        means.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let alpha = (1.0 - level) / 2.0;
+        (quantile(&means, alpha), quantile(&means, 1.0 - alpha))
+    }
+
+    /// Creates a record of the results of episodes.
+    fn record(&mut self, returns: Vec<f32>, lengths: Vec<f32>, suffix: &str) -> Record {
+        let ci = self
+            .confidence_level
+            .map(|level| self.confidence_interval(&returns, level));
+        let mut record = episode_record(returns, lengths, suffix);
+
+        if let Some((lower, upper)) = ci {
+            let key = |name: &str| format!("{}{}", name, suffix);
+            record.insert(key("Episode return CI lower"), RecordValue::Scalar(lower));
+            record.insert(key("Episode return CI upper"), RecordValue::Scalar(upper));
+        }
+
+        record
+    }
+}
+
+impl<E: Env> Evaluator<E> for StatsEvaluator<E> {
+    /// Evaluates the agent and returns the average return with statistics of episodes.
+    ///
+    /// When the stochastic policy is evaluated, the agent is switched to training mode
+    /// during the evaluation, then the mode is restored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The environment fails to reset
+    /// - The environment fails to step
+    fn evaluate<R>(&mut self, agent: &mut Box<dyn Agent<E, R>>) -> Result<(f32, Record)>
+    where
+        R: ReplayBufferBase,
+    {
+        let (returns, lengths) = self.run_episodes(agent)?;
+        let performance = Stats::new(&returns).mean;
+        let mut record = self.record(returns, lengths, "");
+
+        if self.stochastic {
+            let is_train = agent.is_train();
+            agent.train();
+            let (returns, lengths) = self.run_episodes(agent)?;
+            if !is_train {
+                agent.eval();
+            }
+            record.merge_inplace(self.record(returns, lengths, " (stochastic)"));
+        }
+
+        Ok((performance, record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestEnv;
+
+    #[test]
+    fn test_stats() {
+        let stats = Stats::new(&[4.0, 1.0, 8.0, 2.0, 3.0, 100.0, 5.0, 6.0]);
+        assert_eq!(stats.mean, 16.125);
+        assert_eq!(stats.min, 1.0);
+        assert_eq!(stats.max, 100.0);
+        assert_eq!(stats.median, 4.5);
+        assert_eq!(stats.iqm, 4.5);
+
+        let stats = Stats::new(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(stats.std, 2.0);
+        assert_eq!(quantile(&[1.0, 2.0, 3.0, 4.0, 5.0], 0.1), 1.4);
+    }
+
+    #[test]
+    fn test_zero_episodes() {
+        assert!(StatsEvaluator::<TestEnv>::new(&0, 0, 0).is_err());
+        assert!(StatsEvaluator::<TestEnv>::new(&0, 0, 1).is_ok());
+    }
+}
@@ -7,8 +7,13 @@
 //! # Key Components
 //!
 //! - [`SimpleReplayBuffer`]: A generic replay buffer implementation
+//! - [`EpisodicReplayBuffer`]: A replay buffer sampling sub-sequences of episodes
+//! - [`HerReplayBuffer`]: A replay buffer with Hindsight Experience Replay
+//! - [`FrameStackReplayBuffer`]: A replay buffer storing each frame of stacked observations once
+//! - [`RolloutBuffer`]: A buffer of on-policy rollouts with advantage estimation
 //! - [`GenericTransitionBatch`]: A generic batch structure for transitions
 //! - [`SimpleStepProcessor`]: A processor for converting environment steps to transitions
+//! - [`NStepStepProcessor`]: A processor for converting environment steps to n-step transitions
 //! - [`PerConfig`]: Configuration for prioritized experience replay
 //!
 //! # Features
@@ -17,13 +22,32 @@
 //! - Efficient batch processing
 //! - Prioritized experience replay with importance sampling
 //! - Configurable weight normalization
-//! - Step processing for non-vectorized environments
+//! - Episode statistics and sampling of sub-sequences for recurrent policies
+//! - Goal relabeling for goal-conditioned environments
+//! - Deduplication of stacked frames, e.g., of Atari games
+//! - Generalized advantage estimation for on-policy algorithms
+//! - Step processing for non-vectorized and vectorized environments
+//! - Saving and loading the contents of replay buffers in [`npy`] format
 
 mod base;
 mod batch;
 mod config;
+mod episodic;
+mod frame_stack;
+mod her;
+pub mod npy;
+mod rollout;
 mod step_proc;
 pub use base::{IwScheduler, SimpleReplayBuffer, WeightNormalizer};
-pub use batch::{BatchBase, GenericTransitionBatch};
-pub use config::{PerConfig, SimpleReplayBufferConfig};
-pub use step_proc::{SimpleStepProcessor, SimpleStepProcessorConfig};
+pub use batch::{BatchBase, FrameStackBatch, GenericTransitionBatch, GoalObsBatch};
+pub use config::{
+    EpisodicReplayBufferConfig, FrameOrder, FrameStackReplayBufferConfig, HerReplayBufferConfig,
+    HerRewardFn, HerStrategy, PerConfig, RolloutBufferConfig, SimpleReplayBufferConfig,
+};
+pub use episodic::{EpisodeStats, EpisodicReplayBuffer};
+pub use frame_stack::FrameStackReplayBuffer;
+pub use her::HerReplayBuffer;
+pub use rollout::{RolloutBatch, RolloutBuffer};
+pub use step_proc::{
+    NStepStepProcessor, NStepStepProcessorConfig, SimpleStepProcessor, SimpleStepProcessorConfig,
+};
@@ -8,11 +8,22 @@
 
 mod iw_scheduler;
 mod sum_tree;
-use super::{config::PerConfig, BatchBase, GenericTransitionBatch, SimpleReplayBufferConfig};
+use super::{
+    config::PerConfig,
+    npy::{read_npy, write_npy, NpyElement},
+    BatchBase, GenericTransitionBatch, SimpleReplayBufferConfig,
+};
 use crate::{ExperienceBufferBase, ReplayBufferBase, TransitionBatch};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 pub use iw_scheduler::IwScheduler;
-use rand::{rngs::StdRng, RngCore, SeedableRng};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{create_dir_all, File},
+    io::{BufReader, Write},
+    path::Path,
+};
 use sum_tree::SumTree;
 pub use sum_tree::WeightNormalizer;
 
@@ -48,6 +59,31 @@ impl PerState {
     }
 }
 
+/// Metadata of a replay buffer saved by [`SimpleReplayBuffer`].
+#[derive(Debug, Deserialize, Serialize)]
+struct SimpleReplayBufferMeta {
+    /// Maximum number of transitions that can be stored.
+    capacity: usize,
+
+    /// Insertion index.
+    i: usize,
+
+    /// Number of stored transitions.
+    size: usize,
+
+    /// Seed of the random number generator.
+    rng_seed: [u8; 32],
+
+    /// Position in the stream of the random number generator.
+    rng_word_pos: u64,
+
+    /// Number of samples having priorities in the sum tree, if PER is enabled.
+    per_n_samples: Option<usize>,
+
+    /// Scheduler of importance weights, if PER is enabled.
+    iw_scheduler: Option<IwScheduler>,
+}
+
 /// A generic implementation of a replay buffer for reinforcement learning.
 ///
 /// This buffer can store transitions of arbitrary observation and action types,
@@ -115,8 +151,16 @@ where
     /// Storage for truncation flags.
     is_truncated: Vec<i8>,
 
+    /// Storage for the numbers of steps of n-step transitions.
+    ///
+    /// This is allocated when n-step transitions are pushed for the first time.
+    n_step: Option<Vec<usize>>,
+
     /// Random number generator for sampling.
-    rng: StdRng,
+    ///
+    /// This generates the same stream as [`rand::rngs::StdRng`] for the same seed,
+    /// while its state can be saved and restored.
+    rng: ChaCha12Rng,
 
     /// State for prioritized experience replay, if enabled.
     per_state: Option<PerState>,
@@ -180,6 +224,35 @@ where
         }
     }
 
+    /// Pushes the numbers of steps of n-step transitions at the specified index.
+    ///
+    /// If `b` is `None`, the transitions are regarded as one-step transitions.
+    ///
+    /// # Arguments
+    ///
+    /// * `i` - Starting index for insertion
+    /// * `len` - Number of transitions to insert
+    /// * `b` - Optional vector of the numbers of steps to insert
+    fn push_n_step(&mut self, i: usize, len: usize, b: Option<Vec<usize>>) {
+        if self.n_step.is_none() {
+            if b.is_none() {
+                return;
+            }
+            self.n_step = Some(vec![1; self.capacity]);
+        }
+
+        let n_step = self.n_step.as_mut().unwrap();
+        let b = b.unwrap_or_else(|| vec![1; len]);
+        let mut j = i;
+        for n in b.iter() {
+            n_step[j] = *n;
+            j += 1;
+            if j == self.capacity {
+                j = 0;
+            }
+        }
+    }
+
     /// Samples rewards for the given indices.
     ///
     /// # Arguments
@@ -219,19 +292,114 @@ where
         ixs.iter().map(|ix| self.is_truncated[*ix]).collect()
     }
 
+    /// Samples the numbers of steps of n-step transitions for the given indices.
+    fn sample_n_step(&self, ixs: &[usize]) -> Option<Vec<usize>> {
+        self.n_step
+            .as_ref()
+            .map(|n_step| ixs.iter().map(|ix| n_step[*ix]).collect())
+    }
+
+    /// Returns the next observations in the buffer.
+    pub(super) fn next_obs(&self) -> &O {
+        &self.next_obs
+    }
+
+    /// Returns the rewards of the stored transitions.
+    pub(super) fn rewards(&self) -> &[f32] {
+        &self.reward[..self.size]
+    }
+
+    /// Returns the termination and truncation flags of the stored transitions.
+    pub(super) fn done_flags(&self) -> (&[i8], &[i8]) {
+        (
+            &self.is_terminated[..self.size],
+            &self.is_truncated[..self.size],
+        )
+    }
+
+    /// Removes all transitions from the buffer.
+    ///
+    /// The storage is kept allocated and the priorities of PER, if enabled, are not reset.
+    pub(super) fn clear(&mut self) {
+        self.i = 0;
+        self.size = 0;
+    }
+
+    /// Creates a batch of the transitions at the given indices of the buffer.
+    pub(super) fn sample_transitions(
+        &self,
+        ixs: Vec<usize>,
+        weight: Option<Vec<f32>>,
+    ) -> GenericTransitionBatch<O, A> {
+        GenericTransitionBatch {
+            obs: self.obs.sample(&ixs),
+            act: self.act.sample(&ixs),
+            next_obs: self.next_obs.sample(&ixs),
+            reward: self.sample_reward(&ixs),
+            is_terminated: self.sample_is_terminated(&ixs),
+            is_truncated: self.sample_is_truncated(&ixs),
+            n_step: self.sample_n_step(&ixs),
+            ix_sample: Some(ixs),
+            weight,
+        }
+    }
+
     /// Sets priorities for newly added samples in prioritized experience replay.
     ///
     /// # Arguments
     ///
     /// * `batch_size` - Number of new samples to prioritize
-    fn set_priority(&mut self, batch_size: usize) {
+    /// * `priorities` - Priorities of the samples. If `None`, the maximum priority is used.
+    fn set_priority(&mut self, batch_size: usize, priorities: Option<&[f32]>) {
         let sum_tree = &mut self.per_state.as_mut().unwrap().sum_tree;
         let max_p = sum_tree.max();
 
         for j in 0..batch_size {
             let i = (self.i + j) % self.capacity;
-            sum_tree.add(i, max_p);
+            sum_tree.add(i, priorities.map_or(max_p, |p| p[j]));
+        }
+    }
+
+    /// Adds transitions to the buffer, with their priorities if given.
+    fn push_transitions(
+        &mut self,
+        mut tr: GenericTransitionBatch<O, A>,
+        priorities: Option<&[f32]>,
+    ) -> Result<()> {
+        let len = tr.len(); // batch size
+        if len == 0 {
+            return Ok(());
+        }
+        if let Some(priorities) = priorities {
+            if priorities.len() != len {
+                return Err(anyhow!(
+                    "{} priorities were given for {} transitions",
+                    priorities.len(),
+                    len
+                ));
+            }
+        }
+        let n_step = tr.n_step.take();
+        let (obs, act, next_obs, reward, is_terminated, is_truncated, _, _) = tr.unpack();
+        self.obs.push(self.i, obs);
+        self.act.push(self.i, act);
+        self.next_obs.push(self.i, next_obs);
+        self.push_reward(self.i, &reward);
+        self.push_is_terminated(self.i, &is_terminated);
+        self.push_is_truncated(self.i, &is_truncated);
+        self.push_n_step(self.i, len, n_step);
+
+        if self.per_state.is_some() {
+            self.set_priority(len, priorities)
+        };
+
+        self.i = (self.i + len) % self.capacity;
+        self.size += len;
+        if self.size >= self.capacity {
+            self.size = self.capacity;
         }
+
+        Ok(())
     }
 
     /// Returns a batch containing all actions in the buffer.
@@ -265,6 +433,103 @@ where
     pub fn sum_rewards(&self) -> f32 {
         self.reward.iter().sum()
     }
+
+    /// Builds a replay buffer from transitions saved in the directory `path`.
+    ///
+    /// The directory is expected to have the layout written by [`ReplayBufferBase::save`].
+    /// If `meta.yaml` exists, the buffer is restored as it was saved, including the
+    /// insertion index and the priorities of PER. The capacity in `config` is ignored
+    /// in this case.
+    ///
+    /// Otherwise, the directory is regarded as a dataset, e.g., prepared with NumPy,
+    /// consisting of `obs`, `act`, `next_obs`, `reward.npy`, `is_terminated.npy`,
+    /// `is_truncated.npy` and optionally `n_step.npy`. The capacity of the buffer is extended to the number of
+    /// transitions in the dataset if it is smaller. All of the transitions have the
+    /// same priority when PER is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Directory of the saved transitions
+    /// * `config` - Configuration for the replay buffer
+    pub fn from_dir(path: impl AsRef<Path>, config: &SimpleReplayBufferConfig) -> Result<Self> {
+        let path = path.as_ref();
+        let meta_path = path.join("meta.yaml");
+
+        if meta_path.exists() {
+            let file = File::open(&meta_path)?;
+            let meta: SimpleReplayBufferMeta = serde_yaml::from_reader(BufReader::new(file))?;
+            let config = config.clone().capacity(meta.capacity);
+            let mut buffer = Self::build(&config);
+            buffer.load(path)?;
+            Ok(buffer)
+        } else {
+            let (reward, _) = read_npy::<f32>(path.join("reward.npy"))?;
+            let size = reward.len();
+            let config = config.clone().capacity(config.capacity.max(size));
+            let mut buffer = Self::build(&config);
+            buffer.load_transitions(path, size)?;
+            if buffer.per_state.is_some() {
+                buffer.set_priority(size, None);
+            }
+            buffer.i = size % buffer.capacity;
+            buffer.size = size;
+            Ok(buffer)
+        }
+    }
+
+    /// Loads a vector saved in a `.npy` file into the first elements of `buf`.
+    fn load_vec<T: NpyElement>(buf: &mut [T], path: &Path, len: usize) -> Result<()> {
+        let (data, _) = read_npy::<T>(path)?;
+        if data.len() != len {
+            return Err(anyhow!(
+                "{:?} has {} elements, but {} are expected",
+                path,
+                data.len(),
+                len
+            ));
+        }
+        buf[..len].copy_from_slice(&data);
+        Ok(())
+    }
+
+    /// Loads transitions saved in the directory `path` into the first `size` slots.
+    fn load_transitions(&mut self, path: &Path, size: usize) -> Result<()> {
+        for (name, n) in [
+            ("obs", self.obs.load(&path.join("obs"))?),
+            ("act", self.act.load(&path.join("act"))?),
+            ("next_obs", self.next_obs.load(&path.join("next_obs"))?),
+        ]
+        .iter()
+        {
+            if *n != size {
+                return Err(anyhow!(
+                    "{} elements are loaded from {}, but the size is {}",
+                    n,
+                    name,
+                    size
+                ));
+            }
+        }
+        Self::load_vec(&mut self.reward, &path.join("reward.npy"), size)?;
+        Self::load_vec(
+            &mut self.is_terminated,
+            &path.join("is_terminated.npy"),
+            size,
+        )?;
+        Self::load_vec(&mut self.is_truncated, &path.join("is_truncated.npy"), size)?;
+
+        let n_step_path = path.join("n_step.npy");
+        self.n_step = match n_step_path.exists() {
+            true => {
+                let mut n_step = vec![1i64; self.capacity];
+                Self::load_vec(&mut n_step, &n_step_path, size)?;
+                Some(n_step.iter().map(|&n| n as usize).collect())
+            }
+            false => None,
+        };
+
+        Ok(())
+    }
 }
 
 impl<O, A> ExperienceBufferBase for SimpleReplayBuffer<O, A>
@@ -293,26 +558,19 @@ where
     ///
     /// Returns an error if the buffer is full and cannot accept more transitions
     fn push(&mut self, tr: Self::Item) -> Result<()> {
-        let len = tr.len(); // batch size
-        let (obs, act, next_obs, reward, is_terminated, is_truncated, _, _) = tr.unpack();
-        self.obs.push(self.i, obs);
-        self.act.push(self.i, act);
-        self.next_obs.push(self.i, next_obs);
-        self.push_reward(self.i, &reward);
-        self.push_is_terminated(self.i, &is_terminated);
-        self.push_is_truncated(self.i, &is_truncated);
-
-        if self.per_state.is_some() {
-            self.set_priority(len)
-        };
-
-        self.i = (self.i + len) % self.capacity;
-        self.size += len;
-        if self.size >= self.capacity {
-            self.size = self.capacity;
-        }
+        self.push_transitions(tr, None)
+    }
 
-        Ok(())
+    /// Adds a new transition to the buffer with its priorities.
+    ///
+    /// If prioritized experience replay is enabled, the priorities are set in the sum tree
+    /// instead of the maximum priority. Otherwise, they are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the number of priorities differs from that of the transitions
+    fn push_with_priority(&mut self, tr: Self::Item, priorities: Vec<f32>) -> Result<()> {
+        self.push_transitions(tr, Some(&priorities))
     }
 }
 
@@ -350,7 +608,8 @@ where
             reward: vec![0.; capacity],
             is_terminated: vec![0; capacity],
             is_truncated: vec![0; capacity],
-            rng: StdRng::seed_from_u64(config.seed as _),
+            n_step: None,
+            rng: ChaCha12Rng::seed_from_u64(config.seed as _),
             per_state,
         }
     }
@@ -389,16 +648,7 @@ where
             (ixs, weight)
         };
 
-        Ok(Self::Batch {
-            obs: self.obs.sample(&ixs),
-            act: self.act.sample(&ixs),
-            next_obs: self.next_obs.sample(&ixs),
-            reward: self.sample_reward(&ixs),
-            is_terminated: self.sample_is_terminated(&ixs),
-            is_truncated: self.sample_is_truncated(&ixs),
-            ix_sample: Some(ixs),
-            weight,
-        })
+        Ok(self.sample_transitions(ixs, weight))
     }
 
     /// Updates the priorities of transitions in the buffer.
@@ -424,4 +674,215 @@ where
             per_state.iw_scheduler.add_n_opts();
         }
     }
+
+    /// Saves the contents of the replay buffer into the directory `path`.
+    ///
+    /// The directory will contain the following files:
+    ///
+    /// * `meta.yaml` - Capacity, insertion index, size, RNG state and PER scheduler
+    /// * `obs`, `act`, `next_obs` - Directories given to [`BatchBase::save`]
+    /// * `reward.npy`, `is_terminated.npy`, `is_truncated.npy` - Rewards and flags
+    /// * `n_step.npy` - Numbers of steps, if n-step transitions have been pushed
+    /// * `sum_tree.npy` - Nodes of the sum tree, if PER is enabled
+    ///
+    /// Only the first [`len`](ExperienceBufferBase::len) transitions are saved.
+    fn save(&self, path: &Path) -> Result<()> {
+        create_dir_all(path)?;
+        for name in ["obs", "act", "next_obs"].iter() {
+            create_dir_all(path.join(name))?;
+        }
+        self.obs.save(&path.join("obs"), self.size)?;
+        self.act.save(&path.join("act"), self.size)?;
+        self.next_obs.save(&path.join("next_obs"), self.size)?;
+        write_npy(
+            path.join("reward.npy"),
+            &[self.size],
+            &self.reward[..self.size],
+        )?;
+        write_npy(
+            path.join("is_terminated.npy"),
+            &[self.size],
+            &self.is_terminated[..self.size],
+        )?;
+        write_npy(
+            path.join("is_truncated.npy"),
+            &[self.size],
+            &self.is_truncated[..self.size],
+        )?;
+        if let Some(n_step) = &self.n_step {
+            let n_step = n_step[..self.size]
+                .iter()
+                .map(|&n| n as i64)
+                .collect::<Vec<_>>();
+            write_npy(path.join("n_step.npy"), &[self.size], &n_step)?;
+        }
+
+        if let Some(per_state) = &self.per_state {
+            let nodes = per_state.sum_tree.nodes();
+            write_npy(path.join("sum_tree.npy"), &[nodes.len()], nodes)?;
+        }
+
+        let meta = SimpleReplayBufferMeta {
+            capacity: self.capacity,
+            i: self.i,
+            size: self.size,
+            rng_seed: self.rng.get_seed(),
+            rng_word_pos: self.rng.get_word_pos() as u64,
+            per_n_samples: self.per_state.as_ref().map(|s| s.sum_tree.n_samples()),
+            iw_scheduler: self.per_state.as_ref().map(|s| s.iw_scheduler.clone()),
+        };
+        let mut file = File::create(path.join("meta.yaml"))?;
+        file.write_all(serde_yaml::to_string(&meta)?.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Loads the contents of the replay buffer saved by [`ReplayBufferBase::save`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the capacity or the use of PER of this buffer is different
+    /// from that of the saved one.
+    fn load(&mut self, path: &Path) -> Result<()> {
+        let file = File::open(path.join("meta.yaml"))?;
+        let meta: SimpleReplayBufferMeta = serde_yaml::from_reader(BufReader::new(file))?;
+        if meta.capacity != self.capacity {
+            return Err(anyhow!(
+                "Capacity of the saved replay buffer ({}) is different from {}",
+                meta.capacity,
+                self.capacity
+            ));
+        }
+        if meta.per_n_samples.is_some() != self.per_state.is_some() {
+            return Err(anyhow!(
+                "PER configuration of the saved replay buffer does not match"
+            ));
+        }
+
+        self.load_transitions(path, meta.size)?;
+
+        if let Some(per_state) = &mut self.per_state {
+            let (nodes, _) = read_npy::<f32>(path.join("sum_tree.npy"))?;
+            per_state
+                .sum_tree
+                .restore(nodes, meta.per_n_samples.unwrap())?;
+            per_state.iw_scheduler = meta.iw_scheduler.unwrap();
+        }
+
+        self.i = meta.i;
+        self.size = meta.size;
+        self.rng = ChaCha12Rng::from_seed(meta.rng_seed);
+        self.rng.set_word_pos(meta.rng_word_pos as u128);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{TestActBatch, TestObsBatch};
+    use tempdir::TempDir;
+
+    type ReplayBuffer = SimpleReplayBuffer<TestObsBatch, TestActBatch>;
+
+    #[test]
+    fn test_from_dir() -> Result<()> {
+        // Dataset without metadata, e.g., prepared with NumPy
+        let dir = TempDir::new("replay_buffer")?;
+        let path = dir.path();
+        for name in ["obs", "act", "next_obs"].iter() {
+            create_dir_all(path.join(name))?;
+        }
+        let data = (0..6).collect::<Vec<i64>>();
+        write_npy(path.join("obs").join("obs.npy"), &[6], &data)?;
+        write_npy(path.join("act").join("act.npy"), &[6], &data)?;
+        write_npy(path.join("next_obs").join("obs.npy"), &[6], &data)?;
+        write_npy(path.join("reward.npy"), &[6], &[1f32; 6])?;
+        write_npy(path.join("is_terminated.npy"), &[6], &[0i8, 0, 1, 0, 0, 1])?;
+        write_npy(path.join("is_truncated.npy"), &[6], &[0i8; 6])?;
+
+        let config = SimpleReplayBufferConfig::default().capacity(4);
+        let mut buffer = ReplayBuffer::from_dir(path, &config)?;
+        assert_eq!(buffer.len(), 6);
+        assert_eq!(buffer.capacity, 6);
+        assert_eq!(buffer.num_terminated_flags(), 2);
+        assert_eq!(buffer.sum_rewards(), 6.0);
+        assert_eq!(buffer.batch(3)?.len(), 3);
+
+        // Saved replay buffer
+        let dir2 = TempDir::new("replay_buffer")?;
+        buffer.save(dir2.path())?;
+        let buffer2 = ReplayBuffer::from_dir(dir2.path(), &config)?;
+        assert_eq!(buffer2.len(), 6);
+        assert_eq!(buffer2.i, buffer.i);
+        assert_eq!(buffer2.rng.get_word_pos(), buffer.rng.get_word_pos());
+
+        Ok(())
+    }
+
+    /// Batch without support of training checkpoints.
+    struct UnsavableBatch(Vec<usize>);
+
+    impl BatchBase for UnsavableBatch {
+        fn new(capacity: usize) -> Self {
+            Self(vec![0; capacity])
+        }
+
+        fn push(&mut self, ix: usize, data: Self) {
+            self.0[ix] = data.0[0];
+        }
+
+        fn sample(&self, ixs: &Vec<usize>) -> Self {
+            Self(ixs.iter().map(|&ix| self.0[ix]).collect())
+        }
+    }
+
+    #[test]
+    fn test_save_unsupported() -> Result<()> {
+        let config = SimpleReplayBufferConfig::default().capacity(4);
+        let buffer = SimpleReplayBuffer::<UnsavableBatch, TestActBatch>::build(&config);
+        let dir = TempDir::new("replay_buffer")?;
+        let err = buffer.save(dir.path()).unwrap_err();
+        assert!(err.to_string().contains("does not support checkpointing"));
+        Ok(())
+    }
+
+    fn transition(i: usize) -> GenericTransitionBatch<TestObsBatch, TestActBatch> {
+        GenericTransitionBatch {
+            obs: TestObsBatch { obs: vec![i] },
+            act: TestActBatch { act: vec![i] },
+            next_obs: TestObsBatch { obs: vec![i + 1] },
+            reward: vec![0.0],
+            is_terminated: vec![0],
+            is_truncated: vec![0],
+            n_step: None,
+            weight: None,
+            ix_sample: None,
+        }
+    }
+
+    #[test]
+    fn test_push_with_priority() -> Result<()> {
+        let config = SimpleReplayBufferConfig::default()
+            .capacity(4)
+            .per_config(Some(PerConfig::default().alpha(1.0)));
+        let mut buffer = ReplayBuffer::build(&config);
+        buffer.push_with_priority(transition(0), vec![0.0])?;
+        buffer.push_with_priority(transition(1), vec![1.0])?;
+        buffer.push_with_priority(transition(2), vec![0.0])?;
+        assert!(buffer.push_with_priority(transition(3), vec![]).is_err());
+
+        // Only the transition with a non-zero priority is sampled
+        let batch = buffer.batch(10)?;
+        assert_eq!(batch.obs.obs, vec![1; 10]);
+
+        // Transitions pushed without priorities have the maximum priority
+        buffer.push(transition(3))?;
+        let batch = buffer.batch(100)?;
+        assert!(batch.obs.obs.contains(&3));
+        assert!(!batch.obs.obs.contains(&0));
+
+        Ok(())
+    }
 }
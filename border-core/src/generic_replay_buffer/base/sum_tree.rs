@@ -2,6 +2,7 @@
 //!
 //! Code is adapted from <https://github.com/jaromiru/AI-blog/blob/master/SumTree.py> and
 /// <https://github.com/openai/baselines/blob/master/baselines/deepq/replay_buffer.py>
+use anyhow::{anyhow, Result};
 use segment_tree::{
     ops::{MaxIgnoreNaN, MinIgnoreNaN},
     SegmentPoint,
@@ -60,14 +61,14 @@ impl SumTree {
         }
 
         if s <= self.tree[left] || self.tree[right] == 0f32 {
-            return self.retrieve(left, s);
+            self.retrieve(left, s)
         } else {
-            return self.retrieve(right, s - self.tree[left]);
+            self.retrieve(right, s - self.tree[left])
         }
     }
 
     pub fn total(&self) -> f32 {
-        return self.tree[0];
+        self.tree[0]
     }
 
     pub fn max(&self) -> f32 {
@@ -137,7 +138,7 @@ impl SumTree {
         // normalizer within all samples
         let w_max_inv = match self.normalize {
             WeightNormalizer::All => (n * self.min_tree.query(0, self.n_samples)).powf(beta),
-            WeightNormalizer::Batch => 1f32 / ws.iter().fold(0.0 / 0.0, |m, v| v.max(m)),
+            WeightNormalizer::Batch => 1f32 / ws.iter().fold(f32::NAN, |m, v| v.max(m)),
         };
         let ws = ws.iter().map(|w| w * w_max_inv).collect::<Vec<f32>>();
 
@@ -156,6 +157,50 @@ impl SumTree {
         (ixs, ws)
     }
 
+    /// Returns the number of samples having priorities.
+    pub fn n_samples(&self) -> usize {
+        self.n_samples
+    }
+
+    /// Returns the nodes of the sum tree.
+    ///
+    /// The last `capacity` elements are the priority values of samples,
+    /// to which the alpha-th power has already been applied.
+    pub fn nodes(&self) -> &Vec<f32> {
+        &self.tree
+    }
+
+    /// Restores the state of the sum tree from the nodes and the number of samples.
+    ///
+    /// `nodes` should be the one returned by [`SumTree::nodes`] of a sum tree with the same capacity.
+    pub fn restore(&mut self, nodes: Vec<f32>, n_samples: usize) -> Result<()> {
+        if nodes.len() != self.tree.len() || n_samples > self.capacity {
+            return Err(anyhow!(
+                "Sum tree with {} nodes and {} samples does not fit capacity {}",
+                nodes.len(),
+                n_samples,
+                self.capacity
+            ));
+        }
+
+        // Leaves that have never been set are zero, as priority values are always positive
+        let leaves = &nodes[self.capacity - 1..];
+        let min_leaves = leaves
+            .iter()
+            .map(|&p| if p > 0f32 { p } else { f32::MAX })
+            .collect();
+        let max_leaves = leaves
+            .iter()
+            .map(|&p| if p > 0f32 { p } else { 1e-8f32 })
+            .collect();
+        self.min_tree = SegmentPoint::build(min_leaves, MinIgnoreNaN);
+        self.max_tree = SegmentPoint::build(max_leaves, MaxIgnoreNaN);
+        self.tree = nodes;
+        self.n_samples = n_samples;
+
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn print_tree(&self) {
         let mut nl = 1;
@@ -179,10 +224,10 @@ mod tests {
 
     #[test]
     fn test_sum_tree_odd() {
-        let data = vec![0.5f32, 0.2, 0.8, 0.3, 1.1, 2.5, 3.9];
+        let data = [0.5f32, 0.2, 0.8, 0.3, 1.1, 2.5, 3.9];
         let mut sum_tree = SumTree::new(8, 1.0, Batch);
-        for ix in 0..data.len() {
-            sum_tree.add(ix, data[ix]);
+        for (ix, &p) in data.iter().enumerate() {
+            sum_tree.add(ix, p);
         }
         sum_tree.print_tree();
         println!();
@@ -200,6 +245,14 @@ mod tests {
         sum_tree.print_tree();
         println!();
 
+        let mut sum_tree_ = SumTree::new(8, 1.0, Batch);
+        sum_tree_
+            .restore(sum_tree.nodes().clone(), sum_tree.n_samples())
+            .unwrap();
+        assert_eq!(sum_tree_.total(), sum_tree.total());
+        assert_eq!(sum_tree_.max(), sum_tree.max());
+        assert_eq!(sum_tree_.get(2.8), 4);
+
         // let (ixs, ws) = sum_tree.sample(10, 1.0);
         // println!("{:?}", ixs);
         // println!("{:?}", ws);
@@ -7,6 +7,9 @@
 //! - Transition sampling and management
 
 use crate::TransitionBatch;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{ops::Range, path::Path};
 
 /// A trait defining basic batch operations.
 ///
@@ -68,6 +71,77 @@ pub trait BatchBase {
     ///
     /// A new batch containing the sampled data
     fn sample(&self, ixs: &Vec<usize>) -> Self;
+
+    /// Saves the first `len` elements of the batch into the directory `path`.
+    ///
+    /// This method is used to save the contents of a replay buffer, e.g., in training
+    /// checkpoints. The directory is created by the caller. The format of the files
+    /// is up to the implementation, but [`npy`](super::npy) is recommended.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Directory where the data will be saved
+    /// * `len` - Number of elements to be saved
+    ///
+    /// The default implementation returns an error, as the batch does not support
+    /// training checkpoints.
+    #[allow(unused_variables)]
+    fn save(&self, path: &Path, len: usize) -> Result<()> {
+        Err(anyhow!(
+            "{} does not support checkpointing",
+            std::any::type_name::<Self>()
+        ))
+    }
+
+    /// Loads the elements saved by [`BatchBase::save`] from the directory `path`.
+    ///
+    /// The loaded elements are stored from index 0. The batch must have been created
+    /// with a capacity not smaller than the number of saved elements.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Directory where the data was saved
+    ///
+    /// # Returns
+    ///
+    /// The number of loaded elements
+    ///
+    /// The default implementation returns an error, as the batch does not support
+    /// training checkpoints.
+    #[allow(unused_variables)]
+    fn load(&mut self, path: &Path) -> Result<usize> {
+        Err(anyhow!(
+            "{} does not support checkpointing",
+            std::any::type_name::<Self>()
+        ))
+    }
+}
+
+/// A batch of goal-conditioned observations.
+///
+/// This trait is used by [`HerReplayBuffer`](super::HerReplayBuffer) to relabel goals.
+/// Observations are assumed to be flat vectors, e.g., concatenated elements of dict
+/// observations of goal-conditioned environments, and goals are specified with ranges
+/// of elements in the vectors.
+pub trait GoalObsBatch: BatchBase {
+    /// Returns the elements in `range` of each observation in the batch.
+    fn goals(&self, range: Range<usize>) -> Vec<Vec<f32>>;
+
+    /// Overwrites the elements in `range` of each observation in the batch with `goals`.
+    fn set_goals(&mut self, range: Range<usize>, goals: &[Vec<f32>]);
+}
+
+/// A batch of observations consisting of stacked frames of `u8` pixels.
+///
+/// This trait is used by [`FrameStackReplayBuffer`](super::FrameStackReplayBuffer) to store
+/// each frame once. The first axis of the batch is the batch axis and the second one is the
+/// axis of stacked frames, e.g., `[batch_size, 4, 1, 84, 84]` for Atari games.
+pub trait FrameStackBatch: BatchBase {
+    /// Returns the elements of the batch in row-major order and its shape.
+    fn to_frames(&self) -> (Vec<u8>, Vec<usize>);
+
+    /// Creates a batch from elements in row-major order and its shape.
+    fn from_frames(data: Vec<u8>, shape: &[usize]) -> Self;
 }
 
 /// A generic structure representing transitions in reinforcement learning.
@@ -86,6 +160,7 @@ pub trait BatchBase {
 /// ```ignore
 /// let batch = GenericTransitionBatch::<Tensor, Tensor>::with_capacity(32);
 /// ```
+#[derive(Deserialize, Serialize)]
 pub struct GenericTransitionBatch<O, A>
 where
     O: BatchBase,
@@ -109,6 +184,9 @@ where
     /// Episode truncation flags
     pub is_truncated: Vec<i8>,
 
+    /// Number of steps to the next observations, for n-step transitions
+    pub n_step: Option<Vec<usize>>,
+
     /// Weights for prioritized experience replay
     pub weight: Option<Vec<f32>>,
 
@@ -175,6 +253,11 @@ where
     fn act(&self) -> &Self::ActBatch {
         &self.act
     }
+
+    /// Returns the number of steps to the next observations, if given.
+    fn n_step(&self) -> Option<&[usize]> {
+        self.n_step.as_deref()
+    }
 }
 
 impl<O, A> GenericTransitionBatch<O, A>
@@ -199,6 +282,7 @@ where
             reward: Vec::with_capacity(capacity),
             is_terminated: Vec::with_capacity(capacity),
             is_truncated: Vec::with_capacity(capacity),
+            n_step: None,
             weight: None,
             ix_sample: None,
         }
@@ -3,6 +3,9 @@
 //! This module provides configuration structures for the replay buffer, including:
 //! - Basic buffer configuration (capacity, seed)
 //! - Prioritized Experience Replay (PER) configuration
+//! - Episodic and Hindsight Experience Replay (HER) buffer configuration
+//! - Frame stack buffer configuration for stacked image observations
+//! - Rollout buffer configuration for on-policy algorithms
 //! - Serialization and deserialization support
 
 use super::{WeightNormalizer, WeightNormalizer::All};
@@ -12,6 +15,7 @@ use std::{
     default::Default,
     fs::File,
     io::{BufReader, Write},
+    ops::Range,
     path::Path,
 };
 
@@ -292,3 +296,365 @@ impl SimpleReplayBufferConfig {
         Ok(())
     }
 }
+
+/// Configuration of [`EpisodicReplayBuffer`](super::EpisodicReplayBuffer).
+///
+/// # Fields
+///
+/// * `capacity` - Maximum number of transitions to store
+/// * `seed` - Random seed for sampling
+/// * `seq_len` - Length of sub-sequences sampled from episodes
+///
+/// # Examples
+///
+/// ```rust
+/// use border_core::generic_replay_buffer::EpisodicReplayBufferConfig;
+///
+/// let config = EpisodicReplayBufferConfig::default()
+///     .capacity(100000)
+///     .seq_len(8);
+/// ```
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct EpisodicReplayBufferConfig {
+    /// Maximum number of transitions that can be stored in the buffer.
+    /// Episodes whose transitions have been overwritten are removed from the buffer.
+    pub capacity: usize,
+
+    /// Random seed used for sampling sub-sequences.
+    pub seed: u64,
+
+    /// Number of consecutive transitions in a sampled sub-sequence.
+    /// With `seq_len = 1`, the buffer samples transitions uniformly at random.
+    pub seq_len: usize,
+}
+
+impl Default for EpisodicReplayBufferConfig {
+    /// Creates a default configuration with `capacity = 10000`, `seed = 42` and
+    /// `seq_len = 1`.
+    fn default() -> Self {
+        Self {
+            capacity: 10000,
+            seed: 42,
+            seq_len: 1,
+        }
+    }
+}
+
+impl EpisodicReplayBufferConfig {
+    /// Sets the capacity of the replay buffer.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets the random seed for sampling.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the length of sampled sub-sequences.
+    pub fn seq_len(mut self, seq_len: usize) -> Self {
+        self.seq_len = seq_len;
+        self
+    }
+}
+
+/// Strategy for selecting goals used to relabel transitions in
+/// [`HerReplayBuffer`](super::HerReplayBuffer).
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+pub enum HerStrategy {
+    /// Goals achieved at the same or later steps in the episode.
+    Future,
+
+    /// The goal achieved at the last step of the episode.
+    Final,
+
+    /// Goals achieved at any step in the episode.
+    Episode,
+}
+
+/// Reward function used to recompute rewards of relabeled transitions.
+///
+/// The arguments are the achieved goal and the desired goal. Non-capturing closures
+/// can be used as reward functions.
+pub type HerRewardFn = fn(&[f32], &[f32]) -> f32;
+
+/// Configuration of [`HerReplayBuffer`](super::HerReplayBuffer).
+///
+/// # Fields
+///
+/// * `capacity` - Maximum number of transitions to store
+/// * `seed` - Random seed for sampling
+/// * `strategy` - Strategy for selecting goals
+/// * `k` - Ratio of relabeled transitions to original ones in a batch
+/// * `achieved_goal` - Range of the achieved goal in a flat observation
+/// * `desired_goal` - Range of the desired goal in a flat observation
+/// * `reward_fn` - Reward function, which is given to [`HerReplayBufferConfig::new`]
+///
+/// As the reward function cannot be deserialized, this configuration only supports
+/// serialization, where the reward function is skipped.
+///
+/// # Examples
+///
+/// ```rust
+/// use border_core::generic_replay_buffer::{HerReplayBufferConfig, HerStrategy};
+///
+/// // Observations are concatenations of `observation` (10 elements),
+/// // `desired_goal` (3 elements) and `achieved_goal` (3 elements).
+/// let config = HerReplayBufferConfig::new(|achieved, desired| {
+///     let d2: f32 = achieved.iter().zip(desired).map(|(a, d)| (a - d).powi(2)).sum();
+///     -((d2.sqrt() > 0.05) as i32 as f32)
+/// })
+/// .capacity(100000)
+/// .strategy(HerStrategy::Future)
+/// .k(4)
+/// .desired_goal(10..13)
+/// .achieved_goal(13..16);
+/// ```
+#[derive(Debug, Serialize, Clone)]
+pub struct HerReplayBufferConfig {
+    /// Maximum number of transitions that can be stored in the buffer.
+    pub capacity: usize,
+
+    /// Random seed used for sampling transitions and goals.
+    pub seed: u64,
+
+    /// Strategy for selecting goals.
+    pub strategy: HerStrategy,
+
+    /// Ratio of relabeled transitions to original ones. A transition is relabeled
+    /// with probability `k / (k + 1)`.
+    pub k: usize,
+
+    /// Range of the achieved goal in a flat observation.
+    pub achieved_goal: Range<usize>,
+
+    /// Range of the desired goal in a flat observation.
+    pub desired_goal: Range<usize>,
+
+    /// Reward function used for relabeled transitions, which is not serialized.
+    #[serde(skip_serializing)]
+    pub reward_fn: HerRewardFn,
+}
+
+impl HerReplayBufferConfig {
+    /// Creates a configuration with the given reward function.
+    ///
+    /// The other parameters are `capacity = 10000`, `seed = 42`, `strategy = Future`
+    /// and `k = 4`. Goal ranges have to be set.
+    pub fn new(reward_fn: HerRewardFn) -> Self {
+        Self {
+            capacity: 10000,
+            seed: 42,
+            strategy: HerStrategy::Future,
+            k: 4,
+            achieved_goal: 0..0,
+            desired_goal: 0..0,
+            reward_fn,
+        }
+    }
+
+    /// Sets the capacity of the replay buffer.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets the random seed for sampling.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the strategy for selecting goals.
+    pub fn strategy(mut self, strategy: HerStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets the ratio of relabeled transitions to original ones.
+    pub fn k(mut self, k: usize) -> Self {
+        self.k = k;
+        self
+    }
+
+    /// Sets the range of the achieved goal in a flat observation.
+    pub fn achieved_goal(mut self, range: Range<usize>) -> Self {
+        self.achieved_goal = range;
+        self
+    }
+
+    /// Sets the range of the desired goal in a flat observation.
+    pub fn desired_goal(mut self, range: Range<usize>) -> Self {
+        self.desired_goal = range;
+        self
+    }
+
+    /// Sets the reward function used for relabeled transitions.
+    pub fn reward_fn(mut self, reward_fn: HerRewardFn) -> Self {
+        self.reward_fn = reward_fn;
+        self
+    }
+}
+
+/// Order of stacked frames in observations stored in
+/// [`FrameStackReplayBuffer`](super::FrameStackReplayBuffer).
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+pub enum FrameOrder {
+    /// The first frame is the latest one, as in observations of `BorderAtariEnv`.
+    NewestFirst,
+
+    /// The last frame is the latest one, as in observations of
+    /// [`FrameStack`](crate::wrappers::FrameStack) wrapping an environment.
+    OldestFirst,
+}
+
+/// Configuration of [`FrameStackReplayBuffer`](super::FrameStackReplayBuffer).
+///
+/// # Fields
+///
+/// * `capacity` - Maximum number of transitions to store
+/// * `frame_capacity` - Maximum number of frames to store
+/// * `seed` - Random seed for sampling
+/// * `frame_order` - Order of stacked frames in observations
+///
+/// # Examples
+///
+/// ```rust
+/// use border_core::generic_replay_buffer::FrameStackReplayBufferConfig;
+///
+/// let config = FrameStackReplayBufferConfig::default()
+///     .capacity(1000000)
+///     .frame_capacity(1100000);
+/// ```
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct FrameStackReplayBufferConfig {
+    /// Maximum number of transitions that can be stored in the buffer.
+    pub capacity: usize,
+
+    /// Maximum number of frames that can be stored in the buffer.
+    ///
+    /// Each transition adds a frame, and the first transition of an episode adds the frames of
+    /// the initial observation. If `None`, it is `capacity + capacity / 4`. When the frames of
+    /// the oldest transitions are overwritten, these transitions are removed from the buffer.
+    pub frame_capacity: Option<usize>,
+
+    /// Random seed used for sampling transitions.
+    pub seed: u64,
+
+    /// Order of stacked frames in observations.
+    pub frame_order: FrameOrder,
+}
+
+impl Default for FrameStackReplayBufferConfig {
+    /// Creates a default configuration with `capacity = 10000`, `frame_capacity = None`,
+    /// `seed = 42` and `frame_order = NewestFirst`.
+    fn default() -> Self {
+        Self {
+            capacity: 10000,
+            frame_capacity: None,
+            seed: 42,
+            frame_order: FrameOrder::NewestFirst,
+        }
+    }
+}
+
+impl FrameStackReplayBufferConfig {
+    /// Sets the capacity of the replay buffer.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets the maximum number of frames stored in the buffer.
+    pub fn frame_capacity(mut self, frame_capacity: usize) -> Self {
+        self.frame_capacity = Some(frame_capacity);
+        self
+    }
+
+    /// Sets the random seed for sampling.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the order of stacked frames in observations.
+    pub fn frame_order(mut self, frame_order: FrameOrder) -> Self {
+        self.frame_order = frame_order;
+        self
+    }
+}
+
+/// Configuration of [`RolloutBuffer`](super::RolloutBuffer).
+///
+/// # Fields
+///
+/// * `capacity` - Number of transitions collected before each update
+/// * `gamma` - Discount factor
+/// * `gae_lambda` - Parameter of generalized advantage estimation (GAE)
+/// * `seed` - Random seed for shuffling minibatches
+///
+/// # Examples
+///
+/// ```rust
+/// use border_core::generic_replay_buffer::RolloutBufferConfig;
+///
+/// let config = RolloutBufferConfig::default()
+///     .capacity(2048)
+///     .gae_lambda(0.95);
+/// ```
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct RolloutBufferConfig {
+    /// Number of transitions collected before each update, summed over sub-environments.
+    /// It should be a multiple of the number of sub-environments.
+    pub capacity: usize,
+
+    /// Discount factor.
+    pub gamma: f32,
+
+    /// Parameter of generalized advantage estimation, trading off bias and variance.
+    pub gae_lambda: f32,
+
+    /// Random seed for shuffling minibatches.
+    pub seed: u64,
+}
+
+impl Default for RolloutBufferConfig {
+    /// Creates a default configuration with `capacity = 2048`, `gamma = 0.99`,
+    /// `gae_lambda = 0.95` and `seed = 42`.
+    fn default() -> Self {
+        Self {
+            capacity: 2048,
+            gamma: 0.99,
+            gae_lambda: 0.95,
+            seed: 42,
+        }
+    }
+}
+
+impl RolloutBufferConfig {
+    /// Sets the number of transitions collected before each update.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets the discount factor.
+    pub fn gamma(mut self, gamma: f32) -> Self {
+        self.gamma = gamma;
+        self
+    }
+
+    /// Sets the parameter of generalized advantage estimation.
+    pub fn gae_lambda(mut self, gae_lambda: f32) -> Self {
+        self.gae_lambda = gae_lambda;
+        self
+    }
+
+    /// Sets the random seed for shuffling minibatches.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
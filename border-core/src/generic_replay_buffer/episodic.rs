@@ -0,0 +1,497 @@
+//! Episode-aware replay buffer.
+//!
+//! [`EpisodicReplayBuffer`] stores transitions in the same way as [`SimpleReplayBuffer`],
+//! but also keeps track of the episodes the transitions belong to. It supports:
+//! - Sampling contiguous sub-sequences of episodes, e.g., for recurrent policies
+//! - Per-episode statistics (return and length)
+//! - Vectorized environments, whose episodes are interleaved in the buffer
+use super::{
+    npy::{read_npy, write_npy},
+    BatchBase, EpisodicReplayBufferConfig, GenericTransitionBatch, SimpleReplayBuffer,
+    SimpleReplayBufferConfig,
+};
+use crate::{ExperienceBufferBase, ReplayBufferBase, TransitionBatch};
+use anyhow::{anyhow, Result};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
+
+/// Statistics of an episode stored in [`EpisodicReplayBuffer`] or finished in
+/// [`Sampler`](crate::Sampler).
+#[derive(Clone, Debug, PartialEq)]
+pub struct EpisodeStats {
+    /// Sum of the rewards in the episode.
+    pub ret: f32,
+
+    /// Number of transitions in the episode.
+    pub len: usize,
+}
+
+/// An episode in the buffer.
+#[derive(Clone, Debug, Default)]
+struct Episode {
+    /// Absolute indices of the stored transitions, i.e., the numbers of transitions
+    /// pushed into the buffer before them.
+    ixs: VecDeque<usize>,
+
+    /// Sum of the rewards.
+    ret: f32,
+
+    /// Number of transitions, including those which have been overwritten.
+    len: usize,
+}
+
+/// Metadata of an episode saved by [`EpisodicReplayBuffer`].
+#[derive(Debug, Deserialize, Serialize)]
+struct EpisodeMeta {
+    ret: f32,
+    len: usize,
+    n_stored: usize,
+}
+
+/// Metadata saved by [`EpisodicReplayBuffer`] in addition to that of [`SimpleReplayBuffer`].
+#[derive(Debug, Deserialize, Serialize)]
+struct EpisodicReplayBufferMeta {
+    n_pushed: usize,
+    rng_seed: [u8; 32],
+    rng_word_pos: u64,
+    episodes: Vec<EpisodeMeta>,
+    open_episodes: Vec<EpisodeMeta>,
+}
+
+/// A replay buffer that indexes the episodes of the stored transitions.
+///
+/// Each batch pushed into the buffer must have one transition for each sub-environment,
+/// in the same order, as created by
+/// [`SimpleStepProcessor`](super::SimpleStepProcessor). An episode ends at a transition
+/// with the terminated or truncated flag.
+///
+/// [`ReplayBufferBase::batch`] samples `size` sub-sequences of `seq_len` consecutive
+/// transitions, uniformly over all the sub-sequences in the buffer, and returns them
+/// as a [`GenericTransitionBatch`] of `size * seq_len` transitions, where the
+/// transitions of a sub-sequence are contiguous. Sub-sequences are sampled from both
+/// completed and ongoing episodes. With `seq_len = 1`, the buffer works as a uniform
+/// replay buffer and can be used with agents in place of [`SimpleReplayBuffer`].
+///
+/// When the buffer is full, new transitions overwrite the oldest ones and completed
+/// episodes that lose transitions are removed from the buffer.
+///
+/// # Examples
+///
+/// ```ignore
+/// let config = EpisodicReplayBufferConfig::default()
+///     .capacity(100000)
+///     .seq_len(8);
+/// let mut buffer = EpisodicReplayBuffer::<ObsBatch, ActBatch>::build(&config);
+///
+/// // Push transitions, e.g., with `Trainer`
+///
+/// let batch = buffer.batch(32)?; // 32 sub-sequences of 8 transitions
+/// let returns = buffer
+///     .episode_stats()
+///     .iter()
+///     .map(|s| s.ret)
+///     .collect::<Vec<_>>();
+/// ```
+pub struct EpisodicReplayBuffer<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    /// Storage of the transitions.
+    buffer: SimpleReplayBuffer<O, A>,
+
+    /// Maximum number of transitions.
+    capacity: usize,
+
+    /// Length of sampled sub-sequences.
+    seq_len: usize,
+
+    /// Total number of transitions pushed into the buffer.
+    n_pushed: usize,
+
+    /// Completed episodes, in the order of completion.
+    episodes: VecDeque<Episode>,
+
+    /// Ongoing episodes, one for each sub-environment.
+    open_episodes: Vec<Episode>,
+
+    /// Random number generator for sampling sub-sequences.
+    rng: ChaCha12Rng,
+}
+
+impl<O, A> EpisodicReplayBuffer<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    /// Returns the absolute index of the oldest transition in the buffer.
+    fn oldest_ix(&self) -> usize {
+        self.n_pushed.saturating_sub(self.capacity)
+    }
+
+    /// Returns `true` if none of the transitions of the episode has been overwritten.
+    fn is_stored(episode: &Episode, oldest_ix: usize) -> bool {
+        matches!(episode.ixs.front(), Some(&ix) if ix >= oldest_ix)
+    }
+
+    /// Removes transitions that have been overwritten from the index of episodes.
+    ///
+    /// Ongoing episodes lose their first transitions, while completed episodes are removed.
+    /// As episodes of sub-environments are completed in a different order from that of
+    /// their first transitions, some of completed episodes with overwritten transitions
+    /// can remain in the index; they are skipped with [`Self::is_stored`].
+    fn remove_overwritten(&mut self) {
+        let oldest_ix = self.oldest_ix();
+        while matches!(self.episodes.front(), Some(ep) if !Self::is_stored(ep, oldest_ix)) {
+            self.episodes.pop_front();
+        }
+        for ep in self.open_episodes.iter_mut() {
+            while matches!(ep.ixs.front(), Some(&ix) if ix < oldest_ix) {
+                ep.ixs.pop_front();
+            }
+        }
+    }
+
+    /// Samples `size` sub-sequences of `seq_len` transitions uniformly.
+    ///
+    /// For each sub-sequence, `f` is called with the random number generator, the absolute
+    /// indices of the stored transitions of the episode and the position of the first
+    /// transition of the sub-sequence in the episode.
+    pub(super) fn sample_with<T>(
+        &mut self,
+        size: usize,
+        seq_len: usize,
+        mut f: impl FnMut(&mut ChaCha12Rng, &VecDeque<usize>, usize) -> T,
+    ) -> Result<Vec<T>> {
+        let oldest_ix = self.oldest_ix();
+        let episodes = self
+            .episodes
+            .iter()
+            .filter(|ep| Self::is_stored(ep, oldest_ix))
+            .chain(self.open_episodes.iter())
+            .filter(|ep| ep.ixs.len() >= seq_len)
+            .collect::<Vec<_>>();
+
+        // Cumulative numbers of sub-sequences
+        let mut n_seqs = 0;
+        let cum_n_seqs = episodes
+            .iter()
+            .map(|ep| {
+                n_seqs += ep.ixs.len() - seq_len + 1;
+                n_seqs
+            })
+            .collect::<Vec<_>>();
+        if n_seqs == 0 {
+            return Err(anyhow!(
+                "No episode has {} or more transitions in the replay buffer",
+                seq_len
+            ));
+        }
+
+        let rng = &mut self.rng;
+        let samples = (0..size)
+            .map(|_| {
+                let u = (rng.next_u64() as usize) % n_seqs;
+                let k = cum_n_seqs.partition_point(|&c| c <= u);
+                let start = match k {
+                    0 => u,
+                    _ => u - cum_n_seqs[k - 1],
+                };
+                f(rng, &episodes[k].ixs, start)
+            })
+            .collect();
+
+        Ok(samples)
+    }
+
+    /// Returns the buffer storing the transitions.
+    pub(super) fn storage(&self) -> &SimpleReplayBuffer<O, A> {
+        &self.buffer
+    }
+
+    /// Returns the capacity of the buffer.
+    pub(super) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of completed episodes in the buffer.
+    pub fn num_episodes(&self) -> usize {
+        let oldest_ix = self.oldest_ix();
+        self.episodes
+            .iter()
+            .filter(|ep| Self::is_stored(ep, oldest_ix))
+            .count()
+    }
+
+    /// Returns the statistics of completed episodes in the buffer, from the oldest one.
+    pub fn episode_stats(&self) -> Vec<EpisodeStats> {
+        let oldest_ix = self.oldest_ix();
+        self.episodes
+            .iter()
+            .filter(|ep| Self::is_stored(ep, oldest_ix))
+            .map(|ep| EpisodeStats {
+                ret: ep.ret,
+                len: ep.len,
+            })
+            .collect()
+    }
+
+    /// Returns the length of sampled sub-sequences.
+    pub fn seq_len(&self) -> usize {
+        self.seq_len
+    }
+}
+
+impl<O, A> ExperienceBufferBase for EpisodicReplayBuffer<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    type Item = GenericTransitionBatch<O, A>;
+
+    /// Returns the current number of transitions in the buffer.
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Adds transitions of sub-environments to the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the number of transitions is different from that of
+    /// the previously pushed batches.
+    fn push(&mut self, tr: Self::Item) -> Result<()> {
+        let len = tr.len();
+        if len == 0 {
+            return Ok(());
+        }
+        if self.open_episodes.is_empty() {
+            self.open_episodes.resize_with(len, Default::default);
+        } else if self.open_episodes.len() != len {
+            return Err(anyhow!(
+                "Expected {} transitions, one for each sub-environment, but got {}",
+                self.open_episodes.len(),
+                len
+            ));
+        }
+
+        for (i, ep) in self.open_episodes.iter_mut().enumerate() {
+            ep.ixs.push_back(self.n_pushed + i);
+            ep.ret += tr.reward[i];
+            ep.len += 1;
+            if tr.is_terminated[i] == 1 || tr.is_truncated[i] == 1 {
+                self.episodes.push_back(std::mem::take(ep));
+            }
+        }
+
+        self.buffer.push(tr)?;
+        self.n_pushed += len;
+        self.remove_overwritten();
+
+        Ok(())
+    }
+}
+
+impl<O, A> ReplayBufferBase for EpisodicReplayBuffer<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    type Config = EpisodicReplayBufferConfig;
+    type Batch = GenericTransitionBatch<O, A>;
+
+    /// Creates a new replay buffer with the given configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `seq_len` in the configuration is zero.
+    fn build(config: &Self::Config) -> Self {
+        assert!(config.seq_len > 0, "seq_len should be positive");
+        let buffer_config = SimpleReplayBufferConfig::default()
+            .capacity(config.capacity)
+            .seed(config.seed);
+
+        Self {
+            buffer: SimpleReplayBuffer::build(&buffer_config),
+            capacity: config.capacity,
+            seq_len: config.seq_len,
+            n_pushed: 0,
+            episodes: VecDeque::new(),
+            open_episodes: vec![],
+            rng: ChaCha12Rng::seed_from_u64(config.seed),
+        }
+    }
+
+    /// Samples `size` sub-sequences of `seq_len` transitions.
+    ///
+    /// The returned batch has `size * seq_len` transitions. Transitions from
+    /// `j * seq_len` to `(j + 1) * seq_len - 1` form the `j`-th sub-sequence.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no episode in the buffer has `seq_len` or more transitions.
+    fn batch(&mut self, size: usize) -> Result<Self::Batch> {
+        let (capacity, seq_len) = (self.capacity, self.seq_len);
+        let ixs = self
+            .sample_with(size, seq_len, |_, ixs, start| {
+                ixs.range(start..start + seq_len)
+                    .map(|ix| ix % capacity)
+                    .collect::<Vec<_>>()
+            })?
+            .concat();
+
+        Ok(self.buffer.sample_transitions(ixs, None))
+    }
+
+    /// Does nothing, as prioritized sampling is not supported.
+    fn update_priority(&mut self, _ixs: &Option<Vec<usize>>, _td_errs: &Option<Vec<f32>>) {}
+
+    /// Saves the contents of the replay buffer into the directory `path`.
+    ///
+    /// In addition to the files saved by [`SimpleReplayBuffer`], the directory will
+    /// contain the following files:
+    ///
+    /// * `episodes.yaml` - Statistics of episodes, the number of pushed transitions
+    ///   and the RNG state
+    /// * `episode_ixs.npy` - Concatenated indices of the transitions of the completed
+    ///   episodes followed by the ongoing ones
+    fn save(&self, path: &Path) -> Result<()> {
+        self.buffer.save(path)?;
+
+        let to_meta = |ep: &Episode| EpisodeMeta {
+            ret: ep.ret,
+            len: ep.len,
+            n_stored: ep.ixs.len(),
+        };
+        let episode_ixs = self
+            .episodes
+            .iter()
+            .chain(self.open_episodes.iter())
+            .flat_map(|ep| ep.ixs.iter().map(|&ix| ix as i64))
+            .collect::<Vec<_>>();
+        write_npy(
+            path.join("episode_ixs.npy"),
+            &[episode_ixs.len()],
+            &episode_ixs,
+        )?;
+
+        let meta = EpisodicReplayBufferMeta {
+            n_pushed: self.n_pushed,
+            rng_seed: self.rng.get_seed(),
+            rng_word_pos: self.rng.get_word_pos() as u64,
+            episodes: self.episodes.iter().map(to_meta).collect(),
+            open_episodes: self.open_episodes.iter().map(to_meta).collect(),
+        };
+        let mut file = File::create(path.join("episodes.yaml"))?;
+        file.write_all(serde_yaml::to_string(&meta)?.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Loads the contents of the replay buffer saved by [`ReplayBufferBase::save`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the capacity of this buffer is different from that of
+    /// the saved one.
+    fn load(&mut self, path: &Path) -> Result<()> {
+        self.buffer.load(path)?;
+
+        let file = File::open(path.join("episodes.yaml"))?;
+        let meta: EpisodicReplayBufferMeta = serde_yaml::from_reader(BufReader::new(file))?;
+        let (episode_ixs, _) = read_npy::<i64>(path.join("episode_ixs.npy"))?;
+        let mut episode_ixs = episode_ixs.into_iter().map(|ix| ix as usize);
+        let mut from_meta = |m: &EpisodeMeta| Episode {
+            ixs: episode_ixs.by_ref().take(m.n_stored).collect(),
+            ret: m.ret,
+            len: m.len,
+        };
+
+        self.episodes = meta.episodes.iter().map(&mut from_meta).collect();
+        self.open_episodes = meta.open_episodes.iter().map(&mut from_meta).collect();
+        self.n_pushed = meta.n_pushed;
+        self.rng = ChaCha12Rng::from_seed(meta.rng_seed);
+        self.rng.set_word_pos(meta.rng_word_pos as u128);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{TestActBatch, TestObsBatch};
+    use tempdir::TempDir;
+
+    type ReplayBuffer = EpisodicReplayBuffer<TestObsBatch, TestActBatch>;
+
+    fn transition(
+        obs: usize,
+        reward: f32,
+        is_terminated: i8,
+    ) -> GenericTransitionBatch<TestObsBatch, TestActBatch> {
+        GenericTransitionBatch {
+            obs: TestObsBatch { obs: vec![obs] },
+            act: TestActBatch { act: vec![obs] },
+            next_obs: TestObsBatch { obs: vec![obs + 1] },
+            reward: vec![reward],
+            is_terminated: vec![is_terminated],
+            is_truncated: vec![0],
+            n_step: None,
+            ix_sample: None,
+            weight: None,
+        }
+    }
+
+    #[test]
+    fn test_episodic_replay_buffer() -> Result<()> {
+        let config = EpisodicReplayBufferConfig::default().capacity(8).seq_len(3);
+        let mut buffer = ReplayBuffer::build(&config);
+
+        // Episodes of lengths 2, 4 and an ongoing one
+        for (obs, is_terminated) in [(0, 0), (1, 1), (2, 0), (3, 0), (4, 0), (5, 1), (6, 0)] {
+            buffer.push(transition(obs, 1.0, is_terminated))?;
+        }
+        assert_eq!(buffer.num_episodes(), 2);
+        assert_eq!(
+            buffer.episode_stats(),
+            vec![
+                EpisodeStats { ret: 2.0, len: 2 },
+                EpisodeStats { ret: 4.0, len: 4 }
+            ]
+        );
+
+        // Sub-sequences are sampled only from the second episode
+        let batch = buffer.batch(10)?;
+        assert_eq!(batch.len(), 30);
+        for seq in batch.obs.obs.chunks(3) {
+            assert!(seq == [2, 3, 4] || seq == [3, 4, 5]);
+        }
+
+        // The first episode is removed when the buffer is full
+        buffer.push(transition(7, 1.0, 0))?;
+        buffer.push(transition(8, 1.0, 0))?;
+        assert_eq!(buffer.num_episodes(), 1);
+        assert_eq!(buffer.len(), 8);
+        for seq in buffer.batch(10)?.obs.obs.chunks(3) {
+            assert!(seq[1] == seq[0] + 1 && seq[2] == seq[1] + 1);
+        }
+
+        // Save and load
+        let dir = TempDir::new("episodic_replay_buffer")?;
+        buffer.save(dir.path())?;
+        let mut buffer2 = ReplayBuffer::build(&config);
+        buffer2.load(dir.path())?;
+        assert_eq!(buffer2.episode_stats(), buffer.episode_stats());
+        assert_eq!(buffer2.batch(4)?.obs.obs, buffer.batch(4)?.obs.obs);
+
+        Ok(())
+    }
+}
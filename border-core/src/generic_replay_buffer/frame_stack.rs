@@ -0,0 +1,607 @@
+//! Replay buffer storing each frame of stacked observations once.
+//!
+//! [`FrameStackReplayBuffer`] is a replacement of [`SimpleReplayBuffer`] for observations
+//! consisting of stacked frames, e.g., four frames of 84x84 pixels of Atari games. While
+//! [`SimpleReplayBuffer`] stores `obs` and `next_obs` of every transition, so a frame is
+//! stored up to `2 * n_frames` times, this buffer stores each frame once and reconstructs
+//! the stacked observations of sampled transitions.
+//!
+//! [`SimpleReplayBuffer`]: super::SimpleReplayBuffer
+use super::{
+    npy::{read_npy, write_npy},
+    BatchBase, FrameOrder, FrameStackBatch, FrameStackReplayBufferConfig, GenericTransitionBatch,
+};
+use crate::{ExperienceBufferBase, ReplayBufferBase, TransitionBatch};
+use anyhow::{anyhow, Result};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{create_dir_all, File},
+    io::{BufReader, Write},
+    marker::PhantomData,
+    path::Path,
+};
+
+/// Metadata of a replay buffer saved by [`FrameStackReplayBuffer`].
+#[derive(Debug, Deserialize, Serialize)]
+struct FrameStackReplayBufferMeta {
+    capacity: usize,
+    frame_capacity: usize,
+    i: usize,
+    size: usize,
+    n_slots: usize,
+    n_pushed_frames: usize,
+    last_start: Option<usize>,
+    obs_shape: Option<Vec<usize>>,
+    rng_seed: [u8; 32],
+    rng_word_pos: u64,
+}
+
+/// A replay buffer storing each frame of stacked observations once.
+///
+/// Frames are stored in chronological order in a ring buffer. A transition refers to the
+/// `n_frames + 1` consecutive frames, the first `n_frames` of which are stacked into the
+/// observation and the last `n_frames` into the next observation. When a transition is
+/// pushed, only the latest frame of the next observation is added, unless the observation
+/// does not continue from the next observation of the previous transition, e.g., at the
+/// beginning of an episode. In this case, the frames of the observation are also added.
+///
+/// The buffer can be used with [`SimpleStepProcessor`](super::SimpleStepProcessor) and
+/// [`Trainer`](crate::Trainer) in place of [`SimpleReplayBuffer`](super::SimpleReplayBuffer).
+/// Since frames are shared only between consecutive transitions, it is intended for
+/// non-vectorized environments and one-step transitions. Prioritized experience replay is
+/// not supported.
+///
+/// # Type Parameters
+///
+/// * `O` - The type of observations, must implement [`FrameStackBatch`]
+/// * `A` - The type of actions, must implement [`BatchBase`]
+pub struct FrameStackReplayBuffer<O, A>
+where
+    O: FrameStackBatch,
+    A: BatchBase,
+{
+    /// Maximum number of transitions that can be stored.
+    capacity: usize,
+
+    /// Maximum number of frames that can be stored.
+    frame_capacity: usize,
+
+    /// Current insertion index.
+    i: usize,
+
+    /// Current number of stored transitions.
+    size: usize,
+
+    /// Number of slots which have been written, used to save the buffer.
+    n_slots: usize,
+
+    /// Order of stacked frames in observations.
+    frame_order: FrameOrder,
+
+    /// Shape of an observation, excluding the batch axis.
+    ///
+    /// This is set when transitions are pushed for the first time.
+    obs_shape: Option<Vec<usize>>,
+
+    /// Ring buffer of frames, allocated when transitions are pushed for the first time.
+    frames: Vec<u8>,
+
+    /// Number of frames pushed into the buffer.
+    n_pushed_frames: usize,
+
+    /// Absolute index of the first frame of the observation of each transition, i.e., the
+    /// number of frames pushed before it.
+    start: Vec<usize>,
+
+    /// Absolute index of the first frame of the last pushed transition.
+    last_start: Option<usize>,
+
+    /// Storage for actions.
+    act: A,
+
+    /// Storage for rewards.
+    reward: Vec<f32>,
+
+    /// Storage for termination flags.
+    is_terminated: Vec<i8>,
+
+    /// Storage for truncation flags.
+    is_truncated: Vec<i8>,
+
+    /// Random number generator for sampling.
+    rng: ChaCha12Rng,
+
+    phantom: PhantomData<O>,
+}
+
+impl<O, A> FrameStackReplayBuffer<O, A>
+where
+    O: FrameStackBatch,
+    A: BatchBase,
+{
+    /// Returns the number of stacked frames in an observation.
+    fn n_frames(&self) -> usize {
+        self.obs_shape.as_ref().unwrap()[0]
+    }
+
+    /// Returns the number of elements in a frame.
+    fn frame_len(&self) -> usize {
+        self.obs_shape.as_ref().unwrap()[1..].iter().product()
+    }
+
+    /// Returns the number of frames stored in the buffer.
+    pub fn num_frames(&self) -> usize {
+        self.n_pushed_frames.min(self.frame_capacity)
+    }
+
+    /// Returns the frame at the absolute index `k`.
+    fn frame(&self, k: usize) -> &[u8] {
+        let len = self.frame_len();
+        let ix = (k % self.frame_capacity) * len;
+        &self.frames[ix..ix + len]
+    }
+
+    /// Returns the `k`-th frame of an observation in chronological order.
+    fn frame_of<'a>(&self, obs: &'a [u8], k: usize) -> &'a [u8] {
+        let len = self.frame_len();
+        let k = match self.frame_order {
+            FrameOrder::OldestFirst => k,
+            FrameOrder::NewestFirst => self.n_frames() - 1 - k,
+        };
+        &obs[k * len..(k + 1) * len]
+    }
+
+    /// Returns the index of the slot of the oldest transition in the buffer.
+    fn oldest(&self) -> usize {
+        (self.i + self.capacity - self.size) % self.capacity
+    }
+
+    /// Adds a frame, removing the oldest transitions whose frames are overwritten.
+    fn push_frame(&mut self, frame: &[u8]) {
+        self.n_pushed_frames += 1;
+        while self.size > 0
+            && self.start[self.oldest()] + self.frame_capacity < self.n_pushed_frames
+        {
+            self.size -= 1;
+        }
+
+        let len = frame.len();
+        let ix = ((self.n_pushed_frames - 1) % self.frame_capacity) * len;
+        self.frames[ix..ix + len].copy_from_slice(frame);
+    }
+
+    /// Returns `true` if `obs` is the next observation of the last pushed transition.
+    fn is_continued(&self, obs: &[u8]) -> bool {
+        match self.last_start {
+            Some(s) if s + self.n_frames() + 1 == self.n_pushed_frames => {
+                (0..self.n_frames()).all(|k| self.frame(s + 1 + k) == self.frame_of(obs, k))
+            }
+            _ => false,
+        }
+    }
+
+    /// Sets the shape of observations and allocates the frames, or checks the shape.
+    fn init_obs_shape(&mut self, shape: &[usize]) -> Result<()> {
+        match &self.obs_shape {
+            Some(obs_shape) => {
+                if obs_shape[..] != shape[1..] {
+                    return Err(anyhow!(
+                        "Shape of observations {:?} is different from {:?}",
+                        &shape[1..],
+                        obs_shape
+                    ));
+                }
+            }
+            None => {
+                if shape.len() < 2 || shape[1] >= self.frame_capacity {
+                    return Err(anyhow!(
+                        "Invalid shape of stacked frames {:?} for the frame capacity {}",
+                        shape,
+                        self.frame_capacity
+                    ));
+                }
+                self.obs_shape = Some(shape[1..].to_vec());
+                self.frames = vec![0; self.frame_capacity * self.frame_len()];
+            }
+        }
+        Ok(())
+    }
+
+    /// Stacks frames starting from the absolute indices into a batch of observations.
+    fn stack(&self, starts: &[usize]) -> O {
+        let n_frames = self.n_frames();
+        let mut data = Vec::with_capacity(starts.len() * n_frames * self.frame_len());
+        for &s in starts.iter() {
+            for k in 0..n_frames {
+                let k = match self.frame_order {
+                    FrameOrder::OldestFirst => k,
+                    FrameOrder::NewestFirst => n_frames - 1 - k,
+                };
+                data.extend_from_slice(self.frame(s + k));
+            }
+        }
+        let mut shape = vec![starts.len()];
+        shape.extend(self.obs_shape.as_ref().unwrap());
+        O::from_frames(data, &shape)
+    }
+
+    /// Creates a batch of the transitions at the given indices of the buffer.
+    fn sample_transitions(&self, ixs: Vec<usize>) -> GenericTransitionBatch<O, A> {
+        let starts = ixs.iter().map(|&ix| self.start[ix]).collect::<Vec<_>>();
+        let next_starts = starts.iter().map(|s| s + 1).collect::<Vec<_>>();
+        GenericTransitionBatch {
+            obs: self.stack(&starts),
+            act: self.act.sample(&ixs),
+            next_obs: self.stack(&next_starts),
+            reward: ixs.iter().map(|&ix| self.reward[ix]).collect(),
+            is_terminated: ixs.iter().map(|&ix| self.is_terminated[ix]).collect(),
+            is_truncated: ixs.iter().map(|&ix| self.is_truncated[ix]).collect(),
+            n_step: None,
+            ix_sample: Some(ixs),
+            weight: None,
+        }
+    }
+}
+
+impl<O, A> ExperienceBufferBase for FrameStackReplayBuffer<O, A>
+where
+    O: FrameStackBatch,
+    A: BatchBase,
+{
+    type Item = GenericTransitionBatch<O, A>;
+
+    /// Returns the current number of transitions in the buffer.
+    fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Adds transitions to the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the shape of observations changes, if the next observation is
+    /// not the observation shifted by a frame, e.g., for n-step transitions, or if the
+    /// frame capacity is not larger than the number of stacked frames.
+    fn push(&mut self, tr: Self::Item) -> Result<()> {
+        let len = tr.len();
+        if len == 0 {
+            return Ok(());
+        }
+        if tr.n_step().is_some_and(|n| n.iter().any(|&n| n != 1)) {
+            return Err(anyhow!("n-step transitions are not supported"));
+        }
+
+        let (obs, act, next_obs, reward, is_terminated, is_truncated, _, _) = tr.unpack();
+        let (obs, shape) = obs.to_frames();
+        let (next_obs, next_shape) = next_obs.to_frames();
+        if shape != next_shape || shape[0] != len {
+            return Err(anyhow!(
+                "Shapes of observations {:?} and next observations {:?} do not match",
+                shape,
+                next_shape
+            ));
+        }
+        self.init_obs_shape(&shape)?;
+
+        let n_frames = self.n_frames();
+        let obs_len = n_frames * self.frame_len();
+        for b in 0..len {
+            let obs = &obs[b * obs_len..(b + 1) * obs_len];
+            let next_obs = &next_obs[b * obs_len..(b + 1) * obs_len];
+            if (0..n_frames - 1).any(|k| self.frame_of(obs, k + 1) != self.frame_of(next_obs, k)) {
+                return Err(anyhow!(
+                    "Next observation is not the observation shifted by a frame"
+                ));
+            }
+        }
+
+        self.act.push(self.i, act);
+        for b in 0..len {
+            let obs = &obs[b * obs_len..(b + 1) * obs_len];
+            let next_obs = &next_obs[b * obs_len..(b + 1) * obs_len];
+
+            // The slot of the oldest transition is overwritten
+            if self.size == self.capacity {
+                self.size -= 1;
+            }
+
+            let start = match self.is_continued(obs) {
+                true => self.last_start.unwrap() + 1,
+                false => {
+                    let start = self.n_pushed_frames;
+                    for k in 0..n_frames {
+                        self.push_frame(self.frame_of(obs, k));
+                    }
+                    start
+                }
+            };
+            self.push_frame(self.frame_of(next_obs, n_frames - 1));
+
+            self.start[self.i] = start;
+            self.reward[self.i] = reward[b];
+            self.is_terminated[self.i] = is_terminated[b];
+            self.is_truncated[self.i] = is_truncated[b];
+            self.last_start = Some(start);
+            self.i = (self.i + 1) % self.capacity;
+            self.size += 1;
+            self.n_slots = self.capacity.min(self.n_slots + 1);
+        }
+
+        Ok(())
+    }
+}
+
+impl<O, A> ReplayBufferBase for FrameStackReplayBuffer<O, A>
+where
+    O: FrameStackBatch,
+    A: BatchBase,
+{
+    type Config = FrameStackReplayBufferConfig;
+    type Batch = GenericTransitionBatch<O, A>;
+
+    /// Creates a new replay buffer with the given configuration.
+    fn build(config: &Self::Config) -> Self {
+        let capacity = config.capacity;
+        let frame_capacity = config.frame_capacity.unwrap_or(capacity + capacity / 4);
+
+        Self {
+            capacity,
+            frame_capacity,
+            i: 0,
+            size: 0,
+            n_slots: 0,
+            frame_order: config.frame_order,
+            obs_shape: None,
+            frames: vec![],
+            n_pushed_frames: 0,
+            start: vec![0; capacity],
+            last_start: None,
+            act: A::new(capacity),
+            reward: vec![0.; capacity],
+            is_terminated: vec![0; capacity],
+            is_truncated: vec![0; capacity],
+            rng: ChaCha12Rng::seed_from_u64(config.seed),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Samples a batch of transitions uniformly at random.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer is empty.
+    fn batch(&mut self, size: usize) -> Result<Self::Batch> {
+        if self.size == 0 {
+            return Err(anyhow!("No transitions in the replay buffer"));
+        }
+        let oldest = self.oldest();
+        let ixs = (0..size)
+            .map(|_| (oldest + (self.rng.next_u32() as usize) % self.size) % self.capacity)
+            .collect::<Vec<_>>();
+
+        Ok(self.sample_transitions(ixs))
+    }
+
+    /// Does nothing, as prioritized experience replay is not supported.
+    fn update_priority(&mut self, _ixs: &Option<Vec<usize>>, _td_errs: &Option<Vec<f32>>) {}
+
+    /// Saves the contents of the replay buffer into the directory `path`.
+    ///
+    /// The directory will contain the following files:
+    ///
+    /// * `meta.yaml` - Capacities, insertion indices, size, shape of observations and RNG state
+    /// * `frames.npy` - Stored frames
+    /// * `act` - Directory given to [`BatchBase::save`]
+    /// * `start.npy`, `reward.npy`, `is_terminated.npy`, `is_truncated.npy` - Indices of
+    ///   the first frames, rewards and flags
+    fn save(&self, path: &Path) -> Result<()> {
+        create_dir_all(path.join("act"))?;
+        let n = self.n_slots;
+        self.act.save(&path.join("act"), n)?;
+        let start = self.start[..n]
+            .iter()
+            .map(|&s| s as i64)
+            .collect::<Vec<_>>();
+        write_npy(path.join("start.npy"), &[n], &start)?;
+        write_npy(path.join("reward.npy"), &[n], &self.reward[..n])?;
+        write_npy(
+            path.join("is_terminated.npy"),
+            &[n],
+            &self.is_terminated[..n],
+        )?;
+        write_npy(path.join("is_truncated.npy"), &[n], &self.is_truncated[..n])?;
+        write_npy(path.join("frames.npy"), &[self.frames.len()], &self.frames)?;
+
+        let meta = FrameStackReplayBufferMeta {
+            capacity: self.capacity,
+            frame_capacity: self.frame_capacity,
+            i: self.i,
+            size: self.size,
+            n_slots: self.n_slots,
+            n_pushed_frames: self.n_pushed_frames,
+            last_start: self.last_start,
+            obs_shape: self.obs_shape.clone(),
+            rng_seed: self.rng.get_seed(),
+            rng_word_pos: self.rng.get_word_pos() as u64,
+        };
+        let mut file = File::create(path.join("meta.yaml"))?;
+        file.write_all(serde_yaml::to_string(&meta)?.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Loads the contents of the replay buffer saved by [`ReplayBufferBase::save`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the capacities of this buffer are different from those of the
+    /// saved one.
+    fn load(&mut self, path: &Path) -> Result<()> {
+        let file = File::open(path.join("meta.yaml"))?;
+        let meta: FrameStackReplayBufferMeta = serde_yaml::from_reader(BufReader::new(file))?;
+        if meta.capacity != self.capacity || meta.frame_capacity != self.frame_capacity {
+            return Err(anyhow!(
+                "Capacities of the saved replay buffer ({}, {}) are different from ({}, {})",
+                meta.capacity,
+                meta.frame_capacity,
+                self.capacity,
+                self.frame_capacity
+            ));
+        }
+
+        let n = meta.n_slots;
+        if self.act.load(&path.join("act"))? != n {
+            return Err(anyhow!("Number of loaded actions is different from {}", n));
+        }
+        let (start, _) = read_npy::<i64>(path.join("start.npy"))?;
+        let (reward, _) = read_npy::<f32>(path.join("reward.npy"))?;
+        let (is_terminated, _) = read_npy::<i8>(path.join("is_terminated.npy"))?;
+        let (is_truncated, _) = read_npy::<i8>(path.join("is_truncated.npy"))?;
+        for (ix, s) in start.iter().enumerate() {
+            self.start[ix] = *s as usize;
+        }
+        self.reward[..n].copy_from_slice(&reward);
+        self.is_terminated[..n].copy_from_slice(&is_terminated);
+        self.is_truncated[..n].copy_from_slice(&is_truncated);
+        (self.frames, _) = read_npy::<u8>(path.join("frames.npy"))?;
+
+        self.i = meta.i;
+        self.size = meta.size;
+        self.n_slots = meta.n_slots;
+        self.n_pushed_frames = meta.n_pushed_frames;
+        self.last_start = meta.last_start;
+        self.obs_shape = meta.obs_shape;
+        self.rng = ChaCha12Rng::from_seed(meta.rng_seed);
+        self.rng.set_word_pos(meta.rng_word_pos as u128);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    /// Observations of two stacked frames of two pixels.
+    #[derive(Clone, Debug, PartialEq)]
+    struct FrameObs(Vec<u8>, usize);
+
+    impl BatchBase for FrameObs {
+        fn new(_capacity: usize) -> Self {
+            unimplemented!();
+        }
+
+        fn push(&mut self, _ix: usize, _data: Self) {
+            unimplemented!();
+        }
+
+        fn sample(&self, _ixs: &Vec<usize>) -> Self {
+            unimplemented!();
+        }
+    }
+
+    impl FrameStackBatch for FrameObs {
+        fn to_frames(&self) -> (Vec<u8>, Vec<usize>) {
+            (self.0.clone(), vec![self.1, 2, 2])
+        }
+
+        fn from_frames(data: Vec<u8>, shape: &[usize]) -> Self {
+            Self(data, shape[0])
+        }
+    }
+
+    struct ActBatch(Vec<i64>);
+
+    impl BatchBase for ActBatch {
+        fn new(capacity: usize) -> Self {
+            Self(vec![0; capacity])
+        }
+
+        fn push(&mut self, ix: usize, data: Self) {
+            self.0[ix] = data.0[0];
+        }
+
+        fn sample(&self, ixs: &Vec<usize>) -> Self {
+            Self(ixs.iter().map(|&ix| self.0[ix]).collect())
+        }
+
+        fn save(&self, path: &Path, len: usize) -> Result<()> {
+            write_npy(path.join("act.npy"), &[len], &self.0[..len])
+        }
+
+        fn load(&mut self, path: &Path) -> Result<usize> {
+            let (data, _) = read_npy::<i64>(path.join("act.npy"))?;
+            self.0[..data.len()].copy_from_slice(&data);
+            Ok(data.len())
+        }
+    }
+
+    type ReplayBuffer = FrameStackReplayBuffer<FrameObs, ActBatch>;
+
+    /// Frame `t` of the episode is `[10 * episode + t; 2]`, stacked in newest-first order.
+    fn transition(episode: u8, t: u8) -> GenericTransitionBatch<FrameObs, ActBatch> {
+        let frame = |t: u8| [10 * episode + t; 2];
+        let obs = [frame(t), frame(t.saturating_sub(1))].concat();
+        let next_obs = [frame(t + 1), frame(t)].concat();
+        GenericTransitionBatch {
+            obs: FrameObs(obs, 1),
+            act: ActBatch(vec![t as i64]),
+            next_obs: FrameObs(next_obs, 1),
+            reward: vec![t as f32],
+            is_terminated: vec![0],
+            is_truncated: vec![0],
+            n_step: None,
+            weight: None,
+            ix_sample: None,
+        }
+    }
+
+    #[test]
+    fn test_frame_stack_replay_buffer() -> Result<()> {
+        let config = FrameStackReplayBufferConfig::default()
+            .capacity(4)
+            .frame_capacity(7);
+        let mut buffer = ReplayBuffer::build(&config);
+
+        // The first transition adds two frames of obs and a frame of next_obs
+        for t in 0..3 {
+            buffer.push(transition(0, t))?;
+        }
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.num_frames(), 5);
+
+        // A new episode overwrites the frames of the first transition
+        buffer.push(transition(1, 0))?;
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.num_frames(), 7);
+
+        let batch = buffer.sample_transitions(vec![1, 3]);
+        assert_eq!(batch.obs, FrameObs(vec![1, 1, 0, 0, 10, 10, 10, 10], 2));
+        assert_eq!(
+            batch.next_obs,
+            FrameObs(vec![2, 2, 1, 1, 11, 11, 10, 10], 2)
+        );
+        assert_eq!(batch.act.0, vec![1, 0]);
+        assert_eq!(buffer.batch(8)?.len(), 8);
+
+        // Saved and loaded buffer
+        let dir = TempDir::new("frame_stack_replay_buffer")?;
+        buffer.save(dir.path())?;
+        let mut buffer2 = ReplayBuffer::build(&config);
+        buffer2.load(dir.path())?;
+        assert_eq!(buffer2.len(), 3);
+        assert_eq!(buffer2.sample_transitions(vec![1, 3]).obs, batch.obs);
+        buffer2.push(transition(1, 1))?;
+        assert_eq!(buffer2.len(), 3);
+
+        // n-step transitions are not supported
+        let mut tr = transition(1, 2);
+        tr.next_obs = FrameObs(vec![14, 14, 13, 13], 1);
+        assert!(buffer2.push(tr).is_err());
+
+        Ok(())
+    }
+}
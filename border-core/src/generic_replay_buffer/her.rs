@@ -0,0 +1,283 @@
+//! Hindsight Experience Replay (HER).
+//!
+//! [`HerReplayBuffer`] relabels desired goals of sampled transitions with goals achieved
+//! later in the same episodes, as proposed in
+//! [Andrychowicz et al. (2017)](https://arxiv.org/abs/1707.01495). Rewards of relabeled
+//! transitions are recomputed with a user-supplied reward function.
+use super::{
+    BatchBase, EpisodicReplayBuffer, EpisodicReplayBufferConfig, GenericTransitionBatch,
+    GoalObsBatch, HerReplayBufferConfig, HerRewardFn, HerStrategy,
+};
+use crate::{ExperienceBufferBase, ReplayBufferBase};
+use anyhow::Result;
+use rand::RngCore;
+use std::{ops::Range, path::Path};
+
+/// A replay buffer with Hindsight Experience Replay (HER).
+///
+/// Observations are flat vectors containing the achieved and desired goals, e.g.,
+/// concatenated elements of dict observations with keys `observation`, `achieved_goal`
+/// and `desired_goal`. The positions of the goals are given in
+/// [`HerReplayBufferConfig`].
+///
+/// In [`ReplayBufferBase::batch`], each sampled transition is relabeled with probability
+/// `k / (k + 1)`. The desired goals of its observation and next observation are replaced
+/// with the goal achieved in the next observation of another transition in the episode,
+/// selected with [`HerStrategy`]. The reward is then recomputed with the reward function
+/// from the achieved goal of the next observation and the new desired goal.
+///
+/// Transitions are pushed as in [`EpisodicReplayBuffer`], so this buffer can be used with
+/// [`SimpleStepProcessor`](super::SimpleStepProcessor) and [`Trainer`](crate::Trainer)
+/// in place of [`SimpleReplayBuffer`](super::SimpleReplayBuffer).
+pub struct HerReplayBuffer<O, A>
+where
+    O: GoalObsBatch,
+    A: BatchBase,
+{
+    /// Storage of the transitions with the index of episodes.
+    buffer: EpisodicReplayBuffer<O, A>,
+
+    /// Strategy for selecting goals.
+    strategy: HerStrategy,
+
+    /// Ratio of relabeled transitions to original ones.
+    k: usize,
+
+    /// Range of the achieved goal in a flat observation.
+    achieved_goal: Range<usize>,
+
+    /// Range of the desired goal in a flat observation.
+    desired_goal: Range<usize>,
+
+    /// Reward function for relabeled transitions.
+    reward_fn: HerRewardFn,
+}
+
+impl<O, A> HerReplayBuffer<O, A>
+where
+    O: GoalObsBatch,
+    A: BatchBase,
+{
+    /// Returns the buffer of episodes, e.g., to get episode statistics.
+    pub fn episodes(&self) -> &EpisodicReplayBuffer<O, A> {
+        &self.buffer
+    }
+}
+
+impl<O, A> ExperienceBufferBase for HerReplayBuffer<O, A>
+where
+    O: GoalObsBatch,
+    A: BatchBase,
+{
+    type Item = GenericTransitionBatch<O, A>;
+
+    /// Returns the current number of transitions in the buffer.
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Adds transitions of sub-environments to the buffer.
+    fn push(&mut self, tr: Self::Item) -> Result<()> {
+        self.buffer.push(tr)
+    }
+}
+
+impl<O, A> ReplayBufferBase for HerReplayBuffer<O, A>
+where
+    O: GoalObsBatch,
+    A: BatchBase,
+{
+    type Config = HerReplayBufferConfig;
+    type Batch = GenericTransitionBatch<O, A>;
+
+    /// Creates a new replay buffer with the given configuration.
+    fn build(config: &Self::Config) -> Self {
+        let buffer_config = EpisodicReplayBufferConfig::default()
+            .capacity(config.capacity)
+            .seed(config.seed);
+
+        Self {
+            buffer: EpisodicReplayBuffer::build(&buffer_config),
+            strategy: config.strategy,
+            k: config.k,
+            achieved_goal: config.achieved_goal.clone(),
+            desired_goal: config.desired_goal.clone(),
+            reward_fn: config.reward_fn,
+        }
+    }
+
+    /// Samples a batch of transitions, some of which are relabeled with achieved goals.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer is empty.
+    fn batch(&mut self, size: usize) -> Result<Self::Batch> {
+        let (capacity, strategy, k) = (self.buffer.capacity(), self.strategy, self.k as u64);
+
+        // Indices of sampled transitions and those of transitions providing new goals
+        let samples = self.buffer.sample_with(size, 1, |rng, ixs, t| {
+            let goal_ix = match rng.next_u64() % (k + 1) {
+                0 => None,
+                _ => {
+                    let len = ixs.len();
+                    let t_goal = match strategy {
+                        HerStrategy::Future => t + (rng.next_u64() as usize) % (len - t),
+                        HerStrategy::Final => len - 1,
+                        HerStrategy::Episode => (rng.next_u64() as usize) % len,
+                    };
+                    Some(ixs[t_goal] % capacity)
+                }
+            };
+            (ixs[t] % capacity, goal_ix)
+        })?;
+        let ixs = samples.iter().map(|&(ix, _)| ix).collect::<Vec<_>>();
+        let mut batch = self.buffer.storage().sample_transitions(ixs, None);
+
+        let (rows, goal_ixs): (Vec<_>, Vec<_>) = samples
+            .iter()
+            .enumerate()
+            .filter_map(|(row, &(_, goal_ix))| goal_ix.map(|ix| (row, ix)))
+            .unzip();
+        if rows.is_empty() {
+            return Ok(batch);
+        }
+
+        // Relabel desired goals and recompute rewards
+        let new_goals = self
+            .buffer
+            .storage()
+            .next_obs()
+            .sample(&goal_ixs)
+            .goals(self.achieved_goal.clone());
+        let mut goals = batch.obs.goals(self.desired_goal.clone());
+        for (&row, goal) in rows.iter().zip(new_goals) {
+            goals[row] = goal;
+        }
+        batch.obs.set_goals(self.desired_goal.clone(), &goals);
+        batch.next_obs.set_goals(self.desired_goal.clone(), &goals);
+
+        let achieved_goals = batch.next_obs.goals(self.achieved_goal.clone());
+        for &row in rows.iter() {
+            batch.reward[row] = (self.reward_fn)(&achieved_goals[row], &goals[row]);
+        }
+
+        Ok(batch)
+    }
+
+    /// Does nothing, as prioritized sampling is not supported.
+    fn update_priority(&mut self, _ixs: &Option<Vec<usize>>, _td_errs: &Option<Vec<f32>>) {}
+
+    /// Saves the contents of the replay buffer into the directory `path`.
+    ///
+    /// See [`EpisodicReplayBuffer`] for the saved files.
+    fn save(&self, path: &Path) -> Result<()> {
+        self.buffer.save(path)
+    }
+
+    /// Loads the contents of the replay buffer saved by [`ReplayBufferBase::save`].
+    fn load(&mut self, path: &Path) -> Result<()> {
+        self.buffer.load(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Observations with an achieved goal and a desired goal.
+    struct GoalBatch {
+        obs: Vec<[f32; 2]>,
+    }
+
+    impl BatchBase for GoalBatch {
+        fn new(capacity: usize) -> Self {
+            Self {
+                obs: vec![[0.; 2]; capacity],
+            }
+        }
+
+        fn push(&mut self, i: usize, data: Self) {
+            self.obs[i] = data.obs[0];
+        }
+
+        fn sample(&self, ixs: &Vec<usize>) -> Self {
+            let obs = ixs.iter().map(|&ix| self.obs[ix]).collect();
+            Self { obs }
+        }
+    }
+
+    impl GoalObsBatch for GoalBatch {
+        fn goals(&self, range: Range<usize>) -> Vec<Vec<f32>> {
+            self.obs.iter().map(|o| o[range.clone()].to_vec()).collect()
+        }
+
+        fn set_goals(&mut self, range: Range<usize>, goals: &[Vec<f32>]) {
+            for (o, g) in self.obs.iter_mut().zip(goals) {
+                o[range.clone()].copy_from_slice(g);
+            }
+        }
+    }
+
+    type ReplayBuffer = HerReplayBuffer<GoalBatch, GoalBatch>;
+
+    fn transition(
+        achieved_goal: f32,
+        is_terminated: i8,
+    ) -> GenericTransitionBatch<GoalBatch, GoalBatch> {
+        GenericTransitionBatch {
+            obs: GoalBatch {
+                obs: vec![[achieved_goal - 1., 10.]],
+            },
+            act: GoalBatch { obs: vec![[0.; 2]] },
+            next_obs: GoalBatch {
+                obs: vec![[achieved_goal, 10.]],
+            },
+            reward: vec![0.],
+            is_terminated: vec![is_terminated],
+            is_truncated: vec![0],
+            n_step: None,
+            ix_sample: None,
+            weight: None,
+        }
+    }
+
+    #[test]
+    fn test_her_replay_buffer() -> Result<()> {
+        let config = HerReplayBufferConfig::new(|achieved, desired| {
+            (achieved[0] == desired[0]) as i32 as f32
+        })
+        .capacity(100)
+        .k(4)
+        .achieved_goal(0..1)
+        .desired_goal(1..2);
+        let mut buffer = ReplayBuffer::build(&config);
+
+        // Two episodes with achieved goals 1, 2, 3 and 11, 12
+        for (achieved_goal, is_terminated) in [(1., 0), (2., 0), (3., 1), (11., 0), (12., 1)] {
+            buffer.push(transition(achieved_goal, is_terminated))?;
+        }
+
+        let batch = buffer.batch(200)?;
+        let mut n_relabeled = 0;
+        for i in 0..200 {
+            let [achieved_goal, desired_goal] = batch.next_obs.obs[i];
+            assert_eq!(batch.obs.obs[i][1], desired_goal);
+            if desired_goal == 10. {
+                assert_eq!(batch.reward[i], 0.);
+                continue;
+            }
+            n_relabeled += 1;
+
+            // Future goals are achieved in the same episode
+            assert!(desired_goal >= achieved_goal);
+            assert_eq!(desired_goal > 10., achieved_goal > 10.);
+            assert_eq!(
+                batch.reward[i],
+                (desired_goal == achieved_goal) as i32 as f32
+            );
+        }
+        assert!(n_relabeled > 120 && n_relabeled < 200);
+
+        Ok(())
+    }
+}
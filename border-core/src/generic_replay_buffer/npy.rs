@@ -0,0 +1,221 @@
+//! Reading and writing arrays in the [NumPy `.npy` format].
+//!
+//! This module provides minimal support of the `.npy` format (version 1.0),
+//! used to save and load the contents of replay buffers. Arrays are stored
+//! in little-endian, C-contiguous order. Only the element types implementing
+//! [`NpyElement`] are supported.
+//!
+//! Files written by [`write_npy`] can be read with `numpy.load()` and vice versa,
+//! which allows preparing datasets for offline training in Python.
+//!
+//! [NumPy `.npy` format]: https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html
+use anyhow::{anyhow, Result};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Element types of arrays that can be stored in `.npy` files.
+pub trait NpyElement: Copy {
+    /// Type descriptor in the header, e.g., `<f4`.
+    const DESCR: &'static str;
+
+    /// Size of an element in bytes.
+    const SIZE: usize;
+
+    /// Writes the element in little-endian.
+    fn write_le(&self, buf: &mut Vec<u8>);
+
+    /// Reads an element from little-endian bytes of length [`NpyElement::SIZE`].
+    fn read_le(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_npy_element {
+    ($t:ty, $descr:expr) => {
+        impl NpyElement for $t {
+            const DESCR: &'static str = $descr;
+            const SIZE: usize = std::mem::size_of::<$t>();
+
+            fn write_le(&self, buf: &mut Vec<u8>) {
+                buf.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn read_le(bytes: &[u8]) -> Self {
+                let mut b = [0u8; std::mem::size_of::<$t>()];
+                b.copy_from_slice(bytes);
+                <$t>::from_le_bytes(b)
+            }
+        }
+    };
+}
+
+impl_npy_element!(f32, "<f4");
+impl_npy_element!(f64, "<f8");
+impl_npy_element!(i8, "|i1");
+impl_npy_element!(u8, "|u1");
+impl_npy_element!(i32, "<i4");
+impl_npy_element!(i64, "<i8");
+
+/// Writes an array with the given shape to a `.npy` file.
+///
+/// # Errors
+///
+/// Returns an error if the number of elements does not match the shape
+/// or the file cannot be written.
+pub fn write_npy<T: NpyElement>(path: impl AsRef<Path>, shape: &[usize], data: &[T]) -> Result<()> {
+    let n = shape.iter().product::<usize>();
+    if n != data.len() {
+        return Err(anyhow!(
+            "Shape {:?} does not match the number of elements {}",
+            shape,
+            data.len()
+        ));
+    }
+
+    let shape_str = match shape.len() {
+        1 => format!("({},)", shape[0]),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        T::DESCR,
+        shape_str
+    );
+
+    // Pad the header with spaces so that the data is aligned to 64 bytes
+    let unpadded = MAGIC.len() + 2 + 2 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    let mut wtr = BufWriter::new(File::create(path)?);
+    wtr.write_all(MAGIC)?;
+    wtr.write_all(&[1u8, 0u8])?;
+    wtr.write_all(&(header.len() as u16).to_le_bytes())?;
+    wtr.write_all(header.as_bytes())?;
+
+    let mut buf = Vec::with_capacity(data.len() * T::SIZE);
+    data.iter().for_each(|x| x.write_le(&mut buf));
+    wtr.write_all(&buf)?;
+    wtr.flush()?;
+
+    Ok(())
+}
+
+/// Reads an array from a `.npy` file.
+///
+/// Returns the elements in C-contiguous order and the shape of the array.
+///
+/// # Errors
+///
+/// Returns an error if the file is not a valid `.npy` file, is in Fortran order,
+/// or its element type does not match `T`.
+pub fn read_npy<T: NpyElement>(path: impl AsRef<Path>) -> Result<(Vec<T>, Vec<usize>)> {
+    let mut rdr = BufReader::new(File::open(path.as_ref())?);
+    let mut magic = [0u8; 6];
+    rdr.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(anyhow!("{:?} is not a npy file", path.as_ref()));
+    }
+
+    let mut version = [0u8; 2];
+    rdr.read_exact(&mut version)?;
+    let header_len = match version[0] {
+        1 => {
+            let mut b = [0u8; 2];
+            rdr.read_exact(&mut b)?;
+            u16::from_le_bytes(b) as usize
+        }
+        _ => {
+            let mut b = [0u8; 4];
+            rdr.read_exact(&mut b)?;
+            u32::from_le_bytes(b) as usize
+        }
+    };
+    let mut header = vec![0u8; header_len];
+    rdr.read_exact(&mut header)?;
+    let header = String::from_utf8(header)?;
+
+    let descr = header_value(&header, "descr")?;
+    let descr = descr.trim_matches(|c| c == '\'' || c == '"');
+    if descr != T::DESCR && !(T::SIZE == 1 && descr[1..] == T::DESCR[1..]) {
+        return Err(anyhow!(
+            "Element type {} in {:?} does not match {}",
+            descr,
+            path.as_ref(),
+            T::DESCR
+        ));
+    }
+    if header_value(&header, "fortran_order")? != "False" {
+        return Err(anyhow!("Fortran order is not supported"));
+    }
+    let shape = header_value(&header, "shape")?
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<usize>())
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let n = shape.iter().product::<usize>();
+    let mut bytes = vec![0u8; n * T::SIZE];
+    rdr.read_exact(&mut bytes)?;
+    let data = bytes.chunks_exact(T::SIZE).map(T::read_le).collect();
+
+    Ok((data, shape))
+}
+
+/// Extracts the value of a key in the header dictionary of a `.npy` file.
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str> {
+    let pattern = format!("'{}':", key);
+    let start = header
+        .find(&pattern)
+        .ok_or_else(|| anyhow!("Key {} not found in npy header", key))?
+        + pattern.len();
+    let rest = header[start..].trim_start();
+    let end = match rest.chars().next() {
+        Some('(') => rest.find(')').map(|i| i + 1),
+        _ => rest.find(',').or_else(|| rest.find('}')),
+    }
+    .ok_or_else(|| anyhow!("Invalid npy header: {}", header))?;
+
+    Ok(rest[..end].trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_npy_roundtrip() -> Result<()> {
+        let dir = TempDir::new("npy")?;
+
+        let path = dir.path().join("f32.npy");
+        let data = (0..24).map(|x| x as f32 * 0.5).collect::<Vec<_>>();
+        write_npy(&path, &[2, 3, 4], &data)?;
+        let (data_, shape) = read_npy::<f32>(&path)?;
+        assert_eq!(data, data_);
+        assert_eq!(shape, vec![2, 3, 4]);
+
+        let path = dir.path().join("i8.npy");
+        let data = vec![0i8, 1, 0, 1, 1];
+        write_npy(&path, &[5], &data)?;
+        let (data_, shape) = read_npy::<i8>(&path)?;
+        assert_eq!(data, data_);
+        assert_eq!(shape, vec![5]);
+
+        assert!(read_npy::<i64>(&path).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+//! Buffer of on-policy rollouts.
+//!
+//! [`RolloutBuffer`] stores the transitions collected with the current policy, in the
+//! same way as [`SimpleReplayBuffer`], together with the log-probabilities of the actions
+//! and the values of the states given by the agent. It supports:
+//! - Generalized advantage estimation (GAE) for each sub-environment
+//! - Minibatches sampled without replacement, for multiple epochs over the rollout
+//! - Clearing the transitions after each update of the policy
+use super::{
+    BatchBase, GenericTransitionBatch, RolloutBufferConfig, SimpleReplayBuffer,
+    SimpleReplayBufferConfig,
+};
+use crate::{ExperienceBufferBase, ReplayBufferBase, TransitionBatch};
+use anyhow::{anyhow, Result};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
+
+/// Metadata saved by [`RolloutBuffer`] in addition to that of [`SimpleReplayBuffer`].
+#[derive(Debug, Deserialize, Serialize)]
+struct RolloutBufferMeta {
+    n_envs: usize,
+    rng_seed: [u8; 32],
+    rng_word_pos: u64,
+}
+
+/// Values computed by the agent and the advantages estimated from them.
+struct RolloutValues {
+    logp: Vec<f32>,
+    value: Vec<f32>,
+    advantage: Vec<f32>,
+    ret: Vec<f32>,
+}
+
+/// A minibatch sampled from [`RolloutBuffer`].
+pub struct RolloutBatch<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    /// Observations.
+    pub obs: O,
+
+    /// Actions taken in the rollout.
+    pub act: A,
+
+    /// Log-probabilities of the actions under the policy used in the rollout.
+    pub logp: Vec<f32>,
+
+    /// Values of the observations estimated in the rollout.
+    pub value: Vec<f32>,
+
+    /// Advantages estimated with GAE.
+    pub advantage: Vec<f32>,
+
+    /// Targets of the value function, i.e., `advantage + value`.
+    pub ret: Vec<f32>,
+}
+
+/// A buffer of transitions collected with the current policy, for on-policy algorithms
+/// such as PPO.
+///
+/// Each batch pushed into the buffer must have one transition for each sub-environment,
+/// in the same order, as created by
+/// [`SimpleStepProcessor`](super::SimpleStepProcessor). The buffer is
+/// [ready](ReplayBufferBase::is_ready) when `capacity` transitions have been pushed, at
+/// which point [`Trainer`](crate::Trainer) calls the optimization step of the agent.
+///
+/// An optimization step of the agent typically goes as follows:
+///
+/// 1. Takes all the transitions with [`RolloutBuffer::transitions`] and computes the
+///    log-probabilities of the actions and the values of the observations and the next
+///    observations. As the policy is not updated during the rollout, they are the same
+///    as those at the time the actions were taken.
+/// 2. Gives them to [`RolloutBuffer::set_values`], which estimates the advantages
+///    with GAE.
+/// 3. Samples minibatches with [`ReplayBufferBase::batch`] and updates the policy.
+///    Minibatches are sampled without replacement, and the transitions are reshuffled
+///    when all of them have been sampled.
+/// 4. Calls [`RolloutBuffer::clear`] to collect the next rollout with the updated policy.
+///
+/// # Examples
+///
+/// ```ignore
+/// let config = RolloutBufferConfig::default().capacity(2048);
+/// let mut buffer = RolloutBuffer::<ObsBatch, ActBatch>::build(&config);
+///
+/// // Push transitions, e.g., with `Trainer`
+///
+/// let tr = buffer.transitions();
+/// buffer.set_values(logp, value, next_value)?;
+/// for _ in 0..n_epochs * buffer.len() / 64 {
+///     let batch = buffer.batch(64)?;
+///     // Update the policy
+/// }
+/// buffer.clear();
+/// ```
+pub struct RolloutBuffer<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    /// Storage of the transitions.
+    buffer: SimpleReplayBuffer<O, A>,
+
+    /// Number of transitions in a rollout.
+    capacity: usize,
+
+    /// Discount factor.
+    gamma: f32,
+
+    /// Parameter of GAE.
+    gae_lambda: f32,
+
+    /// Number of sub-environments, detected from the first pushed batch.
+    n_envs: usize,
+
+    /// Values set with [`RolloutBuffer::set_values`].
+    values: Option<RolloutValues>,
+
+    /// Shuffled indices of transitions for sampling minibatches.
+    perm: Vec<usize>,
+
+    /// Position of the next minibatch in `perm`.
+    pos: usize,
+
+    /// Random number generator for shuffling.
+    rng: ChaCha12Rng,
+}
+
+impl<O, A> RolloutBuffer<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    /// Returns all the transitions in the buffer, in the order they were pushed.
+    pub fn transitions(&self) -> GenericTransitionBatch<O, A> {
+        let ixs = (0..self.buffer.len()).collect::<Vec<_>>();
+        self.buffer.sample_transitions(ixs, None)
+    }
+
+    /// Sets the log-probabilities of the actions and the values of the observations and
+    /// the next observations of all the transitions, then estimates the advantages.
+    ///
+    /// The values are given in the order of [`RolloutBuffer::transitions`]. The advantage
+    /// of each sub-environment is estimated backward in time as
+    /// `A_t = delta_t + gamma * gae_lambda * A_{t+1}`, where
+    /// `delta_t = r_t + gamma * V(s_{t+1}) - V(s_t)`. The value of the next observation is
+    /// ignored at terminated transitions and the recursion is cut at the end of episodes,
+    /// including truncated ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the length of any of the vectors is different from the number
+    /// of transitions in the buffer.
+    pub fn set_values(
+        &mut self,
+        logp: Vec<f32>,
+        value: Vec<f32>,
+        next_value: Vec<f32>,
+    ) -> Result<()> {
+        let len = self.buffer.len();
+        for (name, v) in [
+            ("logp", &logp),
+            ("value", &value),
+            ("next_value", &next_value),
+        ] {
+            if v.len() != len {
+                return Err(anyhow!(
+                    "{} has {} elements, but the buffer has {} transitions",
+                    name,
+                    v.len(),
+                    len
+                ));
+            }
+        }
+
+        let reward = self.buffer.rewards();
+        let (is_terminated, is_truncated) = self.buffer.done_flags();
+        let mut advantage = vec![0f32; len];
+        for e in 0..self.n_envs {
+            let mut adv = 0f32;
+            for ix in (e..len).step_by(self.n_envs).rev() {
+                let not_terminated = 1.0 - is_terminated[ix] as f32;
+                let not_done = 1.0 - (is_terminated[ix] | is_truncated[ix]) as f32;
+                let delta = reward[ix] + self.gamma * next_value[ix] * not_terminated - value[ix];
+                adv = delta + self.gamma * self.gae_lambda * not_done * adv;
+                advantage[ix] = adv;
+            }
+        }
+        let ret = advantage
+            .iter()
+            .zip(value.iter())
+            .map(|(a, v)| a + v)
+            .collect();
+
+        self.values = Some(RolloutValues {
+            logp,
+            value,
+            advantage,
+            ret,
+        });
+        self.perm.clear();
+
+        Ok(())
+    }
+
+    /// Removes all the transitions and values from the buffer.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.values = None;
+        self.perm.clear();
+    }
+
+    /// Shuffles the indices of the transitions.
+    fn shuffle(&mut self) {
+        let len = self.buffer.len();
+        self.perm = (0..len).collect();
+        for i in (1..len).rev() {
+            let j = (self.rng.next_u64() as usize) % (i + 1);
+            self.perm.swap(i, j);
+        }
+        self.pos = 0;
+    }
+}
+
+impl<O, A> ExperienceBufferBase for RolloutBuffer<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    type Item = GenericTransitionBatch<O, A>;
+
+    /// Returns the current number of transitions in the buffer.
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Adds transitions of sub-environments to the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The number of transitions is different from that of the previously pushed batches
+    /// - The capacity is not a multiple of the number of sub-environments
+    /// - The buffer is full, i.e., it has not been cleared after the last update
+    fn push(&mut self, tr: Self::Item) -> Result<()> {
+        let len = tr.len();
+        if len == 0 {
+            return Ok(());
+        }
+        if self.n_envs == 0 {
+            if self.capacity.checked_rem(len) != Some(0) {
+                return Err(anyhow!(
+                    "Capacity {} is not a multiple of the number of sub-environments {}",
+                    self.capacity,
+                    len
+                ));
+            }
+            self.n_envs = len;
+        } else if self.n_envs != len {
+            return Err(anyhow!(
+                "Expected {} transitions, one for each sub-environment, but got {}",
+                self.n_envs,
+                len
+            ));
+        }
+        if self.buffer.len() + len > self.capacity {
+            return Err(anyhow!(
+                "Rollout buffer is full; it should be cleared after each update"
+            ));
+        }
+
+        self.buffer.push(tr)
+    }
+}
+
+impl<O, A> ReplayBufferBase for RolloutBuffer<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    type Config = RolloutBufferConfig;
+    type Batch = RolloutBatch<O, A>;
+
+    /// Creates a new rollout buffer with the given configuration.
+    fn build(config: &Self::Config) -> Self {
+        let buffer_config = SimpleReplayBufferConfig::default()
+            .capacity(config.capacity)
+            .seed(config.seed);
+
+        Self {
+            buffer: SimpleReplayBuffer::build(&buffer_config),
+            capacity: config.capacity,
+            gamma: config.gamma,
+            gae_lambda: config.gae_lambda,
+            n_envs: 0,
+            values: None,
+            perm: vec![],
+            pos: 0,
+            rng: ChaCha12Rng::seed_from_u64(config.seed),
+        }
+    }
+
+    /// Samples a minibatch of `size` transitions without replacement.
+    ///
+    /// When the remaining transitions are fewer than `size`, the transitions are
+    /// reshuffled and sampling starts over.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The values have not been set with [`RolloutBuffer::set_values`]
+    /// - `size` is larger than the number of transitions in the buffer
+    fn batch(&mut self, size: usize) -> Result<Self::Batch> {
+        if self.values.is_none() {
+            return Err(anyhow!(
+                "Values should be set with set_values() before sampling minibatches"
+            ));
+        }
+        if size > self.buffer.len() {
+            return Err(anyhow!(
+                "Batch size {} is larger than the number of transitions {}",
+                size,
+                self.buffer.len()
+            ));
+        }
+        if self.perm.is_empty() || self.pos + size > self.perm.len() {
+            self.shuffle();
+        }
+
+        let ixs = self.perm[self.pos..self.pos + size].to_vec();
+        self.pos += size;
+        let values = self.values.as_ref().unwrap();
+        let sample = |v: &[f32]| ixs.iter().map(|&ix| v[ix]).collect::<Vec<_>>();
+        let (logp, value, advantage, ret) = (
+            sample(&values.logp),
+            sample(&values.value),
+            sample(&values.advantage),
+            sample(&values.ret),
+        );
+        let (obs, act, _, _, _, _, _, _) = self.buffer.sample_transitions(ixs, None).unpack();
+
+        Ok(RolloutBatch {
+            obs,
+            act,
+            logp,
+            value,
+            advantage,
+            ret,
+        })
+    }
+
+    /// Does nothing, as prioritized sampling is not supported.
+    fn update_priority(&mut self, _ixs: &Option<Vec<usize>>, _td_errs: &Option<Vec<f32>>) {}
+
+    /// Returns `true` if the rollout has `capacity` transitions.
+    fn is_ready(&self) -> bool {
+        self.buffer.len() == self.capacity
+    }
+
+    /// Saves the transitions in the buffer into the directory `path`.
+    ///
+    /// In addition to the files saved by [`SimpleReplayBuffer`], the directory will
+    /// contain `rollout.yaml`, which has the number of sub-environments and the RNG state.
+    /// The values set with [`RolloutBuffer::set_values`] are not saved, as
+    /// [`Trainer`](crate::Trainer) saves checkpoints after optimization steps, when
+    /// the buffer has been cleared.
+    fn save(&self, path: &Path) -> Result<()> {
+        self.buffer.save(path)?;
+
+        let meta = RolloutBufferMeta {
+            n_envs: self.n_envs,
+            rng_seed: self.rng.get_seed(),
+            rng_word_pos: self.rng.get_word_pos() as u64,
+        };
+        let mut file = File::create(path.join("rollout.yaml"))?;
+        file.write_all(serde_yaml::to_string(&meta)?.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Loads the transitions saved by [`ReplayBufferBase::save`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the capacity of this buffer is different from that of
+    /// the saved one.
+    fn load(&mut self, path: &Path) -> Result<()> {
+        self.buffer.load(path)?;
+
+        let file = File::open(path.join("rollout.yaml"))?;
+        let meta: RolloutBufferMeta = serde_yaml::from_reader(BufReader::new(file))?;
+        self.n_envs = meta.n_envs;
+        self.values = None;
+        self.perm.clear();
+        self.rng = ChaCha12Rng::from_seed(meta.rng_seed);
+        self.rng.set_word_pos(meta.rng_word_pos as u128);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{TestActBatch, TestObsBatch};
+
+    type Buffer = RolloutBuffer<TestObsBatch, TestActBatch>;
+
+    fn transitions(
+        obs: Vec<usize>,
+        is_terminated: Vec<i8>,
+        is_truncated: Vec<i8>,
+    ) -> GenericTransitionBatch<TestObsBatch, TestActBatch> {
+        GenericTransitionBatch {
+            obs: TestObsBatch { obs: obs.clone() },
+            act: TestActBatch { act: obs.clone() },
+            next_obs: TestObsBatch { obs },
+            reward: vec![1.0; is_terminated.len()],
+            is_terminated,
+            is_truncated,
+            n_step: None,
+            ix_sample: None,
+            weight: None,
+        }
+    }
+
+    #[test]
+    fn test_rollout_buffer() -> Result<()> {
+        let config = RolloutBufferConfig::default()
+            .capacity(6)
+            .gamma(0.5)
+            .gae_lambda(1.0);
+        let mut buffer = Buffer::build(&config);
+
+        // Two sub-environments; the first one terminates at t = 1, the second one is
+        // truncated at t = 1
+        buffer.push(transitions(vec![0, 1], vec![0, 0], vec![0, 0]))?;
+        buffer.push(transitions(vec![2, 3], vec![1, 0], vec![0, 1]))?;
+        assert!(!buffer.is_ready());
+        buffer.push(transitions(vec![4, 5], vec![0, 0], vec![0, 0]))?;
+        assert!(buffer.is_ready());
+        assert!(buffer
+            .push(transitions(vec![6, 7], vec![0, 0], vec![0, 0]))
+            .is_err());
+        assert!(buffer.batch(2).is_err());
+
+        buffer.set_values(vec![0.0; 6], vec![0.0; 6], vec![2.0; 6])?;
+        let values = buffer.values.as_ref().unwrap();
+        // env 0: A_2 = 1 + 0.5 * 2 = 2, A_1 = 1 (terminated), A_0 = 2 + 0.5 * 1 = 2.5
+        // env 1: A_2 = 2, A_1 = 1 + 0.5 * 2 = 2 (truncated), A_0 = 2 + 0.5 * 2 = 3
+        assert_eq!(values.advantage, vec![2.5, 3.0, 1.0, 2.0, 2.0, 2.0]);
+
+        // Minibatches cover all transitions in an epoch
+        let mut ret = (0..3)
+            .flat_map(|_| buffer.batch(2).unwrap().ret)
+            .collect::<Vec<_>>();
+        ret.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(ret, vec![1.0, 2.0, 2.0, 2.0, 2.5, 3.0]);
+
+        buffer.clear();
+        assert_eq!(buffer.len(), 0);
+        assert!(buffer.batch(2).is_err());
+
+        Ok(())
+    }
+}
@@ -3,16 +3,18 @@
 //! This module provides a generic implementation of the `StepProcessor` trait,
 //! which handles the conversion of environment steps into transitions suitable
 //! for training. It supports:
-//! - 1-step TD backup for non-vectorized environments
+//! - 1-step TD backup for non-vectorized and vectorized environments
+//! - n-step TD backup with [`NStepStepProcessor`]
 //! - Generic observation and action types
 //! - Efficient batch processing
 
 use super::{BatchBase, GenericTransitionBatch};
 use crate::{Env, Obs, StepProcessor};
-use std::{default::Default, marker::PhantomData};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, default::Default, marker::PhantomData};
 
 /// Configuration for the simple step processor.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct SimpleStepProcessorConfig {}
 
 impl Default for SimpleStepProcessorConfig {
@@ -25,9 +27,13 @@ impl Default for SimpleStepProcessorConfig {
 /// A generic implementation of the `StepProcessor` trait.
 ///
 /// This processor converts environment steps into transitions suitable for
-/// training reinforcement learning agents. It supports 1-step TD backup
-/// for non-vectorized environments, meaning that each step contains exactly
-/// one observation.
+/// training reinforcement learning agents. It supports 1-step TD backup.
+///
+/// For vectorized environments, a step contains the observations of all
+/// sub-environments and the produced [`GenericTransitionBatch`] holds one
+/// transition per sub-environment. In this case, `O` and `A` must be able to
+/// hold multiple samples, which is the case for batches that push data along
+/// the first axis.
 ///
 /// # Type Parameters
 ///
@@ -97,16 +103,16 @@ where
     /// # Panics
     ///
     /// This method will panic if:
-    /// - The step contains more than one observation
+    /// - The number of observations does not match the number of rewards
     /// - `reset()` has not been called before processing steps
     /// - The step is terminal but does not contain an initial observation
     fn process(&mut self, step: crate::Step<E>) -> Self::Output {
-        assert_eq!(step.obs.len(), 1);
+        assert_eq!(step.obs.len(), step.n_envs());
 
-        let batch = if self.prev_obs.is_none() {
+        if self.prev_obs.is_none() {
             panic!("prev_obs is not set. Forgot to call reset()?");
         } else {
-            let is_done = step.is_done();
+            let is_done = step.is_any_done();
             let next_obs = step.obs.clone().into();
             let obs = self.prev_obs.replace(step.obs.into()).unwrap();
             let act = step.act.into();
@@ -128,11 +134,271 @@ where
                 reward,
                 is_terminated,
                 is_truncated,
+                n_step: None,
                 ix_sample,
                 weight,
             }
+        }
+    }
+}
+
+/// Configuration for [`NStepStepProcessor`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct NStepStepProcessorConfig {
+    /// Maximum number of steps of a transition.
+    pub n_step: usize,
+
+    /// Discount factor used to accumulate rewards.
+    pub gamma: f32,
+}
+
+impl Default for NStepStepProcessorConfig {
+    /// Creates a new default configuration with `n_step = 3` and `gamma = 0.99`.
+    fn default() -> Self {
+        Self {
+            n_step: 3,
+            gamma: 0.99,
+        }
+    }
+}
+
+impl NStepStepProcessorConfig {
+    /// Sets the maximum number of steps of a transition.
+    pub fn n_step(mut self, n_step: usize) -> Self {
+        self.n_step = n_step;
+        self
+    }
+
+    /// Sets the discount factor used to accumulate rewards.
+    pub fn gamma(mut self, gamma: f32) -> Self {
+        self.gamma = gamma;
+        self
+    }
+}
+
+/// A step processor producing n-step transitions.
+///
+/// For each sub-environment, this processor keeps the last `n_step` observations,
+/// actions and rewards, and emits a transition `(o_t, a_t, o_t+n, r_t)` once `n_step`
+/// steps have been taken from `o_t`, where `r_t` is the discounted sum of the `n_step`
+/// rewards. When an episode ends, by termination or truncation, all of the pending
+/// transitions of the sub-environment are emitted with fewer steps, taking the last
+/// observation of the episode as `o_t+n` and the flags of the last step.
+///
+/// The number of steps n of each transition is set to
+/// [`GenericTransitionBatch::n_step`], so that agents can discount the bootstrapped
+/// value with `gamma^n`. The `gamma` in the configuration should be the same as
+/// that of the agent.
+///
+/// The number of transitions produced by a step varies from zero to the number of
+/// sub-environments times `n_step`. Since transitions are collected per sample,
+/// `O` and `A` must support [`BatchBase::sample`] with a single index on batches
+/// converted from observations and actions.
+///
+/// # Type Parameters
+///
+/// * `E` - The environment type, must implement `Env`
+/// * `O` - The observation batch type, must implement `BatchBase` and `From<E::Obs>`
+/// * `A` - The action batch type, must implement `BatchBase` and `From<E::Act>`
+pub struct NStepStepProcessor<E, O, A> {
+    /// Maximum number of steps of a transition.
+    n_step: usize,
+
+    /// Discount factor.
+    gamma: f32,
+
+    /// The previous observation, used to construct transitions.
+    prev_obs: Option<O>,
+
+    /// Observations, actions and rewards of pending transitions of each sub-environment.
+    queues: Vec<VecDeque<(O, A, f32)>>,
+
+    /// Phantom data to hold the generic type parameters.
+    phantom: PhantomData<E>,
+}
+
+impl<E, O, A> NStepStepProcessor<E, O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    /// Removes the oldest pending transition of the `ix`-th sub-environment.
+    ///
+    /// Returns the observation, the action, the discounted sum of rewards and
+    /// the number of steps of the transition.
+    fn pop_transition(&mut self, ix: usize) -> (O, A, f32, usize) {
+        let queue = &mut self.queues[ix];
+        let n = queue.len();
+        let mut discount = 1.0;
+        let mut reward = 0.0;
+        for (_, _, r) in queue.iter() {
+            reward += discount * r;
+            discount *= self.gamma;
+        }
+        let (obs, act, _) = queue.pop_front().unwrap();
+        (obs, act, reward, n)
+    }
+}
+
+impl<E, O, A> StepProcessor<E> for NStepStepProcessor<E, O, A>
+where
+    E: Env,
+    O: BatchBase + From<E::Obs>,
+    A: BatchBase + From<E::Act>,
+{
+    type Config = NStepStepProcessorConfig;
+    type Output = GenericTransitionBatch<O, A>;
+
+    /// Creates a new step processor with the given configuration.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `n_step` is zero.
+    fn build(config: &Self::Config) -> Self {
+        assert!(config.n_step > 0, "n_step must be positive");
+        Self {
+            n_step: config.n_step,
+            gamma: config.gamma,
+            prev_obs: None,
+            queues: vec![],
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the observation from which the next transitions start.
+    ///
+    /// Pending transitions are kept, because this method is also called when some of
+    /// the sub-environments of a vectorized environment have been reset, while the
+    /// pending transitions of the finished episodes have already been emitted
+    /// in [`NStepStepProcessor::process`].
+    fn reset(&mut self, init_obs: E::Obs) {
+        self.prev_obs = Some(init_obs.into());
+    }
+
+    /// Processes a step from the environment into n-step transitions.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if:
+    /// - The number of observations does not match the number of rewards
+    /// - `reset()` has not been called before processing steps
+    /// - The step is terminal but does not contain an initial observation
+    fn process(&mut self, step: crate::Step<E>) -> Self::Output {
+        let n_envs = step.n_envs();
+        assert_eq!(step.obs.len(), n_envs);
+
+        let prev_obs = self
+            .prev_obs
+            .take()
+            .expect("prev_obs is not set. Forgot to call reset()?");
+        if self.queues.len() != n_envs {
+            self.queues = (0..n_envs).map(|_| VecDeque::new()).collect();
+        }
+
+        let is_done = (0..n_envs)
+            .map(|ix| step.is_done_at(ix))
+            .collect::<Vec<_>>();
+        let next_obs: O = step.obs.into();
+        let act: A = step.act.into();
+
+        // Transitions emitted at this step: (obs, act, reward, n, index of sub-environment)
+        let mut transitions = vec![];
+        for (ix, &done) in is_done.iter().enumerate() {
+            let ixs = vec![ix];
+            self.queues[ix].push_back((prev_obs.sample(&ixs), act.sample(&ixs), step.reward[ix]));
+
+            if done {
+                while !self.queues[ix].is_empty() {
+                    let (o, a, r, n) = self.pop_transition(ix);
+                    transitions.push((o, a, r, n, ix));
+                }
+            } else if self.queues[ix].len() == self.n_step {
+                let (o, a, r, n) = self.pop_transition(ix);
+                transitions.push((o, a, r, n, ix));
+            }
+        }
+
+        let len = transitions.len();
+        let mut batch = GenericTransitionBatch::<O, A>::with_capacity(len);
+        let mut n_step = Vec::with_capacity(len);
+        for (i, (o, a, r, n, ix)) in transitions.into_iter().enumerate() {
+            let ixs = vec![ix];
+            let done = is_done[ix];
+            batch.obs.push(i, o);
+            batch.act.push(i, a);
+            batch.next_obs.push(i, next_obs.sample(&ixs));
+            batch.reward.push(r);
+            batch
+                .is_terminated
+                .push(if done { step.is_terminated[ix] } else { 0 });
+            batch
+                .is_truncated
+                .push(if done { step.is_truncated[ix] } else { 0 });
+            n_step.push(n);
+        }
+        batch.n_step = Some(n_step);
+
+        self.prev_obs = match is_done.iter().any(|&done| done) {
+            true => Some(step.init_obs.expect("Failed to unwrap init_obs").into()),
+            false => Some(next_obs),
         };
 
         batch
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test::{TestAct, TestActBatch, TestEnv, TestInfo, TestObs, TestObsBatch},
+        Step,
+    };
+
+    type Processor = NStepStepProcessor<TestEnv, TestObsBatch, TestActBatch>;
+
+    fn step(obs: usize, reward: f32, is_terminated: i8) -> Step<TestEnv> {
+        Step::new(
+            TestObs { obs },
+            TestAct { act: obs },
+            vec![reward],
+            vec![is_terminated],
+            vec![0],
+            TestInfo {},
+            Some(TestObs { obs: 0 }),
+        )
+    }
+
+    #[test]
+    fn test_n_step_step_processor() {
+        let config = NStepStepProcessorConfig::default().n_step(3).gamma(0.5);
+        let mut processor = Processor::build(&config);
+        processor.reset(TestObs { obs: 0 });
+
+        assert_eq!(processor.process(step(1, 1.0, 0)).reward.len(), 0);
+        assert_eq!(processor.process(step(2, 2.0, 0)).reward.len(), 0);
+
+        let batch = processor.process(step(3, 3.0, 0));
+        assert_eq!(batch.obs.obs, vec![0]);
+        assert_eq!(batch.next_obs.obs, vec![3]);
+        assert_eq!(batch.reward, vec![2.75]);
+        assert_eq!(batch.is_terminated, vec![0]);
+        assert_eq!(batch.n_step, Some(vec![3]));
+
+        // All of the pending transitions are emitted at the end of the episode
+        let batch = processor.process(step(4, 4.0, 1));
+        assert_eq!(batch.obs.obs, vec![1, 2, 3]);
+        assert_eq!(batch.act.act, vec![2, 3, 4]);
+        assert_eq!(batch.next_obs.obs, vec![4, 4, 4]);
+        assert_eq!(batch.reward, vec![4.5, 5.0, 4.0]);
+        assert_eq!(batch.is_terminated, vec![1, 1, 1]);
+        assert_eq!(batch.n_step, Some(vec![3, 2, 1]));
+
+        // The next episode starts from the initial observation
+        processor.reset(TestObs { obs: 0 });
+        processor.process(step(1, 1.0, 0));
+        processor.process(step(2, 1.0, 0));
+        let batch = processor.process(step(3, 1.0, 0));
+        assert_eq!(batch.obs.obs, vec![0]);
+    }
+}
@@ -78,6 +78,11 @@
 //! `E::Act` into their respective [`BatchBase`] types and generates [`GenericTransitionBatch`]. This conversion
 //! relies on the trait bounds `O: From<E::Obs>` and `A: From<E::Act>`.
 //!
+//! [`NStepStepProcessor<E, O, A>`] is a drop-in replacement of [`SimpleStepProcessor<E, O, A>`] for
+//! n-step TD backup. It emits transitions `(o_t, a_t, o_t+n, r_t)`, where `r_t` is the discounted sum of
+//! n rewards, and sets the number of steps n of each transition, which is obtained via
+//! [`TransitionBatch::n_step`] to discount the bootstrapped value with `gamma^n`.
+//!
 //! # Trainer
 //!
 //! The [`Trainer`] manages the training loop and related objects. A [`Trainer`] instance is configured with
@@ -86,6 +91,34 @@
 //! During the training loop, the agent interacts with the environment to collect samples and perform optimization
 //! steps, while simultaneously recording various metrics.
 //!
+//! # Vectorized Environment
+//!
+//! [`VecEnv<E>`] and [`ThreadedVecEnv<E>`] run multiple instances of an environment `E` as a single
+//! environment, which also implements [`Env`]. Their observations and actions hold the data of all
+//! sub-environments, and each call of [`Env::step_with_reset()`] resets only the finished sub-environments.
+//! With [`SimpleStepProcessor`], a step of a vectorized environment is converted into one transition per
+//! sub-environment.
+//!
+//! # Environment Wrappers
+//!
+//! [`EnvWrapper`] wraps an environment and implements [`Env`] itself, so preprocessing can be composed
+//! by nesting wrappers, e.g., `TimeLimit<FrameStack<E>>`. The [`wrappers`] module provides stock wrappers:
+//! [`TimeLimit`], [`FrameStack`], [`ActionRepeat`], [`RewardScale`], [`RewardClip`], [`EpisodicLife`]
+//! and [`RecordEpisodeStatistics`].
+//!
+//! # Schedule
+//!
+//! [`Schedule`] gives the value of a hyperparameter as a function of steps, e.g., constant,
+//! linear, cosine, step decay, warmup or piecewise linear. Agents use it to change learning rates
+//! and other hyperparameters by optimization step.
+//!
+//! # Normalization
+//!
+//! [`NormalizeObs`] and [`NormalizeReward`] normalize observations and rewards with running
+//! statistics, which are shared by clones. Environments apply them to their observations and
+//! rewards. [`NormalizedAgent`] wraps an agent to freeze the statistics during evaluation and to
+//! save and load them alongside the parameters of the agent.
+//!
 //! # Evaluator
 //!
 //! The [`Evaluator<E, P>`] trait is used to evaluate a policy's (`P`) performance in an environment (`E`).
@@ -100,11 +133,26 @@
 //! [`GenericTransitionBatch`]: generic_replay_buffer::GenericTransitionBatch
 //! [`SimpleStepProcessor`]: generic_replay_buffer::SimpleStepProcessor
 //! [`SimpleStepProcessor<E, O, A>`]: generic_replay_buffer::SimpleStepProcessor
+//! [`NStepStepProcessor<E, O, A>`]: generic_replay_buffer::NStepStepProcessor
+//! [`VecEnv<E>`]: vec_env::VecEnv
+//! [`ThreadedVecEnv<E>`]: vec_env::ThreadedVecEnv
+//! [`EnvWrapper`]: wrappers::EnvWrapper
+//! [`TimeLimit`]: wrappers::TimeLimit
+//! [`FrameStack`]: wrappers::FrameStack
+//! [`ActionRepeat`]: wrappers::ActionRepeat
+//! [`RewardScale`]: wrappers::RewardScale
+//! [`RewardClip`]: wrappers::RewardClip
+//! [`EpisodicLife`]: wrappers::EpisodicLife
+//! [`RecordEpisodeStatistics`]: wrappers::RecordEpisodeStatistics
 pub mod dummy;
 pub mod error;
 mod evaluator;
 pub mod generic_replay_buffer;
+mod normalize;
 pub mod record;
+mod schedule;
+pub mod vec_env;
+pub mod wrappers;
 
 mod base;
 pub use base::{
@@ -112,9 +160,15 @@ pub use base::{
     ReplayBufferBase, Step, StepProcessor, TransitionBatch,
 };
 
+pub use normalize::{NormalizeObs, NormalizeReward, NormalizedAgent, RunningMeanStd};
+pub use schedule::Schedule;
+
 mod trainer;
-pub use evaluator::{DefaultEvaluator, Evaluator};
-pub use trainer::{Sampler, Trainer, TrainerConfig};
+pub use evaluator::{DefaultEvaluator, EvalMetric, Evaluator, ParallelEvaluator, StatsEvaluator};
+pub use trainer::{
+    CallbackAction, CallbackContext, Sampler, SaveKind, StopReason, Trainer, TrainerCallback,
+    TrainerConfig, TrainerCore,
+};
 
 // TODO: Consider to compile this module only for tests.
 /// Agent and Env for testing.
@@ -124,7 +178,7 @@ pub mod test {
     /// Obs for testing.
     #[derive(Clone, Debug)]
     pub struct TestObs {
-        obs: usize,
+        pub(crate) obs: usize,
     }
 
     impl crate::Obs for TestObs {
@@ -135,7 +189,7 @@ pub mod test {
 
     /// Batch of obs for testing.
     pub struct TestObsBatch {
-        obs: Vec<usize>,
+        pub(crate) obs: Vec<usize>,
     }
 
     impl crate::generic_replay_buffer::BatchBase for TestObsBatch {
@@ -153,6 +207,23 @@ pub mod test {
             let obs = ixs.iter().map(|ix| self.obs[*ix]).collect();
             Self { obs }
         }
+
+        fn save(&self, path: &std::path::Path, len: usize) -> anyhow::Result<()> {
+            let data = self.obs[..len]
+                .iter()
+                .map(|&x| x as i64)
+                .collect::<Vec<_>>();
+            crate::generic_replay_buffer::npy::write_npy(path.join("obs.npy"), &[len], &data)
+        }
+
+        fn load(&mut self, path: &std::path::Path) -> anyhow::Result<usize> {
+            let (data, _) =
+                crate::generic_replay_buffer::npy::read_npy::<i64>(path.join("obs.npy"))?;
+            for (i, x) in data.iter().enumerate() {
+                self.obs[i] = *x as usize;
+            }
+            Ok(data.len())
+        }
     }
 
     impl From<TestObs> for TestObsBatch {
@@ -164,14 +235,14 @@ pub mod test {
     /// Act for testing.
     #[derive(Clone, Debug)]
     pub struct TestAct {
-        act: usize,
+        pub(crate) act: usize,
     }
 
     impl crate::Act for TestAct {}
 
     /// Batch of act for testing.
     pub struct TestActBatch {
-        act: Vec<usize>,
+        pub(crate) act: Vec<usize>,
     }
 
     impl From<TestAct> for TestActBatch {
@@ -195,6 +266,23 @@ pub mod test {
             let act = ixs.iter().map(|ix| self.act[*ix]).collect();
             Self { act }
         }
+
+        fn save(&self, path: &std::path::Path, len: usize) -> anyhow::Result<()> {
+            let data = self.act[..len]
+                .iter()
+                .map(|&x| x as i64)
+                .collect::<Vec<_>>();
+            crate::generic_replay_buffer::npy::write_npy(path.join("act.npy"), &[len], &data)
+        }
+
+        fn load(&mut self, path: &std::path::Path) -> anyhow::Result<usize> {
+            let (data, _) =
+                crate::generic_replay_buffer::npy::read_npy::<i64>(path.join("act.npy"))?;
+            for (i, x) in data.iter().enumerate() {
+                self.act[i] = *x as usize;
+            }
+            Ok(data.len())
+        }
     }
 
     /// Info for testing.
@@ -0,0 +1,663 @@
+//! Normalization of observations and rewards with running statistics.
+//!
+//! [`NormalizeObs`] and [`NormalizeReward`] keep running statistics of observations and
+//! discounted returns, respectively, and normalize values with them. They work on slices of
+//! `f32`, so environments can apply them to observations of any array type.
+//!
+//! Clones of [`NormalizeObs`] and [`NormalizeReward`] share their statistics. When a clone is
+//! given to the configuration of an environment, the environments for training and evaluation,
+//! both built from the configuration, see the same statistics. [`NormalizedAgent`] freezes the
+//! statistics in evaluation mode and saves them alongside the parameters of the agent.
+//!
+//! When serialized, e.g., as a part of the configuration of an environment sent to another
+//! process, the normalizers carry a snapshot of their statistics. A deserialized normalizer
+//! starts from the snapshot, but does not share the statistics with the original one.
+use crate::{record::Record, Agent, Env, Policy, ReplayBufferBase};
+use anyhow::Result;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// Running mean and variance of vectors.
+///
+/// Statistics are updated with batches of samples by the parallel variant of Welford's algorithm,
+/// which is reduced to Welford's algorithm for a single sample.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct RunningMeanStd {
+    /// The number of samples.
+    pub count: f64,
+
+    /// Mean.
+    pub mean: Vec<f64>,
+
+    /// Variance.
+    pub var: Vec<f64>,
+}
+
+impl RunningMeanStd {
+    /// Creates statistics of vectors of the given dimension.
+    pub fn new(dim: usize) -> Self {
+        Self {
+            count: 0.0,
+            mean: vec![0.0; dim],
+            var: vec![1.0; dim],
+        }
+    }
+
+    /// Returns the dimension of vectors.
+    pub fn dim(&self) -> usize {
+        self.mean.len()
+    }
+
+    /// Updates the statistics with samples.
+    ///
+    /// `xs` is the concatenation of samples, so its length must be a multiple of [`Self::dim()`].
+    pub fn update(&mut self, xs: &[f32]) {
+        let dim = self.dim();
+        assert!(dim > 0, "Dimension of samples must be positive");
+        let n = xs.len() / dim;
+        assert!(
+            n * dim == xs.len(),
+            "Length of samples ({}) is not a multiple of the dimension ({})",
+            xs.len(),
+            dim
+        );
+        if n == 0 {
+            return;
+        }
+        let n = n as f64;
+        let total = self.count + n;
+
+        for i in 0..dim {
+            let batch_mean = xs
+                .iter()
+                .skip(i)
+                .step_by(dim)
+                .map(|x| *x as f64)
+                .sum::<f64>()
+                / n;
+            let batch_var = xs
+                .iter()
+                .skip(i)
+                .step_by(dim)
+                .map(|x| (*x as f64 - batch_mean).powi(2))
+                .sum::<f64>()
+                / n;
+            let delta = batch_mean - self.mean[i];
+            let m2 =
+                self.var[i] * self.count + batch_var * n + delta.powi(2) * self.count * n / total;
+            self.mean[i] += delta * n / total;
+            self.var[i] = m2 / total;
+        }
+        self.count = total;
+    }
+
+    /// Normalizes samples in place, then clips them to `[-clip, clip]`.
+    pub fn normalize(&self, xs: &mut [f32], clip: f64, eps: f64) {
+        let dim = self.dim();
+        for (ix, x) in xs.iter_mut().enumerate() {
+            let i = ix % dim;
+            let y = (*x as f64 - self.mean[i]) / (self.var[i] + eps).sqrt();
+            *x = y.clamp(-clip, clip) as f32;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct NormalizeObsState {
+    stats: Option<RunningMeanStd>,
+    frozen: bool,
+}
+
+/// Parameters and statistics of [`NormalizeObs`], used for its (de)serialization.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct NormalizeObsParams {
+    clip: f64,
+    eps: f64,
+    #[serde(default)]
+    stats: Option<RunningMeanStd>,
+}
+
+/// Normalizes observations with their running mean and variance.
+///
+/// The statistics are shared by clones and created at the first observation, of which the
+/// dimension is given by environments. Observations are clipped to `[-clip, clip]` after
+/// normalization. When serialized, e.g., as a part of the configuration of an environment,
+/// the parameters and a snapshot of the statistics are saved, but not whether they are frozen.
+/// Use [`NormalizeObs::save()`] to save only the statistics.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(from = "NormalizeObsParams", into = "NormalizeObsParams")]
+pub struct NormalizeObs {
+    clip: f64,
+    eps: f64,
+    state: Arc<Mutex<NormalizeObsState>>,
+}
+
+impl From<NormalizeObsParams> for NormalizeObs {
+    fn from(params: NormalizeObsParams) -> Self {
+        let normalize_obs = Self::default().clip(params.clip).eps(params.eps);
+        normalize_obs.state.lock().unwrap().stats = params.stats;
+        normalize_obs
+    }
+}
+
+impl From<NormalizeObs> for NormalizeObsParams {
+    fn from(v: NormalizeObs) -> Self {
+        Self {
+            clip: v.clip,
+            eps: v.eps,
+            stats: v.stats(),
+        }
+    }
+}
+
+impl Default for NormalizeObs {
+    fn default() -> Self {
+        Self {
+            clip: 10.0,
+            eps: 1e-8,
+            state: Arc::new(Mutex::new(NormalizeObsState::default())),
+        }
+    }
+}
+
+impl NormalizeObs {
+    /// Sets the threshold of clipping normalized observations.
+    pub fn clip(mut self, v: f64) -> Self {
+        self.clip = v;
+        self
+    }
+
+    /// Sets the constant added to the variance for numerical stability.
+    pub fn eps(mut self, v: f64) -> Self {
+        self.eps = v;
+        self
+    }
+
+    /// Updates the statistics with observations unless frozen, then normalizes them in place.
+    ///
+    /// `obs` is the concatenation of observations, each of which has `dim` elements.
+    pub fn apply(&self, obs: &mut [f32], dim: usize) {
+        let mut state = self.state.lock().unwrap();
+        let frozen = state.frozen;
+        let stats = state.stats.get_or_insert_with(|| RunningMeanStd::new(dim));
+        if !frozen {
+            stats.update(obs);
+        }
+        stats.normalize(obs, self.clip, self.eps);
+    }
+
+    /// Updates the statistics with observations, even if frozen.
+    ///
+    /// This method is useful to compute the statistics of a dataset for offline training.
+    pub fn update(&self, obs: &[f32], dim: usize) {
+        let mut state = self.state.lock().unwrap();
+        let stats = state.stats.get_or_insert_with(|| RunningMeanStd::new(dim));
+        stats.update(obs);
+    }
+
+    /// Normalizes observations in place without updating the statistics.
+    ///
+    /// Observations are not changed if no statistics have been collected.
+    pub fn normalize(&self, obs: &mut [f32]) {
+        if let Some(stats) = &self.state.lock().unwrap().stats {
+            stats.normalize(obs, self.clip, self.eps);
+        }
+    }
+
+    /// Freezes or unfreezes the statistics.
+    pub fn set_frozen(&self, v: bool) {
+        self.state.lock().unwrap().frozen = v;
+    }
+
+    /// Returns `true` if the statistics are frozen.
+    pub fn is_frozen(&self) -> bool {
+        self.state.lock().unwrap().frozen
+    }
+
+    /// Returns a copy of the statistics.
+    pub fn stats(&self) -> Option<RunningMeanStd> {
+        self.state.lock().unwrap().stats.clone()
+    }
+
+    /// Saves the statistics as a YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = File::create(&path)?;
+        file.write_all(serde_yaml::to_string(&self.stats())?.as_bytes())?;
+        info!("Save statistics of observations to {:?}", path.as_ref());
+        Ok(())
+    }
+
+    /// Loads the statistics from a YAML file.
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<()> {
+        let rdr = BufReader::new(File::open(&path)?);
+        self.state.lock().unwrap().stats = serde_yaml::from_reader(rdr)?;
+        info!("Load statistics of observations from {:?}", path.as_ref());
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct NormalizeRewardState {
+    stats: RunningMeanStd,
+    frozen: bool,
+}
+
+/// Parameters and statistics of [`NormalizeReward`], used for its (de)serialization.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct NormalizeRewardParams {
+    gamma: f64,
+    clip: f64,
+    eps: f64,
+    #[serde(default)]
+    stats: Option<RunningMeanStd>,
+}
+
+/// Scales rewards with the running standard deviation of discounted returns.
+///
+/// Rewards are divided by the standard deviation, without subtracting the mean, then clipped
+/// to `[-clip, clip]`. The statistics are shared by clones, while the discounted returns are
+/// kept by each environment and given to [`NormalizeReward::apply()`]. When serialized, the
+/// parameters and a snapshot of the statistics are saved, but not whether they are frozen.
+/// Use [`NormalizeReward::save()`] to save only the statistics.
+///
+/// Rewards of evaluation environments should not be normalized, so that scores are comparable.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(from = "NormalizeRewardParams", into = "NormalizeRewardParams")]
+pub struct NormalizeReward {
+    gamma: f64,
+    clip: f64,
+    eps: f64,
+    state: Arc<Mutex<NormalizeRewardState>>,
+}
+
+impl From<NormalizeRewardParams> for NormalizeReward {
+    fn from(params: NormalizeRewardParams) -> Self {
+        let normalize_reward = Self::new(params.gamma).clip(params.clip).eps(params.eps);
+        if let Some(stats) = params.stats {
+            normalize_reward.state.lock().unwrap().stats = stats;
+        }
+        normalize_reward
+    }
+}
+
+impl From<NormalizeReward> for NormalizeRewardParams {
+    fn from(v: NormalizeReward) -> Self {
+        Self {
+            gamma: v.gamma,
+            clip: v.clip,
+            eps: v.eps,
+            stats: Some(v.stats()),
+        }
+    }
+}
+
+impl NormalizeReward {
+    /// Creates a reward normalizer with the discount factor of returns.
+    pub fn new(gamma: f64) -> Self {
+        Self {
+            gamma,
+            clip: 10.0,
+            eps: 1e-8,
+            state: Arc::new(Mutex::new(NormalizeRewardState {
+                stats: RunningMeanStd::new(1),
+                frozen: false,
+            })),
+        }
+    }
+
+    /// Sets the threshold of clipping normalized rewards.
+    pub fn clip(mut self, v: f64) -> Self {
+        self.clip = v;
+        self
+    }
+
+    /// Sets the constant added to the variance for numerical stability.
+    pub fn eps(mut self, v: f64) -> Self {
+        self.eps = v;
+        self
+    }
+
+    /// Updates the statistics unless frozen, then normalizes rewards in place.
+    ///
+    /// Each element of `reward` and `is_done` corresponds to a (sub-)environment.
+    /// `returns` has the discounted returns of the (sub-)environments. It is owned by the
+    /// environment calling this method, so that returns of different environments are not mixed.
+    /// The discounted return of an environment is reset when `is_done` is 1.
+    pub fn apply(&self, returns: &mut Vec<f64>, reward: &mut [f32], is_done: &[i8]) {
+        let mut state = self.state.lock().unwrap();
+        returns.resize(reward.len(), 0.0);
+
+        for (ix, r) in reward.iter_mut().enumerate() {
+            returns[ix] = returns[ix] * self.gamma + *r as f64;
+            if !state.frozen {
+                state.stats.update(&[returns[ix] as f32]);
+            }
+            let std = (state.stats.var[0] + self.eps).sqrt();
+            *r = (*r as f64 / std).clamp(-self.clip, self.clip) as f32;
+            if is_done[ix] == 1 {
+                returns[ix] = 0.0;
+            }
+        }
+    }
+
+    /// Freezes or unfreezes the statistics.
+    pub fn set_frozen(&self, v: bool) {
+        self.state.lock().unwrap().frozen = v;
+    }
+
+    /// Returns `true` if the statistics are frozen.
+    pub fn is_frozen(&self) -> bool {
+        self.state.lock().unwrap().frozen
+    }
+
+    /// Returns a copy of the statistics of discounted returns.
+    pub fn stats(&self) -> RunningMeanStd {
+        self.state.lock().unwrap().stats.clone()
+    }
+
+    /// Saves the statistics as a YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = File::create(&path)?;
+        file.write_all(serde_yaml::to_string(&self.stats())?.as_bytes())?;
+        info!("Save statistics of returns to {:?}", path.as_ref());
+        Ok(())
+    }
+
+    /// Loads the statistics from a YAML file.
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<()> {
+        let rdr = BufReader::new(File::open(&path)?);
+        self.state.lock().unwrap().stats = serde_yaml::from_reader(rdr)?;
+        info!("Load statistics of returns from {:?}", path.as_ref());
+        Ok(())
+    }
+}
+
+/// An agent with the statistics of normalization.
+///
+/// This struct wraps an agent and behaves as the agent, except that:
+///
+/// * [`Agent::train()`] unfreezes and [`Agent::eval()`] freezes the statistics, so they are not
+///   updated during evaluation.
+/// * [`Agent::save_params()`] and [`Agent::save_checkpoint()`] save the statistics as
+///   `normalize_obs.yaml` and `normalize_reward.yaml` in the same directory as the parameters
+///   of the agent, and the corresponding methods to load parameters load them.
+///
+/// The normalizers should be clones of those given to the configuration of the environment.
+/// [`Agent::as_any_ref()`] and [`Agent::as_any_mut()`] return this struct itself, so it can be
+/// downcasted to `NormalizedAgent<E, R>`. The wrapped agent is accessed with
+/// [`NormalizedAgent::inner()`] and [`NormalizedAgent::inner_mut()`].
+pub struct NormalizedAgent<E: Env, R: ReplayBufferBase> {
+    agent: Box<dyn Agent<E, R>>,
+    normalize_obs: Option<NormalizeObs>,
+    normalize_reward: Option<NormalizeReward>,
+}
+
+impl<E: Env, R: ReplayBufferBase> NormalizedAgent<E, R> {
+    /// Wraps an agent.
+    pub fn new(agent: Box<dyn Agent<E, R>>) -> Self {
+        Self {
+            agent,
+            normalize_obs: None,
+            normalize_reward: None,
+        }
+    }
+
+    /// Sets the normalizer of observations.
+    pub fn normalize_obs(mut self, v: NormalizeObs) -> Self {
+        self.normalize_obs = Some(v);
+        self
+    }
+
+    /// Sets the normalizer of rewards.
+    pub fn normalize_reward(mut self, v: NormalizeReward) -> Self {
+        self.normalize_reward = Some(v);
+        self
+    }
+
+    /// Returns a reference to the wrapped agent.
+    pub fn inner(&self) -> &dyn Agent<E, R> {
+        self.agent.as_ref()
+    }
+
+    /// Returns a mutable reference to the wrapped agent.
+    pub fn inner_mut(&mut self) -> &mut dyn Agent<E, R> {
+        self.agent.as_mut()
+    }
+
+    fn set_frozen(&self, v: bool) {
+        if let Some(normalize_obs) = &self.normalize_obs {
+            normalize_obs.set_frozen(v);
+        }
+        if let Some(normalize_reward) = &self.normalize_reward {
+            normalize_reward.set_frozen(v);
+        }
+    }
+
+    fn save_stats(&self, path: &Path, mut paths: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+        if let Some(normalize_obs) = &self.normalize_obs {
+            let path = path.join("normalize_obs.yaml");
+            normalize_obs.save(&path)?;
+            paths.push(path);
+        }
+        if let Some(normalize_reward) = &self.normalize_reward {
+            let path = path.join("normalize_reward.yaml");
+            normalize_reward.save(&path)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    fn load_stats(&self, path: &Path) -> Result<()> {
+        if let Some(normalize_obs) = &self.normalize_obs {
+            normalize_obs.load(path.join("normalize_obs.yaml"))?;
+        }
+        if let Some(normalize_reward) = &self.normalize_reward {
+            normalize_reward.load(path.join("normalize_reward.yaml"))?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: Env, R: ReplayBufferBase> Policy<E> for NormalizedAgent<E, R> {
+    fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        self.agent.sample(obs)
+    }
+}
+
+impl<E: Env + 'static, R: ReplayBufferBase + 'static> Agent<E, R> for NormalizedAgent<E, R> {
+    fn train(&mut self) {
+        self.set_frozen(false);
+        self.agent.train();
+    }
+
+    fn eval(&mut self) {
+        self.set_frozen(true);
+        self.agent.eval();
+    }
+
+    fn is_train(&self) -> bool {
+        self.agent.is_train()
+    }
+
+    fn opt(&mut self, buffer: &mut R) {
+        self.agent.opt(buffer);
+    }
+
+    fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        self.agent.opt_with_record(buffer)
+    }
+
+    fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let paths = self.agent.save_params(path)?;
+        self.save_stats(path, paths)
+    }
+
+    fn load_params(&mut self, path: &Path) -> Result<()> {
+        self.agent.load_params(path)?;
+        self.load_stats(path)
+    }
+
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let paths = self.agent.save_checkpoint(path)?;
+        self.save_stats(path, paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.agent.load_checkpoint(path)?;
+        self.load_stats(path)
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.agent.set_learning_rate(lr)
+    }
+
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generic_replay_buffer::SimpleReplayBuffer,
+        test::{TestActBatch, TestAgent, TestEnv, TestObsBatch},
+    };
+    use tempdir::TempDir;
+
+    type ReplayBuffer = SimpleReplayBuffer<TestObsBatch, TestActBatch>;
+
+    #[test]
+    fn test_normalize_obs() -> Result<()> {
+        let xs = [1f32, 10., 2., 20., 3., 30., 4., 40.];
+        let mut stats = RunningMeanStd::new(2);
+        stats.update(&xs[..2]);
+        stats.update(&xs[2..]);
+        assert!((stats.mean[0] - 2.5).abs() < 1e-12);
+        assert!((stats.var[1] - 125.0).abs() < 1e-9);
+
+        let normalize_obs = NormalizeObs::default();
+        let clone = normalize_obs.clone();
+        let mut obs = xs;
+        normalize_obs.apply(&mut obs, 2);
+        assert_eq!(clone.stats().unwrap().count, 4.0);
+
+        // Frozen statistics are not updated
+        clone.set_frozen(true);
+        let mut obs = [2.5f32, 25.];
+        normalize_obs.apply(&mut obs, 2);
+        assert_eq!(obs, [0., 0.]);
+        assert_eq!(clone.stats().unwrap().count, 4.0);
+
+        let dir = TempDir::new("normalize")?;
+        let path = dir.path().join("normalize_obs.yaml");
+        normalize_obs.save(&path)?;
+        let loaded = NormalizeObs::default();
+        loaded.load(&path)?;
+        assert_eq!(loaded.stats(), normalize_obs.stats());
+
+        Ok(())
+    }
+    #[test]
+    fn test_normalize_reward() -> Result<()> {
+        let normalize_reward = NormalizeReward::new(0.5);
+        let clone = normalize_reward.clone();
+
+        // The return of the second environment is reset at the end of its episode
+        let mut returns = vec![];
+        let mut reward = [1f32, 2.];
+        normalize_reward.apply(&mut returns, &mut reward, &[0, 1]);
+        assert_eq!(returns, [1.0, 0.0]);
+        assert_eq!(clone.stats().count, 2.0);
+        assert!((clone.stats().var[0] - 0.25).abs() < 1e-12);
+        // The first reward is scaled with zero variance, then clipped
+        assert_eq!(reward[0], 10.0);
+        assert!((reward[1] - 4.0).abs() < 1e-6);
+
+        // Frozen statistics are not updated
+        clone.set_frozen(true);
+        let mut reward = [1f32, 1.];
+        normalize_reward.apply(&mut returns, &mut reward, &[0, 0]);
+        assert_eq!(returns, [1.5, 1.0]);
+        assert_eq!(clone.stats().count, 2.0);
+        assert!((reward[0] - 2.0).abs() < 1e-6);
+
+        // Returns of another environment are kept separately
+        let mut returns_other = vec![];
+        normalize_reward.apply(&mut returns_other, &mut [1f32], &[0]);
+        assert_eq!(returns_other, [1.0]);
+        assert_eq!(returns, [1.5, 1.0]);
+
+        // Serialized normalizers carry a snapshot of the statistics, which is not shared
+        let yaml = serde_yaml::to_string(&normalize_reward)?;
+        let deserialized: NormalizeReward = serde_yaml::from_str(&yaml)?;
+        assert_eq!(deserialized.stats(), normalize_reward.stats());
+        assert!(!deserialized.is_frozen());
+        deserialized.apply(&mut vec![], &mut [1f32], &[0]);
+        assert_eq!(deserialized.stats().count, 3.0);
+        assert_eq!(normalize_reward.stats().count, 2.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalized_agent() -> Result<()> {
+        let normalize_obs = NormalizeObs::default();
+        normalize_obs.update(&[1., 10., 2., 20.], 2);
+        let normalize_reward = NormalizeReward::new(0.99);
+        normalize_reward.apply(&mut vec![], &mut [1., 3.], &[0, 0]);
+
+        let mut agent = NormalizedAgent::<TestEnv, ReplayBuffer>::new(Box::new(TestAgent {}))
+            .normalize_obs(normalize_obs.clone())
+            .normalize_reward(normalize_reward.clone());
+
+        // Statistics are frozen in evaluation mode
+        agent.eval();
+        assert!(normalize_obs.is_frozen());
+        assert!(normalize_reward.is_frozen());
+        agent.train();
+        assert!(!normalize_obs.is_frozen());
+        assert!(!normalize_reward.is_frozen());
+
+        let dir = TempDir::new("normalized_agent")?;
+        let paths = agent.save_params(dir.path())?;
+        assert!(paths.contains(&dir.path().join("normalize_obs.yaml")));
+        assert!(paths.contains(&dir.path().join("normalize_reward.yaml")));
+
+        let (loaded_obs, loaded_reward) = (NormalizeObs::default(), NormalizeReward::new(0.99));
+        let mut loaded = NormalizedAgent::<TestEnv, ReplayBuffer>::new(Box::new(TestAgent {}))
+            .normalize_obs(loaded_obs.clone())
+            .normalize_reward(loaded_reward.clone());
+        loaded.load_params(dir.path())?;
+        assert_eq!(loaded_obs.stats(), normalize_obs.stats());
+        assert_eq!(loaded_reward.stats(), normalize_reward.stats());
+
+        // Checkpoints also have the statistics
+        let dir = TempDir::new("normalized_agent_checkpoint")?;
+        normalize_obs.update(&[3., 30.], 2);
+        agent.save_checkpoint(dir.path())?;
+        loaded.load_checkpoint(dir.path())?;
+        assert_eq!(loaded_obs.stats().unwrap().count, 3.0);
+
+        // Downcasting gives the normalized agent, not the wrapped one
+        let agent: Box<dyn Agent<TestEnv, ReplayBuffer>> = Box::new(agent);
+        let normalized = agent
+            .as_any_ref()
+            .downcast_ref::<NormalizedAgent<TestEnv, ReplayBuffer>>()
+            .unwrap();
+        assert!(normalized.inner().as_any_ref().is::<TestAgent>());
+        assert!(agent.as_any_ref().downcast_ref::<TestAgent>().is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+//! Schedules of hyperparameters.
+//!
+//! A [`Schedule`] gives the value of a hyperparameter, e.g., a learning rate, the soft update
+//! coefficient of target networks or epsilon of epsilon-greedy exploration, as a function of
+//! the number of steps, typically optimization steps.
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Schedule of a hyperparameter.
+///
+/// [`Schedule::value()`] returns the value at a given step.
+///
+/// ```
+/// use border_core::Schedule;
+///
+/// let s = Schedule::Linear { start: 1.0, end: 0.0, n_steps: 100 };
+/// assert_eq!(s.value(50), 0.5);
+/// assert_eq!(s.value(200), 0.0);
+///
+/// let s = Schedule::Warmup {
+///     start: 0.0,
+///     n_steps: 10,
+///     schedule: Box::new(Schedule::Constant(1.0)),
+/// };
+/// assert_eq!(s.value(5), 0.5);
+/// assert_eq!(s.value(10), 1.0);
+/// ```
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub enum Schedule {
+    /// Constant value.
+    Constant(f64),
+
+    /// Linear interpolation from `start` to `end` in `n_steps` steps, then constant.
+    Linear {
+        /// Value at step 0.
+        start: f64,
+
+        /// Value at and after step `n_steps`.
+        end: f64,
+
+        /// Number of steps of the interpolation.
+        n_steps: usize,
+    },
+
+    /// Cosine annealing from `start` to `end` in `n_steps` steps, then constant.
+    Cosine {
+        /// Value at step 0.
+        start: f64,
+
+        /// Value at and after step `n_steps`.
+        end: f64,
+
+        /// Number of steps of the annealing.
+        n_steps: usize,
+    },
+
+    /// Multiplies the value by `gamma` every `step_size` steps.
+    Step {
+        /// Value at step 0.
+        start: f64,
+
+        /// Multiplicative factor.
+        gamma: f64,
+
+        /// Interval of the decay.
+        step_size: usize,
+    },
+
+    /// Linear warmup from `start` to the initial value of `schedule` in `n_steps` steps.
+    ///
+    /// After the warmup, the value follows `schedule`, of which steps are counted from
+    /// the end of the warmup.
+    Warmup {
+        /// Value at step 0.
+        start: f64,
+
+        /// Number of steps of the warmup.
+        n_steps: usize,
+
+        /// Schedule after the warmup.
+        schedule: Box<Schedule>,
+    },
+
+    /// Piecewise linear interpolation of `(step, value)` pairs.
+    ///
+    /// The pairs must be sorted by step. The value is constant before the first pair
+    /// and after the last pair.
+    Piecewise(Vec<(usize, f64)>),
+}
+
+impl Schedule {
+    /// Returns the value at the given step.
+    pub fn value(&self, step: usize) -> f64 {
+        match self {
+            Self::Constant(v) => *v,
+            Self::Linear {
+                start,
+                end,
+                n_steps,
+            } => start + (end - start) * progress(step, *n_steps),
+            Self::Cosine {
+                start,
+                end,
+                n_steps,
+            } => {
+                let c = 0.5 * (1.0 + (PI * progress(step, *n_steps)).cos());
+                end + (start - end) * c
+            }
+            Self::Step {
+                start,
+                gamma,
+                step_size,
+            } => start * gamma.powi((step / (*step_size).max(1)) as i32),
+            Self::Warmup {
+                start,
+                n_steps,
+                schedule,
+            } => match step < *n_steps {
+                true => start + (schedule.value(0) - start) * progress(step, *n_steps),
+                false => schedule.value(step - n_steps),
+            },
+            Self::Piecewise(points) => {
+                let ix = points.partition_point(|(s, _)| *s <= step);
+                match (ix, points.get(ix)) {
+                    (_, None) => points.last().map_or(0.0, |(_, v)| *v),
+                    (0, Some((_, v))) => *v,
+                    (_, Some((s1, v1))) => {
+                        let (s0, v0) = points[ix - 1];
+                        v0 + (v1 - v0) * progress(step - s0, s1 - s0)
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl From<f64> for Schedule {
+    fn from(v: f64) -> Self {
+        Self::Constant(v)
+    }
+}
+
+/// Returns the ratio of `step` to `n_steps`, clipped to 1.
+fn progress(step: usize, n_steps: usize) -> f64 {
+    match n_steps {
+        0 => 1.0,
+        _ => (step as f64 / n_steps as f64).min(1.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule() {
+        let s = Schedule::Cosine {
+            start: 1.0,
+            end: 0.0,
+            n_steps: 100,
+        };
+        assert!((s.value(0) - 1.0).abs() < 1e-12);
+        assert!((s.value(50) - 0.5).abs() < 1e-12);
+        assert_eq!(s.value(150), 0.0);
+
+        let s = Schedule::Step {
+            start: 1.0,
+            gamma: 0.5,
+            step_size: 10,
+        };
+        assert_eq!(s.value(9), 1.0);
+        assert_eq!(s.value(25), 0.25);
+
+        let s = Schedule::Warmup {
+            start: 0.0,
+            n_steps: 10,
+            schedule: Box::new(Schedule::Linear {
+                start: 1.0,
+                end: 0.0,
+                n_steps: 10,
+            }),
+        };
+        assert_eq!(s.value(0), 0.0);
+        assert_eq!(s.value(5), 0.5);
+        assert_eq!(s.value(15), 0.5);
+        assert_eq!(s.value(30), 0.0);
+
+        let s = Schedule::Piecewise(vec![(10, 1.0), (20, 3.0), (40, 0.0)]);
+        assert_eq!(s.value(0), 1.0);
+        assert_eq!(s.value(15), 2.0);
+        assert_eq!(s.value(20), 3.0);
+        assert_eq!(s.value(30), 1.5);
+        assert_eq!(s.value(50), 0.0);
+    }
+}
@@ -4,18 +4,47 @@
 //! learning agents. It handles environment interactions, experience collection,
 //! optimization steps, and evaluation.
 
+mod callback;
 mod config;
+mod core;
 mod sampler;
-use std::time::{Duration, SystemTime};
+use std::{path::Path, time::SystemTime};
 
+pub use self::core::TrainerCore;
 use crate::{
-    record::{Record, RecordValue::Scalar, Recorder},
+    record::{Record, Recorder},
     Agent, Env, Evaluator, ExperienceBufferBase, ReplayBufferBase, StepProcessor,
 };
 use anyhow::Result;
+pub use callback::{CallbackAction, CallbackContext, SaveKind, TrainerCallback};
 pub use config::TrainerConfig;
-use log::info;
 pub use sampler::Sampler;
+use serde::{Deserialize, Serialize};
+
+/// Reason why training stopped.
+///
+/// It is returned by [`Trainer::train`] and [`Trainer::train_offline`], and recorded
+/// with key `stop_reason` at the end of training.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum StopReason {
+    /// The number of optimization steps reached `max_opts`.
+    MaxOpts,
+
+    /// The evaluation score reached `eval_threshold`.
+    EvalThreshold,
+
+    /// The best evaluation score was not updated in `early_stopping_patience` evaluations.
+    EarlyStopping,
+
+    /// The wall-clock time of training exceeded `max_wall_time_secs`.
+    MaxWallTime,
+
+    /// The number of environment steps reached `max_env_steps`.
+    MaxEnvSteps,
+
+    /// A [`TrainerCallback`] requested to stop training.
+    Callback,
+}
 
 /// Manages the training loop and coordinates interactions between components.
 ///
@@ -58,12 +87,36 @@ pub use sampler::Sampler;
 /// During training, the best performing model is automatically saved based on evaluation rewards:
 ///
 /// * At each evaluation interval (`eval_interval`), the agent's performance is evaluated
-/// * The evaluation reward is obtained from `Record::get_scalar_without_key()`
+/// * The evaluation reward is the score returned by [`Evaluator::evaluate`], or the value
+///   of the metric in the returned record specified by `eval_metric` (see [`EvalMetric`])
 /// * If the current evaluation reward exceeds the previous maximum reward:
 ///   * The model is saved as the "best" model
 ///   * The maximum reward is updated
 /// * This ensures that the saved "best" model represents the agent's peak performance
 ///
+/// # Resuming Training
+///
+/// A training checkpoint holds everything required to continue training:
+///
+/// * Counters and timers of the trainer, and the maximum evaluation reward so far
+/// * The agent, saved with [`Agent::save_checkpoint`]
+/// * The replay buffer, saved with [`ReplayBufferBase::save`]
+/// * The state of the random number generator used in prioritized experience replay
+///
+/// Checkpoints are saved every `checkpoint_interval` optimization steps into `checkpoint_dir`,
+/// or explicitly with [`Trainer::save_checkpoint`]. Training is resumed by calling
+/// [`Trainer::resume_from`] before [`Trainer::train`]. Note that the environment is
+/// reset at the beginning of [`Trainer::train`], so the episode running at the time of the
+/// checkpoint is not continued.
+///
+/// ```ignore
+/// let mut trainer = Trainer::build(config);
+/// if resume {
+///     trainer.resume_from("checkpoint", &mut agent, &mut buffer)?;
+/// }
+/// trainer.train(env, step_proc, &mut agent, &mut buffer, &mut recorder, &mut evaluator)?;
+/// ```
+///
 /// # Configuration
 ///
 /// Training behavior is controlled by various intervals and parameters:
@@ -71,55 +124,63 @@ pub use sampler::Sampler;
 /// * `opt_interval`: Steps between optimization updates
 /// * `eval_interval`: Steps between performance evaluations
 /// * `save_interval`: Steps between model checkpoints
+/// * `checkpoint_interval`: Steps between training checkpoints
 /// * `warmup_period`: Initial steps before optimization begins
 /// * `max_opts`: Maximum number of optimization steps
+///
+/// # On-Policy Training
+///
+/// Optimization steps are skipped while [`ReplayBufferBase::is_ready`] returns `false`.
+/// On-policy agents, e.g., PPO, are trained with
+/// [`RolloutBuffer`](crate::generic_replay_buffer::RolloutBuffer), which becomes ready
+/// when a rollout of `capacity` transitions has been collected and is cleared by the agent
+/// in the optimization step. With `opt_interval = 1` and `warmup_period = 0`, an
+/// optimization step, i.e., an update of the policy over the rollout, is performed right
+/// after each rollout, and `max_opts`, `eval_interval` and the other intervals count
+/// these updates.
+///
+/// # Stopping Training
+///
+/// Training stops when the number of optimization steps reaches `max_opts`, or earlier
+/// when one of the following criteria, if set, is met:
+///
+/// * `eval_threshold`: An evaluation score reaches the threshold
+/// * `early_stopping_patience`: The best evaluation score is not updated in the given
+///   number of evaluations
+/// * `max_wall_time_secs`: The wall-clock time of training exceeds the limit
+/// * `max_env_steps`: The number of environment steps reaches the limit (online training only)
+///
+/// The reason is returned as [`StopReason`].
+///
+/// # Callbacks
+///
+/// Callbacks implementing [`TrainerCallback`], given with [`Trainer::with_callbacks`], are
+/// invoked at environment steps, the ends of episodes, optimization steps, evaluations and
+/// saving models. They can stop training or change the learning rate of the agent through
+/// the returned [`CallbackAction`].
+///
+/// # Shared Training Loop
+///
+/// Counters, evaluation, model saving, checkpoints and stopping criteria are implemented
+/// in [`TrainerCore`], which is shared with asynchronous trainers. This struct adds
+/// sampling from the environment, `opt_interval` and `warmup_period` on top of it.
 pub struct Trainer {
     /// Interval between optimization steps in environment steps.
     /// Ignored for offline training.
     opt_interval: usize,
 
-    /// Interval for recording computational cost in optimization steps.
-    record_compute_cost_interval: usize,
-
-    /// Interval for recording agent information in optimization steps.
-    record_agent_info_interval: usize,
-
-    /// Interval for flushing records in optimization steps.
-    flush_records_interval: usize,
-
-    /// Interval for evaluation in optimization steps.
-    eval_interval: usize,
-
-    /// Interval for saving the model in optimization steps.
-    save_interval: usize,
-
-    /// Maximum number of optimization steps.
-    max_opts: usize,
-
     /// Warmup period for filling replay buffer in environment steps.
     /// Ignored for offline training.
     warmup_period: usize,
 
-    /// Counter for replay buffer samples.
-    samples_counter: usize,
-
-    /// Timer for replay buffer samples.
-    timer_for_samples: Duration,
-
-    /// Counter for optimization steps.
-    opt_steps_counter: usize,
-
-    /// Timer for optimization steps.
-    timer_for_opt_steps: Duration,
-
-    /// Maximum evaluation reward achieved.
-    max_eval_reward: f32,
-
-    /// Current environment step count.
-    env_steps: usize,
+    /// Environment steps at the last call of [`Trainer::train_step`].
+    ///
+    /// An optimization step is performed when the environment steps pass a multiple of
+    /// `opt_interval`, as a vectorized environment takes multiple steps at once.
+    prev_env_steps: usize,
 
-    /// Current optimization step count.
-    opt_steps: usize,
+    /// Counters, evaluation, model saving and stopping criteria.
+    core: TrainerCore,
 }
 
 impl Trainer {
@@ -135,42 +196,66 @@ impl Trainer {
     pub fn build(config: TrainerConfig) -> Self {
         Self {
             opt_interval: config.opt_interval,
-            record_compute_cost_interval: config.record_compute_cost_interval,
-            record_agent_info_interval: config.record_agent_info_interval,
-            flush_records_interval: config.flush_record_interval,
-            eval_interval: config.eval_interval,
-            save_interval: config.save_interval,
-            max_opts: config.max_opts,
             warmup_period: config.warmup_period,
-            samples_counter: 0,
-            timer_for_samples: Duration::new(0, 0),
-            opt_steps_counter: 0,
-            timer_for_opt_steps: Duration::new(0, 0),
-            max_eval_reward: f32::MIN,
-            env_steps: 0,
-            opt_steps: 0,
+            prev_env_steps: 0,
+            core: TrainerCore::build(&config),
         }
     }
 
-    /// Resets the counters.
-    fn reset_counters(&mut self) {
-        self.samples_counter = 0;
-        self.timer_for_samples = Duration::new(0, 0);
-        self.opt_steps_counter = 0;
-        self.timer_for_opt_steps = Duration::new(0, 0);
+    /// Sets callbacks invoked during training.
+    pub fn with_callbacks(mut self, callbacks: Vec<Box<dyn TrainerCallback>>) -> Self {
+        self.core = self.core.with_callbacks(callbacks);
+        self
+    }
+
+    /// Returns the number of environment steps.
+    pub fn env_steps(&self) -> usize {
+        self.core.env_steps()
+    }
+
+    /// Returns the number of optimization steps.
+    pub fn opt_steps(&self) -> usize {
+        self.core.opt_steps()
+    }
+
+    /// Saves a training checkpoint into the directory `path`.
+    ///
+    /// The directory will contain the following:
+    ///
+    /// * `trainer.yaml` - Counters of the trainer and the state of the random number generator
+    /// * `agent` - Directory given to [`Agent::save_checkpoint`]
+    /// * `replay_buffer` - Directory given to [`ReplayBufferBase::save`]
+    ///
+    /// The checkpoint is first written to a temporary directory, which then replaces
+    /// `path`. Thus, an existing checkpoint is not broken even if saving fails.
+    pub fn save_checkpoint<E, R>(
+        &self,
+        path: impl AsRef<Path>,
+        agent: &dyn Agent<E, R>,
+        buffer: &R,
+    ) -> Result<()>
+    where
+        E: Env,
+        R: ReplayBufferBase,
+    {
+        self.core.save_checkpoint(path, agent, buffer)
     }
 
-    /// Calculates average time for optimization steps and samples in milliseconds.
-    fn average_time(&mut self) -> (f32, f32) {
-        let avr_opt_time = match self.opt_steps_counter {
-            0 => -1f32,
-            n => self.timer_for_opt_steps.as_millis() as f32 / n as f32,
-        };
-        let avr_sample_time = match self.samples_counter {
-            0 => -1f32,
-            n => self.timer_for_samples.as_millis() as f32 / n as f32,
-        };
-        (avr_opt_time, avr_sample_time)
+    /// Restores the state of training from the checkpoint saved in the directory `path`.
+    ///
+    /// The agent and the replay buffer should be built with the same configurations
+    /// as those at the time of saving the checkpoint.
+    pub fn resume_from<E, R>(
+        &mut self,
+        path: impl AsRef<Path>,
+        agent: &mut Box<dyn Agent<E, R>>,
+        buffer: &mut R,
+    ) -> Result<()>
+    where
+        E: Env,
+        R: ReplayBufferBase,
+    {
+        self.core.resume_from(path, agent, buffer)
     }
 
     /// Performs a single training step.
@@ -203,67 +288,24 @@ impl Trainer {
         E: Env,
         R: ReplayBufferBase,
     {
-        if self.env_steps < self.warmup_period {
+        let env_steps = self.core.env_steps();
+        let prev_env_steps = std::mem::replace(&mut self.prev_env_steps, env_steps);
+        if env_steps < self.warmup_period {
             Ok((Record::empty(), false))
-        } else if self.env_steps % self.opt_interval != 0 {
+        } else if env_steps / self.opt_interval == prev_env_steps / self.opt_interval {
             // skip optimization step
             Ok((Record::empty(), false))
-        } else if (self.opt_steps + 1) % self.record_agent_info_interval == 0 {
-            // Do optimization step with record
-            let timer = SystemTime::now();
-            let record_agent = agent.opt_with_record(buffer);
-            self.opt_steps += 1;
-            self.timer_for_opt_steps += timer.elapsed()?;
-            self.opt_steps_counter += 1;
-            Ok((record_agent, true))
+        } else if !buffer.is_ready() {
+            // wait until the buffer has enough experiences, e.g., a rollout of on-policy agents
+            Ok((Record::empty(), false))
         } else {
-            // Do optimization step without record
-            let timer = SystemTime::now();
-            agent.opt(buffer);
-            self.opt_steps += 1;
-            self.timer_for_opt_steps += timer.elapsed()?;
-            self.opt_steps_counter += 1;
-            Ok((Record::empty(), true))
+            Ok((self.core.opt_step(agent, buffer)?, true))
         }
     }
 
-    /// Evaluates the agent and saves the best model.
-    fn post_process<E, R, D>(
-        &mut self,
-        agent: &mut Box<dyn Agent<E, R>>,
-        evaluator: &mut D,
-        recorder: &mut Box<dyn Recorder<E, R>>,
-        record: &mut Record,
-    ) -> Result<()>
-    where
-        E: Env,
-        R: ReplayBufferBase,
-        D: Evaluator<E>,
-    {
-        // Evaluation
-        if self.opt_steps % self.eval_interval == 0 {
-            info!("Starts evaluation of the trained model");
-            agent.eval();
-            let (score, record_eval) = evaluator.evaluate(agent)?;
-            agent.train();
-            record.merge_inplace(record_eval);
-
-            // Save the best model up to the current iteration
-            if score > self.max_eval_reward {
-                self.max_eval_reward = score;
-                recorder.save_model("best".as_ref(), agent)?;
-            }
-        };
-
-        // Save the current model
-        if (self.save_interval > 0) && (self.opt_steps % self.save_interval == 0) {
-            recorder.save_model(format!("{}", self.opt_steps).as_ref(), agent)?;
-        }
-
-        Ok(())
-    }
-
     /// Train the agent online.
+    ///
+    /// Returns the reason why training stopped.
     pub fn train<E, P, R, D>(
         &mut self,
         env: E,
@@ -272,7 +314,7 @@ impl Trainer {
         buffer: &mut R,
         recorder: &mut Box<dyn Recorder<E, R>>,
         evaluator: &mut D,
-    ) -> Result<()>
+    ) -> Result<StopReason>
     where
         E: Env,
         P: StepProcessor<E>,
@@ -280,34 +322,51 @@ impl Trainer {
         D: Evaluator<E>,
     {
         let mut sampler = Sampler::new(env, step_proc);
+        let start = SystemTime::now();
         agent.train();
 
         loop {
             // Taking samples from the environment and pushing them to the replay buffer
             let now = SystemTime::now();
             let record = sampler.sample_and_push(agent, buffer)?;
-            self.timer_for_samples += now.elapsed()?;
-            self.samples_counter += 1;
-            self.env_steps += 1;
+            let n_envs = sampler.n_envs();
+            self.core.add_sample_time(n_envs, now.elapsed()?);
+            self.core.add_env_steps(n_envs);
+
+            // Callbacks on the environment step and finished episodes
+            let mut stop_reason = self
+                .core
+                .invoke_callbacks(agent, |cb, ctx| cb.on_env_step(ctx, &record))?;
+            for episode in sampler.finished_episodes() {
+                stop_reason = stop_reason.or(self
+                    .core
+                    .invoke_callbacks(agent, |cb, ctx| cb.on_episode_end(ctx, episode))?);
+            }
 
             // Performe optimization step(s)
             let (mut record, is_opt) = {
                 let (r, is_opt) = self.train_step(agent, buffer)?;
+                if is_opt {
+                    stop_reason = stop_reason.or(self
+                        .core
+                        .invoke_callbacks(agent, |cb, ctx| cb.on_opt_step(ctx, &r))?);
+                }
                 (record.merge(r), is_opt)
             };
 
             // Postprocessing after each training step
             if is_opt {
-                self.post_process(agent, evaluator, recorder, &mut record)?;
+                stop_reason = stop_reason.or(self.core.post_process(
+                    agent,
+                    buffer,
+                    evaluator,
+                    recorder,
+                    &mut record,
+                )?);
             }
 
             // Record average time for optimization steps and sampling steps in milliseconds
-            if self.opt_steps % self.record_compute_cost_interval == 0 {
-                let (avr_opt_time, avr_sample_time) = self.average_time();
-                record.insert("average_opt_time", Scalar(avr_opt_time));
-                record.insert("average_sample_time", Scalar(avr_sample_time));
-                self.reset_counters();
-            }
+            self.core.record_compute_cost(&mut record, true);
 
             // Store record to the recorder
             if !record.is_empty() {
@@ -315,25 +374,26 @@ impl Trainer {
             }
 
             // Flush records
-            if is_opt && ((self.opt_steps - 1) % self.flush_records_interval == 0) {
-                recorder.flush(self.opt_steps as _);
-            }
+            self.core.flush_records(recorder, is_opt);
 
             // Finish training
-            if self.opt_steps == self.max_opts {
-                return Ok(());
+            if let Some(stop_reason) = stop_reason.or(self.core.stop_reason(start, true)?) {
+                self.core.finish(recorder, stop_reason);
+                return Ok(stop_reason);
             }
         }
     }
 
     /// Train the agent offline.
+    ///
+    /// Returns the reason why training stopped.
     pub fn train_offline<E, R, D>(
         &mut self,
         agent: &mut Box<dyn Agent<E, R>>,
         buffer: &mut R,
         recorder: &mut Box<dyn Recorder<E, R>>,
         evaluator: &mut D,
-    ) -> Result<()>
+    ) -> Result<StopReason>
     where
         E: Env,
         R: ReplayBufferBase,
@@ -342,29 +402,38 @@ impl Trainer {
         // Return empty record
         self.warmup_period = 0;
         self.opt_interval = 1;
+        let start = SystemTime::now();
         agent.train();
 
         loop {
             let record = Record::empty();
-            self.env_steps += 1;
+            self.core.add_env_steps(1);
 
             // Performe optimization step(s)
+            let mut stop_reason = None;
             let (mut record, is_opt) = {
                 let (r, is_opt) = self.train_step(agent, buffer)?;
+                if is_opt {
+                    stop_reason = self
+                        .core
+                        .invoke_callbacks(agent, |cb, ctx| cb.on_opt_step(ctx, &r))?;
+                }
                 (record.merge(r), is_opt)
             };
 
             // Postprocessing after each training step
             if is_opt {
-                self.post_process(agent, evaluator, recorder, &mut record)?;
+                stop_reason = stop_reason.or(self.core.post_process(
+                    agent,
+                    buffer,
+                    evaluator,
+                    recorder,
+                    &mut record,
+                )?);
             }
 
-            // Record average time for optimization steps and sampling steps in milliseconds
-            if self.opt_steps % self.record_compute_cost_interval == 0 {
-                let (avr_opt_time, _) = self.average_time();
-                record.insert("average_opt_time", Scalar(avr_opt_time));
-                self.reset_counters();
-            }
+            // Record average time for optimization steps in milliseconds
+            self.core.record_compute_cost(&mut record, false);
 
             // Store record to the recorder
             if !record.is_empty() {
@@ -372,14 +441,227 @@ impl Trainer {
             }
 
             // Flush records
-            if is_opt && ((self.opt_steps - 1) % self.flush_records_interval == 0) {
-                recorder.flush(self.opt_steps as _);
-            }
+            self.core.flush_records(recorder, is_opt);
 
             // Finish training
-            if self.opt_steps == self.max_opts {
-                return Ok(());
+            if let Some(stop_reason) = stop_reason.or(self.core.stop_reason(start, false)?) {
+                self.core.finish(recorder, stop_reason);
+                return Ok(stop_reason);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generic_replay_buffer::{
+            SimpleReplayBuffer, SimpleReplayBufferConfig, SimpleStepProcessor,
+            SimpleStepProcessorConfig,
+        },
+        record::{NullRecorder, RecordValue::Scalar},
+        test::{TestActBatch, TestAgent, TestEnv, TestObsBatch},
+    };
+    use tempdir::TempDir;
+
+    type ReplayBuffer = SimpleReplayBuffer<TestObsBatch, TestActBatch>;
+    type StepProc = SimpleStepProcessor<TestEnv, TestObsBatch, TestActBatch>;
+
+    /// Evaluator returning the given scores in order.
+    struct ScoreEvaluator(Vec<f32>);
+
+    impl Evaluator<TestEnv> for ScoreEvaluator {
+        fn evaluate<R>(&mut self, _agent: &mut Box<dyn Agent<TestEnv, R>>) -> Result<(f32, Record)>
+        where
+            R: ReplayBufferBase,
+        {
+            let score = self.0.remove(0);
+            Ok((score, Record::from_slice(&[("score", Scalar(score))])))
+        }
+    }
+
+    fn train(
+        trainer: &mut Trainer,
+        agent: &mut Box<dyn Agent<TestEnv, ReplayBuffer>>,
+        buffer: &mut ReplayBuffer,
+        scores: &[f32],
+    ) -> Result<StopReason> {
+        let env = TestEnv::build(&0, 0)?;
+        let step_proc = StepProc::build(&SimpleStepProcessorConfig::default());
+        let mut recorder: Box<dyn Recorder<TestEnv, ReplayBuffer>> = Box::new(NullRecorder::new());
+        let mut evaluator = ScoreEvaluator(scores.to_vec());
+        trainer.train(env, step_proc, agent, buffer, &mut recorder, &mut evaluator)
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() -> Result<()> {
+        let dir = TempDir::new("trainer_checkpoint")?;
+        let checkpoint_dir = dir.path().join("checkpoint");
+        let config = TrainerConfig::default()
+            .eval_interval(1000)
+            .warmup_period(3)
+            .checkpoint_interval(5)
+            .checkpoint_dir(checkpoint_dir.to_str().unwrap());
+        let buffer_config = SimpleReplayBufferConfig::default().capacity(8);
+
+        let mut trainer = Trainer::build(config.clone().max_opts(10));
+        let mut agent: Box<dyn Agent<TestEnv, ReplayBuffer>> = Box::new(TestAgent {});
+        let mut buffer = ReplayBuffer::build(&buffer_config);
+        train(&mut trainer, &mut agent, &mut buffer, &[])?;
+
+        let mut trainer_ = Trainer::build(config.max_opts(15));
+        let mut buffer_ = ReplayBuffer::build(&buffer_config);
+        trainer_.resume_from(&checkpoint_dir, &mut agent, &mut buffer_)?;
+        assert_eq!(trainer_.opt_steps(), trainer.opt_steps());
+        assert_eq!(trainer_.env_steps(), trainer.env_steps());
+        assert_eq!(buffer_.len(), buffer.len());
+        assert_eq!(buffer_.batch(4)?.ix_sample, buffer.batch(4)?.ix_sample);
+
+        train(&mut trainer_, &mut agent, &mut buffer_, &[])?;
+        assert_eq!(trainer_.opt_steps(), 15);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkpoint_interval_zero() -> Result<()> {
+        let dir = TempDir::new("trainer_checkpoint")?;
+        let checkpoint_dir = dir.path().join("checkpoint");
+        let config = TrainerConfig::default()
+            .max_opts(5)
+            .eval_interval(1000)
+            .warmup_period(3)
+            .checkpoint_interval(0)
+            .checkpoint_dir(checkpoint_dir.to_str().unwrap());
+        let buffer_config = SimpleReplayBufferConfig::default().capacity(8);
+
+        let mut trainer = Trainer::build(config);
+        let mut agent: Box<dyn Agent<TestEnv, ReplayBuffer>> = Box::new(TestAgent {});
+        let mut buffer = ReplayBuffer::build(&buffer_config);
+        train(&mut trainer, &mut agent, &mut buffer, &[])?;
+        assert_eq!(trainer.opt_steps(), 5);
+        assert!(!checkpoint_dir.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stop_criteria() -> Result<()> {
+        let config = TrainerConfig::default()
+            .max_opts(100)
+            .eval_interval(1000)
+            .warmup_period(3);
+        let buffer_config = SimpleReplayBufferConfig::default().capacity(8);
+        let mut agent: Box<dyn Agent<TestEnv, ReplayBuffer>> = Box::new(TestAgent {});
+        let scores = [1.0, 2.0, 2.0, 1.0, 3.0];
+
+        let cases = [
+            (config.clone(), StopReason::MaxOpts, 100),
+            (
+                config.clone().eval_interval(1).eval_threshold(3.0),
+                StopReason::EvalThreshold,
+                5,
+            ),
+            (
+                config.clone().eval_interval(1).early_stopping_patience(2),
+                StopReason::EarlyStopping,
+                4,
+            ),
+            (config.clone().max_env_steps(10), StopReason::MaxEnvSteps, 8),
+            (config.max_wall_time_secs(0), StopReason::MaxWallTime, 0),
+        ];
+        for (config, stop_reason, opt_steps) in cases.iter() {
+            let mut trainer = Trainer::build(config.clone());
+            let mut buffer = ReplayBuffer::build(&buffer_config);
+            assert_eq!(
+                train(&mut trainer, &mut agent, &mut buffer, &scores)?,
+                *stop_reason
+            );
+            assert_eq!(trainer.opt_steps(), *opt_steps);
+        }
+
+        Ok(())
+    }
+
+    /// Callback counting invocations of hooks, which stops training at an evaluation.
+    #[derive(Default)]
+    struct CountCallback {
+        env_steps: usize,
+        opt_steps: usize,
+        scores: Vec<f32>,
+        saves: Vec<SaveKind>,
+        stop_at_eval: usize,
+    }
+
+    impl TrainerCallback for std::rc::Rc<std::cell::RefCell<CountCallback>> {
+        fn on_env_step(&mut self, _ctx: &CallbackContext, _: &Record) -> Result<CallbackAction> {
+            self.borrow_mut().env_steps += 1;
+            Ok(CallbackAction::default())
+        }
+
+        fn on_opt_step(&mut self, ctx: &CallbackContext, _: &Record) -> Result<CallbackAction> {
+            let mut this = self.borrow_mut();
+            this.opt_steps += 1;
+            assert_eq!(this.opt_steps, ctx.opt_steps);
+            Ok(CallbackAction::default())
+        }
+
+        fn on_eval(
+            &mut self,
+            ctx: &CallbackContext,
+            score: f32,
+            _: &Record,
+        ) -> Result<CallbackAction> {
+            let mut this = self.borrow_mut();
+            this.scores.push(score);
+            assert!(ctx.max_eval_reward >= score);
+            match this.scores.len() >= this.stop_at_eval {
+                true => Ok(CallbackAction::stop()),
+                false => Ok(CallbackAction::default()),
             }
         }
+
+        fn on_save(
+            &mut self,
+            _ctx: &CallbackContext,
+            kind: SaveKind,
+            _: &Path,
+        ) -> Result<CallbackAction> {
+            self.borrow_mut().saves.push(kind);
+            Ok(CallbackAction::default())
+        }
+    }
+
+    #[test]
+    fn test_callbacks() -> Result<()> {
+        let config = TrainerConfig::default()
+            .max_opts(100)
+            .eval_interval(2)
+            .save_interval(4)
+            .warmup_period(3);
+        let buffer_config = SimpleReplayBufferConfig::default().capacity(8);
+        let mut agent: Box<dyn Agent<TestEnv, ReplayBuffer>> = Box::new(TestAgent {});
+        let mut buffer = ReplayBuffer::build(&buffer_config);
+        let callback = std::rc::Rc::new(std::cell::RefCell::new(CountCallback {
+            stop_at_eval: 3,
+            ..Default::default()
+        }));
+
+        let mut trainer = Trainer::build(config).with_callbacks(vec![Box::new(callback.clone())]);
+        let stop_reason = train(&mut trainer, &mut agent, &mut buffer, &[1.0, 3.0, 2.0])?;
+        assert_eq!(stop_reason, StopReason::Callback);
+        assert_eq!(trainer.opt_steps(), 6);
+
+        let callback = callback.borrow();
+        assert_eq!(callback.env_steps, trainer.env_steps());
+        assert_eq!(callback.opt_steps, 6);
+        assert_eq!(callback.scores, vec![1.0, 3.0, 2.0]);
+        assert_eq!(
+            callback.saves,
+            vec![SaveKind::BestModel, SaveKind::BestModel, SaveKind::Model]
+        );
+
+        Ok(())
     }
 }
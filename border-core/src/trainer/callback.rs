@@ -0,0 +1,151 @@
+//! Callbacks invoked by [`Trainer`](crate::Trainer) during training.
+use crate::{generic_replay_buffer::EpisodeStats, record::Record};
+use anyhow::Result;
+use std::path::Path;
+
+/// State of training given to the hooks of [`TrainerCallback`].
+#[derive(Clone, Copy, Debug)]
+pub struct CallbackContext {
+    /// Number of environment steps.
+    pub env_steps: usize,
+
+    /// Number of optimization steps.
+    pub opt_steps: usize,
+
+    /// Best evaluation score so far.
+    pub max_eval_reward: f32,
+}
+
+/// Requests from a [`TrainerCallback`] to [`Trainer`](crate::Trainer).
+///
+/// When multiple callbacks return actions at the same hook, training stops if any of
+/// them requests it, and the learning rate given by the last callback is applied.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CallbackAction {
+    /// If `true`, training stops with [`StopReason::Callback`](crate::StopReason::Callback).
+    pub stop: bool,
+
+    /// If set, the learning rate is changed with [`Agent::set_learning_rate`].
+    ///
+    /// [`Agent::set_learning_rate`]: crate::Agent::set_learning_rate
+    pub learning_rate: Option<f64>,
+}
+
+impl CallbackAction {
+    /// Returns an action requesting to stop training.
+    pub fn stop() -> Self {
+        Self {
+            stop: true,
+            learning_rate: None,
+        }
+    }
+
+    /// Returns an action requesting to change the learning rate.
+    pub fn learning_rate(lr: f64) -> Self {
+        Self {
+            stop: false,
+            learning_rate: Some(lr),
+        }
+    }
+
+    /// Merges two actions, where `other` takes precedence for the learning rate.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            stop: self.stop || other.stop,
+            learning_rate: other.learning_rate.or(self.learning_rate),
+        }
+    }
+}
+
+/// Kind of a model or a checkpoint saved during training.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveKind {
+    /// The best model, saved when the evaluation score is updated.
+    BestModel,
+
+    /// A model saved every `save_interval` optimization steps.
+    Model,
+
+    /// A training checkpoint, saved every `checkpoint_interval` optimization steps.
+    Checkpoint,
+}
+
+/// Hooks invoked by [`Trainer`](crate::Trainer) during training.
+///
+/// All hooks do nothing by default. Implement the ones required, e.g., for custom
+/// logging, learning rate schedules or stopping criteria. Callbacks are given to
+/// [`Trainer::with_callbacks`](crate::Trainer::with_callbacks).
+///
+/// ```ignore
+/// struct StopAt(usize);
+///
+/// impl TrainerCallback for StopAt {
+///     fn on_opt_step(&mut self, ctx: &CallbackContext, _: &Record) -> Result<CallbackAction> {
+///         match ctx.opt_steps >= self.0 {
+///             true => Ok(CallbackAction::stop()),
+///             false => Ok(CallbackAction::default()),
+///         }
+///     }
+/// }
+///
+/// let mut trainer = Trainer::build(config).with_callbacks(vec![Box::new(StopAt(1000))]);
+/// ```
+pub trait TrainerCallback {
+    /// Called after each environment step with the record of the environment.
+    ///
+    /// Not called in offline training.
+    #[allow(unused_variables)]
+    fn on_env_step(&mut self, ctx: &CallbackContext, record: &Record) -> Result<CallbackAction> {
+        Ok(CallbackAction::default())
+    }
+
+    /// Called when an episode of a (sub-)environment ends.
+    ///
+    /// Not called in offline training.
+    #[allow(unused_variables)]
+    fn on_episode_end(
+        &mut self,
+        ctx: &CallbackContext,
+        episode: &EpisodeStats,
+    ) -> Result<CallbackAction> {
+        Ok(CallbackAction::default())
+    }
+
+    /// Called after each optimization step with the record of the agent.
+    ///
+    /// The record is empty except for every `record_agent_info_interval` steps.
+    #[allow(unused_variables)]
+    fn on_opt_step(&mut self, ctx: &CallbackContext, record: &Record) -> Result<CallbackAction> {
+        Ok(CallbackAction::default())
+    }
+
+    /// Called after each evaluation with the score and the record of the evaluator.
+    ///
+    /// `ctx.max_eval_reward` includes the given score.
+    #[allow(unused_variables)]
+    fn on_eval(
+        &mut self,
+        ctx: &CallbackContext,
+        score: f32,
+        record: &Record,
+    ) -> Result<CallbackAction> {
+        Ok(CallbackAction::default())
+    }
+
+    /// Called after a model or a training checkpoint is saved.
+    ///
+    /// For models, `path` is the one given to [`Recorder::save_model`], which is
+    /// relative to the directory managed by the recorder. For checkpoints, `path` is
+    /// the checkpoint directory.
+    ///
+    /// [`Recorder::save_model`]: crate::record::Recorder::save_model
+    #[allow(unused_variables)]
+    fn on_save(
+        &mut self,
+        ctx: &CallbackContext,
+        kind: SaveKind,
+        path: &Path,
+    ) -> Result<CallbackAction> {
+        Ok(CallbackAction::default())
+    }
+}
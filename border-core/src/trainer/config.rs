@@ -13,6 +13,8 @@
 //! * Evaluation frequency and model selection
 //! * Performance monitoring and metrics recording
 //! * Model checkpointing and warmup periods
+//! * Criteria for stopping training before `max_opts`
+use crate::EvalMetric;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -34,6 +36,8 @@ pub struct TrainerConfig {
 
     /// Number of environment steps between optimization updates.
     /// For example, if set to 1, optimization occurs after every environment step.
+    /// With a vectorized environment, a step of each sub-environment is counted.
+    #[serde(default = "default_opt_interval")]
     pub opt_interval: usize,
 
     /// Number of optimization steps between performance evaluations.
@@ -59,6 +63,51 @@ pub struct TrainerConfig {
     /// Number of optimization steps between saving model checkpoints.
     /// These checkpoints can be used for resuming training or analysis.
     pub save_interval: usize,
+
+    /// Number of optimization steps between saving training checkpoints.
+    /// A training checkpoint includes the state of the trainer, the agent and
+    /// the replay buffer, from which training can be resumed.
+    /// If 0, training checkpoints are not saved.
+    #[serde(default = "default_checkpoint_interval")]
+    pub checkpoint_interval: usize,
+
+    /// Directory where training checkpoints are saved.
+    /// If `None`, training checkpoints are not saved.
+    #[serde(default)]
+    pub checkpoint_dir: Option<String>,
+
+    /// Evaluation score at which training stops.
+    /// Training stops when the score of an evaluation reaches this value.
+    #[serde(default)]
+    pub eval_threshold: Option<f32>,
+
+    /// Number of evaluations without improvement of the best score after which
+    /// training stops. If `None`, early stopping is disabled.
+    #[serde(default)]
+    pub early_stopping_patience: Option<usize>,
+
+    /// Wall-clock time in seconds after which training stops.
+    /// The time is measured from the beginning of each call of the training method.
+    #[serde(default)]
+    pub max_wall_time_secs: Option<u64>,
+
+    /// Number of environment steps after which training stops.
+    /// Ignored for offline training.
+    #[serde(default)]
+    pub max_env_steps: Option<usize>,
+
+    /// Metric of evaluation used to choose the best model and for the criteria on
+    /// evaluation scores.
+    #[serde(default)]
+    pub eval_metric: EvalMetric,
+}
+
+fn default_opt_interval() -> usize {
+    1
+}
+
+fn default_checkpoint_interval() -> usize {
+    usize::MAX
 }
 
 impl Default for TrainerConfig {
@@ -73,16 +122,28 @@ impl Default for TrainerConfig {
     /// * `record_agent_info_interval`: usize::MAX (never record)
     /// * `warmup_period`: 0 (no warmup)
     /// * `save_interval`: usize::MAX (never save)
+    /// * `checkpoint_interval`: usize::MAX (never save)
+    /// * `checkpoint_dir`: None (never save)
+    /// * `eval_threshold`, `early_stopping_patience`, `max_wall_time_secs`,
+    ///   `max_env_steps`: None (train until `max_opts`)
+    /// * `eval_metric`: [`EvalMetric::Score`] (the score returned by the evaluator)
     fn default() -> Self {
         Self {
             max_opts: 0,
             eval_interval: 0,
-            opt_interval: 1,
+            opt_interval: default_opt_interval(),
             flush_record_interval: usize::MAX,
             record_compute_cost_interval: usize::MAX,
             record_agent_info_interval: usize::MAX,
             warmup_period: 0,
             save_interval: usize::MAX,
+            checkpoint_interval: default_checkpoint_interval(),
+            checkpoint_dir: None,
+            eval_threshold: None,
+            early_stopping_patience: None,
+            max_wall_time_secs: None,
+            max_env_steps: None,
+            eval_metric: EvalMetric::Score,
         }
     }
 }
@@ -116,13 +177,74 @@ impl TrainerConfig {
         self
     }
 
-    /// (Deprecated) Sets the evaluation threshold.
+    /// Sets the evaluation score at which training stops.
+    ///
+    /// # Arguments
+    ///
+    /// * `v` - Threshold of the evaluation score
+    ///
+    /// # Returns
+    ///
+    /// Self with the updated configuration
+    pub fn eval_threshold(mut self, v: f32) -> Self {
+        self.eval_threshold = Some(v);
+        self
+    }
+
+    /// Sets the number of evaluations without improvement after which training stops.
+    ///
+    /// # Arguments
+    ///
+    /// * `v` - Number of evaluations without improvement of the best score
+    ///
+    /// # Returns
+    ///
+    /// Self with the updated configuration
+    pub fn early_stopping_patience(mut self, v: usize) -> Self {
+        self.early_stopping_patience = Some(v);
+        self
+    }
+
+    /// Sets the wall-clock time after which training stops.
+    ///
+    /// # Arguments
+    ///
+    /// * `v` - Wall-clock time in seconds
+    ///
+    /// # Returns
+    ///
+    /// Self with the updated configuration
+    pub fn max_wall_time_secs(mut self, v: u64) -> Self {
+        self.max_wall_time_secs = Some(v);
+        self
+    }
+
+    /// Sets the number of environment steps after which training stops.
+    ///
+    /// # Arguments
+    ///
+    /// * `v` - Maximum number of environment steps
+    ///
+    /// # Returns
+    ///
+    /// Self with the updated configuration
+    pub fn max_env_steps(mut self, v: usize) -> Self {
+        self.max_env_steps = Some(v);
+        self
+    }
+
+    /// Sets the metric of evaluation used for model selection.
+    ///
+    /// # Arguments
+    ///
+    /// * `v` - Metric of evaluation
     ///
-    /// This method is currently unimplemented and may be removed in future versions.
-    pub fn eval_threshold(/*mut */ self, _v: f32) -> Self {
-        unimplemented!();
-        // self.eval_threshold = Some(v);
-        // self
+    /// # Returns
+    ///
+    /// Self with the updated configuration
+    pub fn eval_metric(mut self, v: EvalMetric) -> Self {
+        self.eval_metric = v;
+        self
     }
 
     /// Sets the interval between optimization updates.
@@ -209,6 +331,34 @@ impl TrainerConfig {
         self
     }
 
+    /// Sets the interval for saving training checkpoints.
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoint_interval` - Number of optimization steps between training checkpoints
+    ///
+    /// # Returns
+    ///
+    /// Self with the updated configuration
+    pub fn checkpoint_interval(mut self, checkpoint_interval: usize) -> Self {
+        self.checkpoint_interval = checkpoint_interval;
+        self
+    }
+
+    /// Sets the directory where training checkpoints are saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoint_dir` - Directory of training checkpoints
+    ///
+    /// # Returns
+    ///
+    /// Self with the updated configuration
+    pub fn checkpoint_dir(mut self, checkpoint_dir: impl Into<String>) -> Self {
+        self.checkpoint_dir = Some(checkpoint_dir.into());
+        self
+    }
+
     /// Loads configuration from a YAML file.
     ///
     /// # Arguments
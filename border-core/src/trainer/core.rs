@@ -0,0 +1,520 @@
+//! Training-loop logic shared by [`Trainer`](crate::Trainer) and asynchronous trainers.
+use super::{
+    CallbackAction, CallbackContext, SaveKind, StopReason, TrainerCallback, TrainerConfig,
+};
+use crate::{
+    record::{
+        Record,
+        RecordValue::{Scalar, String as Text},
+        Recorder,
+    },
+    Agent, Env, EvalMetric, Evaluator, ReplayBufferBase,
+};
+use anyhow::{Context, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{create_dir_all, remove_dir_all, rename, File},
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// State of [`TrainerCore`] saved in a training checkpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct TrainerState {
+    env_steps: usize,
+    opt_steps: usize,
+    max_eval_reward: f32,
+    samples_counter: usize,
+    timer_for_samples: Duration,
+    opt_steps_counter: usize,
+    timer_for_opt_steps: Duration,
+
+    /// State of the thread-local random number generator of [`fastrand`],
+    /// used in prioritized experience replay.
+    fastrand_seed: u64,
+
+    /// Number of evaluations since the best evaluation score was updated.
+    #[serde(default)]
+    n_evals_without_improvement: usize,
+}
+
+/// Counters, evaluation, model saving, checkpoints and stopping criteria of a training loop.
+///
+/// [`Trainer`](crate::Trainer) and `AsyncTrainer` in `border-async-trainer` drive their
+/// loops with this struct, so that these features behave identically in both. A loop
+/// takes samples in its own way and calls the methods of this struct as follows:
+///
+/// ```ignore
+/// let start = SystemTime::now();
+/// loop {
+///     // Take samples, then count them
+///     core.add_env_steps(n_samples);
+///     core.add_sample_time(n_samples, duration);
+///
+///     // Optimization step, evaluation and saving
+///     let mut record = core.opt_step(agent, buffer)?;
+///     let mut stop_reason = core.invoke_callbacks(agent, |cb, ctx| cb.on_opt_step(ctx, &record))?;
+///     stop_reason = stop_reason.or(core.post_process(agent, buffer, evaluator, recorder, &mut record)?);
+///
+///     // Records
+///     core.record_compute_cost(&mut record, true);
+///     recorder.store(record);
+///     core.flush_records(recorder, true);
+///
+///     if let Some(stop_reason) = stop_reason.or(core.stop_reason(start, true)?) {
+///         core.finish(recorder, stop_reason);
+///         break;
+///     }
+/// }
+/// ```
+pub struct TrainerCore {
+    /// Interval for recording computational cost in optimization steps.
+    record_compute_cost_interval: usize,
+
+    /// Interval for recording agent information in optimization steps.
+    record_agent_info_interval: usize,
+
+    /// Interval for flushing records in optimization steps.
+    flush_records_interval: usize,
+
+    /// Interval for evaluation in optimization steps.
+    eval_interval: usize,
+
+    /// Interval for saving the model in optimization steps.
+    save_interval: usize,
+
+    /// Interval for saving training checkpoints in optimization steps.
+    checkpoint_interval: usize,
+
+    /// Directory of training checkpoints.
+    checkpoint_dir: Option<PathBuf>,
+
+    /// Maximum number of optimization steps.
+    max_opts: usize,
+
+    /// Evaluation score at which training stops.
+    eval_threshold: Option<f32>,
+
+    /// Number of evaluations without improvement after which training stops.
+    early_stopping_patience: Option<usize>,
+
+    /// Wall-clock time after which training stops.
+    max_wall_time: Option<Duration>,
+
+    /// Number of environment steps after which training stops.
+    max_env_steps: Option<usize>,
+
+    /// Metric of evaluation used for model selection.
+    eval_metric: EvalMetric,
+
+    /// Counter for replay buffer samples.
+    samples_counter: usize,
+
+    /// Timer for replay buffer samples.
+    timer_for_samples: Duration,
+
+    /// Counter for optimization steps.
+    opt_steps_counter: usize,
+
+    /// Timer for optimization steps.
+    timer_for_opt_steps: Duration,
+
+    /// Maximum evaluation reward achieved.
+    max_eval_reward: f32,
+
+    /// Number of evaluations since the maximum evaluation reward was updated.
+    n_evals_without_improvement: usize,
+
+    /// Current environment step count.
+    env_steps: usize,
+
+    /// Current optimization step count.
+    opt_steps: usize,
+
+    /// Callbacks invoked during training.
+    callbacks: Vec<Box<dyn TrainerCallback>>,
+}
+
+impl TrainerCore {
+    /// Creates the core of a training loop with the specified configuration.
+    ///
+    /// `opt_interval` and `warmup_period` of the configuration are not used, as they
+    /// depend on how the loop takes samples.
+    pub fn build(config: &TrainerConfig) -> Self {
+        Self {
+            record_compute_cost_interval: config.record_compute_cost_interval,
+            record_agent_info_interval: config.record_agent_info_interval,
+            flush_records_interval: config.flush_record_interval,
+            eval_interval: config.eval_interval,
+            save_interval: config.save_interval,
+            checkpoint_interval: config.checkpoint_interval,
+            checkpoint_dir: config.checkpoint_dir.as_ref().map(PathBuf::from),
+            max_opts: config.max_opts,
+            eval_threshold: config.eval_threshold,
+            early_stopping_patience: config.early_stopping_patience,
+            max_wall_time: config.max_wall_time_secs.map(Duration::from_secs),
+            max_env_steps: config.max_env_steps,
+            eval_metric: config.eval_metric.clone(),
+            samples_counter: 0,
+            timer_for_samples: Duration::new(0, 0),
+            opt_steps_counter: 0,
+            timer_for_opt_steps: Duration::new(0, 0),
+            max_eval_reward: f32::MIN,
+            n_evals_without_improvement: 0,
+            env_steps: 0,
+            opt_steps: 0,
+            callbacks: vec![],
+        }
+    }
+
+    /// Sets callbacks invoked during training.
+    pub fn with_callbacks(mut self, callbacks: Vec<Box<dyn TrainerCallback>>) -> Self {
+        self.callbacks = callbacks;
+        self
+    }
+
+    /// Returns the number of environment steps.
+    pub fn env_steps(&self) -> usize {
+        self.env_steps
+    }
+
+    /// Returns the number of optimization steps.
+    pub fn opt_steps(&self) -> usize {
+        self.opt_steps
+    }
+
+    /// Adds the number of environment steps.
+    pub fn add_env_steps(&mut self, n: usize) {
+        self.env_steps += n;
+    }
+
+    /// Adds the number of samples pushed to the replay buffer and the time taken,
+    /// from which `average_sample_time` is computed.
+    pub fn add_sample_time(&mut self, n_samples: usize, duration: Duration) {
+        self.samples_counter += n_samples;
+        self.timer_for_samples += duration;
+    }
+
+    /// Invokes a hook of the callbacks and applies the requested learning rate.
+    ///
+    /// Returns [`StopReason::Callback`] if any of the callbacks requests to stop training.
+    pub fn invoke_callbacks<E, R>(
+        &mut self,
+        agent: &mut Box<dyn Agent<E, R>>,
+        mut hook: impl FnMut(&mut dyn TrainerCallback, &CallbackContext) -> Result<CallbackAction>,
+    ) -> Result<Option<StopReason>>
+    where
+        E: Env,
+        R: ReplayBufferBase,
+    {
+        let ctx = CallbackContext {
+            env_steps: self.env_steps,
+            opt_steps: self.opt_steps,
+            max_eval_reward: self.max_eval_reward,
+        };
+        let mut action = CallbackAction::default();
+        for callback in self.callbacks.iter_mut() {
+            action = action.merge(hook(callback.as_mut(), &ctx)?);
+        }
+
+        if let Some(lr) = action.learning_rate {
+            info!(
+                "Set learning rate to {} at {} optimization steps",
+                lr, self.opt_steps
+            );
+            agent.set_learning_rate(lr)?;
+        }
+
+        match action.stop {
+            true => Ok(Some(StopReason::Callback)),
+            false => Ok(None),
+        }
+    }
+
+    /// Resets the counters.
+    fn reset_counters(&mut self) {
+        self.samples_counter = 0;
+        self.timer_for_samples = Duration::new(0, 0);
+        self.opt_steps_counter = 0;
+        self.timer_for_opt_steps = Duration::new(0, 0);
+    }
+
+    /// Calculates average time for optimization steps and samples in milliseconds.
+    fn average_time(&mut self) -> (f32, f32) {
+        let avr_opt_time = match self.opt_steps_counter {
+            0 => -1f32,
+            n => self.timer_for_opt_steps.as_millis() as f32 / n as f32,
+        };
+        let avr_sample_time = match self.samples_counter {
+            0 => -1f32,
+            n => self.timer_for_samples.as_millis() as f32 / n as f32,
+        };
+        (avr_opt_time, avr_sample_time)
+    }
+
+    /// Performs an optimization step of the agent.
+    ///
+    /// The record of the agent is returned every `record_agent_info_interval` steps,
+    /// otherwise the returned record is empty.
+    pub fn opt_step<E, R>(
+        &mut self,
+        agent: &mut Box<dyn Agent<E, R>>,
+        buffer: &mut R,
+    ) -> Result<Record>
+    where
+        E: Env,
+        R: ReplayBufferBase,
+    {
+        let timer = SystemTime::now();
+        let record = if is_interval(self.opt_steps + 1, self.record_agent_info_interval) {
+            agent.opt_with_record(buffer)
+        } else {
+            agent.opt(buffer);
+            Record::empty()
+        };
+        self.opt_steps += 1;
+        self.timer_for_opt_steps += timer.elapsed()?;
+        self.opt_steps_counter += 1;
+        Ok(record)
+    }
+
+    /// Evaluates the agent, saves the best model and saves a training checkpoint.
+    ///
+    /// This method is called after each optimization step. Returns the reason to stop
+    /// training if a criterion on evaluation scores is met.
+    pub fn post_process<E, R, D>(
+        &mut self,
+        agent: &mut Box<dyn Agent<E, R>>,
+        buffer: &R,
+        evaluator: &mut D,
+        recorder: &mut Box<dyn Recorder<E, R>>,
+        record: &mut Record,
+    ) -> Result<Option<StopReason>>
+    where
+        E: Env,
+        R: ReplayBufferBase,
+        D: Evaluator<E>,
+    {
+        let mut stop_reason = None;
+
+        // Evaluation
+        if is_interval(self.opt_steps, self.eval_interval) {
+            info!("Starts evaluation of the trained model");
+            agent.eval();
+            let (score, record_eval) = evaluator.evaluate(agent)?;
+            let score = self.eval_metric.value(score, &record_eval)?;
+            agent.train();
+
+            // Save the best model up to the current iteration
+            let is_best = score > self.max_eval_reward;
+            if is_best {
+                self.max_eval_reward = score;
+                self.n_evals_without_improvement = 0;
+                recorder.save_model("best".as_ref(), agent)?;
+            } else {
+                self.n_evals_without_improvement += 1;
+            }
+
+            stop_reason = stop_reason
+                .or(self.invoke_callbacks(agent, |cb, ctx| cb.on_eval(ctx, score, &record_eval))?);
+            if is_best {
+                stop_reason = stop_reason.or(self.invoke_callbacks(agent, |cb, ctx| {
+                    cb.on_save(ctx, SaveKind::BestModel, "best".as_ref())
+                })?);
+            }
+            record.merge_inplace(record_eval);
+
+            if matches!(self.eval_threshold, Some(th) if score >= th) {
+                stop_reason = stop_reason.or(Some(StopReason::EvalThreshold));
+            } else if matches!(
+                self.early_stopping_patience,
+                Some(n) if self.n_evals_without_improvement >= n
+            ) {
+                stop_reason = stop_reason.or(Some(StopReason::EarlyStopping));
+            }
+        };
+
+        // Save the current model
+        if is_interval(self.opt_steps, self.save_interval) {
+            let path = PathBuf::from(format!("{}", self.opt_steps));
+            recorder.save_model(&path, agent)?;
+            stop_reason = stop_reason
+                .or(self
+                    .invoke_callbacks(agent, |cb, ctx| cb.on_save(ctx, SaveKind::Model, &path))?);
+        }
+
+        // Save a training checkpoint
+        if let Some(checkpoint_dir) = self.checkpoint_dir.clone() {
+            if is_interval(self.opt_steps, self.checkpoint_interval) {
+                self.save_checkpoint(&checkpoint_dir, agent.as_ref(), buffer)?;
+                stop_reason = stop_reason.or(self.invoke_callbacks(agent, |cb, ctx| {
+                    cb.on_save(ctx, SaveKind::Checkpoint, &checkpoint_dir)
+                })?);
+            }
+        }
+
+        Ok(stop_reason)
+    }
+
+    /// Records average time for optimization steps and, if `with_samples` is `true`,
+    /// sampling steps in milliseconds every `record_compute_cost_interval` optimization steps.
+    ///
+    /// Returns `true` if the values are recorded.
+    pub fn record_compute_cost(&mut self, record: &mut Record, with_samples: bool) -> bool {
+        if !is_interval(self.opt_steps, self.record_compute_cost_interval) {
+            return false;
+        }
+        let (avr_opt_time, avr_sample_time) = self.average_time();
+        record.insert("average_opt_time", Scalar(avr_opt_time));
+        if with_samples {
+            record.insert("average_sample_time", Scalar(avr_sample_time));
+        }
+        self.reset_counters();
+        true
+    }
+
+    /// Flushes records every `flush_record_interval` optimization steps if an optimization
+    /// step has been just performed.
+    pub fn flush_records<E, R>(&self, recorder: &mut Box<dyn Recorder<E, R>>, is_opt: bool)
+    where
+        E: Env,
+        R: ReplayBufferBase,
+    {
+        if is_opt && is_interval(self.opt_steps - 1, self.flush_records_interval) {
+            recorder.flush(self.opt_steps as _);
+        }
+    }
+
+    /// Returns the reason to stop training other than evaluation scores, if any.
+    ///
+    /// `max_env_steps` is checked only if `online` is `true`.
+    pub fn stop_reason(&self, start: SystemTime, online: bool) -> Result<Option<StopReason>> {
+        if self.opt_steps >= self.max_opts {
+            Ok(Some(StopReason::MaxOpts))
+        } else if online && matches!(self.max_env_steps, Some(n) if self.env_steps >= n) {
+            Ok(Some(StopReason::MaxEnvSteps))
+        } else if matches!(self.max_wall_time, Some(t) if start.elapsed()? >= t) {
+            Ok(Some(StopReason::MaxWallTime))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Records the reason to stop training and flushes records.
+    pub fn finish<E, R>(&self, recorder: &mut Box<dyn Recorder<E, R>>, stop_reason: StopReason)
+    where
+        E: Env,
+        R: ReplayBufferBase,
+    {
+        info!(
+            "Training stopped at {} optimization steps: {:?}",
+            self.opt_steps, stop_reason
+        );
+        recorder.store(Record::from_slice(&[(
+            "stop_reason",
+            Text(format!("{:?}", stop_reason)),
+        )]));
+        recorder.flush(self.opt_steps as _);
+    }
+
+    /// Saves a training checkpoint into the directory `path`.
+    ///
+    /// See [`Trainer::save_checkpoint`](crate::Trainer::save_checkpoint).
+    pub fn save_checkpoint<E, R>(
+        &self,
+        path: impl AsRef<Path>,
+        agent: &dyn Agent<E, R>,
+        buffer: &R,
+    ) -> Result<()>
+    where
+        E: Env,
+        R: ReplayBufferBase,
+    {
+        let path = path.as_ref();
+        let tmp_path = PathBuf::from(format!("{}.tmp", path.display()));
+        if tmp_path.exists() {
+            remove_dir_all(&tmp_path)?;
+        }
+        create_dir_all(tmp_path.join("agent"))?;
+        create_dir_all(tmp_path.join("replay_buffer"))?;
+
+        agent
+            .save_checkpoint(&tmp_path.join("agent"))
+            .context("Failed to save the agent in a training checkpoint")?;
+        buffer
+            .save(&tmp_path.join("replay_buffer"))
+            .context("Failed to save the replay buffer in a training checkpoint")?;
+
+        let state = TrainerState {
+            env_steps: self.env_steps,
+            opt_steps: self.opt_steps,
+            max_eval_reward: self.max_eval_reward,
+            samples_counter: self.samples_counter,
+            timer_for_samples: self.timer_for_samples,
+            opt_steps_counter: self.opt_steps_counter,
+            timer_for_opt_steps: self.timer_for_opt_steps,
+            fastrand_seed: fastrand::get_seed(),
+            n_evals_without_improvement: self.n_evals_without_improvement,
+        };
+        let mut file = File::create(tmp_path.join("trainer.yaml"))?;
+        file.write_all(serde_yaml::to_string(&state)?.as_bytes())?;
+
+        if path.exists() {
+            remove_dir_all(path)?;
+        }
+        rename(&tmp_path, path)?;
+        info!("Saved a training checkpoint in {:?}", path);
+
+        Ok(())
+    }
+
+    /// Restores the state of training from the checkpoint saved in the directory `path`.
+    ///
+    /// See [`Trainer::resume_from`](crate::Trainer::resume_from).
+    pub fn resume_from<E, R>(
+        &mut self,
+        path: impl AsRef<Path>,
+        agent: &mut Box<dyn Agent<E, R>>,
+        buffer: &mut R,
+    ) -> Result<()>
+    where
+        E: Env,
+        R: ReplayBufferBase,
+    {
+        let path = path.as_ref();
+        let file = File::open(path.join("trainer.yaml"))?;
+        let state: TrainerState = serde_yaml::from_reader(BufReader::new(file))?;
+
+        agent
+            .load_checkpoint(&path.join("agent"))
+            .context("Failed to load the agent from a training checkpoint")?;
+        buffer
+            .load(&path.join("replay_buffer"))
+            .context("Failed to load the replay buffer from a training checkpoint")?;
+
+        self.env_steps = state.env_steps;
+        self.opt_steps = state.opt_steps;
+        self.max_eval_reward = state.max_eval_reward;
+        self.samples_counter = state.samples_counter;
+        self.timer_for_samples = state.timer_for_samples;
+        self.opt_steps_counter = state.opt_steps_counter;
+        self.timer_for_opt_steps = state.timer_for_opt_steps;
+        self.n_evals_without_improvement = state.n_evals_without_improvement;
+        fastrand::seed(state.fastrand_seed);
+        info!(
+            "Resumed training from {:?} at {} optimization steps",
+            path, self.opt_steps
+        );
+
+        Ok(())
+    }
+}
+
+/// Returns `true` if `steps` is a multiple of `interval`.
+///
+/// An interval of 0 disables the corresponding operation, so it always returns `false`.
+fn is_interval(steps: usize, interval: usize) -> bool {
+    steps.checked_rem(interval) == Some(0)
+}
@@ -21,7 +21,10 @@
 //! 3. Performance Monitoring:
 //!    * Monitor episode length
 //!    * Record environment metrics
-use crate::{record::Record, Agent, Env, ExperienceBufferBase, ReplayBufferBase, StepProcessor};
+use crate::{
+    generic_replay_buffer::EpisodeStats, record::Record, Agent, Env, ExperienceBufferBase,
+    ReplayBufferBase, Step, StepProcessor,
+};
 use anyhow::Result;
 
 /// Manages the sampling of experiences from the environment.
@@ -47,6 +50,15 @@ where
 
     /// Processor for converting steps into transitions
     step_processor: P,
+
+    /// Returns of the running episodes of sub-environments
+    returns: Vec<f32>,
+
+    /// Lengths of the running episodes of sub-environments
+    lengths: Vec<usize>,
+
+    /// Episodes finished in the last call of `sample_and_push()`
+    finished_episodes: Vec<EpisodeStats>,
 }
 
 impl<E, P> Sampler<E, P>
@@ -69,6 +81,45 @@ where
             env,
             prev_obs: None,
             step_processor,
+            returns: vec![],
+            lengths: vec![],
+            finished_episodes: vec![],
+        }
+    }
+
+    /// Returns the episodes finished in the last call of [`Sampler::sample_and_push`].
+    ///
+    /// For vectorized environments, an episode is counted for each sub-environment.
+    pub fn finished_episodes(&self) -> &[EpisodeStats] {
+        &self.finished_episodes
+    }
+
+    /// Returns the number of sub-environments stepped in the last call of
+    /// [`Sampler::sample_and_push`], i.e., the number of environment steps taken.
+    pub fn n_envs(&self) -> usize {
+        self.returns.len()
+    }
+
+    /// Accumulates rewards of the running episodes and collects finished ones.
+    fn track_episodes(&mut self, step: &Step<E>) {
+        let n_envs = step.n_envs();
+        if self.returns.len() != n_envs {
+            self.returns = vec![0.0; n_envs];
+            self.lengths = vec![0; n_envs];
+        }
+
+        self.finished_episodes.clear();
+        for ix in 0..n_envs {
+            self.returns[ix] += step.reward[ix];
+            self.lengths[ix] += 1;
+            if step.is_done_at(ix) {
+                self.finished_episodes.push(EpisodeStats {
+                    ret: self.returns[ix],
+                    len: self.lengths[ix],
+                });
+                self.returns[ix] = 0.0;
+                self.lengths[ix] = 0;
+            }
         }
     }
 
@@ -81,6 +132,9 @@ where
     /// 4. Processes the resulting step
     /// 5. Stores the experience in the replay buffer
     ///
+    /// When the environment is vectorized (e.g., [`VecEnv`]), a single call steps all
+    /// sub-environments and pushes one transition per sub-environment.
+    ///
     /// # Arguments
     ///
     /// * `agent` - The agent to sample actions from
@@ -96,6 +150,8 @@ where
     /// * The environment fails to reset
     /// * The environment step fails
     /// * The replay buffer operation fails
+    ///
+    /// [`VecEnv`]: crate::vec_env::VecEnv
     pub fn sample_and_push<R, R_>(
         &mut self,
         agent: &mut Box<dyn Agent<E, R>>,
@@ -110,6 +166,8 @@ where
             // For a vectorized environments, reset all environments in `env`
             // by giving `None` to reset() method
             self.prev_obs = Some(self.env.reset(None)?);
+            self.returns.clear();
+            self.lengths.clear();
             self.step_processor
                 .reset(self.prev_obs.as_ref().unwrap().clone());
         }
@@ -118,7 +176,7 @@ where
         let (step, record, is_done) = {
             let act = agent.sample(self.prev_obs.as_ref().unwrap());
             let (step, record) = self.env.step_with_reset(&act);
-            let is_done = step.is_done(); // not support vectorized env
+            let is_done = step.is_any_done();
             (step, record, is_done)
         };
 
@@ -128,6 +186,9 @@ where
             false => Some(step.obs.clone()),
         };
 
+        // Track returns and lengths of episodes
+        self.track_episodes(&step);
+
         // Produce transition
         let transition = self.step_processor.process(step);
 
@@ -0,0 +1,304 @@
+//! Vectorized environments.
+//!
+//! This module provides wrappers that run `N` instances of an [`Env`] as a single
+//! environment. A vectorized environment implements [`Env`] itself, so it can be
+//! used with [`Sampler`], [`Trainer`] and [`SimpleStepProcessor`] without changes.
+//!
+//! # Batched observations and actions
+//!
+//! The observation and action types of a vectorized environment are the same as
+//! those of the wrapped environment, but hold the data of all sub-environments:
+//!
+//! * [`VecEnvObs::concat`] stacks the observations of the sub-environments into one
+//!   observation, whose [`Obs::len`] returns the number of sub-environments.
+//! * [`VecEnvAct::split`] splits an action given by the agent into the actions of the
+//!   sub-environments.
+//!
+//! The rewards and termination/truncation flags in [`Step`] have one element for each
+//! sub-environment, and the information of the sub-environments is kept in
+//! `Vec<E::Info>`.
+//!
+//! # Partial auto-reset
+//!
+//! [`Env::step_with_reset`] of a vectorized environment resets only the finished
+//! sub-environments. In this case, [`Step::init_obs`] holds the observations from which
+//! the sub-environments continue: the initial observation of the new episode for finished
+//! sub-environments and the next observation for the others. This is the observation
+//! [`Sampler`] gives to the agent at the next step.
+//!
+//! # Implementations
+//!
+//! * [`VecEnv`] steps the sub-environments sequentially in the current thread.
+//! * [`ThreadedVecEnv`] runs each sub-environment in its own thread and steps
+//!   them concurrently.
+//!
+//! Both are configured with [`VecEnvConfig`]. The `i`-th sub-environment is built with
+//! seed `seed + i`.
+//!
+//! [`Sampler`]: crate::Sampler
+//! [`Trainer`]: crate::Trainer
+//! [`SimpleStepProcessor`]: crate::generic_replay_buffer::SimpleStepProcessor
+mod base;
+mod config;
+mod threaded;
+pub use base::VecEnv;
+pub use config::VecEnvConfig;
+pub use threaded::ThreadedVecEnv;
+
+use crate::{record::Record, Act, Env, Obs, Step};
+
+/// Observations that can be stacked over sub-environments of a vectorized environment.
+pub trait VecEnvObs: Obs {
+    /// Concatenates observations of sub-environments into a single observation.
+    ///
+    /// The order of the observations must be preserved.
+    fn concat(obs: Vec<Self>) -> Self;
+}
+
+/// Actions that can be split into actions of sub-environments of a vectorized environment.
+pub trait VecEnvAct: Act {
+    /// Splits an action into `n` actions, one for each sub-environment.
+    fn split(self, n: usize) -> Vec<Self>;
+}
+
+/// Merges steps of sub-environments into a step of the vectorized environment.
+///
+/// `act` is the action given to the vectorized environment. `with_reset` should be
+/// `true` if the finished sub-environments have been reset, i.e., their steps have
+/// `init_obs`. Records of sub-environments are merged into a single record.
+fn merge_steps<E, V>(
+    act: &E::Act,
+    steps: Vec<(Step<E>, Record)>,
+    with_reset: bool,
+) -> (Step<V>, Record)
+where
+    E: Env,
+    E::Obs: VecEnvObs,
+    V: Env<Obs = E::Obs, Act = E::Act, Info = Vec<E::Info>>,
+{
+    let n_envs = steps.len();
+    let is_any_done = with_reset && steps.iter().any(|(step, _)| step.is_done());
+    let mut obs = Vec::with_capacity(n_envs);
+    let mut init_obs = Vec::with_capacity(n_envs);
+    let mut reward = Vec::with_capacity(n_envs);
+    let mut is_terminated = Vec::with_capacity(n_envs);
+    let mut is_truncated = Vec::with_capacity(n_envs);
+    let mut info = Vec::with_capacity(n_envs);
+    let mut record = Record::empty();
+
+    for (step, r) in steps.into_iter() {
+        if is_any_done {
+            init_obs.push(match step.is_done() {
+                true => step.init_obs.expect("Failed to unwrap init_obs"),
+                false => step.obs.clone(),
+            });
+        }
+        obs.push(step.obs);
+        reward.extend(step.reward);
+        is_terminated.extend(step.is_terminated);
+        is_truncated.extend(step.is_truncated);
+        info.push(step.info);
+        record.merge_inplace(r);
+    }
+
+    let init_obs = match is_any_done {
+        true => Some(E::Obs::concat(init_obs)),
+        false => None,
+    };
+    let step = Step::new(
+        E::Obs::concat(obs),
+        act.clone(),
+        reward,
+        is_terminated,
+        is_truncated,
+        info,
+        init_obs,
+    );
+
+    (step, record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generic_replay_buffer::{
+            BatchBase, SimpleReplayBuffer, SimpleReplayBufferConfig, SimpleStepProcessor,
+            SimpleStepProcessorConfig,
+        },
+        ExperienceBufferBase, Info, ReplayBufferBase, StepProcessor,
+    };
+    use anyhow::Result;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct CountObs(Vec<usize>);
+
+    impl Obs for CountObs {
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    impl VecEnvObs for CountObs {
+        fn concat(obs: Vec<Self>) -> Self {
+            Self(obs.into_iter().flat_map(|o| o.0).collect())
+        }
+    }
+
+    impl BatchBase for CountObs {
+        fn new(capacity: usize) -> Self {
+            Self(vec![0; capacity])
+        }
+
+        fn push(&mut self, ix: usize, data: Self) {
+            let n = self.0.len();
+            for (j, v) in data.0.into_iter().enumerate() {
+                self.0[(ix + j) % n] = v;
+            }
+        }
+
+        fn sample(&self, ixs: &Vec<usize>) -> Self {
+            Self(ixs.iter().map(|&ix| self.0[ix]).collect())
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct CountAct(Vec<usize>);
+
+    impl Act for CountAct {}
+
+    impl VecEnvAct for CountAct {
+        fn split(self, n: usize) -> Vec<Self> {
+            assert_eq!(self.0.len(), n);
+            self.0.into_iter().map(|a| Self(vec![a])).collect()
+        }
+    }
+
+    impl BatchBase for CountAct {
+        fn new(capacity: usize) -> Self {
+            Self(vec![0; capacity])
+        }
+
+        fn push(&mut self, ix: usize, data: Self) {
+            let n = self.0.len();
+            for (j, v) in data.0.into_iter().enumerate() {
+                self.0[(ix + j) % n] = v;
+            }
+        }
+
+        fn sample(&self, ixs: &Vec<usize>) -> Self {
+            Self(ixs.iter().map(|&ix| self.0[ix]).collect())
+        }
+    }
+
+    struct CountInfo;
+
+    impl Info for CountInfo {}
+
+    /// Counts up the state and terminates when it reaches the length of the episode.
+    struct CountEnv {
+        episode_len: usize,
+        state: usize,
+    }
+
+    impl Env for CountEnv {
+        type Config = usize;
+        type Obs = CountObs;
+        type Act = CountAct;
+        type Info = CountInfo;
+
+        fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+            Ok(Self {
+                episode_len: *config + seed as usize,
+                state: 0,
+            })
+        }
+
+        fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+            self.state += a.0[0];
+            let is_terminated = (self.state >= self.episode_len) as i8;
+            let step = Step::new(
+                CountObs(vec![self.state]),
+                a.clone(),
+                vec![1.0],
+                vec![is_terminated],
+                vec![0],
+                CountInfo,
+                None,
+            );
+            (step, Record::empty())
+        }
+
+        fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+            self.state = 0;
+            Ok(CountObs(vec![self.state]))
+        }
+
+        fn reset_with_index(&mut self, _ix: usize) -> Result<Self::Obs> {
+            self.reset(None)
+        }
+    }
+
+    fn check_partial_reset<V>(mut env: V) -> Result<()>
+    where
+        V: Env<Obs = CountObs, Act = CountAct>,
+    {
+        // Episode lengths of the sub-environments are 2 and 3
+        let obs = env.reset(None)?;
+        assert_eq!(obs, CountObs(vec![0, 0]));
+        let act = CountAct(vec![1, 1]);
+
+        let (step, _) = env.step_with_reset(&act);
+        assert_eq!(step.obs, CountObs(vec![1, 1]));
+        assert!(!step.is_any_done());
+        assert!(step.init_obs.is_none());
+
+        let (step, _) = env.step_with_reset(&act);
+        assert_eq!(step.obs, CountObs(vec![2, 2]));
+        assert_eq!(step.dones(), vec![1, 0]);
+        assert_eq!(step.init_obs, Some(CountObs(vec![0, 2])));
+
+        let (step, _) = env.step_with_reset(&act);
+        assert_eq!(step.obs, CountObs(vec![1, 3]));
+        assert_eq!(step.dones(), vec![0, 1]);
+        assert_eq!(step.init_obs, Some(CountObs(vec![1, 0])));
+
+        let obs = env.reset(Some(&vec![1, 0]))?;
+        assert_eq!(obs, CountObs(vec![0, 0]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vec_env_partial_reset() -> Result<()> {
+        let config = VecEnvConfig::new(2usize, 2);
+        check_partial_reset(VecEnv::<CountEnv>::build(&config, 0)?)?;
+        check_partial_reset(ThreadedVecEnv::<CountEnv>::build(&config, 0)?)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_vec_env_transitions() -> Result<()> {
+        let config = VecEnvConfig::new(2usize, 3);
+        let mut env = VecEnv::<CountEnv>::build(&config, 0)?;
+        let mut step_proc = SimpleStepProcessor::<VecEnv<CountEnv>, CountObs, CountAct>::build(
+            &SimpleStepProcessorConfig::default(),
+        );
+        let mut buffer = SimpleReplayBuffer::<CountObs, CountAct>::build(
+            &SimpleReplayBufferConfig::default().capacity(100),
+        );
+        step_proc.reset(env.reset(None)?);
+
+        let act = CountAct(vec![1, 1, 1]);
+        for _ in 0..3 {
+            let (step, _) = env.step_with_reset(&act);
+            buffer.push(step_proc.process(step))?;
+        }
+        assert_eq!(buffer.len(), 9);
+
+        let batch = buffer.batch(1)?;
+        assert_eq!(batch.obs.0.len(), 1);
+
+        Ok(())
+    }
+}
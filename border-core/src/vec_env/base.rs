@@ -0,0 +1,149 @@
+//! Vectorized environment running sub-environments in the current thread.
+use super::{merge_steps, VecEnvAct, VecEnvConfig, VecEnvObs};
+use crate::{record::Record, Env, Step};
+use anyhow::{anyhow, Result};
+
+/// A vectorized environment that steps `N` sub-environments sequentially.
+///
+/// The sub-environments are owned by this struct and stepped one by one in the
+/// calling thread. This is useful when the environment is cheap to step, or when
+/// it cannot be moved to another thread. See [`ThreadedVecEnv`] for a concurrent
+/// implementation.
+///
+/// # Examples
+///
+/// ```ignore
+/// let config = VecEnvConfig::new(env_config, 8);
+/// let env = VecEnv::<GymEnv<_>>::build(&config, 42)?;
+/// let sampler = Sampler::new(env, step_proc);
+/// ```
+///
+/// [`ThreadedVecEnv`]: super::ThreadedVecEnv
+pub struct VecEnv<E: Env> {
+    /// Sub-environments.
+    envs: Vec<E>,
+
+    /// The latest observation of each sub-environment.
+    ///
+    /// This is used to construct the observation returned by a partial reset.
+    last_obs: Vec<Option<E::Obs>>,
+}
+
+impl<E: Env> VecEnv<E> {
+    /// Returns the number of sub-environments.
+    pub fn n_envs(&self) -> usize {
+        self.envs.len()
+    }
+
+    /// Returns a reference to the sub-environments.
+    pub fn envs(&self) -> &Vec<E> {
+        &self.envs
+    }
+}
+
+impl<E> Env for VecEnv<E>
+where
+    E: Env,
+    E::Obs: VecEnvObs,
+    E::Act: VecEnvAct,
+{
+    type Config = VecEnvConfig<E::Config>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = Vec<E::Info>;
+
+    /// Builds sub-environments, where the `i`-th one is given seed `seed + i`.
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let envs = (0..config.n_envs)
+            .map(|i| E::build(&config.env_config, seed + i as i64))
+            .collect::<Result<Vec<_>>>()?;
+        let last_obs = (0..config.n_envs).map(|_| None).collect();
+        Ok(Self { envs, last_obs })
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let acts = a.clone().split(self.n_envs());
+        let steps = self
+            .envs
+            .iter_mut()
+            .zip(acts.iter())
+            .map(|(env, a)| env.step(a))
+            .collect::<Vec<_>>();
+        for (last_obs, (step, _)) in self.last_obs.iter_mut().zip(steps.iter()) {
+            *last_obs = Some(step.obs.clone());
+        }
+        merge_steps(a, steps, false)
+    }
+
+    /// Steps all sub-environments and resets the finished ones.
+    fn step_with_reset(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let acts = a.clone().split(self.n_envs());
+        let steps = self
+            .envs
+            .iter_mut()
+            .zip(acts.iter())
+            .map(|(env, a)| env.step_with_reset(a))
+            .collect::<Vec<_>>();
+        for (last_obs, (step, _)) in self.last_obs.iter_mut().zip(steps.iter()) {
+            *last_obs = match step.is_done() {
+                true => step.init_obs.clone(),
+                false => Some(step.obs.clone()),
+            };
+        }
+        merge_steps(a, steps, true)
+    }
+
+    /// Resets sub-environments.
+    ///
+    /// If `is_done` is `None`, all sub-environments are reset. Otherwise, the `i`-th
+    /// sub-environment is reset only if `is_done[i] == 1`.
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        if let Some(is_done) = is_done {
+            if is_done.len() != self.n_envs() {
+                return Err(anyhow!(
+                    "Length of is_done ({}) does not match the number of sub-environments ({})",
+                    is_done.len(),
+                    self.n_envs()
+                ));
+            }
+        }
+
+        let mut obs = Vec::with_capacity(self.n_envs());
+        for (i, (env, last_obs)) in self
+            .envs
+            .iter_mut()
+            .zip(self.last_obs.iter_mut())
+            .enumerate()
+        {
+            let reset = match is_done {
+                None => true,
+                Some(is_done) => is_done[i] == 1 || last_obs.is_none(),
+            };
+            if reset {
+                *last_obs = Some(env.reset(None)?);
+            }
+            obs.push(last_obs.clone().unwrap());
+        }
+
+        Ok(E::Obs::concat(obs))
+    }
+
+    /// Resets all sub-environments, where the `i`-th one is reset with index
+    /// `ix * n_envs + i`.
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        let n_envs = self.n_envs();
+        let mut obs = Vec::with_capacity(n_envs);
+        for (i, (env, last_obs)) in self
+            .envs
+            .iter_mut()
+            .zip(self.last_obs.iter_mut())
+            .enumerate()
+        {
+            let o = env.reset_with_index(ix * n_envs + i)?;
+            *last_obs = Some(o.clone());
+            obs.push(o);
+        }
+
+        Ok(E::Obs::concat(obs))
+    }
+}
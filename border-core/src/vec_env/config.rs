@@ -0,0 +1,44 @@
+//! Configuration of vectorized environments.
+use serde::{Deserialize, Serialize};
+
+/// Configuration of [`VecEnv`] and [`ThreadedVecEnv`].
+///
+/// # Type Parameters
+///
+/// * `C` - Configuration of the sub-environments
+///
+/// [`VecEnv`]: super::VecEnv
+/// [`ThreadedVecEnv`]: super::ThreadedVecEnv
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct VecEnvConfig<C> {
+    /// Configuration used to build each sub-environment.
+    pub env_config: C,
+
+    /// Number of sub-environments.
+    pub n_envs: usize,
+}
+
+impl<C> VecEnvConfig<C> {
+    /// Creates a configuration with `n_envs` sub-environments built from `env_config`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_envs` is 0.
+    pub fn new(env_config: C, n_envs: usize) -> Self {
+        assert!(n_envs > 0, "n_envs must be positive");
+        Self { env_config, n_envs }
+    }
+
+    /// Sets the configuration of the sub-environments.
+    pub fn env_config(mut self, env_config: C) -> Self {
+        self.env_config = env_config;
+        self
+    }
+
+    /// Sets the number of sub-environments.
+    pub fn n_envs(mut self, n_envs: usize) -> Self {
+        assert!(n_envs > 0, "n_envs must be positive");
+        self.n_envs = n_envs;
+        self
+    }
+}
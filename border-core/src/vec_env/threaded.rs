@@ -0,0 +1,275 @@
+//! Vectorized environment running each sub-environment in its own thread.
+use super::{merge_steps, VecEnvAct, VecEnvConfig, VecEnvObs};
+use crate::{record::Record, Env, Step};
+use anyhow::{anyhow, Result};
+use std::{
+    sync::mpsc::{channel, Receiver, Sender},
+    thread::JoinHandle,
+};
+
+/// Commands sent to a worker thread.
+enum Command<A> {
+    Step(A),
+    StepWithReset(A),
+    Reset,
+    ResetWithIndex(usize),
+}
+
+/// Responses sent from a worker thread.
+enum Response<E: Env> {
+    Built(Result<()>),
+    Step(Step<E>, Record),
+    Obs(Result<E::Obs>),
+}
+
+/// A thread owning a sub-environment.
+struct Worker<E: Env> {
+    tx: Option<Sender<Command<E::Act>>>,
+    rx: Receiver<Response<E>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<E: Env> Worker<E> {
+    fn send(&self, command: Command<E::Act>) {
+        self.tx
+            .as_ref()
+            .unwrap()
+            .send(command)
+            .expect("Failed to send a command to the worker thread of ThreadedVecEnv");
+    }
+
+    fn recv(&self) -> Response<E> {
+        self.rx
+            .recv()
+            .expect("Failed to receive a response from the worker thread of ThreadedVecEnv")
+    }
+
+    fn recv_step(&self) -> (Step<E>, Record) {
+        match self.recv() {
+            Response::Step(step, record) => (step, record),
+            _ => panic!("Unexpected response from the worker thread of ThreadedVecEnv"),
+        }
+    }
+
+    fn recv_obs(&self) -> Result<E::Obs> {
+        match self.recv() {
+            Response::Obs(obs) => obs,
+            _ => Err(anyhow!(
+                "Unexpected response from the worker thread of ThreadedVecEnv"
+            )),
+        }
+    }
+}
+
+impl<E: Env> Drop for Worker<E> {
+    fn drop(&mut self) {
+        // Closing the channel terminates the loop in the worker thread
+        self.tx = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Loop of a worker thread.
+fn run_worker<E: Env>(
+    config: E::Config,
+    seed: i64,
+    rx: Receiver<Command<E::Act>>,
+    tx: Sender<Response<E>>,
+) {
+    let mut env = match E::build(&config, seed) {
+        Ok(env) => {
+            let _ = tx.send(Response::Built(Ok(())));
+            env
+        }
+        Err(e) => {
+            let _ = tx.send(Response::Built(Err(e)));
+            return;
+        }
+    };
+
+    while let Ok(command) = rx.recv() {
+        let response = match command {
+            Command::Step(a) => {
+                let (step, record) = env.step(&a);
+                Response::Step(step, record)
+            }
+            Command::StepWithReset(a) => {
+                let (step, record) = env.step_with_reset(&a);
+                Response::Step(step, record)
+            }
+            Command::Reset => Response::Obs(env.reset(None)),
+            Command::ResetWithIndex(ix) => Response::Obs(env.reset_with_index(ix)),
+        };
+        if tx.send(response).is_err() {
+            break;
+        }
+    }
+}
+
+/// A vectorized environment that steps `N` sub-environments concurrently.
+///
+/// Each sub-environment is built and owned by a dedicated thread, so the
+/// environment itself does not need to be [`Send`]. Actions are sent to all threads
+/// before the results are collected, so the sub-environments are stepped in parallel.
+/// The order of the sub-environments in observations and flags is deterministic.
+///
+/// The threads are terminated when this struct is dropped.
+///
+/// # Panics
+///
+/// Stepping panics if a worker thread has terminated unexpectedly,
+/// e.g., due to a panic in the sub-environment.
+pub struct ThreadedVecEnv<E: Env> {
+    /// Worker threads, each of which owns a sub-environment.
+    workers: Vec<Worker<E>>,
+
+    /// The latest observation of each sub-environment.
+    last_obs: Vec<Option<E::Obs>>,
+}
+
+impl<E: Env> ThreadedVecEnv<E> {
+    /// Returns the number of sub-environments.
+    pub fn n_envs(&self) -> usize {
+        self.workers.len()
+    }
+
+    fn step_inner(&mut self, a: &E::Act, with_reset: bool) -> Vec<(Step<E>, Record)>
+    where
+        E::Act: VecEnvAct,
+    {
+        let acts = a.clone().split(self.n_envs());
+        for (worker, a) in self.workers.iter().zip(acts) {
+            worker.send(match with_reset {
+                true => Command::StepWithReset(a),
+                false => Command::Step(a),
+            });
+        }
+        self.workers.iter().map(|w| w.recv_step()).collect()
+    }
+}
+
+impl<E> Env for ThreadedVecEnv<E>
+where
+    E: Env + 'static,
+    E::Config: Send + 'static,
+    E::Obs: VecEnvObs + Send + 'static,
+    E::Act: VecEnvAct + Send + 'static,
+    E::Info: Send + 'static,
+{
+    type Config = VecEnvConfig<E::Config>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = Vec<E::Info>;
+
+    /// Spawns worker threads, where the `i`-th sub-environment is given seed `seed + i`.
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let mut workers = Vec::with_capacity(config.n_envs);
+        for i in 0..config.n_envs {
+            let (tx_command, rx_command) = channel();
+            let (tx_response, rx_response) = channel();
+            let env_config = config.env_config.clone();
+            let seed = seed + i as i64;
+            let handle = std::thread::Builder::new()
+                .name(format!("vec_env_{}", i))
+                .spawn(move || run_worker::<E>(env_config, seed, rx_command, tx_response))?;
+            workers.push(Worker {
+                tx: Some(tx_command),
+                rx: rx_response,
+                handle: Some(handle),
+            });
+        }
+
+        for worker in workers.iter() {
+            match worker.recv() {
+                Response::Built(result) => result?,
+                _ => return Err(anyhow!("Failed to build a sub-environment")),
+            }
+        }
+
+        let last_obs = (0..config.n_envs).map(|_| None).collect();
+        Ok(Self { workers, last_obs })
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let steps = self.step_inner(a, false);
+        for (last_obs, (step, _)) in self.last_obs.iter_mut().zip(steps.iter()) {
+            *last_obs = Some(step.obs.clone());
+        }
+        merge_steps(a, steps, false)
+    }
+
+    /// Steps all sub-environments and resets the finished ones.
+    fn step_with_reset(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let steps = self.step_inner(a, true);
+        for (last_obs, (step, _)) in self.last_obs.iter_mut().zip(steps.iter()) {
+            *last_obs = match step.is_done() {
+                true => step.init_obs.clone(),
+                false => Some(step.obs.clone()),
+            };
+        }
+        merge_steps(a, steps, true)
+    }
+
+    /// Resets sub-environments.
+    ///
+    /// If `is_done` is `None`, all sub-environments are reset. Otherwise, the `i`-th
+    /// sub-environment is reset only if `is_done[i] == 1`.
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        if let Some(is_done) = is_done {
+            if is_done.len() != self.n_envs() {
+                return Err(anyhow!(
+                    "Length of is_done ({}) does not match the number of sub-environments ({})",
+                    is_done.len(),
+                    self.n_envs()
+                ));
+            }
+        }
+
+        let reset = (0..self.n_envs())
+            .map(|i| match is_done {
+                None => true,
+                Some(is_done) => is_done[i] == 1 || self.last_obs[i].is_none(),
+            })
+            .collect::<Vec<_>>();
+        for (worker, &reset) in self.workers.iter().zip(reset.iter()) {
+            if reset {
+                worker.send(Command::Reset);
+            }
+        }
+
+        let mut obs = Vec::with_capacity(self.n_envs());
+        for ((worker, last_obs), &reset) in self
+            .workers
+            .iter()
+            .zip(self.last_obs.iter_mut())
+            .zip(reset.iter())
+        {
+            if reset {
+                *last_obs = Some(worker.recv_obs()?);
+            }
+            obs.push(last_obs.clone().unwrap());
+        }
+
+        Ok(E::Obs::concat(obs))
+    }
+
+    /// Resets all sub-environments, where the `i`-th one is reset with index
+    /// `ix * n_envs + i`.
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        let n_envs = self.n_envs();
+        for (i, worker) in self.workers.iter().enumerate() {
+            worker.send(Command::ResetWithIndex(ix * n_envs + i));
+        }
+
+        let mut obs = Vec::with_capacity(n_envs);
+        for (worker, last_obs) in self.workers.iter().zip(self.last_obs.iter_mut()) {
+            let o = worker.recv_obs()?;
+            *last_obs = Some(o.clone());
+            obs.push(o);
+        }
+
+        Ok(E::Obs::concat(obs))
+    }
+}
@@ -0,0 +1,300 @@
+//! Wrappers of environments.
+//!
+//! This module provides [`EnvWrapper`], an environment that wraps another [`Env`] and exposes
+//! the same [`Env`] interface. Preprocessing of observations, actions and rewards, which is
+//! otherwise implemented in each environment crate, can be composed by nesting wrappers:
+//!
+//! ```ignore
+//! type Env = TimeLimit<FrameStack<ActionRepeat<GymEnv<NdarrayConverter>>>>;
+//!
+//! let env_config = WrapperConfig::new(gym_env_config, ActionRepeatConfig::new(4));
+//! let env_config = WrapperConfig::new(env_config, FrameStackConfig::new(4));
+//! let env_config = WrapperConfig::new(env_config, TimeLimitConfig::new(1000));
+//! let env = Env::build(&env_config, 42)?;
+//! ```
+//!
+//! A wrapped environment is configured with [`WrapperConfig`], which holds the configuration
+//! of the inner environment and that of the wrapper. Since the configuration of a wrapper
+//! includes the configuration of the inner environment, the environments for training and
+//! evaluation can be wrapped differently, e.g., rewards are clipped only in training.
+//!
+//! # Stock wrappers
+//!
+//! * [`TimeLimit`] truncates episodes after a number of steps.
+//! * [`FrameStack`] stacks the latest observations into an observation.
+//! * [`ActionRepeat`] repeats an action for a number of steps and sums the rewards.
+//! * [`RewardScale`] and [`RewardClip`] scale and clip rewards.
+//! * [`EpisodicLife`] terminates episodes when a life is lost.
+//! * [`RecordEpisodeStatistics`] records the return and length of episodes.
+//!
+//! [`TimeLimit`], [`RewardScale`], [`RewardClip`] and [`RecordEpisodeStatistics`] handle
+//! vectorized environments, with one value for each sub-environment. The others should wrap
+//! sub-environments, not a vectorized environment.
+mod action_repeat;
+mod episode_statistics;
+mod episodic_life;
+mod frame_stack;
+mod reward;
+mod time_limit;
+pub use action_repeat::{ActionRepeat, ActionRepeatConfig};
+pub use episode_statistics::{RecordEpisodeStatistics, RecordEpisodeStatisticsConfig};
+pub use episodic_life::{EpisodicLife, EpisodicLifeConfig, LivesInfo};
+pub use frame_stack::{FrameStack, FrameStackConfig, FrameStackObs};
+pub use reward::{RewardClip, RewardClipConfig, RewardScale, RewardScaleConfig};
+pub use time_limit::{TimeLimit, TimeLimitConfig};
+
+use crate::{Env, Step};
+use serde::{Deserialize, Serialize};
+
+/// An environment wrapping another environment.
+///
+/// A wrapper implements [`Env`] with [`WrapperConfig`] as its configuration, so it can be
+/// used wherever an environment is used, including as the inner environment of another
+/// wrapper. [`Env::build()`] of a wrapper builds the inner environment, then wraps it with
+/// [`EnvWrapper::wrap()`].
+pub trait EnvWrapper: Env {
+    /// The wrapped environment.
+    type Inner: Env;
+
+    /// Configuration of the wrapper, excluding that of the inner environment.
+    type WrapperConfig: Clone;
+
+    /// Wraps an environment.
+    fn wrap(env: Self::Inner, config: &Self::WrapperConfig) -> Self
+    where
+        Self: Sized;
+
+    /// Returns a reference to the wrapped environment.
+    fn inner(&self) -> &Self::Inner;
+
+    /// Returns a mutable reference to the wrapped environment.
+    fn inner_mut(&mut self) -> &mut Self::Inner;
+
+    /// Unwraps the environment.
+    fn into_inner(self) -> Self::Inner
+    where
+        Self: Sized;
+}
+
+/// Configuration of a wrapped environment.
+///
+/// # Type Parameters
+///
+/// * `C` - Configuration of the inner environment
+/// * `W` - Configuration of the wrapper
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct WrapperConfig<C, W> {
+    /// Configuration of the inner environment.
+    pub env_config: C,
+
+    /// Configuration of the wrapper.
+    pub wrapper_config: W,
+}
+
+impl<C, W> WrapperConfig<C, W> {
+    /// Creates a configuration of an environment wrapped with a wrapper.
+    pub fn new(env_config: C, wrapper_config: W) -> Self {
+        Self {
+            env_config,
+            wrapper_config,
+        }
+    }
+
+    /// Sets the configuration of the inner environment.
+    pub fn env_config(mut self, env_config: C) -> Self {
+        self.env_config = env_config;
+        self
+    }
+
+    /// Sets the configuration of the wrapper.
+    pub fn wrapper_config(mut self, wrapper_config: W) -> Self {
+        self.wrapper_config = wrapper_config;
+        self
+    }
+}
+
+/// Converts a step of the inner environment into a step of the wrapper.
+fn convert_step<E, W>(step: Step<E>) -> Step<W>
+where
+    E: Env,
+    W: Env<Obs = E::Obs, Act = E::Act, Info = E::Info>,
+{
+    Step::new(
+        step.obs,
+        step.act,
+        step.reward,
+        step.is_terminated,
+        step.is_truncated,
+        step.info,
+        step.init_obs,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{record::Record, Act, Info, Obs};
+    use anyhow::Result;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct CountObs(Vec<usize>);
+
+    impl Obs for CountObs {
+        fn len(&self) -> usize {
+            1
+        }
+    }
+
+    impl FrameStackObs for CountObs {
+        fn stack(frames: &[Self]) -> Self {
+            Self(frames.iter().flat_map(|o| o.0.clone()).collect())
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct CountAct(usize);
+
+    impl Act for CountAct {}
+
+    struct CountInfo {
+        lives: usize,
+    }
+
+    impl Info for CountInfo {}
+
+    impl LivesInfo for CountInfo {
+        fn lives(&self) -> usize {
+            self.lives
+        }
+    }
+
+    /// Counts up the state, gives a reward of 2 for each step and loses a life every
+    /// 2 counts. The episode terminates when the state reaches the given length.
+    struct CountEnv {
+        episode_len: usize,
+        state: usize,
+    }
+
+    impl Env for CountEnv {
+        type Config = usize;
+        type Obs = CountObs;
+        type Act = CountAct;
+        type Info = CountInfo;
+
+        fn build(config: &Self::Config, _seed: i64) -> Result<Self> {
+            Ok(Self {
+                episode_len: *config,
+                state: 0,
+            })
+        }
+
+        fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+            self.state += a.0;
+            let is_terminated = (self.state >= self.episode_len) as i8;
+            let lives = (self.episode_len + 1).saturating_sub(self.state) / 2;
+            let step = Step::new(
+                CountObs(vec![self.state]),
+                a.clone(),
+                vec![2.0],
+                vec![is_terminated],
+                vec![0],
+                CountInfo { lives },
+                None,
+            );
+            (step, Record::empty())
+        }
+
+        fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+            self.state = 0;
+            Ok(CountObs(vec![self.state]))
+        }
+
+        fn reset_with_index(&mut self, _ix: usize) -> Result<Self::Obs> {
+            self.reset(None)
+        }
+    }
+
+    #[test]
+    fn test_time_limit_and_action_repeat() -> Result<()> {
+        let config = WrapperConfig::new(10usize, ActionRepeatConfig::new(3));
+        let config = WrapperConfig::new(config, TimeLimitConfig::new(2));
+        let mut env = TimeLimit::<ActionRepeat<CountEnv>>::build(&config, 0)?;
+        env.reset(None)?;
+
+        let (step, _) = env.step_with_reset(&CountAct(1));
+        assert_eq!(step.obs, CountObs(vec![3]));
+        assert_eq!(step.reward, vec![6.0]);
+        assert!(!step.is_done());
+
+        let (step, _) = env.step_with_reset(&CountAct(1));
+        assert_eq!(step.obs, CountObs(vec![6]));
+        assert_eq!(step.is_truncated, vec![1]);
+        assert_eq!(step.init_obs, Some(CountObs(vec![0])));
+
+        // Repeating an action stops at the end of the episode
+        let (step, _) = env.step_with_reset(&CountAct(5));
+        assert_eq!(step.obs, CountObs(vec![10]));
+        assert_eq!(step.reward, vec![4.0]);
+        assert_eq!(step.is_terminated, vec![1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frame_stack() -> Result<()> {
+        let config = WrapperConfig::new(10usize, FrameStackConfig::new(3));
+        let mut env = FrameStack::<CountEnv>::build(&config, 0)?;
+        assert_eq!(env.reset(None)?, CountObs(vec![0, 0, 0]));
+
+        let (step, _) = env.step(&CountAct(1));
+        assert_eq!(step.obs, CountObs(vec![0, 0, 1]));
+        let (step, _) = env.step(&CountAct(2));
+        assert_eq!(step.obs, CountObs(vec![0, 1, 3]));
+        let (step, _) = env.step(&CountAct(3));
+        assert_eq!(step.obs, CountObs(vec![1, 3, 6]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reward_and_episode_statistics() -> Result<()> {
+        let config = WrapperConfig::new(4usize, RecordEpisodeStatisticsConfig::default());
+        let config = WrapperConfig::new(config, RewardScaleConfig::new(0.5));
+        let config = WrapperConfig::new(config, RewardClipConfig::new(-0.5, 0.5));
+        let mut env =
+            RewardClip::<RewardScale<RecordEpisodeStatistics<CountEnv>>>::build(&config, 0)?;
+        env.reset(None)?;
+
+        let (step, record) = env.step_with_reset(&CountAct(2));
+        assert_eq!(step.reward, vec![0.5]);
+        assert!(record.get("episode_return").is_none());
+
+        // Statistics are computed with raw rewards of the inner environment
+        let (_, record) = env.step_with_reset(&CountAct(2));
+        assert_eq!(record.get_scalar("episode_return")?, 4.0);
+        assert_eq!(record.get_scalar("episode_length")?, 2.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_episodic_life() -> Result<()> {
+        let config = WrapperConfig::new(4usize, EpisodicLifeConfig::default());
+        let mut env = EpisodicLife::<CountEnv>::build(&config, 0)?;
+        env.reset(None)?;
+
+        // Losing a life terminates the episode, but the game continues
+        let (step, _) = env.step_with_reset(&CountAct(1));
+        assert!(!step.is_done());
+        let (step, _) = env.step_with_reset(&CountAct(1));
+        assert_eq!(step.is_terminated, vec![1]);
+        assert_eq!(step.init_obs, Some(CountObs(vec![2])));
+
+        // The game is reset when it is really over
+        let (step, _) = env.step_with_reset(&CountAct(2));
+        assert_eq!(step.is_terminated, vec![1]);
+        assert_eq!(step.init_obs, Some(CountObs(vec![0])));
+
+        Ok(())
+    }
+}
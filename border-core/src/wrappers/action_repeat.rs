@@ -0,0 +1,98 @@
+//! Wrapper repeating actions.
+use super::{convert_step, EnvWrapper, WrapperConfig};
+use crate::{record::Record, Env, Step};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Configuration of [`ActionRepeat`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ActionRepeatConfig {
+    /// The number of times an action is repeated.
+    pub n_repeats: usize,
+}
+
+impl ActionRepeatConfig {
+    /// Creates a configuration with the number of times an action is repeated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_repeats` is 0.
+    pub fn new(n_repeats: usize) -> Self {
+        assert!(n_repeats > 0, "n_repeats must be positive");
+        Self { n_repeats }
+    }
+}
+
+/// Repeats an action for a number of steps, which is also known as frame skipping.
+///
+/// The returned step has the observation, flags and information of the last step and the
+/// sum of the rewards. Repetition stops when the episode ends. Records of the steps are
+/// merged into a record.
+pub struct ActionRepeat<E: Env> {
+    env: E,
+    n_repeats: usize,
+}
+
+impl<E: Env> EnvWrapper for ActionRepeat<E> {
+    type Inner = E;
+    type WrapperConfig = ActionRepeatConfig;
+
+    fn wrap(env: E, config: &Self::WrapperConfig) -> Self {
+        Self {
+            env,
+            n_repeats: config.n_repeats,
+        }
+    }
+
+    fn inner(&self) -> &E {
+        &self.env
+    }
+
+    fn inner_mut(&mut self) -> &mut E {
+        &mut self.env
+    }
+
+    fn into_inner(self) -> E {
+        self.env
+    }
+}
+
+impl<E: Env> Env for ActionRepeat<E> {
+    type Config = WrapperConfig<E::Config, ActionRepeatConfig>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = E::Info;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let env = E::build(&config.env_config, seed)?;
+        Ok(Self::wrap(env, &config.wrapper_config))
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (mut step, mut record) = self.env.step(a);
+        for _ in 1..self.n_repeats {
+            if step.is_done() {
+                break;
+            }
+            let (step_, record_) = self.env.step(a);
+            let reward = step
+                .reward
+                .iter()
+                .zip(step_.reward.iter())
+                .map(|(r, r_)| r + r_)
+                .collect();
+            step = step_;
+            step.reward = reward;
+            record.merge_inplace(record_);
+        }
+        (convert_step(step), record)
+    }
+
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        self.env.reset(is_done)
+    }
+
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.env.reset_with_index(ix)
+    }
+}
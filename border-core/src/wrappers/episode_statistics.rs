@@ -0,0 +1,104 @@
+//! Wrapper recording statistics of episodes.
+use super::{convert_step, EnvWrapper, WrapperConfig};
+use crate::{
+    record::{Record, RecordValue},
+    Env, Step,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Configuration of [`RecordEpisodeStatistics`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct RecordEpisodeStatisticsConfig {}
+
+/// Records the return and length of episodes.
+///
+/// When an episode ends, `episode_return` and `episode_length` are added to the record
+/// returned by [`Env::step()`]. For vectorized environments, they are averaged over the
+/// sub-environments whose episodes end at the step.
+///
+/// The return is computed with the rewards of the inner environment, so this wrapper should
+/// be placed inside the wrappers transforming rewards to record raw returns.
+pub struct RecordEpisodeStatistics<E: Env> {
+    env: E,
+    returns: Vec<f32>,
+    lengths: Vec<usize>,
+}
+
+impl<E: Env> EnvWrapper for RecordEpisodeStatistics<E> {
+    type Inner = E;
+    type WrapperConfig = RecordEpisodeStatisticsConfig;
+
+    fn wrap(env: E, _config: &Self::WrapperConfig) -> Self {
+        Self {
+            env,
+            returns: vec![],
+            lengths: vec![],
+        }
+    }
+
+    fn inner(&self) -> &E {
+        &self.env
+    }
+
+    fn inner_mut(&mut self) -> &mut E {
+        &mut self.env
+    }
+
+    fn into_inner(self) -> E {
+        self.env
+    }
+}
+
+impl<E: Env> Env for RecordEpisodeStatistics<E> {
+    type Config = WrapperConfig<E::Config, RecordEpisodeStatisticsConfig>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = E::Info;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let env = E::build(&config.env_config, seed)?;
+        Ok(Self::wrap(env, &config.wrapper_config))
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (step, mut record) = self.env.step(a);
+        self.returns.resize(step.n_envs(), 0.0);
+        self.lengths.resize(step.n_envs(), 0);
+
+        let mut finished = vec![];
+        for ix in 0..step.n_envs() {
+            self.returns[ix] += step.reward[ix];
+            self.lengths[ix] += 1;
+            if step.is_done_at(ix) {
+                finished.push((self.returns[ix], self.lengths[ix]));
+                self.returns[ix] = 0.0;
+                self.lengths[ix] = 0;
+            }
+        }
+
+        if !finished.is_empty() {
+            let n = finished.len() as f32;
+            let ret = finished.iter().map(|(r, _)| r).sum::<f32>() / n;
+            let len = finished.iter().map(|(_, l)| *l as f32).sum::<f32>() / n;
+            record.insert("episode_return", RecordValue::Scalar(ret));
+            record.insert("episode_length", RecordValue::Scalar(len));
+        }
+
+        (convert_step(step), record)
+    }
+
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        if is_done.is_none() {
+            self.returns.clear();
+            self.lengths.clear();
+        }
+        self.env.reset(is_done)
+    }
+
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.returns.clear();
+        self.lengths.clear();
+        self.env.reset_with_index(ix)
+    }
+}
@@ -0,0 +1,111 @@
+//! Wrapper terminating episodes when a life is lost.
+use super::{convert_step, EnvWrapper, WrapperConfig};
+use crate::{record::Record, Env, Info, Step};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Information with the number of lives, e.g., in Atari games.
+pub trait LivesInfo: Info {
+    /// Returns the number of remaining lives.
+    fn lives(&self) -> usize;
+}
+
+/// Configuration of [`EpisodicLife`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct EpisodicLifeConfig {}
+
+/// Terminates episodes when a life is lost, while the game continues.
+///
+/// When the number of lives given by [`LivesInfo::lives()`] decreases, `is_terminated` of the
+/// step is set to 1. [`Env::reset()`] resets the inner environment only if the game is over.
+/// Otherwise, it returns the last observation, from which the game continues.
+///
+/// This wrapper is used only in training. Scores should be evaluated with the whole game.
+pub struct EpisodicLife<E: Env>
+where
+    E::Info: LivesInfo,
+{
+    env: E,
+    lives: Option<usize>,
+    was_real_done: bool,
+    last_obs: Option<E::Obs>,
+}
+
+impl<E> EnvWrapper for EpisodicLife<E>
+where
+    E: Env,
+    E::Info: LivesInfo,
+{
+    type Inner = E;
+    type WrapperConfig = EpisodicLifeConfig;
+
+    fn wrap(env: E, _config: &Self::WrapperConfig) -> Self {
+        Self {
+            env,
+            lives: None,
+            was_real_done: true,
+            last_obs: None,
+        }
+    }
+
+    fn inner(&self) -> &E {
+        &self.env
+    }
+
+    fn inner_mut(&mut self) -> &mut E {
+        &mut self.env
+    }
+
+    fn into_inner(self) -> E {
+        self.env
+    }
+}
+
+impl<E> Env for EpisodicLife<E>
+where
+    E: Env,
+    E::Info: LivesInfo,
+{
+    type Config = WrapperConfig<E::Config, EpisodicLifeConfig>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = E::Info;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let env = E::build(&config.env_config, seed)?;
+        Ok(Self::wrap(env, &config.wrapper_config))
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (mut step, record) = self.env.step(a);
+        self.was_real_done = step.is_done();
+
+        let lives = step.info.lives();
+        if let Some(prev_lives) = self.lives {
+            if lives < prev_lives && lives > 0 {
+                step.is_terminated[0] = 1;
+            }
+        }
+        self.lives = Some(lives);
+        self.last_obs = Some(step.obs.clone());
+
+        (convert_step(step), record)
+    }
+
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        match (self.was_real_done, self.last_obs.take()) {
+            (false, Some(obs)) => Ok(obs),
+            _ => {
+                self.lives = None;
+                self.env.reset(is_done)
+            }
+        }
+    }
+
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.lives = None;
+        self.was_real_done = true;
+        self.last_obs = None;
+        self.env.reset_with_index(ix)
+    }
+}
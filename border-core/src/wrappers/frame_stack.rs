@@ -0,0 +1,132 @@
+//! Wrapper stacking the latest observations.
+use super::{convert_step, EnvWrapper, WrapperConfig};
+use crate::{record::Record, Env, Obs, Step};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Observations that can be stacked over time steps.
+pub trait FrameStackObs: Obs {
+    /// Stacks observations, ordered from the oldest to the latest, into an observation.
+    ///
+    /// For image observations, frames are typically stacked along the channel axis.
+    fn stack(frames: &[Self]) -> Self;
+}
+
+/// Configuration of [`FrameStack`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct FrameStackConfig {
+    /// The number of stacked observations.
+    pub n_frames: usize,
+}
+
+impl FrameStackConfig {
+    /// Creates a configuration with the number of stacked observations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_frames` is 0.
+    pub fn new(n_frames: usize) -> Self {
+        assert!(n_frames > 0, "n_frames must be positive");
+        Self { n_frames }
+    }
+}
+
+/// Stacks the latest observations into an observation with [`FrameStackObs::stack()`].
+///
+/// At the beginning of an episode, the buffer of observations is filled with the initial
+/// observation.
+pub struct FrameStack<E: Env>
+where
+    E::Obs: FrameStackObs,
+{
+    env: E,
+    n_frames: usize,
+    frames: VecDeque<E::Obs>,
+}
+
+impl<E> FrameStack<E>
+where
+    E: Env,
+    E::Obs: FrameStackObs,
+{
+    fn fill(&mut self, obs: E::Obs) -> E::Obs {
+        self.frames.clear();
+        self.frames.resize(self.n_frames, obs);
+        self.stack()
+    }
+
+    fn push(&mut self, obs: E::Obs) -> E::Obs {
+        self.frames.pop_front();
+        self.frames.push_back(obs);
+        self.stack()
+    }
+
+    fn stack(&mut self) -> E::Obs {
+        E::Obs::stack(self.frames.make_contiguous())
+    }
+}
+
+impl<E> EnvWrapper for FrameStack<E>
+where
+    E: Env,
+    E::Obs: FrameStackObs,
+{
+    type Inner = E;
+    type WrapperConfig = FrameStackConfig;
+
+    fn wrap(env: E, config: &Self::WrapperConfig) -> Self {
+        Self {
+            env,
+            n_frames: config.n_frames,
+            frames: VecDeque::with_capacity(config.n_frames),
+        }
+    }
+
+    fn inner(&self) -> &E {
+        &self.env
+    }
+
+    fn inner_mut(&mut self) -> &mut E {
+        &mut self.env
+    }
+
+    fn into_inner(self) -> E {
+        self.env
+    }
+}
+
+impl<E> Env for FrameStack<E>
+where
+    E: Env,
+    E::Obs: FrameStackObs,
+{
+    type Config = WrapperConfig<E::Config, FrameStackConfig>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = E::Info;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let env = E::build(&config.env_config, seed)?;
+        Ok(Self::wrap(env, &config.wrapper_config))
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (mut step, record) = self.env.step(a);
+        step.obs = match self.frames.is_empty() {
+            true => self.fill(step.obs),
+            false => self.push(step.obs),
+        };
+        (convert_step(step), record)
+    }
+
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        let obs = self.env.reset(is_done)?;
+        Ok(self.fill(obs))
+    }
+
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        let obs = self.env.reset_with_index(ix)?;
+        Ok(self.fill(obs))
+    }
+}
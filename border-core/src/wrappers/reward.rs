@@ -0,0 +1,187 @@
+//! Wrappers transforming rewards.
+use super::{convert_step, EnvWrapper, WrapperConfig};
+use crate::{record::Record, Env, Step};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Configuration of [`RewardScale`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RewardScaleConfig {
+    /// Scale multiplied to rewards.
+    pub scale: f32,
+}
+
+impl RewardScaleConfig {
+    /// Creates a configuration with the scale multiplied to rewards.
+    pub fn new(scale: f32) -> Self {
+        Self { scale }
+    }
+}
+
+/// Multiplies rewards by a constant.
+pub struct RewardScale<E: Env> {
+    env: E,
+    scale: f32,
+}
+
+impl<E: Env> EnvWrapper for RewardScale<E> {
+    type Inner = E;
+    type WrapperConfig = RewardScaleConfig;
+
+    fn wrap(env: E, config: &Self::WrapperConfig) -> Self {
+        Self {
+            env,
+            scale: config.scale,
+        }
+    }
+
+    fn inner(&self) -> &E {
+        &self.env
+    }
+
+    fn inner_mut(&mut self) -> &mut E {
+        &mut self.env
+    }
+
+    fn into_inner(self) -> E {
+        self.env
+    }
+}
+
+impl<E: Env> Env for RewardScale<E> {
+    type Config = WrapperConfig<E::Config, RewardScaleConfig>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = E::Info;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let env = E::build(&config.env_config, seed)?;
+        Ok(Self::wrap(env, &config.wrapper_config))
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (mut step, record) = self.env.step(a);
+        step.reward.iter_mut().for_each(|r| *r *= self.scale);
+        (convert_step(step), record)
+    }
+
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        self.env.reset(is_done)
+    }
+
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.env.reset_with_index(ix)
+    }
+}
+
+/// Configuration of [`RewardClip`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RewardClipConfig {
+    /// The lower bound of rewards.
+    pub min: f32,
+
+    /// The upper bound of rewards.
+    pub max: f32,
+
+    /// If `true`, rewards are replaced with their signs before clipping.
+    pub sign: bool,
+}
+
+impl Default for RewardClipConfig {
+    fn default() -> Self {
+        Self {
+            min: -1.0,
+            max: 1.0,
+            sign: false,
+        }
+    }
+}
+
+impl RewardClipConfig {
+    /// Creates a configuration clipping rewards to `[min, max]`.
+    pub fn new(min: f32, max: f32) -> Self {
+        Self {
+            min,
+            max,
+            sign: false,
+        }
+    }
+
+    /// Creates a configuration replacing rewards with their signs, i.e., -1, 0 or 1.
+    ///
+    /// This is the reward clipping commonly applied to Atari games.
+    pub fn sign() -> Self {
+        Self {
+            sign: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// Clips rewards to a range.
+///
+/// Rewards of evaluation environments should not be clipped, so that scores are comparable.
+pub struct RewardClip<E: Env> {
+    env: E,
+    config: RewardClipConfig,
+}
+
+impl<E: Env> RewardClip<E> {
+    fn clip(&self, r: f32) -> f32 {
+        let r = match self.config.sign && r != 0.0 {
+            true => r.signum(),
+            false => r,
+        };
+        r.clamp(self.config.min, self.config.max)
+    }
+}
+
+impl<E: Env> EnvWrapper for RewardClip<E> {
+    type Inner = E;
+    type WrapperConfig = RewardClipConfig;
+
+    fn wrap(env: E, config: &Self::WrapperConfig) -> Self {
+        Self {
+            env,
+            config: config.clone(),
+        }
+    }
+
+    fn inner(&self) -> &E {
+        &self.env
+    }
+
+    fn inner_mut(&mut self) -> &mut E {
+        &mut self.env
+    }
+
+    fn into_inner(self) -> E {
+        self.env
+    }
+}
+
+impl<E: Env> Env for RewardClip<E> {
+    type Config = WrapperConfig<E::Config, RewardClipConfig>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = E::Info;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let env = E::build(&config.env_config, seed)?;
+        Ok(Self::wrap(env, &config.wrapper_config))
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (mut step, record) = self.env.step(a);
+        step.reward = step.reward.iter().map(|r| self.clip(*r)).collect();
+        (convert_step(step), record)
+    }
+
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        self.env.reset(is_done)
+    }
+
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.env.reset_with_index(ix)
+    }
+}
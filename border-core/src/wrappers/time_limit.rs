@@ -0,0 +1,98 @@
+//! Wrapper limiting the length of episodes.
+use super::{convert_step, EnvWrapper, WrapperConfig};
+use crate::{record::Record, Env, Step};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Configuration of [`TimeLimit`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TimeLimitConfig {
+    /// The maximum number of steps in an episode.
+    pub max_steps: usize,
+}
+
+impl TimeLimitConfig {
+    /// Creates a configuration with the maximum number of steps in an episode.
+    pub fn new(max_steps: usize) -> Self {
+        Self { max_steps }
+    }
+}
+
+/// Truncates episodes after a number of steps.
+///
+/// When the number of steps in an episode reaches [`TimeLimitConfig::max_steps`],
+/// `is_truncated` of the step is set to 1. Steps are counted for each sub-environment
+/// of a vectorized environment, and the counts are reset with [`Env::reset()`].
+pub struct TimeLimit<E: Env> {
+    env: E,
+    max_steps: usize,
+    count_steps: Vec<usize>,
+}
+
+impl<E: Env> EnvWrapper for TimeLimit<E> {
+    type Inner = E;
+    type WrapperConfig = TimeLimitConfig;
+
+    fn wrap(env: E, config: &Self::WrapperConfig) -> Self {
+        Self {
+            env,
+            max_steps: config.max_steps,
+            count_steps: vec![],
+        }
+    }
+
+    fn inner(&self) -> &E {
+        &self.env
+    }
+
+    fn inner_mut(&mut self) -> &mut E {
+        &mut self.env
+    }
+
+    fn into_inner(self) -> E {
+        self.env
+    }
+}
+
+impl<E: Env> Env for TimeLimit<E> {
+    type Config = WrapperConfig<E::Config, TimeLimitConfig>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = E::Info;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let env = E::build(&config.env_config, seed)?;
+        Ok(Self::wrap(env, &config.wrapper_config))
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (mut step, record) = self.env.step(a);
+        self.count_steps.resize(step.n_envs(), 0);
+        for (ix, count) in self.count_steps.iter_mut().enumerate() {
+            *count += 1;
+            if *count >= self.max_steps {
+                step.is_truncated[ix] = 1;
+            }
+        }
+        (convert_step(step), record)
+    }
+
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        match is_done {
+            None => self.count_steps.clear(),
+            Some(is_done) => {
+                for (count, is_done) in self.count_steps.iter_mut().zip(is_done.iter()) {
+                    if *is_done == 1 {
+                        *count = 0;
+                    }
+                }
+            }
+        }
+        self.env.reset(is_done)
+    }
+
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.count_steps.clear();
+        self.env.reset_with_index(ix)
+    }
+}
@@ -1,14 +1,20 @@
 //! Observation, action types and corresponding converters for the AntMaze environment implemented with candle.
 use crate::{
-    util::ndarray::{arrayd_to_pyobj, pyobj_to_arrayd},
+    util::{
+        candle::{load_tensor, save_tensor},
+        ndarray::{arrayd_to_pyobj, pyobj_to_arrayd},
+    },
     MinariConverter, MinariDataset,
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use border_core::generic_replay_buffer::BatchBase;
 use candle_core::{DType, Device, Tensor};
 use ndarray::{ArrayBase, ArrayD, Axis, Slice};
 use pyo3::{types::PyIterator, PyAny, PyObject, Python};
-use std::convert::{TryFrom, TryInto};
+use std::{
+    convert::{TryFrom, TryInto},
+    path::Path,
+};
 
 const DIM_OBS: usize = 27;
 const DIM_ACT: usize = 8;
@@ -41,7 +47,9 @@ mod obs {
 }
 
 mod obs_batch {
-    use super::{AntMazeObs, BatchBase, DType, Device, Tensor, DIM_ACT};
+    use super::{
+        anyhow, save_tensor, AntMazeObs, BatchBase, DType, Device, Path, Result, Tensor, DIM_ACT,
+    };
 
     /// Batch of observations.
     ///
@@ -94,6 +102,45 @@ mod obs_batch {
                 obs: Some(self.obs.as_ref().unwrap().index_select(&ixs, 0).unwrap()),
             }
         }
+
+        /// Saves the first `len` observations.
+        ///
+        /// Nothing is saved if the internal buffer has not been initialized.
+        fn save(&self, path: &Path, len: usize) -> Result<()> {
+            match &self.obs {
+                Some(obs) => save_tensor(path, obs, len),
+                None => Ok(()),
+            }
+        }
+
+        /// Loads the saved observations.
+        ///
+        /// If no observations were saved, nothing is loaded.
+        fn load(&mut self, path: &Path) -> Result<usize> {
+            let path = path.join("data.npy");
+            if !path.exists() {
+                return Ok(0);
+            }
+
+            let obs = Tensor::read_npy(&path)?.to_dtype(DType::F32)?;
+            let len = obs.dims()[0];
+            if len > self.capacity {
+                return Err(anyhow!(
+                    "{:?} has {} observations, more than the capacity {}",
+                    path,
+                    len,
+                    self.capacity
+                ));
+            }
+            self.push(
+                0,
+                Self {
+                    capacity: len,
+                    obs: Some(obs),
+                },
+            );
+            Ok(len)
+        }
     }
 
     impl From<AntMazeObs> for AntMazeObsBatch {
@@ -147,7 +194,10 @@ mod act {
 }
 
 mod act_batch {
-    use super::{AntMazeAct, BatchBase, DType, Device, Tensor, TryInto, DIM_ACT};
+    use super::{
+        load_tensor, save_tensor, AntMazeAct, BatchBase, DType, Device, Path, Result, Tensor,
+        TryInto, DIM_ACT,
+    };
 
     /// Batch of actions.
     ///
@@ -198,6 +248,14 @@ mod act_batch {
 
             Self { action }
         }
+
+        fn save(&self, path: &Path, len: usize) -> Result<()> {
+            save_tensor(path, &self.action, len)
+        }
+
+        fn load(&mut self, path: &Path) -> Result<usize> {
+            load_tensor(path, &self.action)
+        }
     }
 
     impl From<AntMazeAct> for AntMazeActBatch {
@@ -1,8 +1,8 @@
 //! Observation, action types and corresponding converters for the AntMaze environment implemented with ndarray.
-use std::fmt::Debug;
+use std::{fmt::Debug, path::Path};
 
 use crate::{
-    util::ndarray::{arrayd_to_pyobj, pyobj_to_arrayd},
+    util::ndarray::{arrayd_to_pyobj, load_arrayd, pyobj_to_arrayd, save_arrayd},
     MinariConverter,
 };
 use anyhow::Result;
@@ -59,6 +59,14 @@ impl BatchBase for AntMazeObsBatch {
             obs: self.obs.select(Axis(0), ixs),
         }
     }
+
+    fn save(&self, path: &Path, len: usize) -> Result<()> {
+        save_arrayd(path, &self.obs, len)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<usize> {
+        load_arrayd(path, &mut self.obs)
+    }
 }
 
 impl From<AntMazeObs> for AntMazeObsBatch {
@@ -108,6 +116,14 @@ impl BatchBase for AntMazeActBatch {
             action: self.action.select(Axis(0), ixs),
         }
     }
+
+    fn save(&self, path: &Path, len: usize) -> Result<()> {
+        save_arrayd(path, &self.action, len)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<usize> {
+        load_arrayd(path, &mut self.action)
+    }
 }
 
 impl From<AntMazeAct> for AntMazeActBatch {
@@ -2,10 +2,14 @@
 use std::{
     convert::{TryFrom, TryInto},
     fmt::Debug,
+    path::Path,
 };
 
 use crate::{
-    util::ndarray::{arrayd_to_pyobj, pyobj_to_arrayd},
+    util::{
+        candle::{load_tensor, save_tensor},
+        ndarray::{arrayd_to_pyobj, pyobj_to_arrayd},
+    },
     MinariConverter,
 };
 use anyhow::Result;
@@ -74,6 +78,14 @@ impl BatchBase for KitchenObsBatch {
             obs: self.obs.index_select(&ixs, 0).unwrap(),
         }
     }
+
+    fn save(&self, path: &Path, len: usize) -> Result<()> {
+        save_tensor(path, &self.obs, len)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<usize> {
+        load_tensor(path, &self.obs)
+    }
 }
 
 impl From<KitchenObs> for KitchenObsBatch {
@@ -157,6 +169,14 @@ impl BatchBase for KitchenActBatch {
 
         Self { action }
     }
+
+    fn save(&self, path: &Path, len: usize) -> Result<()> {
+        save_tensor(path, &self.action, len)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<usize> {
+        load_tensor(path, &self.action)
+    }
 }
 
 impl From<KitchenAct> for KitchenActBatch {
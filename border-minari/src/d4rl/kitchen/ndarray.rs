@@ -1,8 +1,8 @@
 //! Observation, action types and corresponding converters for the Kitchen environment implemented with ndarray.
-use std::fmt::Debug;
+use std::{fmt::Debug, path::Path};
 
 use crate::{
-    util::ndarray::{arrayd_to_pyobj, pyobj_to_arrayd},
+    util::ndarray::{arrayd_to_pyobj, load_arrayd, pyobj_to_arrayd, save_arrayd},
     MinariConverter,
 };
 use anyhow::Result;
@@ -56,6 +56,14 @@ impl BatchBase for KitchenObsBatch {
             obs: self.obs.select(Axis(0), ixs),
         }
     }
+
+    fn save(&self, path: &Path, len: usize) -> Result<()> {
+        save_arrayd(path, &self.obs, len)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<usize> {
+        load_arrayd(path, &mut self.obs)
+    }
 }
 
 impl From<KitchenObs> for KitchenObsBatch {
@@ -105,6 +113,14 @@ impl BatchBase for KitchenActBatch {
             action: self.action.select(Axis(0), ixs),
         }
     }
+
+    fn save(&self, path: &Path, len: usize) -> Result<()> {
+        save_arrayd(path, &self.action, len)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<usize> {
+        load_arrayd(path, &mut self.action)
+    }
 }
 
 impl From<KitchenAct> for KitchenActBatch {
@@ -7,8 +7,9 @@ use crate::{
     MinariConverter, MinariDataset,
 };
 use anyhow::Result;
+use border_core::NormalizeObs;
 use candle_core::Tensor;
-use ndarray::{concatenate, ArrayD, Axis, IxDyn, Slice};
+use ndarray::{ArrayD, Axis, Slice};
 use pyo3::{types::PyIterator, PyAny, PyObject, Python};
 use std::convert::TryFrom;
 
@@ -18,11 +19,28 @@ pub type PenActBatch = TensorBatch;
 pub type PenObsBatch = TensorBatch;
 
 /// Configuration of [`PenConverter`].
-pub struct PenConverterConfig {}
+pub struct PenConverterConfig {
+    /// Normalizer of observations.
+    ///
+    /// If it has no statistics, they are computed from the observations in the dataset.
+    pub normalize_obs: NormalizeObs,
+}
 
 impl Default for PenConverterConfig {
     fn default() -> Self {
-        Self {}
+        Self {
+            normalize_obs: NormalizeObs::default(),
+        }
+    }
+}
+
+impl PenConverterConfig {
+    /// Sets the normalizer of observations.
+    ///
+    /// This is useful to reuse the statistics loaded with [`NormalizeObs::load()`].
+    pub fn normalize_obs(mut self, v: NormalizeObs) -> Self {
+        self.normalize_obs = v;
+        self
     }
 }
 
@@ -31,45 +49,54 @@ impl Default for PenConverterConfig {
 /// This struct normalizes observations based on the statistics
 /// of the observations in the dataset.
 pub struct PenConverter {
-    mean: ArrayD<f32>, // for normalizing observation
-    std: ArrayD<f32>,  // for normalizing observation
+    normalize_obs: NormalizeObs,
 }
 
 impl PenConverter {
     /// Creates a new Pen converter.
     ///
-    /// `dataset` is used to calculate the mean and standard deviation of the observations.
-    pub fn new(_config: PenConverterConfig, dataset: &MinariDataset) -> Result<Self> {
-        let (mean, std) = Python::with_gil(|py| -> Result<(ArrayD<f32>, ArrayD<f32>)> {
-            // Iterate all episodes
-            let episodes = dataset
-                .dataset
-                .call_method1(py, "iterate_episodes", (None::<i32>,))?;
-            let mut all_obs = ArrayD::<f32>::zeros(IxDyn(&[0, 45]));
-
-            // Collect all observations for calculating mean and std
-            for ep in PyIterator::from_object(py, &episodes)? {
-                // ep is minari.dataset.episode_data.EpisodeData
-                let ep = ep?;
-                let obj = ep.getattr("observations")?;
-
-                let obs_batch = pyobj_to_ndarray1(obj)?;
-                all_obs = concatenate![Axis(0), all_obs, obs_batch];
-            }
-
-            // Calculate mean and std
-            let mean = all_obs.mean_axis(Axis(0)).unwrap().insert_axis(Axis(0));
-            let std = all_obs.std_axis(Axis(0), 1.0).insert_axis(Axis(0));
-            debug_assert_eq!(mean.shape(), &[1, 45]);
-
-            Ok((mean, std))
-        })?;
+    /// `dataset` is used to calculate the mean and standard deviation of the observations,
+    /// which are frozen after that.
+    pub fn new(config: PenConverterConfig, dataset: &MinariDataset) -> Result<Self> {
+        let normalize_obs = config.normalize_obs;
+
+        if normalize_obs.stats().is_none() {
+            Python::with_gil(|py| -> Result<()> {
+                // Iterate all episodes
+                let episodes =
+                    dataset
+                        .dataset
+                        .call_method1(py, "iterate_episodes", (None::<i32>,))?;
+
+                // Update statistics with observations in all episodes
+                for ep in PyIterator::from_object(py, &episodes)? {
+                    // ep is minari.dataset.episode_data.EpisodeData
+                    let ep = ep?;
+                    let obj = ep.getattr("observations")?;
+
+                    let obs_batch = pyobj_to_ndarray1(obj)?;
+                    normalize_obs.update(obs_batch.as_slice().unwrap(), 45);
+                }
+
+                Ok(())
+            })?;
+        }
+        normalize_obs.set_frozen(true);
+
+        Ok(Self { normalize_obs })
+    }
 
-        Ok(Self { mean, std })
+    /// Returns the normalizer of observations.
+    ///
+    /// Its statistics can be saved with [`NormalizeObs::save()`] to be reused in evaluation.
+    pub fn normalize_obs(&self) -> &NormalizeObs {
+        &self.normalize_obs
     }
 
     fn normalize_observation(&self, obs: &NdarrayObs) -> Result<NdarrayObs> {
-        let normalized_obs = (&obs.0 - &self.mean) / &self.std;
+        let mut normalized_obs = obs.0.as_standard_layout().into_owned();
+        self.normalize_obs
+            .normalize(normalized_obs.as_slice_mut().unwrap());
         Ok(normalized_obs.into())
     }
 }
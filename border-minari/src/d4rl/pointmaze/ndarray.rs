@@ -1,8 +1,8 @@
 //! Observation, actiontypes and corresponding converters for the Point Maze environment implemented with ndarray.
-use std::fmt::Debug;
+use std::{fmt::Debug, path::Path};
 
 use crate::{
-    util::ndarray::{arrayd_to_pyobj, pyobj_to_arrayd},
+    util::ndarray::{arrayd_to_pyobj, load_arrayd, pyobj_to_arrayd, save_arrayd},
     MinariConverter,
 };
 use anyhow::Result;
@@ -59,6 +59,14 @@ impl BatchBase for PointMazeObsBatch {
             obs: self.obs.select(Axis(0), ixs),
         }
     }
+
+    fn save(&self, path: &Path, len: usize) -> Result<()> {
+        save_arrayd(path, &self.obs, len)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<usize> {
+        load_arrayd(path, &mut self.obs)
+    }
 }
 
 impl From<PointMazeObs> for PointMazeObsBatch {
@@ -108,6 +116,14 @@ impl BatchBase for PointMazeActBatch {
             action: self.action.select(Axis(0), ixs),
         }
     }
+
+    fn save(&self, path: &Path, len: usize) -> Result<()> {
+        save_arrayd(path, &self.action, len)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<usize> {
+        load_arrayd(path, &mut self.action)
+    }
 }
 
 impl From<PointMazeAct> for PointMazeActBatch {
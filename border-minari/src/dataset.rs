@@ -139,6 +139,7 @@ impl MinariDataset {
             reward,
             is_terminated,
             is_truncated,
+            n_step: None,
             weight: None,
             ix_sample: None,
         })
@@ -68,6 +68,47 @@
 //! [`MinariDataset`]: crate::MinariDataset
 //! [`Env`]: border_core::Env
 //!
+//! # Reusing datasets without Python
+//!
+//! The batch types in this crate implement [`BatchBase::save`] and [`BatchBase::load`].
+//! A replay buffer created from a dataset can be saved with [`ReplayBufferBase::save`]
+//! and later built with [`SimpleReplayBuffer::from_dir`], which does not require
+//! the Python interpreter.
+//!
+//! ```no_run
+//! # use anyhow::Result;
+//! use border_core::{
+//!     generic_replay_buffer::{SimpleReplayBuffer, SimpleReplayBufferConfig},
+//!     ReplayBufferBase,
+//! };
+//! use border_minari::{
+//!     d4rl::kitchen::ndarray::{KitchenActBatch, KitchenConverter, KitchenObsBatch},
+//!     MinariDataset,
+//! };
+//! # use std::path::Path;
+//!
+//! fn main() -> Result<()> {
+//!     let dataset = MinariDataset::load_dataset("D4RL/kitchen/complete-v1", true)?;
+//!     let mut converter = KitchenConverter {};
+//!     let replay_buffer = dataset.create_replay_buffer(&mut converter, None)?;
+//!     replay_buffer.save(Path::new("kitchen_complete"))?;
+//!
+//!     // Later, possibly in another process
+//!     let config = SimpleReplayBufferConfig::default();
+//!     let replay_buffer = SimpleReplayBuffer::<KitchenObsBatch, KitchenActBatch>::from_dir(
+//!         "kitchen_complete",
+//!         &config,
+//!     )?;
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`BatchBase::save`]: border_core::generic_replay_buffer::BatchBase::save
+//! [`BatchBase::load`]: border_core::generic_replay_buffer::BatchBase::load
+//! [`ReplayBufferBase::save`]: border_core::ReplayBufferBase::save
+//! [`SimpleReplayBuffer::from_dir`]: border_core::generic_replay_buffer::SimpleReplayBuffer::from_dir
+//!
 //! # Integration with Border
 //!
 //! This crate implements the [`Env`] trait from `border-core`, making it compatible with other Border components
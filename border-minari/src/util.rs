@@ -3,10 +3,13 @@
 pub mod candle;
 
 pub mod ndarray {
-    use ndarray::ArrayD;
+    use anyhow::{anyhow, Result};
+    use border_core::generic_replay_buffer::npy::{read_npy, write_npy};
+    use ndarray::{ArrayD, Axis, IxDyn, Slice};
     use num_traits::cast::AsPrimitive;
     use numpy::{Element, PyArrayDyn};
     use pyo3::{IntoPy, PyObject};
+    use std::path::Path;
 
     /// Converts PyObject to ArrayD.
     pub fn pyobj_to_arrayd<T1, T2>(obs: PyObject) -> ArrayD<T2>
@@ -39,6 +42,37 @@ pub mod ndarray {
             act.into_py(py)
         })
     }
+
+    /// Saves the first `len` rows of an array as `data.npy` in the directory `path`.
+    ///
+    /// This function is used to implement [`BatchBase::save`].
+    ///
+    /// [`BatchBase::save`]: border_core::generic_replay_buffer::BatchBase::save
+    pub fn save_arrayd(path: &Path, a: &ArrayD<f32>, len: usize) -> Result<()> {
+        let a = a.slice_axis(Axis(0), Slice::from(..len));
+        let data = a.iter().cloned().collect::<Vec<_>>();
+        write_npy(path.join("data.npy"), a.shape(), &data)
+    }
+
+    /// Loads the rows saved by [`save_arrayd`] into the head of an array.
+    ///
+    /// Returns the number of the loaded rows.
+    pub fn load_arrayd(path: &Path, a: &mut ArrayD<f32>) -> Result<usize> {
+        let path = path.join("data.npy");
+        let (data, shape) = read_npy::<f32>(&path)?;
+        if shape.is_empty() || shape[1..] != a.shape()[1..] || shape[0] > a.shape()[0] {
+            return Err(anyhow!(
+                "Shape {:?} of {:?} does not fit in {:?}",
+                shape,
+                path,
+                a.shape()
+            ));
+        }
+        let len = shape[0];
+        let data = ArrayD::from_shape_vec(IxDyn(&shape), data)?;
+        a.slice_axis_mut(Axis(0), Slice::from(..len)).assign(&data);
+        Ok(len)
+    }
 }
 
 pub mod vec {
@@ -5,11 +5,11 @@ pub use ndarray_act::NdarrayAct;
 pub use ndarray_obs::NdarrayObs;
 pub use tensor_batch::TensorBatch;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use candle_core::{Tensor, WithDType};
 use ndarray::ArrayD;
 use num_traits::AsPrimitive;
-use std::convert::TryFrom;
+use std::{convert::TryFrom, path::Path};
 
 fn arrayd_to_tensor<T1, T2>(a: ArrayD<T1>, add_batch_dim: bool) -> Result<Tensor>
 where
@@ -42,3 +42,31 @@ where
 
     Ok(ndarray::Array1::<T>::from(v).into_shape(ndarray::IxDyn(&shape))?)
 }
+
+/// Saves the first `len` rows of a tensor as `data.npy` in the directory `path`.
+///
+/// This function is used to implement [`BatchBase::save`].
+///
+/// [`BatchBase::save`]: border_core::generic_replay_buffer::BatchBase::save
+pub fn save_tensor(path: &Path, t: &Tensor, len: usize) -> Result<()> {
+    t.narrow(0, 0, len)?.write_npy(path.join("data.npy"))?;
+    Ok(())
+}
+
+/// Loads the rows saved by [`save_tensor`] into the head of a tensor.
+///
+/// Returns the number of the loaded rows.
+pub fn load_tensor(path: &Path, t: &Tensor) -> Result<usize> {
+    let path = path.join("data.npy");
+    let data = Tensor::read_npy(&path)?.to_dtype(t.dtype())?;
+    if data.dims()[1..] != t.dims()[1..] || data.dims()[0] > t.dims()[0] {
+        return Err(anyhow!(
+            "Shape {:?} of {:?} does not fit in {:?}",
+            data.dims(),
+            path,
+            t.dims()
+        ));
+    }
+    t.slice_set(&data, 0, 0)?;
+    Ok(data.dims()[0])
+}
@@ -1,5 +1,8 @@
+use super::save_tensor;
+use anyhow::{anyhow, Result};
 use border_core::generic_replay_buffer::BatchBase;
 use candle_core::{/*error::Result, DType,*/ Device, Tensor};
+use std::path::Path;
 
 // /// Adds capability of constructing [`Tensor`] with a static method.
 // ///
@@ -88,6 +91,37 @@ impl BatchBase for TensorBatch {
             capacity: ixs.len(),
         }
     }
+
+    /// Saves the first `len` samples as `data.npy` in the directory `path`.
+    fn save(&self, path: &Path, len: usize) -> Result<()> {
+        if len == 0 {
+            return Ok(());
+        }
+        let t = Tensor::cat(&self.buf[..len], 0)?;
+        save_tensor(path, &t, len)
+    }
+
+    /// Loads the samples in `data.npy` in the directory `path`.
+    fn load(&mut self, path: &Path) -> Result<usize> {
+        let path = path.join("data.npy");
+        if !path.exists() {
+            return Ok(0);
+        }
+
+        let t = Tensor::read_npy(&path)?;
+        let len = t.dims()[0];
+        if len > self.capacity {
+            return Err(anyhow!(
+                "{:?} has {} samples, more than the capacity {}",
+                path,
+                len,
+                self.capacity
+            ));
+        }
+        self.buf.clear();
+        self.push(0, t.into());
+        Ok(len)
+    }
 }
 
 impl From<TensorBatch> for Tensor {
@@ -54,13 +54,15 @@ struct SetTagParams<'a> {
 /// as metrics. As an exception, `opt_steps` is treated as the `step` field of Mlflow's metric data
 /// (<https://mlflow.org/docs/latest/rest-api.html#metric>).
 ///
-/// Other types of values like [`RecordValue::Array1`] will be ignored.
+/// [`RecordValue::String`] values are set as tags of the run, e.g., `stop_reason` recorded
+/// at the end of training. Other types of values like [`RecordValue::Array1`] will be ignored.
 ///
 /// When dropped, this struct updates run's status to "FINISHED"
 /// (<https://mlflow.org/docs/latest/rest-api.html#mlflowrunstatus>).
 ///
 /// [`RecordValue::Scalar`]: border_core::record::RecordValue::Scalar
 /// [`RecordValue::Array1`]: border_core::record::RecordValue::Array1
+/// [`RecordValue::String`]: border_core::record::RecordValue::String
 pub struct MlflowTrackingRecorder<E, R>
 where
     E: Env,
@@ -218,6 +220,12 @@ where
                             .unwrap();
                         // TODO: error handling caused by API call
                     }
+                    RecordValue::String(v) => {
+                        // e.g., the reason why training stopped
+                        if let Err(e) = self.set_tag(key, v) {
+                            log::warn!("Failed to set tag {}: {}", key, e);
+                        }
+                    }
                     _ => {} // ignore record value
                 }
             }
@@ -23,11 +23,13 @@ struct RandomPolicy;
 impl Policy<Env> for RandomPolicy {
     fn sample(&mut self, _: &Obs) -> Act {
         Act::new_cont(
-            Array::from(
+            Array::from_shape_vec(
+                (1, 8),
                 (0..8)
                     .map(|_| 2f32 * fastrand::f32() - 1f32)
                     .collect::<Vec<_>>(),
             )
+            .unwrap()
             .into_dyn(),
         )
     }
@@ -49,7 +51,7 @@ fn main() -> Result<()> {
 
     let env_config = GymEnvConfig::default()
         .name("Ant-v4".to_string())
-        .converter_config(NdarrayConverterConfig {})
+        .converter_config(NdarrayConverterConfig::default())
         .render_mode(Some("human".to_string()));
     let mut policy = Box::new(RandomPolicy) as _;
 
@@ -64,7 +66,7 @@ fn test_random_ant() -> Result<()> {
 
     let env_config = GymEnvConfig::default()
         .name("Ant-v4".to_string())
-        .converter_config(NdarrayConverterConfig {});
+        .converter_config(NdarrayConverterConfig::default());
     let mut policy = Box::new(RandomPolicy) as _;
 
     let _ = Evaluator::new(&env_config, 0, 1)?.evaluate(&mut policy);
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use border_core::{
+    vec_env::{VecEnv, VecEnvConfig},
+    Env as _,
+};
+use border_py_gym_env::{
+    ndarray::{NdarrayConverter, NdarrayConverterConfig},
+    GymEnv, GymEnvConfig, GymEnvConverter,
+};
+use ndarray::Array;
+
+type Env = VecEnv<GymEnv<NdarrayConverter>>;
+type Act = <NdarrayConverter as GymEnvConverter>::Act;
+
+const N_ENVS: usize = 4;
+
+/// Samples random actions of all sub-environments.
+fn sample_act() -> Act {
+    Act::Discrete(
+        Array::from((0..N_ENVS).map(|_| fastrand::i64(0..2)).collect::<Vec<_>>()).into_dyn(),
+    )
+}
+
+fn run(n_steps: usize) -> Result<()> {
+    let env_config = GymEnvConfig::default()
+        .name("CartPole-v1".to_string())
+        .converter_config(NdarrayConverterConfig::default());
+    let config = VecEnvConfig::new(env_config, N_ENVS);
+    let mut env = Env::build(&config, 42)?;
+
+    let obs = env.reset(None)?;
+    assert_eq!(obs.0.shape(), &[N_ENVS, 4]);
+
+    let mut n_episodes = 0;
+    for _ in 0..n_steps {
+        let (step, _) = env.step_with_reset(&sample_act());
+        assert_eq!(step.obs.0.shape(), &[N_ENVS, 4]);
+        assert_eq!(step.reward.len(), N_ENVS);
+        if let Some(init_obs) = step.init_obs.as_ref() {
+            assert_eq!(init_obs.0.shape(), &[N_ENVS, 4]);
+        }
+        n_episodes += step
+            .is_terminated
+            .iter()
+            .zip(step.is_truncated.iter())
+            .filter(|(&t, &u)| t == 1 || u == 1)
+            .count();
+    }
+    log::info!("{} episodes finished in {} steps", n_episodes, n_steps);
+
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    fastrand::seed(42);
+
+    run(1000)
+}
+
+#[test]
+fn test_random_cartpole_vec() -> Result<()> {
+    fastrand::seed(42);
+
+    run(200)
+}
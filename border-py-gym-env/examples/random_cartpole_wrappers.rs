@@ -0,0 +1,61 @@
+use anyhow::Result;
+use border_core::{
+    wrappers::{FrameStack, FrameStackConfig, TimeLimit, TimeLimitConfig, WrapperConfig},
+    Env as _,
+};
+use border_py_gym_env::{
+    ndarray::{NdarrayConverter, NdarrayConverterConfig},
+    GymEnv, GymEnvConfig, GymEnvConverter,
+};
+use ndarray::Array;
+
+type Env = TimeLimit<FrameStack<GymEnv<NdarrayConverter>>>;
+type Act = <NdarrayConverter as GymEnvConverter>::Act;
+
+const N_FRAMES: usize = 4;
+const MAX_STEPS: usize = 20;
+
+fn sample_act() -> Act {
+    Act::Discrete(Array::from(vec![fastrand::i64(0..2)]).into_dyn())
+}
+
+fn run(n_steps: usize) -> Result<()> {
+    let env_config = GymEnvConfig::default()
+        .name("CartPole-v1".to_string())
+        .converter_config(NdarrayConverterConfig::default());
+    let env_config = WrapperConfig::new(env_config, FrameStackConfig::new(N_FRAMES));
+    let env_config = WrapperConfig::new(env_config, TimeLimitConfig::new(MAX_STEPS));
+    let mut env = Env::build(&env_config, 42)?;
+
+    // Four observations of CartPole are stacked
+    let obs = env.reset(None)?;
+    assert_eq!(obs.0.shape(), &[1, 4 * N_FRAMES]);
+
+    let mut len = 0;
+    for _ in 0..n_steps {
+        let (step, _) = env.step_with_reset(&sample_act());
+        assert_eq!(step.obs.0.shape(), &[1, 4 * N_FRAMES]);
+        len += 1;
+        assert!(len <= MAX_STEPS);
+        if step.is_done() {
+            log::info!("episode length: {}", len);
+            len = 0;
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    fastrand::seed(42);
+
+    run(1000)
+}
+
+#[test]
+fn test_random_cartpole_wrappers() -> Result<()> {
+    fastrand::seed(42);
+
+    run(200)
+}
@@ -3,7 +3,7 @@
 use anyhow::Result;
 use border_core::{
     record::{Record, RecordValue::Scalar},
-    Env, Info, Step,
+    Env, Info, NormalizeReward, Step,
 };
 use log::{info, trace};
 // use pyo3::IntoPy;
@@ -56,9 +56,6 @@ pub struct GymEnvConfig<C>
 where
     C: GymEnvConverter,
 {
-    /// The maximum interaction steps in an episode.
-    pub max_steps: Option<usize>,
-
     /// `true` to support rendering for PyBullet gym environment.
     pub pybullet: bool,
 
@@ -73,6 +70,13 @@ where
 
     /// Converter of observation and action.
     pub converter_config: C::Config,
+
+    /// Normalizer of rewards.
+    ///
+    /// This should not be set in the configuration of environments for evaluation,
+    /// so that evaluation scores are computed with raw rewards.
+    #[serde(default)]
+    pub normalize_reward: Option<NormalizeReward>,
 }
 
 impl<C> Default for GymEnvConfig<C>
@@ -81,12 +85,12 @@ where
 {
     fn default() -> Self {
         Self {
-            max_steps: None,
             pybullet: false,
             name: "".to_string(),
             render_mode: None,
             wait: Duration::from_millis(0),
             converter_config: Default::default(),
+            normalize_reward: None,
         }
     }
 }
@@ -122,9 +126,18 @@ where
         self.converter_config = config;
         self
     }
+
+    /// Sets the normalizer of rewards.
+    pub fn normalize_reward(mut self, v: Option<NormalizeReward>) -> Self {
+        self.normalize_reward = v;
+        self
+    }
 }
 
 /// An wrapper of [Gymnasium](https://gymnasium.farama.org).
+///
+/// The length of episodes can be limited by wrapping the environment with
+/// [`TimeLimit`](border_core::wrappers::TimeLimit).
 #[derive(Debug)]
 pub struct GymEnv<C>
 where
@@ -133,11 +146,13 @@ where
     render: bool,
     env: PyObject,
     count_steps: usize,
-    max_steps: Option<usize>,
     converter: C,
     wait: Duration,
     pybullet: bool,
     pybullet_state: Option<PyObject>,
+    normalize_reward: Option<NormalizeReward>,
+    /// Discounted return of rewards for [`NormalizeReward`], kept by each environment.
+    returns: Vec<f64>,
     /// Initial seed.
     ///
     /// This value will be used at the first call of the reset method.
@@ -163,12 +178,6 @@ where
         Ok(())
     }
 
-    /// Set the maximum number of steps in the environment.
-    pub fn max_steps(mut self, v: Option<usize>) -> Self {
-        self.max_steps = v;
-        self
-    }
-
     /// Set wait time at every interaction steps.
     pub fn set_wait(&mut self, d: Duration) {
         self.wait = d;
@@ -297,10 +306,10 @@ where
             };
 
             // Reward
-            let reward: Vec<f32> = vec![step.get_item(1).extract().unwrap()];
+            let mut reward: Vec<f32> = vec![step.get_item(1).extract().unwrap()];
 
             // Terminated/Truncated flags
-            let (is_terminated, mut is_truncated) = {
+            let (is_terminated, is_truncated) = {
                 let (is_terminated, is_truncated) = Self::is_done(step).unwrap();
                 (vec![is_terminated], vec![is_truncated])
             };
@@ -312,18 +321,19 @@ where
 
             self.count_steps += 1; //.replace(c + 1);
 
-            // Terminated or truncated
-            if let Some(max_steps) = self.max_steps {
-                if self.count_steps >= max_steps {
-                    is_truncated[0] = 1;
-                }
-            };
-
             if (is_terminated[0] | is_truncated[0]) == 1 {
                 record.insert("episode_length", Scalar(self.count_steps as _));
                 self.count_steps = 0;
             }
 
+            if let Some(normalize_reward) = &self.normalize_reward {
+                normalize_reward.apply(
+                    &mut self.returns,
+                    &mut reward,
+                    &[is_terminated[0] | is_truncated[0]],
+                );
+            }
+
             // Returned step object
             let step = Step {
                 obs,
@@ -465,9 +475,10 @@ def update_camera_pos(env):
             render,
             count_steps: 0,
             wait: config.wait,
-            max_steps: config.max_steps,
             pybullet: config.pybullet,
             pybullet_state,
+            normalize_reward: config.normalize_reward.clone(),
+            returns: vec![],
             initial_seed: Some(seed),
         })
     }
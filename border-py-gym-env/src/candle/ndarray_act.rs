@@ -1,9 +1,13 @@
 use super::{arrayd_to_tensor, tensor_to_arrayd, TensorBatch};
+use border_core::vec_env::VecEnvAct;
 use candle_core::{DType, Tensor, D};
-use ndarray::ArrayD;
+use ndarray::{ArrayD, Axis};
 
 #[derive(Clone, Debug)]
 /// Action.
+///
+/// The first axis of the array is the batch axis, as in the observation. Its size is 1
+/// for a non-vectorized environment.
 pub enum NdarrayAct {
     Continuous(ArrayD<f32>),
     Discrete(ArrayD<i64>),
@@ -11,12 +15,36 @@ pub enum NdarrayAct {
 
 impl border_core::Act for NdarrayAct {}
 
+impl VecEnvAct for NdarrayAct {
+    /// Splits the action along the batch axis.
+    ///
+    /// Each of the returned actions keeps the batch axis of size 1.
+    fn split(self, n: usize) -> Vec<Self> {
+        match self {
+            Self::Continuous(array) => {
+                assert_eq!(array.shape()[0], n);
+                array
+                    .axis_chunks_iter(Axis(0), 1)
+                    .map(|a| Self::Continuous(a.to_owned()))
+                    .collect()
+            }
+            Self::Discrete(array) => {
+                assert_eq!(array.shape()[0], n);
+                array
+                    .axis_chunks_iter(Axis(0), 1)
+                    .map(|a| Self::Discrete(a.to_owned()))
+                    .collect()
+            }
+        }
+    }
+}
+
 impl Into<Tensor> for NdarrayAct {
     fn into(self) -> Tensor {
         match self {
-            Self::Continuous(array) => arrayd_to_tensor::<_, f32>(array, true).unwrap(),
+            Self::Continuous(array) => arrayd_to_tensor::<_, f32>(array, false).unwrap(),
             Self::Discrete(array) => {
-                let t = arrayd_to_tensor::<_, i64>(array, true).unwrap();
+                let t = arrayd_to_tensor::<_, i64>(array, false).unwrap();
                 t.unsqueeze(D::Minus1).unwrap()
             }
         }
@@ -26,8 +54,10 @@ impl Into<Tensor> for NdarrayAct {
 impl From<Tensor> for NdarrayAct {
     fn from(t: Tensor) -> Self {
         match t.dtype() {
-            DType::F32 => Self::Continuous(tensor_to_arrayd(t, true).unwrap()),
-            DType::I64 => Self::Discrete(tensor_to_arrayd(t, true).unwrap()),
+            DType::F32 => Self::Continuous(tensor_to_arrayd(t, false).unwrap()),
+            DType::I64 => {
+                Self::Discrete(tensor_to_arrayd(t.flatten_all().unwrap(), false).unwrap())
+            }
             _ => panic!(),
         }
     }
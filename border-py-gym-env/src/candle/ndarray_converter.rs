@@ -5,17 +5,37 @@
 use super::{NdarrayAct, NdarrayObs};
 use crate::{util::pyobj_to_arrayd, GymEnvConverter};
 use anyhow::Result;
+use border_core::NormalizeObs;
+use ndarray::Axis;
 use numpy::PyArrayDyn;
 use pyo3::{IntoPy, PyObject};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 /// Configuration of [`NdarrayConverter`].
-pub struct NdarrayConverterConfig {}
+pub struct NdarrayConverterConfig {
+    /// Normalizer of observations.
+    ///
+    /// Observations are normalized with running statistics shared by the clones of the
+    /// normalizer. The statistics are frozen during evaluation if the agent is wrapped with
+    /// [`NormalizedAgent`](border_core::NormalizedAgent).
+    #[serde(default)]
+    pub normalize_obs: Option<NormalizeObs>,
+}
 
 impl Default for NdarrayConverterConfig {
     fn default() -> Self {
-        Self {}
+        Self {
+            normalize_obs: None,
+        }
+    }
+}
+
+impl NdarrayConverterConfig {
+    /// Sets the normalizer of observations.
+    pub fn normalize_obs(mut self, v: NormalizeObs) -> Self {
+        self.normalize_obs = Some(v);
+        self
     }
 }
 
@@ -26,15 +46,19 @@ impl Default for NdarrayConverterConfig {
 /// The former is represented as a vector, while the latter is represented as an integer.
 /// The action type is automatically detected from samples, those are outputs
 /// of the model being trained.
-pub struct NdarrayConverter {}
+pub struct NdarrayConverter {
+    normalize_obs: Option<NormalizeObs>,
+}
 
 impl GymEnvConverter for NdarrayConverter {
     type Obs = NdarrayObs;
     type Act = NdarrayAct;
     type Config = NdarrayConverterConfig;
 
-    fn new(_config: &Self::Config) -> Result<Self> {
-        let converter = Self {};
+    fn new(config: &Self::Config) -> Result<Self> {
+        let converter = Self {
+            normalize_obs: config.normalize_obs.clone(),
+        };
         Ok(converter)
     }
 
@@ -43,7 +67,7 @@ impl GymEnvConverter for NdarrayConverter {
     /// Data type should be f32.
     fn filt_obs(&mut self, obs: PyObject) -> Result<Self::Obs> {
         // ndarray
-        let obs = pyo3::Python::with_gil(|py| {
+        let mut obs = pyo3::Python::with_gil(|py| {
             if obs.as_ref(py).get_type().name().unwrap() == "NoneType" {
                 panic!();
             } else {
@@ -51,13 +75,21 @@ impl GymEnvConverter for NdarrayConverter {
             }
         });
 
+        if let Some(normalize_obs) = &self.normalize_obs {
+            let dim = obs.len() / obs.shape()[0];
+            normalize_obs.apply(obs.as_slice_mut().unwrap(), dim);
+        }
+
         Ok(NdarrayObs(obs))
     }
 
     /// Convert [`Self::Act`] to [`PyObject`].
+    ///
+    /// The batch axis of the action, whose size must be 1, is removed.
     fn filt_act(&mut self, act: Self::Act) -> Result<PyObject> {
         match act {
             NdarrayAct::Continuous(arrayd) => {
+                let arrayd = arrayd.index_axis_move(Axis(0), 0);
                 let pyobj = pyo3::Python::with_gil(|py| {
                     let act = PyArrayDyn::<f32>::from_array(py, &arrayd);
                     act.into_py(py)
@@ -65,6 +97,7 @@ impl GymEnvConverter for NdarrayConverter {
                 Ok(pyobj)
             }
             NdarrayAct::Discrete(arrayd) => {
+                let arrayd = arrayd.index_axis_move(Axis(0), 0);
                 let pyobj = pyo3::Python::with_gil(|py| {
                     let act = PyArrayDyn::<i64>::from_array(py, &arrayd);
                     act.into_py(py)
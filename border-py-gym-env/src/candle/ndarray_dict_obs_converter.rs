@@ -2,6 +2,7 @@
 use super::{NdarrayAct, NdarrayDictObs};
 use crate::{util::pyobj_to_arrayd, GymEnvConverter};
 use anyhow::Result;
+use ndarray::Axis;
 use numpy::PyArrayDyn;
 use pyo3::{IntoPy, PyObject};
 use serde::{Deserialize, Serialize};
@@ -56,9 +57,12 @@ impl GymEnvConverter for NdarrayDictObsConverter {
     }
 
     /// Convert [`Self::Act`] to [`PyObject`].
+    ///
+    /// The batch axis of the action, whose size must be 1, is removed.
     fn filt_act(&mut self, act: Self::Act) -> Result<PyObject> {
         match act {
             NdarrayAct::Continuous(arrayd) => {
+                let arrayd = arrayd.index_axis_move(Axis(0), 0);
                 let pyobj = pyo3::Python::with_gil(|py| {
                     let act = PyArrayDyn::<f32>::from_array(py, &arrayd);
                     act.into_py(py)
@@ -66,6 +70,7 @@ impl GymEnvConverter for NdarrayDictObsConverter {
                 Ok(pyobj)
             }
             NdarrayAct::Discrete(arrayd) => {
+                let arrayd = arrayd.index_axis_move(Axis(0), 0);
                 let pyobj = pyo3::Python::with_gil(|py| {
                     let act = PyArrayDyn::<i64>::from_array(py, &arrayd);
                     act.into_py(py)
@@ -1,6 +1,7 @@
 use super::{arrayd_to_tensor, TensorBatch};
+use border_core::{vec_env::VecEnvObs, wrappers::FrameStackObs};
 use candle_core::Tensor;
-use ndarray::ArrayD;
+use ndarray::{ArrayD, Axis};
 
 #[derive(Clone, Debug)]
 /// Observation.
@@ -12,6 +13,22 @@ impl border_core::Obs for NdarrayObs {
     }
 }
 
+impl VecEnvObs for NdarrayObs {
+    /// Concatenates observations along the batch axis.
+    fn concat(obs: Vec<Self>) -> Self {
+        let views = obs.iter().map(|o| o.0.view()).collect::<Vec<_>>();
+        Self(ndarray::concatenate(Axis(0), &views).unwrap())
+    }
+}
+
+impl FrameStackObs for NdarrayObs {
+    /// Stacks observations along the axis next to the batch axis.
+    fn stack(frames: &[Self]) -> Self {
+        let views = frames.iter().map(|o| o.0.view()).collect::<Vec<_>>();
+        Self(ndarray::concatenate(Axis(1), &views).unwrap())
+    }
+}
+
 impl Into<Tensor> for NdarrayObs {
     fn into(self) -> Tensor {
         arrayd_to_tensor::<_, f32>(self.0, false).unwrap()
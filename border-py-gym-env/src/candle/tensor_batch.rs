@@ -1,5 +1,6 @@
-use border_core::generic_replay_buffer::BatchBase;
+use border_core::generic_replay_buffer::{BatchBase, GoalObsBatch};
 use candle_core::{error::Result, DType, Device, Tensor};
+use std::ops::Range;
 
 /// Adds capability of constructing [`Tensor`] with a static method.
 ///
@@ -90,6 +91,34 @@ impl BatchBase for TensorBatch {
     }
 }
 
+impl GoalObsBatch for TensorBatch {
+    /// Returns the elements in `range` along the second axis of each `f32` sample.
+    fn goals(&self, range: Range<usize>) -> Vec<Vec<f32>> {
+        self.buf
+            .iter()
+            .map(|t| {
+                t.narrow(1, range.start, range.len())
+                    .unwrap()
+                    .flatten_all()
+                    .unwrap()
+                    .to_vec1::<f32>()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    /// Overwrites the elements in `range` along the second axis of each sample.
+    fn set_goals(&mut self, range: Range<usize>, goals: &[Vec<f32>]) {
+        for (t, goal) in self.buf.iter_mut().zip(goals) {
+            let goal = Tensor::from_slice(goal, (1, goal.len()), t.device())
+                .unwrap()
+                .to_dtype(t.dtype())
+                .unwrap();
+            *t = t.slice_assign(&[0..1, range.clone()], &goal).unwrap();
+        }
+    }
+}
+
 impl From<TensorBatch> for Tensor {
     fn from(b: TensorBatch) -> Self {
         Tensor::cat(&b.buf[..], 0).unwrap()
@@ -1,7 +1,11 @@
-use ndarray::ArrayD;
+use border_core::vec_env::VecEnvAct;
+use ndarray::{ArrayD, Axis};
 
 #[derive(Clone, Debug)]
 /// Action.
+///
+/// The first axis of the array is the batch axis, as in the observation. Its size is 1
+/// for a non-vectorized environment.
 pub enum NdarrayAct {
     Continuous(ArrayD<f32>),
     Discrete(ArrayD<i64>),
@@ -9,8 +13,34 @@ pub enum NdarrayAct {
 
 impl border_core::Act for NdarrayAct {}
 
+impl VecEnvAct for NdarrayAct {
+    /// Splits the action along the batch axis.
+    ///
+    /// Each of the returned actions keeps the batch axis of size 1.
+    fn split(self, n: usize) -> Vec<Self> {
+        match self {
+            Self::Continuous(array) => {
+                assert_eq!(array.shape()[0], n);
+                array
+                    .axis_chunks_iter(Axis(0), 1)
+                    .map(|a| Self::Continuous(a.to_owned()))
+                    .collect()
+            }
+            Self::Discrete(array) => {
+                assert_eq!(array.shape()[0], n);
+                array
+                    .axis_chunks_iter(Axis(0), 1)
+                    .map(|a| Self::Discrete(a.to_owned()))
+                    .collect()
+            }
+        }
+    }
+}
+
 impl NdarrayAct {
     /// Creates continuous action from a vector of [`ArrayD`]`<f32>`.
+    ///
+    /// `a` must have the batch axis.
     pub fn new_cont(a: ArrayD<f32>) -> Self {
         Self::Continuous(a)
     }
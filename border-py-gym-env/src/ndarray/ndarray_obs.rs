@@ -1,4 +1,5 @@
-use ndarray::ArrayD;
+use border_core::{vec_env::VecEnvObs, wrappers::FrameStackObs};
+use ndarray::{ArrayD, Axis};
 
 #[derive(Clone, Debug)]
 /// Observation.
@@ -9,3 +10,19 @@ impl border_core::Obs for NdarrayObs {
         self.0.shape()[0]
     }
 }
+
+impl VecEnvObs for NdarrayObs {
+    /// Concatenates observations along the batch axis.
+    fn concat(obs: Vec<Self>) -> Self {
+        let views = obs.iter().map(|o| o.0.view()).collect::<Vec<_>>();
+        Self(ndarray::concatenate(Axis(0), &views).unwrap())
+    }
+}
+
+impl FrameStackObs for NdarrayObs {
+    /// Stacks observations along the axis next to the batch axis.
+    fn stack(frames: &[Self]) -> Self {
+        let views = frames.iter().map(|o| o.0.view()).collect::<Vec<_>>();
+        Self(ndarray::concatenate(Axis(1), &views).unwrap())
+    }
+}
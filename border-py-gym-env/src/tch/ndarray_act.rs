@@ -1,9 +1,13 @@
 use super::{arrayd_to_tensor, tensor_to_arrayd, TensorBatch};
-use ndarray::ArrayD;
+use border_core::vec_env::VecEnvAct;
+use ndarray::{ArrayD, Axis};
 use tch::{Kind, Tensor};
 
 #[derive(Clone, Debug)]
 /// Action.
+///
+/// The first axis of the array is the batch axis, as in the observation. Its size is 1
+/// for a non-vectorized environment.
 pub enum NdarrayAct {
     Continuous(ArrayD<f32>),
     Discrete(ArrayD<i64>),
@@ -11,11 +15,38 @@ pub enum NdarrayAct {
 
 impl border_core::Act for NdarrayAct {}
 
+impl VecEnvAct for NdarrayAct {
+    /// Splits the action along the batch axis.
+    ///
+    /// Each of the returned actions keeps the batch axis of size 1.
+    fn split(self, n: usize) -> Vec<Self> {
+        match self {
+            Self::Continuous(array) => {
+                assert_eq!(array.shape()[0], n);
+                array
+                    .axis_chunks_iter(Axis(0), 1)
+                    .map(|a| Self::Continuous(a.to_owned()))
+                    .collect()
+            }
+            Self::Discrete(array) => {
+                assert_eq!(array.shape()[0], n);
+                array
+                    .axis_chunks_iter(Axis(0), 1)
+                    .map(|a| Self::Discrete(a.to_owned()))
+                    .collect()
+            }
+        }
+    }
+}
+
 impl Into<Tensor> for NdarrayAct {
     fn into(self) -> Tensor {
         match self {
-            Self::Continuous(array) => arrayd_to_tensor::<_, f32>(array, true),
-            Self::Discrete(array) => arrayd_to_tensor::<_, i64>(array, true),
+            Self::Continuous(array) => {
+                let shape = array.shape().iter().map(|&x| x as i64).collect::<Vec<_>>();
+                arrayd_to_tensor::<_, f32>(array, false).reshape(&shape)
+            }
+            Self::Discrete(array) => arrayd_to_tensor::<_, i64>(array, false).unsqueeze(-1),
         }
     }
 }
@@ -23,8 +54,8 @@ impl Into<Tensor> for NdarrayAct {
 impl From<Tensor> for NdarrayAct {
     fn from(t: Tensor) -> Self {
         match t.kind() {
-            Kind::Float => Self::Continuous(tensor_to_arrayd(t, true)),
-            Kind::Int64 => Self::Discrete(tensor_to_arrayd(t.flatten(0, -1), true)),
+            Kind::Float => Self::Continuous(tensor_to_arrayd(t, false)),
+            Kind::Int64 => Self::Discrete(tensor_to_arrayd(t.flatten(0, -1), false)),
             _ => panic!(),
         }
     }
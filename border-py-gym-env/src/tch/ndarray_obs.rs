@@ -1,5 +1,6 @@
 use super::TensorBatch;
-use ndarray::ArrayD;
+use border_core::{vec_env::VecEnvObs, wrappers::FrameStackObs};
+use ndarray::{ArrayD, Axis};
 use std::convert::TryFrom;
 use tch::Tensor;
 
@@ -13,6 +14,22 @@ impl border_core::Obs for NdarrayObs {
     }
 }
 
+impl VecEnvObs for NdarrayObs {
+    /// Concatenates observations along the batch axis.
+    fn concat(obs: Vec<Self>) -> Self {
+        let views = obs.iter().map(|o| o.0.view()).collect::<Vec<_>>();
+        Self(ndarray::concatenate(Axis(0), &views).unwrap())
+    }
+}
+
+impl FrameStackObs for NdarrayObs {
+    /// Stacks observations along the axis next to the batch axis.
+    fn stack(frames: &[Self]) -> Self {
+        let views = frames.iter().map(|o| o.0.view()).collect::<Vec<_>>();
+        Self(ndarray::concatenate(Axis(1), &views).unwrap())
+    }
+}
+
 impl Into<Tensor> for NdarrayObs {
     fn into(self) -> Tensor {
         Tensor::try_from(&self.0).unwrap()
@@ -2,14 +2,15 @@
 use super::{config::DqnConfig, explorer::DqnExplorer, model::DqnModel};
 use crate::{
     model::{ModelBase, SubModel},
-    util::{track, CriticLoss, OutDim},
+    util::{gamma_n, insert_scheduled, load_yaml, save_yaml, track, CriticLoss, OutDim},
 };
 use anyhow::Result;
 use border_core::{
     record::{Record, RecordValue},
-    Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
+    Agent, Configurable, Env, Policy, ReplayBufferBase, Schedule, TransitionBatch,
 };
-use serde::{de::DeserializeOwned, Serialize};
+use log::warn;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     convert::{TryFrom, TryInto},
     fs,
@@ -35,6 +36,7 @@ where
     pub(in crate::dqn) phantom: PhantomData<(E, R)>,
     pub(in crate::dqn) discount_factor: f64,
     pub(in crate::dqn) tau: f64,
+    pub(in crate::dqn) tau_schedule: Option<Schedule>,
     pub(in crate::dqn) explorer: DqnExplorer,
     pub(in crate::dqn) device: Device,
     pub(in crate::dqn) n_opts: usize,
@@ -47,6 +49,43 @@ where
     record_verbose_level: usize,
 }
 
+/// Training state of [`Dqn`] saved in checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct DqnState {
+    n_opts: usize,
+    soft_update_counter: usize,
+    explorer: DqnExplorer,
+}
+
+impl<E, Q, R> Dqn<E, Q, R>
+where
+    Q: SubModel<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Computes the target values of the action values.
+    fn td_target(
+        &self,
+        next_obs: &Q::Input,
+        reward: Tensor,
+        is_terminated: Tensor,
+        discount: Tensor,
+    ) -> Tensor {
+        let q = if self.double_dqn {
+            let x = self.qnet.forward(next_obs);
+            let y = x.argmax(-1, false).unsqueeze(-1);
+            self.qnet_tgt
+                .forward(next_obs)
+                .gather(-1, &y, false)
+                .squeeze()
+        } else {
+            let x = self.qnet_tgt.forward(next_obs);
+            let y = x.argmax(-1, false).unsqueeze(-1);
+            x.gather(-1, &y, false).squeeze()
+        };
+        reward + (1 - is_terminated) * discount * q
+    }
+}
+
 impl<E, Q, R> Dqn<E, Q, R>
 where
     E: Env,
@@ -60,8 +99,11 @@ where
     fn update_critic(&mut self, buffer: &mut R) -> Record {
         let mut record = Record::empty();
         let batch = buffer.batch(self.batch_size).unwrap();
+        let n_step = batch.n_step().map(|n_step| n_step.to_vec());
         let (obs, act, next_obs, reward, is_terminated, _is_truncated, ixs, weight) =
             batch.unpack();
+        let discount =
+            gamma_n(self.discount_factor, n_step.as_deref(), reward.len()).to(self.device);
         let obs = obs.into();
         let act = act.into().to(self.device);
         let next_obs = next_obs.into();
@@ -88,21 +130,7 @@ where
             record.insert("reward_mean", RecordValue::Scalar(reward_mean));
         }
 
-        let tgt: Tensor = no_grad(|| {
-            let q = if self.double_dqn {
-                let x = self.qnet.forward(&next_obs);
-                let y = x.argmax(-1, false).unsqueeze(-1);
-                self.qnet_tgt
-                    .forward(&next_obs)
-                    .gather(-1, &y, false)
-                    .squeeze()
-            } else {
-                let x = self.qnet_tgt.forward(&next_obs);
-                let y = x.argmax(-1, false).unsqueeze(-1);
-                x.gather(-1, &y, false).squeeze()
-            };
-            reward + (1 - is_terminated) * self.discount_factor * q
-        });
+        let tgt: Tensor = no_grad(|| self.td_target(&next_obs, reward, is_terminated, discount));
 
         if self.record_verbose_level >= 2 {
             record.insert(
@@ -187,6 +215,18 @@ where
             record_ = record_.merge(record);
         }
 
+        if let Some(tau_schedule) = &self.tau_schedule {
+            self.tau = tau_schedule.value(self.n_opts);
+        }
+        insert_scheduled(
+            &mut record_,
+            &[
+                ("tau", self.tau_schedule.as_ref().map(|_| self.tau)),
+                ("lr", self.qnet.scheduled_learning_rate()),
+                ("eps", self.explorer.eps()),
+            ],
+        );
+
         self.soft_update_counter += 1;
         if self.soft_update_counter == self.soft_update_interval {
             self.soft_update_counter = 0;
@@ -269,6 +309,7 @@ where
             batch_size: config.batch_size,
             discount_factor: config.discount_factor,
             tau: config.tau,
+            tau_schedule: config.tau_schedule,
             train: config.train,
             explorer: config.explorer,
             device,
@@ -361,6 +402,43 @@ where
         Ok(())
     }
 
+    /// Saves the state of the agent for resuming training.
+    ///
+    /// In addition to the files saved by [`Agent::save_params`], the counters and the
+    /// explorer are saved in `agent.yaml`. The state of the optimizer is not saved,
+    /// as tch does not support serialization of optimizers.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+
+        let state_path = path.join("agent.yaml");
+        let state = DqnState {
+            n_opts: self.n_opts,
+            soft_update_counter: self.soft_update_counter,
+            explorer: self.explorer.clone(),
+        };
+        save_yaml(&state_path, &state)?;
+        paths.push(state_path);
+
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+
+        let state: DqnState = load_yaml(path.join("agent.yaml"))?;
+        self.n_opts = state.n_opts;
+        self.soft_update_counter = state.soft_update_counter;
+        self.explorer = state.explorer;
+        warn!("The state of the optimizer is not restored, as it is not supported by tch");
+
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.qnet.set_learning_rate(lr);
+        Ok(())
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
@@ -371,7 +449,12 @@ where
 }
 
 #[cfg(feature = "border-async-trainer")]
-use {crate::util::NamedTensors, border_async_trainer::SyncModel};
+use {
+    super::EpsilonGreedy,
+    crate::util::NamedTensors,
+    border_async_trainer::{ApeXAgent, SyncModel},
+    border_core::generic_replay_buffer::{BatchBase, GenericTransitionBatch},
+};
 
 #[cfg(feature = "border-async-trainer")]
 impl<E, Q, R> SyncModel for Dqn<E, Q, R>
@@ -395,8 +478,162 @@ where
         )
     }
 
+    /// Synchronizes both the action-value network and its target with the given parameters.
+    ///
+    /// The target network is used in [`ApeXAgent::compute_priorities`].
     fn sync_model(&mut self, model_info: &Self::ModelInfo) {
-        let vs = self.qnet.get_var_store_mut();
-        model_info.copy_to(vs);
+        model_info.copy_to(self.qnet.get_var_store_mut());
+        model_info.copy_to(self.qnet_tgt.get_var_store_mut());
+    }
+}
+
+#[cfg(feature = "border-async-trainer")]
+impl<E, Q, R, O, A> ApeXAgent<GenericTransitionBatch<O, A>> for Dqn<E, Q, R>
+where
+    Q: SubModel<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    O: BatchBase + Clone + Into<Q::Input>,
+    A: BatchBase + Clone + Into<Tensor>,
+{
+    /// Computes the absolute TD errors of the transitions with the synced model.
+    ///
+    /// The TD errors are clipped with [`DqnConfig::clip_td_err`] as in the learner.
+    fn compute_priorities(&mut self, item: &GenericTransitionBatch<O, A>) -> Vec<f32> {
+        no_grad(|| {
+            let discount = gamma_n(
+                self.discount_factor,
+                item.n_step.as_deref(),
+                item.reward.len(),
+            )
+            .to(self.device);
+            let obs = item.obs.clone().into();
+            let act = item.act.clone().into().to(self.device);
+            let next_obs = item.next_obs.clone().into();
+            let reward = Tensor::from_slice(&item.reward[..]).to(self.device);
+            let is_terminated = Tensor::from_slice(&item.is_terminated[..]).to(self.device);
+
+            let pred = self.qnet.forward(&obs).gather(-1, &act, false).squeeze();
+            let tgt = self.td_target(&next_obs, reward, is_terminated, discount);
+            let td_errs = match self.clip_td_err {
+                None => (&pred - &tgt).abs(),
+                Some((min, max)) => (&pred - &tgt).abs().clip(min, max),
+            };
+            Vec::<f32>::try_from(td_errs.reshape(&[-1])).expect("Failed to convert Tensor to f32")
+        })
+    }
+
+    /// Replaces the explorer with epsilon-greedy of the constant exploration rate.
+    fn set_epsilon(&mut self, epsilon: f64) {
+        self.explorer =
+            DqnExplorer::EpsilonGreedy(EpsilonGreedy::new().eps_start(epsilon).eps_final(epsilon));
+    }
+}
+
+#[cfg(all(test, feature = "border-async-trainer"))]
+mod tests {
+    use super::*;
+    use crate::{
+        dqn::DqnModelConfig,
+        mlp::{Mlp, MlpConfig},
+        opt::OptimizerConfig,
+        TensorBatch,
+    };
+    use border_core::{generic_replay_buffer::SimpleReplayBuffer, Step};
+    use tch::kind::FLOAT_CPU;
+
+    #[derive(Clone, Debug)]
+    struct TestObs(Tensor);
+
+    impl border_core::Obs for TestObs {
+        fn len(&self) -> usize {
+            1
+        }
+    }
+
+    impl From<TestObs> for Tensor {
+        fn from(obs: TestObs) -> Self {
+            obs.0
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct TestAct(Tensor);
+
+    impl border_core::Act for TestAct {}
+
+    impl From<Tensor> for TestAct {
+        fn from(t: Tensor) -> Self {
+            Self(t)
+        }
+    }
+
+    /// Environment only used for type parameters.
+    struct TestEnv;
+
+    impl Env for TestEnv {
+        type Config = ();
+        type Obs = TestObs;
+        type Act = TestAct;
+        type Info = ();
+
+        fn build(_config: &Self::Config, _seed: i64) -> Result<Self> {
+            unimplemented!();
+        }
+
+        fn step(&mut self, _a: &Self::Act) -> (Step<Self>, Record) {
+            unimplemented!();
+        }
+
+        fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+            unimplemented!();
+        }
+
+        fn step_with_reset(&mut self, _a: &Self::Act) -> (Step<Self>, Record) {
+            unimplemented!();
+        }
+
+        fn reset_with_index(&mut self, _ix: usize) -> Result<Self::Obs> {
+            unimplemented!();
+        }
+    }
+
+    type Buffer = SimpleReplayBuffer<TensorBatch, TensorBatch>;
+
+    fn dqn() -> Dqn<TestEnv, Mlp, Buffer> {
+        let model_config = DqnModelConfig::default()
+            .q_config(MlpConfig::new(3, vec![8], 2, false))
+            .opt_config(OptimizerConfig::Adam { lr: 1e-3 });
+        let config = DqnConfig::default()
+            .model_config(model_config)
+            .double_dqn(true)
+            .device(Device::Cpu);
+        Dqn::build(config)
+    }
+
+    #[test]
+    fn test_compute_priorities_after_sync() {
+        tch::manual_seed(42);
+        let mut learner = dqn();
+        let mut actor = dqn();
+        let batch = GenericTransitionBatch {
+            obs: TensorBatch::from_tensor(Tensor::randn(&[4, 3], FLOAT_CPU)),
+            act: TensorBatch::from_tensor(Tensor::from_slice(&[0i64, 1, 1, 0]).reshape(&[4, 1])),
+            next_obs: TensorBatch::from_tensor(Tensor::randn(&[4, 3], FLOAT_CPU)),
+            reward: vec![1.0, 0.0, -1.0, 0.5],
+            is_terminated: vec![0, 0, 1, 0],
+            is_truncated: vec![0, 0, 0, 0],
+            n_step: None,
+            weight: None,
+            ix_sample: None,
+        };
+
+        // The actor has been initialized independently of the learner
+        let priorities = learner.compute_priorities(&batch);
+        assert_ne!(actor.compute_priorities(&batch), priorities);
+
+        // Both the action-value network and its target are synchronized
+        let (_, model_info) = learner.model_info();
+        actor.sync_model(&model_info);
+        assert_eq!(actor.compute_priorities(&batch), priorities);
     }
 }
@@ -10,6 +10,7 @@ use crate::{
     Device,
 };
 use anyhow::Result;
+use border_core::Schedule;
 use log::info;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
@@ -34,6 +35,8 @@ where
     pub batch_size: usize,
     pub discount_factor: f64,
     pub tau: f64,
+    #[serde(default)]
+    pub tau_schedule: Option<Schedule>,
     pub train: bool,
     pub explorer: DqnExplorer,
     #[serde(default)]
@@ -60,6 +63,7 @@ where
             batch_size: self.batch_size,
             discount_factor: self.discount_factor,
             tau: self.tau,
+            tau_schedule: self.tau_schedule.clone(),
             train: self.train,
             explorer: self.explorer.clone(),
             clip_reward: self.clip_reward,
@@ -87,6 +91,7 @@ where
             batch_size: 1,
             discount_factor: 0.99,
             tau: 0.005,
+            tau_schedule: None,
             train: false,
             // replay_burffer_capacity: 100,
             explorer: DqnExplorer::Softmax(Softmax::new()),
@@ -137,6 +142,12 @@ where
         self
     }
 
+    /// Schedule of the soft update coefficient by optimization step, which overrides `tau`.
+    pub fn tau_schedule(mut self, v: Schedule) -> Self {
+        self.tau_schedule = Some(v);
+        self
+    }
+
     /// Explorer.
     pub fn explorer(mut self, v: DqnExplorer) -> Self {
         self.explorer = v;
@@ -1,6 +1,7 @@
 //! Exploration strategies of DQN.
 use std::convert::TryInto;
 
+use border_core::Schedule;
 use serde::{Deserialize, Serialize};
 use tch::Tensor;
 
@@ -14,6 +15,16 @@ pub enum DqnExplorer {
     EpsilonGreedy(EpsilonGreedy),
 }
 
+impl DqnExplorer {
+    /// Returns the current epsilon if the explorer is [`EpsilonGreedy`].
+    pub fn eps(&self) -> Option<f64> {
+        match self {
+            Self::EpsilonGreedy(egreedy) => Some(egreedy.eps()),
+            _ => None,
+        }
+    }
+}
+
 /// Softmax explorer for DQN.
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct Softmax {}
@@ -32,12 +43,18 @@ impl Softmax {
 }
 
 /// Epsilon-greedy explorer for DQN.
+///
+/// Epsilon decreases linearly from `eps_start` to `eps_final` in `final_step` steps,
+/// unless `eps_schedule` is given. Steps are counted by the number of calls of
+/// [`EpsilonGreedy::action()`] or [`EpsilonGreedy::action_with_best()`].
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct EpsilonGreedy {
     pub n_opts: usize,
     pub eps_start: f64,
     pub eps_final: f64,
     pub final_step: usize,
+    #[serde(default)]
+    pub eps_schedule: Option<Schedule>,
 }
 
 #[allow(clippy::new_without_default)]
@@ -49,6 +66,7 @@ impl EpsilonGreedy {
             eps_start: 1.0,
             eps_final: 0.02,
             final_step: 100_000,
+            eps_schedule: None,
         }
     }
 
@@ -61,13 +79,24 @@ impl EpsilonGreedy {
             eps_start: 1.0,
             eps_final: 0.02,
             final_step,
+            eps_schedule: None,
         })
     }
 
+    /// Returns the current epsilon.
+    pub fn eps(&self) -> f64 {
+        match &self.eps_schedule {
+            Some(eps_schedule) => eps_schedule.value(self.n_opts),
+            None => {
+                let d = (self.eps_start - self.eps_final) / (self.final_step as f64);
+                (self.eps_start - d * self.n_opts as f64).max(self.eps_final)
+            }
+        }
+    }
+
     /// Takes an action based on the observation and the critic.
     pub fn action(&mut self, a: &Tensor) -> Tensor {
-        let d = (self.eps_start - self.eps_final) / (self.final_step as f64);
-        let eps = (self.eps_start - d * self.n_opts as f64).max(self.eps_final);
+        let eps = self.eps();
         let r = fastrand::f64();
         let is_random = r < eps;
         self.n_opts += 1;
@@ -91,8 +120,7 @@ impl EpsilonGreedy {
 
     /// Takes an action based on the observation and the critic.
     pub fn action_with_best(&mut self, a: &Tensor) -> (Tensor, bool) {
-        let d = (self.eps_start - self.eps_final) / (self.final_step as f64);
-        let eps = (self.eps_start - d * self.n_opts as f64).max(self.eps_final);
+        let eps = self.eps();
         let r = fastrand::f64();
         let is_random = r < eps;
         self.n_opts += 1;
@@ -132,4 +160,11 @@ impl EpsilonGreedy {
         s.eps_start = v;
         s
     }
+
+    /// Set the schedule of epsilon, which overrides the linear decay.
+    pub fn eps_schedule(self, v: Schedule) -> Self {
+        let mut s = self;
+        s.eps_schedule = Some(v);
+        s
+    }
 }
@@ -89,6 +89,16 @@ where
     pub fn param_stats(&self) -> Record {
         crate::util::param_stats(&self.var_store)
     }
+
+    /// Sets the learning rate of the optimizer.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.opt.set_learning_rate(lr);
+    }
+
+    /// Returns the current learning rate if it is scheduled.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        self.opt.scheduled_learning_rate()
+    }
 }
 
 impl<Q> Clone for DqnModel<Q>
@@ -2,15 +2,15 @@
 use super::{average, IqnConfig, IqnExplorer, IqnModel, IqnSample};
 use crate::{
     model::{ModelBase, SubModel},
-    util::{quantile_huber_loss, track, OutDim},
+    util::{gamma_n, insert_scheduled, load_yaml, quantile_huber_loss, save_yaml, track, OutDim},
 };
 use anyhow::Result;
 use border_core::{
     record::{Record, RecordValue},
-    Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
+    Agent, Configurable, Env, Policy, ReplayBufferBase, Schedule, TransitionBatch,
 };
-use log::trace;
-use serde::{de::DeserializeOwned, Serialize};
+use log::{trace, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     convert::TryFrom,
     fs,
@@ -40,6 +40,7 @@ where
     pub(in crate::iqn) phantom: PhantomData<(E, R)>,
     pub(in crate::iqn) discount_factor: f64,
     pub(in crate::iqn) tau: f64,
+    pub(in crate::iqn) tau_schedule: Option<Schedule>,
     pub(in crate::iqn) sample_percents_pred: IqnSample,
     pub(in crate::iqn) sample_percents_tgt: IqnSample,
     pub(in crate::iqn) sample_percents_act: IqnSample,
@@ -48,6 +49,14 @@ where
     pub(in crate::iqn) n_opts: usize,
 }
 
+/// Training state of [`Iqn`] saved in checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct IqnState {
+    n_opts: usize,
+    soft_update_counter: usize,
+    explorer: IqnExplorer,
+}
+
 impl<E, F, M, R> Iqn<E, F, M, R>
 where
     E: Env,
@@ -63,8 +72,12 @@ where
     fn update_critic(&mut self, buffer: &mut R) -> f32 {
         trace!("IQN::update_critic()");
         let batch = buffer.batch(self.batch_size).unwrap();
+        let n_step = batch.n_step().map(|n_step| n_step.to_vec());
         let (obs, act, next_obs, reward, is_terminated, _is_truncated, _ixs, _weight) =
             batch.unpack();
+        let discount = gamma_n(self.discount_factor, n_step.as_deref(), reward.len())
+            .to(self.device)
+            .unsqueeze(-1);
         let obs = obs.into();
         let act = act.into().to(self.device);
         let next_obs = next_obs.into();
@@ -145,7 +158,7 @@ where
                 debug_assert_eq!(z.size().as_slice(), &[batch_size, n_percent_points]);
 
                 // target value
-                let tgt: Tensor = reward + (1 - is_terminated) * self.discount_factor * z;
+                let tgt: Tensor = reward + (1 - is_terminated) * discount * z;
                 debug_assert_eq!(tgt.size().as_slice(), &[batch_size, n_percent_points]);
 
                 tgt.unsqueeze(-1)
@@ -177,6 +190,10 @@ where
             loss_critic += loss;
         }
 
+        if let Some(tau_schedule) = &self.tau_schedule {
+            self.tau = tau_schedule.value(self.n_opts);
+        }
+
         self.soft_update_counter += 1;
         if self.soft_update_counter == self.soft_update_interval {
             self.soft_update_counter = 0;
@@ -185,9 +202,19 @@ where
 
         loss_critic /= self.n_updates_per_opt as f32;
 
+        let mut record = Record::from_slice(&[("loss_critic", RecordValue::Scalar(loss_critic))]);
+        insert_scheduled(
+            &mut record,
+            &[
+                ("tau", self.tau_schedule.as_ref().map(|_| self.tau)),
+                ("lr", self.iqn.scheduled_learning_rate()),
+                ("eps", self.explorer.eps()),
+            ],
+        );
+
         self.n_opts += 1;
 
-        Record::from_slice(&[("loss_critic", RecordValue::Scalar(loss_critic))])
+        record
     }
 }
 
@@ -258,6 +285,7 @@ where
             batch_size: config.batch_size,
             discount_factor: config.discount_factor,
             tau: config.tau,
+            tau_schedule: config.tau_schedule,
             sample_percents_pred: config.sample_percents_pred,
             sample_percents_tgt: config.sample_percents_tgt,
             sample_percents_act: config.sample_percents_act,
@@ -316,6 +344,43 @@ where
         Ok(())
     }
 
+    /// Saves the state of the agent for resuming training.
+    ///
+    /// In addition to the files saved by [`Agent::save_params`], the counters and the
+    /// explorer are saved in `agent.yaml`. The state of the optimizer is not saved,
+    /// as tch does not support serialization of optimizers.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+
+        let state_path = path.join("agent.yaml");
+        let state = IqnState {
+            n_opts: self.n_opts,
+            soft_update_counter: self.soft_update_counter,
+            explorer: self.explorer.clone(),
+        };
+        save_yaml(&state_path, &state)?;
+        paths.push(state_path);
+
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+
+        let state: IqnState = load_yaml(path.join("agent.yaml"))?;
+        self.n_opts = state.n_opts;
+        self.soft_update_counter = state.soft_update_counter;
+        self.explorer = state.explorer;
+        warn!("The state of the optimizer is not restored, as it is not supported by tch");
+
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        self.iqn.set_learning_rate(lr);
+        Ok(())
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
@@ -7,6 +7,7 @@ use crate::{
     Device,
 };
 use anyhow::Result;
+use border_core::Schedule;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     default::Default,
@@ -31,6 +32,8 @@ where
     pub batch_size: usize,
     pub discount_factor: f64,
     pub tau: f64,
+    #[serde(default)]
+    pub tau_schedule: Option<Schedule>,
     pub train: bool,
     pub explorer: IqnExplorer,
     pub sample_percents_pred: IqnSample,
@@ -55,6 +58,7 @@ where
             batch_size: 1,
             discount_factor: 0.99,
             tau: 0.005,
+            tau_schedule: None,
             sample_percents_pred: IqnSample::Uniform8,
             sample_percents_tgt: IqnSample::Uniform8,
             sample_percents_act: IqnSample::Const32,
@@ -110,6 +114,12 @@ where
         self
     }
 
+    /// Schedule of the soft update coefficient by optimization step, which overrides `tau`.
+    pub fn tau_schedule(mut self, v: Schedule) -> Self {
+        self.tau_schedule = Some(v);
+        self
+    }
+
     /// Set explorer.
     pub fn explorer(mut self, v: IqnExplorer) -> Self {
         self.explorer = v;
@@ -222,6 +232,7 @@ where
             batch_size: self.batch_size,
             discount_factor: self.discount_factor,
             tau: self.tau,
+            tau_schedule: self.tau_schedule.clone(),
             sample_percents_pred: self.sample_percents_pred.clone(),
             sample_percents_tgt: self.sample_percents_tgt.clone(),
             sample_percents_act: self.sample_percents_act.clone(),
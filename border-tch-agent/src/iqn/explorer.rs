@@ -1,4 +1,5 @@
 //! Exploration strategies of IQN.
+use border_core::Schedule;
 use serde::{Deserialize, Serialize};
 use std::default::Default;
 use tch::Tensor;
@@ -13,6 +14,16 @@ pub enum IqnExplorer {
     EpsilonGreedy(EpsilonGreedy),
 }
 
+impl IqnExplorer {
+    /// Returns the current epsilon if the explorer is [`EpsilonGreedy`].
+    pub fn eps(&self) -> Option<f64> {
+        match self {
+            Self::EpsilonGreedy(egreedy) => Some(egreedy.eps()),
+            _ => None,
+        }
+    }
+}
+
 /// Softmax explorer for IQN.
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct Softmax {}
@@ -32,11 +43,17 @@ impl Softmax {
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 /// Epsilon-greedy explorer for IQN.
+///
+/// Epsilon decreases linearly from `eps_start` to `eps_final` in `final_step` steps,
+/// unless a schedule is given with [`EpsilonGreedy::eps_schedule()`]. Steps are counted
+/// by the number of calls of [`EpsilonGreedy::action()`].
 pub struct EpsilonGreedy {
     n_opts: usize,
     eps_start: f64,
     eps_final: f64,
     final_step: usize,
+    #[serde(default)]
+    eps_schedule: Option<Schedule>,
 }
 
 impl Default for EpsilonGreedy {
@@ -46,6 +63,7 @@ impl Default for EpsilonGreedy {
             eps_start: 1.0,
             eps_final: 0.02,
             final_step: 100_000,
+            eps_schedule: None,
         }
     }
 }
@@ -59,6 +77,7 @@ impl EpsilonGreedy {
             eps_start,
             eps_final,
             final_step,
+            eps_schedule: None,
         })
     }
 
@@ -71,13 +90,31 @@ impl EpsilonGreedy {
             eps_start: 1.0,
             eps_final: 0.02,
             final_step,
+            eps_schedule: None,
         })
     }
 
+    /// Sets the schedule of epsilon, which overrides the linear decay.
+    pub fn eps_schedule(self, v: Schedule) -> IqnExplorer {
+        let mut s = self;
+        s.eps_schedule = Some(v);
+        IqnExplorer::EpsilonGreedy(s)
+    }
+
+    /// Returns the current epsilon.
+    pub fn eps(&self) -> f64 {
+        match &self.eps_schedule {
+            Some(eps_schedule) => eps_schedule.value(self.n_opts),
+            None => {
+                let d = (self.eps_start - self.eps_final) / (self.final_step as f64);
+                (self.eps_start - d * self.n_opts as f64).max(self.eps_final)
+            }
+        }
+    }
+
     /// Takes an action based on the observation and the critic.
     pub fn action(&mut self, action_value: Tensor) -> Tensor {
-        let d = (self.eps_start - self.eps_final) / (self.final_step as f64);
-        let eps = (self.eps_start - d * self.n_opts as f64).max(self.eps_final);
+        let eps = self.eps();
         let r = fastrand::f64();
         let is_random = r < eps;
         self.n_opts += 1;
@@ -1,5 +1,6 @@
 //! Optimizers.
 use anyhow::Result;
+use border_core::Schedule;
 use core::f64;
 use serde::{Deserialize, Serialize};
 use tch::{
@@ -25,6 +26,18 @@ pub enum OptimizerConfig {
         eps: f64,
         amsgrad: bool,
     },
+
+    /// Optimizer with a learning rate schedule.
+    ///
+    /// The learning rate of each update is given by `lr_schedule` with the number of updates
+    /// done by the optimizer, instead of the learning rate in `config`.
+    Scheduled {
+        /// Configuration of the optimizer.
+        config: Box<OptimizerConfig>,
+
+        /// Schedule of the learning rate.
+        lr_schedule: Schedule,
+    },
 }
 
 impl OptimizerConfig {
@@ -53,6 +66,32 @@ impl OptimizerConfig {
                 .build(vs, *lr)?;
                 Ok(Optimizer::AdamW(opt))
             }
+            OptimizerConfig::Scheduled {
+                config,
+                lr_schedule,
+            } => {
+                let lr = lr_schedule.value(0);
+                let mut opt = config.build(vs)?;
+                opt.set_learning_rate(lr);
+                Ok(Optimizer::Scheduled {
+                    opt: Box::new(opt),
+                    lr_schedule: lr_schedule.clone(),
+                    lr,
+                    n_steps: 0,
+                })
+            }
+        }
+    }
+
+    /// Sets the schedule of the learning rate by the number of updates of the optimizer.
+    pub fn lr_schedule(self, lr_schedule: Schedule) -> Self {
+        let config = match self {
+            Self::Scheduled { config, .. } => config,
+            config => Box::new(config),
+        };
+        Self::Scheduled {
+            config,
+            lr_schedule,
         }
     }
 }
@@ -67,6 +106,21 @@ pub enum Optimizer {
     Adam(Optimizer_),
 
     AdamW(Optimizer_),
+
+    /// Optimizer with a learning rate schedule.
+    Scheduled {
+        /// The optimizer.
+        opt: Box<Optimizer>,
+
+        /// Schedule of the learning rate.
+        lr_schedule: Schedule,
+
+        /// Current learning rate.
+        lr: f64,
+
+        /// Number of updates.
+        n_steps: usize,
+    },
 }
 
 impl Optimizer {
@@ -79,6 +133,43 @@ impl Optimizer {
             Self::AdamW(opt) => {
                 opt.backward_step(loss);
             }
+            Self::Scheduled {
+                opt,
+                lr_schedule,
+                lr,
+                n_steps,
+            } => {
+                *lr = lr_schedule.value(*n_steps);
+                opt.set_learning_rate(*lr);
+                opt.backward_step(loss);
+                *n_steps += 1;
+            }
+        }
+    }
+
+    /// Sets the learning rate.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        match self {
+            Self::Adam(opt) => {
+                opt.set_lr(lr);
+            }
+            Self::AdamW(opt) => {
+                opt.set_lr(lr);
+            }
+            Self::Scheduled { opt, .. } => {
+                opt.set_learning_rate(lr);
+            }
+        }
+    }
+
+    /// Returns the current learning rate if it is scheduled.
+    ///
+    /// If the learning rate is scheduled, the value set by [`Optimizer::set_learning_rate()`]
+    /// is overwritten at the next update.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        match self {
+            Self::Scheduled { lr, .. } => Some(*lr),
+            _ => None,
         }
     }
 }
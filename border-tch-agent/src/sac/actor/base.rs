@@ -81,6 +81,16 @@ where
         debug_assert_eq!(std.size().as_slice()[1], self.out_dim);
         (mean, std)
     }
+
+    /// Sets the learning rate of the optimizer.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.opt.set_learning_rate(lr);
+    }
+
+    /// Returns the current learning rate if it is scheduled.
+    pub fn scheduled_learning_rate(&self) -> Option<f64> {
+        self.opt.scheduled_learning_rate()
+    }
 }
 
 impl<P> Clone for Actor<P>
@@ -1,15 +1,15 @@
 use super::{Actor, Critic, EntCoef, SacConfig};
 use crate::{
     model::{ModelBase, SubModel, SubModel2},
-    util::{track, CriticLoss, OutDim},
+    util::{gamma_n, insert_scheduled, load_yaml, save_yaml, track, CriticLoss, OutDim},
 };
 use anyhow::Result;
 use border_core::{
     record::{Record, RecordValue},
-    Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
+    Agent, Configurable, Env, Policy, ReplayBufferBase, Schedule, TransitionBatch,
 };
-use serde::{de::DeserializeOwned, Serialize};
-// use log::info;
+use log::warn;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     convert::TryFrom,
     fs,
@@ -41,6 +41,7 @@ where
     pub(super) pi: Actor<P>,
     pub(super) gamma: f64,
     pub(super) tau: f64,
+    pub(super) tau_schedule: Option<Schedule>,
     pub(super) ent_coef: EntCoef,
     pub(super) epsilon: f64,
     pub(super) min_lstd: f64,
@@ -55,6 +56,12 @@ where
     pub(super) device: tch::Device,
 }
 
+/// Training state of [`Sac`] saved in checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct SacState {
+    n_opts: usize,
+}
+
 impl<E, Q, P, R> Sac<E, Q, P, R>
 where
     E: Env,
@@ -106,7 +113,9 @@ where
 
     fn update_critic(&mut self, batch: R::Batch) -> f32 {
         let losses = {
+            let n_step = batch.n_step().map(|n_step| n_step.to_vec());
             let (obs, act, next_obs, reward, is_terminated, _is_truncated, _, _) = batch.unpack();
+            let discount = gamma_n(self.gamma, n_step.as_deref(), reward.len()).to(self.device);
             let reward = Tensor::from_slice(&reward[..]).to(self.device);
             let is_terminated = Tensor::from_slice(&is_terminated[..]).to(self.device);
 
@@ -117,8 +126,7 @@ where
                     let next_q = self.qvals_min(&self.qnets_tgt, &next_obs.into(), &next_a.into());
                     next_q - self.ent_coef.alpha() * next_log_p
                 });
-                self.reward_scale * reward
-                    + (1f32 - &is_terminated) * Tensor::from(self.gamma) * next_q
+                self.reward_scale * reward + (1f32 - &is_terminated) * discount * next_q
             };
 
             debug_assert_eq!(tgt.size().as_slice(), [self.batch_size as i64]);
@@ -180,6 +188,9 @@ where
             let batch = buffer.batch(self.batch_size).unwrap();
             loss_actor += self.update_actor(&batch);
             loss_critic += self.update_critic(batch);
+            if let Some(tau_schedule) = &self.tau_schedule {
+                self.tau = tau_schedule.value(self.n_opts);
+            }
             self.soft_update();
             self.n_opts += 1;
         }
@@ -187,14 +198,24 @@ where
         loss_critic /= self.n_updates_per_opt as f32;
         loss_actor /= self.n_updates_per_opt as f32;
 
-        Record::from_slice(&[
+        let mut record = Record::from_slice(&[
             ("loss_critic", RecordValue::Scalar(loss_critic)),
             ("loss_actor", RecordValue::Scalar(loss_actor)),
             (
                 "ent_coef",
                 RecordValue::Scalar(self.ent_coef.alpha().double_value(&[0]) as f32),
             ),
-        ])
+        ]);
+        insert_scheduled(
+            &mut record,
+            &[
+                ("tau", self.tau_schedule.as_ref().map(|_| self.tau)),
+                ("lr_actor", self.pi.scheduled_learning_rate()),
+                ("lr_critic", self.qnets[0].scheduled_learning_rate()),
+                ("target_entropy", self.ent_coef.scheduled_target_entropy()),
+            ],
+        );
+        record
     }
 
     pub fn get_policy_net(&self) -> &Actor<P> {
@@ -263,6 +284,7 @@ where
             pi,
             gamma: config.gamma,
             tau: config.tau,
+            tau_schedule: config.tau_schedule,
             ent_coef: EntCoef::new(config.ent_coef_mode, device),
             epsilon: config.epsilon,
             min_lstd: config.min_lstd,
@@ -344,6 +366,44 @@ where
         Ok(())
     }
 
+    /// Saves the state of the agent for resuming training.
+    ///
+    /// In addition to the files saved by [`Agent::save_params`], the counter of
+    /// optimization steps is saved in `agent.yaml`. The states of the optimizers are
+    /// not saved, as tch does not support serialization of optimizers.
+    fn save_checkpoint(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = self.save_params(path)?;
+
+        let state_path = path.join("agent.yaml");
+        save_yaml(
+            &state_path,
+            &SacState {
+                n_opts: self.n_opts,
+            },
+        )?;
+        paths.push(state_path);
+
+        Ok(paths)
+    }
+
+    fn load_checkpoint(&mut self, path: &Path) -> Result<()> {
+        self.load_params(path)?;
+
+        let state: SacState = load_yaml(path.join("agent.yaml"))?;
+        self.n_opts = state.n_opts;
+        warn!("The states of the optimizers are not restored, as it is not supported by tch");
+
+        Ok(())
+    }
+
+    fn set_learning_rate(&mut self, lr: f64) -> Result<()> {
+        for qnet in self.qnets.iter_mut() {
+            qnet.set_learning_rate(lr);
+        }
+        self.pi.set_learning_rate(lr);
+        Ok(())
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
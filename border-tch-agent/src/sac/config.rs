@@ -8,6 +8,7 @@ use crate::{
     Device,
 };
 use anyhow::Result;
+use border_core::Schedule;
 use log::info;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
@@ -31,6 +32,8 @@ where
     pub critic_config: CriticConfig<Q::Config>,
     pub gamma: f64,
     pub tau: f64,
+    #[serde(default)]
+    pub tau_schedule: Option<Schedule>,
     pub ent_coef_mode: EntCoefMode,
     pub epsilon: f64,
     pub min_lstd: f64,
@@ -59,6 +62,7 @@ where
             critic_config: self.critic_config.clone(),
             gamma: self.gamma.clone(),
             tau: self.tau.clone(),
+            tau_schedule: self.tau_schedule.clone(),
             ent_coef_mode: self.ent_coef_mode.clone(),
             epsilon: self.epsilon.clone(),
             min_lstd: self.min_lstd.clone(),
@@ -88,6 +92,7 @@ where
             critic_config: Default::default(),
             gamma: 0.99,
             tau: 0.005,
+            tau_schedule: None,
             ent_coef_mode: EntCoefMode::Fix(1.0),
             epsilon: 1e-4,
             min_lstd: -20.0,
@@ -136,6 +141,12 @@ where
         self
     }
 
+    /// Schedule of the soft update coefficient by optimization step, which overrides `tau`.
+    pub fn tau_schedule(mut self, v: Schedule) -> Self {
+        self.tau_schedule = Some(v);
+        self
+    }
+
     /// SAC-alpha.
     pub fn ent_coef_mode(mut self, v: EntCoefMode) -> Self {
         self.ent_coef_mode = v;
@@ -1,5 +1,6 @@
 //! Entropy coefficient of SAC.
 use anyhow::Result;
+use border_core::Schedule;
 use log::{info, trace};
 use serde::{Deserialize, Serialize};
 use std::{/*borrow::Borrow,*/ path::Path};
@@ -12,6 +13,10 @@ pub enum EntCoefMode {
     Fix(f64),
     /// Automatic tuning given `(target_entropy, learning_rate)`.
     Auto(f64, f64),
+    /// Automatic tuning given `(target_entropy_schedule, learning_rate)`.
+    ///
+    /// The target entropy is scheduled by the number of updates of the coefficient.
+    AutoScheduled(Schedule, f64),
 }
 
 /// The entropy coefficient of SAC.
@@ -19,7 +24,9 @@ pub struct EntCoef {
     var_store: nn::VarStore,
     log_alpha: Tensor,
     target_entropy: Option<f64>,
+    target_entropy_schedule: Option<Schedule>,
     opt: Option<nn::Optimizer>,
+    n_updates: usize,
 }
 
 impl EntCoef {
@@ -27,6 +34,10 @@ impl EntCoef {
     pub fn new(mode: EntCoefMode, device: tch::Device) -> Self {
         let var_store = nn::VarStore::new(device);
         let path = &var_store.root();
+        let target_entropy_schedule = match &mode {
+            EntCoefMode::AutoScheduled(schedule, _) => Some(schedule.clone()),
+            _ => None,
+        };
         let (log_alpha, target_entropy, opt) = match mode {
             EntCoefMode::Fix(alpha) => {
                 let init = nn::Init::Const(alpha.ln());
@@ -43,6 +54,14 @@ impl EntCoef {
                     .unwrap();
                 (log_alpha, Some(target_entropy), Some(opt))
             }
+            EntCoefMode::AutoScheduled(schedule, learning_rate) => {
+                let init = nn::Init::Const(0.0);
+                let log_alpha = path.var("log_alpha", &[1], init);
+                let opt = nn::Adam::default()
+                    .build(&var_store, learning_rate)
+                    .unwrap();
+                (log_alpha, Some(schedule.value(0)), Some(opt))
+            }
         };
 
         Self {
@@ -50,6 +69,8 @@ impl EntCoef {
             log_alpha,
             opt,
             target_entropy,
+            target_entropy_schedule,
+            n_updates: 0,
         }
     }
 
@@ -65,8 +86,19 @@ impl EntCoef {
         }
     }
 
+    /// Returns the target entropy if it is scheduled.
+    pub fn scheduled_target_entropy(&self) -> Option<f64> {
+        self.target_entropy_schedule
+            .as_ref()
+            .and(self.target_entropy)
+    }
+
     /// Update the parameter given an action probability vector.
     pub fn update(&mut self, logp: &Tensor) {
+        if let Some(schedule) = &self.target_entropy_schedule {
+            self.target_entropy = Some(schedule.value(self.n_updates));
+            self.n_updates += 1;
+        }
         if let Some(target_entropy) = &self.target_entropy {
             let target_entropy = Tensor::from(*target_entropy);
             let loss = -(&self.log_alpha * (logp + target_entropy).detach()).mean(tch::Kind::Float);
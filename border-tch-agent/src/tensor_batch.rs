@@ -1,4 +1,7 @@
-use border_core::generic_replay_buffer::BatchBase;
+use crate::util::TensorData;
+use border_core::generic_replay_buffer::{BatchBase, FrameStackBatch};
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use std::{convert::TryFrom, path::Path};
 use tch::{Device, Tensor};
 
 /// Adds capability of constructing [`Tensor`] with a static method.
@@ -40,6 +43,8 @@ impl ZeroTensor for i64 {
 /// [`TensorBatch::push`] method. `[1..]` means that the first axis of the
 /// given data is ignored as it might be batch size.
 ///
+/// It is serialized with the shape and elements of the tensor, and deserialized on the CPU.
+///
 /// [`Tensor`]: https://docs.rs/tch/0.16.0/tch/struct.Tensor.html
 pub struct TensorBatch {
     buf: Option<Tensor>,
@@ -60,6 +65,37 @@ impl Clone for TensorBatch {
     }
 }
 
+/// Serialized form of [`TensorBatch`].
+#[derive(Deserialize, Serialize)]
+struct TensorBatchData {
+    buf: Option<TensorData>,
+    capacity: i64,
+}
+
+impl Serialize for TensorBatch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let buf = match &self.buf {
+            Some(buf) => Some(TensorData::from_tensor(buf).map_err(ser::Error::custom)?),
+            None => None,
+        };
+        TensorBatchData {
+            buf,
+            capacity: self.capacity,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TensorBatch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = TensorBatchData::deserialize(deserializer)?;
+        Ok(Self {
+            buf: data.buf.map(|buf| buf.to_tensor()),
+            capacity: data.capacity,
+        })
+    }
+}
+
 impl TensorBatch {
     pub fn from_tensor(t: Tensor) -> Self {
         let capacity = t.size()[0] as _;
@@ -118,6 +154,58 @@ impl BatchBase for TensorBatch {
             capacity: ixs.len() as i64,
         }
     }
+
+    /// Saves the first `len` elements as `data.npy` in the directory `path`.
+    ///
+    /// Nothing is saved if the internal buffer has not been initialized.
+    fn save(&self, path: &Path, len: usize) -> anyhow::Result<()> {
+        if let Some(buf) = &self.buf {
+            buf.narrow(0, 0, len as i64)
+                .write_npy(path.join("data.npy"))?;
+        }
+        Ok(())
+    }
+
+    /// Loads the elements in `data.npy` in the directory `path`.
+    ///
+    /// If the file does not exist, no elements are loaded.
+    fn load(&mut self, path: &Path) -> anyhow::Result<usize> {
+        let path = path.join("data.npy");
+        if !path.exists() {
+            return Ok(0);
+        }
+
+        let data = Tensor::read_npy(&path)?;
+        let len = data.size()[0];
+        if len > self.capacity {
+            return Err(anyhow::anyhow!(
+                "{:?} has {} elements, more than the capacity {}",
+                path,
+                len,
+                self.capacity
+            ));
+        }
+        if len > 0 {
+            self.push(0, Self::from_tensor(data));
+        }
+        Ok(len as usize)
+    }
+}
+
+impl FrameStackBatch for TensorBatch {
+    /// Returns the elements of the internal `u8` tensor.
+    fn to_frames(&self) -> (Vec<u8>, Vec<usize>) {
+        let buf = self.buf.as_ref().unwrap();
+        let shape = buf.size().iter().map(|&d| d as usize).collect();
+        let data = Vec::<u8>::try_from(buf.to_device(Device::Cpu).reshape([-1])).unwrap();
+        (data, shape)
+    }
+
+    /// Creates a batch with a `u8` tensor on the CPU.
+    fn from_frames(data: Vec<u8>, shape: &[usize]) -> Self {
+        let shape = shape.iter().map(|&d| d as i64).collect::<Vec<_>>();
+        Self::from_tensor(Tensor::from_slice(&data).reshape(&shape[..]))
+    }
 }
 
 impl From<TensorBatch> for Tensor {
@@ -125,3 +213,25 @@ impl From<TensorBatch> for Tensor {
         b.buf.unwrap()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_serde() {
+        let data = Tensor::arange(6, (tch::Kind::Int64, Device::Cpu)).reshape(&[3, 2]);
+        let batch = TensorBatch::from_tensor(data.copy());
+        let batch: TensorBatch =
+            bincode::deserialize(&bincode::serialize(&batch).unwrap()).unwrap();
+        assert_eq!(batch.capacity, 3);
+        let t: Tensor = batch.into();
+        assert_eq!(t, data);
+
+        // An empty batch
+        let batch: TensorBatch =
+            bincode::deserialize(&bincode::serialize(&TensorBatch::new(5)).unwrap()).unwrap();
+        assert!(batch.buf.is_none());
+        assert_eq!(batch.capacity, 5);
+    }
+}
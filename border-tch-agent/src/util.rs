@@ -1,16 +1,23 @@
 //! Utilities.
 use crate::model::ModelBase;
 use log::trace;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 mod named_tensors;
 mod quantile_loss;
+mod tensor_data;
 use border_core::record::{Record, RecordValue};
 pub use named_tensors::NamedTensors;
 use ndarray::ArrayD;
 use num_traits::cast::AsPrimitive;
 pub use quantile_loss::quantile_huber_loss;
-use std::convert::TryFrom;
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
 use tch::{nn::VarStore, Tensor};
+pub(crate) use tensor_data::TensorData;
 
 /// Critic loss type.
 #[allow(clippy::upper_case_acronyms)]
@@ -23,6 +30,23 @@ pub enum CriticLoss {
     SmoothL1,
 }
 
+/// Returns discount factors of n-step transitions.
+///
+/// The discount factor of a transition is `gamma^n`, where n is given by
+/// [`TransitionBatch::n_step`]. If `n_step` is `None`, n is 1 for all transitions.
+///
+/// [`TransitionBatch::n_step`]: border_core::TransitionBatch::n_step
+pub fn gamma_n(gamma: f64, n_step: Option<&[usize]>, batch_size: usize) -> Tensor {
+    let gamma_n = match n_step {
+        Some(n_step) => n_step
+            .iter()
+            .map(|&n| gamma.powi(n as i32) as f32)
+            .collect::<Vec<_>>(),
+        None => vec![gamma as f32; batch_size],
+    };
+    Tensor::from_slice(&gamma_n[..])
+}
+
 /// Apply soft update on variables.
 ///
 /// Variables are identified by their names.
@@ -78,6 +102,31 @@ pub fn param_stats(var_store: &VarStore) -> Record {
     record
 }
 
+/// Inserts the current values of scheduled hyperparameters into a record.
+///
+/// Hyperparameters given as `None`, i.e., not scheduled, are skipped.
+pub fn insert_scheduled(record: &mut Record, values: &[(&str, Option<f64>)]) {
+    for (name, v) in values.iter() {
+        if let Some(v) = v {
+            record.insert(*name, RecordValue::Scalar(*v as _));
+        }
+    }
+}
+
+/// Saves a value as a YAML file.
+pub fn save_yaml<T: Serialize>(path: impl AsRef<Path>, v: &T) -> anyhow::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(serde_yaml::to_string(v)?.as_bytes())?;
+    Ok(())
+}
+
+/// Loads a value from a YAML file.
+pub fn load_yaml<T: DeserializeOwned>(path: impl AsRef<Path>) -> anyhow::Result<T> {
+    let file = File::open(path)?;
+    let rdr = BufReader::new(file);
+    Ok(serde_yaml::from_reader(rdr)?)
+}
+
 pub fn vec_to_tensor<T1, T2>(v: Vec<T1>, add_batch_dim: bool) -> Tensor
 where
     T1: AsPrimitive<T2>,
@@ -1,7 +1,13 @@
+use super::tensor_data::TensorData;
+use anyhow::Result;
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
 use std::{collections::HashMap, iter::FromIterator};
 use tch::{nn::VarStore, Device::Cpu, Tensor};
 
 /// Named tensors to send model parameters using a channel.
+///
+/// The tensors are serialized with their shapes and elements, so that model parameters
+/// can also be sent to actors in other processes.
 pub struct NamedTensors {
     pub named_tensors: HashMap<String, Tensor>,
 }
@@ -48,6 +54,28 @@ impl Clone for NamedTensors {
     }
 }
 
+impl Serialize for NamedTensors {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let named_tensors = self
+            .named_tensors
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), TensorData::from_tensor(v)?)))
+            .collect::<Result<HashMap<_, _>>>()
+            .map_err(ser::Error::custom)?;
+        named_tensors.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for NamedTensors {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let named_tensors = HashMap::<String, TensorData>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(k, v)| (k, v.to_tensor()))
+            .collect();
+        Ok(Self { named_tensors })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::NamedTensors;
@@ -80,7 +108,9 @@ mod test {
         let t1: Vec<f64> = model1.forward(&tensor1).try_into().unwrap();
         let t2: Vec<f64> = model2.forward(&tensor1.to(device)).try_into().unwrap();
 
+        // Send the parameters of model1 through a serialized message
         let nt = NamedTensors::copy_from(&vs1);
+        let nt: NamedTensors = bincode::deserialize(&bincode::serialize(&nt).unwrap()).unwrap();
         nt.copy_to(&mut vs2);
 
         let t3: Vec<f64> = model2.forward(&tensor1.to(device)).try_into().unwrap();
@@ -0,0 +1,53 @@
+//! Serializable data of tensors.
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use tch::{Kind, Tensor};
+
+/// Elements of a tensor.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+enum Elements {
+    U8(Vec<u8>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+/// Shape and elements of a tensor.
+///
+/// This is used to serialize tensors, e.g., to send them to actors in other processes.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub(crate) struct TensorData {
+    shape: Vec<i64>,
+    elements: Elements,
+}
+
+impl TensorData {
+    /// Copies the shape and elements of a tensor.
+    pub fn from_tensor(t: &Tensor) -> Result<Self> {
+        let shape = t.size();
+        let t = t.detach().to(tch::Device::Cpu).flatten(0, -1);
+        let elements = match t.kind() {
+            Kind::Uint8 => Elements::U8(Vec::try_from(&t)?),
+            Kind::Int => Elements::I32(Vec::try_from(&t)?),
+            Kind::Int64 => Elements::I64(Vec::try_from(&t)?),
+            Kind::Float => Elements::F32(Vec::try_from(&t)?),
+            Kind::Double => Elements::F64(Vec::try_from(&t)?),
+            kind => return Err(anyhow!("Unsupported kind: {:?}", kind)),
+        };
+        Ok(Self { shape, elements })
+    }
+
+    /// Creates a tensor on the CPU.
+    pub fn to_tensor(&self) -> Tensor {
+        let t = match &self.elements {
+            Elements::U8(v) => Tensor::from_slice(v),
+            Elements::I32(v) => Tensor::from_slice(v),
+            Elements::I64(v) => Tensor::from_slice(v),
+            Elements::F32(v) => Tensor::from_slice(v),
+            Elements::F64(v) => Tensor::from_slice(v),
+        };
+        t.reshape(&self.shape)
+    }
+}
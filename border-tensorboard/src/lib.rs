@@ -75,7 +75,7 @@ where
             if *k != self.step_key {
                 match v {
                     RecordValue::Scalar(v) => self.writer.add_scalar(k, *v as f32, step),
-                    RecordValue::DateTime(_) => {} // discard value
+                    RecordValue::DateTime(_) | RecordValue::String(_) => {} // discard value
                     RecordValue::Array2(data, shape) => {
                         let shape = [3, shape[0], shape[1]];
                         let min = data.iter().fold(f32::MAX, |m, v| v.min(m));
@@ -7,7 +7,7 @@ use border_candle_agent::{
     opt::OptimizerConfig,
     util::CriticLoss,
 };
-use border_core::{generic_replay_buffer::SimpleReplayBufferConfig, TrainerConfig};
+use border_core::{generic_replay_buffer::FrameStackReplayBufferConfig, EvalMetric, TrainerConfig};
 use serde::Serialize;
 use std::marker::PhantomData;
 
@@ -15,7 +15,7 @@ use std::marker::PhantomData;
 pub struct DqnAtariConfig {
     pub args: Args,
     pub env_config: EnvConfig,
-    pub replay_buffer_config: SimpleReplayBufferConfig,
+    pub replay_buffer_config: FrameStackReplayBufferConfig,
     pub agent_config: DqnConfig<AtariCnn>,
     pub trainer_config: TrainerConfig,
 }
@@ -43,7 +43,7 @@ impl DqnAtariConfig {
         self.env_config.clone()
     }
 
-    pub fn clone_replay_buffer_config(&self) -> SimpleReplayBufferConfig {
+    pub fn clone_replay_buffer_config(&self) -> FrameStackReplayBufferConfig {
         self.replay_buffer_config.clone()
     }
 
@@ -52,12 +52,11 @@ impl DqnAtariConfig {
     }
 }
 
-fn create_replay_buffer_config(_args: &Args) -> SimpleReplayBufferConfig {
-    SimpleReplayBufferConfig {
-        capacity: 262144,
-        seed: 42,
-        per_config: None,
-    }
+fn create_replay_buffer_config(_args: &Args) -> FrameStackReplayBufferConfig {
+    // Frames of observations are stored once, so the buffer can hold 1M transitions
+    FrameStackReplayBufferConfig::default()
+        .capacity(1000000)
+        .seed(42)
 }
 
 fn create_agent_config(args: &Args) -> DqnConfig<AtariCnn> {
@@ -84,16 +83,19 @@ fn create_agent_config(args: &Args) -> DqnConfig<AtariCnn> {
         batch_size: 32,
         discount_factor: 0.99,
         tau: 1.0,
+        tau_schedule: None,
         train: false,
         explorer: DqnExplorer::EpsilonGreedy(EpsilonGreedy {
             n_opts: 0,
             eps_start: 1.0,
             eps_final: 0.02,
             final_step: 1000000,
+            eps_schedule: None,
         }),
         clip_reward: Some(1.0),
         double_dqn: false,
         clip_td_err: None,
+        cql_alpha: None,
         critic_loss: CriticLoss::Mse,
         record_verbose_level: 0,
         device: Some(device.into()),
@@ -111,5 +113,12 @@ fn create_trainer_config(_args: &Args) -> TrainerConfig {
         flush_record_interval: 3000,
         warmup_period: 32,
         save_interval: 300000,
+        checkpoint_interval: usize::MAX,
+        checkpoint_dir: None,
+        eval_threshold: None,
+        early_stopping_patience: None,
+        max_wall_time_secs: None,
+        max_env_steps: None,
+        eval_metric: EvalMetric::Score,
     }
 }
@@ -5,7 +5,7 @@ use border_atari_env::{
 };
 use border_candle_agent::{atari_cnn::AtariCnn, dqn::Dqn as Dqn_, TensorBatch};
 use border_core::{
-    generic_replay_buffer::{SimpleReplayBuffer, SimpleStepProcessor},
+    generic_replay_buffer::{FrameStackReplayBuffer, SimpleStepProcessor},
     DefaultEvaluator,
 };
 
@@ -18,6 +18,6 @@ pub type ActFilter = BorderAtariActRawFilter<Act>;
 pub type EnvConfig = BorderAtariEnvConfig<Obs, Act, ObsFilter, ActFilter>;
 pub type Env = BorderAtariEnv<Obs, Act, ObsFilter, ActFilter>;
 pub type StepProc = SimpleStepProcessor<Env, ObsBatch, ActBatch>;
-pub type ReplayBuffer = SimpleReplayBuffer<ObsBatch, ActBatch>;
+pub type ReplayBuffer = FrameStackReplayBuffer<ObsBatch, ActBatch>;
 pub type Dqn = Dqn_<Env, AtariCnn, ReplayBuffer>;
 pub type Evaluator = DefaultEvaluator<Env>;
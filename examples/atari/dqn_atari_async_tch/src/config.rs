@@ -88,12 +88,14 @@ fn create_agent_config(args: &Args) -> DqnConfig<AtariCnn> {
         batch_size: 32,
         discount_factor: 0.99,
         tau: 1.0,
+        tau_schedule: None,
         train: false,
         explorer: DqnExplorer::EpsilonGreedy(EpsilonGreedy {
             n_opts: 0,
             eps_start: 1.0,
             eps_final: 0.02,
             final_step: 1000000,
+            eps_schedule: None,
         }),
         clip_reward: Some(1.0),
         double_dqn: false,
@@ -106,14 +108,13 @@ fn create_agent_config(args: &Args) -> DqnConfig<AtariCnn> {
 }
 
 fn create_trainer_config(_args: &Args) -> AsyncTrainerConfig {
-    AsyncTrainerConfig {
-        max_opts: 3000000,
-        eval_interval: 3000,
-        record_agent_info_interval: 3000,
-        record_compute_cost_interval: 3000,
-        flush_record_interval: 3000,
-        warmup_period: 32,
-        save_interval: 300000,
-        sync_interval: 1,
-    }
+    AsyncTrainerConfig::default()
+        .max_opts(3000000)
+        .eval_interval(3000)
+        .record_agent_info_interval(3000)
+        .record_compute_cost_interval(3000)
+        .flush_record_interval(3000)
+        .warmup_period(32)
+        .save_interval(300000)
+        .sync_interval(1)
 }
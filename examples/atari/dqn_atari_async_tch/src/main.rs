@@ -85,7 +85,7 @@ fn train(config: &DqnAtariAsyncConfig) -> Result<()> {
         &trainer_config,
         &mut recorder,
         &mut evaluator,
-    );
+    )?;
 
     Ok(())
 }
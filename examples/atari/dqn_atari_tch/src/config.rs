@@ -1,7 +1,7 @@
 use crate::args::Args;
 use crate::types::*;
 use border_atari_env::BorderAtariEnvConfig;
-use border_core::{generic_replay_buffer::SimpleReplayBufferConfig, TrainerConfig};
+use border_core::{generic_replay_buffer::SimpleReplayBufferConfig, EvalMetric, TrainerConfig};
 use border_tch_agent::{
     cnn::{AtariCnn, AtariCnnConfig},
     dqn::{DqnConfig, DqnExplorer, DqnModelConfig, EpsilonGreedy},
@@ -85,12 +85,14 @@ fn create_agent_config(args: &Args) -> DqnConfig<AtariCnn> {
         batch_size: 32,
         discount_factor: 0.99,
         tau: 1.0,
+        tau_schedule: None,
         train: false,
         explorer: DqnExplorer::EpsilonGreedy(EpsilonGreedy {
             n_opts: 0,
             eps_start: 1.0,
             eps_final: 0.02,
             final_step: 1000000,
+            eps_schedule: None,
         }),
         clip_reward: Some(1.0),
         double_dqn: false,
@@ -112,5 +114,12 @@ fn create_trainer_config(_args: &Args) -> TrainerConfig {
         flush_record_interval: 3000,
         warmup_period: 32,
         save_interval: 300000,
+        checkpoint_interval: usize::MAX,
+        checkpoint_dir: None,
+        eval_threshold: None,
+        early_stopping_patience: None,
+        max_wall_time_secs: None,
+        max_env_steps: None,
+        eval_metric: EvalMetric::Score,
     }
 }
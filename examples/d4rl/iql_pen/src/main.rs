@@ -306,7 +306,7 @@ fn main() -> Result<()> {
 
     let config = PenConfig::new(args.clone());
     let dataset = MinariDataset::load_dataset(args.dataset_name(), true)?;
-    let converter = PenConverter::new(PenConverterConfig {}, &dataset)?;
+    let converter = PenConverter::new(PenConverterConfig::default(), &dataset)?;
 
     match args.mode.as_str() {
         "train" => train(config, dataset, converter),
@@ -331,6 +331,6 @@ fn test() -> Result<()> {
     };
     let config = PenConfig::new(args.clone());
     let dataset = MinariDataset::load_dataset(args.dataset_name(), true)?;
-    let converter = PenConverter::new(PenConverterConfig {}, &dataset)?;
+    let converter = PenConverter::new(PenConverterConfig::default(), &dataset)?;
     train(config, dataset, converter)
 }
@@ -8,7 +8,7 @@ use border_candle_agent::{
 };
 use border_core::{
     generic_replay_buffer::{
-        SimpleReplayBuffer, SimpleReplayBufferConfig, SimpleStepProcessor,
+        HerReplayBuffer, HerReplayBufferConfig, HerStrategy, SimpleStepProcessor,
         SimpleStepProcessorConfig,
     },
     record::Recorder,
@@ -31,7 +31,7 @@ use clap::Parser;
 use serde::Serialize;
 
 type Env = GymEnv<NdarrayDictObsConverter>;
-type ReplayBuffer = SimpleReplayBuffer<TensorBatch, TensorBatch>;
+type ReplayBuffer = HerReplayBuffer<TensorBatch, TensorBatch>;
 type StepProc = SimpleStepProcessor<Env, TensorBatch, TensorBatch>;
 type Evaluator = DefaultEvaluator<Env>;
 
@@ -45,6 +45,8 @@ const OPT_INTERVAL: usize = 1;
 const MAX_OPTS: usize = 20_000_000;
 const EVAL_INTERVAL: usize = 5_000;
 const REPLAY_BUFFER_CAPACITY: usize = 131_072;
+const HER_K: usize = 4;
+const DISTANCE_THRESHOLD: f32 = 0.05;
 const N_EPISODES_PER_EVAL: usize = 5;
 const N_CRITICS: usize = 2;
 const TAU: f64 = 0.05;
@@ -94,6 +96,28 @@ fn create_env_config(render: bool) -> Result<GymEnvConfig<NdarrayDictObsConverte
     Ok(env_config)
 }
 
+/// Reward of FetchReach, used to relabel transitions with HER.
+fn compute_reward(achieved_goal: &[f32], desired_goal: &[f32]) -> f32 {
+    let d2: f32 = achieved_goal
+        .iter()
+        .zip(desired_goal.iter())
+        .map(|(a, d)| (a - d).powi(2))
+        .sum();
+    -((d2.sqrt() > DISTANCE_THRESHOLD) as i32 as f32)
+}
+
+/// Observations are concatenations of `observation` (10 elements), `desired_goal`
+/// (3 elements) and `achieved_goal` (3 elements), in the order of the keys in
+/// [`create_env_config`].
+fn create_replay_buffer_config() -> HerReplayBufferConfig {
+    HerReplayBufferConfig::new(compute_reward)
+        .capacity(REPLAY_BUFFER_CAPACITY)
+        .strategy(HerStrategy::Future)
+        .k(HER_K)
+        .desired_goal(10..13)
+        .achieved_goal(13..16)
+}
+
 fn create_actor_config(in_dim: i64, out_dim: i64) -> GaussianActorConfig<MlpConfig> {
     GaussianActorConfig::default()
         .opt_config(OptimizerConfig::Adam { lr: LR_ACTOR })
@@ -222,7 +246,7 @@ impl SacFetchReachConfig {
 fn train(args: &Args, max_opts: usize, model_dir: &str, eval_interval: usize) -> Result<()> {
     let config = SacFetchReachConfig::new(DIM_OBS, DIM_ACT, max_opts, eval_interval)?;
     let step_proc_config = SimpleStepProcessorConfig {};
-    let replay_buffer_config = SimpleReplayBufferConfig::default().capacity(REPLAY_BUFFER_CAPACITY);
+    let replay_buffer_config = create_replay_buffer_config();
     let mut recorder = create_recorder(&args, model_dir, Some(&config))?;
     let mut trainer = Trainer::build(config.trainer_config.clone());
 
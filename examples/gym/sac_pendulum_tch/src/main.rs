@@ -65,7 +65,7 @@ struct Args {
 fn create_env_config(render: bool) -> Result<GymEnvConfig<NdarrayConverter>> {
     let mut env_config = GymEnvConfig::default()
         .name(ENV_NAME.to_string())
-        .converter_config(NdarrayConverterConfig {});
+        .converter_config(NdarrayConverterConfig::default());
 
     if render {
         env_config = env_config